@@ -0,0 +1,211 @@
+use std::hint::black_box;
+use std::io::Write;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use heed::types::Bytes;
+use heed::PutFlags;
+use heed_benchmarks::{Fixture, Rng, ENTRIES, VALUE_SIZE};
+
+/// The number of keys looked up or written by an iteration of the benchmarks.
+const BATCH: u64 = 1_000;
+
+/// The number of entries read by the chunks of the chunked iteration.
+const CHUNK: u64 = 100;
+
+fn point_lookups(c: &mut Criterion) {
+    let fixture = Fixture::filled();
+    let keys: Vec<u64> = Rng::new(7).shuffled(ENTRIES).into_iter().take(BATCH as usize).collect();
+    let mut sorted_keys = keys.clone();
+    sorted_keys.sort_unstable();
+    let rtxn = fixture.env.read_txn().unwrap();
+
+    let mut group = c.benchmark_group("point-lookups");
+    group.throughput(Throughput::Elements(BATCH));
+    group.bench_function("get", |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(fixture.db.get(&rtxn, key).unwrap());
+            }
+        })
+    });
+    group.bench_function("get-sorted-keys", |b| {
+        b.iter(|| {
+            for key in &sorted_keys {
+                black_box(fixture.db.get(&rtxn, key).unwrap());
+            }
+        })
+    });
+    group.bench_function("cursor-get", |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(fixture.db.range(&rtxn, &(key..=key)).unwrap().next());
+            }
+        })
+    });
+    group.bench_function("get-sorted", |b| {
+        b.iter(|| black_box(fixture.db.get_sorted(&rtxn, &keys).unwrap()))
+    });
+    group.finish();
+}
+
+fn iteration(c: &mut Criterion) {
+    let fixture = Fixture::filled();
+    let rtxn = fixture.env.read_txn().unwrap();
+
+    let mut group = c.benchmark_group("iteration");
+    group.throughput(Throughput::Elements(ENTRIES));
+    group.bench_function("iter", |b| {
+        b.iter(|| {
+            for entry in fixture.db.iter(&rtxn).unwrap() {
+                black_box(entry.unwrap());
+            }
+        })
+    });
+    group.bench_function("rev-iter", |b| {
+        b.iter(|| {
+            for entry in fixture.db.rev_iter(&rtxn).unwrap() {
+                black_box(entry.unwrap());
+            }
+        })
+    });
+    // The pagination of a service, a new range for every chunk of entries.
+    group.bench_function("chunked-iter", |b| {
+        b.iter(|| {
+            for start in (0..ENTRIES).step_by(CHUNK as usize) {
+                let range = start..start + CHUNK;
+                for entry in fixture.db.range(&rtxn, &range).unwrap() {
+                    black_box(entry.unwrap());
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn writes(c: &mut Criterion) {
+    let shuffled = Rng::new(11).shuffled(BATCH);
+    let values: Vec<Vec<u8>> = {
+        let mut rng = Rng::new(13);
+        (0..BATCH).map(|_| rng.bytes(VALUE_SIZE)).collect()
+    };
+
+    let mut group = c.benchmark_group("writes");
+    group.throughput(Throughput::Elements(BATCH));
+    group.bench_function("put-shuffled", |b| {
+        b.iter_batched(
+            Fixture::empty,
+            |fixture| {
+                let mut wtxn = fixture.env.write_txn().unwrap();
+                for (key, value) in shuffled.iter().zip(&values) {
+                    fixture.db.put(&mut wtxn, key, value).unwrap();
+                }
+                wtxn.commit().unwrap();
+                fixture
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("put-sorted", |b| {
+        b.iter_batched(
+            Fixture::empty,
+            |fixture| {
+                let mut wtxn = fixture.env.write_txn().unwrap();
+                for (key, value) in (0..BATCH).zip(&values) {
+                    fixture.db.put(&mut wtxn, &key, value).unwrap();
+                }
+                wtxn.commit().unwrap();
+                fixture
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("append", |b| {
+        b.iter_batched(
+            Fixture::empty,
+            |fixture| {
+                let mut wtxn = fixture.env.write_txn().unwrap();
+                for (key, value) in (0..BATCH).zip(&values) {
+                    fixture.db.put_with_flags(&mut wtxn, PutFlags::APPEND, &key, value).unwrap();
+                }
+                wtxn.commit().unwrap();
+                fixture
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("put-reserved", |b| {
+        b.iter_batched(
+            Fixture::empty,
+            |fixture| {
+                let mut wtxn = fixture.env.write_txn().unwrap();
+                for (key, value) in (0..BATCH).zip(&values) {
+                    fixture
+                        .db
+                        .put_reserved(&mut wtxn, &key, value.len(), |space| space.write_all(value))
+                        .unwrap();
+                }
+                wtxn.commit().unwrap();
+                fixture
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+/// Deletes the keys from 2^16 to [`ENTRIES`], the keys starting with `[0, 0, 0, 0, 0, 1]`,
+/// in a write transaction aborted outside of the measure.
+fn deletes(c: &mut Criterion) {
+    let fixture = Fixture::filled();
+    let start = 1 << 16;
+    let prefix = [0, 0, 0, 0, 0, 1];
+
+    let mut group = c.benchmark_group("deletes");
+    group.throughput(Throughput::Elements(ENTRIES - start));
+    group.bench_function("delete-range", |b| {
+        b.iter_batched(
+            || fixture.env.write_txn().unwrap(),
+            |mut wtxn| {
+                assert_eq!(
+                    fixture.db.delete_range(&mut wtxn, &(start..)).unwrap() as u64,
+                    ENTRIES - start
+                );
+                wtxn
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("delete-prefix", |b| {
+        let db = fixture.db.remap_key_type::<Bytes>();
+        b.iter_batched(
+            || fixture.env.write_txn().unwrap(),
+            |mut wtxn| {
+                assert_eq!(db.delete_prefix(&mut wtxn, &prefix).unwrap() as u64, ENTRIES - start);
+                wtxn
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("collect-then-delete", |b| {
+        b.iter_batched(
+            || fixture.env.write_txn().unwrap(),
+            |mut wtxn| {
+                let keys: Vec<u64> = fixture
+                    .db
+                    .range(&wtxn, &(start..))
+                    .unwrap()
+                    .map(|entry| entry.unwrap().0)
+                    .collect();
+                for key in &keys {
+                    fixture.db.delete(&mut wtxn, key).unwrap();
+                }
+                wtxn
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, point_lookups, iteration, writes, deletes);
+criterion_main!(benches);
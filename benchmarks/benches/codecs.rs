@@ -0,0 +1,59 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use heed::byteorder::BigEndian;
+use heed::types::{SerdeBincode, SerdeJson, Str, U64};
+use heed::{BytesDecode, BytesEncode};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct User {
+    id: u64,
+    name: String,
+    email: String,
+    tags: Vec<String>,
+}
+
+fn user() -> User {
+    User {
+        id: 42,
+        name: "Kerollmops".to_string(),
+        email: "kerollmops@example.com".to_string(),
+        tags: vec!["admin".to_string(), "maintainer".to_string()],
+    }
+}
+
+/// Benchmarks the encoding in a new buffer, in a reused buffer, and the decoding of a codec.
+fn bench_codec<'a, C>(c: &mut Criterion, name: &str, item: &'a C::EItem)
+where
+    C: BytesEncode<'a> + for<'b> BytesDecode<'b>,
+{
+    let mut group = c.benchmark_group(name);
+    group.bench_function("encode", |b| b.iter(|| black_box(C::bytes_encode(item).unwrap())));
+    let mut buffer = Vec::new();
+    group.bench_function("encode-into", |b| {
+        b.iter(|| {
+            buffer.clear();
+            C::bytes_encode_into(item, &mut buffer).unwrap();
+            black_box(&buffer);
+        })
+    });
+    let bytes = C::bytes_encode(item).unwrap().into_owned();
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            let _ = black_box(C::bytes_decode(&bytes).unwrap());
+        })
+    });
+    group.finish();
+}
+
+fn codecs(c: &mut Criterion) {
+    bench_codec::<U64<BigEndian>>(c, "u64", &42);
+    bench_codec::<Str>(c, "str", "kerollmops@example.com");
+    let user = user();
+    bench_codec::<SerdeBincode<User>>(c, "serde-bincode", &user);
+    bench_codec::<SerdeJson<User>>(c, "serde-json", &user);
+}
+
+criterion_group!(benches, codecs);
+criterion_main!(benches);
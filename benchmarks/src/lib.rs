@@ -0,0 +1,85 @@
+//! The fixtures of the benchmarks, generated deterministically so that
+//! the numbers of two runs, or two commits, can be compared.
+//!
+//! ```sh
+//! cd benchmarks && cargo bench
+//! ```
+
+use heed::byteorder::BigEndian;
+use heed::types::{Bytes, U64};
+use heed::{Database, Env, EnvOpenOptions, PutFlags};
+use tempfile::TempDir;
+
+/// The number of entries of the fixture databases.
+pub const ENTRIES: u64 = 100_000;
+
+/// The size of the values of the fixture databases.
+pub const VALUE_SIZE: usize = 64;
+
+/// The keys of the fixture databases, big-endian to be sorted like the integers.
+pub type Key = U64<BigEndian>;
+
+/// A pseudo-random generator, a xorshift seeded with a constant.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns bytes of the given length.
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+
+    /// Returns the integers from zero to `count` in a random order.
+    pub fn shuffled(&mut self, count: u64) -> Vec<u64> {
+        let mut numbers: Vec<u64> = (0..count).collect();
+        for i in (1..numbers.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            numbers.swap(i, j);
+        }
+        numbers
+    }
+}
+
+/// An environment in a temporary directory, removed when the fixture is dropped.
+pub struct Fixture {
+    pub env: Env,
+    pub db: Database<Key, Bytes>,
+    _dir: TempDir,
+}
+
+impl Fixture {
+    /// Opens an empty environment.
+    pub fn empty() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            EnvOpenOptions::new().map_size(1024 * 1024 * 1024).max_dbs(1).open(dir.path()).unwrap()
+        };
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env.create_database(&mut wtxn, Some("fixture")).unwrap();
+        wtxn.commit().unwrap();
+        Fixture { env, db, _dir: dir }
+    }
+
+    /// Opens an environment with the keys from zero to [`ENTRIES`] and random values.
+    pub fn filled() -> Fixture {
+        let fixture = Fixture::empty();
+        let mut rng = Rng::new(42);
+        let mut wtxn = fixture.env.write_txn().unwrap();
+        for key in 0..ENTRIES {
+            let value = rng.bytes(VALUE_SIZE);
+            fixture.db.put_with_flags(&mut wtxn, PutFlags::APPEND, &key, &value).unwrap();
+        }
+        wtxn.commit().unwrap();
+        fixture
+    }
+}
@@ -0,0 +1,137 @@
+//! Round-trips the values through the built-in codecs, decodes arbitrary bytes with them and
+//! checks that the codecs documented as order-preserving sort their keys like their values.
+
+#![no_main]
+
+use std::fmt::Debug;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use arbitrary::Arbitrary;
+use heed::byteorder::{BigEndian, LittleEndian};
+use heed::types::*;
+use heed::{BytesDecode, BytesEncode};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+enum Input {
+    U8(u8, u8),
+    I8(i8, i8),
+    BigEndianU16(u16, u16),
+    BigEndianU32(u32, u32),
+    BigEndianU64(u64, u64),
+    BigEndianU128(u128, u128),
+    LittleEndianI32(i32, i32),
+    LittleEndianI64(i64, i64),
+    VarU64(u64, u64),
+    Str(String, String),
+    Bytes(Vec<u8>, Vec<u8>),
+    FixedWidth([u8; 16], [u8; 16]),
+    Ipv4Address([u8; 4], [u8; 4]),
+    Ipv6Address([u8; 16], [u8; 16]),
+    IpAddress(IpAddr, IpAddr),
+    SocketAddress(SocketAddr, SocketAddr),
+    Decode(Vec<u8>),
+}
+
+/// Encodes and decodes the value, the decoded value must be the encoded one.
+fn round_trip<C, T>(value: &T) -> Vec<u8>
+where
+    C: for<'a> BytesEncode<'a, EItem = T> + for<'a> BytesDecode<'a, DItem = T>,
+    T: PartialEq + Debug,
+{
+    let bytes = C::bytes_encode(value).expect("the value can be encoded").into_owned();
+    let mut buffer = Vec::new();
+    C::bytes_encode_into(value, &mut buffer).expect("the value can be encoded in a buffer");
+    assert_eq!(bytes, buffer, "the encodings of {value:?} differ");
+    let decoded = C::bytes_decode(&bytes).expect("the encoded value can be decoded");
+    assert_eq!(*value, decoded);
+    bytes
+}
+
+/// Round-trips both values, the order of their encodings must be the order of the values.
+fn ordered<C, T>(a: &T, b: &T)
+where
+    C: for<'a> BytesEncode<'a, EItem = T> + for<'a> BytesDecode<'a, DItem = T>,
+    T: Ord + Debug,
+{
+    let (a_bytes, b_bytes) = (round_trip::<C, T>(a), round_trip::<C, T>(b));
+    assert_eq!(a.cmp(b), a_bytes.cmp(&b_bytes), "{a:?} and {b:?} are not ordered like their keys");
+}
+
+/// Decodes arbitrary bytes, a decoded value must be encoded back to the same bytes.
+fn canonical<C, T>(bytes: &[u8])
+where
+    C: for<'a> BytesEncode<'a, EItem = T> + for<'a> BytesDecode<'a, DItem = T>,
+{
+    if let Ok(value) = C::bytes_decode(bytes) {
+        let encoded = C::bytes_encode(&value).expect("a decoded value can be encoded");
+        assert_eq!(bytes, &encoded[..]);
+    }
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::U8(a, b) => ordered::<U8, _>(&a, &b),
+        Input::I8(a, b) => {
+            round_trip::<I8, _>(&a);
+            round_trip::<I8, _>(&b);
+        }
+        Input::BigEndianU16(a, b) => ordered::<U16<BigEndian>, _>(&a, &b),
+        Input::BigEndianU32(a, b) => ordered::<U32<BigEndian>, _>(&a, &b),
+        Input::BigEndianU64(a, b) => ordered::<U64<BigEndian>, _>(&a, &b),
+        Input::BigEndianU128(a, b) => ordered::<U128<BigEndian>, _>(&a, &b),
+        Input::LittleEndianI32(a, b) => {
+            round_trip::<I32<LittleEndian>, _>(&a);
+            round_trip::<I32<LittleEndian>, _>(&b);
+        }
+        Input::LittleEndianI64(a, b) => {
+            round_trip::<I64<LittleEndian>, _>(&a);
+            round_trip::<I64<LittleEndian>, _>(&b);
+        }
+        Input::VarU64(a, b) => ordered::<VarU64, _>(&a, &b),
+        Input::Str(a, b) => {
+            let a_bytes = Str::bytes_encode(&a).unwrap();
+            let b_bytes = Str::bytes_encode(&b).unwrap();
+            assert_eq!(Str::bytes_decode(&a_bytes).unwrap(), a);
+            assert_eq!(a.cmp(&b), a_bytes.cmp(&b_bytes));
+        }
+        Input::Bytes(a, b) => {
+            let a_bytes = Bytes::bytes_encode(&a).unwrap();
+            let b_bytes = Bytes::bytes_encode(&b).unwrap();
+            assert_eq!(Bytes::bytes_decode(&a_bytes).unwrap(), a);
+            assert_eq!(a.cmp(&b), a_bytes.cmp(&b_bytes));
+        }
+        Input::FixedWidth(a, b) => {
+            let (a_bytes, b_bytes) = (round_trip_fixed(&a), round_trip_fixed(&b));
+            assert_eq!(a.cmp(&b), a_bytes.cmp(&b_bytes));
+        }
+        Input::Ipv4Address(a, b) => {
+            ordered::<Ipv4Address, _>(&Ipv4Addr::from(a), &Ipv4Addr::from(b));
+        }
+        Input::Ipv6Address(a, b) => {
+            ordered::<Ipv6Address, _>(&Ipv6Addr::from(a), &Ipv6Addr::from(b));
+        }
+        Input::IpAddress(a, b) => ordered::<IpAddress, _>(&a, &b),
+        Input::SocketAddress(a, b) => {
+            round_trip::<SocketAddress, _>(&a);
+            round_trip::<SocketAddress, _>(&b);
+        }
+        Input::Decode(bytes) => {
+            canonical::<U8, _>(&bytes);
+            canonical::<U64<BigEndian>, _>(&bytes);
+            canonical::<I128<LittleEndian>, _>(&bytes);
+            canonical::<VarU64, _>(&bytes);
+            canonical::<Ipv4Address, _>(&bytes);
+            canonical::<Ipv6Address, _>(&bytes);
+            canonical::<IpAddress, _>(&bytes);
+            canonical::<SocketAddress, _>(&bytes);
+        }
+    }
+});
+
+/// The `FixedWidth` codec decodes a reference to the array, it is round-tripped by hand.
+fn round_trip_fixed(value: &[u8; 16]) -> Vec<u8> {
+    let bytes = FixedWidth::<[u8; 16]>::bytes_encode(value).unwrap().into_owned();
+    assert_eq!(FixedWidth::<[u8; 16]>::bytes_decode(&bytes).unwrap(), value);
+    bytes
+}
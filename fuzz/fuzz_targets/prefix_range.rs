@@ -0,0 +1,89 @@
+//! Compares the prefix and range iterators, and the bounds they compute, with a `BTreeMap`.
+
+#![no_main]
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::LazyLock;
+
+use arbitrary::Arbitrary;
+use heed::types::*;
+use heed::{Database, Env, EnvOpenOptions, Error};
+use libfuzzer_sys::fuzz_target;
+use tempfile::TempDir;
+
+struct Fixture {
+    _dir: TempDir,
+    env: Env,
+    db: Database<Bytes, Unit>,
+}
+
+/// The environment is reused by every run, the keys are written in a transaction that is aborted.
+static FIXTURE: LazyLock<Fixture> = LazyLock::new(|| {
+    let dir = tempfile::tempdir().unwrap();
+    let env = unsafe { EnvOpenOptions::new().map_size(100 * 1024 * 1024).open(dir.path()) };
+    let env = env.unwrap();
+    let mut wtxn = env.write_txn().unwrap();
+    let db = env.create_database(&mut wtxn, None).unwrap();
+    wtxn.commit().unwrap();
+    Fixture { _dir: dir, env, db }
+});
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    keys: Vec<Vec<u8>>,
+    prefix: Vec<u8>,
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+}
+
+fuzz_target!(|input: Input| {
+    let Fixture { env, db, .. } = &*FIXTURE;
+    let mut wtxn = env.write_txn().unwrap();
+
+    // LMDB refuses the empty keys and the keys larger than 511 bytes.
+    let mut model = BTreeMap::new();
+    for key in input.keys.into_iter().filter(|key| (1..=511).contains(&key.len())) {
+        db.put(&mut wtxn, &key, &()).unwrap();
+        model.insert(key, ());
+    }
+
+    // The prefix iterators find their last entry with the successor of the prefix.
+    let prefix = &input.prefix[..];
+    let expected: Vec<_> = model.keys().filter(|key| key.starts_with(prefix)).collect();
+    let keys: Vec<_> = db.prefix_iter(&wtxn, prefix).unwrap().map(|e| e.unwrap().0).collect();
+    assert_eq!(keys, expected);
+    let keys: Vec<_> = db.rev_prefix_iter(&wtxn, prefix).unwrap().map(|e| e.unwrap().0).collect();
+    assert_eq!(keys, expected.iter().rev().copied().collect::<Vec<_>>());
+    let last = db.prefix_iter(&wtxn, prefix).unwrap().last().map(|e| e.unwrap().0);
+    assert_eq!(last, expected.last().map(|key| &key[..]));
+    let last = db.rev_prefix_iter(&wtxn, prefix).unwrap().last().map(|e| e.unwrap().0);
+    assert_eq!(last, expected.first().map(|key| &key[..]));
+
+    let start = input.start.as_ref().map(Vec::as_slice);
+    let end = input.end.as_ref().map(Vec::as_slice);
+    let range = (start, end);
+    let expected: Vec<_> = match (start, end) {
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e))
+            if s > e =>
+        {
+            assert!(matches!(db.range(&wtxn, &range), Err(Error::InvalidRange)));
+            assert!(matches!(db.rev_range(&wtxn, &range), Err(Error::InvalidRange)));
+            return;
+        }
+        // The BTreeMap refuses this empty range.
+        (Bound::Excluded(s), Bound::Excluded(e)) if s == e => Vec::new(),
+        _ => model.range::<[u8], _>(range).map(|(key, ())| key).collect(),
+    };
+
+    let keys: Vec<_> = db.range(&wtxn, &range).unwrap().map(|e| e.unwrap().0).collect();
+    assert_eq!(keys, expected);
+    let keys: Vec<_> = db.rev_range(&wtxn, &range).unwrap().map(|e| e.unwrap().0).collect();
+    assert_eq!(keys, expected.iter().rev().copied().collect::<Vec<_>>());
+    let last = db.range(&wtxn, &range).unwrap().last().map(|e| e.unwrap().0);
+    assert_eq!(last, expected.last().map(|key| &key[..]));
+    let last = db.rev_range(&wtxn, &range).unwrap().last().map(|e| e.unwrap().0);
+    assert_eq!(last, expected.first().map(|key| &key[..]));
+
+    wtxn.abort();
+});
@@ -0,0 +1,122 @@
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Ident, LitStr, Path, Token, Type};
+
+/// The byte order used to encode the integer fields.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+    Native,
+}
+
+impl Endian {
+    fn parse(meta: &ParseNestedMeta) -> syn::Result<Endian> {
+        let lit: LitStr = meta.value()?.parse()?;
+        match lit.value().as_str() {
+            "big" => Ok(Endian::Big),
+            "little" => Ok(Endian::Little),
+            "native" => Ok(Endian::Native),
+            _ => Err(syn::Error::new_spanned(lit, r#"expected "big", "little" or "native""#)),
+        }
+    }
+}
+
+/// The attributes that can be set on the container, i.e. `#[heed(crate = "heed3")]`.
+pub struct ContainerAttributes {
+    /// The path to the `heed` crate, `::heed` by default.
+    pub crate_path: Path,
+    /// The byte order of the integer fields, if specified.
+    pub endian: Option<Endian>,
+}
+
+impl ContainerAttributes {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<ContainerAttributes> {
+        let mut crate_path = syn::parse_quote!(::heed);
+        let mut endian = None;
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("heed")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    crate_path = lit.parse()?;
+                    Ok(())
+                } else if meta.path.is_ident("endian") {
+                    endian = Some(Endian::parse(&meta)?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported heed attribute"))
+                }
+            })?;
+        }
+
+        Ok(ContainerAttributes { crate_path, endian })
+    }
+}
+
+/// The attributes that can be set on a field, i.e. `#[heed(codec = "Str")]`.
+#[derive(Default)]
+pub struct FieldAttributes {
+    /// The byte order of this integer field, overriding the container one.
+    pub endian: Option<Endian>,
+    /// The codec used to encode this field.
+    pub codec: Option<Type>,
+}
+
+impl FieldAttributes {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<FieldAttributes> {
+        let mut field_attrs = FieldAttributes::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("heed")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("endian") {
+                    field_attrs.endian = Some(Endian::parse(&meta)?);
+                    Ok(())
+                } else if meta.path.is_ident("codec") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    field_attrs.codec = Some(lit.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported heed attribute"))
+                }
+            })?;
+        }
+
+        Ok(field_attrs)
+    }
+}
+
+/// The attributes that can be set on a schema field,
+/// i.e. `#[heed(name = "users", flags = "DUP_SORT | DUP_FIXED")]`.
+#[derive(Default)]
+pub struct SchemaFieldAttributes {
+    /// The name of the database, the name of the field by default.
+    pub name: Option<LitStr>,
+    /// The database flags, without the `DatabaseFlags::` prefix.
+    pub flags: Vec<Ident>,
+}
+
+impl SchemaFieldAttributes {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<SchemaFieldAttributes> {
+        let mut field_attrs = SchemaFieldAttributes::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("heed")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    field_attrs.name = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("flags") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    let flags =
+                        lit.parse_with(Punctuated::<Ident, Token![|]>::parse_separated_nonempty)?;
+                    field_attrs.flags.extend(flags);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported heed attribute"))
+                }
+            })?;
+        }
+
+        Ok(field_attrs)
+    }
+}
@@ -0,0 +1,238 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, Ident, Member, Type};
+
+use crate::attributes::{ContainerAttributes, Endian, FieldAttributes};
+
+/// How a single field is laid out in the encoded bytes.
+enum FieldKind {
+    /// A primitive integer, encoded with a fixed size.
+    Integer { signed: bool, endian: Endian },
+    /// A boolean, encoded as a single byte.
+    Bool,
+    /// A byte array, encoded as is.
+    ByteArray,
+    /// A field encoded with a codec, it consumes all the remaining bytes.
+    Codec(Box<Type>),
+}
+
+struct Field {
+    member: Member,
+    binding: Ident,
+    ty: Type,
+    kind: FieldKind,
+}
+
+struct Struct {
+    crate_path: syn::Path,
+    name: Ident,
+    fields: Vec<Field>,
+    style: Fields,
+}
+
+fn parse(input: &DeriveInput) -> syn::Result<Struct> {
+    let ContainerAttributes { crate_path, endian } = ContainerAttributes::parse(&input.attrs)?;
+    let name = input.ident.clone();
+
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        _ => {
+            return Err(Error::new_spanned(&input.ident, "codecs can only be derived for structs"))
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "codecs cannot be derived for generic structs",
+        ));
+    }
+
+    let style = data.fields.clone();
+    let count = data.fields.len();
+    let mut fields = Vec::with_capacity(count);
+    for (i, field) in data.fields.iter().enumerate() {
+        let attrs = FieldAttributes::parse(&field.attrs)?;
+        let endian = attrs.endian.or(endian).unwrap_or(Endian::Big);
+
+        let kind = match attrs.codec {
+            Some(codec) if i + 1 == count => FieldKind::Codec(Box::new(codec)),
+            Some(codec) => {
+                return Err(Error::new_spanned(
+                    codec,
+                    "only the last field can be encoded with a codec as it consumes the remaining bytes",
+                ))
+            }
+            None => match field_kind(&field.ty, endian) {
+                Some(kind) => kind,
+                None => {
+                    return Err(Error::new_spanned(
+                        &field.ty,
+                        "unsupported field type, use an integer, a bool, a byte array \
+                         or specify a codec with #[heed(codec = \"...\")] on the last field",
+                    ))
+                }
+            },
+        };
+
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(i.into()),
+        };
+
+        fields.push(Field {
+            member,
+            binding: format_ident!("field{}", i),
+            ty: field.ty.clone(),
+            kind,
+        });
+    }
+
+    Ok(Struct { crate_path, name, fields, style })
+}
+
+fn field_kind(ty: &Type, endian: Endian) -> Option<FieldKind> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            let ident = path.path.get_ident()?.to_string();
+            match ident.as_str() {
+                "u8" | "u16" | "u32" | "u64" | "u128" => {
+                    Some(FieldKind::Integer { signed: false, endian })
+                }
+                "i8" | "i16" | "i32" | "i64" | "i128" => {
+                    Some(FieldKind::Integer { signed: true, endian })
+                }
+                "bool" => Some(FieldKind::Bool),
+                _ => None,
+            }
+        }
+        Type::Array(array) => match &*array.elem {
+            Type::Path(path) if path.path.is_ident("u8") => Some(FieldKind::ByteArray),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn expand_encode(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Struct { crate_path, name, fields, .. } = parse(&input)?;
+
+    let encode_fields = fields.iter().map(|Field { member, ty, kind, .. }| match kind {
+        FieldKind::Integer { signed, endian } => {
+            let to_bytes = match endian {
+                Endian::Big => quote!(to_be_bytes),
+                Endian::Little => quote!(to_le_bytes),
+                Endian::Native => quote!(to_ne_bytes),
+            };
+            if *signed && *endian == Endian::Big {
+                // Flipping the sign bit makes negative numbers sort before positive ones.
+                quote! { bytes.extend_from_slice(&(item.#member ^ #ty::MIN).#to_bytes()); }
+            } else {
+                quote! { bytes.extend_from_slice(&item.#member.#to_bytes()); }
+            }
+        }
+        FieldKind::Bool => quote! { bytes.push(item.#member as u8); },
+        FieldKind::ByteArray => quote! { bytes.extend_from_slice(&item.#member); },
+        FieldKind::Codec(codec) => quote! {
+            bytes.extend_from_slice(&<#codec as #crate_path::BytesEncode<'a>>::bytes_encode(&item.#member)?);
+        },
+    });
+
+    let capacity = fields.iter().map(|Field { ty, kind, .. }| match kind {
+        FieldKind::Codec(_) => quote!(0),
+        _ => quote!(::std::mem::size_of::<#ty>()),
+    });
+
+    Ok(quote! {
+        impl<'a> #crate_path::BytesEncode<'a> for #name {
+            type EItem = #name;
+
+            fn bytes_encode(
+                item: &'a Self::EItem,
+            ) -> ::std::result::Result<::std::borrow::Cow<'a, [u8]>, #crate_path::BoxedError> {
+                let mut bytes = ::std::vec::Vec::with_capacity(0 #(+ #capacity)*);
+                #(#encode_fields)*
+                ::std::result::Result::Ok(::std::borrow::Cow::Owned(bytes))
+            }
+        }
+    })
+}
+
+pub fn expand_decode(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Struct { crate_path, name, fields, style } = parse(&input)?;
+
+    let invalid = quote! {
+        #crate_path::BoxedError::from(concat!("invalid bytes for ", stringify!(#name)))
+    };
+
+    let decode_fields = fields.iter().map(|Field { binding, ty, kind, .. }| {
+        let take = quote! {
+            if bytes.len() < ::std::mem::size_of::<#ty>() {
+                return ::std::result::Result::Err(#invalid);
+            }
+            let (head, tail) = bytes.split_at(::std::mem::size_of::<#ty>());
+            bytes = tail;
+        };
+
+        match kind {
+            FieldKind::Integer { signed, endian } => {
+                let from_bytes = match endian {
+                    Endian::Big => quote!(from_be_bytes),
+                    Endian::Little => quote!(from_le_bytes),
+                    Endian::Native => quote!(from_ne_bytes),
+                };
+                let flip =
+                    if *signed && *endian == Endian::Big { quote!(^ #ty::MIN) } else { quote!() };
+                quote! {
+                    #take
+                    let #binding = #ty::#from_bytes(head.try_into().unwrap()) #flip;
+                }
+            }
+            FieldKind::Bool => quote! {
+                #take
+                let #binding = match head[0] {
+                    0 => false,
+                    1 => true,
+                    _ => return ::std::result::Result::Err(#invalid),
+                };
+            },
+            FieldKind::ByteArray => quote! {
+                #take
+                let #binding: #ty = head.try_into().unwrap();
+            },
+            FieldKind::Codec(codec) => quote! {
+                let #binding = ::std::convert::Into::into(
+                    <#codec as #crate_path::BytesDecode<'a>>::bytes_decode(bytes)?,
+                );
+                bytes = &[];
+            },
+        }
+    });
+
+    let bindings = fields.iter().map(|f| &f.binding);
+    let construct = match style {
+        Fields::Named(_) => {
+            let members = fields.iter().map(|f| &f.member);
+            quote!(#name { #(#members: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(#name(#(#bindings),*)),
+        Fields::Unit => quote!(#name),
+    };
+
+    Ok(quote! {
+        impl<'a> #crate_path::BytesDecode<'a> for #name {
+            type DItem = #name;
+
+            fn bytes_decode(
+                bytes: &'a [u8],
+            ) -> ::std::result::Result<Self::DItem, #crate_path::BoxedError> {
+                let mut bytes = bytes;
+                #(#decode_fields)*
+                if !bytes.is_empty() {
+                    return ::std::result::Result::Err(#invalid);
+                }
+                ::std::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
@@ -0,0 +1,79 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Expr, ExprLit, Fields, Lit};
+
+use crate::attributes::ContainerAttributes;
+
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let ContainerAttributes { crate_path, endian } = ContainerAttributes::parse(&input.attrs)?;
+    let name = &input.ident;
+
+    if endian.is_some() {
+        return Err(Error::new_spanned(
+            name,
+            "the endian attribute is not supported by Discriminant",
+        ));
+    }
+
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Discriminant can only be derived for enums",
+            ))
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "Discriminant cannot be derived for generic enums",
+        ));
+    }
+
+    let mut to_arms = Vec::new();
+    let mut from_arms = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(variant, "Discriminant variants must not have fields"));
+        }
+
+        let value = match &variant.discriminant {
+            Some((_, Expr::Lit(ExprLit { lit: Lit::Int(int), .. }))) => {
+                int.base10_parse::<u64>()?
+            }
+            Some((_, expr)) => {
+                return Err(Error::new_spanned(expr, "the discriminant must be an integer literal"))
+            }
+            None => {
+                return Err(Error::new_spanned(
+                    variant,
+                    "every variant must have an explicit discriminant, e.g. `Variant = 1`",
+                ))
+            }
+        };
+
+        to_arms.push(quote! { #name::#ident => #value });
+        from_arms.push(quote! { #value => ::std::option::Option::Some(#name::#ident) });
+    }
+
+    Ok(quote! {
+        impl #crate_path::types::Discriminant for #name {
+            fn discriminant(&self) -> u64 {
+                match self {
+                    #(#to_arms,)*
+                }
+            }
+
+            fn from_discriminant(discriminant: u64) -> ::std::option::Option<Self> {
+                match discriminant {
+                    #(#from_arms,)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
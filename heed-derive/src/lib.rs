@@ -0,0 +1,84 @@
+//! Derive macros for the traits of the `heed` and `heed3` crates.
+//!
+//! These macros are re-exported by `heed-types` when the `derive` feature is enabled,
+//! you should not depend on this crate directly.
+//!
+//! By default the generated code refers to the `heed` crate. When using `heed3`,
+//! annotate the type with `#[heed(crate = "heed3")]`.
+
+mod attributes;
+mod codec;
+mod discriminant;
+mod schema;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implements the `Discriminant` trait for a fieldless enum,
+/// making it usable with the `UnitEnum` codec.
+///
+/// Every variant must have an explicit, non-negative, discriminant so that
+/// reordering or adding variants never changes what is stored on disk.
+///
+/// ```ignore
+/// #[derive(Discriminant)]
+/// enum Color {
+///     Red = 1,
+///     Green = 2,
+///     Blue = 3,
+/// }
+/// ```
+#[proc_macro_derive(Discriminant, attributes(heed))]
+pub fn derive_discriminant(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    discriminant::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implements `BytesEncode` for a struct, the struct being its own codec.
+///
+/// Fields are encoded one after the other, in declaration order. Integers are written
+/// in big-endian by default, with the sign bit of signed integers flipped, so that the
+/// lexicographic order of the encoded bytes is the order of the fields. The byte order can
+/// be changed with `#[heed(endian = "little")]` or `#[heed(endian = "native")]`, on the
+/// struct or on a field, at the cost of this ordering.
+///
+/// Supported fields are the primitive integers, `bool` and byte arrays. The last field
+/// can be encoded with any codec using `#[heed(codec = "...")]`, it then takes all the
+/// remaining bytes. Its type must be convertible from the codec decoded type.
+///
+/// ```ignore
+/// #[derive(BytesEncode, BytesDecode)]
+/// struct UserEvent {
+///     user_id: u32,
+///     timestamp: i64,
+///     #[heed(codec = "Str")]
+///     kind: String,
+/// }
+///
+/// let db: Database<UserEvent, Unit> = env.create_database(&mut wtxn, None)?;
+/// ```
+#[proc_macro_derive(BytesEncode, attributes(heed))]
+pub fn derive_bytes_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    codec::expand_encode(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implements `BytesDecode` for a struct, the struct being its own codec.
+///
+/// See the [`BytesEncode`](derive@BytesEncode) derive for the encoding of the fields.
+#[proc_macro_derive(BytesDecode, attributes(heed))]
+pub fn derive_bytes_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    codec::expand_decode(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implements the `DatabaseSchema` trait for a struct whose fields are all databases.
+///
+/// Each database is named after its field unless `#[heed(name = "...")]` is specified,
+/// and its flags can be set with `#[heed(flags = "DUP_SORT | DUP_FIXED")]`.
+/// The codecs and comparators are the ones of the type of the field.
+#[proc_macro_derive(DatabaseSchema, attributes(heed))]
+pub fn derive_database_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    schema::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
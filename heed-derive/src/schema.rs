@@ -0,0 +1,85 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, LitStr};
+
+use crate::attributes::{ContainerAttributes, SchemaFieldAttributes};
+
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let ContainerAttributes { crate_path, endian } = ContainerAttributes::parse(&input.attrs)?;
+    let name = &input.ident;
+
+    if endian.is_some() {
+        return Err(Error::new_spanned(
+            name,
+            "the endian attribute is not supported by DatabaseSchema",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(name, "DatabaseSchema requires named fields")),
+        },
+        _ => {
+            return Err(Error::new_spanned(name, "DatabaseSchema can only be derived for structs"))
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "DatabaseSchema cannot be derived for generic structs",
+        ));
+    }
+
+    let mut idents = Vec::new();
+    let mut names = Vec::new();
+    let mut options = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let SchemaFieldAttributes { name, flags } = SchemaFieldAttributes::parse(&field.attrs)?;
+        let name = name.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+
+        // The codecs and comparators are inferred from the type of the field.
+        options.push(quote! {{
+            let mut options = env
+                .database_options()
+                .types()
+                .key_comparator()
+                .dup_sort_comparator();
+            options.name(#name);
+            options.flags(#crate_path::DatabaseFlags::empty() #(| #crate_path::DatabaseFlags::#flags)*);
+            options
+        }});
+
+        idents.push(ident);
+        names.push(name);
+    }
+
+    Ok(quote! {
+        impl #crate_path::DatabaseSchema for #name {
+            const DATABASE_NAMES: &'static [&'static str] = &[#(#names),*];
+
+            fn open<T>(
+                env: &#crate_path::Env<T>,
+                wtxn: &mut impl #crate_path::WriteTxn,
+            ) -> #crate_path::Result<Self> {
+                ::std::result::Result::Ok(#name {
+                    #(#idents: #options.create(wtxn)?,)*
+                })
+            }
+
+            fn open_existing<T>(
+                env: &#crate_path::Env<T>,
+                rtxn: &impl #crate_path::ReadTxn,
+            ) -> #crate_path::Result<::std::option::Option<Self>> {
+                #(
+                    let ::std::option::Option::Some(#idents) = #options.open(rtxn)? else {
+                        return ::std::result::Result::Ok(::std::option::Option::None);
+                    };
+                )*
+                ::std::result::Result::Ok(::std::option::Option::Some(#name { #(#idents),* }))
+            }
+        }
+    })
+}
@@ -12,6 +12,7 @@
 use std::borrow::Cow;
 use std::cmp::{Ord, Ordering};
 use std::error::Error as StdError;
+use std::io;
 
 /// A boxed `Send + Sync + 'static` error.
 pub type BoxedError = Box<dyn StdError + Send + Sync + 'static>;
@@ -23,6 +24,20 @@ pub trait BytesEncode<'a> {
 
     /// Encode the given item as bytes.
     fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError>;
+
+    /// Encode the given item by writing its bytes in the writer.
+    ///
+    /// The writes of heed encode the keys and values in a buffer reused by the transaction,
+    /// and the small keys in a buffer on the stack. The default implementation copies the
+    /// bytes returned by [`BytesEncode::bytes_encode`], the codecs that allocate their bytes
+    /// can write them in the buffer instead.
+    fn bytes_encode_into<W: io::Write>(
+        item: &'a Self::EItem,
+        writer: &mut W,
+    ) -> Result<(), BoxedError> {
+        writer.write_all(&Self::bytes_encode(item)?)?;
+        Ok(())
+    }
 }
 
 /// A trait that represents a decoding structure.
@@ -34,6 +49,73 @@ pub trait BytesDecode<'a> {
     fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError>;
 }
 
+/// A trait that represents a decoding structure whose decoded items do not borrow the bytes.
+///
+/// It is automatically implemented for the [`BytesDecode`] types
+/// that decode the same type whatever the lifetime of the bytes.
+pub trait BytesDecodeOwned {
+    /// The type to decode.
+    type DItem;
+
+    /// Decode the given bytes as `DItem`.
+    fn bytes_decode_owned(bytes: &[u8]) -> Result<Self::DItem, BoxedError>;
+}
+
+impl<T, D> BytesDecodeOwned for D
+where
+    D: for<'a> BytesDecode<'a, DItem = T>,
+{
+    type DItem = T;
+
+    fn bytes_decode_owned(bytes: &[u8]) -> Result<Self::DItem, BoxedError> {
+        D::bytes_decode(bytes)
+    }
+}
+
+/// A codec whose encodings all have the same size.
+///
+/// The values of a database with the `DUP_FIXED` flag must all have the same size, the
+/// [`BytesEncode`] types that implement this trait can be used as their codec.
+pub trait FixedSize {
+    /// The size of every encoded item, in bytes.
+    const SIZE: usize;
+}
+
+/// A stable identity of the format of the bytes of a codec, or of the order of a comparator.
+///
+/// heed records the identities of the codecs and comparators of the databases whose codecs are
+/// checked, and refuses to open them with other ones. Unlike the type names, the identities
+/// depend neither on the version of the compiler nor on the path of the types.
+pub trait CodecIdentity {
+    /// The name of the format, unique among the codecs or among the comparators.
+    const NAME: &'static str;
+
+    /// The version of the format, to increase whenever the bytes, or the order, change.
+    const VERSION: u32 = 1;
+
+    /// Writes the identity, the name and the version of the format followed by its parameters
+    /// in brackets, if any, e.g. `str@1` or `versioned@1[serde-json@1]`.
+    fn write_identity(identity: &mut String) {
+        identity.push_str(Self::NAME);
+        identity.push('@');
+        identity.push_str(&Self::VERSION.to_string());
+        let start = identity.len();
+        identity.push('[');
+        Self::write_parameters(identity);
+        if identity.len() == start + 1 {
+            identity.truncate(start);
+        } else {
+            identity.push(']');
+        }
+    }
+
+    /// Writes the parameters of the format, e.g. the identities of the wrapped codecs
+    /// separated by commas. There are none by default.
+    fn write_parameters(identity: &mut String) {
+        let _ = identity;
+    }
+}
+
 /// Define a custom key comparison function for a database.
 ///
 /// The comparison function is called whenever it is necessary to compare a key specified
@@ -56,6 +138,9 @@ pub trait Comparator {
 pub trait LexicographicComparator: Comparator {
     /// Compare a single byte; this function is used to implement [`Comparator::compare`]
     /// by definition of lexicographic ordering.
+    ///
+    /// Two different bytes can be equal, the keys only differing by these bytes are then
+    /// considered equal too.
     fn compare_elem(a: u8, b: u8) -> Ordering;
 
     /// Advances the given `elem` to its immediate lexicographic successor, if possible.
@@ -79,7 +164,10 @@ impl<C: LexicographicComparator> Comparator for C {
     fn compare(a: &[u8], b: &[u8]) -> Ordering {
         for idx in 0..std::cmp::min(a.len(), b.len()) {
             if a[idx] != b[idx] {
-                return C::compare_elem(a[idx], b[idx]);
+                match C::compare_elem(a[idx], b[idx]) {
+                    Ordering::Equal => continue,
+                    ordering => return ordering,
+                }
             }
         }
         Ord::cmp(&a.len(), &b.len())
@@ -0,0 +1,32 @@
+use std::borrow::Cow;
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+use roaring::RoaringBitmap;
+
+/// Describes a [`RoaringBitmap`] stored in the portable roaring serialization format.
+///
+/// This is the format shared by the roaring implementations of the other languages
+/// and the one the zero-copy, frozen, views of CRoaring can read directly.
+pub enum Bitmap {}
+
+impl BytesEncode<'_> for Bitmap {
+    type EItem = RoaringBitmap;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        let mut bytes = Vec::with_capacity(item.serialized_size());
+        item.serialize_into(&mut bytes)?;
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+impl BytesDecode<'_> for Bitmap {
+    type DItem = RoaringBitmap;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        RoaringBitmap::deserialize_from(bytes).map_err(Into::into)
+    }
+}
+
+impl CodecIdentity for Bitmap {
+    const NAME: &'static str = "bitmap";
+}
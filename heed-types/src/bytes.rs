@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
 
 /// Describes a byte slice `[u8]` that is totally borrowed and doesn't depend on
 /// any [memory alignment].
@@ -23,3 +23,7 @@ impl<'a> BytesDecode<'a> for Bytes {
         Ok(bytes)
     }
 }
+
+impl CodecIdentity for Bytes {
+    const NAME: &'static str = "bytes";
+}
@@ -0,0 +1,367 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+
+/// The header byte used for values that are stored without compression,
+/// because compressing them would not have made them smaller.
+const STORED_ID: u8 = 0;
+
+/// The size of the header prepended to every value: the algorithm
+/// identifier followed by the big-endian uncompressed length.
+const HEADER_LEN: usize = 1 + 4;
+
+/// A compression algorithm that can be used with the [`Compressed`] codec.
+pub trait Compression {
+    /// A unique, non-zero, identifier stored in the header of every compressed value.
+    ///
+    /// It is used to detect values that were written with another algorithm.
+    const ID: u8;
+
+    /// The largest ratio between the uncompressed and the compressed sizes the algorithm
+    /// can produce.
+    ///
+    /// The uncompressed length in the header of a value is checked against it before
+    /// decompressing, a corrupted header can't make the codec allocate a huge buffer.
+    const MAX_RATIO: usize;
+
+    /// Compresses the given bytes.
+    fn compress(bytes: &[u8]) -> Result<Vec<u8>, BoxedError>;
+
+    /// Decompresses the given bytes, `uncompressed_len` being the size of the original value.
+    fn decompress(bytes: &[u8], uncompressed_len: usize) -> Result<Vec<u8>, BoxedError>;
+}
+
+/// A dictionary that compression algorithms can use to better compress small values.
+///
+/// The same dictionary must be used to read values that were written with it.
+pub trait Dictionary {
+    /// The raw dictionary, for example trained with `zstd --train`.
+    const DICTIONARY: &'static [u8];
+}
+
+/// Do not use any dictionary when compressing values.
+pub enum NoDictionary {}
+
+impl Dictionary for NoDictionary {
+    const DICTIONARY: &'static [u8] = &[];
+}
+
+/// Wraps another codec and compresses the bytes it produces.
+///
+/// Every value is prefixed by a small header containing the algorithm identifier
+/// and the uncompressed length. Values that do not shrink when compressed are
+/// stored as is, only prefixed by this header.
+///
+/// Decoding must go through a temporary buffer, therefore the inner codec
+/// must be able to decode owned values from any slice.
+///
+/// ```
+/// # #[cfg(all(feature = "compression-zstd", feature = "serde-json"))] {
+/// use heed_traits::{BytesDecode, BytesEncode};
+/// use heed_types::{Compressed, SerdeJson, Zstd};
+///
+/// type CompressedJson = Compressed<SerdeJson<Vec<String>>, Zstd>;
+///
+/// let words = vec![String::from("hello"); 100];
+/// let bytes = CompressedJson::bytes_encode(&words).unwrap();
+/// assert!(bytes.len() < serde_json::to_vec(&words).unwrap().len());
+/// assert_eq!(CompressedJson::bytes_decode(&bytes).unwrap(), words);
+/// # }
+/// ```
+pub struct Compressed<C, A>(PhantomData<(C, A)>);
+
+impl<'a, C, A> BytesEncode<'a> for Compressed<C, A>
+where
+    C: BytesEncode<'a>,
+    A: Compression,
+{
+    type EItem = C::EItem;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        let bytes = C::bytes_encode(item)?;
+        let uncompressed_len =
+            u32::try_from(bytes.len()).map_err(|_| CompressionError::TooLarge)?;
+        let compressed = A::compress(&bytes)?;
+
+        let (id, payload) = if compressed.len() < bytes.len() {
+            (A::ID, &compressed[..])
+        } else {
+            (STORED_ID, &bytes[..])
+        };
+
+        let mut output = Vec::with_capacity(HEADER_LEN + payload.len());
+        output.push(id);
+        output.extend_from_slice(&uncompressed_len.to_be_bytes());
+        output.extend_from_slice(payload);
+        Ok(Cow::Owned(output))
+    }
+}
+
+impl<'a, C, A, T: 'a> BytesDecode<'a> for Compressed<C, A>
+where
+    C: for<'b> BytesDecode<'b, DItem = T>,
+    A: Compression,
+{
+    type DItem = T;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CompressionError::InvalidHeader.into());
+        }
+
+        let (header, payload) = bytes.split_at(HEADER_LEN);
+        let id = header[0];
+        let uncompressed_len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+
+        match id {
+            STORED_ID if payload.len() != uncompressed_len => {
+                Err(CompressionError::InvalidLength.into())
+            }
+            STORED_ID => C::bytes_decode(payload),
+            id if id == A::ID => {
+                if uncompressed_len > payload.len().saturating_mul(A::MAX_RATIO) {
+                    return Err(CompressionError::InvalidLength.into());
+                }
+                let decompressed = A::decompress(payload, uncompressed_len)?;
+                if decompressed.len() != uncompressed_len {
+                    return Err(CompressionError::InvalidLength.into());
+                }
+                C::bytes_decode(&decompressed)
+            }
+            id => Err(CompressionError::UnknownAlgorithm(id).into()),
+        }
+    }
+}
+
+impl<C: CodecIdentity, A: CodecIdentity> CodecIdentity for Compressed<C, A> {
+    const NAME: &'static str = "compressed";
+
+    fn write_parameters(identity: &mut String) {
+        C::write_identity(identity);
+        identity.push(',');
+        A::write_identity(identity);
+    }
+}
+
+unsafe impl<C, A> Send for Compressed<C, A> {}
+
+unsafe impl<C, A> Sync for Compressed<C, A> {}
+
+/// The error returned by the [`Compressed`] codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionError {
+    /// The value is too short to contain the compression header.
+    InvalidHeader,
+    /// The length recorded in the header is impossible for the value, or the
+    /// decompressed value does not have it.
+    InvalidLength,
+    /// The value was compressed with another algorithm.
+    UnknownAlgorithm(u8),
+    /// The value to compress is larger than 4GiB.
+    TooLarge,
+}
+
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressionError::InvalidHeader => f.write_str("missing compression header"),
+            CompressionError::InvalidLength => {
+                f.write_str("decompressed length does not match the header")
+            }
+            CompressionError::UnknownAlgorithm(id) => {
+                write!(f, "value compressed with an unknown algorithm ({id})")
+            }
+            CompressionError::TooLarge => f.write_str("value too large to be compressed"),
+        }
+    }
+}
+
+impl Error for CompressionError {}
+
+/// The [zstd](https://facebook.github.io/zstd/) compression algorithm,
+/// using the default compression level and an optional dictionary.
+#[cfg(feature = "compression-zstd")]
+pub struct Zstd<D = NoDictionary>(PhantomData<D>);
+
+#[cfg(feature = "compression-zstd")]
+impl<D: Dictionary> Compression for Zstd<D> {
+    const ID: u8 = 1;
+    // A block of at most 128KiB repeating a single byte takes four bytes.
+    const MAX_RATIO: usize = 32 * 1024;
+
+    fn compress(bytes: &[u8]) -> Result<Vec<u8>, BoxedError> {
+        let level = zstd::DEFAULT_COMPRESSION_LEVEL;
+        let mut compressor = zstd::bulk::Compressor::with_dictionary(level, D::DICTIONARY)?;
+        compressor.compress(bytes).map_err(Into::into)
+    }
+
+    fn decompress(bytes: &[u8], uncompressed_len: usize) -> Result<Vec<u8>, BoxedError> {
+        let mut decompressor = zstd::bulk::Decompressor::with_dictionary(D::DICTIONARY)?;
+        decompressor.decompress(bytes, uncompressed_len).map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "compression-zstd")]
+impl<D> CodecIdentity for Zstd<D> {
+    const NAME: &'static str = "zstd";
+}
+
+/// The [LZ4](https://lz4.org/) block compression algorithm,
+/// with an optional dictionary.
+#[cfg(feature = "compression-lz4")]
+pub struct Lz4<D = NoDictionary>(PhantomData<D>);
+
+#[cfg(feature = "compression-lz4")]
+impl<D: Dictionary> Compression for Lz4<D> {
+    const ID: u8 = 2;
+    // A match length grows by 255 for every additional byte.
+    const MAX_RATIO: usize = 255;
+
+    fn compress(bytes: &[u8]) -> Result<Vec<u8>, BoxedError> {
+        Ok(lz4_flex::block::compress_with_dict(bytes, D::DICTIONARY))
+    }
+
+    fn decompress(bytes: &[u8], uncompressed_len: usize) -> Result<Vec<u8>, BoxedError> {
+        lz4_flex::block::decompress_with_dict(bytes, uncompressed_len, D::DICTIONARY)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "compression-lz4")]
+impl<D> CodecIdentity for Lz4<D> {
+    const NAME: &'static str = "lz4";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes decoded in an owned vector, as required by the inner codec.
+    enum OwnedBytes {}
+
+    impl BytesEncode<'_> for OwnedBytes {
+        type EItem = [u8];
+
+        fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+            Ok(Cow::Borrowed(item))
+        }
+    }
+
+    impl BytesDecode<'_> for OwnedBytes {
+        type DItem = Vec<u8>;
+
+        fn bytes_decode(bytes: &[u8]) -> Result<Self::DItem, BoxedError> {
+            Ok(bytes.to_vec())
+        }
+    }
+
+    /// An algorithm that never shrinks the values.
+    enum Incompressible {}
+
+    impl Compression for Incompressible {
+        const ID: u8 = 42;
+        const MAX_RATIO: usize = 1;
+
+        fn compress(bytes: &[u8]) -> Result<Vec<u8>, BoxedError> {
+            Ok(bytes.to_vec())
+        }
+
+        fn decompress(_bytes: &[u8], _uncompressed_len: usize) -> Result<Vec<u8>, BoxedError> {
+            unreachable!("the values are always stored")
+        }
+    }
+
+    #[allow(dead_code)]
+    enum Words {}
+
+    impl Dictionary for Words {
+        const DICTIONARY: &'static [u8] = b"the quick brown fox jumps over the lazy dog";
+    }
+
+    fn error(result: Result<Vec<u8>, BoxedError>) -> CompressionError {
+        *result.unwrap_err().downcast::<CompressionError>().unwrap()
+    }
+
+    fn header(id: u8, len: u32) -> Vec<u8> {
+        let mut bytes = vec![id];
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn stored_values() {
+        type Codec = Compressed<OwnedBytes, Incompressible>;
+
+        let bytes = Codec::bytes_encode(b"hello").unwrap();
+        assert_eq!(bytes, [&header(STORED_ID, 5)[..], b"hello"].concat());
+        assert_eq!(Codec::bytes_decode(&bytes).unwrap(), b"hello");
+
+        // The length of a stored value must match its header.
+        let bytes = [&header(STORED_ID, 6)[..], b"hello"].concat();
+        assert_eq!(error(Codec::bytes_decode(&bytes)), CompressionError::InvalidLength);
+        assert_eq!(error(Codec::bytes_decode(&[STORED_ID, 0])), CompressionError::InvalidHeader);
+    }
+
+    #[test]
+    fn unknown_algorithm() {
+        type Codec = Compressed<OwnedBytes, Incompressible>;
+
+        let bytes = [&header(7, 5)[..], b"hello"].concat();
+        assert_eq!(error(Codec::bytes_decode(&bytes)), CompressionError::UnknownAlgorithm(7));
+    }
+
+    #[cfg(feature = "compression-zstd")]
+    #[test]
+    fn zstd() {
+        let value = b"the quick brown fox jumps over the lazy dog".repeat(100);
+        let bytes = Compressed::<OwnedBytes, Zstd>::bytes_encode(&value).unwrap();
+        assert_eq!(bytes[0], Zstd::<NoDictionary>::ID);
+        assert!(bytes.len() < value.len());
+        assert_eq!(Compressed::<OwnedBytes, Zstd>::bytes_decode(&bytes).unwrap(), value);
+
+        // The values compressed with a dictionary need it to be decompressed.
+        let value = b"the lazy dog jumps over the quick brown fox";
+        let bytes = Compressed::<OwnedBytes, Zstd<Words>>::bytes_encode(value).unwrap();
+        assert_eq!(bytes[0], Zstd::<Words>::ID);
+        assert_eq!(Compressed::<OwnedBytes, Zstd<Words>>::bytes_decode(&bytes).unwrap(), value);
+        assert!(Compressed::<OwnedBytes, Zstd>::bytes_decode(&bytes).is_err());
+
+        // A value of another algorithm is detected.
+        let result = Compressed::<OwnedBytes, Zstd>::bytes_decode(&header(2, 0));
+        assert_eq!(error(result), CompressionError::UnknownAlgorithm(2));
+    }
+
+    #[cfg(feature = "compression-lz4")]
+    #[test]
+    fn lz4() {
+        let value = b"the quick brown fox jumps over the lazy dog".repeat(100);
+        let bytes = Compressed::<OwnedBytes, Lz4>::bytes_encode(&value).unwrap();
+        assert_eq!(bytes[0], Lz4::<NoDictionary>::ID);
+        assert!(bytes.len() < value.len());
+        assert_eq!(Compressed::<OwnedBytes, Lz4>::bytes_decode(&bytes).unwrap(), value);
+
+        let value = b"the lazy dog jumps over the quick brown fox";
+        let bytes = Compressed::<OwnedBytes, Lz4<Words>>::bytes_encode(value).unwrap();
+        assert_eq!(bytes[0], Lz4::<Words>::ID);
+        assert_eq!(Compressed::<OwnedBytes, Lz4<Words>>::bytes_decode(&bytes).unwrap(), value);
+        assert!(Compressed::<OwnedBytes, Lz4>::bytes_decode(&bytes).is_err());
+    }
+
+    #[cfg(feature = "compression-lz4")]
+    #[test]
+    fn implausible_lengths() {
+        type Codec = Compressed<OwnedBytes, Lz4>;
+
+        // The header can't make the codec allocate more than the algorithm can produce.
+        let bytes = [&header(Lz4::<NoDictionary>::ID, u32::MAX)[..], &[0; 16]].concat();
+        assert_eq!(error(Codec::bytes_decode(&bytes)), CompressionError::InvalidLength);
+
+        let value = vec![0; 10_000];
+        let mut bytes = Codec::bytes_encode(&value).unwrap().into_owned();
+        bytes[1..HEADER_LEN].copy_from_slice(&9_999u32.to_be_bytes());
+        assert!(Codec::bytes_decode(&bytes).is_err());
+    }
+}
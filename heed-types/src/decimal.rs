@@ -0,0 +1,222 @@
+use std::borrow::Cow;
+use std::{error, fmt};
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+use rust_decimal::Decimal;
+
+const NEGATIVE: u8 = 0x00;
+const ZERO: u8 = 0x01;
+const POSITIVE: u8 = 0x02;
+
+/// The bias added to the decimal exponent so that it fits in a byte.
+const EXPONENT_BIAS: i32 = 128;
+
+/// Describes a [`rust_decimal::Decimal`] stored with an order-preserving encoding.
+///
+/// The lexicographic order of the encoded bytes is the numeric order of the decimals,
+/// monetary amounts can therefore be used as keys and scanned by range.
+/// Decimals are normalized before being encoded: `1.50` and `1.5` are the same key
+/// and decode to `1.5`.
+///
+/// The encoding is a sign byte, followed, for non-zero values, by the biased decimal
+/// exponent and the significant digits packed in nibbles, terminated by a zero nibble.
+/// The bytes following the sign are inverted for negative values.
+pub enum SortableDecimal {}
+
+impl BytesEncode<'_> for SortableDecimal {
+    type EItem = Decimal;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        let decimal = item.normalize();
+        if decimal.is_zero() {
+            return Ok(Cow::Owned(vec![ZERO]));
+        }
+
+        let digits = decimal.mantissa().unsigned_abs().to_string();
+        let exponent = digits.len() as i32 - decimal.scale() as i32 + EXPONENT_BIAS;
+
+        let mut buf = Vec::with_capacity(2 + digits.len() / 2 + 1);
+        buf.push(if decimal.is_sign_negative() { NEGATIVE } else { POSITIVE });
+        buf.push(exponent as u8);
+
+        // Digits are stored as nibbles from 1 to 10 so that the zero nibble terminates them.
+        let mut nibbles = digits.bytes().map(|d| d - b'0' + 1).chain(Some(0));
+        while let Some(high) = nibbles.next() {
+            let low = nibbles.next().unwrap_or(0);
+            buf.push(high << 4 | low);
+        }
+
+        if decimal.is_sign_negative() {
+            buf[1..].iter_mut().for_each(|b| *b = !*b);
+        }
+
+        Ok(Cow::Owned(buf))
+    }
+}
+
+impl BytesDecode<'_> for SortableDecimal {
+    type DItem = Decimal;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let (negative, rest) = match bytes.split_first() {
+            Some((&ZERO, [])) => return Ok(Decimal::ZERO),
+            Some((&NEGATIVE, rest)) => (true, rest),
+            Some((&POSITIVE, rest)) => (false, rest),
+            _ => return Err(InvalidDecimalError.into()),
+        };
+
+        let mut rest = rest.iter().map(|&b| if negative { !b } else { b });
+        let exponent = rest.next().ok_or(InvalidDecimalError)? as i32 - EXPONENT_BIAS;
+
+        let mut mantissa: i128 = 0;
+        let mut digits = 0;
+        let mut terminated = false;
+        for byte in rest {
+            if terminated {
+                return Err(InvalidDecimalError.into());
+            }
+            for nibble in [byte >> 4, byte & 0x0f] {
+                match nibble {
+                    // The padding nibble after a high terminator must also be zero.
+                    0 if byte & 0x0f == 0 => {
+                        terminated = true;
+                        break;
+                    }
+                    1..=10 => {
+                        mantissa = mantissa
+                            .checked_mul(10)
+                            .and_then(|m| m.checked_add(nibble as i128 - 1))
+                            .ok_or(InvalidDecimalError)?;
+                        digits += 1;
+                    }
+                    _ => return Err(InvalidDecimalError.into()),
+                }
+            }
+        }
+
+        if !terminated || digits == 0 {
+            return Err(InvalidDecimalError.into());
+        }
+
+        let mut scale = digits - exponent;
+        if scale < 0 {
+            mantissa = 10i128
+                .checked_pow(-scale as u32)
+                .and_then(|p| mantissa.checked_mul(p))
+                .ok_or(InvalidDecimalError)?;
+            scale = 0;
+        }
+
+        if negative {
+            mantissa = -mantissa;
+        }
+
+        Decimal::try_from_i128_with_scale(mantissa, scale as u32).map_err(Into::into)
+    }
+}
+
+impl CodecIdentity for SortableDecimal {
+    const NAME: &'static str = "sortable-decimal";
+}
+
+/// The slice of bytes does not represent a valid sortable decimal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidDecimalError;
+
+impl fmt::Display for InvalidDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the slice of bytes does not represent a valid sortable decimal")
+    }
+}
+
+impl error::Error for InvalidDecimalError {}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn decimals() -> Vec<Decimal> {
+        [
+            "-79228162514264337593543950335",
+            "-1000",
+            "-999.99",
+            "-12.5",
+            "-1.5",
+            "-1",
+            "-0.5",
+            "-0.05",
+            "-0.0000000000000000000000000001",
+            "0",
+            "0.0000000000000000000000000001",
+            "0.05",
+            "0.055",
+            "0.5",
+            "1",
+            "1.05",
+            "1.5",
+            "10",
+            "12.5",
+            "100",
+            "999.99",
+            "1000",
+            "79228162514264337593543950335",
+        ]
+        .iter()
+        .map(|d| Decimal::from_str(d).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for decimal in decimals() {
+            let bytes = SortableDecimal::bytes_encode(&decimal).unwrap();
+            assert_eq!(SortableDecimal::bytes_decode(&bytes).unwrap(), decimal);
+        }
+
+        // The scale is normalized away.
+        let scaled = Decimal::from_str("1.500").unwrap();
+        let normalized = Decimal::from_str("1.5").unwrap();
+        let bytes = SortableDecimal::bytes_encode(&scaled).unwrap();
+        assert_eq!(bytes, SortableDecimal::bytes_encode(&normalized).unwrap());
+        let decoded = SortableDecimal::bytes_decode(&bytes).unwrap();
+        assert_eq!(decoded, scaled);
+        assert_eq!(decoded.scale(), 1);
+
+        let negative_zero = Decimal::from_str("-0.00").unwrap();
+        assert_eq!(&SortableDecimal::bytes_encode(&negative_zero).unwrap()[..], [ZERO]);
+    }
+
+    #[test]
+    fn ordering_and_prefix_freeness() {
+        let encoded: Vec<_> = decimals()
+            .iter()
+            .map(|d| SortableDecimal::bytes_encode(d).unwrap().into_owned())
+            .collect();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+
+        for (i, a) in encoded.iter().enumerate() {
+            for (j, b) in encoded.iter().enumerate() {
+                assert!(i == j || !b.starts_with(a), "{a:?} is a prefix of {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        let decimal = Decimal::from_str("1.5").unwrap();
+        let bytes = SortableDecimal::bytes_encode(&decimal).unwrap();
+        assert_eq!(&bytes[..], [POSITIVE, 129, 0x26, 0x00]);
+
+        assert!(SortableDecimal::bytes_decode(&[]).is_err());
+        assert!(SortableDecimal::bytes_decode(&[ZERO, 0]).is_err());
+        assert!(SortableDecimal::bytes_decode(&[POSITIVE]).is_err());
+        // Missing terminator, trailing byte and non-zero padding nibble.
+        assert!(SortableDecimal::bytes_decode(&[POSITIVE, 129, 0x26]).is_err());
+        assert!(SortableDecimal::bytes_decode(&[POSITIVE, 129, 0x26, 0x00, 0x00]).is_err());
+        assert!(SortableDecimal::bytes_decode(&[POSITIVE, 129, 0x26, 0x05]).is_err());
+        // A digit nibble above ten.
+        assert!(SortableDecimal::bytes_decode(&[POSITIVE, 129, 0x2c, 0x00]).is_err());
+    }
+}
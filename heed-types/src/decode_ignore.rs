@@ -1,4 +1,4 @@
-use heed_traits::BoxedError;
+use heed_traits::{BoxedError, CodecIdentity};
 
 /// A convenient struct made to ignore the type when decoding it.
 ///
@@ -13,3 +13,7 @@ impl heed_traits::BytesDecode<'_> for DecodeIgnore {
         Ok(())
     }
 }
+
+impl CodecIdentity for DecodeIgnore {
+    const NAME: &'static str = "decode-ignore";
+}
@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::{error, fmt};
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity, FixedSize};
+
+/// Describes an array of bytes of a fixed width, like a hash or an identifier.
+///
+/// The items are stored as is and decoded without copying the bytes. As every
+/// encoding has the same size, it can be the value codec of a `DUP_FIXED` database.
+///
+/// ```
+/// use heed_traits::{BytesDecode, BytesEncode, FixedSize};
+/// use heed_types::FixedWidth;
+///
+/// type Hash = FixedWidth<[u8; 4]>;
+///
+/// let bytes = Hash::bytes_encode(&[0, 1, 2, 3]).unwrap();
+/// assert_eq!(bytes.len(), Hash::SIZE);
+/// assert_eq!(Hash::bytes_decode(&bytes).unwrap(), &[0, 1, 2, 3]);
+/// assert!(Hash::bytes_decode(&[0, 1, 2]).is_err());
+/// ```
+pub struct FixedWidth<T>(PhantomData<T>);
+
+impl<const N: usize> FixedSize for FixedWidth<[u8; N]> {
+    const SIZE: usize = N;
+}
+
+impl<'a, const N: usize> BytesEncode<'a> for FixedWidth<[u8; N]> {
+    type EItem = [u8; N];
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        Ok(Cow::Borrowed(item))
+    }
+}
+
+impl<'a, const N: usize> BytesDecode<'a> for FixedWidth<[u8; N]> {
+    type DItem = &'a [u8; N];
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        bytes.try_into().map_err(|_| InvalidWidthError { expected: N, found: bytes.len() }.into())
+    }
+}
+
+impl<const N: usize> CodecIdentity for FixedWidth<[u8; N]> {
+    const NAME: &'static str = "fixed-width";
+
+    fn write_parameters(identity: &mut String) {
+        identity.push_str(&N.to_string());
+    }
+}
+
+/// The slice of bytes doesn't have the width of the [`FixedWidth`] array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidWidthError {
+    /// The width of the array.
+    pub expected: usize,
+    /// The length of the slice of bytes.
+    pub found: usize,
+}
+
+impl fmt::Display for InvalidWidthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {} bytes but found {} bytes", self.expected, self.found)
+    }
+}
+
+impl error::Error for InvalidWidthError {}
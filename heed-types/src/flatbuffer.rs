@@ -0,0 +1,86 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use flatbuffers::{FlatBufferBuilder, Follow, Verifiable};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+
+/// Describes a [FlatBuffers](https://flatbuffers.dev/) table of type `T`.
+///
+/// Decoding verifies the buffer and returns the generated reader borrowing the
+/// database bytes, nothing is copied. Encoding takes a finished [`FlatBufferBuilder`]
+/// and stores its data as is, the builder can then be [`reset`](FlatBufferBuilder::reset)
+/// and reused as a scratch buffer for the next value.
+///
+/// Encoding verifies that the builder holds a finished `T` table and returns an
+/// error otherwise, an unfinished builder is never stored.
+///
+/// ```ignore
+/// let mut builder = FlatBufferBuilder::new();
+/// for user in users {
+///     builder.reset();
+///     let root = User::create(&mut builder, &user.args());
+///     builder.finish(root, None);
+///     db.put(&mut wtxn, &user.id, &builder)?;
+/// }
+///
+/// let user: User = db.get(&rtxn, &42)?.unwrap();
+/// ```
+pub struct FlatBuffer<T>(PhantomData<T>);
+
+impl<'a, T> BytesEncode<'a> for FlatBuffer<T>
+where
+    T: 'a + Follow<'a> + Verifiable,
+{
+    type EItem = FlatBufferBuilder<'a>;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        // `finished_data` panics on an unfinished builder, we verify the data instead.
+        let bytes = item.unfinished_data();
+        flatbuffers::root::<T>(bytes)?;
+        Ok(Cow::Borrowed(bytes))
+    }
+}
+
+impl<'a, T> BytesDecode<'a> for FlatBuffer<T>
+where
+    T: 'a + Follow<'a> + Verifiable,
+    T::Inner: 'a,
+{
+    type DItem = T::Inner;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        flatbuffers::root::<T>(bytes).map_err(Into::into)
+    }
+}
+
+impl<T> CodecIdentity for FlatBuffer<T> {
+    const NAME: &'static str = "flatbuffer";
+}
+
+unsafe impl<T> Send for FlatBuffer<T> {}
+
+unsafe impl<T> Sync for FlatBuffer<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut builder = FlatBufferBuilder::new();
+        let root = builder.create_string("hello");
+        builder.finish(root, None);
+
+        let bytes = FlatBuffer::<&str>::bytes_encode(&builder).unwrap();
+        assert_eq!(FlatBuffer::<&str>::bytes_decode(&bytes).unwrap(), "hello");
+    }
+
+    #[test]
+    fn unfinished_builder() {
+        let mut builder = FlatBufferBuilder::new();
+        assert!(FlatBuffer::<&str>::bytes_encode(&builder).is_err());
+
+        builder.create_string("hello");
+        assert!(FlatBuffer::<&str>::bytes_encode(&builder).is_err());
+    }
+}
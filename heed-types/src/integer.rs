@@ -1,9 +1,10 @@
 use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::mem::size_of;
+use std::{error, fmt, io, num};
 
-use byteorder::{ByteOrder, ReadBytesExt};
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity, FixedSize};
 
 /// Encodable version of [`u8`].
 pub struct U8;
@@ -16,6 +17,10 @@ impl BytesEncode<'_> for U8 {
     }
 }
 
+impl FixedSize for U8 {
+    const SIZE: usize = 1;
+}
+
 impl BytesDecode<'_> for U8 {
     type DItem = u8;
 
@@ -24,6 +29,10 @@ impl BytesDecode<'_> for U8 {
     }
 }
 
+impl CodecIdentity for U8 {
+    const NAME: &'static str = "u8";
+}
+
 /// Encodable version of [`i8`].
 pub struct I8;
 
@@ -35,6 +44,10 @@ impl BytesEncode<'_> for I8 {
     }
 }
 
+impl FixedSize for I8 {
+    const SIZE: usize = 1;
+}
+
 impl BytesDecode<'_> for I8 {
     type DItem = i8;
 
@@ -43,6 +56,10 @@ impl BytesDecode<'_> for I8 {
     }
 }
 
+impl CodecIdentity for I8 {
+    const NAME: &'static str = "i8";
+}
+
 macro_rules! define_type {
     ($name:ident, $native:ident, $read_method:ident, $write_method:ident) => {
         #[doc = "Encodable version of [`"]
@@ -59,6 +76,19 @@ macro_rules! define_type {
                 O::$write_method(&mut buf, *item);
                 Ok(Cow::from(buf))
             }
+
+            fn bytes_encode_into<W: io::Write>(
+                item: &Self::EItem,
+                writer: &mut W,
+            ) -> Result<(), BoxedError> {
+                let mut buf = [0; size_of::<Self::EItem>()];
+                O::$write_method(&mut buf, *item);
+                writer.write_all(&buf).map_err(Into::into)
+            }
+        }
+
+        impl<O> FixedSize for $name<O> {
+            const SIZE: usize = size_of::<$native>();
         }
 
         impl<O: ByteOrder> BytesDecode<'_> for $name<O> {
@@ -68,6 +98,14 @@ macro_rules! define_type {
                 bytes.$read_method::<O>().map_err(Into::into)
             }
         }
+
+        impl CodecIdentity for $name<BigEndian> {
+            const NAME: &'static str = concat!(stringify!($native), "-be");
+        }
+
+        impl CodecIdentity for $name<LittleEndian> {
+            const NAME: &'static str = concat!(stringify!($native), "-le");
+        }
     };
 }
 
@@ -79,3 +117,171 @@ define_type!(I16, i16, read_i16, write_i16);
 define_type!(I32, i32, read_i32, write_i32);
 define_type!(I64, i64, read_i64, write_i64);
 define_type!(I128, i128, read_i128, write_i128);
+
+/// Encodable version of [`std::num::NonZeroU8`].
+pub struct NonZeroU8;
+
+impl BytesEncode<'_> for NonZeroU8 {
+    type EItem = num::NonZeroU8;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        Ok(Cow::from([item.get()].to_vec()))
+    }
+}
+
+impl FixedSize for NonZeroU8 {
+    const SIZE: usize = 1;
+}
+
+impl BytesDecode<'_> for NonZeroU8 {
+    type DItem = num::NonZeroU8;
+
+    fn bytes_decode(mut bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let n = bytes.read_u8()?;
+        num::NonZeroU8::new(n).ok_or_else(|| ZeroError.into())
+    }
+}
+
+impl CodecIdentity for NonZeroU8 {
+    const NAME: &'static str = "non-zero-u8";
+}
+
+/// Encodable version of [`std::num::NonZeroI8`].
+pub struct NonZeroI8;
+
+impl BytesEncode<'_> for NonZeroI8 {
+    type EItem = num::NonZeroI8;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        Ok(Cow::from([item.get() as u8].to_vec()))
+    }
+}
+
+impl FixedSize for NonZeroI8 {
+    const SIZE: usize = 1;
+}
+
+impl BytesDecode<'_> for NonZeroI8 {
+    type DItem = num::NonZeroI8;
+
+    fn bytes_decode(mut bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let n = bytes.read_i8()?;
+        num::NonZeroI8::new(n).ok_or_else(|| ZeroError.into())
+    }
+}
+
+impl CodecIdentity for NonZeroI8 {
+    const NAME: &'static str = "non-zero-i8";
+}
+
+macro_rules! define_nonzero_type {
+    ($name:ident, $native:ident, $read_method:ident, $write_method:ident) => {
+        #[doc = "Encodable version of [`std::num::"]
+        #[doc = stringify!($name)]
+        #[doc = "`]."]
+
+        pub struct $name<O>(PhantomData<O>);
+
+        impl<O: ByteOrder> BytesEncode<'_> for $name<O> {
+            type EItem = num::$name;
+
+            fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+                let mut buf = vec![0; size_of::<$native>()];
+                O::$write_method(&mut buf, item.get());
+                Ok(Cow::from(buf))
+            }
+
+            fn bytes_encode_into<W: io::Write>(
+                item: &Self::EItem,
+                writer: &mut W,
+            ) -> Result<(), BoxedError> {
+                let mut buf = [0; size_of::<$native>()];
+                O::$write_method(&mut buf, item.get());
+                writer.write_all(&buf).map_err(Into::into)
+            }
+        }
+
+        impl<O> FixedSize for $name<O> {
+            const SIZE: usize = size_of::<$native>();
+        }
+
+        impl<O: ByteOrder> BytesDecode<'_> for $name<O> {
+            type DItem = num::$name;
+
+            fn bytes_decode(mut bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+                let n = bytes.$read_method::<O>()?;
+                num::$name::new(n).ok_or_else(|| ZeroError.into())
+            }
+        }
+
+        impl CodecIdentity for $name<BigEndian> {
+            const NAME: &'static str = concat!("non-zero-", stringify!($native), "-be");
+        }
+
+        impl CodecIdentity for $name<LittleEndian> {
+            const NAME: &'static str = concat!("non-zero-", stringify!($native), "-le");
+        }
+    };
+}
+
+define_nonzero_type!(NonZeroU16, u16, read_u16, write_u16);
+define_nonzero_type!(NonZeroU32, u32, read_u32, write_u32);
+define_nonzero_type!(NonZeroU64, u64, read_u64, write_u64);
+define_nonzero_type!(NonZeroU128, u128, read_u128, write_u128);
+define_nonzero_type!(NonZeroI16, i16, read_i16, write_i16);
+define_nonzero_type!(NonZeroI32, i32, read_i32, write_i32);
+define_nonzero_type!(NonZeroI64, i64, read_i64, write_i64);
+define_nonzero_type!(NonZeroI128, i128, read_i128, write_i128);
+
+/// The decoded integer is zero and therefore cannot be a non-zero integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ZeroError;
+
+impl fmt::Display for ZeroError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the decoded integer is zero and therefore cannot be a non-zero integer")
+    }
+}
+
+impl error::Error for ZeroError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_zero_round_trip() {
+        let n = num::NonZeroU8::new(42).unwrap();
+        let bytes = NonZeroU8::bytes_encode(&n).unwrap();
+        assert_eq!(NonZeroU8::bytes_decode(&bytes).unwrap(), n);
+
+        let n = num::NonZeroI8::new(-42).unwrap();
+        let bytes = NonZeroI8::bytes_encode(&n).unwrap();
+        assert_eq!(NonZeroI8::bytes_decode(&bytes).unwrap(), n);
+
+        let n = num::NonZeroU32::new(0x0102_0304).unwrap();
+        let bytes = NonZeroU32::<BigEndian>::bytes_encode(&n).unwrap();
+        assert_eq!(&bytes[..], [1, 2, 3, 4]);
+        assert_eq!(NonZeroU32::<BigEndian>::bytes_decode(&bytes).unwrap(), n);
+        let bytes = NonZeroU32::<LittleEndian>::bytes_encode(&n).unwrap();
+        assert_eq!(&bytes[..], [4, 3, 2, 1]);
+        assert_eq!(NonZeroU32::<LittleEndian>::bytes_decode(&bytes).unwrap(), n);
+
+        let n = num::NonZeroI128::new(i128::MIN).unwrap();
+        let bytes = NonZeroI128::<BigEndian>::bytes_encode(&n).unwrap();
+        assert_eq!(NonZeroI128::<BigEndian>::bytes_decode(&bytes).unwrap(), n);
+    }
+
+    #[test]
+    fn non_zero_rejects_zero() {
+        let is_zero_error = |e: BoxedError| e.downcast_ref::<ZeroError>().is_some();
+        assert!(NonZeroU8::bytes_decode(&[0]).is_err_and(is_zero_error));
+        assert!(NonZeroI8::bytes_decode(&[0]).is_err_and(is_zero_error));
+        assert!(NonZeroU16::<BigEndian>::bytes_decode(&[0; 2]).is_err_and(is_zero_error));
+        assert!(NonZeroU64::<LittleEndian>::bytes_decode(&[0; 8]).is_err_and(is_zero_error));
+        assert!(NonZeroI32::<BigEndian>::bytes_decode(&[0; 4]).is_err_and(is_zero_error));
+
+        // Too short slices are still reported as I/O errors.
+        assert!(NonZeroU32::<BigEndian>::bytes_decode(&[0; 3]).is_err_and(|e| !is_zero_error(e)));
+    }
+}
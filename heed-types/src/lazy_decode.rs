@@ -1,6 +1,6 @@
 use std::marker;
 
-use heed_traits::BoxedError;
+use heed_traits::{BoxedError, CodecIdentity};
 
 /// Lazily decodes the data bytes.
 ///
@@ -17,6 +17,16 @@ impl<'a, C: 'static> heed_traits::BytesDecode<'a> for LazyDecode<C> {
     }
 }
 
+/// The bytes are those of the wrapped codec, it has its identity.
+impl<C: CodecIdentity> CodecIdentity for LazyDecode<C> {
+    const NAME: &'static str = C::NAME;
+    const VERSION: u32 = C::VERSION;
+
+    fn write_identity(identity: &mut String) {
+        C::write_identity(identity)
+    }
+}
+
 /// Owns bytes that can be decoded on demand.
 #[derive(Copy, Clone)]
 pub struct Lazy<'a, C> {
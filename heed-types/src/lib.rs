@@ -9,12 +9,26 @@
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "roaring")]
+mod bitmap;
 mod bytes;
+mod compressed;
+#[cfg(feature = "decimal")]
+mod decimal;
 mod decode_ignore;
+mod fixed_width;
+#[cfg(feature = "flatbuffers")]
+mod flatbuffer;
 mod integer;
 mod lazy_decode;
+mod net;
+#[cfg(feature = "normalized-str")]
+mod normalized_str;
 mod str;
 mod unit;
+mod unit_enum;
+mod varint;
+mod versioned;
 
 #[cfg(feature = "serde-bincode")]
 mod serde_bincode;
@@ -22,18 +36,45 @@ mod serde_bincode;
 #[cfg(feature = "serde-json")]
 mod serde_json;
 
+#[cfg(feature = "serde-postcard")]
+mod serde_postcard;
+
 #[cfg(feature = "serde-rmp")]
 mod serde_rmp;
 
+#[cfg(feature = "roaring")]
+pub use self::bitmap::Bitmap;
 pub use self::bytes::Bytes;
+#[cfg(feature = "compression-lz4")]
+pub use self::compressed::Lz4;
+#[cfg(feature = "compression-zstd")]
+pub use self::compressed::Zstd;
+pub use self::compressed::{Compressed, Compression, CompressionError, Dictionary, NoDictionary};
+#[cfg(feature = "decimal")]
+pub use self::decimal::{InvalidDecimalError, SortableDecimal};
 pub use self::decode_ignore::DecodeIgnore;
+pub use self::fixed_width::{FixedWidth, InvalidWidthError};
+#[cfg(feature = "flatbuffers")]
+pub use self::flatbuffer::FlatBuffer;
 pub use self::integer::*;
 pub use self::lazy_decode::{Lazy, LazyDecode};
+pub use self::net::{InvalidAddressError, IpAddress, Ipv4Address, Ipv6Address, SocketAddress};
+#[cfg(feature = "normalized-str")]
+pub use self::normalized_str::{
+    CaseFolding, CaseInsensitive, CaseSensitive, Nfc, Nfkc, NormalizationForm, NormalizedStr,
+};
 #[cfg(feature = "serde-bincode")]
 pub use self::serde_bincode::SerdeBincode;
 #[cfg(feature = "serde-json")]
 pub use self::serde_json::SerdeJson;
+#[cfg(feature = "serde-postcard")]
+pub use self::serde_postcard::SerdePostcard;
 #[cfg(feature = "serde-rmp")]
 pub use self::serde_rmp::SerdeRmp;
 pub use self::str::Str;
 pub use self::unit::Unit;
+pub use self::unit_enum::{Discriminant, InvalidDiscriminantError, UnitEnum};
+pub use self::varint::{InvalidVarIntError, VarU64};
+pub use self::versioned::{MissingVersionError, Versioned};
+#[cfg(feature = "derive")]
+pub use heed_derive::Discriminant;
@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::{error, fmt};
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+
+const V4_TAG: u8 = 4;
+const V6_TAG: u8 = 6;
+
+/// Encodable version of [`Ipv4Addr`].
+///
+/// The address is stored as its four big-endian octets, the lexicographic
+/// order of the keys is therefore the numeric order of the addresses and a
+/// CIDR block can be scanned with a range from its first to its last address.
+pub enum Ipv4Address {}
+
+impl BytesEncode<'_> for Ipv4Address {
+    type EItem = Ipv4Addr;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        Ok(Cow::from(item.octets().to_vec()))
+    }
+}
+
+impl BytesDecode<'_> for Ipv4Address {
+    type DItem = Ipv4Addr;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let octets: [u8; 4] = bytes.try_into().map_err(|_| InvalidAddressError)?;
+        Ok(Ipv4Addr::from(octets))
+    }
+}
+
+impl CodecIdentity for Ipv4Address {
+    const NAME: &'static str = "ipv4-address";
+}
+
+/// Encodable version of [`Ipv6Addr`].
+///
+/// The address is stored as its sixteen big-endian octets, the lexicographic
+/// order of the keys is therefore the numeric order of the addresses.
+pub enum Ipv6Address {}
+
+impl BytesEncode<'_> for Ipv6Address {
+    type EItem = Ipv6Addr;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        Ok(Cow::from(item.octets().to_vec()))
+    }
+}
+
+impl BytesDecode<'_> for Ipv6Address {
+    type DItem = Ipv6Addr;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let octets: [u8; 16] = bytes.try_into().map_err(|_| InvalidAddressError)?;
+        Ok(Ipv6Addr::from(octets))
+    }
+}
+
+impl CodecIdentity for Ipv6Address {
+    const NAME: &'static str = "ipv6-address";
+}
+
+/// Encodable version of [`IpAddr`].
+///
+/// The address is prefixed by a tag byte so that all the IPv4 addresses are
+/// sorted before the IPv6 ones, each family being sorted numerically.
+pub enum IpAddress {}
+
+fn encode_ip(ip: &IpAddr, buf: &mut Vec<u8>) {
+    match ip {
+        IpAddr::V4(ip) => {
+            buf.push(V4_TAG);
+            buf.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            buf.push(V6_TAG);
+            buf.extend_from_slice(&ip.octets());
+        }
+    }
+}
+
+/// Decodes a tagged IP address and returns the remaining bytes.
+fn decode_ip(bytes: &[u8]) -> Result<(IpAddr, &[u8]), InvalidAddressError> {
+    match bytes.split_first() {
+        Some((&V4_TAG, rest)) if rest.len() >= 4 => {
+            let (octets, rest) = rest.split_at(4);
+            let octets: [u8; 4] = octets.try_into().unwrap();
+            Ok((IpAddr::V4(Ipv4Addr::from(octets)), rest))
+        }
+        Some((&V6_TAG, rest)) if rest.len() >= 16 => {
+            let (octets, rest) = rest.split_at(16);
+            let octets: [u8; 16] = octets.try_into().unwrap();
+            Ok((IpAddr::V6(Ipv6Addr::from(octets)), rest))
+        }
+        _ => Err(InvalidAddressError),
+    }
+}
+
+impl BytesEncode<'_> for IpAddress {
+    type EItem = IpAddr;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        let mut buf = Vec::with_capacity(1 + 16);
+        encode_ip(item, &mut buf);
+        Ok(Cow::from(buf))
+    }
+}
+
+impl BytesDecode<'_> for IpAddress {
+    type DItem = IpAddr;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        match decode_ip(bytes)? {
+            (ip, []) => Ok(ip),
+            _ => Err(InvalidAddressError.into()),
+        }
+    }
+}
+
+impl CodecIdentity for IpAddress {
+    const NAME: &'static str = "ip-address";
+}
+
+/// Encodable version of [`SocketAddr`].
+///
+/// The socket address is stored as an [`IpAddress`] followed by the big-endian port,
+/// keys are therefore sorted by address then by port. The flow information and
+/// scope identifier of IPv6 socket addresses are stored after the port.
+pub enum SocketAddress {}
+
+impl BytesEncode<'_> for SocketAddress {
+    type EItem = SocketAddr;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        let mut buf = Vec::with_capacity(1 + 16 + 2 + 4 + 4);
+        encode_ip(&item.ip(), &mut buf);
+        buf.extend_from_slice(&item.port().to_be_bytes());
+        if let SocketAddr::V6(addr) = item {
+            buf.extend_from_slice(&addr.flowinfo().to_be_bytes());
+            buf.extend_from_slice(&addr.scope_id().to_be_bytes());
+        }
+        Ok(Cow::from(buf))
+    }
+}
+
+impl BytesDecode<'_> for SocketAddress {
+    type DItem = SocketAddr;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let (ip, rest) = decode_ip(bytes)?;
+        match (ip, rest) {
+            (IpAddr::V4(ip), &[p0, p1]) => {
+                let port = u16::from_be_bytes([p0, p1]);
+                Ok(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+            }
+            (IpAddr::V6(ip), &[p0, p1, f0, f1, f2, f3, s0, s1, s2, s3]) => {
+                let port = u16::from_be_bytes([p0, p1]);
+                let flowinfo = u32::from_be_bytes([f0, f1, f2, f3]);
+                let scope_id = u32::from_be_bytes([s0, s1, s2, s3]);
+                Ok(SocketAddr::V6(SocketAddrV6::new(ip, port, flowinfo, scope_id)))
+            }
+            _ => Err(InvalidAddressError.into()),
+        }
+    }
+}
+
+impl CodecIdentity for SocketAddress {
+    const NAME: &'static str = "socket-address";
+}
+
+/// The slice of bytes does not represent a valid network address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidAddressError;
+
+impl fmt::Display for InvalidAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the slice of bytes does not represent a valid network address")
+    }
+}
+
+impl error::Error for InvalidAddressError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<'a, C: BytesEncode<'a>>(item: &'a C::EItem) -> Vec<u8> {
+        C::bytes_encode(item).unwrap().into_owned()
+    }
+
+    #[test]
+    fn round_trip() {
+        let ipv4 = Ipv4Addr::new(192, 168, 1, 42);
+        assert_eq!(Ipv4Address::bytes_decode(&encode::<Ipv4Address>(&ipv4)).unwrap(), ipv4);
+
+        let ipv6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        assert_eq!(Ipv6Address::bytes_decode(&encode::<Ipv6Address>(&ipv6)).unwrap(), ipv6);
+
+        for ip in [IpAddr::V4(ipv4), IpAddr::V6(ipv6)] {
+            assert_eq!(IpAddress::bytes_decode(&encode::<IpAddress>(&ip)).unwrap(), ip);
+        }
+
+        let addrs = [
+            SocketAddr::V4(SocketAddrV4::new(ipv4, 8080)),
+            SocketAddr::V6(SocketAddrV6::new(ipv6, 443, 7, 3)),
+        ];
+        for addr in addrs {
+            assert_eq!(SocketAddress::bytes_decode(&encode::<SocketAddress>(&addr)).unwrap(), addr);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let ips: [IpAddr; 5] = [
+            Ipv4Addr::new(9, 255, 255, 255).into(),
+            Ipv4Addr::new(10, 0, 0, 1).into(),
+            Ipv4Addr::new(255, 255, 255, 255).into(),
+            Ipv6Addr::UNSPECIFIED.into(),
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into(),
+        ];
+        let encoded: Vec<_> = ips.iter().map(encode::<IpAddress>).collect();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+
+        let addrs: [SocketAddr; 4] = [
+            (Ipv4Addr::new(10, 0, 0, 1), 80).into(),
+            (Ipv4Addr::new(10, 0, 0, 1), 8080).into(),
+            (Ipv4Addr::new(10, 0, 0, 2), 22).into(),
+            (Ipv6Addr::LOCALHOST, 22).into(),
+        ];
+        let encoded: Vec<_> = addrs.iter().map(encode::<SocketAddress>).collect();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn invalid_lengths() {
+        assert!(Ipv4Address::bytes_decode(&[10, 0, 0]).is_err());
+        assert!(Ipv4Address::bytes_decode(&[10, 0, 0, 1, 0]).is_err());
+        assert!(Ipv6Address::bytes_decode(&[0; 15]).is_err());
+        assert!(Ipv6Address::bytes_decode(&[0; 17]).is_err());
+
+        let ip = encode::<IpAddress>(&Ipv4Addr::LOCALHOST.into());
+        assert!(IpAddress::bytes_decode(&[]).is_err());
+        assert!(IpAddress::bytes_decode(&ip[..ip.len() - 1]).is_err());
+        assert!(IpAddress::bytes_decode(&[&ip[..], &[0]].concat()).is_err());
+        assert!(IpAddress::bytes_decode(&[V6_TAG, 127, 0, 0, 1]).is_err());
+        assert!(IpAddress::bytes_decode(&[5, 127, 0, 0, 1]).is_err());
+
+        let addr = encode::<SocketAddress>(&(Ipv6Addr::LOCALHOST, 22).into());
+        assert!(SocketAddress::bytes_decode(&addr[..addr.len() - 1]).is_err());
+        assert!(SocketAddress::bytes_decode(&[&addr[..], &[0]].concat()).is_err());
+        assert!(SocketAddress::bytes_decode(&ip).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::str;
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// A Unicode normalization form applied by the [`NormalizedStr`] codec.
+pub trait NormalizationForm {
+    /// Normalizes the string, borrowing it when it is already normalized.
+    fn normalize(s: &str) -> Cow<'_, str>;
+}
+
+/// The Unicode Normalization Form C (canonical composition).
+pub enum Nfc {}
+
+impl NormalizationForm for Nfc {
+    fn normalize(s: &str) -> Cow<'_, str> {
+        match is_nfc_quick(s.chars()) {
+            IsNormalized::Yes => Cow::Borrowed(s),
+            _ => Cow::Owned(s.nfc().collect()),
+        }
+    }
+}
+
+impl CodecIdentity for Nfc {
+    const NAME: &'static str = "nfc";
+}
+
+/// The Unicode Normalization Form KC (compatibility composition).
+pub enum Nfkc {}
+
+impl NormalizationForm for Nfkc {
+    fn normalize(s: &str) -> Cow<'_, str> {
+        match is_nfkc_quick(s.chars()) {
+            IsNormalized::Yes => Cow::Borrowed(s),
+            _ => Cow::Owned(s.nfkc().collect()),
+        }
+    }
+}
+
+impl CodecIdentity for Nfkc {
+    const NAME: &'static str = "nfkc";
+}
+
+/// Whether the [`NormalizedStr`] codec folds the case of the strings.
+pub trait CaseFolding {
+    /// Folds the case of the string, borrowing it when there is nothing to fold.
+    fn fold(s: Cow<'_, str>) -> Cow<'_, str>;
+}
+
+/// Keep the case of the strings as is.
+pub enum CaseSensitive {}
+
+impl CaseFolding for CaseSensitive {
+    fn fold(s: Cow<'_, str>) -> Cow<'_, str> {
+        s
+    }
+}
+
+impl CodecIdentity for CaseSensitive {
+    const NAME: &'static str = "case-sensitive";
+}
+
+/// Lowercase the strings so that lookups are case-insensitive.
+pub enum CaseInsensitive {}
+
+impl CaseFolding for CaseInsensitive {
+    fn fold(s: Cow<'_, str>) -> Cow<'_, str> {
+        // Not only the uppercase characters are lowercased, the titlecase ones are too.
+        if s.chars().any(|c| c.to_lowercase().ne([c])) {
+            Cow::Owned(s.to_lowercase())
+        } else {
+            s
+        }
+    }
+}
+
+impl CodecIdentity for CaseInsensitive {
+    const NAME: &'static str = "case-insensitive";
+    // The first version didn't fold the titlecase characters.
+    const VERSION: u32 = 2;
+}
+
+/// Describes a [`prim@str`] that is normalized, and optionally case folded, before being stored.
+///
+/// Equivalent strings are stored as the same bytes, lookups and prefix searches
+/// therefore find them regardless of how the caller wrote them. The decoded
+/// string is the normalized one, not the one that was originally encoded.
+///
+/// ```
+/// use heed_traits::{BytesDecode, BytesEncode};
+/// use heed_types::{CaseInsensitive, Nfc, NormalizedStr};
+///
+/// type Key = NormalizedStr<Nfc, CaseInsensitive>;
+///
+/// // "é" written as a single code point and as an "e" followed by a combining accent.
+/// let composed = Key::bytes_encode("Caf\u{e9}").unwrap();
+/// let decomposed = Key::bytes_encode("CAFE\u{301}").unwrap();
+/// assert_eq!(composed, decomposed);
+/// assert_eq!(Key::bytes_decode(&composed).unwrap(), "caf\u{e9}");
+/// ```
+pub struct NormalizedStr<N = Nfc, C = CaseSensitive>(PhantomData<(N, C)>);
+
+impl<'a, N: NormalizationForm, C: CaseFolding> BytesEncode<'a> for NormalizedStr<N, C> {
+    type EItem = str;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        match C::fold(N::normalize(item)) {
+            Cow::Borrowed(s) => Ok(Cow::Borrowed(s.as_bytes())),
+            Cow::Owned(s) => Ok(Cow::Owned(s.into_bytes())),
+        }
+    }
+}
+
+impl<'a, N, C> BytesDecode<'a> for NormalizedStr<N, C> {
+    type DItem = &'a str;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        str::from_utf8(bytes).map_err(Into::into)
+    }
+}
+
+impl<N: CodecIdentity, C: CodecIdentity> CodecIdentity for NormalizedStr<N, C> {
+    const NAME: &'static str = "normalized-str";
+
+    fn write_parameters(identity: &mut String) {
+        N::write_identity(identity);
+        identity.push(',');
+        C::write_identity(identity);
+    }
+}
+
+unsafe impl<N, C> Send for NormalizedStr<N, C> {}
+
+unsafe impl<N, C> Sync for NormalizedStr<N, C> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_insensitive_folds_titlecase() {
+        type Key = NormalizedStr<Nfc, CaseInsensitive>;
+
+        // U+01C5 is the titlecase "Dž", it is neither uppercase nor lowercase.
+        let titlecase = Key::bytes_encode("\u{1c5}ungla").unwrap();
+        let uppercase = Key::bytes_encode("\u{1c4}UNGLA").unwrap();
+        let lowercase = Key::bytes_encode("\u{1c6}ungla").unwrap();
+        assert_eq!(titlecase, lowercase);
+        assert_eq!(uppercase, lowercase);
+        assert!(matches!(lowercase, Cow::Borrowed(_)));
+
+        let mut identity = String::new();
+        Key::write_identity(&mut identity);
+        assert_eq!(identity, "normalized-str@1[nfc@1,case-insensitive@2]");
+    }
+}
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
+use std::io;
 
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
 use serde::{Deserialize, Serialize};
 
 /// Describes a type that is [`Serialize`]/[`Deserialize`] and uses `bincode` to do so.
@@ -17,6 +18,13 @@ where
     fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
         bincode::serialize(item).map(Cow::Owned).map_err(Into::into)
     }
+
+    fn bytes_encode_into<W: io::Write>(
+        item: &'a Self::EItem,
+        writer: &mut W,
+    ) -> Result<(), BoxedError> {
+        bincode::serialize_into(writer, item).map_err(Into::into)
+    }
 }
 
 impl<'a, T: 'a> BytesDecode<'a> for SerdeBincode<T>
@@ -30,6 +38,10 @@ where
     }
 }
 
+impl<T> CodecIdentity for SerdeBincode<T> {
+    const NAME: &'static str = "serde-bincode";
+}
+
 unsafe impl<T> Send for SerdeBincode<T> {}
 
 unsafe impl<T> Sync for SerdeBincode<T> {}
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
+use std::io;
 
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
 use serde::{Deserialize, Serialize};
 
 /// Describes a type that is [`Serialize`]/[`Deserialize`] and uses `serde_json` to do so.
@@ -17,6 +18,13 @@ where
     fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
         serde_json::to_vec(item).map(Cow::Owned).map_err(Into::into)
     }
+
+    fn bytes_encode_into<W: io::Write>(
+        item: &Self::EItem,
+        writer: &mut W,
+    ) -> Result<(), BoxedError> {
+        serde_json::to_writer(writer, item).map_err(Into::into)
+    }
 }
 
 impl<'a, T: 'a> BytesDecode<'a> for SerdeJson<T>
@@ -30,6 +38,10 @@ where
     }
 }
 
+impl<T> CodecIdentity for SerdeJson<T> {
+    const NAME: &'static str = "serde-json";
+}
+
 unsafe impl<T> Send for SerdeJson<T> {}
 
 unsafe impl<T> Sync for SerdeJson<T> {}
@@ -0,0 +1,42 @@
+use std::borrow::Cow;
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+use serde::{Deserialize, Serialize};
+
+/// Describes a type that is [`Serialize`]/[`Deserialize`] and uses `postcard` to do so.
+///
+/// Postcard is a compact, non self-describing format: values must be decoded
+/// with the exact same type they were encoded with.
+///
+/// It can borrow bytes from the original slice.
+pub struct SerdePostcard<T>(std::marker::PhantomData<T>);
+
+impl<'a, T: 'a> BytesEncode<'a> for SerdePostcard<T>
+where
+    T: Serialize,
+{
+    type EItem = T;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        postcard::to_stdvec(item).map(Cow::Owned).map_err(Into::into)
+    }
+}
+
+impl<'a, T: 'a> BytesDecode<'a> for SerdePostcard<T>
+where
+    T: Deserialize<'a>,
+{
+    type DItem = T;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        postcard::from_bytes(bytes).map_err(Into::into)
+    }
+}
+
+impl<T> CodecIdentity for SerdePostcard<T> {
+    const NAME: &'static str = "serde-postcard";
+}
+
+unsafe impl<T> Send for SerdePostcard<T> {}
+
+unsafe impl<T> Sync for SerdePostcard<T> {}
@@ -1,10 +1,13 @@
 use std::borrow::Cow;
 
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
 use serde::{Deserialize, Serialize};
 
 /// Describes a type that is [`Serialize`]/[`Deserialize`] and uses `rmp_serde` to do so.
 ///
+/// Values are written in the compact MessagePack representation (structs as arrays),
+/// matching what `rmp_serde::to_vec` produces on the wire.
+///
 /// It can borrow bytes from the original slice.
 pub struct SerdeRmp<T>(std::marker::PhantomData<T>);
 
@@ -14,7 +17,7 @@ where
 {
     type EItem = T;
 
-    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
         rmp_serde::to_vec(item).map(Cow::Owned).map_err(Into::into)
     }
 }
@@ -30,6 +33,10 @@ where
     }
 }
 
+impl<T> CodecIdentity for SerdeRmp<T> {
+    const NAME: &'static str = "serde-rmp";
+}
+
 unsafe impl<T> Send for SerdeRmp<T> {}
 
 unsafe impl<T> Sync for SerdeRmp<T> {}
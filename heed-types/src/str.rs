@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::str;
 
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
 
 /// Describes a [`prim@str`].
 pub enum Str {}
@@ -21,3 +21,7 @@ impl<'a> BytesDecode<'a> for Str {
         str::from_utf8(bytes).map_err(Into::into)
     }
 }
+
+impl CodecIdentity for Str {
+    const NAME: &'static str = "str";
+}
@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::{error, fmt};
 
-use heed_traits::{BoxedError, BytesDecode, BytesEncode};
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
 
 /// Describes the unit `()` type.
 pub enum Unit {}
@@ -26,6 +26,10 @@ impl BytesDecode<'_> for Unit {
     }
 }
 
+impl CodecIdentity for Unit {
+    const NAME: &'static str = "unit";
+}
+
 /// The slice of bytes is non-empty and therefore is not a unit `()` type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NonEmptyError;
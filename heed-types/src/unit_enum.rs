@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::{error, fmt};
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+
+/// The largest value stored directly in the first byte of a discriminant.
+const MAX_INLINE: u8 = 247;
+
+/// A fieldless enum with a stable mapping between its variants and integers.
+///
+/// It can be implemented with `#[derive(Discriminant)]` when the `derive` feature is enabled.
+pub trait Discriminant: Sized {
+    /// Returns the discriminant of this variant.
+    fn discriminant(&self) -> u64;
+
+    /// Returns the variant associated with this discriminant, if any.
+    fn from_discriminant(discriminant: u64) -> Option<Self>;
+}
+
+/// Describes a fieldless enum stored as its [`Discriminant`].
+///
+/// Discriminants up to 247 are stored in a single byte, larger ones are prefixed
+/// by a byte giving their length. The encoding preserves the order of the
+/// discriminants, the enum can therefore be used as a key in range scans.
+pub struct UnitEnum<T>(PhantomData<T>);
+
+impl<'a, T: Discriminant + 'a> BytesEncode<'a> for UnitEnum<T> {
+    type EItem = T;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        let discriminant = item.discriminant();
+        if discriminant <= MAX_INLINE as u64 {
+            Ok(Cow::Owned(vec![discriminant as u8]))
+        } else {
+            let bytes = discriminant.to_be_bytes();
+            let skip = discriminant.leading_zeros() as usize / 8;
+            let mut buf = Vec::with_capacity(1 + bytes.len() - skip);
+            buf.push(MAX_INLINE + (bytes.len() - skip) as u8);
+            buf.extend_from_slice(&bytes[skip..]);
+            Ok(Cow::Owned(buf))
+        }
+    }
+}
+
+impl<'a, T: Discriminant + 'a> BytesDecode<'a> for UnitEnum<T> {
+    type DItem = T;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        let discriminant = match bytes.split_first() {
+            Some((&first, [])) if first <= MAX_INLINE => first as u64,
+            Some((&first, rest))
+                if first > MAX_INLINE && rest.len() == (first - MAX_INLINE) as usize =>
+            {
+                let mut buf = [0; 8];
+                buf[8 - rest.len()..].copy_from_slice(rest);
+                let discriminant = u64::from_be_bytes(buf);
+                // Every discriminant has a single encoding, the shortest one.
+                if rest[0] == 0 || discriminant <= MAX_INLINE as u64 {
+                    return Err(InvalidDiscriminantError.into());
+                }
+                discriminant
+            }
+            _ => return Err(InvalidDiscriminantError.into()),
+        };
+
+        T::from_discriminant(discriminant).ok_or_else(|| InvalidDiscriminantError.into())
+    }
+}
+
+impl<T> CodecIdentity for UnitEnum<T> {
+    const NAME: &'static str = "unit-enum";
+}
+
+unsafe impl<T> Send for UnitEnum<T> {}
+
+unsafe impl<T> Sync for UnitEnum<T> {}
+
+/// The slice of bytes is not the discriminant of any variant of the enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidDiscriminantError;
+
+impl fmt::Display for InvalidDiscriminantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the slice of bytes is not the discriminant of any variant of the enum")
+    }
+}
+
+impl error::Error for InvalidDiscriminantError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Raw(u64);
+
+    impl Discriminant for Raw {
+        fn discriminant(&self) -> u64 {
+            self.0
+        }
+
+        fn from_discriminant(discriminant: u64) -> Option<Self> {
+            (discriminant != 42).then_some(Raw(discriminant))
+        }
+    }
+
+    #[test]
+    fn round_trip_and_order() {
+        let discriminants = [0, 1, 247, 248, 255, 256, 65_535, 65_536, u64::MAX];
+        let encoded: Vec<_> = discriminants
+            .iter()
+            .map(|&d| UnitEnum::<Raw>::bytes_encode(&Raw(d)).unwrap().into_owned())
+            .collect();
+        for (bytes, &discriminant) in encoded.iter().zip(&discriminants) {
+            assert_eq!(UnitEnum::<Raw>::bytes_decode(bytes).unwrap(), Raw(discriminant));
+        }
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(encoded[3], [248, 248]);
+        assert_eq!(encoded[5], [249, 1, 0]);
+    }
+
+    #[test]
+    fn non_canonical_encodings() {
+        let invalid: &[&[u8]] = &[
+            &[],
+            // The long form of discriminants stored inline.
+            &[248, 5],
+            &[248, 247],
+            &[249, 0, 5],
+            // Leading zero bytes.
+            &[249, 0, 248],
+            &[255, 0, 0, 0, 0, 0, 0, 1, 0],
+            // Lengths that don't match the prefix.
+            &[248],
+            &[249, 1],
+            &[248, 248, 0],
+            &[5, 0],
+        ];
+        for bytes in invalid {
+            assert!(UnitEnum::<Raw>::bytes_decode(bytes).is_err(), "{bytes:?} was decoded");
+        }
+
+        // A discriminant without variant.
+        assert!(UnitEnum::<Raw>::bytes_decode(&[42]).is_err());
+    }
+}
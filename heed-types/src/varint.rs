@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+use std::{error, fmt, io};
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+
+/// The largest value stored on a single byte.
+const ONE_BYTE_MAX: u64 = 240;
+/// The largest value stored on two bytes.
+const TWO_BYTES_MAX: u64 = 2287;
+/// The largest value stored on three bytes.
+const THREE_BYTES_MAX: u64 = 67823;
+
+/// Encodable version of [`u64`] stored on a variable number of bytes.
+///
+/// The values up to 240 are stored on a single byte, up to 2287 on two bytes and up to 67823
+/// on three bytes, the larger values are stored big-endian after a byte telling their length.
+/// The first byte grows with the length of the value, the lexicographic order of the keys is
+/// therefore the numeric order of the values: the ranges of values are correct with the default
+/// comparator of the database. It halves the size of the small integers compared to a
+/// [`U64`](crate::U64) key.
+///
+/// ```
+/// use heed_traits::{BytesDecode, BytesEncode};
+/// use heed_types::VarU64;
+///
+/// # fn main() -> Result<(), heed_traits::BoxedError> {
+/// let values = [0, 240, 241, 2287, 2288, 67823, 67824, u64::MAX];
+/// let keys: Vec<_> = values.iter().map(VarU64::bytes_encode).collect::<Result<_, _>>()?;
+/// assert_eq!(keys.iter().map(|key| key.len()).collect::<Vec<_>>(), [1, 1, 2, 2, 3, 3, 4, 9]);
+/// assert!(keys.windows(2).all(|keys| keys[0] < keys[1]));
+/// assert_eq!(VarU64::bytes_decode(&keys[4])?, 2288);
+/// # Ok(()) }
+/// ```
+pub enum VarU64 {}
+
+/// Encodes the value in the buffer and returns the number of bytes used.
+fn encode(value: u64, buf: &mut [u8; 9]) -> usize {
+    match value {
+        0..=ONE_BYTE_MAX => {
+            buf[0] = value as u8;
+            1
+        }
+        241..=TWO_BYTES_MAX => {
+            let value = value - (ONE_BYTE_MAX + 1);
+            buf[0] = 241 + (value / 256) as u8;
+            buf[1] = value as u8;
+            2
+        }
+        2288..=THREE_BYTES_MAX => {
+            let value = value - (TWO_BYTES_MAX + 1);
+            buf[0] = 249;
+            buf[1..3].copy_from_slice(&(value as u16).to_be_bytes());
+            3
+        }
+        _ => {
+            let len = 8 - value.leading_zeros() as usize / 8;
+            buf[0] = 247 + len as u8;
+            buf[1..=len].copy_from_slice(&value.to_be_bytes()[8 - len..]);
+            1 + len
+        }
+    }
+}
+
+impl BytesEncode<'_> for VarU64 {
+    type EItem = u64;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<'_, [u8]>, BoxedError> {
+        let mut buf = [0; 9];
+        let len = encode(*item, &mut buf);
+        Ok(Cow::from(buf[..len].to_vec()))
+    }
+
+    fn bytes_encode_into<W: io::Write>(
+        item: &Self::EItem,
+        writer: &mut W,
+    ) -> Result<(), BoxedError> {
+        let mut buf = [0; 9];
+        let len = encode(*item, &mut buf);
+        writer.write_all(&buf[..len]).map_err(Into::into)
+    }
+}
+
+impl BytesDecode<'_> for VarU64 {
+    type DItem = u64;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, BoxedError> {
+        let value = match *bytes {
+            [first @ 0..=240] => u64::from(first),
+            [first @ 241..=248, second] => {
+                ONE_BYTE_MAX + 1 + u64::from(first - 241) * 256 + u64::from(second)
+            }
+            [249, high, low] => TWO_BYTES_MAX + 1 + u64::from(u16::from_be_bytes([high, low])),
+            [first @ 250..=255, ref rest @ ..] if rest.len() == usize::from(first - 247) => {
+                let mut buf = [0; 8];
+                buf[8 - rest.len()..].copy_from_slice(rest);
+                let value = u64::from_be_bytes(buf);
+                // Every value has a single encoding, the shortest one.
+                let shortest = rest.len() == 3 || value >> ((rest.len() - 1) * 8) != 0;
+                if value <= THREE_BYTES_MAX || !shortest {
+                    return Err(InvalidVarIntError.into());
+                }
+                value
+            }
+            _ => return Err(InvalidVarIntError.into()),
+        };
+        Ok(value)
+    }
+}
+
+impl CodecIdentity for VarU64 {
+    const NAME: &'static str = "var-u64";
+}
+
+/// The slice of bytes does not represent a valid variable-length integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidVarIntError;
+
+impl fmt::Display for InvalidVarIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the slice of bytes does not represent a valid variable-length integer")
+    }
+}
+
+impl error::Error for InvalidVarIntError {}
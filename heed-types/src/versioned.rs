@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::{error, fmt};
+
+use heed_traits::{BoxedError, BytesDecode, BytesEncode, CodecIdentity};
+
+/// The size of the big-endian version prepended to every value.
+const VERSION_LEN: usize = 8;
+
+/// Wraps another codec and prefixes its bytes with a version, for optimistic concurrency.
+///
+/// The items are a version and a value of the inner codec. The version is stored in big-endian
+/// before the bytes of the value, which are decoded without copying them. Once set as the value
+/// codec of a database, `Database::put_versioned` writes a value only if the stored version is
+/// the expected one and increments it.
+///
+/// ```
+/// use heed_traits::{BytesDecode, BytesEncode};
+/// use heed_types::{Str, Versioned};
+///
+/// type VersionedStr = Versioned<Str>;
+///
+/// let bytes = VersionedStr::bytes_encode(&(3, "hello")).unwrap();
+/// assert_eq!(&bytes[..8], 3u64.to_be_bytes());
+/// assert_eq!(VersionedStr::bytes_decode(&bytes).unwrap(), (3, "hello"));
+/// assert!(VersionedStr::bytes_decode(&[0, 1, 2]).is_err());
+/// ```
+pub struct Versioned<C>(PhantomData<C>);
+
+impl<'a, C> BytesEncode<'a> for Versioned<C>
+where
+    C: BytesEncode<'a>,
+    C::EItem: 'a,
+{
+    type EItem = (u64, &'a C::EItem);
+
+    fn bytes_encode((version, item): &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        let bytes = C::bytes_encode(item)?;
+        let mut output = Vec::with_capacity(VERSION_LEN + bytes.len());
+        output.extend_from_slice(&version.to_be_bytes());
+        output.extend_from_slice(&bytes);
+        Ok(Cow::Owned(output))
+    }
+}
+
+impl<'a, C> BytesDecode<'a> for Versioned<C>
+where
+    C: BytesDecode<'a>,
+{
+    type DItem = (u64, C::DItem);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        let (version, value) = split_version(bytes)?;
+        Ok((version, C::bytes_decode(value)?))
+    }
+}
+
+impl<C: CodecIdentity> CodecIdentity for Versioned<C> {
+    const NAME: &'static str = "versioned";
+
+    fn write_parameters(identity: &mut String) {
+        C::write_identity(identity)
+    }
+}
+
+unsafe impl<C> Send for Versioned<C> {}
+
+unsafe impl<C> Sync for Versioned<C> {}
+
+/// Splits the bytes written by the [`Versioned`] codec into the version and the value bytes.
+fn split_version(bytes: &[u8]) -> Result<(u64, &[u8]), MissingVersionError> {
+    match bytes.split_first_chunk::<VERSION_LEN>() {
+        Some((version, value)) => Ok((u64::from_be_bytes(*version), value)),
+        None => Err(MissingVersionError { len: bytes.len() }),
+    }
+}
+
+/// The bytes are too short to start with the version of the [`Versioned`] codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MissingVersionError {
+    /// The length of the bytes.
+    pub len: usize,
+}
+
+impl fmt::Display for MissingVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a version of {VERSION_LEN} bytes but found {} bytes", self.len)
+    }
+}
+
+impl error::Error for MissingVersionError {}
@@ -0,0 +1,46 @@
+use std::error::Error;
+
+use heed::byteorder::BigEndian;
+use heed::types::*;
+use heed::{Database, DatabaseSchema, EnvOpenOptions};
+
+// Every database is named after its field unless a name is given,
+// codecs and comparators are the ones of the field type.
+#[derive(DatabaseSchema)]
+struct Schema {
+    users: Database<U32<BigEndian>, Str>,
+    #[heed(name = "users-by-age", flags = "DUP_SORT")]
+    by_age: Database<U8, U32<BigEndian>>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let env_path = tempfile::tempdir()?;
+
+    let env = unsafe {
+        EnvOpenOptions::new()
+            .map_size(10 * 1024 * 1024) // 10MB
+            .max_dbs(Schema::DATABASE_NAMES.len() as u32)
+            .open(env_path)?
+    };
+
+    let rtxn = env.read_txn()?;
+    assert!(Schema::open_existing(&env, &rtxn)?.is_none());
+    drop(rtxn);
+
+    let mut wtxn = env.write_txn()?;
+    let schema = Schema::open(&env, &mut wtxn)?;
+
+    schema.users.put(&mut wtxn, &1, "kero")?;
+    schema.users.put(&mut wtxn, &2, "tamo")?;
+    schema.by_age.put(&mut wtxn, &28, &1)?;
+    schema.by_age.put(&mut wtxn, &28, &2)?;
+    wtxn.commit()?;
+
+    let rtxn = env.read_txn()?;
+    let schema = Schema::open_existing(&env, &rtxn)?.expect("all the databases exist");
+    let same_age: Vec<_> =
+        schema.by_age.get_duplicates(&rtxn, &28)?.unwrap().collect::<Result<_, _>>()?;
+    assert_eq!(same_age, vec![(28, 1), (28, 2)]);
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+
+use heed::types::*;
+use heed::{BytesDecode, BytesEncode, Database, EnvOpenOptions};
+
+// Fields are encoded in declaration order, integers in big-endian with the
+// sign bit flipped, the keys are therefore sorted by user then by timestamp.
+#[derive(Debug, PartialEq, Eq, BytesEncode, BytesDecode)]
+struct UserEvent {
+    user_id: u32,
+    timestamp: i64,
+    #[heed(codec = "Str")]
+    kind: String,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let env_path = tempfile::tempdir()?;
+
+    let env = unsafe {
+        EnvOpenOptions::new()
+            .map_size(10 * 1024 * 1024) // 10MB
+            .max_dbs(3)
+            .open(env_path)?
+    };
+
+    let mut wtxn = env.write_txn()?;
+    let db: Database<UserEvent, Unit> = env.create_database(&mut wtxn, Some("events"))?;
+
+    let event =
+        |user_id, timestamp, kind: &str| UserEvent { user_id, timestamp, kind: kind.into() };
+    db.put(&mut wtxn, &event(2, -10, "login"), &())?;
+    db.put(&mut wtxn, &event(1, 42, "logout"), &())?;
+    db.put(&mut wtxn, &event(1, -5, "login"), &())?;
+
+    let mut iter = db.iter(&wtxn)?;
+    assert_eq!(iter.next().transpose()?, Some((event(1, -5, "login"), ())));
+    assert_eq!(iter.next().transpose()?, Some((event(1, 42, "logout"), ())));
+    assert_eq!(iter.next().transpose()?, Some((event(2, -10, "login"), ())));
+    assert_eq!(iter.next().transpose()?, None);
+
+    drop(iter);
+    wtxn.commit()?;
+
+    Ok(())
+}
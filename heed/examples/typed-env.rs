@@ -0,0 +1,40 @@
+use std::error::Error;
+
+use heed::byteorder::BigEndian;
+use heed::types::*;
+use heed::{typed_env, Database, EnvOpenOptions};
+
+typed_env! {
+    /// The only way to reach the databases is through the typed accessors.
+    struct AppEnv {
+        users: Database<U32<BigEndian>, Str>,
+        by_age: Database<U8, U32<BigEndian>> = "users-by-age" where DUP_SORT,
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let env_path = tempfile::tempdir()?;
+
+    let env = unsafe {
+        EnvOpenOptions::new()
+            .map_size(10 * 1024 * 1024) // 10MB
+            .max_dbs(AppEnv::DATABASE_NAMES.len() as u32)
+            .open(env_path)?
+    };
+    let env = AppEnv::new(env)?;
+
+    let mut wtxn = env.write_txn()?;
+    env.users().put(&mut wtxn, &1, "kero")?;
+    env.users().put(&mut wtxn, &2, "tamo")?;
+    env.by_age().put(&mut wtxn, &28, &1)?;
+    env.by_age().put(&mut wtxn, &28, &2)?;
+    wtxn.commit()?;
+
+    let rtxn = env.read_txn()?;
+    assert_eq!(env.users().get(&rtxn, &2)?, Some("tamo"));
+    let same_age: Vec<_> =
+        env.by_age().get_duplicates(&rtxn, &28)?.unwrap().collect::<Result<_, _>>()?;
+    assert_eq!(same_age, vec![(28, 1), (28, 2)]);
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::error::Error;
+
+use heed::types::*;
+use heed::{Database, EnvOpenOptions};
+
+// The explicit discriminants are what is stored in the database,
+// variants can be reordered or added without breaking existing entries.
+#[derive(Debug, PartialEq, Eq, Discriminant)]
+enum Status {
+    Pending = 1,
+    Active = 2,
+    Archived = 300,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let env_path = tempfile::tempdir()?;
+
+    let env = unsafe {
+        EnvOpenOptions::new()
+            .map_size(10 * 1024 * 1024) // 10MB
+            .max_dbs(3)
+            .open(env_path)?
+    };
+
+    let mut wtxn = env.write_txn()?;
+    let db: Database<UnitEnum<Status>, Str> = env.create_database(&mut wtxn, Some("status"))?;
+
+    db.put(&mut wtxn, &Status::Archived, "archived")?;
+    db.put(&mut wtxn, &Status::Pending, "pending")?;
+    db.put(&mut wtxn, &Status::Active, "active")?;
+
+    // Entries are sorted by discriminant.
+    let mut iter = db.iter(&wtxn)?;
+    assert_eq!(iter.next().transpose()?, Some((Status::Pending, "pending")));
+    assert_eq!(iter.next().transpose()?, Some((Status::Active, "active")));
+    assert_eq!(iter.next().transpose()?, Some((Status::Archived, "archived")));
+    assert_eq!(iter.next().transpose()?, None);
+
+    drop(iter);
+    wtxn.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,438 @@
+//! Analysis of the space used by the databases of an environment.
+//!
+//! LMDB never shrinks its file, the pages freed by the write transactions are kept in a
+//! freelist and reused by the next ones. A [`SpaceReport`] compares the bytes of the entries
+//! to the pages allocated to hold them, to decide when it is worth compacting the environment
+//! with [`Env::copy_to_path`](crate::Env::copy_to_path).
+//!
+//! The freed pages can only be reused once no read transaction can see them anymore,
+//! a [`FreelistReport`] tells the pages pinned by the old readers from the free pages
+//! scattered across the file.
+
+use std::ffi::CString;
+use std::mem;
+use std::ops::Range;
+
+use crate::envs::reader_txn_ids;
+use crate::mdb::error::mdb_result;
+use crate::mdb::ffi::{self, FREE_DBI, MAIN_DBI};
+use crate::types::Bytes;
+use crate::*;
+
+/// The space used by the databases of an environment, returned by [`space_report`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SpaceReport {
+    /// The size of a page.
+    pub page_size: u32,
+    /// The size of the memory map, the largest the environment can grow.
+    pub map_size: usize,
+    /// The number of pages of the file used by the last committed transaction.
+    pub used_pages: usize,
+    /// The number of pages in the freelist, waiting to be reused.
+    pub free_pages: usize,
+    /// The number of pages storing the freelist itself.
+    pub freelist_pages: usize,
+    /// The unnamed database followed by the named databases, in the order of their names.
+    pub databases: Vec<DatabaseSpace>,
+}
+
+/// The space used by a database, see [`SpaceReport::databases`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DatabaseSpace {
+    /// The name of the database, `None` for the unnamed one.
+    pub name: Option<String>,
+    /// The statistics of the pages of the database.
+    pub stat: DatabaseStat,
+    /// The number of bytes of the keys and values of the entries.
+    pub live_bytes: u64,
+}
+
+impl SpaceReport {
+    /// The number of bytes of the keys and values of all the databases.
+    pub fn live_bytes(&self) -> u64 {
+        self.databases.iter().map(|db| db.live_bytes).sum()
+    }
+
+    /// The number of bytes of the file used by the last committed transaction.
+    pub fn used_bytes(&self) -> u64 {
+        self.used_pages as u64 * self.page_size as u64
+    }
+
+    /// The number of bytes of the pages in the freelist.
+    pub fn free_bytes(&self) -> u64 {
+        self.free_pages as u64 * self.page_size as u64
+    }
+
+    /// The ratio of the used bytes of the file to the bytes of the entries,
+    /// a compacted environment with well filled pages is close to one.
+    pub fn space_amplification(&self) -> f64 {
+        match self.live_bytes() {
+            0 => 0.0,
+            live => self.used_bytes() as f64 / live as f64,
+        }
+    }
+}
+
+impl DatabaseSpace {
+    /// The number of branch, leaf and overflow pages of the database.
+    pub fn allocated_pages(&self) -> usize {
+        self.stat.branch_pages + self.stat.leaf_pages + self.stat.overflow_pages
+    }
+
+    /// The number of bytes of the pages of the database.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocated_pages() as u64 * self.stat.page_size as u64
+    }
+
+    /// The number of bytes of the overflow pages, which store the values too large
+    /// to fit in a leaf page.
+    pub fn overflow_bytes(&self) -> u64 {
+        self.stat.overflow_pages as u64 * self.stat.page_size as u64
+    }
+
+    /// The part of the pages of the database used by the keys and values, between zero and one
+    /// if the page headers are ignored.
+    pub fn fill_factor(&self) -> f64 {
+        match self.allocated_bytes() {
+            0 => 0.0,
+            allocated => self.live_bytes as f64 / allocated as f64,
+        }
+    }
+}
+
+/// Walks the pages of every database and of the freelist of the environment of the transaction.
+///
+/// Every entry is read, it can take a while on a large environment.
+/// The named databases can be opened or not by the environment.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::analyze::space_report;
+/// use heed::types::*;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+/// db.put(&mut wtxn, "kero", "admin")?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// let report = space_report(&rtxn)?;
+/// let users = report.databases.iter().find(|db| db.name.as_deref() == Some("users")).unwrap();
+/// assert_eq!(users.live_bytes, 9);
+/// assert_eq!(users.allocated_pages(), 1);
+/// # Ok(()) }
+/// ```
+pub fn space_report(txn: &impl ReadTxn) -> Result<SpaceReport> {
+    let mut info = mem::MaybeUninit::uninit();
+    let info = unsafe {
+        ffi::mdb_env_info(txn.env_mut_ptr().as_ptr(), info.as_mut_ptr());
+        info.assume_init()
+    };
+
+    let main = database(txn, MAIN_DBI);
+    let mut databases = vec![database_space(txn, None, main)?];
+    for result in main.iter(txn)? {
+        let (key, _) = result?;
+        let name = match std::str::from_utf8(key) {
+            Ok(name) if !name.contains('\0') => name,
+            _ => continue,
+        };
+        // The keys that are not database names can't be opened as databases.
+        match open_dbi(txn, name) {
+            Ok(dbi) => databases.push(database_space(txn, Some(name), database(txn, dbi))?),
+            Err(Error::Mdb(MdbError::Incompatible)) => (),
+            Err(e) => return Err(e),
+        }
+    }
+
+    let free_pages = freelist_entries(txn)?.iter().map(FreelistEntry::page_count).sum();
+    let stat = database(txn, FREE_DBI).stat(txn)?;
+
+    Ok(SpaceReport {
+        page_size: stat.page_size,
+        map_size: info.me_mapsize,
+        used_pages: info.me_last_pgno + 1,
+        free_pages,
+        freelist_pages: stat.branch_pages + stat.leaf_pages + stat.overflow_pages,
+        databases,
+    })
+}
+
+/// The pages freed by the write transactions, returned by [`freelist_report`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FreelistReport {
+    /// The size of a page.
+    pub page_size: u32,
+    /// The id of the oldest snapshot still read, including by the transaction
+    /// given to [`freelist_report`]. `None` if there is no reader.
+    pub oldest_reader: Option<u64>,
+    /// The pages freed by each write transaction, in the order of the transaction ids.
+    pub entries: Vec<FreelistEntry>,
+}
+
+/// The pages freed by a write transaction, see [`FreelistReport::entries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FreelistEntry {
+    /// The id of the transaction that freed the pages.
+    pub txn_id: u64,
+    /// The ranges of contiguous page numbers, in ascending order.
+    pub pages: Vec<Range<u64>>,
+}
+
+impl FreelistEntry {
+    /// The number of pages freed by the transaction.
+    pub fn page_count(&self) -> usize {
+        self.pages.iter().map(|range| (range.end - range.start) as usize).sum()
+    }
+
+    /// Whether the pages can't be reused yet because a reader can still see them,
+    /// LMDB only reuses the pages freed before the oldest snapshot still read.
+    pub fn is_pinned(&self, oldest_reader: Option<u64>) -> bool {
+        oldest_reader.is_some_and(|oldest| self.txn_id >= oldest)
+    }
+}
+
+impl FreelistReport {
+    /// The number of pages in the freelist.
+    pub fn free_pages(&self) -> usize {
+        self.entries.iter().map(FreelistEntry::page_count).sum()
+    }
+
+    /// The number of free pages that can't be reused until the old readers end.
+    pub fn pinned_pages(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_pinned(self.oldest_reader))
+            .map(FreelistEntry::page_count)
+            .sum()
+    }
+
+    /// The number of free pages the next write transactions can reuse.
+    pub fn reusable_pages(&self) -> usize {
+        self.free_pages() - self.pinned_pages()
+    }
+
+    /// The ranges of contiguous reusable pages, in ascending order.
+    ///
+    /// LMDB merges the reusable entries before allocating, a range can span several entries.
+    pub fn reusable_runs(&self) -> Vec<Range<u64>> {
+        let mut pages: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.is_pinned(self.oldest_reader))
+            .flat_map(|entry| entry.pages.iter().cloned().flatten())
+            .collect();
+        pages.sort_unstable();
+        page_ranges(pages)
+    }
+
+    /// The number of pages of the largest range of contiguous reusable pages,
+    /// the largest value that can be stored without growing the file.
+    pub fn largest_reusable_run(&self) -> usize {
+        let runs = self.reusable_runs();
+        runs.iter().map(|run| (run.end - run.start) as usize).max().unwrap_or(0)
+    }
+
+    /// The part of the reusable pages outside of the largest contiguous range,
+    /// zero when they are all contiguous and close to one when they are all scattered.
+    pub fn fragmentation(&self) -> f64 {
+        match self.reusable_pages() {
+            0 => 0.0,
+            reusable => 1.0 - self.largest_reusable_run() as f64 / reusable as f64,
+        }
+    }
+}
+
+/// Decodes the freelist of the environment of the transaction.
+///
+/// A freelist mostly made of pinned pages means that long read transactions prevent the reuse
+/// of the space, ending them is enough. A fragmented freelist can only be fixed by compacting
+/// the environment with [`Env::copy_to_path`](crate::Env::copy_to_path).
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::analyze::freelist_report;
+/// use heed::types::*;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+/// db.put(&mut wtxn, "kero", "admin")?;
+/// wtxn.commit()?;
+///
+/// let mut wtxn = env.write_txn()?;
+/// db.put(&mut wtxn, "kero", "guest")?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// let report = freelist_report(&rtxn)?;
+/// assert!(report.free_pages() > 0);
+/// println!("{:.0}% fragmented", report.fragmentation() * 100.0);
+/// # Ok(()) }
+/// ```
+pub fn freelist_report(txn: &impl ReadTxn) -> Result<FreelistReport> {
+    Ok(FreelistReport {
+        page_size: database(txn, FREE_DBI).stat(txn)?.page_size,
+        oldest_reader: reader_txn_ids(txn.env_mut_ptr())?.into_iter().min(),
+        entries: freelist_entries(txn)?,
+    })
+}
+
+pub(crate) fn freelist_entries(txn: &impl ReadTxn) -> Result<Vec<FreelistEntry>> {
+    let mut entries = Vec::new();
+    for result in database(txn, FREE_DBI).iter(txn)? {
+        // A freelist entry is keyed by a transaction id and lists
+        // the page numbers prefixed by their count.
+        let (key, data) = result?;
+        let mut words = data.chunks_exact(mem::size_of::<usize>()).map(read_usize);
+        let count = words.next().unwrap_or(0);
+        let mut pages: Vec<_> = words.take(count).map(|page| page as u64).collect();
+        pages.sort_unstable();
+        entries.push(FreelistEntry { txn_id: read_usize(key) as u64, pages: page_ranges(pages) });
+    }
+    Ok(entries)
+}
+
+fn read_usize(bytes: &[u8]) -> usize {
+    usize::from_ne_bytes(bytes.try_into().unwrap())
+}
+
+/// Groups sorted page numbers in ranges of contiguous pages.
+fn page_ranges(pages: Vec<u64>) -> Vec<Range<u64>> {
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    for page in pages {
+        match ranges.last_mut() {
+            Some(range) if range.end == page => range.end += 1,
+            _ => ranges.push(page..page + 1),
+        }
+    }
+    ranges
+}
+
+pub(crate) fn database(txn: &impl ReadTxn, dbi: ffi::MDB_dbi) -> Database<Bytes, Bytes> {
+    Database::new(txn.env_generation(), dbi)
+}
+
+fn database_space(
+    txn: &impl ReadTxn,
+    name: Option<&str>,
+    db: Database<Bytes, Bytes>,
+) -> Result<DatabaseSpace> {
+    let mut live_bytes = 0;
+    for result in db.iter(txn)? {
+        let (key, data) = result?;
+        live_bytes += (key.len() + data.len()) as u64;
+    }
+    Ok(DatabaseSpace { name: name.map(str::to_owned), stat: db.stat(txn)?, live_bytes })
+}
+
+/// Opens a named database without registering it in the environment, the handle is
+/// closed with the transaction if the database wasn't already opened.
+pub(crate) fn open_dbi(txn: &impl ReadTxn, name: &str) -> Result<ffi::MDB_dbi> {
+    let name = CString::new(name).unwrap();
+    let mut dbi = 0;
+    unsafe {
+        mdb_result(ffi::mdb_dbi_open(txn.txn_ptr().as_mut(), name.as_ptr(), 0, &mut dbi))?;
+    }
+    Ok(dbi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn space_reports() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let unnamed = env.create_database::<Bytes, Bytes>(&mut wtxn, None)?;
+        let small = env.create_database::<Bytes, Bytes>(&mut wtxn, Some("small"))?;
+        let large = env.create_database::<Bytes, Bytes>(&mut wtxn, Some("large"))?;
+        unnamed.put(&mut wtxn, b"not-a-db", b"value")?;
+        small.put(&mut wtxn, b"key", b"value")?;
+        large.put(&mut wtxn, b"key", &vec![0; 3 * 4096])?;
+        wtxn.commit()?;
+
+        // Free some pages.
+        let mut wtxn = env.write_txn()?;
+        large.clear(&mut wtxn)?;
+        large.put(&mut wtxn, b"key", b"value")?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let report = space_report(&rtxn)?;
+        let names: Vec<_> = report.databases.iter().map(|db| db.name.as_deref()).collect();
+        assert_eq!(names, [None, Some("large"), Some("small")]);
+        assert_eq!(report.databases[2].live_bytes, 8);
+        assert_eq!(report.databases[2].allocated_pages(), 1);
+        assert!(report.databases[2].fill_factor() > 0.0);
+        assert_eq!(report.databases[1].overflow_bytes(), 0);
+        assert!(report.free_pages > 0);
+        assert!(report.used_pages >= report.free_pages);
+        assert!(report.space_amplification() > 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn page_ranges() {
+        assert_eq!(super::page_ranges(vec![]), []);
+        assert_eq!(super::page_ranges(vec![3, 4, 5, 9, 11, 12]), [3..6, 9..10, 11..13]);
+    }
+
+    #[test]
+    fn freelist_reports() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().read_txn_without_tls().open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db = env.create_database::<Bytes, Bytes>(&mut wtxn, None)?;
+        db.put(&mut wtxn, b"key", &vec![0; 8 * 4096])?;
+        wtxn.commit()?;
+
+        // The overflow pages freed by this transaction can't be reused while this reader lives.
+        let old = env.read_txn()?;
+        let mut wtxn = env.write_txn()?;
+        db.delete(&mut wtxn, b"key")?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let report = freelist_report(&rtxn)?;
+        assert_eq!(report.oldest_reader, Some(old.id() as u64));
+        assert!(report.pinned_pages() >= 8);
+        assert!(report
+            .entries
+            .iter()
+            .all(|entry| entry.pages.windows(2).all(|w| w[0].end < w[1].start)));
+
+        // A write transaction is not a reader.
+        drop((old, rtxn));
+        let wtxn = env.write_txn()?;
+        let report = freelist_report(&wtxn)?;
+        assert_eq!(report.oldest_reader, None);
+        assert_eq!(report.pinned_pages(), 0);
+        assert!(report.reusable_pages() >= 8);
+        assert!(report.largest_reusable_run() >= 8);
+        assert!((0.0..1.0).contains(&report.fragmentation()));
+        Ok(())
+    }
+}
@@ -0,0 +1,891 @@
+//! Capture of the changes made by write transactions.
+//!
+//! Once enabled with [`Env::set_change_capture`], the puts and deletes issued through the
+//! [`Database`] methods of a write transaction are recorded as [`Change`]s. When the transaction
+//! commits, the changes are appended to the [change log](ChangeLog) in the same commit and/or
+//! handed to a sink once the commit succeeded. The changes of an aborted transaction are lost,
+//! a nested transaction hands its changes to its parent when it commits.
+//!
+//! Every change records the raw bytes of the key and of the values before and after the write.
+//! In databases allowing duplicates, a change is the addition or the removal of a single
+//! duplicate. Clearing or removing a database records the removal of every one of its entries.
+//!
+//! The capture can also [audit](ChangeCapture::audit) the changes, appending a timestamped
+//! record per change to the [`AuditLog`] in the same commit, with the values optionally
+//! [redacted](ChangeCapture::redact).
+//!
+//! ```
+//! # use heed::EnvOpenOptions;
+//! use heed::changes::{Change, ChangeCapture, ChangeLog};
+//! use heed::types::*;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! # let env = unsafe { EnvOpenOptions::new()
+//! #     .map_size(10 * 1024 * 1024) // 10MB
+//! #     .max_dbs(3000)
+//! #     .open(dir.path())?
+//! # };
+//! let mut capture = ChangeCapture::new();
+//! capture.log(true).sink(|txn_id, changes| println!("{txn_id}: {} changes", changes.len()));
+//! env.set_change_capture(Some(capture));
+//!
+//! let mut wtxn = env.write_txn()?;
+//! let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+//! db.put(&mut wtxn, "kero", "28")?;
+//! db.put(&mut wtxn, "kero", "29")?;
+//! wtxn.commit()?;
+//!
+//! let rtxn = env.read_txn()?;
+//! let log = ChangeLog::open(&env, &rtxn)?.unwrap();
+//! let (_txn_id, changes) = log.iter_since(&rtxn, 0)?.next().unwrap()?;
+//! assert_eq!(changes[1], Change {
+//!     database: Some(String::from("users")),
+//!     key: b"kero".to_vec(),
+//!     old: Some(b"28".to_vec()),
+//!     new: Some(b"29".to_vec()),
+//! });
+//! # Ok(()) }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use byteorder::BigEndian;
+
+use crate::cursor::RoCursor;
+use crate::envs::EnvInner;
+use crate::mdb::lmdb_flags::DatabaseFlags;
+use crate::meta::Metadata;
+use crate::types::{Bytes, U64};
+use crate::{
+    BoxedError, BytesDecode, BytesEncode, Database, Env, ReadTxn, Result, RwTxn, WriteTxn,
+};
+
+/// The name of the database where the changes are logged.
+pub const CHANGE_LOG_DATABASE_NAME: &str = "__heed_changes";
+
+/// The name of the database where the changes are audited.
+pub const AUDIT_LOG_DATABASE_NAME: &str = "__heed_audit";
+
+/// A put or a delete of an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The name of the database, `None` for the unnamed database.
+    pub database: Option<String>,
+    /// The key of the entry.
+    pub key: Vec<u8>,
+    /// The value before the change, `None` if there was no entry.
+    pub old: Option<Vec<u8>>,
+    /// The value after the change, `None` if the entry was deleted.
+    pub new: Option<Vec<u8>>,
+}
+
+type Sink = Box<dyn Fn(u64, &[Change]) + Send + Sync>;
+
+type Redactor = Box<dyn Fn(&mut Change) + Send + Sync>;
+
+/// The raw keys and values of the entries of a database.
+type RawEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Describes what is done with the changes of the committed write transactions.
+///
+/// By default the changes are recorded but neither logged nor handed to a sink.
+#[derive(Default)]
+pub struct ChangeCapture {
+    log: bool,
+    sink: Option<Sink>,
+    audit: bool,
+    redact: Option<Redactor>,
+    /// Whether a transaction logging its changes with this capture has been committed,
+    /// see [`ChangeLog::logged_since`].
+    logging: AtomicBool,
+}
+
+impl ChangeCapture {
+    /// Creates the default capture.
+    pub fn new() -> ChangeCapture {
+        ChangeCapture::default()
+    }
+
+    /// Appends the changes of every transaction to the [`ChangeLog`], `false` by default.
+    ///
+    /// The changes are written in the committing transaction, the log is therefore exactly
+    /// as durable as the data. The log database counts in the
+    /// [`EnvOpenOptions::max_dbs`](crate::EnvOpenOptions::max_dbs) limit.
+    pub fn log(&mut self, log: bool) -> &mut Self {
+        self.log = log;
+        self
+    }
+
+    /// Hands the identifier and the changes of every transaction to the sink
+    /// once it is committed.
+    ///
+    /// The sink is called on the committing thread and is not called for transactions without
+    /// changes. It is not called if the process stops right after a commit.
+    pub fn sink<F>(&mut self, sink: F) -> &mut Self
+    where
+        F: Fn(u64, &[Change]) + Send + Sync + 'static,
+    {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Appends a record of every change to the [`AuditLog`], `false` by default.
+    ///
+    /// Like the change log, the records are written in the committing transaction
+    /// and count in the [`EnvOpenOptions::max_dbs`](crate::EnvOpenOptions::max_dbs) limit.
+    pub fn audit(&mut self, audit: bool) -> &mut Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Modifies the changes before they are audited, e.g. to hash or remove the sensitive values.
+    ///
+    /// Only the audit log is redacted, the change log and the sink receive the original changes.
+    pub fn redact<F>(&mut self, redact: F) -> &mut Self
+    where
+        F: Fn(&mut Change) + Send + Sync + 'static,
+    {
+        self.redact = Some(Box::new(redact));
+        self
+    }
+}
+
+impl fmt::Debug for ChangeCapture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChangeCapture")
+            .field("log", &self.log)
+            .field("sink", &self.sink.is_some())
+            .field("audit", &self.audit)
+            .field("redact", &self.redact.is_some())
+            .finish()
+    }
+}
+
+impl<T> Env<T> {
+    /// Enables the capture of the changes with `Some`, or disables it with `None`.
+    ///
+    /// Only the write transactions started afterward are affected.
+    /// See the [`changes`](crate::changes) module for the details.
+    pub fn set_change_capture(&self, capture: Option<ChangeCapture>) {
+        *self.inner.change_capture.write().unwrap() = capture.map(Arc::new);
+    }
+}
+
+/// A handle to the change log database of an environment.
+///
+/// The changes of every transaction are stored under the identifier of the transaction,
+/// i.e. the [`RoTxn::id`](crate::RoTxn::id) of the read transactions that see them first.
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeLog {
+    db: Database<U64<BigEndian>, ChangesCodec>,
+    /// Where the first logged transaction is stored, `None` if it has never been stored.
+    metadata: Option<Metadata>,
+}
+
+impl ChangeLog {
+    /// Opens the change log database, creating it and the metadata database if they don't exist.
+    pub fn create<T>(env: &Env<T>, wtxn: &mut impl WriteTxn) -> Result<ChangeLog> {
+        let db = env.create_database(wtxn, Some(CHANGE_LOG_DATABASE_NAME))?;
+        Ok(ChangeLog { db, metadata: Some(Metadata::create(env, wtxn)?) })
+    }
+
+    /// Opens the change log database, returns `None` if it doesn't exist.
+    pub fn open<T>(env: &Env<T>, rtxn: &impl ReadTxn) -> Result<Option<ChangeLog>> {
+        let db = env.open_database(rtxn, Some(CHANGE_LOG_DATABASE_NAME))?;
+        match db {
+            Some(db) => Ok(Some(ChangeLog { db, metadata: Metadata::open(env, rtxn)? })),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the identifier of the first transaction from which the changes of every
+    /// transaction are in the log, `None` if it is unknown.
+    ///
+    /// It is the first transaction logged by the capture enabled last, the transactions
+    /// committed before it may have been made without a capture. It moves forward when
+    /// the log is [truncated](ChangeLog::truncate).
+    pub fn logged_since(&self, rtxn: &impl ReadTxn) -> Result<Option<u64>> {
+        match self.metadata {
+            Some(metadata) => metadata.change_log_since(rtxn),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the changes of the given transaction, if it made any.
+    pub fn get(&self, rtxn: &impl ReadTxn, txn_id: u64) -> Result<Option<Vec<Change>>> {
+        self.db.get(rtxn, &txn_id)
+    }
+
+    /// Returns the identifier of the last logged transaction.
+    pub fn last_txn_id(&self, rtxn: &impl ReadTxn) -> Result<Option<u64>> {
+        Ok(self.db.remap_data_type::<Bytes>().last(rtxn)?.map(|(txn_id, _)| txn_id))
+    }
+
+    /// Iterates over the logged transactions with an identifier greater than or equal to
+    /// `txn_id`, in commit order.
+    pub fn iter_since<'txn>(
+        &self,
+        rtxn: &'txn impl ReadTxn,
+        txn_id: u64,
+    ) -> Result<impl Iterator<Item = Result<(u64, Vec<Change>)>> + 'txn> {
+        self.db.range(rtxn, &(txn_id..))
+    }
+
+    /// Deletes the changes of the transactions with an identifier lower than `txn_id`,
+    /// returns the number of deleted transactions.
+    pub fn truncate(&self, wtxn: &mut impl WriteTxn, txn_id: u64) -> Result<usize> {
+        if let Some(metadata) = self.metadata {
+            if metadata.change_log_since(wtxn)?.is_some_and(|since| since < txn_id) {
+                metadata.set_change_log_since(wtxn, txn_id)?;
+            }
+        }
+        self.db.delete_range(wtxn, &(..txn_id))
+    }
+}
+
+/// A change recorded in the [`AuditLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The identifier of the transaction that made the change.
+    pub txn_id: u64,
+    /// The time at which the transaction was committed.
+    pub timestamp: SystemTime,
+    /// The change, after the [redaction](ChangeCapture::redact).
+    pub change: Change,
+}
+
+/// A handle to the audit log database of an environment, see [`ChangeCapture::audit`].
+///
+/// The records are stored under the identifier of their transaction and their position in it,
+/// starting at zero, and this handle doesn't allow to modify them. A record removed through
+/// another handle on the database therefore leaves a gap in the positions.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::changes::{AuditLog, ChangeCapture};
+/// use heed::types::*;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut capture = ChangeCapture::new();
+/// capture.audit(true).redact(|change| {
+///     if change.database.as_deref() == Some("passwords") {
+///         change.old = change.old.as_ref().map(|_| b"<redacted>".to_vec());
+///         change.new = change.new.as_ref().map(|_| b"<redacted>".to_vec());
+///     }
+/// });
+/// env.set_change_capture(Some(capture));
+///
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, Str>(&mut wtxn, Some("passwords"))?;
+/// db.put(&mut wtxn, "kero", "hunter2")?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// let audit = AuditLog::open(&env, &rtxn)?.unwrap();
+/// let record = audit.iter_since(&rtxn, 0)?.next().unwrap()?;
+/// assert_eq!(record.change.key, b"kero");
+/// assert_eq!(record.change.new.as_deref(), Some(&b"<redacted>"[..]));
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct AuditLog {
+    db: Database<AuditKeyCodec, AuditValueCodec>,
+}
+
+impl AuditLog {
+    /// Opens the audit log database, creating it if it doesn't exist.
+    fn create<T>(env: &Env<T>, wtxn: &mut impl WriteTxn) -> Result<AuditLog> {
+        let db = env.create_database(wtxn, Some(AUDIT_LOG_DATABASE_NAME))?;
+        Ok(AuditLog { db })
+    }
+
+    /// Opens the audit log database, returns `None` if it doesn't exist.
+    pub fn open<T>(env: &Env<T>, rtxn: &impl ReadTxn) -> Result<Option<AuditLog>> {
+        let db = env.open_database(rtxn, Some(AUDIT_LOG_DATABASE_NAME))?;
+        Ok(db.map(|db| AuditLog { db }))
+    }
+
+    /// Returns the identifier of the last audited transaction.
+    pub fn last_txn_id(&self, rtxn: &impl ReadTxn) -> Result<Option<u64>> {
+        let last = self.db.remap_data_type::<Bytes>().last(rtxn)?;
+        Ok(last.map(|((txn_id, _), _)| txn_id))
+    }
+
+    /// Iterates over the records of the transactions with an identifier greater than
+    /// or equal to `txn_id`, in commit order.
+    pub fn iter_since<'txn>(
+        &self,
+        rtxn: &'txn impl ReadTxn,
+        txn_id: u64,
+    ) -> Result<impl Iterator<Item = Result<AuditRecord>> + 'txn> {
+        let records = self.db.range(rtxn, &((txn_id, 0)..))?;
+        Ok(records.map(|result| {
+            let ((txn_id, _), (timestamp, change)) = result?;
+            Ok(AuditRecord { txn_id, timestamp, change })
+        }))
+    }
+
+    /// Appends the records of the changes of a transaction about to be committed.
+    fn append(
+        &self,
+        wtxn: &mut RwTxn,
+        txn_id: u64,
+        changes: &[Change],
+        redact: Option<&Redactor>,
+    ) -> Result<()> {
+        let timestamp = SystemTime::now();
+        for (index, change) in changes.iter().enumerate() {
+            let mut change = change.clone();
+            if let Some(redact) = redact {
+                redact(&mut change);
+            }
+            self.db.put(wtxn, &(txn_id, index as u32), &(timestamp, change))?;
+        }
+        Ok(())
+    }
+}
+
+/// The changes recorded by a write transaction, see [`WriteTxn`].
+#[doc(hidden)]
+pub struct ChangeRecorder {
+    env: Arc<EnvInner>,
+    capture: Arc<ChangeCapture>,
+    log: Option<ChangeLog>,
+    audit: Option<AuditLog>,
+    changes: Vec<Change>,
+}
+
+impl ChangeRecorder {
+    /// Creates the recorder of a new write transaction if the capture is enabled,
+    /// creating the change log database if needed.
+    pub(crate) fn start<T>(env: &Env<T>, wtxn: &mut RwTxn) -> Result<Option<ChangeRecorder>> {
+        let capture = match env.inner.change_capture.read().unwrap().clone() {
+            Some(capture) => capture,
+            None => return Ok(None),
+        };
+
+        let log = if capture.log { Some(ChangeLog::create(env, wtxn)?) } else { None };
+        // The transactions committed before the capture may not be logged.
+        if capture.log && !capture.logging.load(Ordering::Acquire) {
+            let txn_id = wtxn.id() as u64;
+            Metadata::create(env, wtxn)?.set_change_log_since(wtxn, txn_id)?;
+        }
+        let audit = if capture.audit { Some(AuditLog::create(env, wtxn)?) } else { None };
+        Ok(Some(ChangeRecorder {
+            env: env.inner.clone(),
+            capture,
+            log,
+            audit,
+            changes: Vec::new(),
+        }))
+    }
+
+    /// Creates the recorder of a nested transaction, its changes are logged by the parent.
+    pub(crate) fn nested(&self) -> ChangeRecorder {
+        ChangeRecorder {
+            env: self.env.clone(),
+            capture: self.capture.clone(),
+            log: None,
+            audit: None,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Creates the recorder of the transaction that follows a checkpoint of this one.
+    pub(crate) fn restart(&self) -> ChangeRecorder {
+        ChangeRecorder {
+            env: self.env.clone(),
+            capture: self.capture.clone(),
+            log: self.log,
+            audit: self.audit,
+            changes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    fn record(&mut self, dbi: u32, key: &[u8], old: Option<Vec<u8>>, new: Option<&[u8]>) {
+        let database = self.env.database_names.read().unwrap().get(&dbi).cloned().flatten();
+        self.changes.push(Change {
+            database,
+            key: key.to_vec(),
+            old,
+            new: new.map(<[u8]>::to_vec),
+        });
+    }
+
+    /// Appends the changes to the change log and to the audit log,
+    /// in the transaction about to be committed.
+    pub(crate) fn write_log(&self, wtxn: &mut RwTxn) -> Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        let txn_id = wtxn.id() as u64;
+        if let Some(log) = self.log {
+            log.db.put(wtxn, &txn_id, &self.changes)?;
+        }
+        if let Some(audit) = self.audit {
+            audit.append(wtxn, txn_id, &self.changes, self.capture.redact.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Hands the changes of a committed transaction to its parent or to the sink.
+    pub(crate) fn committed(mut self, txn_id: u64, parent: Option<&mut ChangeRecorder>) {
+        match parent {
+            Some(parent) => parent.changes.append(&mut self.changes),
+            None => {
+                if self.log.is_some() {
+                    self.capture.logging.store(true, Ordering::Release);
+                }
+                if let Some(sink) = self.capture.sink.as_ref().filter(|_| !self.changes.is_empty())
+                {
+                    sink(txn_id, &self.changes);
+                }
+            }
+        }
+    }
+}
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Returns the value of the key before a put when the transaction records its changes.
+    ///
+    /// The value is always `None` in databases allowing duplicates, as a put adds a duplicate
+    /// instead of replacing the value. Putting a duplicate that already exists changes nothing,
+    /// `None` is then returned and the put must not be recorded.
+    pub(crate) fn value_before_put(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &[u8],
+        data: Option<&[u8]>,
+    ) -> Result<Option<Option<Vec<u8>>>> {
+        if txn.change_recorder().is_none() {
+            return Ok(None);
+        }
+        if self.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT) {
+            let exists = match data {
+                Some(data) => RoCursor::new(txn, self.dbi)?.move_on_key_value(key, data)?,
+                None => false,
+            };
+            return Ok((!exists).then_some(None));
+        }
+        let db = self.remap_types::<Bytes, Bytes>();
+        Ok(Some(db.get(txn, key)?.map(<[u8]>::to_vec)))
+    }
+
+    /// Returns the values of the key, all its duplicates, before a delete
+    /// when the transaction records its changes.
+    pub(crate) fn values_before_delete(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &[u8],
+    ) -> Result<Option<Vec<Vec<u8>>>> {
+        if txn.change_recorder().is_none() {
+            return Ok(None);
+        }
+        let db = self.remap_types::<Bytes, Bytes>();
+        let mut values = Vec::new();
+        if let Some(duplicates) = db.get_duplicates(txn, key)? {
+            for result in duplicates {
+                values.push(result?.1.to_vec());
+            }
+        }
+        Ok(Some(values))
+    }
+
+    /// Returns all the entries before a clear when the transaction records its changes.
+    pub(crate) fn entries_before_clear(
+        &self,
+        txn: &mut impl WriteTxn,
+    ) -> Result<Option<RawEntries>> {
+        if txn.change_recorder().is_none() {
+            return Ok(None);
+        }
+        let db = self.remap_types::<Bytes, Bytes>();
+        let entries = db.iter(txn)?.map(|result| result.map(|(k, v)| (k.to_vec(), v.to_vec())));
+        entries.collect::<Result<_>>().map(Some)
+    }
+
+    /// Records the value after a write whose key was absent or had the given old value.
+    pub(crate) fn record_change(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &[u8],
+        old: Option<Vec<u8>>,
+        new: Option<&[u8]>,
+    ) {
+        if let Some(recorder) = txn.change_recorder() {
+            recorder.record(self.dbi, key, old, new);
+        }
+    }
+
+    /// Records the value written in the reserved space of a put when the transaction
+    /// records its changes.
+    pub(crate) fn record_reserved_put(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &[u8],
+        old: Option<Vec<u8>>,
+    ) -> Result<()> {
+        if txn.change_recorder().is_some() {
+            let db = self.remap_types::<Bytes, Bytes>();
+            let new = db.get(txn, key)?.map(<[u8]>::to_vec);
+            self.record_change(txn, key, old, new.as_deref());
+        }
+        Ok(())
+    }
+}
+
+/// Encodes the changes of a transaction: for every change, the optional database name,
+/// the key and the optional old and new values, prefixed by their big-endian `u32` length.
+enum ChangesCodec {}
+
+impl<'a> BytesEncode<'a> for ChangesCodec {
+    type EItem = [Change];
+
+    fn bytes_encode(changes: &'a [Change]) -> std::result::Result<Cow<'a, [u8]>, BoxedError> {
+        fn write(bytes: &mut Vec<u8>, field: &[u8]) -> std::result::Result<(), BoxedError> {
+            let len = u32::try_from(field.len())?;
+            bytes.extend_from_slice(&len.to_be_bytes());
+            bytes.extend_from_slice(field);
+            Ok(())
+        }
+
+        fn write_option(
+            bytes: &mut Vec<u8>,
+            field: Option<&[u8]>,
+        ) -> std::result::Result<(), BoxedError> {
+            match field {
+                Some(field) => {
+                    bytes.push(1);
+                    write(bytes, field)
+                }
+                None => {
+                    bytes.push(0);
+                    Ok(())
+                }
+            }
+        }
+
+        let mut bytes = Vec::new();
+        for change in changes {
+            write_option(&mut bytes, change.database.as_deref().map(str::as_bytes))?;
+            write(&mut bytes, &change.key)?;
+            write_option(&mut bytes, change.old.as_deref())?;
+            write_option(&mut bytes, change.new.as_deref())?;
+        }
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> BytesDecode<'a> for ChangesCodec {
+    type DItem = Vec<Change>;
+
+    fn bytes_decode(mut bytes: &'a [u8]) -> std::result::Result<Vec<Change>, BoxedError> {
+        fn read<'a>(bytes: &mut &'a [u8]) -> std::result::Result<&'a [u8], BoxedError> {
+            let (len, rest) = bytes.split_first_chunk::<4>().ok_or("truncated change length")?;
+            let len = u32::from_be_bytes(*len) as usize;
+            if rest.len() < len {
+                return Err("truncated change field".into());
+            }
+            let (field, rest) = rest.split_at(len);
+            *bytes = rest;
+            Ok(field)
+        }
+
+        fn read_option<'a>(
+            bytes: &mut &'a [u8],
+        ) -> std::result::Result<Option<&'a [u8]>, BoxedError> {
+            let (&tag, rest) = bytes.split_first().ok_or("truncated change field")?;
+            *bytes = rest;
+            match tag {
+                0 => Ok(None),
+                1 => read(bytes).map(Some),
+                tag => Err(format!("invalid change field tag {tag}").into()),
+            }
+        }
+
+        let mut changes = Vec::new();
+        while !bytes.is_empty() {
+            let database = match read_option(&mut bytes)? {
+                Some(name) => Some(std::str::from_utf8(name)?.to_owned()),
+                None => None,
+            };
+            let key = read(&mut bytes)?.to_vec();
+            let old = read_option(&mut bytes)?.map(<[u8]>::to_vec);
+            let new = read_option(&mut bytes)?.map(<[u8]>::to_vec);
+            changes.push(Change { database, key, old, new });
+        }
+        Ok(changes)
+    }
+}
+
+/// Encodes the key of an audit record: the big-endian transaction identifier
+/// followed by the big-endian position of the change in the transaction.
+enum AuditKeyCodec {}
+
+impl<'a> BytesEncode<'a> for AuditKeyCodec {
+    type EItem = (u64, u32);
+
+    fn bytes_encode(
+        &(txn_id, index): &'a (u64, u32),
+    ) -> std::result::Result<Cow<'a, [u8]>, BoxedError> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.extend_from_slice(&txn_id.to_be_bytes());
+        bytes.extend_from_slice(&index.to_be_bytes());
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> BytesDecode<'a> for AuditKeyCodec {
+    type DItem = (u64, u32);
+
+    fn bytes_decode(bytes: &'a [u8]) -> std::result::Result<(u64, u32), BoxedError> {
+        let bytes: &[u8; 12] = bytes.try_into().map_err(|_| "invalid audit key length")?;
+        let (txn_id, index) = bytes.split_at(8);
+        Ok((u64::from_be_bytes(txn_id.try_into()?), u32::from_be_bytes(index.try_into()?)))
+    }
+}
+
+/// Encodes the value of an audit record: the big-endian nanoseconds since the Unix epoch
+/// followed by the change encoded like in the change log.
+enum AuditValueCodec {}
+
+impl<'a> BytesEncode<'a> for AuditValueCodec {
+    type EItem = (SystemTime, Change);
+
+    fn bytes_encode(
+        (timestamp, change): &'a (SystemTime, Change),
+    ) -> std::result::Result<Cow<'a, [u8]>, BoxedError> {
+        let nanos = u64::try_from(timestamp.duration_since(UNIX_EPOCH)?.as_nanos())?;
+        let mut bytes = nanos.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&ChangesCodec::bytes_encode(std::slice::from_ref(change))?);
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> BytesDecode<'a> for AuditValueCodec {
+    type DItem = (SystemTime, Change);
+
+    fn bytes_decode(bytes: &'a [u8]) -> std::result::Result<(SystemTime, Change), BoxedError> {
+        let (nanos, change) = bytes.split_first_chunk::<8>().ok_or("truncated audit timestamp")?;
+        let timestamp = UNIX_EPOCH + Duration::from_nanos(u64::from_be_bytes(*nanos));
+        match <[Change; 1]>::try_from(ChangesCodec::bytes_decode(change)?) {
+            Ok([change]) => Ok((timestamp, change)),
+            Err(_) => Err("an audit record must contain a single change".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::types::{Str, U8};
+    use crate::{DatabaseFlags, EnvOpenOptions};
+
+    fn change(database: &str, key: &str, old: Option<&str>, new: Option<&str>) -> Change {
+        Change {
+            database: Some(database.to_owned()),
+            key: key.as_bytes().to_vec(),
+            old: old.map(|old| old.as_bytes().to_vec()),
+            new: new.map(|new| new.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn log_and_sink_changes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("words"))?;
+        db.put(&mut wtxn, "before", "capture")?;
+        wtxn.commit()?;
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let mut capture = ChangeCapture::new();
+        capture.log(true).sink(move |txn_id, changes| {
+            sink.lock().unwrap().push((txn_id, changes.to_vec()));
+        });
+        env.set_change_capture(Some(capture));
+
+        let mut wtxn = env.write_txn()?;
+        db.put(&mut wtxn, "hello", "world")?;
+        db.put(&mut wtxn, "hello", "there")?;
+        assert!(db.delete(&mut wtxn, "before")?);
+        assert!(!db.delete(&mut wtxn, "missing")?);
+        let txn_id = wtxn.id() as u64;
+        assert_eq!(wtxn.changes().len(), 3);
+        wtxn.commit()?;
+
+        let expected = vec![
+            change("words", "hello", None, Some("world")),
+            change("words", "hello", Some("world"), Some("there")),
+            change("words", "before", Some("capture"), None),
+        ];
+        assert_eq!(*received.lock().unwrap(), [(txn_id, expected.clone())]);
+
+        // Aborted transactions are neither logged nor handed to the sink.
+        let mut wtxn = env.write_txn()?;
+        db.put(&mut wtxn, "aborted", "write")?;
+        wtxn.abort();
+
+        let mut wtxn = env.write_txn()?;
+        db.clear(&mut wtxn)?;
+        let clear_id = wtxn.id() as u64;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let log = ChangeLog::open(&env, &rtxn)?.unwrap();
+        assert_eq!(log.get(&rtxn, txn_id)?, Some(expected));
+        assert_eq!(log.last_txn_id(&rtxn)?, Some(clear_id));
+        let logged: Vec<_> = log.iter_since(&rtxn, txn_id + 1)?.collect::<Result<_>>()?;
+        assert_eq!(logged, [(clear_id, vec![change("words", "hello", Some("there"), None)])]);
+        assert_eq!(received.lock().unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_and_split_transactions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        env.set_change_capture(Some(ChangeCapture::new()));
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env
+            .database_options()
+            .types()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        db.put(&mut wtxn, "key", "a")?;
+        // Putting a duplicate that already exists changes nothing.
+        db.put(&mut wtxn, "key", "a")?;
+
+        let mut nested = env.nested_write_txn(&mut wtxn)?;
+        db.put(&mut nested, "key", "b")?;
+        nested.abort();
+
+        let mut nested = env.nested_write_txn(&mut wtxn)?;
+        db.put(&mut nested, "key", "c")?;
+        nested.commit()?;
+
+        {
+            let (_read, mut write) = wtxn.split();
+            db.delete(&mut write, "key")?;
+        }
+
+        assert_eq!(
+            wtxn.changes(),
+            [
+                change("dups", "key", None, Some("a")),
+                change("dups", "key", None, Some("c")),
+                change("dups", "key", Some("a"), None),
+                change("dups", "key", Some("c"), None),
+            ]
+        );
+
+        // Without a log, the capture doesn't create the change log database.
+        wtxn.commit()?;
+        assert!(ChangeLog::open(&env, &env.read_txn()?)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_changes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut capture = ChangeCapture::new();
+        capture.log(true).audit(true).redact(|change| change.new = None);
+        env.set_change_capture(Some(capture));
+
+        let before = SystemTime::now();
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("words"))?;
+        db.put(&mut wtxn, "hello", "world")?;
+        wtxn.checkpoint()?;
+        let txn_id = wtxn.id() as u64;
+        db.put(&mut wtxn, "hello", "there")?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let audit = AuditLog::open(&env, &rtxn)?.unwrap();
+        let records: Vec<_> = audit.iter_since(&rtxn, 0)?.collect::<Result<_>>()?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].change, change("words", "hello", None, None));
+        assert_eq!(records[1].txn_id, txn_id);
+        assert_eq!(records[1].change, change("words", "hello", Some("world"), None));
+        assert!(records.iter().all(|record| record.timestamp >= before));
+        assert_eq!(audit.last_txn_id(&rtxn)?, Some(txn_id));
+
+        // The change log is not redacted.
+        let log = ChangeLog::open(&env, &rtxn)?.unwrap();
+        assert_eq!(log.get(&rtxn, txn_id)?.unwrap()[0].new.as_deref(), Some(&b"there"[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_deletions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        // Single bytes, the changes can be described with one character strings.
+        let db: Database<U8, U8> = env
+            .database_options()
+            .types()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        for (key, data) in [(b'a', b'1'), (b'a', b'2'), (b'b', b'1'), (b'b', b'2'), (b'b', b'3')] {
+            db.put(&mut wtxn, &key, &data)?;
+        }
+        db.put(&mut wtxn, &b'c', &b'1')?;
+        wtxn.commit()?;
+
+        env.set_change_capture(Some(ChangeCapture::new()));
+        let mut wtxn = env.write_txn()?;
+        assert_eq!(db.delete_dup_range(&mut wtxn, &b'b', &(b'2'..))?, 2);
+        assert_eq!(db.delete_range(&mut wtxn, &(b'a'..=b'b'))?, 3);
+        assert_eq!(
+            wtxn.changes(),
+            [
+                change("dups", "b", Some("2"), None),
+                change("dups", "b", Some("3"), None),
+                change("dups", "a", Some("1"), None),
+                change("dups", "a", Some("2"), None),
+                change("dups", "b", Some("1"), None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn changes_codec_roundtrip() {
+        let changes = vec![
+            change("words", "hello", None, Some("world")),
+            Change { database: None, key: Vec::new(), old: Some(Vec::new()), new: None },
+        ];
+        let bytes = ChangesCodec::bytes_encode(&changes).unwrap();
+        assert_eq!(ChangesCodec::bytes_decode(&bytes).unwrap(), changes);
+        assert!(ChangesCodec::bytes_decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
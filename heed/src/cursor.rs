@@ -3,22 +3,261 @@ use std::{marker, mem, ptr};
 
 use crate::mdb::error::mdb_result;
 use crate::mdb::ffi;
+use crate::observer::TxnObservation;
+use crate::paranoid::ValueCopies;
+#[cfg(unix)]
+use crate::prefetch::Prefetch;
 use crate::*;
 
 pub struct RoCursor<'txn> {
     cursor: *mut ffi::MDB_cursor,
+    /// The metrics of the transaction, if the environment has an observer.
+    observation: Option<&'txn TxnObservation>,
+    /// The copies of the entries, if the transaction copies its values.
+    copies: Option<&'txn ValueCopies>,
+    /// The readahead of the pages of the scan, if the iterator prefetches them.
+    #[cfg(unix)]
+    prefetch: Option<Prefetch>,
+    /// Whether the values of the keys of this `DUP_FIXED` database are read by page.
+    batched: bool,
+    /// The key of the entry read before by the batched cursor.
+    previous_key: Option<&'txn [u8]>,
+    /// The page of values the batched cursor is reading.
+    batch: Option<DupBatch<'txn>>,
     _marker: marker::PhantomData<&'txn ()>,
 }
 
+/// A page of the values of a key of a `DUP_FIXED` database, read with a single call.
+///
+/// LMDB moves the cursor on the last value of the page, the cursor is only logically
+/// on the current value, see [`RoCursor::sync_batch`].
+struct DupBatch<'txn> {
+    key: &'txn [u8],
+    /// The value the cursor is logically on.
+    current: &'txn [u8],
+    /// The values following the current one in the page.
+    rest: &'txn [u8],
+}
+
 impl<'txn> RoCursor<'txn> {
     pub(crate) fn new(txn: &'txn impl ReadTxn, dbi: ffi::MDB_dbi) -> Result<RoCursor<'txn>> {
         let mut cursor: *mut ffi::MDB_cursor = ptr::null_mut();
-        let mut txn = txn.txn_ptr();
-        unsafe { mdb_result(ffi::mdb_cursor_open(txn.as_mut(), dbi, &mut cursor))? }
-        Ok(RoCursor { cursor, _marker: marker::PhantomData })
+        let observation = txn.observation();
+        let copies = txn.value_copies();
+        let mut raw_txn = txn.txn_ptr();
+        unsafe { mdb_result(ffi::mdb_cursor_open(raw_txn.as_mut(), dbi, &mut cursor))? }
+        Ok(RoCursor {
+            cursor,
+            observation,
+            copies,
+            #[cfg(unix)]
+            prefetch: None,
+            batched: false,
+            previous_key: None,
+            batch: None,
+            _marker: marker::PhantomData,
+        })
+    }
+
+    /// Makes the cursor read the values of the keys by page when it moves on the next
+    /// entries, if the database is `DUP_FIXED`.
+    ///
+    /// The pages of values are read with a single call to LMDB, instead of a call by value.
+    pub(crate) fn batch_fixed_duplicates(mut self) -> Result<RoCursor<'txn>> {
+        self.batched = self.database_flags()?.contains(DatabaseFlags::DUP_FIXED);
+        Ok(self)
+    }
+
+    /// Makes the cursor return the entries as is, without recording nor copying them,
+    /// for the callers that only record and copy the entries they return.
+    pub(crate) fn untracked(mut self) -> RoCursor<'txn> {
+        self.observation = None;
+        self.copies = None;
+        self
+    }
+
+    /// The flags of the database of the cursor.
+    fn database_flags(&self) -> Result<DatabaseFlags> {
+        let mut flags = 0;
+        unsafe {
+            let txn = ffi::mdb_cursor_txn(self.cursor);
+            mdb_result(ffi::mdb_dbi_flags(txn, ffi::mdb_cursor_dbi(self.cursor), &mut flags))?;
+        }
+        Ok(DatabaseFlags::from_bits_truncate(flags))
+    }
+
+    /// Opens another cursor on the database and transaction of this one, which reads the
+    /// entries the same way.
+    pub(crate) fn sibling(&self) -> Result<RoCursor<'txn>> {
+        let mut cursor: *mut ffi::MDB_cursor = ptr::null_mut();
+        unsafe {
+            let txn = ffi::mdb_cursor_txn(self.cursor);
+            let dbi = ffi::mdb_cursor_dbi(self.cursor);
+            mdb_result(ffi::mdb_cursor_open(txn, dbi, &mut cursor))?;
+        }
+        Ok(RoCursor {
+            cursor,
+            observation: self.observation,
+            copies: self.copies,
+            #[cfg(unix)]
+            prefetch: None,
+            batched: self.batched,
+            previous_key: None,
+            batch: None,
+            _marker: marker::PhantomData,
+        })
+    }
+
+    /// Moves the cursor on the value it is logically on, if it reads a page of values,
+    /// before it is moved by another operation.
+    fn sync_batch(&mut self) -> Result<()> {
+        self.previous_key = None;
+        let Some(batch) = self.batch.take() else { return Ok(()) };
+        if batch.rest.is_empty() {
+            // The cursor is already on the last value of the page.
+            return Ok(());
+        }
+
+        self.move_on_key_value(batch.key, batch.current)?;
+        Ok(())
+    }
+
+    /// Advises the given number of pages following each entry read by the cursor,
+    /// in reverse order if `backward`.
+    #[cfg(unix)]
+    pub(crate) fn prefetch(&mut self, pages: usize, backward: bool) {
+        self.prefetch = Some(Prefetch::new(pages, backward));
+    }
+
+    /// Records the bytes of an entry read by the cursor, if the transaction is observed,
+    /// and returns the entry, copied if the transaction copies its values.
+    fn entry(&mut self, key: &'txn [u8], data: &'txn [u8]) -> (&'txn [u8], &'txn [u8]) {
+        #[cfg(unix)]
+        if let Some(prefetch) = &mut self.prefetch {
+            prefetch.touch(data);
+        }
+        if let Some(observation) = self.observation {
+            observation.read(key, data);
+        }
+        if let Some(copies) = self.copies {
+            return (copies.copy(key), copies.copy(data));
+        }
+        (key, data)
+    }
+
+    /// Moves the cursor with the given operation and returns the key of the entry,
+    /// without reading or copying its value.
+    ///
+    /// The key is only used by `MDB_SET_RANGE`, as the lower bound of the position.
+    /// The returned key is valid until the next update of the database.
+    pub(crate) fn move_key_only(
+        &mut self,
+        op: ffi::MDB_cursor_op,
+        key: &[u8],
+    ) -> Result<Option<&[u8]>> {
+        self.sync_batch()?;
+
+        let mut key_val = unsafe { crate::into_val(key) };
+
+        let result = unsafe {
+            mdb_result(ffi::mdb_cursor_get(
+                self.cursor,
+                &mut key_val,
+                &mut ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() },
+                op,
+            ))
+        };
+
+        match result {
+            Ok(()) => Ok(Some(unsafe { crate::from_val(key_val) })),
+            Err(e) if e.not_found() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the number of values of the key the cursor is on, in a `DUP_SORT` database.
+    pub(crate) fn count_duplicates(&mut self) -> Result<usize> {
+        self.sync_batch()?;
+
+        let mut count = 0;
+        unsafe { mdb_result(ffi::mdb_cursor_count(self.cursor, &mut count))? };
+        Ok(count)
+    }
+
+    /// Moves the cursor on the value at the given index among the values of the key it is on,
+    /// in a `DUP_SORT` database.
+    ///
+    /// The values of a `DUP_FIXED` database are found by counting the values of their pages,
+    /// the other values by moving from the first or the last value of the key, whichever
+    /// is the closest. Returns `None` if the key has fewer values.
+    pub(crate) fn move_on_dup_index(
+        &mut self,
+        index: usize,
+    ) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        let count = self.count_duplicates()?;
+        if index >= count {
+            return Ok(None);
+        }
+
+        if index > 0 && self.database_flags()?.contains(DatabaseFlags::DUP_FIXED) {
+            let Some((key, first)) = self.move_on_first(MoveOperation::Dup)? else {
+                return Ok(None);
+            };
+            if !first.is_empty() {
+                return match self.fixed_dup_at(first.len(), index)? {
+                    Some(data) if self.move_on_key_value(key, data)? => self.current(),
+                    _ => Ok(None),
+                };
+            }
+        }
+
+        if index <= count / 2 {
+            let mut entry = self.move_on_first(MoveOperation::Dup)?;
+            for _ in 0..index {
+                entry = self.move_on_next(MoveOperation::Dup)?;
+            }
+            Ok(entry)
+        } else {
+            let mut entry = self.move_on_last(MoveOperation::Dup)?;
+            for _ in index + 1..count {
+                entry = self.move_on_prev(MoveOperation::Dup)?;
+            }
+            Ok(entry)
+        }
+    }
+
+    /// Returns the value at the given index among the values of the `DUP_FIXED` key the
+    /// cursor is on, the cursor must be on its first value.
+    ///
+    /// The values are read by page and the cursor is left on the last value of the page
+    /// of the returned value.
+    fn fixed_dup_at(&mut self, size: usize, mut index: usize) -> Result<Option<&'txn [u8]>> {
+        let mut op = ffi::cursor_op::MDB_GET_MULTIPLE;
+        loop {
+            let mut key_val = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+            let mut data_val = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+            let result = unsafe {
+                mdb_result(ffi::mdb_cursor_get(self.cursor, &mut key_val, &mut data_val, op))
+            };
+            match result {
+                Ok(()) if data_val.mv_data.is_null() => return Ok(None),
+                Ok(()) => (),
+                Err(e) if e.not_found() => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+
+            let page = unsafe { crate::from_val(data_val) };
+            match page.chunks_exact(size).nth(index) {
+                Some(data) => return Ok(Some(data)),
+                None => index -= page.len() / size,
+            }
+            op = ffi::cursor_op::MDB_NEXT_MULTIPLE;
+        }
     }
 
     pub fn current(&mut self) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        self.sync_batch()?;
+
         let mut key_val = mem::MaybeUninit::uninit();
         let mut data_val = mem::MaybeUninit::uninit();
 
@@ -36,6 +275,7 @@ impl<'txn> RoCursor<'txn> {
             Ok(()) => {
                 let key = unsafe { crate::from_val(key_val.assume_init()) };
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                let (key, data) = self.entry(key, data);
                 Ok(Some((key, data)))
             }
             Err(e) if e.not_found() => Ok(None),
@@ -44,6 +284,8 @@ impl<'txn> RoCursor<'txn> {
     }
 
     pub fn move_on_first(&mut self, op: MoveOperation) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        self.sync_batch()?;
+
         let mut key_val = mem::MaybeUninit::uninit();
         let mut data_val = mem::MaybeUninit::uninit();
 
@@ -77,6 +319,7 @@ impl<'txn> RoCursor<'txn> {
             Ok(()) => {
                 let key = unsafe { crate::from_val(key_val.assume_init()) };
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                let (key, data) = self.entry(key, data);
                 Ok(Some((key, data)))
             }
             Err(e) if e.not_found() => Ok(None),
@@ -85,6 +328,8 @@ impl<'txn> RoCursor<'txn> {
     }
 
     pub fn move_on_last(&mut self, op: MoveOperation) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        self.sync_batch()?;
+
         let mut key_val = mem::MaybeUninit::uninit();
         let mut data_val = mem::MaybeUninit::uninit();
 
@@ -118,6 +363,7 @@ impl<'txn> RoCursor<'txn> {
             Ok(()) => {
                 let key = unsafe { crate::from_val(key_val.assume_init()) };
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                let (key, data) = self.entry(key, data);
                 Ok(Some((key, data)))
             }
             Err(e) if e.not_found() => Ok(None),
@@ -126,6 +372,8 @@ impl<'txn> RoCursor<'txn> {
     }
 
     pub fn move_on_key(&mut self, key: &[u8]) -> Result<bool> {
+        self.sync_batch()?;
+
         let mut key_val = unsafe { crate::into_val(key) };
 
         // Move the cursor to the specified key
@@ -145,10 +393,67 @@ impl<'txn> RoCursor<'txn> {
         }
     }
 
+    /// Moves the cursor on the given value of the key, in a `DUP_SORT` database.
+    ///
+    /// Returns `false` if the key doesn't have this value.
+    pub(crate) fn move_on_key_value(&mut self, key: &[u8], data: &[u8]) -> Result<bool> {
+        self.sync_batch()?;
+
+        let mut key_val = unsafe { crate::into_val(key) };
+        let mut data_val = unsafe { crate::into_val(data) };
+
+        let result = unsafe {
+            mdb_result(ffi::mdb_cursor_get(
+                self.cursor,
+                &mut key_val,
+                &mut data_val,
+                ffi::cursor_op::MDB_GET_BOTH,
+            ))
+        };
+
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if e.not_found() => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Moves the cursor on the first value of the key greater than or equal to the given
+    /// one, in a `DUP_SORT` database, and returns this value.
+    ///
+    /// Returns `None` if the key doesn't exist or if all its values are lower.
+    pub(crate) fn move_on_key_value_greater_than_or_equal_to(
+        &mut self,
+        key: &[u8],
+        data: &[u8],
+    ) -> Result<Option<&'txn [u8]>> {
+        self.sync_batch()?;
+
+        let mut key_val = unsafe { crate::into_val(key) };
+        let mut data_val = unsafe { crate::into_val(data) };
+
+        let result = unsafe {
+            mdb_result(ffi::mdb_cursor_get(
+                self.cursor,
+                &mut key_val,
+                &mut data_val,
+                ffi::cursor_op::MDB_GET_BOTH_RANGE,
+            ))
+        };
+
+        match result {
+            Ok(()) => Ok(Some(unsafe { crate::from_val(data_val) })),
+            Err(e) if e.not_found() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub fn move_on_key_greater_than_or_equal_to(
         &mut self,
         key: &[u8],
     ) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        self.sync_batch()?;
+
         let mut key_val = unsafe { crate::into_val(key) };
         let mut data_val = mem::MaybeUninit::uninit();
 
@@ -166,6 +471,7 @@ impl<'txn> RoCursor<'txn> {
             Ok(()) => {
                 let key = unsafe { crate::from_val(key_val) };
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                let (key, data) = self.entry(key, data);
                 Ok(Some((key, data)))
             }
             Err(e) if e.not_found() => Ok(None),
@@ -174,6 +480,8 @@ impl<'txn> RoCursor<'txn> {
     }
 
     pub fn move_on_prev(&mut self, op: MoveOperation) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        self.sync_batch()?;
+
         let mut key_val = mem::MaybeUninit::uninit();
         let mut data_val = mem::MaybeUninit::uninit();
 
@@ -197,6 +505,7 @@ impl<'txn> RoCursor<'txn> {
             Ok(()) => {
                 let key = unsafe { crate::from_val(key_val.assume_init()) };
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                let (key, data) = self.entry(key, data);
                 Ok(Some((key, data)))
             }
             Err(e) if e.not_found() => Ok(None),
@@ -205,6 +514,16 @@ impl<'txn> RoCursor<'txn> {
     }
 
     pub fn move_on_next(&mut self, op: MoveOperation) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        let batched = self.batched && !matches!(op, MoveOperation::NoDup);
+        if batched {
+            if let Some((key, data)) = self.move_on_next_in_batch()? {
+                let (key, data) = self.entry(key, data);
+                return Ok(Some((key, data)));
+            }
+        } else {
+            self.sync_batch()?;
+        }
+
         let mut key_val = mem::MaybeUninit::uninit();
         let mut data_val = mem::MaybeUninit::uninit();
 
@@ -228,12 +547,82 @@ impl<'txn> RoCursor<'txn> {
             Ok(()) => {
                 let key = unsafe { crate::from_val(key_val.assume_init()) };
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                if batched {
+                    self.start_batch(key, data)?;
+                }
+                let (key, data) = self.entry(key, data);
                 Ok(Some((key, data)))
             }
             Err(e) if e.not_found() => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
+
+    /// Reads the page of the value the cursor is on, if it is a following value of the
+    /// key read before, as the key is then likely to have many values.
+    fn start_batch(&mut self, key: &'txn [u8], data: &'txn [u8]) -> Result<()> {
+        let previous_key = self.previous_key.replace(key);
+        if previous_key != Some(key) || data.is_empty() {
+            return Ok(());
+        }
+
+        let mut data_val = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+        unsafe {
+            mdb_result(ffi::mdb_cursor_get(
+                self.cursor,
+                ptr::null_mut(),
+                &mut data_val,
+                ffi::cursor_op::MDB_GET_MULTIPLE,
+            ))?
+        };
+        if data_val.mv_data.is_null() {
+            // The key only has a single value.
+            return Ok(());
+        }
+
+        // The values of a page of a `DUP_FIXED` database are contiguous, and the page is
+        // returned from its first value.
+        let page = unsafe { crate::from_val(data_val) };
+        let offset = data.as_ptr() as usize - page.as_ptr() as usize;
+        debug_assert!(offset < page.len() && offset.is_multiple_of(data.len()));
+        let rest = &page[offset + data.len()..];
+        self.batch = Some(DupBatch { key, current: data, rest });
+        Ok(())
+    }
+
+    /// Moves the cursor on the next value of the page of values it reads, or on the first
+    /// value of the next page of values of the key.
+    ///
+    /// Returns `None` if the cursor doesn't read a page of values or the key has no more values.
+    fn move_on_next_in_batch(&mut self) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        let Some(batch) = &mut self.batch else { return Ok(None) };
+
+        if batch.rest.is_empty() {
+            let mut key_val = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+            let mut data_val = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+            let result = unsafe {
+                mdb_result(ffi::mdb_cursor_get(
+                    self.cursor,
+                    &mut key_val,
+                    &mut data_val,
+                    ffi::cursor_op::MDB_NEXT_MULTIPLE,
+                ))
+            };
+            match result {
+                Ok(()) => batch.rest = unsafe { crate::from_val(data_val) },
+                Err(e) if e.not_found() => {
+                    self.batch = None;
+                    return Ok(None);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let (current, rest) = batch.rest.split_at(batch.current.len());
+        batch.current = current;
+        batch.rest = rest;
+        Ok(Some((batch.key, current)))
+    }
 }
 
 impl Drop for RoCursor<'_> {
@@ -244,11 +633,26 @@ impl Drop for RoCursor<'_> {
 
 pub struct RwCursor<'txn> {
     cursor: RoCursor<'txn>,
+    /// The entries deleted through this cursor, when the transaction records its changes.
+    deleted: Option<Vec<(Vec<u8>, Vec<u8>)>>,
 }
 
 impl<'txn> RwCursor<'txn> {
     pub(crate) fn new(txn: &'txn impl WriteTxn, dbi: ffi::MDB_dbi) -> Result<RwCursor<'txn>> {
-        Ok(RwCursor { cursor: RoCursor::new(txn, dbi)? })
+        Ok(RwCursor { cursor: RoCursor::new(txn, dbi)?, deleted: None })
+    }
+
+    /// Keeps the entries deleted through this cursor, to record them in the changes
+    /// of the transaction, see [`RwCursor::into_deleted`].
+    pub(crate) fn record_deletions(mut self, record: bool) -> Self {
+        self.deleted = record.then(Vec::new);
+        self
+    }
+
+    /// Returns the entries deleted through this cursor, in order,
+    /// if it [records its deletions](RwCursor::record_deletions).
+    pub(crate) fn into_deleted(mut self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.deleted.take().unwrap_or_default()
     }
 
     /// Delete the entry the cursor is currently pointing to.
@@ -265,6 +669,12 @@ impl<'txn> RwCursor<'txn> {
     ///
     /// [undefined behavior]: https://doc.rust-lang.org/reference/behavior-considered-undefined.html
     pub unsafe fn del_current(&mut self) -> Result<bool> {
+        if let Some(deleted) = &mut self.deleted {
+            if let Some((key, data)) = self.cursor.current()? {
+                deleted.push((key.to_vec(), data.to_vec()));
+            }
+        }
+
         // Delete the current entry
         let result = mdb_result(ffi::mdb_cursor_del(self.cursor.cursor, 0));
 
@@ -274,6 +684,41 @@ impl<'txn> RwCursor<'txn> {
             Err(e) => Err(e.into()),
         }
     }
+
+    /// Delete the entry the cursor is currently pointing to, with all the other values
+    /// of its key if the database is `DUP_SORT`.
+    ///
+    /// Returns the number of deleted entries.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`RwCursor::del_current`], `dup_sort` must also be the `DUP_SORT`
+    /// flag of the database.
+    pub(crate) unsafe fn del_current_key(&mut self, dup_sort: bool) -> Result<usize> {
+        // The recorded deletions need the deleted values, they are deleted one by one.
+        if self.deleted.is_some() {
+            let Some((key, _)) = self.cursor.current()? else { return Ok(0) };
+            let key = key.to_vec();
+            let mut count = 0;
+            loop {
+                self.del_current()?;
+                count += 1;
+                match self.cursor.current()? {
+                    Some((next, _)) if dup_sort && next == &key[..] => (),
+                    _ => return Ok(count),
+                }
+            }
+        }
+
+        if !dup_sort {
+            mdb_result(ffi::mdb_cursor_del(self.cursor.cursor, 0))?;
+            return Ok(1);
+        }
+
+        let count = self.cursor.count_duplicates()?;
+        mdb_result(ffi::mdb_cursor_del(self.cursor.cursor, ffi::MDB_NODUPDATA))?;
+        Ok(count)
+    }
 }
 
 impl<'txn> Deref for RwCursor<'txn> {
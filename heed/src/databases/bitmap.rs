@@ -0,0 +1,104 @@
+use roaring::RoaringBitmap;
+use types::{Bitmap, Bytes};
+
+use crate::*;
+
+/// Helpers to update the [`RoaringBitmap`]s stored in a database.
+///
+/// Bitmaps that become empty are deleted from the database,
+/// a missing key is equivalent to an empty bitmap.
+impl<KC, C, CDUP> Database<KC, Bitmap, C, CDUP> {
+    /// Applies `f` to the bitmap stored under `key`, or to an empty bitmap if
+    /// the key does not exist, and writes the result back.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use roaring::RoaringBitmap;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Bitmap> = env.create_database(&mut wtxn, Some("postings"))?;
+    ///
+    /// db.update_bitmap(&mut wtxn, "hello", |bitmap| bitmap.extend([1, 2, 3]))?;
+    /// db.union_bitmap(&mut wtxn, "hello", &RoaringBitmap::from_iter([3, 4]))?;
+    /// db.difference_bitmap(&mut wtxn, "hello", &RoaringBitmap::from_iter([1]))?;
+    ///
+    /// let ret = db.get(&wtxn, "hello")?;
+    /// assert_eq!(ret, Some(RoaringBitmap::from_iter([2, 3, 4])));
+    ///
+    /// db.intersect_bitmap(&mut wtxn, "hello", &RoaringBitmap::new())?;
+    /// assert_eq!(db.get(&wtxn, "hello")?, None);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn update_bitmap<'a, F>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        f: F,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+        F: FnOnce(&mut RoaringBitmap),
+    {
+        let key_bytes = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let db = self.remap_key_type::<Bytes>();
+
+        let mut bitmap = db.get(txn, &key_bytes)?.unwrap_or_default();
+        f(&mut bitmap);
+
+        if bitmap.is_empty() {
+            db.delete(txn, &key_bytes).map(drop)
+        } else {
+            db.put(txn, &key_bytes, &bitmap)
+        }
+    }
+
+    /// Adds all the values of `other` to the bitmap stored under `key`.
+    pub fn union_bitmap<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        other: &RoaringBitmap,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.update_bitmap(txn, key, |bitmap| *bitmap |= other)
+    }
+
+    /// Only keeps the values of the bitmap stored under `key` that are also in `other`.
+    pub fn intersect_bitmap<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        other: &RoaringBitmap,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.update_bitmap(txn, key, |bitmap| *bitmap &= other)
+    }
+
+    /// Removes all the values of `other` from the bitmap stored under `key`.
+    pub fn difference_bitmap<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        other: &RoaringBitmap,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.update_bitmap(txn, key, |bitmap| *bitmap -= other)
+    }
+}
@@ -0,0 +1,556 @@
+use std::io::{self, BufRead, Read, Write};
+use std::{fmt, marker};
+
+use crate::databases::key_buffer::{encode_key, KeyBuffer};
+use crate::types::Bytes;
+use crate::*;
+
+/// The size of the chunks by default, they fit in the leaf pages of 4 KiB whatever the key.
+const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// The length of the blob and the size of its chunks, before the first bytes of the blob.
+const HEADER_LEN: usize = 12;
+
+/// A [`Database`] of large values split into chunks stored under consecutive keys.
+///
+/// LMDB stores a value larger than half of a page in dedicated overflow pages, which must be
+/// contiguous in the file and are copied entirely when the value is updated. The blobs are
+/// instead stored in chunks of [`Self::chunk_size`] bytes, each one under the key followed by
+/// the big-endian index of the chunk. The first chunk also holds the length of the blob and
+/// the chunk size it was written with, changing the chunk size doesn't break the existing blobs.
+///
+/// The blobs can be written from a [`Read`] and read into a [`Write`], or written and read in
+/// pieces with [`Self::open_write`] and [`Self::open_read`], without holding them in memory.
+/// The database must only hold blobs and use the default comparator.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::types::*;
+/// use heed::{BlobDatabase, Database};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let db: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("attachments"))?;
+/// let blobs = BlobDatabase::new(db);
+///
+/// let report = vec![42; 100_000];
+/// blobs.put(&mut wtxn, "report.pdf", &report)?;
+/// assert_eq!(blobs.blob_len(&wtxn, "report.pdf")?, Some(100_000));
+/// assert_eq!(blobs.get(&wtxn, "report.pdf")?, Some(report));
+///
+/// let mut file = Vec::new();
+/// blobs.put_reader(&mut wtxn, "notes.txt", &mut &b"hello"[..])?;
+/// assert_eq!(blobs.write_to(&wtxn, "notes.txt", &mut file)?, Some(5));
+/// assert_eq!(file, b"hello");
+///
+/// assert!(blobs.delete(&mut wtxn, "report.pdf")?);
+/// assert_eq!(db.len(&wtxn)?, 1);
+/// wtxn.commit()?;
+/// # Ok(()) }
+/// ```
+pub struct BlobDatabase<KC> {
+    db: Database<Bytes, Bytes>,
+    chunk_size: usize,
+    _marker: marker::PhantomData<KC>,
+}
+
+impl<KC> BlobDatabase<KC> {
+    /// Wraps a database, its blobs are written in chunks of 1 KiB.
+    pub fn new(db: Database<KC, Bytes>) -> Self {
+        BlobDatabase {
+            db: db.remap_key_type::<Bytes>(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            _marker: marker::PhantomData,
+        }
+    }
+
+    /// The size of the chunks the blobs are written in.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Writes the next blobs in chunks of this size, at least the size of the header of
+    /// the first chunk plus one byte.
+    pub fn set_chunk_size(&mut self, size: usize) -> &mut Self {
+        self.chunk_size = size.max(HEADER_LEN + 1);
+        self
+    }
+
+    /// The database of the chunks, whose keys are the keys of the blobs followed
+    /// by the big-endian index of the chunks.
+    pub fn database(&self) -> Database<Bytes, Bytes> {
+        self.db
+    }
+
+    /// Writes a blob, replacing the existing one.
+    pub fn put<'a>(&self, wtxn: &mut impl WriteTxn, key: &'a KC::EItem, blob: &[u8]) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.put_reader(wtxn, key, &mut &blob[..]).map(drop)
+    }
+
+    /// Writes a blob read until the end of the reader, replacing the existing one.
+    ///
+    /// Only a chunk is held in memory. Returns the length of the blob, an error of the reader
+    /// leaves the blob partially written and the write transaction must be aborted.
+    pub fn put_reader<'a>(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        reader: &mut impl Read,
+    ) -> Result<u64>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let mut writer = self.open_write(wtxn, key)?;
+        match io::copy(reader, &mut writer) {
+            Ok(_) => writer.finish(),
+            Err(e) => Err(writer.error.take().unwrap_or(Error::Io(e))),
+        }
+    }
+
+    /// Opens a blob for writing, replacing the existing one once [`BlobWriter::finish`]ed.
+    ///
+    /// The chunks are written as soon as they are full, only a chunk and the first bytes of
+    /// the blob are held in memory. A writer dropped without being finished leaves the blob
+    /// partially written and the write transaction must be aborted.
+    pub fn open_write<'a, 't, W>(
+        &self,
+        wtxn: &'t mut W,
+        key: &'a KC::EItem,
+    ) -> Result<BlobWriter<'t, W>>
+    where
+        KC: BytesEncode<'a>,
+        W: WriteTxn,
+    {
+        let key = encode_key::<KC>(key)?;
+        let previous = self.header(wtxn, &key)?;
+        Ok(BlobWriter {
+            wtxn,
+            db: self.db,
+            key,
+            chunk_size: self.chunk_size,
+            previous,
+            first: vec![0; HEADER_LEN],
+            chunk: Vec::with_capacity(self.chunk_size),
+            index: 1,
+            len: 0,
+            error: None,
+        })
+    }
+
+    /// Opens a blob for reading, `None` if there is no blob for the key.
+    ///
+    /// The chunks are read one after the other from the memory map of the transaction,
+    /// the blob is never copied entirely.
+    pub fn open_read<'a, 'txn, R>(
+        &self,
+        txn: &'txn R,
+        key: &'a KC::EItem,
+    ) -> Result<Option<BlobReader<'txn, R>>>
+    where
+        KC: BytesEncode<'a>,
+        R: ReadTxn,
+    {
+        let key = encode_key::<KC>(key)?;
+        let Some(header) = self.header(txn, &key)? else { return Ok(None) };
+        Ok(Some(BlobReader {
+            txn,
+            db: self.db,
+            key,
+            len: header.len,
+            remaining: header.len,
+            chunks: header.chunks(),
+            next: 0,
+            current: &[],
+        }))
+    }
+
+    /// Reads a blob entirely, `None` if there is no blob for the key.
+    pub fn get<'a>(&self, txn: &impl ReadTxn, key: &'a KC::EItem) -> Result<Option<Vec<u8>>>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let key = encode_key::<KC>(key)?;
+        let Some(header) = self.header(txn, &key)? else { return Ok(None) };
+        let mut blob = Vec::with_capacity(header.len as usize);
+        self.copy_chunks(txn, &key, header, &mut blob)?;
+        Ok(Some(blob))
+    }
+
+    /// Writes a blob into the writer, chunk by chunk, and returns its length,
+    /// `None` if there is no blob for the key.
+    pub fn write_to<'a>(
+        &self,
+        txn: &impl ReadTxn,
+        key: &'a KC::EItem,
+        writer: &mut impl Write,
+    ) -> Result<Option<u64>>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let key = encode_key::<KC>(key)?;
+        let Some(header) = self.header(txn, &key)? else { return Ok(None) };
+        self.copy_chunks(txn, &key, header, writer)?;
+        Ok(Some(header.len))
+    }
+
+    /// Returns the length of a blob, `None` if there is no blob for the key.
+    pub fn blob_len<'a>(&self, txn: &impl ReadTxn, key: &'a KC::EItem) -> Result<Option<u64>>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let key = encode_key::<KC>(key)?;
+        Ok(self.header(txn, &key)?.map(|header| header.len))
+    }
+
+    /// Deletes a blob and all its chunks, returns `true` if it existed.
+    pub fn delete<'a>(&self, wtxn: &mut impl WriteTxn, key: &'a KC::EItem) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let key = encode_key::<KC>(key)?;
+        let Some(header) = self.header(wtxn, &key)? else { return Ok(false) };
+        for index in 0..header.chunks() {
+            self.db.delete(wtxn, &chunk_key(&key, index))?;
+        }
+        Ok(true)
+    }
+
+    /// Reads the header of the first chunk of a blob.
+    fn header(&self, txn: &impl ReadTxn, key: &[u8]) -> Result<Option<Header>> {
+        match self.db.get(txn, &chunk_key(key, 0))? {
+            Some(first) => Header::decode(first).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the chunks of a blob into the writer, the chunks must add up to its length.
+    fn copy_chunks(
+        &self,
+        txn: &impl ReadTxn,
+        key: &[u8],
+        header: Header,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        let (mut remaining, chunks) = (header.len, header.chunks());
+        for index in 0..chunks {
+            writer.write_all(read_chunk(self.db, txn, key, index, chunks, &mut remaining)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl<KC> Clone for BlobDatabase<KC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<KC> Copy for BlobDatabase<KC> {}
+
+impl<KC> fmt::Debug for BlobDatabase<KC> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlobDatabase")
+            .field("db", &self.db)
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
+
+/// Writes a blob chunk by chunk, returned by [`BlobDatabase::open_write`].
+///
+/// The blob is only replaced once the writer is [finished](Self::finish).
+pub struct BlobWriter<'t, W> {
+    wtxn: &'t mut W,
+    db: Database<Bytes, Bytes>,
+    key: KeyBuffer,
+    chunk_size: usize,
+    /// The header of the replaced blob, whose extra chunks are deleted.
+    previous: Option<Header>,
+    /// The first chunk, written last once the length of the blob is known.
+    first: Vec<u8>,
+    chunk: Vec<u8>,
+    index: u64,
+    len: u64,
+    /// The error of a write, returned by [`Self::finish`].
+    error: Option<Error>,
+}
+
+impl<W: WriteTxn> BlobWriter<'_, W> {
+    /// The number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.len
+    }
+
+    /// Writes the last chunk and the first one, deletes the chunks of the replaced blob that
+    /// weren't overwritten, and returns the length of the blob.
+    pub fn finish(mut self) -> Result<u64> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if !self.chunk.is_empty() {
+            self.write_chunk()?;
+        }
+        let header = Header { len: self.len, chunk_size: self.chunk_size as u32 };
+        self.first[..HEADER_LEN].copy_from_slice(&header.encode());
+        self.db.put(self.wtxn, &chunk_key(&self.key, 0), &self.first)?;
+        if let Some(previous) = self.previous {
+            for index in self.index..previous.chunks() {
+                self.db.delete(self.wtxn, &chunk_key(&self.key, index))?;
+            }
+        }
+        Ok(self.len)
+    }
+
+    fn write_chunk(&mut self) -> Result<()> {
+        self.db.put(self.wtxn, &chunk_key(&self.key, self.index), &self.chunk)?;
+        self.chunk.clear();
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl<W: WriteTxn> Write for BlobWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(error) = &self.error {
+            return Err(into_io_error(error));
+        }
+        let buffer =
+            if self.first.len() < self.chunk_size { &mut self.first } else { &mut self.chunk };
+        let len = buf.len().min(self.chunk_size - buffer.len());
+        buffer.extend_from_slice(&buf[..len]);
+        self.len += len as u64;
+        if self.chunk.len() == self.chunk_size {
+            if let Err(error) = self.write_chunk() {
+                let io_error = into_io_error(&error);
+                self.error = Some(error);
+                return Err(io_error);
+            }
+        }
+        Ok(len)
+    }
+
+    /// The chunks are written when full, the last one is written by [`Self::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W> fmt::Debug for BlobWriter<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlobWriter").field("key", &self.key).field("len", &self.len).finish()
+    }
+}
+
+/// Reads a blob chunk by chunk, returned by [`BlobDatabase::open_read`].
+pub struct BlobReader<'txn, R> {
+    txn: &'txn R,
+    db: Database<Bytes, Bytes>,
+    key: KeyBuffer,
+    len: u64,
+    /// The bytes of the blob in the chunks not read yet.
+    remaining: u64,
+    chunks: u64,
+    next: u64,
+    current: &'txn [u8],
+}
+
+impl<R> BlobReader<'_, R> {
+    /// The length of the blob.
+    pub fn blob_len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl<R: ReadTxn> BufRead for BlobReader<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.current.is_empty() && self.next < self.chunks {
+            let (txn, key) = (self.txn, &self.key[..]);
+            self.current =
+                read_chunk(self.db, txn, key, self.next, self.chunks, &mut self.remaining)
+                    .map_err(|e| into_io_error(&e))?;
+            self.next += 1;
+        }
+        Ok(self.current)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.current = &self.current[amt..];
+    }
+}
+
+impl<R: ReadTxn> Read for BlobReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R> fmt::Debug for BlobReader<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlobReader").field("key", &self.key).field("len", &self.len).finish()
+    }
+}
+
+/// Reads the bytes of a chunk of a blob, without the header of the first chunk, and ensures
+/// the chunks add up to the length of the blob.
+fn read_chunk<'txn>(
+    db: Database<Bytes, Bytes>,
+    txn: &'txn impl ReadTxn,
+    key: &[u8],
+    index: u64,
+    chunks: u64,
+    remaining: &mut u64,
+) -> Result<&'txn [u8]> {
+    let chunk = db.get(txn, &chunk_key(key, index))?.ok_or(MdbError::Corrupted)?;
+    let bytes = if index == 0 { &chunk[HEADER_LEN..] } else { chunk };
+    *remaining = remaining.checked_sub(bytes.len() as u64).ok_or(MdbError::Corrupted)?;
+    match index + 1 == chunks && *remaining != 0 {
+        true => Err(MdbError::Corrupted.into()),
+        false => Ok(bytes),
+    }
+}
+
+fn into_io_error(error: &Error) -> io::Error {
+    match error {
+        Error::Io(error) => io::Error::new(error.kind(), error.to_string()),
+        error => io::Error::other(error.to_string()),
+    }
+}
+
+/// The key of a chunk, the key of the blob followed by the big-endian index of the chunk.
+fn chunk_key(key: &[u8], index: u64) -> KeyBuffer {
+    let mut bytes = KeyBuffer::from_slice(key);
+    bytes.extend_from_slice(&index.to_be_bytes());
+    bytes
+}
+
+/// The big-endian length of a blob and size of its chunks, at the start of its first chunk.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    len: u64,
+    chunk_size: u32,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[..8].copy_from_slice(&self.len.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.chunk_size.to_be_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Header> {
+        match bytes.get(..HEADER_LEN) {
+            Some(header) => {
+                let len = u64::from_be_bytes(header[..8].try_into().unwrap());
+                let chunk_size = u32::from_be_bytes(header[8..].try_into().unwrap());
+                match chunk_size as usize > HEADER_LEN {
+                    true => Ok(Header { len, chunk_size }),
+                    false => Err(MdbError::Corrupted.into()),
+                }
+            }
+            None => Err(MdbError::Corrupted.into()),
+        }
+    }
+
+    /// The number of chunks, the first one holds the header and the first bytes of the blob.
+    fn chunks(&self) -> u64 {
+        let first = (self.chunk_size as usize - HEADER_LEN) as u64;
+        1 + self.len.saturating_sub(first).div_ceil(self.chunk_size as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+
+    #[test]
+    fn chunked_blobs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("blobs"))?;
+        let mut blobs = BlobDatabase::new(db);
+        blobs.set_chunk_size(100);
+
+        let blob: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        for len in [0, 1, 87, 88, 89, 188, 189, 1000] {
+            blobs.put(&mut wtxn, "blob", &blob[..len])?;
+            assert_eq!(blobs.get(&wtxn, "blob")?.as_deref(), Some(&blob[..len]));
+            let chunks = Header { len: len as u64, chunk_size: 100 }.chunks();
+            assert_eq!(db.len(&wtxn)?, chunks);
+        }
+        assert_eq!(db.len(&wtxn)?, 11);
+
+        // The blob is read with the chunk size it was written with.
+        blobs.set_chunk_size(300);
+        assert_eq!(blobs.get(&wtxn, "blob")?, Some(blob.clone()));
+        assert_eq!(blobs.put_reader(&mut wtxn, "blob", &mut &blob[..500])?, 500);
+        assert_eq!(db.len(&wtxn)?, 2);
+        blobs.put(&mut wtxn, "blobs", b"other")?;
+        assert_eq!(blobs.blob_len(&wtxn, "blob")?, Some(500));
+
+        // A missing chunk is detected.
+        db.remap_key_type::<Bytes>().delete(&mut wtxn, &chunk_key(b"blob", 1))?;
+        let result = blobs.write_to(&wtxn, "blob", &mut io::sink());
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Corrupted))));
+
+        assert!(blobs.delete(&mut wtxn, "blob")?);
+        assert!(!blobs.delete(&mut wtxn, "blob")?);
+        assert_eq!(blobs.get(&wtxn, "blob")?, None);
+        assert_eq!(blobs.get(&wtxn, "blobs")?.as_deref(), Some(&b"other"[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn streaming_blobs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("blobs"))?;
+        let mut blobs = BlobDatabase::new(db);
+        blobs.set_chunk_size(64);
+        blobs.put(&mut wtxn, "blob", &[1; 1000])?;
+
+        let blob: Vec<u8> = (0..700u32).map(|i| (i % 251) as u8).collect();
+        let mut writer = blobs.open_write(&mut wtxn, "blob")?;
+        for piece in blob.chunks(33) {
+            writer.write_all(piece)?;
+        }
+        assert_eq!(writer.written(), 700);
+        assert_eq!(writer.finish()?, 700);
+        assert_eq!(db.len(&wtxn)?, Header { len: 700, chunk_size: 64 }.chunks());
+
+        let mut reader = blobs.open_read(&wtxn, "blob")?.unwrap();
+        assert_eq!(reader.blob_len(), 700);
+        let mut start = [0; 100];
+        reader.read_exact(&mut start)?;
+        assert_eq!(start, blob[..100]);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest)?;
+        assert_eq!(rest, blob[100..]);
+        assert!(blobs.open_read(&wtxn, "missing")?.is_none());
+
+        // A truncated chunk is an error of the reader.
+        db.remap_key_type::<Bytes>().put(&mut wtxn, &chunk_key(b"blob", 3), &[0; 10])?;
+        let mut reader = blobs.open_read(&wtxn, "blob")?.unwrap();
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), Error::Mdb(MdbError::Corrupted).to_string());
+        Ok(())
+    }
+}
@@ -1,16 +1,23 @@
 use std::borrow::Cow;
 use std::ops::{Bound, RangeBounds};
-use std::{any, fmt, marker, mem, ptr};
+use std::{any, fmt, io, marker, mem, ptr};
 
-use heed_traits::{Comparator, LexicographicComparator};
+use heed_traits::{Comparator, FixedSize, LexicographicComparator};
 use types::LazyDecode;
 
 use crate::cursor::MoveOperation;
+use crate::databases::key_buffer::{encode_key, encode_range};
+use crate::databases::limits::{set_size_limits, SizeLimits};
 use crate::envs::DefaultComparator;
 use crate::iteration_method::MoveOnCurrentKeyDuplicates;
+use crate::iterator::starts_with;
 use crate::mdb::error::mdb_result;
 use crate::mdb::ffi;
 use crate::mdb::lmdb_flags::{AllDatabaseFlags, DatabaseFlags};
+use crate::meta::Metadata;
+use crate::observer::{record_read, record_written};
+use crate::paranoid::copy_value;
+use crate::txn::{give_back_scratch, take_scratch};
 use crate::*;
 
 /// Options and flags which can be used to configure how a [`Database`] is opened.
@@ -60,6 +67,11 @@ pub struct DatabaseOpenOptions<'e, 'n, T, KC, DC, C = DefaultComparator, CDUP =
     types: marker::PhantomData<(KC, DC, C, CDUP)>,
     name: Option<&'n str>,
     flags: AllDatabaseFlags,
+    check_codecs: bool,
+    /// The identity of the codecs and comparators, reset when they change.
+    codec_identity: Option<fn() -> String>,
+    max_key_size: Option<usize>,
+    max_value_size: Option<usize>,
 }
 
 impl<'e, T> DatabaseOpenOptions<'e, 'static, T, Unspecified, Unspecified> {
@@ -70,6 +82,10 @@ impl<'e, T> DatabaseOpenOptions<'e, 'static, T, Unspecified, Unspecified> {
             types: Default::default(),
             name: None,
             flags: AllDatabaseFlags::empty(),
+            check_codecs: false,
+            codec_identity: None,
+            max_key_size: None,
+            max_value_size: None,
         }
     }
 }
@@ -85,31 +101,104 @@ impl<'e, 'n, T, KC, DC, C, CDUP> DatabaseOpenOptions<'e, 'n, T, KC, DC, C, CDUP>
             types: Default::default(),
             name: self.name,
             flags: self.flags,
+            check_codecs: self.check_codecs,
+            codec_identity: None,
+            max_key_size: self.max_key_size,
+            max_value_size: self.max_value_size,
         }
     }
 
     /// Change the customized key compare function of the database.
     ///
     /// By default no customized compare function will be set when opening a database.
+    ///
+    /// The comparator is installed in LMDB with `mdb_set_compare` every time the database
+    /// is opened, it defines the order of the keys in the B-tree and not only the bounds
+    /// checked by heed. A database must always be opened with the same comparator, see
+    /// the [cookbook](crate::cookbook#use-custom-key-comparator).
     pub fn key_comparator<NC>(self) -> DatabaseOpenOptions<'e, 'n, T, KC, DC, NC, CDUP> {
         DatabaseOpenOptions {
             env: self.env,
             types: Default::default(),
             name: self.name,
             flags: self.flags,
+            check_codecs: self.check_codecs,
+            codec_identity: None,
+            max_key_size: self.max_key_size,
+            max_value_size: self.max_value_size,
         }
     }
 
     /// Change the customized dup sort compare function of the database.
     ///
     /// By default no customized compare function will be set when opening a database.
+    ///
+    /// Like the [key comparator](Self::key_comparator), it is installed in LMDB, with
+    /// `mdb_set_dupsort`, and defines the order of the values of the keys.
     pub fn dup_sort_comparator<NCDUP>(self) -> DatabaseOpenOptions<'e, 'n, T, KC, DC, C, NCDUP> {
         DatabaseOpenOptions {
             env: self.env,
             types: Default::default(),
             name: self.name,
             flags: self.flags,
+            check_codecs: self.check_codecs,
+            codec_identity: None,
+            max_key_size: self.max_key_size,
+            max_value_size: self.max_value_size,
+        }
+    }
+
+    /// Change the type of the keys to integers compared natively by LMDB, the type of the
+    /// data is kept.
+    ///
+    /// The keys are encoded in native byte order, as LMDB expects, and the
+    /// [`IntegerComparator`] sets the [`DatabaseFlags::INTEGER_KEY`] flag. They must have the
+    /// size of an `unsigned int` or a `size_t`, the [`IntegerKey`] types are `u32` and `u64`
+    /// on the 64-bit targets. Opening a database created without integer keys fails with
+    /// [`MdbError::Incompatible`].
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env
+    ///     .database_options()
+    ///     .types::<Unit, Str>()
+    ///     .integer_keys::<u32>()
+    ///     .name("integers")
+    ///     .create(&mut wtxn)?;
+    ///
+    /// db.put(&mut wtxn, &256, "two hundred fifty-six")?;
+    /// db.put(&mut wtxn, &1, "one")?;
+    ///
+    /// // The keys are sorted by their numeric value.
+    /// let mut iter = db.iter(&wtxn)?;
+    /// assert_eq!(iter.next().transpose()?, Some((1, "one")));
+    /// assert_eq!(iter.next().transpose()?, Some((256, "two hundred fifty-six")));
+    /// drop(iter);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn integer_keys<I: IntegerKey>(
+        self,
+    ) -> DatabaseOpenOptions<'e, 'n, T, I::Codec, DC, IntegerComparator, CDUP> {
+        const {
+            let size = <I::Codec as FixedSize>::SIZE;
+            assert!(
+                size == mem::size_of::<libc::c_uint>() || size == mem::size_of::<libc::size_t>(),
+                "the integer keys must have the size of an unsigned int or a size_t",
+            );
         }
+        self.types::<I::Codec, DC>().key_comparator::<IntegerComparator>()
     }
 
     /// Change the name of the database.
@@ -126,6 +215,114 @@ impl<'e, 'n, T, KC, DC, C, CDUP> DatabaseOpenOptions<'e, 'n, T, KC, DC, C, CDUP>
         self
     }
 
+    /// Persist and validate the identity of the codecs and comparators of the database.
+    ///
+    /// When enabled, [`Self::create`] records the [identities](CodecIdentity) of the codecs and
+    /// comparators, the names and the versions of their formats, in the [metadata](crate::meta)
+    /// of the environment if none is recorded yet, and both [`Self::create`] and [`Self::open`]
+    /// fail with [`Error::CodecMismatch`] if the recorded identity differs from the requested one.
+    /// The identities include the parameters of the formats, a `U32<BigEndian>` database therefore
+    /// can't be opened as a `U32<LittleEndian>` one, and they don't change with the Rust version.
+    ///
+    /// The types and comparators must be set before enabling the check, [`Self::create`] and
+    /// [`Self::open`] fail with an [`io::ErrorKind::InvalidInput`] error if they are changed
+    /// after. The metadata database counts in the [`EnvOpenOptions::max_dbs`] limit.
+    /// Disabled by default.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Error;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let mut options = env.database_options().types::<Str, Str>();
+    /// options.name("names").check_codecs(true);
+    /// let db = options.create(&mut wtxn)?;
+    /// db.put(&mut wtxn, "kero", "tamo")?;
+    /// wtxn.commit()?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// let mut options = env.database_options().types::<Str, Bytes>();
+    /// options.name("names").check_codecs(true);
+    /// assert!(matches!(options.open(&rtxn), Err(Error::CodecMismatch { .. })));
+    /// # Ok(()) }
+    /// ```
+    pub fn check_codecs(&mut self, enabled: bool) -> &mut Self
+    where
+        KC: CodecIdentity,
+        DC: CodecIdentity,
+        C: CodecIdentity,
+        CDUP: CodecIdentity,
+    {
+        self.check_codecs = enabled;
+        self.codec_identity = Some(codec_identity::<KC, DC, C, CDUP>);
+        self
+    }
+
+    /// Limits the size of the encoded keys written to the database.
+    ///
+    /// The writes of larger keys fail with [`Error::KeyTooLarge`] before reaching LMDB.
+    /// The limit applies to every handle on the database once it is opened or created
+    /// with these options, until the environment is closed. No limit is set by default,
+    /// LMDB itself refuses the keys larger than [`Env::max_key_size`].
+    pub fn max_key_size(&mut self, size: usize) -> &mut Self {
+        self.max_key_size = Some(size);
+        self
+    }
+
+    /// Limits the size of the encoded values written to the database.
+    ///
+    /// The writes of larger values fail with [`Error::ValueTooLarge`] before reaching LMDB,
+    /// which protects against writing huge values by mistake. The limit applies to every
+    /// handle on the database once it is opened or created with these options, until the
+    /// environment is closed. No limit is set by default.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Error;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let mut options = env.database_options().types::<Str, Bytes>();
+    /// options.name("blobs").max_value_size(1024);
+    /// let db = options.create(&mut wtxn)?;
+    ///
+    /// db.put(&mut wtxn, "small", &[0; 1024])?;
+    /// let result = db.put(&mut wtxn, "large", &[0; 1025]);
+    /// assert!(matches!(result, Err(Error::ValueTooLarge { len: 1025, max: 1024, .. })));
+    /// # Ok(()) }
+    /// ```
+    pub fn max_value_size(&mut self, size: usize) -> &mut Self {
+        self.max_value_size = Some(size);
+        self
+    }
+
+    /// Registers the size limits of the database, if any is set.
+    fn set_size_limits(&self, dbi: ffi::MDB_dbi) {
+        if self.max_key_size.is_some() || self.max_value_size.is_some() {
+            let limits = SizeLimits {
+                name: self.name.map(ToOwned::to_owned),
+                max_key_size: self.max_key_size,
+                max_value_size: self.max_value_size,
+            };
+            set_size_limits(self.env.inner.generation, dbi, limits);
+        }
+    }
+
     /// Opens a typed database that already exists in this environment.
     ///
     /// If the database was previously opened in this program run, types will be checked.
@@ -144,6 +341,8 @@ impl<'e, 'n, T, KC, DC, C, CDUP> DatabaseOpenOptions<'e, 'n, T, KC, DC, C, CDUP>
     ///
     /// If not done, you might raise `Io(Os { code: 22, kind: InvalidInput, message: "Invalid argument" })`
     /// known as `EINVAL`.
+    ///
+    /// Fails with an [`io::ErrorKind::InvalidInput`] error if the transaction is a [`SyncRoTxn`](crate::SyncRoTxn).
     pub fn open(&self, rtxn: &impl ReadTxn) -> Result<Option<Database<KC, DC, C, CDUP>>>
     where
         KC: 'static,
@@ -152,12 +351,29 @@ impl<'e, 'n, T, KC, DC, C, CDUP> DatabaseOpenOptions<'e, 'n, T, KC, DC, C, CDUP>
         CDUP: Comparator + 'static,
     {
         assert_eq_env_txn!(self.env, rtxn);
+        if rtxn.is_shared() {
+            let msg = "databases can't be opened with a transaction shared between threads";
+            return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg)));
+        }
+        rtxn.check_usable()?;
 
-        match self.env.raw_init_database::<C, CDUP>(rtxn.txn_ptr(), self.name, self.flags) {
-            Ok(dbi) => Ok(Some(Database::new(self.env.env_mut_ptr().as_ptr() as _, dbi))),
-            Err(Error::Mdb(e)) if e.not_found() => Ok(None),
-            Err(e) => Err(e),
+        let dbi = match self.env.raw_init_database::<C, CDUP>(rtxn.txn_ptr(), self.name, self.flags)
+        {
+            Ok(dbi) => dbi,
+            Err(Error::Mdb(e)) if e.not_found() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if self.check_codecs {
+            if let Some(metadata) = Metadata::open(self.env, rtxn)? {
+                if let Some(stored) = metadata.codec_identifier(rtxn, self.name)? {
+                    self.ensure_codec_identity(stored)?;
+                }
+            }
         }
+
+        self.set_size_limits(dbi);
+        Ok(Some(Database::new(self.env.inner.generation, dbi)))
     }
 
     /// Creates a typed database that can already exist in this environment.
@@ -177,15 +393,217 @@ impl<'e, 'n, T, KC, DC, C, CDUP> DatabaseOpenOptions<'e, 'n, T, KC, DC, C, CDUP>
         CDUP: Comparator + 'static,
     {
         assert_eq_env_txn!(self.env, wtxn);
+        wtxn.check_usable()?;
 
         let flags = self.flags | AllDatabaseFlags::CREATE;
-        match self.env.raw_init_database::<C, CDUP>(wtxn.txn_ptr(), self.name, flags) {
-            Ok(dbi) => Ok(Database::new(self.env.env_mut_ptr().as_ptr() as _, dbi)),
-            Err(e) => Err(e),
+        let dbi = self.env.raw_init_database::<C, CDUP>(wtxn.txn_ptr(), self.name, flags)?;
+
+        if self.check_codecs {
+            let metadata = Metadata::create(self.env, wtxn)?;
+            match metadata.codec_identifier(wtxn, self.name)? {
+                Some(stored) => self.ensure_codec_identity(stored)?,
+                None => {
+                    let identity = self.requested_codec_identity()?;
+                    metadata.set_codec_identifier(wtxn, self.name, &identity)?;
+                }
+            }
+        }
+
+        self.set_size_limits(dbi);
+        Ok(Database::new(self.env.inner.generation, dbi))
+    }
+
+    /// Opens a [`MultiDatabase`] that already exists in this environment.
+    ///
+    /// The [`DatabaseFlags::DUP_SORT`] flag is added to the flags of the options,
+    /// see [`Self::open`]. Fails with [`MdbError::Incompatible`] if the database was
+    /// created without duplicates.
+    pub fn open_multi(&self, rtxn: &impl ReadTxn) -> Result<Option<MultiDatabase<KC, DC, C, CDUP>>>
+    where
+        KC: 'static,
+        DC: 'static,
+        C: Comparator + 'static,
+        CDUP: Comparator + 'static,
+    {
+        let mut options = *self;
+        options.flags |= AllDatabaseFlags::DUP_SORT;
+        match options.open(rtxn)? {
+            Some(db) => Ok(Some(ensure_dup_sort(db, rtxn)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates a [`MultiDatabase`] that can already exist in this environment.
+    ///
+    /// The [`DatabaseFlags::DUP_SORT`] flag is added to the flags of the options,
+    /// see [`Self::create`]. Fails with [`MdbError::Incompatible`] if the database
+    /// already exists without duplicates.
+    pub fn create_multi(&self, wtxn: &mut impl WriteTxn) -> Result<MultiDatabase<KC, DC, C, CDUP>>
+    where
+        KC: 'static,
+        DC: 'static,
+        C: Comparator + 'static,
+        CDUP: Comparator + 'static,
+    {
+        let mut options = *self;
+        options.flags |= AllDatabaseFlags::DUP_SORT;
+        let db = options.create(wtxn)?;
+        ensure_dup_sort(db, wtxn)
+    }
+
+    /// Opens a [`MultiDatabase`] with values of a fixed size that already exists in this
+    /// environment.
+    ///
+    /// The [`DatabaseFlags::DUP_SORT`] and [`DatabaseFlags::DUP_FIXED`] flags are added to
+    /// the flags of the options, see [`Self::open_multi`]. Fails with [`MdbError::Incompatible`]
+    /// if the database was created without these flags or if its values don't have the
+    /// size of the encodings of `DC`.
+    pub fn open_multi_fixed(
+        &self,
+        rtxn: &impl ReadTxn,
+    ) -> Result<Option<MultiDatabase<KC, DC, C, CDUP>>>
+    where
+        KC: 'static,
+        DC: FixedSize + 'static,
+        C: Comparator + 'static,
+        CDUP: Comparator + 'static,
+    {
+        let mut options = *self;
+        options.flags |= AllDatabaseFlags::DUP_FIXED;
+        match options.open_multi(rtxn)? {
+            Some(db) => Ok(Some(ensure_dup_fixed(db, rtxn)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates a [`MultiDatabase`] with values of a fixed size that can already exist in
+    /// this environment.
+    ///
+    /// The [`DatabaseFlags::DUP_SORT`] and [`DatabaseFlags::DUP_FIXED`] flags are added to
+    /// the flags of the options, see [`Self::create_multi`]. Fails with
+    /// [`MdbError::Incompatible`] if the database already exists without these flags or
+    /// if its values don't have the size of the encodings of `DC`.
+    ///
+    /// The values of a key are read by page when iterating forward over them.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let hashes = env
+    ///     .database_options()
+    ///     .types::<Str, FixedWidth<[u8; 4]>>()
+    ///     .name("hashes")
+    ///     .create_multi_fixed(&mut wtxn)?;
+    ///
+    /// hashes.put_dup(&mut wtxn, "file", &[0, 1, 2, 3])?;
+    /// hashes.put_dup(&mut wtxn, "file", &[0, 0, 0, 1])?;
+    ///
+    /// let values: Vec<&[u8; 4]> = hashes.iter_dups(&wtxn, "file")?.collect::<heed::Result<_>>()?;
+    /// assert_eq!(values, [&[0, 0, 0, 1], &[0, 1, 2, 3]]);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn create_multi_fixed(
+        &self,
+        wtxn: &mut impl WriteTxn,
+    ) -> Result<MultiDatabase<KC, DC, C, CDUP>>
+    where
+        KC: 'static,
+        DC: FixedSize + 'static,
+        C: Comparator + 'static,
+        CDUP: Comparator + 'static,
+    {
+        let mut options = *self;
+        options.flags |= AllDatabaseFlags::DUP_FIXED;
+        let db = options.create_multi(wtxn)?;
+        ensure_dup_fixed(db, wtxn)
+    }
+
+    /// Returns the identity of the requested codecs and comparators,
+    /// fails if they changed since the check was enabled.
+    fn requested_codec_identity(&self) -> Result<String> {
+        match self.codec_identity {
+            Some(identity) => Ok(identity()),
+            None => {
+                let msg = "the codecs must be checked after setting the types and comparators";
+                Err(Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg)))
+            }
+        }
+    }
+
+    fn ensure_codec_identity(&self, stored: &str) -> Result<()> {
+        let requested = self.requested_codec_identity()?;
+        if stored == requested {
+            Ok(())
+        } else {
+            Err(Error::CodecMismatch {
+                database: self.name.map(ToOwned::to_owned),
+                stored: stored.to_owned(),
+                requested,
+            })
         }
     }
 }
 
+/// Wraps the database in a [`MultiDatabase`], LMDB opens an existing database with its
+/// own flags whatever the requested ones.
+fn ensure_dup_sort<KC, DC, C, CDUP>(
+    db: Database<KC, DC, C, CDUP>,
+    txn: &impl ReadTxn,
+) -> Result<MultiDatabase<KC, DC, C, CDUP>> {
+    if db.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT) {
+        Ok(MultiDatabase::new(db))
+    } else {
+        Err(MdbError::Incompatible.into())
+    }
+}
+
+/// Checks that the values of the multi database have the size of the encodings of `DC`,
+/// LMDB only checks the size of the values when they are written.
+fn ensure_dup_fixed<KC, DC: FixedSize, C, CDUP>(
+    db: MultiDatabase<KC, DC, C, CDUP>,
+    txn: &impl ReadTxn,
+) -> Result<MultiDatabase<KC, DC, C, CDUP>> {
+    let raw = db.database().remap_types::<types::Bytes, types::Bytes>();
+    if !raw.database_flags(txn)?.contains(DatabaseFlags::DUP_FIXED) {
+        return Err(MdbError::Incompatible.into());
+    }
+    match raw.first(txn)? {
+        Some((_, value)) if value.len() != DC::SIZE => Err(MdbError::Incompatible.into()),
+        _ => Ok(db),
+    }
+}
+
+/// The identity of the codecs and comparators of a database, as recorded in the metadata:
+/// the identities of the key and data codecs and of the key and duplicate comparators.
+fn codec_identity<KC, DC, C, CDUP>() -> String
+where
+    KC: CodecIdentity,
+    DC: CodecIdentity,
+    C: CodecIdentity,
+    CDUP: CodecIdentity,
+{
+    let mut identity = String::new();
+    KC::write_identity(&mut identity);
+    identity.push(' ');
+    DC::write_identity(&mut identity);
+    identity.push(' ');
+    C::write_identity(&mut identity);
+    identity.push(' ');
+    CDUP::write_identity(&mut identity);
+    identity
+}
+
 impl<T, KC, DC, C, CDUP> Clone for DatabaseOpenOptions<'_, '_, T, KC, DC, C, CDUP> {
     fn clone(&self) -> Self {
         *self
@@ -196,6 +614,10 @@ impl<T, KC, DC, C, CDUP> Copy for DatabaseOpenOptions<'_, '_, T, KC, DC, C, CDUP
 
 /// A typed database that accepts only the types it was created with.
 ///
+/// A database belongs to the environment it was opened in. Every operation checks that
+/// the transaction belongs to it too, and fails with [`Error::ForeignDatabase`] otherwise,
+/// or with [`Error::EnvClosed`] once the environment is closed.
+///
 /// # Example: Iterate over databases entries
 ///
 /// In this example we store numbers in big endian this way those are ordered.
@@ -306,15 +728,44 @@ impl<T, KC, DC, C, CDUP> Copy for DatabaseOpenOptions<'_, '_, T, KC, DC, C, CDUP
 /// wtxn.commit()?;
 /// # Ok(()) }
 /// ```
+// The layout is the same for all the codecs and comparators, see `Database::as_any`.
+#[repr(C)]
 pub struct Database<KC, DC, C = DefaultComparator, CDUP = DefaultComparator> {
-    pub(crate) env_ident: usize,
+    /// The generation of the environment the database was opened in.
+    pub(crate) env_generation: u64,
     pub(crate) dbi: ffi::MDB_dbi,
     marker: marker::PhantomData<(KC, DC, C, CDUP)>,
 }
 
+/// A database without its codecs and comparators, see [`Database::as_any`].
+pub type DatabaseAny = Database<Unspecified, Unspecified>;
+
 impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
-    pub(crate) fn new(env_ident: usize, dbi: ffi::MDB_dbi) -> Database<KC, DC, C, CDUP> {
-        Database { env_ident, dbi, marker: std::marker::PhantomData }
+    pub(crate) fn new(env_generation: u64, dbi: ffi::MDB_dbi) -> Database<KC, DC, C, CDUP> {
+        Database { env_generation, dbi, marker: std::marker::PhantomData }
+    }
+
+    /// Returns this database without its codecs and comparators,
+    /// e.g. to list it in [`RwTxn::split_with`].
+    pub fn as_any(&self) -> &DatabaseAny {
+        // SAFETY: The struct is repr(C) and the types only appear in a PhantomData.
+        unsafe { &*(self as *const Self).cast::<DatabaseAny>() }
+    }
+
+    /// Ensures the database was opened in the environment of the transaction.
+    ///
+    /// The handle of a database is only valid in the environment it was opened in, another
+    /// environment can give the same handle to an unrelated database, so using it would
+    /// silently read or write the wrong entries.
+    pub(crate) fn check_env<T: ReadTxn + ?Sized>(&self, txn: &T) -> Result<()> {
+        if self.env_generation == txn.env_generation() {
+            txn.check_usable()?;
+            txn.check_dbi(self.dbi)
+        } else if crate::envs::is_env_generation_opened(self.env_generation) {
+            Err(Error::ForeignDatabase)
+        } else {
+            Err(Error::EnvClosed)
+        }
     }
 
     /// Retrieves the value associated with a key.
@@ -359,9 +810,9 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
 
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
         let mut data_val = mem::MaybeUninit::uninit();
@@ -378,6 +829,8 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         match result {
             Ok(()) => {
                 let data = unsafe { crate::from_val(data_val.assume_init()) };
+                record_read(txn, &key_bytes, data);
+                let data = copy_value(txn, data);
                 let data = DC::bytes_decode(data).map_err(Error::Decoding)?;
                 Ok(Some(data))
             }
@@ -386,6 +839,128 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         }
     }
 
+    /// Retrieves the value associated with a key like [`Database::get`], decoded in a value
+    /// that does not borrow the transaction.
+    ///
+    /// The value can be kept across the writes, even the writes to the same database through
+    /// the [`WriteHalf`] of a split transaction, which can reuse the pages the zero-copy values
+    /// point to.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEU32 = U32<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, BEU32> = env.create_database(&mut wtxn, Some("get-owned"))?;
+    /// db.put(&mut wtxn, "counter", &41)?;
+    ///
+    /// let (read, mut write) = wtxn.split();
+    /// let counter = db.get_owned(&read, "counter")?.unwrap();
+    /// db.put(&mut write, "counter", &(counter + 1))?;
+    /// assert_eq!(db.get_owned(&write, "counter")?, Some(42));
+    /// # Ok(()) }
+    /// ```
+    pub fn get_owned<'a>(&self, txn: &impl ReadTxn, key: &'a KC::EItem) -> Result<Option<DC::DItem>>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesDecodeOwned,
+    {
+        match self.remap_data_type::<types::Bytes>().get(txn, key)? {
+            Some(bytes) => DC::bytes_decode_owned(bytes).map(Some).map_err(Error::Decoding),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves the values associated with many keys, in the order of the keys.
+    ///
+    /// The keys are looked up in the order of the database with a single cursor, moving
+    /// forward from one key to the next instead of searching every key from the root of the
+    /// tree, which is much faster for close keys. The keys don't need to be sorted, they are
+    /// sorted with the comparator of the database first, it only has to check their order
+    /// when they already are.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEU32 = U32<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<BEU32, Str> = env.create_database(&mut wtxn, Some("get-sorted"))?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// db.put(&mut wtxn, &13, "i-am-thirteen")?;
+    /// db.put(&mut wtxn, &27, "i-am-twenty-seven")?;
+    /// db.put(&mut wtxn, &42, "i-am-forty-two")?;
+    ///
+    /// let values = db.get_sorted(&wtxn, &[42, 1, 13])?;
+    /// assert_eq!(values, [Some("i-am-forty-two"), None, Some("i-am-thirteen")]);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn get_sorted<'a, 'txn>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        keys: &'a [KC::EItem],
+    ) -> Result<Vec<Option<DC::DItem>>>
+    where
+        KC: BytesEncode<'a>,
+        KC::EItem: Sized,
+        DC: BytesDecode<'txn>,
+        C: Comparator,
+    {
+        check_env_db_txn!(self, txn);
+
+        let keys_bytes = keys.iter().map(encode_key::<KC>).collect::<Result<Vec<_>>>()?;
+        let mut order: Vec<usize> = (0..keys_bytes.len()).collect();
+        order.sort_by(|&a, &b| C::compare(&keys_bytes[a], &keys_bytes[b]));
+
+        let mut values: Vec<_> = keys.iter().map(|_| None).collect();
+        // Only the values found are recorded and copied, like with `get`.
+        let mut cursor = RoCursor::new(txn, self.dbi)?.untracked();
+        let mut found: Option<(&[u8], &[u8])> = None;
+        for index in order {
+            let key = &keys_bytes[index][..];
+            // The cursor stays on the first key greater than or equal to the previous one.
+            if found.is_none_or(|(found_key, _)| C::compare(found_key, key).is_lt()) {
+                found = cursor.move_on_key_greater_than_or_equal_to(key)?;
+            }
+            match found {
+                Some((found_key, data)) if C::compare(found_key, key).is_eq() => {
+                    record_read(txn, key, data);
+                    let data = copy_value(txn, data);
+                    values[index] = Some(DC::bytes_decode(data).map_err(Error::Decoding)?);
+                }
+                // No key is greater than or equal to this one, nor to the next ones.
+                None => break,
+                Some(_) => (),
+            }
+        }
+
+        Ok(values)
+    }
+
     /// Returns an iterator over all of the values of a single key.
     ///
     /// You can make this iterator `Send`able between threads by opening
@@ -447,10 +1022,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     where
         KC: BytesEncode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
-        let mut cursor = RoCursor::new(txn, self.dbi)?;
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let mut cursor = RoCursor::new(txn, self.dbi)?.batch_fixed_duplicates()?;
+        let key_bytes = encode_key::<KC>(key)?;
         if cursor.move_on_key(&key_bytes)? {
             Ok(Some(RoIter::new(cursor)))
         } else {
@@ -458,18 +1033,237 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         }
     }
 
-    /// Retrieves the key/value pair lower than the given one in this database.
-    ///
-    /// If the database if empty or there is no key lower than the given one,
-    /// then `None` is returned.
+    /// Returns an iterator over the values of the key starting from the value at the given
+    /// index, to read the values of a key page by page.
     ///
-    /// Comparisons are made by using the bytes representation of the key.
+    /// The values of a `DUP_FIXED` database are skipped a page at a time. Returns `None` if
+    /// the key doesn't exist or has no value at this index.
     ///
     /// ```
     /// # use std::fs;
     /// # use std::path::Path;
-    /// # use heed::EnvOpenOptions;
-    /// use heed::Database;
+    /// # use heed::{DatabaseFlags, EnvOpenOptions};
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEI64 = I64<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.database_options()
+    ///     .types::<BEI64, BEI64>()
+    ///     .flags(DatabaseFlags::DUP_SORT)
+    ///     .name("dup-sort")
+    ///     .create(&mut wtxn)?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// for value in 0..10 {
+    ///     db.put(&mut wtxn, &68, &value)?;
+    /// }
+    ///
+    /// let mut page = db.get_duplicates_from(&wtxn, &68, 4)?.expect("the key has a fifth value");
+    /// assert_eq!(page.next().transpose()?, Some((68, 4)));
+    /// assert_eq!(page.next().transpose()?, Some((68, 5)));
+    /// drop(page);
+    /// assert!(db.get_duplicates_from(&wtxn, &68, 10)?.is_none());
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn get_duplicates_from<'a, 'txn>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        key: &'a KC::EItem,
+        index: usize,
+    ) -> Result<Option<RoIter<'txn, KC, DC, MoveOnCurrentKeyDuplicates>>>
+    where
+        KC: BytesEncode<'a>,
+    {
+        check_env_db_txn!(self, txn);
+
+        let mut cursor = RoCursor::new(txn, self.dbi)?.batch_fixed_duplicates()?;
+        let key_bytes = encode_key::<KC>(key)?;
+        if !cursor.move_on_key(&key_bytes)? {
+            return Ok(None);
+        }
+
+        // The iterator starts after the value preceding the index.
+        match index.checked_sub(1) {
+            None => Ok(Some(RoIter::new(cursor))),
+            Some(_) if index >= cursor.count_duplicates()? => Ok(None),
+            Some(previous) => match cursor.move_on_dup_index(previous)? {
+                Some(_) => Ok(Some(RoIter::new_after_current(cursor))),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Returns an iterator over the keys of this `DUP_SORT` database, with an iterator over
+    /// the values of each key.
+    ///
+    /// The keys are sorted with the comparator `C` and the values with the comparator `CDUP`.
+    /// Fails with [`MdbError::Incompatible`] if the database was created without the
+    /// [`DatabaseFlags::DUP_SORT`] flag.
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::Path;
+    /// # use heed::{DatabaseFlags, EnvOpenOptions};
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEI64 = I64<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.database_options()
+    ///     .types::<BEI64, BEI64>()
+    ///     .flags(DatabaseFlags::DUP_SORT)
+    ///     .name("dup-sort")
+    ///     .create(&mut wtxn)?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// db.put(&mut wtxn, &68, &120)?;
+    /// db.put(&mut wtxn, &68, &121)?;
+    /// db.put(&mut wtxn, &35, &120)?;
+    /// db.put(&mut wtxn, &92, &32)?;
+    /// db.put(&mut wtxn, &92, &31)?;
+    ///
+    /// let mut groups = Vec::new();
+    /// for result in db.iter_groups(&wtxn)? {
+    ///     let (key, values) = result?;
+    ///     groups.push((key, values.collect::<heed::Result<Vec<_>>>()?));
+    /// }
+    /// assert_eq!(groups, [(35, vec![120]), (68, vec![120, 121]), (92, vec![31, 32])]);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn iter_groups<'txn>(&self, txn: &'txn impl ReadTxn) -> Result<RoGroups<'txn, KC, DC>> {
+        check_env_db_txn!(self, txn);
+
+        if !self.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT) {
+            return Err(MdbError::Incompatible.into());
+        }
+
+        RoCursor::new(txn, self.dbi)?.batch_fixed_duplicates().map(RoGroups::new)
+    }
+
+    /// Returns whether the key is associated with the given value in this database.
+    ///
+    /// The value is compared in its encoded form and the stored values are never decoded.
+    /// The value of a `DUP_SORT` database is searched among the duplicates of the key with
+    /// the comparator `CDUP`, the value of another database is compared byte by byte.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::{Database, DatabaseFlags};
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEI64 = I64<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.database_options()
+    ///     .types::<BEI64, BEI64>()
+    ///     .flags(DatabaseFlags::DUP_SORT)
+    ///     .name("dup-sort")
+    ///     .create(&mut wtxn)?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// db.put(&mut wtxn, &68, &120)?;
+    /// db.put(&mut wtxn, &68, &121)?;
+    /// db.put(&mut wtxn, &35, &120)?;
+    ///
+    /// assert!(db.contains(&wtxn, &68, &121)?);
+    /// assert!(!db.contains(&wtxn, &68, &122)?);
+    /// assert!(!db.contains(&wtxn, &42, &120)?);
+    ///
+    /// let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("plain"))?;
+    /// db.put(&mut wtxn, "hello", "world")?;
+    /// assert!(db.contains(&wtxn, "hello", "world")?);
+    /// assert!(!db.contains(&wtxn, "hello", "kitty")?);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn contains<'a>(
+        &self,
+        txn: &impl ReadTxn,
+        key: &'a KC::EItem,
+        data: &'a DC::EItem,
+    ) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        check_env_db_txn!(self, txn);
+
+        let key_bytes = encode_key::<KC>(key)?;
+        let data_bytes = encode_key::<DC>(data)?;
+
+        // LMDB only compares the values of the `DUP_SORT` databases.
+        if self.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT) {
+            let mut cursor = RoCursor::new(txn, self.dbi)?;
+            cursor.move_on_key_value(&key_bytes, &data_bytes)
+        } else {
+            let db = self.remap_types::<types::Bytes, types::Bytes>();
+            Ok(db.get(txn, &key_bytes)?.is_some_and(|stored| stored == &data_bytes[..]))
+        }
+    }
+
+    /// Returns the number of values of the key of this `DUP_SORT` database,
+    /// zero if the key doesn't exist.
+    pub(crate) fn count_duplicates<'a>(
+        &self,
+        txn: &impl ReadTxn,
+        key: &'a KC::EItem,
+    ) -> Result<usize>
+    where
+        KC: BytesEncode<'a>,
+    {
+        check_env_db_txn!(self, txn);
+
+        let mut cursor = RoCursor::new(txn, self.dbi)?;
+        let key_bytes = encode_key::<KC>(key)?;
+        if cursor.move_on_key(&key_bytes)? {
+            cursor.count_duplicates()
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Retrieves the key/value pair lower than the given one in this database.
+    ///
+    /// If the database if empty or there is no key lower than the given one,
+    /// then `None` is returned.
+    ///
+    /// Comparisons are made by using the bytes representation of the key.
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::Path;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
     /// use heed::types::*;
     /// use heed::byteorder::BigEndian;
     ///
@@ -511,10 +1305,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a> + BytesDecode<'txn>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut cursor = RoCursor::new(txn, self.dbi)?;
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         cursor.move_on_key_greater_than_or_equal_to(&key_bytes)?;
 
         match cursor.move_on_prev(MoveOperation::NoDup) {
@@ -580,10 +1374,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a> + BytesDecode<'txn>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut cursor = RoCursor::new(txn, self.dbi)?;
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         let result = match cursor.move_on_key_greater_than_or_equal_to(&key_bytes) {
             Ok(Some((key, data))) if key == &key_bytes[..] => Ok(Some((key, data))),
             Ok(_) => cursor.move_on_prev(MoveOperation::NoDup),
@@ -653,10 +1447,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a> + BytesDecode<'txn>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut cursor = RoCursor::new(txn, self.dbi)?;
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         let entry = match cursor.move_on_key_greater_than_or_equal_to(&key_bytes)? {
             Some((key, data)) if key > &key_bytes[..] => Some((key, data)),
             Some((_key, _data)) => cursor.move_on_next(MoveOperation::NoDup)?,
@@ -725,10 +1519,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a> + BytesDecode<'txn>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut cursor = RoCursor::new(txn, self.dbi)?;
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         match cursor.move_on_key_greater_than_or_equal_to(&key_bytes) {
             Ok(Some((key, data))) => match (KC::bytes_decode(key), DC::bytes_decode(data)) {
                 (Ok(key), Ok(data)) => Ok(Some((key, data))),
@@ -780,7 +1574,7 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesDecode<'txn>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut cursor = RoCursor::new(txn, self.dbi)?;
         match cursor.move_on_first(MoveOperation::Any) {
@@ -834,7 +1628,7 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesDecode<'txn>,
         DC: BytesDecode<'txn>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut cursor = RoCursor::new(txn, self.dbi)?;
         match cursor.move_on_last(MoveOperation::Any) {
@@ -972,7 +1766,7 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     /// # Ok(()) }
     /// ```
     pub fn stat(&self, txn: &impl ReadTxn) -> Result<DatabaseStat> {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         let mut db_stat = mem::MaybeUninit::uninit();
         let result = unsafe {
@@ -1037,8 +1831,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     /// # Ok(()) }
     /// ```
     pub fn iter<'txn>(&self, txn: &'txn impl ReadTxn) -> Result<RoIter<'txn, KC, DC>> {
-        assert_eq_env_db_txn!(self, txn);
-        RoCursor::new(txn, self.dbi).map(|cursor| RoIter::new(cursor))
+        check_env_db_txn!(self, txn);
+        RoCursor::new(txn, self.dbi)
+            .and_then(RoCursor::batch_fixed_duplicates)
+            .map(|cursor| RoIter::new(cursor))
     }
 
     /// Return a reverse ordered iterator of all key-value pairs in this database.
@@ -1083,14 +1879,15 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     /// # Ok(()) }
     /// ```
     pub fn rev_iter<'txn>(&self, txn: &'txn impl ReadTxn) -> Result<RoRevIter<'txn, KC, DC>> {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
         RoCursor::new(txn, self.dbi).map(|cursor| RoRevIter::new(cursor))
     }
 
     /// Return an ordered iterator of a range of key-value pairs in this database.
     ///
-    /// Comparisons are made by using the comparator `C`.
+    /// Comparisons are made by using the comparator `C`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
     ///
     /// You can make this iterator `Send`able between threads by opening
     /// the environment with the [`EnvOpenOptions::read_txn_without_tls`]
@@ -1178,40 +1975,22 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     ) -> Result<RoRange<'txn, KC, DC, C>>
     where
         KC: BytesEncode<'a>,
+        C: Comparator,
         R: RangeBounds<KC::EItem>,
     {
-        assert_eq_env_db_txn!(self, txn);
-
-        let start_bound = match range.start_bound() {
-            Bound::Included(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Included(bytes.into_owned())
-            }
-            Bound::Excluded(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Excluded(bytes.into_owned())
-            }
-            Bound::Unbounded => Bound::Unbounded,
-        };
+        check_env_db_txn!(self, txn);
 
-        let end_bound = match range.end_bound() {
-            Bound::Included(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Included(bytes.into_owned())
-            }
-            Bound::Excluded(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Excluded(bytes.into_owned())
-            }
-            Bound::Unbounded => Bound::Unbounded,
-        };
+        let (start_bound, end_bound) = encode_range::<KC, C, _>(range)?;
 
-        RoCursor::new(txn, self.dbi).map(|cursor| RoRange::new(cursor, start_bound, end_bound))
+        RoCursor::new(txn, self.dbi)
+            .and_then(RoCursor::batch_fixed_duplicates)
+            .map(|cursor| RoRange::new(cursor, start_bound, end_bound))
     }
 
     /// Return a reverse ordered iterator of a range of key-value pairs in this database.
     ///
-    /// Comparisons are made by using the comparator `C`.
+    /// Comparisons are made by using the comparator `C`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
     ///
     /// You can make this iterator `Send`able between threads by opening
     /// the environment with the [`EnvOpenOptions::read_txn_without_tls`]
@@ -1260,33 +2039,12 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     ) -> Result<RoRevRange<'txn, KC, DC, C>>
     where
         KC: BytesEncode<'a>,
+        C: Comparator,
         R: RangeBounds<KC::EItem>,
     {
-        assert_eq_env_db_txn!(self, txn);
-
-        let start_bound = match range.start_bound() {
-            Bound::Included(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Included(bytes.into_owned())
-            }
-            Bound::Excluded(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Excluded(bytes.into_owned())
-            }
-            Bound::Unbounded => Bound::Unbounded,
-        };
+        check_env_db_txn!(self, txn);
 
-        let end_bound = match range.end_bound() {
-            Bound::Included(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Included(bytes.into_owned())
-            }
-            Bound::Excluded(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Excluded(bytes.into_owned())
-            }
-            Bound::Unbounded => Bound::Unbounded,
-        };
+        let (start_bound, end_bound) = encode_range::<KC, C, _>(range)?;
 
         RoCursor::new(txn, self.dbi).map(|cursor| RoRevRange::new(cursor, start_bound, end_bound))
     }
@@ -1346,11 +2104,12 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         C: LexicographicComparator,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
-        let prefix_bytes = KC::bytes_encode(prefix).map_err(Error::Encoding)?;
-        let prefix_bytes = prefix_bytes.into_owned();
-        RoCursor::new(txn, self.dbi).map(|cursor| RoPrefix::new(cursor, prefix_bytes))
+        let prefix_bytes = encode_key::<KC>(prefix)?;
+        RoCursor::new(txn, self.dbi)
+            .and_then(RoCursor::batch_fixed_duplicates)
+            .map(|cursor| RoPrefix::new(cursor, prefix_bytes))
     }
 
     /// Return a reversed lexicographically ordered iterator of all key-value pairs
@@ -1408,10 +2167,9 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         C: LexicographicComparator,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_txn!(self, txn);
 
-        let prefix_bytes = KC::bytes_encode(prefix).map_err(Error::Encoding)?;
-        let prefix_bytes = prefix_bytes.into_owned();
+        let prefix_bytes = encode_key::<KC>(prefix)?;
         RoCursor::new(txn, self.dbi).map(|cursor| RoRevPrefix::new(cursor, prefix_bytes))
     }
 
@@ -1455,14 +2213,19 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         DC: BytesEncode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
-        let data_bytes: Cow<[u8]> = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        let mut buffer = take_scratch(txn);
+        KC::bytes_encode_into(key, &mut buffer).map_err(Error::Encoding)?;
+        let key_len = buffer.len();
+        DC::bytes_encode_into(data, &mut buffer).map_err(Error::Encoding)?;
+        let (key_bytes, data_bytes) = buffer.split_at(key_len);
+        self.check_sizes(key_bytes, data_bytes.len())?;
 
-        let mut key_val = unsafe { crate::into_val(&key_bytes) };
-        let mut data_val = unsafe { crate::into_val(&data_bytes) };
+        let mut key_val = unsafe { crate::into_val(key_bytes) };
+        let mut data_val = unsafe { crate::into_val(data_bytes) };
         let flags = 0;
+        let old = self.value_before_put(txn, key_bytes, Some(data_bytes))?;
 
         unsafe {
             mdb_result(ffi::mdb_put(
@@ -1473,7 +2236,12 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
                 flags,
             ))?
         }
+        record_written(txn, key_bytes, data_bytes.len());
 
+        if let Some(old) = old {
+            self.record_change(txn, key_bytes, old, Some(data_bytes));
+        }
+        give_back_scratch(txn, buffer);
         Ok(())
     }
 
@@ -1524,12 +2292,15 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         F: FnOnce(&mut ReservedSpace) -> io::Result<()>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let mut key_bytes = take_scratch(txn);
+        KC::bytes_encode_into(key, &mut key_bytes).map_err(Error::Encoding)?;
+        self.check_sizes(&key_bytes, data_size)?;
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
         let mut reserved = ffi::reserve_size_val(data_size);
         let flags = ffi::MDB_RESERVE;
+        let old = self.value_before_put(txn, &key_bytes, None)?;
 
         unsafe {
             mdb_result(ffi::mdb_put(
@@ -1540,14 +2311,19 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
                 flags,
             ))?
         }
+        record_written(txn, &key_bytes, data_size);
 
         let mut reserved = unsafe { ReservedSpace::from_val(reserved) };
         write_func(&mut reserved)?;
-        if reserved.remaining() == 0 {
-            Ok(())
-        } else {
-            Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        if reserved.remaining() != 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        if let Some(old) = old {
+            self.record_reserved_put(txn, &key_bytes, old)?;
         }
+        give_back_scratch(txn, key_bytes);
+        Ok(())
     }
 
     /// Insert a key-value pair in this database, replacing any previous value. The entry is
@@ -1620,14 +2396,16 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         DC: BytesEncode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         let data_bytes: Cow<[u8]> = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        self.check_sizes(&key_bytes, data_bytes.len())?;
 
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
         let mut data_val = unsafe { crate::into_val(&data_bytes) };
         let flags = flags.bits();
+        let old = self.value_before_put(txn, &key_bytes, Some(&data_bytes))?;
 
         unsafe {
             mdb_result(ffi::mdb_put(
@@ -1638,10 +2416,79 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
                 flags,
             ))?
         }
+        record_written(txn, &key_bytes, data_bytes.len());
 
+        if let Some(old) = old {
+            self.record_change(txn, &key_bytes, old, Some(&data_bytes));
+        }
         Ok(())
     }
 
+    /// Inserts a value of the key in this `DUP_SORT` database, if the key doesn't already
+    /// have this value.
+    ///
+    /// Returns `false` if the key already had this value, rather than the
+    /// [`MdbError::KeyExist`] error of the [`PutFlags::NO_DUP_DATA`] flag it uses.
+    /// Fails with [`MdbError::Incompatible`] if the database was created without the
+    /// [`DatabaseFlags::DUP_SORT`] flag.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::DatabaseFlags;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEI64 = I64<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.database_options()
+    ///     .types::<BEI64, BEI64>()
+    ///     .flags(DatabaseFlags::DUP_SORT)
+    ///     .name("dup-sort")
+    ///     .create(&mut wtxn)?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// assert!(db.put_if_new_dup(&mut wtxn, &68, &120)?);
+    /// assert!(db.put_if_new_dup(&mut wtxn, &68, &121)?);
+    /// assert!(!db.put_if_new_dup(&mut wtxn, &68, &120)?);
+    ///
+    /// let values: Vec<_> = db.get_duplicates(&wtxn, &68)?.unwrap().collect::<heed::Result<_>>()?;
+    /// assert_eq!(values, [(68, 120), (68, 121)]);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn put_if_new_dup<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        data: &'a DC::EItem,
+    ) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        check_env_db_wtxn!(self, txn);
+
+        // Without duplicates LMDB ignores the flag and overwrites the value.
+        if !self.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT) {
+            return Err(MdbError::Incompatible.into());
+        }
+
+        match self.put_with_flags(txn, PutFlags::NO_DUP_DATA, key, data) {
+            Ok(()) => Ok(true),
+            Err(Error::Mdb(MdbError::KeyExist)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Attempt to insert a key-value pair in this database, or if a value already exists for the
     /// key, returns the previous value.
     ///
@@ -1733,10 +2580,11 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         DC: BytesEncode<'a> + BytesDecode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         let data_bytes: Cow<[u8]> = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        self.check_sizes(&key_bytes, data_bytes.len())?;
 
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
         let mut data_val = unsafe { crate::into_val(&data_bytes) };
@@ -1754,10 +2602,15 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
 
         match result {
             // the value was successfully inserted
-            Ok(()) => Ok(None),
+            Ok(()) => {
+                record_written(txn, &key_bytes, data_bytes.len());
+                self.record_change(txn, &key_bytes, None, Some(&data_bytes));
+                Ok(None)
+            }
             // the key already exists: the previous value is stored in the data parameter
             Err(MdbError::KeyExist) => {
                 let bytes = unsafe { crate::from_val(data_val) };
+                record_read(txn, &key_bytes, bytes);
                 let data = DC::bytes_decode(bytes).map_err(Error::Decoding)?;
                 Ok(Some(data))
             }
@@ -1890,9 +2743,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         F: FnOnce(&mut ReservedSpace) -> io::Result<()>,
         DC: BytesDecode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
+        self.check_sizes(&key_bytes, data_size)?;
 
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
         let mut reserved = ffi::reserve_size_val(data_size);
@@ -1913,15 +2767,17 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
             Ok(()) => {
                 let mut reserved = unsafe { ReservedSpace::from_val(reserved) };
                 write_func(&mut reserved)?;
-                if reserved.remaining() == 0 {
-                    Ok(None)
-                } else {
-                    Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+                if reserved.remaining() != 0 {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
                 }
+                record_written(txn, &key_bytes, data_size);
+                self.record_reserved_put(txn, &key_bytes, None)?;
+                Ok(None)
             }
             // the key already exists: the previous value is stored in the data parameter
             Err(MdbError::KeyExist) => {
                 let bytes = unsafe { crate::from_val(reserved) };
+                record_read(txn, &key_bytes, bytes);
                 let data = DC::bytes_decode(bytes).map_err(Error::Decoding)?;
                 Ok(Some(data))
             }
@@ -1976,10 +2832,11 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     where
         KC: BytesEncode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
+        let values = self.values_before_delete(txn, &key_bytes)?;
 
         let result = unsafe {
             mdb_result(ffi::mdb_del(
@@ -1991,7 +2848,12 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         };
 
         match result {
-            Ok(()) => Ok(true),
+            Ok(()) => {
+                for value in values.into_iter().flatten() {
+                    self.record_change(txn, &key_bytes, Some(value), None);
+                }
+                Ok(true)
+            }
             Err(e) if e.not_found() => Ok(false),
             Err(e) => Err(e.into()),
         }
@@ -2066,9 +2928,9 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         KC: BytesEncode<'a>,
         DC: BytesEncode<'a>,
     {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn);
 
-        let key_bytes: Cow<[u8]> = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let key_bytes = encode_key::<KC>(key)?;
         let data_bytes: Cow<[u8]> = DC::bytes_encode(data).map_err(Error::Encoding)?;
         let mut key_val = unsafe { crate::into_val(&key_bytes) };
         let mut data_val = unsafe { crate::into_val(&data_bytes) };
@@ -2083,7 +2945,10 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         };
 
         match result {
-            Ok(()) => Ok(true),
+            Ok(()) => {
+                self.record_change(txn, &key_bytes, Some(data_bytes.to_vec()), None);
+                Ok(true)
+            }
             Err(e) if e.not_found() => Ok(false),
             Err(e) => Err(e.into()),
         }
@@ -2093,7 +2958,8 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     ///
     /// Prefer using [`clear`] instead of a call to this method with a full range ([`..`]).
     ///
-    /// Comparisons are made by using the comparator `C`.
+    /// Comparisons are made by using the comparator `C`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
     ///
     /// [`clear`]: crate::Database::clear
     /// [`..`]: std::ops::RangeFull
@@ -2144,89 +3010,241 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
         C: Comparator,
         R: RangeBounds<KC::EItem>,
     {
-        assert_eq_env_db_txn!(self, txn);
-
-        let start_bound = match range.start_bound() {
-            Bound::Included(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Included(bytes.into_owned())
-            }
-            Bound::Excluded(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Excluded(bytes.into_owned())
-            }
-            Bound::Unbounded => Bound::Unbounded,
-        };
-
-        let end_bound = match range.end_bound() {
-            Bound::Included(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Included(bytes.into_owned())
-            }
-            Bound::Excluded(bound) => {
-                let bytes = KC::bytes_encode(bound).map_err(Error::Encoding)?;
-                Bound::Excluded(bytes.into_owned())
-            }
-            Bound::Unbounded => Bound::Unbounded,
-        };
-
-        let mut cursor = RwCursor::new(txn, self.dbi)?;
-        let mut count = 0;
+        check_env_db_wtxn!(self, txn);
 
-        // Move to range start
-        let first = match &start_bound {
-            Bound::Included(start) => cursor.move_on_key_greater_than_or_equal_to(start),
-            Bound::Excluded(start) => {
-                match cursor.move_on_key_greater_than_or_equal_to(start)? {
-                    Some((key, _)) if key == start.as_slice() => {
-                        cursor.move_on_next(MoveOperation::NoDup)
-                    }
-                    result => Ok(result),
-                }
-            }
-            Bound::Unbounded => cursor.move_on_first(MoveOperation::NoDup),
-        };
+        let (start_bound, end_bound) = encode_range::<KC, C, _>(range)?;
 
-        match first {
-            Ok(Some((key, _))) => {
-                let in_bounds = match &end_bound {
-                    Bound::Included(end) => C::compare(key, end).is_le(),
-                    Bound::Excluded(end) => C::compare(key, end).is_lt(),
-                    Bound::Unbounded => true,
-                };
-                if !in_bounds {
-                    return Ok(0);
-                }
-            }
-            Ok(None) => return Ok(0),
-            Err(e) => return Err(e),
-        }
+        let start = start_bound.as_ref().map(|start| &start[..]);
+        self.delete_keys(txn, start, |key| match &end_bound {
+            Bound::Included(end) => C::compare(key, end).is_le(),
+            Bound::Excluded(end) => C::compare(key, end).is_lt(),
+            Bound::Unbounded => true,
+        })
+    }
 
-        // Delete entries while within the range
-        loop {
+    /// Deletes all the key-value pairs in this database whose keys start with the given prefix.
+    ///
+    /// Returns the number of deleted entries.
+    ///
+    /// Comparisons are made by using the bytes representation of the key.
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::Path;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, I32<BigEndian>>(&mut wtxn, Some("delete-prefix"))?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// db.put(&mut wtxn, "all-forty-two", &42)?;
+    /// db.put(&mut wtxn, "all-twenty-seven", &27)?;
+    /// db.put(&mut wtxn, "i-am-thirteen", &13)?;
+    /// db.put(&mut wtxn, "all-five-hundred-and-twenty-one", &521)?;
+    ///
+    /// let ret = db.delete_prefix(&mut wtxn, "all")?;
+    /// assert_eq!(ret, 3);
+    ///
+    /// let mut iter = db.iter(&wtxn)?;
+    /// assert_eq!(iter.next().transpose()?, Some(("i-am-thirteen", 13)));
+    /// assert_eq!(iter.next().transpose()?, None);
+    ///
+    /// drop(iter);
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn delete_prefix<'a>(&self, txn: &mut impl WriteTxn, prefix: &'a KC::EItem) -> Result<usize>
+    where
+        KC: BytesEncode<'a>,
+        C: LexicographicComparator,
+    {
+        check_env_db_wtxn!(self, txn);
+
+        let prefix_bytes = encode_key::<KC>(prefix)?;
+        self.delete_keys(txn, Bound::Included(&prefix_bytes), |key| {
+            starts_with::<C>(key, &prefix_bytes)
+        })
+    }
+
+    /// Deletes a range of values of a key in this `DUP_SORT` database, the values outside
+    /// of the range are kept.
+    ///
+    /// Returns the number of deleted values. Fails with [`MdbError::Incompatible`] if the
+    /// database was created without the [`DatabaseFlags::DUP_SORT`] flag.
+    ///
+    /// Comparisons are made by using the comparator `CDUP`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::Path;
+    /// # use heed::{DatabaseFlags, EnvOpenOptions};
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEI64 = I64<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.database_options()
+    ///     .types::<BEI64, BEI64>()
+    ///     .flags(DatabaseFlags::DUP_SORT)
+    ///     .name("dup-sort")
+    ///     .create(&mut wtxn)?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// db.put(&mut wtxn, &68, &120)?;
+    /// db.put(&mut wtxn, &68, &121)?;
+    /// db.put(&mut wtxn, &68, &122)?;
+    /// db.put(&mut wtxn, &68, &123)?;
+    /// db.put(&mut wtxn, &92, &121)?;
+    ///
+    /// assert_eq!(db.delete_dup_range(&mut wtxn, &68, &(..=121))?, 2);
+    /// assert_eq!(db.delete_dup_range(&mut wtxn, &68, &(200..))?, 0);
+    ///
+    /// let mut iter = db.iter(&wtxn)?;
+    /// assert_eq!(iter.next().transpose()?, Some((68, 122)));
+    /// assert_eq!(iter.next().transpose()?, Some((68, 123)));
+    /// assert_eq!(iter.next().transpose()?, Some((92, 121)));
+    /// assert_eq!(iter.next().transpose()?, None);
+    /// drop(iter);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn delete_dup_range<'a, R>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        range: &'a R,
+    ) -> Result<usize>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+        C: Comparator,
+        CDUP: Comparator,
+        R: RangeBounds<DC::EItem>,
+    {
+        check_env_db_wtxn!(self, txn);
+
+        // LMDB can only position the cursor on a value in the DUP_SORT databases.
+        if !self.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT) {
+            return Err(MdbError::Incompatible.into());
+        }
+
+        let key_bytes = encode_key::<KC>(key)?;
+        let (start_bound, end_bound) = encode_range::<DC, CDUP, _>(range)?;
+        let in_bounds = |data: &[u8]| match &end_bound {
+            Bound::Included(end) => CDUP::compare(data, end).is_le(),
+            Bound::Excluded(end) => CDUP::compare(data, end).is_lt(),
+            Bound::Unbounded => true,
+        };
+
+        let record = txn.change_recorder().is_some();
+        let mut cursor = RwCursor::new(txn, self.dbi)?.record_deletions(record);
+        let mut count = 0;
+
+        // Move to the first value of the range
+        let first = match &start_bound {
+            Bound::Included(start) => {
+                cursor.move_on_key_value_greater_than_or_equal_to(&key_bytes, start)?
+            }
+            Bound::Excluded(start) => {
+                match cursor.move_on_key_value_greater_than_or_equal_to(&key_bytes, start)? {
+                    Some(data) if CDUP::compare(data, start).is_eq() => {
+                        cursor.move_on_next(MoveOperation::Dup)?.map(|(_, data)| data)
+                    }
+                    first => first,
+                }
+            }
+            Bound::Unbounded => match cursor.move_on_key(&key_bytes)? {
+                true => cursor.current()?.map(|(_, data)| data),
+                false => None,
+            },
+        };
+
+        // Delete the values while within the range
+        let mut in_range = first.is_some_and(in_bounds);
+        while in_range {
             // safety: We do not keep any reference from the database while using `del_current`.
             //         The user can't keep any reference inside of the database as we ask for a
             //         mutable reference to the `txn`.
             unsafe { cursor.del_current()? };
             count += 1;
 
+            // After deletion, cursor moves to the next value, or the next key once the last
+            // value of the key is deleted
+            in_range = cursor
+                .current()?
+                .is_some_and(|(key, data)| C::compare(key, &key_bytes).is_eq() && in_bounds(data));
+        }
+
+        for (key, data) in cursor.into_deleted() {
+            self.record_change(txn, &key, Some(data), None);
+        }
+        Ok(count)
+    }
+
+    /// Deletes the entries from the first key after the start bound while `in_bounds`
+    /// accepts their keys, and returns the number of deleted entries.
+    ///
+    /// The keys are read without their values and all the values of a key are deleted at
+    /// once in the `DUP_SORT` databases, unless the changes are recorded, as they need the
+    /// deleted values.
+    fn delete_keys(
+        &self,
+        txn: &mut impl WriteTxn,
+        start: Bound<&[u8]>,
+        in_bounds: impl Fn(&[u8]) -> bool,
+    ) -> Result<usize> {
+        use ffi::cursor_op::{MDB_FIRST, MDB_GET_CURRENT, MDB_NEXT_NODUP, MDB_SET_RANGE};
+
+        let dup_sort = self.database_flags(txn)?.contains(DatabaseFlags::DUP_SORT);
+        let record = txn.change_recorder().is_some();
+        let mut cursor = RwCursor::new(txn, self.dbi)?.record_deletions(record);
+        let mut count = 0;
+
+        // Move to range start
+        let first = match start {
+            Bound::Included(start) => cursor.move_key_only(MDB_SET_RANGE, start)?,
+            Bound::Excluded(start) => match cursor.move_key_only(MDB_SET_RANGE, start)? {
+                Some(key) if key == start => cursor.move_key_only(MDB_NEXT_NODUP, &[])?,
+                first => first,
+            },
+            Bound::Unbounded => cursor.move_key_only(MDB_FIRST, &[])?,
+        };
+
+        // Delete entries while within the range
+        let mut in_range = first.is_some_and(&in_bounds);
+        while in_range {
+            // safety: We do not keep any reference from the database while using `del_current`.
+            //         The user can't keep any reference inside of the database as we ask for a
+            //         mutable reference to the `txn`.
+            count += unsafe { cursor.del_current_key(dup_sort)? };
+
             // After deletion, cursor moves to next entry automatically
-            match cursor.current() {
-                Ok(Some((key, _))) => {
-                    let in_bounds = match &end_bound {
-                        Bound::Included(end) => C::compare(key, end).is_le(),
-                        Bound::Excluded(end) => C::compare(key, end).is_lt(),
-                        Bound::Unbounded => true,
-                    };
-                    if !in_bounds {
-                        break;
-                    }
-                }
-                Ok(None) => break,
-                Err(e) => return Err(e),
-            }
+            in_range = cursor.move_key_only(MDB_GET_CURRENT, &[])?.is_some_and(&in_bounds);
         }
 
+        for (key, data) in cursor.into_deleted() {
+            self.record_change(txn, &key, Some(data), None);
+        }
         Ok(count)
     }
 
@@ -2272,11 +3290,15 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     /// # Ok(()) }
     /// ```
     pub fn clear(&self, txn: &mut impl WriteTxn) -> Result<()> {
-        assert_eq_env_db_txn!(self, txn);
+        check_env_db_wtxn!(self, txn, drop);
 
-        unsafe {
-            mdb_result(ffi::mdb_drop(txn.txn_ptr().as_mut(), self.dbi, 0)).map_err(Into::into)
+        let entries = self.entries_before_clear(txn)?;
+        unsafe { mdb_result(ffi::mdb_drop(txn.txn_ptr().as_mut(), self.dbi, 0))? };
+
+        for (key, data) in entries.into_iter().flatten() {
+            self.record_change(txn, &key, Some(data), None);
         }
+        Ok(())
     }
 
     /// Removes this database entirely.
@@ -2348,11 +3370,15 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     /// # Ok(()) }
     /// ```
     pub unsafe fn remove(self, rwtxn: &mut impl WriteTxn) -> Result<()> {
-        assert_eq_env_db_txn!(self, rwtxn);
+        check_env_db_wtxn!(self, rwtxn, drop);
 
-        unsafe {
-            mdb_result(ffi::mdb_drop(rwtxn.txn_ptr().as_mut(), self.dbi, 1)).map_err(Into::into)
+        let entries = self.entries_before_clear(rwtxn)?;
+        unsafe { mdb_result(ffi::mdb_drop(rwtxn.txn_ptr().as_mut(), self.dbi, 1))? };
+
+        for (key, data) in entries.into_iter().flatten() {
+            self.record_change(rwtxn, &key, Some(data), None);
         }
+        Ok(())
     }
 
     /// Change the codec types of this database, specifying the codecs.
@@ -2397,7 +3423,7 @@ impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
     /// # Ok(()) }
     /// ```
     pub fn remap_types<KC2, DC2>(&self) -> Database<KC2, DC2, C> {
-        Database::new(self.env_ident, self.dbi)
+        Database::new(self.env_generation, self.dbi)
     }
 
     /// Change the key codec type of this database, specifying the new codec.
@@ -2461,6 +3487,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn scratch_buffer_reuse() -> Result<()> {
+        use std::io::Write;
+
+        type BEU32 = U32<BigEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+        let mut txn = env.write_txn()?;
+        let db = env.create_database::<BEU32, Str>(&mut txn, None)?;
+
+        db.put(&mut txn, &1, "one")?;
+        let scratch = txn.scratch_buffer().unwrap().take();
+        assert!(scratch.is_empty() && scratch.capacity() >= 7);
+        txn.scratch_buffer().unwrap().set(scratch);
+
+        db.put(&mut txn, &2, "two")?;
+        db.put_reserved(&mut txn, &3, 5, |reserved| reserved.write_all(b"three"))?;
+        let values: Vec<_> = db.range(&txn, &(2..))?.collect::<Result<_>>()?;
+        assert_eq!(values, [(2, "two"), (3, "three")]);
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(feature = "longer-keys")]
     fn longer_keys() -> Result<()> {
@@ -2511,4 +3561,467 @@ mod tests {
         assert_eq!(i, range.end - range.start);
         Ok(())
     }
+
+    #[test]
+    fn get_sorted() -> Result<()> {
+        type NEU32 = U32<NativeEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+        let mut txn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<NEU32, NEU32>()
+            .key_comparator::<IntegerComparator>()
+            .create(&mut txn)?;
+
+        for i in (0..1000).step_by(10) {
+            db.put(&mut txn, &i, &(i * 2))?;
+        }
+
+        let keys = [990, 5, 10, 10, 0, 256, 1000, 300];
+        let values = db.get_sorted(&txn, &keys)?;
+        let expected: Vec<_> = keys.iter().map(|key| db.get(&txn, key).unwrap()).collect();
+        assert_eq!(values, expected);
+        assert_eq!(values, [Some(1980), None, Some(20), Some(20), Some(0), None, None, Some(600)]);
+
+        assert!(db.get_sorted(&txn, &[])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn get_sorted_through_a_split() -> Result<()> {
+        type BEU32 = U32<BigEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let mut options = EnvOpenOptions::new();
+        unsafe { options.max_dbs(10).flags(EnvFlags::WRITE_MAP) };
+        let env = unsafe { options.open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db: Database<BEU32, Str> = env.create_database(&mut wtxn, Some("db"))?;
+        db.put(&mut wtxn, &1, "one")?;
+        db.put(&mut wtxn, &2, "two")?;
+
+        let (read, mut write) = wtxn.split();
+        let values = db.get_sorted(&read, &[2, 3, 1])?;
+        // The values of the same size are overwritten in place in the memory map.
+        db.put(&mut write, &1, "uno")?;
+        db.put(&mut write, &2, "dos")?;
+        assert_eq!(values, [Some("two"), None, Some("one")]);
+        assert_eq!(db.get_sorted(&read, &[1, 2])?, [Some("uno"), Some("dos")]);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_dup_sort_keys() -> Result<()> {
+        type BEU16 = U16<BigEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+        let mut txn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<BEU16, BEU16>()
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut txn)?;
+
+        for key in [0x0101, 0x0102, 0x0201, 0x0202, 0x0301] {
+            for i in 0..3 {
+                db.put(&mut txn, &key, &i)?;
+            }
+        }
+
+        // Every value of the deleted keys is counted.
+        let bytes_db = db.remap_key_type::<Bytes>();
+        assert_eq!(bytes_db.delete_prefix(&mut txn, &[0x01][..])?, 6);
+        assert_eq!(bytes_db.delete_prefix(&mut txn, &[0x01][..])?, 0);
+        assert_eq!(db.delete_range(&mut txn, &(0x0200..0x0300))?, 6);
+        assert_eq!(db.delete_range(&mut txn, &(0x0400..))?, 0);
+
+        let keys: Vec<_> =
+            db.iter(&txn)?.map(|entry| entry.map(|(key, _)| key)).collect::<Result<_>>()?;
+        assert_eq!(keys, [0x0301, 0x0301, 0x0301]);
+        assert_eq!(db.delete_range(&mut txn, &(..))?, 3);
+        assert!(db.is_empty(&txn)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_dup_range_of_a_key() -> Result<()> {
+        type BEU16 = U16<BigEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut txn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<BEU16, BEU16>()
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut txn)?;
+
+        for key in [1, 2, 3] {
+            for i in 0..10 {
+                db.put(&mut txn, &key, &i)?;
+            }
+        }
+
+        assert_eq!(db.delete_dup_range(&mut txn, &2, &(3..6))?, 3);
+        assert_eq!(db.delete_dup_range(&mut txn, &2, &(3..6))?, 0);
+        let after_six = (Bound::Excluded(6), Bound::Unbounded);
+        assert_eq!(db.delete_dup_range(&mut txn, &2, &after_six)?, 3);
+        assert_eq!(db.delete_dup_range(&mut txn, &4, &(..))?, 0);
+        let values = db.get_duplicates(&txn, &2)?.unwrap().map(|entry| entry.map(|(_, v)| v));
+        assert_eq!(values.collect::<Result<Vec<_>>>()?, [0, 1, 2, 6]);
+
+        // The following keys are kept once the last values of a key are deleted.
+        assert_eq!(db.delete_dup_range(&mut txn, &1, &(..))?, 10);
+        assert_eq!(db.delete_dup_range(&mut txn, &2, &(..))?, 4);
+        assert_eq!(db.get(&txn, &1)?, None);
+        assert_eq!(db.len(&txn)?, 10);
+
+        // The values of a database without duplicates can't be positioned on.
+        let plain =
+            env.database_options().types::<BEU16, BEU16>().name("plain").create(&mut txn)?;
+        plain.put(&mut txn, &1, &1)?;
+        let result = plain.delete_dup_range(&mut txn, &1, &(..));
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+        Ok(())
+    }
+
+    #[test]
+    fn put_if_new_dup() -> Result<()> {
+        type BEU16 = U16<BigEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut txn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<BEU16, BEU16>()
+            .flags(DatabaseFlags::DUP_SORT)
+            .name("dup-sort")
+            .create(&mut txn)?;
+
+        assert!(db.put_if_new_dup(&mut txn, &1, &20)?);
+        assert!(db.put_if_new_dup(&mut txn, &1, &10)?);
+        assert!(!db.put_if_new_dup(&mut txn, &1, &20)?);
+        assert!(db.put_if_new_dup(&mut txn, &2, &20)?);
+        let values = db.get_duplicates(&txn, &1)?.unwrap().map(|entry| entry.map(|(_, v)| v));
+        assert_eq!(values.collect::<Result<Vec<_>>>()?, [10, 20]);
+        assert_eq!(db.len(&txn)?, 3);
+
+        // The value of a database without duplicates is not overwritten.
+        let plain =
+            env.database_options().types::<BEU16, BEU16>().name("plain").create(&mut txn)?;
+        plain.put(&mut txn, &1, &10)?;
+        let result = plain.put_if_new_dup(&mut txn, &1, &20);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+        assert_eq!(plain.get(&txn, &1)?, Some(10));
+        Ok(())
+    }
+
+    #[test]
+    fn contains() -> Result<()> {
+        type BEU16 = U16<BigEndian>;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut txn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<BEU16, BEU16>()
+            .flags(DatabaseFlags::DUP_SORT)
+            .name("dup-sort")
+            .create(&mut txn)?;
+        for value in [10, 20, 30] {
+            db.put(&mut txn, &1, &value)?;
+        }
+        db.put(&mut txn, &2, &40)?;
+
+        // Every value of a key is found, not only the first one.
+        for value in [10, 20, 30] {
+            assert!(db.contains(&txn, &1, &value)?);
+        }
+        assert!(!db.contains(&txn, &1, &15)?);
+        assert!(!db.contains(&txn, &1, &40)?);
+        assert!(!db.contains(&txn, &3, &10)?);
+
+        let plain =
+            env.database_options().types::<BEU16, BEU16>().name("plain").create(&mut txn)?;
+        plain.put(&mut txn, &1, &10)?;
+        plain.put(&mut txn, &1, &20)?;
+        assert!(plain.contains(&txn, &1, &20)?);
+        assert!(!plain.contains(&txn, &1, &10)?);
+        assert!(!plain.contains(&txn, &2, &20)?);
+        Ok(())
+    }
+
+    #[test]
+    fn comparators_order_the_btree() -> Result<()> {
+        enum Descending {}
+
+        impl Comparator for Descending {
+            fn compare(a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+                b.cmp(a)
+            }
+        }
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<U8, U8>()
+            .key_comparator::<Descending>()
+            .dup_sort_comparator::<Descending>()
+            .flags(DatabaseFlags::DUP_SORT)
+            .name("descending")
+            .create(&mut wtxn)?;
+
+        for (key, data) in [(1, 1), (3, 1), (2, 1), (3, 2)] {
+            db.put(&mut wtxn, &key, &data)?;
+        }
+        wtxn.commit()?;
+
+        // The entries are read in the order of LMDB, even without the comparators of heed.
+        let rtxn = env.read_txn()?;
+        let entries: Vec<_> =
+            db.remap_types::<Bytes, Bytes>().iter(&rtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [(&[3][..], &[2][..]), (&[3], &[1]), (&[2], &[1]), (&[1], &[1])]);
+        assert_eq!(db.first(&rtxn)?, Some((3, 2)));
+        assert_eq!(db.get_greater_than(&rtxn, &3)?, Some((2, 1)));
+        Ok(())
+    }
+
+    #[test]
+    fn integer_keys_option() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Unit, Unit>().integer_keys::<u32>();
+        let db = options.name("integers").create(&mut wtxn)?;
+
+        for key in [65536, 1, 256, u32::MAX, 0] {
+            db.put(&mut wtxn, &key, &())?;
+        }
+        let keys: Vec<_> =
+            db.iter(&wtxn)?.map(|e| e.map(|(key, ())| key)).collect::<Result<_>>()?;
+        assert_eq!(keys, [0, 1, 256, 65536, u32::MAX]);
+
+        // The `usize` keys have the size of a `size_t`.
+        assert_eq!(<<usize as IntegerKey>::Codec as FixedSize>::SIZE, mem::size_of::<usize>());
+        let mut sizes = env.database_options().types::<Unit, Unit>().integer_keys::<usize>();
+        let db = sizes.name("sizes").create(&mut wtxn)?;
+        for key in [usize::MAX as _, 1 << 20, 3, 0] {
+            db.put(&mut wtxn, &key, &())?;
+        }
+        let keys: Vec<_> =
+            db.iter(&wtxn)?.map(|e| e.map(|(key, ())| key)).collect::<Result<_>>()?;
+        assert_eq!(keys, [0, 3, 1 << 20, usize::MAX as _]);
+
+        // A database created without integer keys can't be opened with them.
+        env.create_database::<Str, Unit>(&mut wtxn, Some("strings"))?;
+        let result = options.name("strings").open(&wtxn);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_comparators() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let mut options = env
+            .database_options()
+            .types::<Str, Str>()
+            .key_comparator::<ReverseComparator>()
+            .dup_sort_comparator::<ReverseComparator>();
+        let db = options.name("reverse").flags(DatabaseFlags::DUP_SORT).create(&mut wtxn)?;
+
+        for key in ["hola", "hello", "bonjour", "ciao"] {
+            db.put(&mut wtxn, key, "aa")?;
+            db.put(&mut wtxn, key, "ba")?;
+            db.put(&mut wtxn, key, "b")?;
+        }
+
+        // The keys and the values are sorted from their last byte, the ranges too.
+        let keys = db.iter(&wtxn)?.move_between_keys().map(|e| e.map(|(key, _)| key));
+        assert_eq!(keys.collect::<Result<Vec<_>>>()?, ["hola", "ciao", "hello", "bonjour"]);
+        let values = db.get_duplicates(&wtxn, "ciao")?.unwrap().map(|e| e.map(|(_, value)| value));
+        assert_eq!(values.collect::<Result<Vec<_>>>()?, ["aa", "ba", "b"]);
+
+        let range = (Bound::Included("ciao"), Bound::Included("hello"));
+        let keys = db.range(&wtxn, &range)?.move_between_keys().map(|e| e.map(|(key, _)| key));
+        assert_eq!(keys.collect::<Result<Vec<_>>>()?, ["ciao", "hello"]);
+        let range = (Bound::Included("a"), Bound::Included("hola"));
+        assert_eq!(db.delete_range(&mut wtxn, &range)?, 3);
+        assert_eq!(db.len(&wtxn)?, 9);
+
+        // A database created without reverse keys can't be opened with them.
+        env.create_database::<Str, Str>(&mut wtxn, Some("forward"))?;
+        let result = options.name("forward").open(&wtxn);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+        Ok(())
+    }
+
+    #[test]
+    fn ascii_case_insensitive_prefixes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<Str, U32<BigEndian>>()
+            .key_comparator::<AsciiCaseInsensitive>()
+            .create(&mut wtxn)?;
+
+        let entries = [("Apple", 1), ("APPLICATION", 2), ("apricot", 3), ("@at", 4), ("[x", 5)];
+        for (key, value) in entries {
+            db.put(&mut wtxn, key, &value)?;
+        }
+        // The keys only differing by their case are the same key.
+        db.put(&mut wtxn, "apple", &6)?;
+        db.put(&mut wtxn, "Banana", &7)?;
+        assert_eq!(db.len(&wtxn)?, 6);
+        assert_eq!(db.get(&wtxn, "APPLE")?, Some(6));
+
+        let values = db.iter(&wtxn)?.map(|e| e.map(|(_, value)| value));
+        assert_eq!(values.collect::<Result<Vec<_>>>()?, [4, 5, 6, 2, 3, 7]);
+        let values = db.prefix_iter(&wtxn, "app")?.map(|e| e.map(|(_, value)| value));
+        assert_eq!(values.collect::<Result<Vec<_>>>()?, [6, 2]);
+        let values = db.rev_prefix_iter(&wtxn, "APP")?.map(|e| e.map(|(_, value)| value));
+        assert_eq!(values.collect::<Result<Vec<_>>>()?, [2, 6]);
+        assert_eq!(db.prefix_iter(&wtxn, "aPp")?.last().transpose()?.map(|(_, v)| v), Some(2));
+
+        assert_eq!(db.delete_prefix(&mut wtxn, "b")?, 1);
+        assert_eq!(db.delete_prefix(&mut wtxn, "Z")?, 0);
+        assert_eq!(db.len(&wtxn)?, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn registered_comparators() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        env.register_comparator::<ReverseComparator>(Some("suffixes"));
+        env.register_dup_sort_comparator::<ReverseComparator>(Some("suffixes"));
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Str, Str>();
+        let db = options.name("suffixes").flags(DatabaseFlags::DUP_SORT).create(&mut wtxn)?;
+        let other = env.create_database::<Str, Str>(&mut wtxn, Some("other"))?;
+        for (key, value) in [("ab", "ab"), ("ab", "ba"), ("ba", "ab")] {
+            db.put(&mut wtxn, key, value)?;
+            other.put(&mut wtxn, key, value)?;
+        }
+
+        // Only the databases of the registered name are ordered by the registered comparators.
+        let entries = db.iter(&wtxn)?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(entries, [("ba", "ab"), ("ab", "ba"), ("ab", "ab")]);
+        let entries = other.iter(&wtxn)?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(entries, [("ab", "ba"), ("ba", "ab")]);
+
+        // The databases can't be opened with other comparators than the registered ones.
+        let result = env
+            .database_options()
+            .types::<Str, Str>()
+            .key_comparator::<IntegerComparator>()
+            .name("suffixes")
+            .open(&wtxn);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+        let result = options.key_comparator::<ReverseComparator>().name("suffixes").open(&wtxn);
+        assert!(result?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn check_codecs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<U32<BigEndian>, Str>();
+        options.name("numbers").check_codecs(true);
+        let db = options.create(&mut wtxn)?;
+        db.put(&mut wtxn, &42, "forty-two")?;
+
+        // The identity is only recorded once, creating it again with the same types is fine.
+        options.create(&mut wtxn)?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        assert!(options.open(&rtxn)?.is_some());
+
+        let mut options = env.database_options().types::<U32<LittleEndian>, Str>();
+        options.name("numbers");
+        // Codecs are not checked unless asked.
+        assert!(options.open(&rtxn)?.is_some());
+        options.check_codecs(true);
+        match options.open(&rtxn) {
+            Err(Error::CodecMismatch { database, stored, requested }) => {
+                assert_eq!(database.as_deref(), Some("numbers"));
+                assert_eq!(stored, "u32-be@1 str@1 default@1 default@1");
+                assert_eq!(requested, "u32-le@1 str@1 default@1 default@1");
+            }
+            otherwise => panic!("expected a codec mismatch, got {otherwise:?}"),
+        }
+        drop(rtxn);
+
+        // The check must be enabled again once the types changed.
+        let mut wtxn = env.write_txn()?;
+        let options = options.types::<U32<BigEndian>, Str>();
+        assert!(matches!(
+            options.create(&mut wtxn),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn env_generation() -> Result<()> {
+        let closed_dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(closed_dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let closed_db = env.create_database::<Str, Str>(&mut wtxn, None)?;
+        wtxn.commit()?;
+        drop(env);
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db = env.create_database::<Str, Str>(&mut wtxn, None)?;
+        db.put(&mut wtxn, "hello", "world")?;
+        wtxn.commit()?;
+
+        let other_dir = tempfile::tempdir()?;
+        let other_env = unsafe { EnvOpenOptions::new().open(other_dir.path())? };
+        let other_rtxn = other_env.read_txn()?;
+
+        // The databases share the same handle but not the same environment.
+        assert_eq!(closed_db.dbi, db.dbi);
+        let rtxn = env.read_txn()?;
+        assert_eq!(db.get(&rtxn, "hello")?, Some("world"));
+        assert!(matches!(closed_db.get(&rtxn, "hello"), Err(Error::EnvClosed)));
+        assert!(matches!(db.get(&other_rtxn, "hello"), Err(Error::ForeignDatabase)));
+        assert!(matches!(db.iter(&other_rtxn), Err(Error::ForeignDatabase)));
+        drop(other_rtxn);
+
+        // Nothing is written in the other environment.
+        let mut other_wtxn = other_env.write_txn()?;
+        assert!(matches!(db.put(&mut other_wtxn, "hello", "there"), Err(Error::ForeignDatabase)));
+        assert!(matches!(db.clear(&mut other_wtxn), Err(Error::ForeignDatabase)));
+        let other_db = other_env.create_database::<Str, Str>(&mut other_wtxn, None)?;
+        assert!(other_db.is_empty(&other_wtxn)?);
+
+        // The halves of a split carry the generation of their transaction.
+        let generation = other_wtxn.env_generation();
+        let (read, mut write) = other_wtxn.split();
+        assert_eq!((read.env_generation(), write.env_generation()), (generation, generation));
+        assert!(matches!(db.get(&read, "hello"), Err(Error::ForeignDatabase)));
+        assert!(matches!(db.put(&mut write, "hello", "there"), Err(Error::ForeignDatabase)));
+        assert!(other_db.is_empty(&read)?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,295 @@
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::mem;
+
+use crate::mdb::ffi;
+use crate::mdb::lmdb_flags::AllDatabaseFlags;
+use crate::types::Bytes;
+use crate::*;
+
+/// The version of the `mdb_dump` format.
+const DUMP_VERSION: u32 = 3;
+
+/// The database flags written in the header, with their `mdb_dump` names.
+pub(crate) const DUMP_FLAGS: [(AllDatabaseFlags, &str); 6] = [
+    (AllDatabaseFlags::REVERSE_KEY, "reversekey"),
+    (AllDatabaseFlags::DUP_SORT, "dupsort"),
+    (AllDatabaseFlags::INTEGER_KEY, "integerkey"),
+    (AllDatabaseFlags::DUP_FIXED, "dupfixed"),
+    (AllDatabaseFlags::INTEGER_DUP, "integerdup"),
+    (AllDatabaseFlags::REVERSE_DUP, "reversedup"),
+];
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Writes all the entries of this database in the `mdb_dump -p` text format.
+    ///
+    /// The output can be loaded with `mdb_load -s <name>`, or with [`Self::load_from`],
+    /// the name of the database is not part of the dump as a [`Database`] doesn't know it.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("source"))?;
+    /// db.put(&mut wtxn, "hello", &[0, 1, 2])?;
+    /// db.put(&mut wtxn, "back\\slash", b"text")?;
+    ///
+    /// let mut dump = Vec::new();
+    /// db.dump_to(&wtxn, &mut dump)?;
+    /// let dump = String::from_utf8(dump)?;
+    /// assert!(dump.ends_with("HEADER=END\n back\\\\slash\n text\n hello\n \\00\\01\\02\nDATA=END\n"));
+    ///
+    /// let copy: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("copy"))?;
+    /// copy.load_from(&mut wtxn, dump.as_bytes())?;
+    /// assert_eq!(copy.get(&wtxn, "hello")?, Some(&[0, 1, 2][..]));
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn dump_to(&self, rtxn: &impl ReadTxn, writer: impl Write) -> Result<()>
+    where
+        C: Comparator + 'static,
+    {
+        check_env_db_txn!(self, rtxn);
+
+        let mut info = mem::MaybeUninit::uninit();
+        unsafe { ffi::mdb_env_info(rtxn.env_mut_ptr().as_ptr(), info.as_mut_ptr()) };
+        let info = unsafe { info.assume_init() };
+        let flags = AllDatabaseFlags::from_bits_truncate(self.database_flags(rtxn)?.bits());
+        let stat = self.stat(rtxn)?;
+
+        let mut writer = BufWriter::new(writer);
+        writeln!(writer, "VERSION={DUMP_VERSION}")?;
+        writeln!(writer, "format=print")?;
+        writeln!(writer, "type=btree")?;
+        writeln!(writer, "mapsize={}", info.me_mapsize)?;
+        writeln!(writer, "maxreaders={}", info.me_maxreaders)?;
+        if flags.contains(AllDatabaseFlags::DUP_SORT) {
+            writeln!(writer, "duplicates=1")?;
+        }
+        for (flag, name) in DUMP_FLAGS {
+            if flags.contains(flag) {
+                writeln!(writer, "{name}=1")?;
+            }
+        }
+        writeln!(writer, "db_pagesize={}", stat.page_size)?;
+        writeln!(writer, "HEADER=END")?;
+
+        for result in self.remap_types::<Bytes, Bytes>().iter(rtxn)? {
+            let (key, data) = result?;
+            write_printable(&mut writer, key)?;
+            write_printable(&mut writer, data)?;
+        }
+
+        writeln!(writer, "DATA=END")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Inserts the entries of a dump in the `mdb_dump` text format into this database.
+    ///
+    /// Both the `print` (`mdb_dump -p`) and `bytevalue` formats are supported. Only the first
+    /// database of the dump is loaded and the flags of the dump must match the ones of this
+    /// database, if not it fails with [`MdbError::Incompatible`]. Malformed dumps fail with
+    /// an [`io::ErrorKind::InvalidData`] error.
+    pub fn load_from(&self, wtxn: &mut impl WriteTxn, reader: impl Read) -> Result<()>
+    where
+        C: Comparator + 'static,
+    {
+        check_env_db_wtxn!(self, wtxn);
+
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        let mut printable = false;
+        let mut dump_flags = AllDatabaseFlags::empty();
+
+        loop {
+            if !read_line(&mut reader, &mut line)? {
+                return Err(invalid_dump("missing the end of the header").into());
+            }
+            if line == b"HEADER=END" {
+                break;
+            }
+            let header = String::from_utf8_lossy(&line);
+            let (name, value) =
+                header.split_once('=').ok_or_else(|| invalid_dump("invalid header line"))?;
+            match name {
+                "VERSION" if value != DUMP_VERSION.to_string() => {
+                    return Err(invalid_dump("unsupported dump version").into())
+                }
+                "format" => match value {
+                    "print" => printable = true,
+                    "bytevalue" => printable = false,
+                    _ => return Err(invalid_dump("unsupported dump format").into()),
+                },
+                "type" if value != "btree" => {
+                    return Err(invalid_dump("unsupported dump type").into())
+                }
+                _ => {
+                    if let Some((flag, _)) = DUMP_FLAGS.iter().find(|(_, n)| *n == name) {
+                        dump_flags.set(*flag, value == "1");
+                    }
+                }
+            }
+        }
+
+        let flags = AllDatabaseFlags::from_bits_truncate(self.database_flags(wtxn)?.bits());
+        let dumped_flags =
+            DUMP_FLAGS.iter().fold(AllDatabaseFlags::empty(), |acc, (f, _)| acc | *f);
+        if flags & dumped_flags != dump_flags {
+            return Err(Error::Mdb(MdbError::Incompatible));
+        }
+
+        let db = self.remap_types::<Bytes, Bytes>();
+        let mut key = Vec::new();
+        let mut data = Vec::new();
+        loop {
+            if !read_line(&mut reader, &mut line)? {
+                return Err(invalid_dump("missing the end of the data").into());
+            }
+            if line == b"DATA=END" {
+                return Ok(());
+            }
+            decode_value(&line, printable, &mut key)?;
+            if !read_line(&mut reader, &mut line)? {
+                return Err(invalid_dump("missing the data of a key").into());
+            }
+            decode_value(&line, printable, &mut data)?;
+            db.put(wtxn, &key, &data)?;
+        }
+    }
+}
+
+/// Writes a value as a line of the `print` format: a space followed by the printable ASCII
+/// characters as is, backslashes doubled and the other bytes as a backslash and two hex digits.
+pub(crate) fn write_printable(writer: &mut impl Write, value: &[u8]) -> io::Result<()> {
+    let mut line = Vec::with_capacity(value.len() + 2);
+    line.push(b' ');
+    for &byte in value {
+        match byte {
+            b'\\' => line.extend_from_slice(b"\\\\"),
+            b' '..=b'~' => line.push(byte),
+            _ => line.extend_from_slice(&[
+                b'\\',
+                HEX_DIGITS[usize::from(byte >> 4)],
+                HEX_DIGITS[usize::from(byte & 0xf)],
+            ]),
+        }
+    }
+    line.push(b'\n');
+    writer.write_all(&line)
+}
+
+/// Reads a line without its line feed, returns `false` at the end of the input.
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<bool> {
+    line.clear();
+    if reader.read_until(b'\n', line)? == 0 {
+        return Ok(false);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Ok(true)
+}
+
+/// Decodes a value line of the `print` or `bytevalue` format.
+fn decode_value(line: &[u8], printable: bool, output: &mut Vec<u8>) -> io::Result<()> {
+    output.clear();
+    let mut bytes = match line.split_first() {
+        Some((b' ', rest)) => rest,
+        _ => return Err(invalid_dump("a value line must start with a space")),
+    };
+
+    while let Some((&byte, rest)) = bytes.split_first() {
+        if printable && byte != b'\\' {
+            output.push(byte);
+            bytes = rest;
+        } else if printable && rest.first() == Some(&b'\\') {
+            output.push(b'\\');
+            bytes = &rest[1..];
+        } else {
+            // A backslash followed by two hex digits, or two hex digits in the bytevalue format.
+            let digits = if printable { rest } else { bytes };
+            match digits {
+                [high, low, rest @ ..] => {
+                    output.push(hex_value(*high)? << 4 | hex_value(*low)?);
+                    bytes = rest;
+                }
+                _ => return Err(invalid_dump("truncated hexadecimal byte")),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn hex_value(digit: u8) -> io::Result<u8> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(invalid_dump("invalid hexadecimal digit")),
+    }
+}
+
+fn invalid_dump(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn dump_and_load_duplicates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Bytes, Bytes>();
+        options.flags(DatabaseFlags::DUP_SORT);
+        let source = options.name("source").create(&mut wtxn)?;
+        source.put(&mut wtxn, b"key", b"\x00first")?;
+        source.put(&mut wtxn, b"key", b"second\n")?;
+        source.put(&mut wtxn, b"\xff", b"")?;
+
+        let mut dump = Vec::new();
+        source.dump_to(&wtxn, &mut dump)?;
+        let text = String::from_utf8(dump.clone()).unwrap();
+        assert!(text.starts_with("VERSION=3\nformat=print\ntype=btree\n"));
+        assert!(text.contains("\nduplicates=1\ndupsort=1\n"));
+        assert!(text.ends_with(" key\n \\00first\n key\n second\\0a\n \\ff\n \nDATA=END\n"));
+
+        let copy = options.name("copy").create(&mut wtxn)?;
+        copy.load_from(&mut wtxn, &dump[..])?;
+        let entries: Vec<_> = copy.iter(&wtxn)?.collect::<Result<_>>()?;
+        let expected: Vec<_> = source.iter(&wtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, expected);
+
+        // The flags of the dump must match the ones of the database.
+        let plain = env.create_database::<Bytes, Bytes>(&mut wtxn, Some("plain"))?;
+        let result = plain.load_from(&mut wtxn, &dump[..]);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+
+        let bytevalue =
+            "VERSION=3\nformat=bytevalue\ntype=btree\nHEADER=END\n 6869\n 00ff\nDATA=END\n";
+        plain.load_from(&mut wtxn, bytevalue.as_bytes())?;
+        assert_eq!(plain.get(&wtxn, b"hi")?, Some(&b"\x00\xff"[..]));
+
+        let truncated = "VERSION=3\nformat=print\nHEADER=END\n key\n";
+        let result = plain.load_from(&mut wtxn, truncated.as_bytes());
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+        Ok(())
+    }
+}
@@ -111,6 +111,36 @@ impl<'e, 'n, T, KC, DC, C, CDUP> EncryptedDatabaseOpenOptions<'e, 'n, T, KC, DC,
         self
     }
 
+    /// Persist and validate the identity of the codecs and comparators of the database.
+    ///
+    /// See [`DatabaseOpenOptions::check_codecs`] for more information.
+    pub fn check_codecs(&mut self, enabled: bool) -> &mut Self
+    where
+        KC: CodecIdentity,
+        DC: CodecIdentity,
+        C: CodecIdentity,
+        CDUP: CodecIdentity,
+    {
+        self.inner.check_codecs(enabled);
+        self
+    }
+
+    /// Limits the size of the encoded keys written to the database.
+    ///
+    /// See [`DatabaseOpenOptions::max_key_size`] for more information.
+    pub fn max_key_size(&mut self, size: usize) -> &mut Self {
+        self.inner.max_key_size(size);
+        self
+    }
+
+    /// Limits the size of the encoded values written to the database.
+    ///
+    /// See [`DatabaseOpenOptions::max_value_size`] for more information.
+    pub fn max_value_size(&mut self, size: usize) -> &mut Self {
+        self.inner.max_value_size(size);
+        self
+    }
+
     /// Opens a typed database that already exists in this environment.
     ///
     /// If the database was previously opened in this program run, types will be checked.
@@ -933,7 +963,8 @@ impl<KC, DC, C, CDUP> EncryptedDatabase<KC, DC, C, CDUP> {
 
     /// Return an ordered iterator of a range of key-value pairs in this database.
     ///
-    /// Comparisons are made by using the comparator `C`.
+    /// Comparisons are made by using the comparator `C`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
     ///
     /// You can make this iterator `Send`able between threads by opening
     /// the environment with the [`EnvOpenOptions::read_txn_without_tls`]
@@ -1021,6 +1052,7 @@ impl<KC, DC, C, CDUP> EncryptedDatabase<KC, DC, C, CDUP> {
     ) -> Result<RoRange<'txn, KC, DC, C>>
     where
         KC: BytesEncode<'a>,
+        C: Comparator,
         R: RangeBounds<KC::EItem>,
     {
         self.inner.range(txn, range)
@@ -1029,7 +1061,8 @@ impl<KC, DC, C, CDUP> EncryptedDatabase<KC, DC, C, CDUP> {
     /// Return a reverse ordered iterator of a range of key-value
     /// pairs in this database.
     ///
-    /// Comparisons are made by using the comparator `C`.
+    /// Comparisons are made by using the comparator `C`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
     ///
     /// You can make this iterator `Send`able between threads by opening
     /// the environment with the [`EnvOpenOptions::read_txn_without_tls`]
@@ -1078,6 +1111,7 @@ impl<KC, DC, C, CDUP> EncryptedDatabase<KC, DC, C, CDUP> {
     ) -> Result<RoRevRange<'txn, KC, DC, C>>
     where
         KC: BytesEncode<'a>,
+        C: Comparator,
         R: RangeBounds<KC::EItem>,
     {
         self.inner.rev_range(txn, range)
@@ -1713,7 +1747,8 @@ impl<KC, DC, C, CDUP> EncryptedDatabase<KC, DC, C, CDUP> {
     ///
     /// Prefer using [`clear`] instead of a call to this method with a full range ([`..`]).
     ///
-    /// Comparisons are made by using the comparator `C`.
+    /// Comparisons are made by using the comparator `C`, a range starting after its end
+    /// returns an [`Error::InvalidRange`].
     ///
     /// [`clear`]: crate::Database::clear
     /// [`..`]: std::ops::RangeFull
@@ -1767,6 +1802,74 @@ impl<KC, DC, C, CDUP> EncryptedDatabase<KC, DC, C, CDUP> {
         self.inner.delete_range(txn, range)
     }
 
+    /// Deletes all the key-value pairs in this database whose keys start with the given prefix.
+    ///
+    /// Returns the number of deleted entries.
+    ///
+    /// Comparisons are made by using the bytes representation of the key.
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::Path;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, I32<BigEndian>>(&mut wtxn, Some("delete-prefix"))?;
+    ///
+    /// # db.clear(&mut wtxn)?;
+    /// db.put(&mut wtxn, "all-forty-two", &42)?;
+    /// db.put(&mut wtxn, "all-twenty-seven", &27)?;
+    /// db.put(&mut wtxn, "i-am-thirteen", &13)?;
+    ///
+    /// let ret = db.delete_prefix(&mut wtxn, "all")?;
+    /// assert_eq!(ret, 2);
+    ///
+    /// let mut iter = db.iter(&wtxn)?;
+    /// assert_eq!(iter.next().transpose()?, Some(("i-am-thirteen", 13)));
+    /// assert_eq!(iter.next().transpose()?, None);
+    ///
+    /// drop(iter);
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn delete_prefix<'a>(&self, txn: &mut impl WriteTxn, prefix: &'a KC::EItem) -> Result<usize>
+    where
+        KC: BytesEncode<'a>,
+        C: LexicographicComparator,
+    {
+        self.inner.delete_prefix(txn, prefix)
+    }
+
+    /// Deletes a range of values of a key in this `DUP_SORT` database, the values outside
+    /// of the range are kept.
+    ///
+    /// Returns the number of deleted values. For more info, see [`Database::delete_dup_range`].
+    pub fn delete_dup_range<'a, R>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        range: &'a R,
+    ) -> Result<usize>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+        C: Comparator,
+        CDUP: Comparator,
+        R: RangeBounds<DC::EItem>,
+    {
+        self.inner.delete_dup_range(txn, key, range)
+    }
+
     /// Deletes all key/value pairs in this database.
     ///
     /// Prefer using this method instead of a call to [`delete_range`] with a full range ([`..`]).
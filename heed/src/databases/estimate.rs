@@ -0,0 +1,238 @@
+use std::marker;
+
+use heed_traits::LexicographicComparator;
+
+use crate::databases::key_buffer::encode_key;
+use crate::debug::{decode_node, u16_at, Node, PageKind, PageReader, P_BRANCH, P_LEAF};
+use crate::iterator::starts_with;
+use crate::*;
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Estimates the number of entries whose keys start with the prefix, without iterating
+    /// over them.
+    ///
+    /// Only the pages on the two paths from the root to the first and the last keys of the
+    /// prefix are read, a few pages whatever the number of entries. The leaves of these paths
+    /// are counted exactly, and the subtrees in between are estimated from the number of
+    /// entries of the database, shared evenly between the children of each branch page.
+    /// The bounds always hold: a subtree holds at least one entry per leaf and its branch
+    /// pages at least two children.
+    ///
+    /// The pages are read from the data file, the estimate isn't available for write
+    /// transactions nor for encrypted environments. The duplicates of the keys of a
+    /// `DUP_SORT` database in the read leaves count as one entry.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Unit> = env.create_database(&mut wtxn, Some("words"))?;
+    /// for i in 0..10_000 {
+    ///     db.put(&mut wtxn, &format!("{}-{i:05}", ["en", "fr"][i % 2]), &())?;
+    /// }
+    /// wtxn.commit()?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// let count = db.estimate_prefix_count(&rtxn, "fr-")?;
+    /// assert!(count.low <= 5_000 && 5_000 <= count.high);
+    /// assert_eq!(db.estimate_prefix_count(&rtxn, "de-")?.estimate, 0);
+    /// # Ok(()) }
+    /// ```
+    pub fn estimate_prefix_count<'a, T>(
+        &self,
+        rtxn: &RoTxn<T>,
+        prefix: &'a KC::EItem,
+    ) -> Result<CountEstimate>
+    where
+        KC: BytesEncode<'a>,
+        C: LexicographicComparator,
+    {
+        let stat = self.stat(rtxn)?;
+        let prefix = encode_key::<KC>(prefix)?;
+        let reader = PageReader::open(rtxn)?;
+        let mut count = Count::default();
+        if let Some(root) = reader.root(rtxn, self.dbi)? {
+            let estimator =
+                Estimator::<C> { reader, prefix: &prefix, _marker: marker::PhantomData };
+            let height = stat.depth.saturating_sub(1) as usize;
+            estimator.page(root, height, stat.entries as f64, &mut count);
+        }
+
+        let entries = stat.entries as u64;
+        let high = entries.saturating_sub(count.outside as u64);
+        let low = (count.inside as u64).min(high);
+        let estimate = (count.estimate.round() as u64).clamp(low, high);
+        Ok(CountEstimate { estimate, low, high })
+    }
+}
+
+/// The number of entries estimated by [`Database::estimate_prefix_count`], with bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountEstimate {
+    /// The estimated number of entries.
+    pub estimate: u64,
+    /// There are at least this number of entries.
+    pub low: u64,
+    /// There are at most this number of entries.
+    pub high: u64,
+}
+
+impl CountEstimate {
+    /// Whether the bounds are equal, the estimate is then the exact count.
+    pub fn is_exact(&self) -> bool {
+        self.low == self.high
+    }
+}
+
+/// The entries counted in the visited pages, and the ones known to be in or out of the prefix.
+#[derive(Default)]
+struct Count {
+    estimate: f64,
+    inside: f64,
+    outside: f64,
+}
+
+struct Estimator<'p, C> {
+    reader: PageReader,
+    prefix: &'p [u8],
+    _marker: marker::PhantomData<C>,
+}
+
+impl<C: LexicographicComparator> Estimator<'_, C> {
+    /// Counts the entries of the prefix in the page, which holds about `size` entries in the
+    /// pages `height` levels below it.
+    fn page(&self, number: usize, height: usize, size: f64, count: &mut Count) {
+        let header = self.reader.header;
+        let page = match self.reader.read(number) {
+            Ok(page) if page.len() >= header => page,
+            _ => return count.estimate += size / 2.0,
+        };
+        let flags = u16_at(&page, header - 6);
+        let lower = u16_at(&page, header - 4) as usize;
+        if lower < header || lower > page.len() {
+            return count.estimate += size / 2.0;
+        }
+        let keys = (lower - header) / 2;
+        let kind = if flags & P_BRANCH != 0 && height > 0 {
+            PageKind::Branch
+        } else if flags & P_LEAF != 0 {
+            PageKind::Leaf
+        } else {
+            return count.estimate += size / 2.0;
+        };
+        let nodes: Option<Vec<_>> = (0..keys)
+            .map(|i| decode_node(&page, u16_at(&page, header + 2 * i) as usize, kind))
+            .collect();
+        let Some(nodes) = nodes.filter(|nodes| !nodes.is_empty()) else {
+            return count.estimate += size / 2.0;
+        };
+
+        if kind == PageKind::Leaf {
+            let inside = nodes.iter().filter(|(key, _)| starts_with::<C>(key, self.prefix)).count();
+            count.estimate += inside as f64;
+            count.inside += inside as f64;
+            count.outside += (nodes.len() - inside) as f64;
+            return;
+        }
+
+        // The child of an entry holds the keys from its key to the key of the next entry,
+        // the key of the first entry is ignored.
+        let (child_size, child_min) = (size / nodes.len() as f64, min_entries(height - 1));
+        for (i, (key, node)) in nodes.iter().enumerate() {
+            let Node::Child { page: child } = *node else { continue };
+            let lower = (i > 0).then_some(*key);
+            let upper = nodes.get(i + 1).map(|(key, _)| *key);
+            match self.overlap(lower, upper) {
+                Overlap::Disjoint => count.outside += child_min,
+                Overlap::Contained => {
+                    count.estimate += child_size;
+                    count.inside += child_min;
+                }
+                Overlap::Partial => self.page(child, height - 1, child_size, count),
+            }
+        }
+    }
+
+    /// How the keys from `lower` included to `upper` excluded overlap the keys of the prefix,
+    /// which follow each other.
+    fn overlap(&self, lower: Option<&[u8]>, upper: Option<&[u8]>) -> Overlap {
+        let prefixed = |key: &[u8]| starts_with::<C>(key, self.prefix);
+        let before = upper.is_some_and(|upper| C::compare(upper, self.prefix).is_le());
+        let after = lower.is_some_and(|lower| C::compare(lower, self.prefix).is_gt())
+            && !lower.is_some_and(prefixed);
+        if before || after {
+            Overlap::Disjoint
+        } else if lower.is_some_and(prefixed) && upper.is_some_and(prefixed) {
+            Overlap::Contained
+        } else {
+            Overlap::Partial
+        }
+    }
+}
+
+enum Overlap {
+    Disjoint,
+    Contained,
+    Partial,
+}
+
+/// The fewest entries of a subtree of this height, its pages hold at least one entry and its
+/// branch pages at least two children.
+fn min_entries(height: usize) -> f64 {
+    2f64.powi(height.min(64) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Str, Unit};
+
+    #[test]
+    fn estimate_within_bounds() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Unit> = env.create_database(&mut wtxn, Some("index"))?;
+        let prefixes = ["a", "b", "ba", "c"];
+        for i in 0..20_000 {
+            db.put(&mut wtxn, &format!("{}-{i:06}", prefixes[i % 7 % 4]), &())?;
+        }
+        db.put(&mut wtxn, "only", &())?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        assert!(db.stat(&rtxn)?.depth > 2);
+        for prefix in ["a", "b", "ba", "b-", "c-0001", "only", "z", ""] {
+            let exact = db.prefix_iter(&rtxn, prefix)?.count() as u64;
+            let count = db.estimate_prefix_count(&rtxn, prefix)?;
+            assert!(count.low <= exact && exact <= count.high, "{prefix}: {exact} {count:?}");
+            assert!(count.low <= count.estimate && count.estimate <= count.high);
+            let error = count.estimate.abs_diff(exact) as f64;
+            assert!(error <= exact as f64 / 2.0 + 200.0, "{prefix}: {exact} {count:?}");
+        }
+        assert_eq!(db.estimate_prefix_count(&rtxn, "only")?.estimate, 1);
+        assert_eq!(db.estimate_prefix_count(&rtxn, "z")?.estimate, 0);
+        assert_eq!(db.estimate_prefix_count(&rtxn, "")?.high, 20_001);
+
+        let empty: Database<Str, Unit> = {
+            let mut wtxn = env.write_txn()?;
+            let empty = env.create_database(&mut wtxn, Some("empty"))?;
+            wtxn.commit()?;
+            empty
+        };
+        let rtxn = env.read_txn()?;
+        let count = empty.estimate_prefix_count(&rtxn, "a")?;
+        assert_eq!(count, CountEstimate { estimate: 0, low: 0, high: 0 });
+        Ok(())
+    }
+}
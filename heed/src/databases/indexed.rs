@@ -0,0 +1,485 @@
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::reencode::next_batch;
+use crate::iteration_method::MoveOnCurrentKeyDuplicates;
+use crate::types::Bytes;
+use crate::*;
+
+/// The identifier of the next [`IndexedDatabase`], to recognize its [`SecondaryIndex`]es.
+static NEXT_INDEXED_DATABASE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The function extracting the encoded index keys of an entry.
+type Extractor<K, V> = Box<dyn Fn(&K, &V) -> Result<Vec<Vec<u8>>> + Send + Sync>;
+
+struct IndexEntry<K, V> {
+    name: String,
+    /// The index database, it maps the index keys to the primary keys.
+    db: Database<Bytes, Bytes>,
+    /// Whether an index key can only map to a single primary key.
+    unique: bool,
+    extractor: Extractor<K, V>,
+}
+
+impl<K, V> IndexEntry<K, V> {
+    /// Fails with [`Error::UniqueViolation`] if the index is unique and
+    /// the index key already maps to another primary key.
+    fn ensure_unique(&self, rtxn: &impl ReadTxn, index_key: &[u8], key_bytes: &[u8]) -> Result<()> {
+        if !self.unique {
+            return Ok(());
+        }
+        match self.db.get(rtxn, index_key)? {
+            Some(existing) if existing != key_bytes => {
+                Err(Error::UniqueViolation { index: self.name.clone(), key: index_key.to_vec() })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A handle to a secondary index of an [`IndexedDatabase`], returned by [`IndexedDatabase::add_index`].
+///
+/// `IKC` is the codec of the index keys.
+pub struct SecondaryIndex<IKC> {
+    /// The identifier of the [`IndexedDatabase`] the index was registered on.
+    owner: u64,
+    position: usize,
+    _marker: PhantomData<IKC>,
+}
+
+impl<IKC> Clone for SecondaryIndex<IKC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<IKC> Copy for SecondaryIndex<IKC> {}
+
+/// A [`Database`] whose secondary indexes are kept in sync with its entries.
+///
+/// Every index is stored in its own `DUP_SORT` database, mapping the keys extracted from
+/// the entries to the primary keys. The entries inserted or deleted with [`Self::put`] and
+/// [`Self::delete`] update all the indexes in the same write transaction. The primary
+/// database must not be written directly nor use duplicates, else the indexes get out of sync
+/// and must be rebuilt with [`Self::rebuild_indexes`].
+///
+/// The indexes are not persisted with their extractors: they must be registered again, in the
+/// same order, every time the environment is opened. The codecs must decode owned values.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::byteorder::BigEndian;
+/// use heed::types::*;
+/// use heed::{Database, IndexedDatabase};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let users: Database<U32<BigEndian>, SerdeBincode<(String, u8)>> =
+///     env.create_database(&mut wtxn, Some("users"))?;
+/// let mut users = IndexedDatabase::new(users);
+/// let by_age = users.add_index::<U8, _, _, _>(&env, &mut wtxn, "users-by-age", |_id, (_name, age)| {
+///     vec![*age]
+/// })?;
+///
+/// users.put(&mut wtxn, &1, &("kero".to_string(), 28))?;
+/// users.put(&mut wtxn, &2, &("tamo".to_string(), 28))?;
+/// users.put(&mut wtxn, &3, &("many".to_string(), 31))?;
+/// users.put(&mut wtxn, &2, &("tamo".to_string(), 29))?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// let same_age: Vec<_> = users.by_index(&rtxn, by_age, &28)?.collect::<heed::Result<_>>()?;
+/// assert_eq!(same_age, vec![(1, ("kero".to_string(), 28))]);
+/// # Ok(()) }
+/// ```
+pub struct IndexedDatabase<KC, DC, C = DefaultComparator>
+where
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+{
+    id: u64,
+    primary: Database<KC, DC, C>,
+    indexes: Vec<IndexEntry<KC::DItem, DC::DItem>>,
+}
+
+impl<KC, DC, C> IndexedDatabase<KC, DC, C>
+where
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+    C: Comparator + 'static,
+{
+    /// Wraps the primary database, without any index.
+    pub fn new(primary: Database<KC, DC, C>) -> Self {
+        let id = NEXT_INDEXED_DATABASE_ID.fetch_add(1, Ordering::Relaxed);
+        IndexedDatabase { id, primary, indexes: Vec::new() }
+    }
+
+    /// The primary database, to read the entries.
+    pub fn primary(&self) -> Database<KC, DC, C> {
+        self.primary
+    }
+
+    /// Registers a secondary index, creating its database if it doesn't exist.
+    ///
+    /// The `extractor` returns the index keys of an entry, encoded with `IKC`, it must
+    /// always return the same keys for the same entry. An existing index database
+    /// is not rebuilt, use [`Self::rebuild_indexes`] when the extractor changes.
+    pub fn add_index<IKC, I, F, T>(
+        &mut self,
+        env: &Env<T>,
+        wtxn: &mut impl WriteTxn,
+        name: &str,
+        extractor: F,
+    ) -> Result<SecondaryIndex<IKC>>
+    where
+        IKC: for<'a> BytesEncode<'a> + 'static,
+        I: for<'a> Borrow<<IKC as BytesEncode<'a>>::EItem>,
+        F: Fn(&KC::DItem, &DC::DItem) -> Vec<I> + Send + Sync + 'static,
+    {
+        self.register_index(env, wtxn, name, false, extractor)
+    }
+
+    /// Registers a unique secondary index, creating its database if it doesn't exist.
+    ///
+    /// Works like [`Self::add_index`] but an index key can only map to a single entry:
+    /// [`Self::put`] fails with [`Error::UniqueViolation`], without writing anything,
+    /// when one of the index keys of the entry already maps to another primary key.
+    pub fn add_unique_index<IKC, I, F, T>(
+        &mut self,
+        env: &Env<T>,
+        wtxn: &mut impl WriteTxn,
+        name: &str,
+        extractor: F,
+    ) -> Result<SecondaryIndex<IKC>>
+    where
+        IKC: for<'a> BytesEncode<'a> + 'static,
+        I: for<'a> Borrow<<IKC as BytesEncode<'a>>::EItem>,
+        F: Fn(&KC::DItem, &DC::DItem) -> Vec<I> + Send + Sync + 'static,
+    {
+        self.register_index(env, wtxn, name, true, extractor)
+    }
+
+    fn register_index<IKC, I, F, T>(
+        &mut self,
+        env: &Env<T>,
+        wtxn: &mut impl WriteTxn,
+        name: &str,
+        unique: bool,
+        extractor: F,
+    ) -> Result<SecondaryIndex<IKC>>
+    where
+        IKC: for<'a> BytesEncode<'a> + 'static,
+        I: for<'a> Borrow<<IKC as BytesEncode<'a>>::EItem>,
+        F: Fn(&KC::DItem, &DC::DItem) -> Vec<I> + Send + Sync + 'static,
+    {
+        let db = env
+            .database_options()
+            .types()
+            .name(name)
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(wtxn)?;
+
+        let extractor = move |key: &KC::DItem, data: &DC::DItem| {
+            let mut keys = Vec::new();
+            for index_key in extractor(key, data) {
+                let bytes = IKC::bytes_encode(index_key.borrow()).map_err(Error::Encoding)?;
+                keys.push(bytes.into_owned());
+            }
+            Ok(keys)
+        };
+
+        let name = name.to_owned();
+        self.indexes.push(IndexEntry { name, db, unique, extractor: Box::new(extractor) });
+        let position = self.indexes.len() - 1;
+        Ok(SecondaryIndex { owner: self.id, position, _marker: PhantomData })
+    }
+
+    /// Inserts an entry and updates the indexes accordingly.
+    ///
+    /// Fails with [`Error::UniqueViolation`] before writing anything if the entry
+    /// conflicts with another one in a unique index.
+    pub fn put<'a>(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        data: &'a DC::EItem,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        let key_bytes = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let data_bytes = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        let primary = self.primary.remap_types::<Bytes, Bytes>();
+
+        let old_data = self.get_owned(wtxn, &key_bytes)?;
+        let key = KC::bytes_decode_owned(&key_bytes).map_err(Error::Decoding)?;
+        let data = DC::bytes_decode_owned(&data_bytes).map_err(Error::Decoding)?;
+
+        let mut changes = Vec::with_capacity(self.indexes.len());
+        for index in &self.indexes {
+            let old_keys = match &old_data {
+                Some(old_data) => (index.extractor)(&key, old_data)?,
+                None => Vec::new(),
+            };
+            let new_keys = (index.extractor)(&key, &data)?;
+            changes.push((index, old_keys, new_keys));
+        }
+
+        for (index, _, new_keys) in &changes {
+            for new_key in new_keys {
+                index.ensure_unique(wtxn, new_key, &key_bytes)?;
+            }
+        }
+
+        for (index, old_keys, new_keys) in changes {
+            for old_key in old_keys.iter().filter(|k| !new_keys.contains(k)) {
+                index.db.delete_one_duplicate(wtxn, old_key, &key_bytes)?;
+            }
+            for new_key in &new_keys {
+                index.db.put(wtxn, new_key, &key_bytes)?;
+            }
+        }
+
+        primary.put(wtxn, &key_bytes, &data_bytes)
+    }
+
+    /// Deletes an entry and its index keys, returns `true` if the entry existed.
+    pub fn delete<'a>(&self, wtxn: &mut impl WriteTxn, key: &'a KC::EItem) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let key_bytes = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let Some(old_data) = self.get_owned(wtxn, &key_bytes)? else {
+            return Ok(false);
+        };
+        let key = KC::bytes_decode_owned(&key_bytes).map_err(Error::Decoding)?;
+
+        for index in &self.indexes {
+            for old_key in (index.extractor)(&key, &old_data)? {
+                index.db.delete_one_duplicate(wtxn, &old_key, &key_bytes)?;
+            }
+        }
+
+        self.primary.remap_types::<Bytes, Bytes>().delete(wtxn, &key_bytes)
+    }
+
+    /// Iterates over the entries whose index keys contain `index_key`,
+    /// in the lexicographic order of the encoded primary keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index was registered on another [`IndexedDatabase`].
+    pub fn by_index<'a, 'txn, R, IKC>(
+        &self,
+        rtxn: &'txn R,
+        index: SecondaryIndex<IKC>,
+        index_key: &'a IKC::EItem,
+    ) -> Result<ByIndex<'txn, R, KC, DC, C>>
+    where
+        R: ReadTxn,
+        IKC: BytesEncode<'a>,
+    {
+        assert_eq!(index.owner, self.id, "the index was registered on another IndexedDatabase");
+        let entry = &self.indexes[index.position];
+        let index_key = IKC::bytes_encode(index_key).map_err(Error::Encoding)?;
+        let keys = entry.db.get_duplicates(rtxn, &index_key)?;
+        Ok(ByIndex { rtxn, primary: self.primary, keys })
+    }
+
+    /// Clears the indexes and rebuilds them from the entries of the primary database.
+    ///
+    /// Fails with [`Error::UniqueViolation`] if two entries conflict in a unique index,
+    /// the indexes are then partially rebuilt and the transaction must be aborted.
+    pub fn rebuild_indexes(&self, wtxn: &mut impl WriteTxn) -> Result<()> {
+        /// The number of primary entries read at once.
+        const BATCH_SIZE: usize = 1024;
+
+        for index in &self.indexes {
+            index.db.clear(wtxn)?;
+        }
+
+        let primary = self.primary.remap_types::<Bytes, Bytes>();
+        let mut position = None;
+        loop {
+            let entries = next_batch(primary, wtxn, &mut position, BATCH_SIZE)?;
+            for (key_bytes, data_bytes) in &entries {
+                let key = KC::bytes_decode_owned(key_bytes).map_err(Error::Decoding)?;
+                let data = DC::bytes_decode_owned(data_bytes).map_err(Error::Decoding)?;
+                for index in &self.indexes {
+                    for index_key in (index.extractor)(&key, &data)? {
+                        index.ensure_unique(wtxn, &index_key, key_bytes)?;
+                        index.db.put(wtxn, &index_key, key_bytes)?;
+                    }
+                }
+            }
+            if entries.len() < BATCH_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns the decoded data stored under the encoded key.
+    fn get_owned(&self, rtxn: &impl ReadTxn, key_bytes: &[u8]) -> Result<Option<DC::DItem>> {
+        match self.primary.remap_types::<Bytes, Bytes>().get(rtxn, key_bytes)? {
+            Some(bytes) => DC::bytes_decode_owned(bytes).map(Some).map_err(Error::Decoding),
+            None => Ok(None),
+        }
+    }
+}
+
+/// An iterator over the entries matching an index key, returned by [`IndexedDatabase::by_index`].
+pub struct ByIndex<'txn, R, KC, DC, C> {
+    rtxn: &'txn R,
+    primary: Database<KC, DC, C>,
+    keys: Option<RoIter<'txn, Bytes, Bytes, MoveOnCurrentKeyDuplicates>>,
+}
+
+impl<R, KC, DC, C> Iterator for ByIndex<'_, R, KC, DC, C>
+where
+    R: ReadTxn,
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+    C: Comparator + 'static,
+{
+    type Item = Result<(KC::DItem, DC::DItem)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.keys.as_mut()?.next()?;
+        Some(result.and_then(|(_, key_bytes)| {
+            let primary = self.primary.remap_types::<Bytes, Bytes>();
+            // An index key pointing to a missing entry means the index is out of sync.
+            let data_bytes = primary.get(self.rtxn, key_bytes)?.ok_or(MdbError::NotFound)?;
+            let key = KC::bytes_decode_owned(key_bytes).map_err(Error::Decoding)?;
+            let data = DC::bytes_decode_owned(data_bytes).map_err(Error::Decoding)?;
+            Ok((key, data))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::BigEndian;
+
+    use super::*;
+    #[cfg(feature = "serde-bincode")]
+    use crate::types::{SerdeBincode, Str};
+    use crate::types::{U32, U8};
+    use crate::EnvOpenOptions;
+
+    #[cfg(feature = "serde-bincode")]
+    type Users = IndexedDatabase<U32<BigEndian>, SerdeBincode<(String, u8)>>;
+
+    #[test]
+    #[cfg(feature = "serde-bincode")]
+    fn indexes_follow_puts_and_deletes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let users = env.create_database(&mut wtxn, Some("users"))?;
+        let mut users: Users = IndexedDatabase::new(users);
+        let by_age =
+            users.add_index::<U8, _, _, _>(&env, &mut wtxn, "by-age", |_, (_, age)| vec![*age])?;
+        let by_word =
+            users.add_index::<Str, _, _, _>(&env, &mut wtxn, "by-word", |_, (name, _)| {
+                name.split(' ').map(str::to_lowercase).collect()
+            })?;
+
+        users.put(&mut wtxn, &1, &("Kero Tamo".to_string(), 28))?;
+        users.put(&mut wtxn, &2, &("Tamo".to_string(), 28))?;
+        users.put(&mut wtxn, &3, &("Many".to_string(), 31))?;
+        // Updating an entry removes its previous index keys.
+        users.put(&mut wtxn, &1, &("Kero".to_string(), 31))?;
+        assert!(users.delete(&mut wtxn, &3)?);
+        assert!(!users.delete(&mut wtxn, &3)?);
+
+        let ids = |users: &Users, rtxn, index, key| -> Result<Vec<u32>> {
+            users.by_index::<_, Str>(rtxn, index, key)?.map(|r| r.map(|(id, _)| id)).collect()
+        };
+        assert_eq!(ids(&users, &wtxn, by_word, "tamo")?, vec![2]);
+        assert_eq!(ids(&users, &wtxn, by_word, "kero")?, vec![1]);
+        assert_eq!(ids(&users, &wtxn, by_word, "many")?, Vec::<u32>::new());
+
+        let ages: Vec<_> = users.by_index(&wtxn, by_age, &31)?.collect::<Result<_>>()?;
+        assert_eq!(ages, vec![(1, ("Kero".to_string(), 31))]);
+
+        // Entries written directly in the primary database are indexed once rebuilt.
+        users.primary().put(&mut wtxn, &4, &("Ferris".to_string(), 28))?;
+        users.rebuild_indexes(&mut wtxn)?;
+        let ages: Vec<_> = users.by_index(&wtxn, by_age, &28)?.collect::<Result<_>>()?;
+        assert_eq!(ages, vec![(2, ("Tamo".to_string(), 28)), (4, ("Ferris".to_string(), 28))]);
+
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "serde-bincode")]
+    fn unique_index_violations_write_nothing() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let users = env.create_database(&mut wtxn, Some("users"))?;
+        let mut users: Users = IndexedDatabase::new(users);
+        let by_age =
+            users.add_index::<U8, _, _, _>(&env, &mut wtxn, "by-age", |_, (_, age)| vec![*age])?;
+        let by_name = users.add_unique_index::<Str, _, _, _>(
+            &env,
+            &mut wtxn,
+            "by-name",
+            |_, (name, _)| vec![name.clone()],
+        )?;
+
+        users.put(&mut wtxn, &1, &("kero".to_string(), 28))?;
+        // Updating an entry without changing its unique key is fine.
+        users.put(&mut wtxn, &1, &("kero".to_string(), 29))?;
+
+        match users.put(&mut wtxn, &2, &("kero".to_string(), 31)) {
+            Err(Error::UniqueViolation { index, key }) => {
+                assert_eq!(index, "by-name");
+                assert_eq!(key, b"kero");
+            }
+            otherwise => panic!("expected a unique violation, got {otherwise:?}"),
+        }
+        assert_eq!(users.primary().get(&wtxn, &2)?, None);
+        assert_eq!(users.by_index(&wtxn, by_age, &31)?.count(), 0);
+
+        // The key is available again once the entry is renamed.
+        users.put(&mut wtxn, &1, &("tamo".to_string(), 29))?;
+        users.put(&mut wtxn, &2, &("kero".to_string(), 31))?;
+        let kero: Vec<_> = users.by_index(&wtxn, by_name, "kero")?.collect::<Result<_>>()?;
+        assert_eq!(kero, vec![(2, ("kero".to_string(), 31))]);
+
+        users.primary().put(&mut wtxn, &3, &("kero".to_string(), 40))?;
+        assert!(matches!(users.rebuild_indexes(&mut wtxn), Err(Error::UniqueViolation { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic = "the index was registered on another IndexedDatabase"]
+    fn foreign_index_panics() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()).unwrap() };
+
+        let mut wtxn = env.write_txn().unwrap();
+        let scores: Database<U32<BigEndian>, U32<BigEndian>> =
+            env.create_database(&mut wtxn, Some("scores")).unwrap();
+        let mut first = IndexedDatabase::new(scores);
+        let mut second = IndexedDatabase::new(scores);
+        let by_score = |_: &u32, score: &u32| vec![*score as u8];
+        let index = first.add_index::<U8, _, _, _>(&env, &mut wtxn, "first", by_score).unwrap();
+        second.add_index::<U8, _, _, _>(&env, &mut wtxn, "second", by_score).unwrap();
+
+        let _ = second.by_index(&wtxn, index, &1);
+    }
+}
@@ -0,0 +1,84 @@
+use std::ops::{Bound, RangeBounds};
+
+use smallvec::SmallVec;
+
+use crate::{BytesEncode, Comparator, Error, Result};
+
+/// The number of bytes of the keys kept on the stack, the maximum key size of LMDB
+/// when it is built without the `longer-keys` feature.
+const INLINE_KEY_LEN: usize = 511;
+
+/// A buffer keeping the encoded keys on the stack, only the longer keys are allocated.
+pub(crate) type KeyBuffer = SmallVec<[u8; INLINE_KEY_LEN]>;
+
+/// Encodes a key in a buffer that doesn't allocate for the usual key sizes.
+pub(crate) fn encode_key<'a, KC: BytesEncode<'a>>(key: &'a KC::EItem) -> Result<KeyBuffer> {
+    let mut bytes = KeyBuffer::new();
+    KC::bytes_encode_into(key, &mut bytes).map_err(Error::Encoding)?;
+    Ok(bytes)
+}
+
+/// Encodes the bounds of a range, the range is invalid if its start is after its end
+/// as defined by the `C` comparator.
+pub(crate) fn encode_range<'a, KC, C, R>(
+    range: &'a R,
+) -> Result<(Bound<KeyBuffer>, Bound<KeyBuffer>)>
+where
+    KC: BytesEncode<'a>,
+    C: Comparator,
+    R: RangeBounds<KC::EItem> + ?Sized,
+{
+    let start = encode_bound::<KC>(range.start_bound())?;
+    let end = encode_bound::<KC>(range.end_bound())?;
+    match (&start, &end) {
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) if C::compare(start, end).is_gt() => Err(Error::InvalidRange),
+        _ => Ok((start, end)),
+    }
+}
+
+/// Encodes a bound of a range in the buffer kept by the range iterators.
+fn encode_bound<'a, KC: BytesEncode<'a>>(bound: Bound<&'a KC::EItem>) -> Result<Bound<KeyBuffer>> {
+    Ok(match bound {
+        Bound::Included(key) => Bound::Included(encode_key::<KC>(key)?),
+        Bound::Excluded(key) => Bound::Excluded(encode_key::<KC>(key)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Bytes;
+    use crate::DefaultComparator;
+
+    #[test]
+    fn keys_around_the_inline_capacity() -> Result<()> {
+        let short = vec![1; INLINE_KEY_LEN];
+        let long = vec![2; INLINE_KEY_LEN + 1];
+
+        let key = encode_key::<Bytes>(&short)?;
+        assert!(!key.spilled());
+        assert_eq!(&key[..], &short[..]);
+
+        let key = encode_key::<Bytes>(&long)?;
+        assert!(key.spilled());
+        assert_eq!(&key[..], &long[..]);
+
+        let range = (Bound::Included(&short[..]), Bound::Included(&long[..]));
+        match encode_range::<Bytes, DefaultComparator, _>(&range)? {
+            (Bound::Included(start), Bound::Included(end)) => {
+                assert_eq!((&start[..], &end[..]), (&short[..], &long[..]));
+            }
+            bounds => panic!("unexpected bounds {bounds:?}"),
+        }
+
+        let range = (Bound::Included(&long[..]), Bound::Excluded(&short[..]));
+        let result = encode_range::<Bytes, DefaultComparator, _>(&range);
+        assert!(matches!(result, Err(Error::InvalidRange)));
+
+        Ok(())
+    }
+}
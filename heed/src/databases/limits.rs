@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+use crate::mdb::ffi;
+#[allow(unused)] // for cargo auto doc links
+use crate::DatabaseOpenOptions;
+use crate::{Database, Error, Result};
+
+/// The number of bytes of the key kept in the errors.
+const KEY_PREVIEW_LEN: usize = 32;
+
+/// The size limits of the databases, by the generation of their environment and their handle.
+static SIZE_LIMITS: LazyLock<RwLock<HashMap<(u64, ffi::MDB_dbi), SizeLimits>>> =
+    LazyLock::new(RwLock::default);
+
+/// The size limits of a database, see [`DatabaseOpenOptions::max_value_size`].
+#[derive(Debug, Clone)]
+pub(crate) struct SizeLimits {
+    /// The name of the database, `None` for the unnamed one.
+    pub(crate) name: Option<String>,
+    pub(crate) max_key_size: Option<usize>,
+    pub(crate) max_value_size: Option<usize>,
+}
+
+/// Sets the limits of a database, for every handle on it until its environment is closed.
+pub(crate) fn set_size_limits(generation: u64, dbi: ffi::MDB_dbi, limits: SizeLimits) {
+    SIZE_LIMITS.write().unwrap().insert((generation, dbi), limits);
+}
+
+/// Forgets the limits of the databases of a closed environment.
+pub(crate) fn forget_size_limits(generation: u64) {
+    SIZE_LIMITS.write().unwrap().retain(|(g, _), _| *g != generation);
+}
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Ensures the encoded key and value are within the limits of the database.
+    pub(crate) fn check_sizes(&self, key: &[u8], data_len: usize) -> Result<()> {
+        let limits = SIZE_LIMITS.read().unwrap();
+        let Some(limits) = limits.get(&(self.env_generation, self.dbi)) else {
+            return Ok(());
+        };
+
+        let database = || limits.name.clone();
+        let key_preview = || key[..key.len().min(KEY_PREVIEW_LEN)].to_vec();
+        match (limits.max_key_size, limits.max_value_size) {
+            (Some(max), _) if key.len() > max => Err(Error::KeyTooLarge {
+                database: database(),
+                key_preview: key_preview(),
+                len: key.len(),
+                max,
+            }),
+            (_, Some(max)) if data_len > max => Err(Error::ValueTooLarge {
+                database: database(),
+                key_preview: key_preview(),
+                len: data_len,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Bytes, Str};
+    use crate::{Database, EnvOpenOptions, Error, PutFlags, Result};
+
+    #[test]
+    fn size_limits() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Str, Bytes>();
+        options.name("limited").max_key_size(8).max_value_size(4);
+        let db = options.create(&mut wtxn)?;
+        let unlimited: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("unlimited"))?;
+
+        db.put(&mut wtxn, "12345678", b"1234")?;
+        let result = db.put(&mut wtxn, "123456789", b"1234");
+        assert!(matches!(
+            result,
+            Err(Error::KeyTooLarge { ref database, len: 9, max: 8, .. })
+                if database.as_deref() == Some("limited")
+        ));
+        let result = db.put_with_flags(&mut wtxn, PutFlags::empty(), "key", b"12345");
+        assert!(matches!(result, Err(Error::ValueTooLarge { len: 5, max: 4, .. })));
+        let result = db.put_reserved(&mut wtxn, "key", 5, |_| Ok(()));
+        assert!(matches!(result, Err(Error::ValueTooLarge { len: 5, max: 4, .. })));
+        unlimited.put(&mut wtxn, "123456789", b"12345")?;
+
+        // The limits apply to the other handles on the database.
+        let other: Database<Str, Bytes> = env.open_database(&wtxn, Some("limited"))?.unwrap();
+        let result = other.get_or_put(&mut wtxn, "key", b"12345");
+        assert!(matches!(result, Err(Error::ValueTooLarge { ref key_preview, .. })
+            if key_preview == b"key"));
+        assert_eq!(db.len(&wtxn)?, 1);
+        Ok(())
+    }
+}
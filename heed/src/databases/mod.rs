@@ -1,10 +1,34 @@
-pub use database::{Database, DatabaseOpenOptions};
+pub use blob::{BlobDatabase, BlobReader, BlobWriter};
+pub use database::{Database, DatabaseAny, DatabaseOpenOptions};
 #[cfg(master3)]
 pub use encrypted_database::{EncryptedDatabase, EncryptedDatabaseOpenOptions};
+pub use estimate::CountEstimate;
+pub use indexed::{ByIndex, IndexedDatabase, SecondaryIndex};
+pub use multi::{DupValues, MultiDatabase};
+pub use namespace::{Namespace, NamespaceIter};
+pub use schema::DatabaseSchema;
+pub use shadow::{ShadowDatabase, ShadowDivergence};
 
+#[cfg(feature = "roaring")]
+mod bitmap;
+mod blob;
 mod database;
+pub(crate) mod dump;
 #[cfg(master3)]
 mod encrypted_database;
+mod estimate;
+mod indexed;
+pub(crate) mod key_buffer;
+pub(crate) mod limits;
+mod multi;
+mod namespace;
+#[cfg(feature = "serde-json")]
+mod ndjson;
+mod reencode;
+mod schema;
+mod shadow;
+mod swap;
+mod versioned;
 
 /// Statistics for a database in the environment.
 #[derive(Debug, Clone, Copy)]
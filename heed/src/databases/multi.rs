@@ -0,0 +1,324 @@
+use std::fmt;
+
+use crate::iteration_method::MoveOnCurrentKeyDuplicates;
+use crate::types::DecodeIgnore;
+use crate::*;
+
+/// A `DUP_SORT` database, where every key maps to a sorted set of values.
+///
+/// It is created with [`DatabaseOpenOptions::create_multi`] or opened with
+/// [`DatabaseOpenOptions::open_multi`], which set the [`DatabaseFlags::DUP_SORT`] flag,
+/// and only exposes the operations on the values of a key. Storing the values of a key
+/// in a plain [`Database`] requires to set the flag, to delete with
+/// [`Database::delete_one_duplicate`] and to iterate with [`Database::get_duplicates`],
+/// else every value of the key is replaced or deleted. The other operations are
+/// available on the [`Self::database`].
+///
+/// The values are sorted with the comparator `CDUP`.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::types::*;
+/// use heed::byteorder::BigEndian;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let tags = env
+///     .database_options()
+///     .types::<Str, U32<BigEndian>>()
+///     .name("tags")
+///     .create_multi(&mut wtxn)?;
+///
+/// tags.put_dup(&mut wtxn, "rust", &3)?;
+/// tags.put_dup(&mut wtxn, "rust", &1)?;
+/// tags.put_dup(&mut wtxn, "rust", &2)?;
+/// tags.put_dup(&mut wtxn, "lmdb", &1)?;
+/// // A value is only stored once for a key.
+/// tags.put_dup(&mut wtxn, "rust", &1)?;
+///
+/// assert_eq!(tags.dup_count(&wtxn, "rust")?, 3);
+/// let documents: Vec<u32> = tags.iter_dups(&wtxn, "rust")?.collect::<heed::Result<_>>()?;
+/// assert_eq!(documents, [1, 2, 3]);
+///
+/// assert!(tags.delete_dup(&mut wtxn, "rust", &2)?);
+/// assert!(tags.delete_key(&mut wtxn, "lmdb")?);
+/// assert_eq!(tags.dup_count(&wtxn, "lmdb")?, 0);
+/// assert_eq!(tags.iter_dups(&wtxn, "lmdb")?.count(), 0);
+///
+/// wtxn.commit()?;
+/// # Ok(()) }
+/// ```
+pub struct MultiDatabase<KC, DC, C = DefaultComparator, CDUP = DefaultComparator> {
+    inner: Database<KC, DC, C, CDUP>,
+}
+
+impl<KC, DC, C, CDUP> MultiDatabase<KC, DC, C, CDUP> {
+    /// Wraps a database opened with the `DUP_SORT` flag.
+    pub(crate) fn new(inner: Database<KC, DC, C, CDUP>) -> Self {
+        MultiDatabase { inner }
+    }
+
+    /// The underlying `DUP_SORT` database, for the operations on the whole database.
+    pub fn database(&self) -> Database<KC, DC, C, CDUP> {
+        self.inner
+    }
+
+    /// Inserts a value of the key, it is a no-op if the key already has this value.
+    pub fn put_dup<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        value: &'a DC::EItem,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        self.inner.put(txn, key, value)
+    }
+
+    /// Inserts a value of the key, if the key doesn't already have this value.
+    ///
+    /// Returns `false` if the key already had this value.
+    pub fn put_if_new_dup<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        value: &'a DC::EItem,
+    ) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        self.inner.put_if_new_dup(txn, key, value)
+    }
+
+    /// Deletes a value of the key, the other values are kept.
+    ///
+    /// Returns `true` if the key had this value.
+    pub fn delete_dup<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        value: &'a DC::EItem,
+    ) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        self.inner.delete_one_duplicate(txn, key, value)
+    }
+
+    /// Deletes the key with all its values.
+    ///
+    /// Returns `true` if the key existed.
+    pub fn delete_key<'a>(&self, txn: &mut impl WriteTxn, key: &'a KC::EItem) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.inner.delete(txn, key)
+    }
+
+    /// Returns an iterator over the values of the key, sorted with the comparator `CDUP`.
+    ///
+    /// The iterator is empty if the key doesn't exist.
+    pub fn iter_dups<'a, 'txn>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        key: &'a KC::EItem,
+    ) -> Result<DupValues<'txn, DC>>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let iter = self.inner.get_duplicates(txn, key)?;
+        Ok(DupValues::new(iter.map(|iter| iter.remap_key_type::<DecodeIgnore>())))
+    }
+
+    /// Returns an iterator over the values of the key starting from the value at the given
+    /// index, sorted with the comparator `CDUP`.
+    ///
+    /// The iterator is empty if the key doesn't exist or has no value at this index.
+    pub fn iter_dups_from<'a, 'txn>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        key: &'a KC::EItem,
+        index: usize,
+    ) -> Result<DupValues<'txn, DC>>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let iter = self.inner.get_duplicates_from(txn, key, index)?;
+        Ok(DupValues::new(iter.map(|iter| iter.remap_key_type::<DecodeIgnore>())))
+    }
+
+    /// Returns the number of values of the key, zero if it doesn't exist.
+    pub fn dup_count<'a>(&self, txn: &impl ReadTxn, key: &'a KC::EItem) -> Result<usize>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.inner.count_duplicates(txn, key)
+    }
+}
+
+impl<KC, DC, C, CDUP> Clone for MultiDatabase<KC, DC, C, CDUP> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<KC, DC, C, CDUP> Copy for MultiDatabase<KC, DC, C, CDUP> {}
+
+impl<KC, DC, C, CDUP> fmt::Debug for MultiDatabase<KC, DC, C, CDUP> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MultiDatabase").field(&self.inner).finish()
+    }
+}
+
+/// An iterator over the values of a key, returned by [`MultiDatabase::iter_dups`]
+/// and [`Database::iter_groups`].
+pub struct DupValues<'txn, DC> {
+    iter: Option<RoIter<'txn, DecodeIgnore, DC, MoveOnCurrentKeyDuplicates>>,
+}
+
+impl<'txn, DC> DupValues<'txn, DC> {
+    pub(crate) fn new(
+        iter: Option<RoIter<'txn, DecodeIgnore, DC, MoveOnCurrentKeyDuplicates>>,
+    ) -> Self {
+        DupValues { iter }
+    }
+}
+
+impl<'txn, DC> Iterator for DupValues<'txn, DC>
+where
+    DC: BytesDecode<'txn>,
+{
+    type Item = Result<DC::DItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.iter.as_mut()?.next()?;
+        Some(result.map(|((), value)| value))
+    }
+}
+
+impl<DC> fmt::Debug for DupValues<'_, DC> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DupValues").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::BigEndian;
+
+    use super::*;
+    use crate::types::{FixedWidth, Str, U16, U32};
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn multi_database_flags() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Str, U32<BigEndian>>();
+        options.name("tags").flags(DatabaseFlags::DUP_FIXED);
+        let tags = options.create_multi(&mut wtxn)?;
+        let flags = tags.database().database_flags(&wtxn)?;
+        assert!(flags.contains(DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED));
+
+        for value in (0..1000).rev() {
+            assert!(tags.put_if_new_dup(&mut wtxn, "many", &value)?);
+        }
+        assert!(!tags.put_if_new_dup(&mut wtxn, "many", &500)?);
+        assert_eq!(tags.dup_count(&wtxn, "many")?, 1000);
+        assert_eq!(tags.dup_count(&wtxn, "none")?, 0);
+        let values: Vec<_> = tags.iter_dups(&wtxn, "many")?.collect::<Result<_>>()?;
+        assert_eq!(values, (0..1000).collect::<Vec<_>>());
+
+        // A database without duplicates can't be opened as a multi database.
+        env.create_database::<Str, Str>(&mut wtxn, Some("plain"))?;
+        options.name("plain");
+        assert!(matches!(options.open_multi(&wtxn), Err(Error::Mdb(MdbError::Incompatible))));
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        options.name("tags");
+        let tags = options.open_multi(&rtxn)?.unwrap();
+        assert_eq!(tags.dup_count(&rtxn, "many")?, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_fixed_database_sizes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Str, U32<BigEndian>>();
+        options.name("ids");
+        let ids = options.create_multi_fixed(&mut wtxn)?;
+        let flags = ids.database().database_flags(&wtxn)?;
+        assert!(flags.contains(DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED));
+        ids.put_dup(&mut wtxn, "one", &1)?;
+
+        // The values don't have the size of the encodings of the codec.
+        let mut narrow = env.database_options().types::<Str, U16<BigEndian>>();
+        narrow.name("ids");
+        assert!(matches!(narrow.open_multi_fixed(&wtxn), Err(Error::Mdb(MdbError::Incompatible))));
+
+        // A multi database without fixed size values can't be opened as one.
+        options.name("tags").create_multi(&mut wtxn)?;
+        let mut hashes = env.database_options().types::<Str, FixedWidth<[u8; 4]>>();
+        hashes.name("tags");
+        let result = hashes.create_multi_fixed(&mut wtxn);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        options.name("ids");
+        let ids = options.open_multi_fixed(&rtxn)?.unwrap();
+        assert_eq!(ids.iter_dups(&rtxn, "one")?.collect::<Result<Vec<_>>>()?, [1]);
+        Ok(())
+    }
+
+    #[test]
+    fn iter_dups_from_an_index() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Str, U32<BigEndian>>();
+        let fixed = options.name("fixed").create_multi_fixed(&mut wtxn)?;
+        let sorted = options.name("sorted").create_multi(&mut wtxn)?;
+        for value in 0..5000 {
+            fixed.put_dup(&mut wtxn, "many", &value)?;
+            sorted.put_dup(&mut wtxn, "many", &value)?;
+        }
+        fixed.put_dup(&mut wtxn, "one", &7)?;
+        sorted.put_dup(&mut wtxn, "one", &7)?;
+        wtxn.commit()?;
+
+        // The values of the fixed database are found by page, the others by moving from the
+        // first value or the last one.
+        let rtxn = env.read_txn()?;
+        for db in [fixed, sorted] {
+            for index in [0, 1, 1000, 2499, 2500, 4000, 4999] {
+                let page: Vec<_> =
+                    db.iter_dups_from(&rtxn, "many", index)?.take(3).collect::<Result<_>>()?;
+                let expected: Vec<_> = (index as u32..5000).take(3).collect();
+                assert_eq!(page, expected);
+            }
+            assert_eq!(db.iter_dups_from(&rtxn, "many", 5000)?.count(), 0);
+            assert_eq!(db.iter_dups_from(&rtxn, "one", 0)?.collect::<Result<Vec<_>>>()?, [7]);
+            assert_eq!(db.iter_dups_from(&rtxn, "one", 1)?.count(), 0);
+            assert_eq!(db.iter_dups_from(&rtxn, "none", 0)?.count(), 0);
+        }
+        Ok(())
+    }
+}
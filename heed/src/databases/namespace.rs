@@ -0,0 +1,300 @@
+use std::ops::{Bound, RangeBounds};
+use std::{fmt, marker};
+
+use heed_traits::LexicographicComparator;
+
+use crate::databases::key_buffer::{encode_range, KeyBuffer};
+use crate::types::{Bytes, DecodeIgnore};
+use crate::*;
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Returns a view of the entries whose keys start with the prefix, a virtual database
+    /// sharing this one with the other namespaces.
+    ///
+    /// The keys of the view are written with the prefix and read without it, the view can't
+    /// read or write an entry outside of its prefix. Multiplexing the tenants of an application
+    /// inside one database doesn't use the [`EnvOpenOptions::max_dbs`] slots, but the prefixes
+    /// must not be prefixes of one another: the keys of the `ab` namespace are in the `a` one.
+    /// Fixed size prefixes, like a big endian tenant id, avoid that.
+    ///
+    /// The duplicates of a `DUP_SORT` database are read as separate entries.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("settings"))?;
+    /// let kero = db.namespace(&1u64.to_be_bytes());
+    /// let tamo = db.namespace(&2u64.to_be_bytes());
+    ///
+    /// kero.put(&mut wtxn, "theme", "dark")?;
+    /// tamo.put(&mut wtxn, "theme", "light")?;
+    /// tamo.put(&mut wtxn, "lang", "fr")?;
+    ///
+    /// assert_eq!(kero.get(&wtxn, "theme")?, Some("dark"));
+    /// assert_eq!(kero.get(&wtxn, "lang")?, None);
+    /// let settings: Vec<_> = tamo.iter(&wtxn)?.collect::<heed::Result<_>>()?;
+    /// assert_eq!(settings, [("lang", "fr"), ("theme", "light")]);
+    /// assert_eq!(db.len(&wtxn)?, 3);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn namespace(&self, prefix: &[u8]) -> Namespace<KC, DC, C>
+    where
+        C: LexicographicComparator,
+    {
+        Namespace {
+            db: self.remap_key_type::<Bytes>(),
+            prefix: prefix.into(),
+            _marker: marker::PhantomData,
+        }
+    }
+}
+
+/// The entries of a [`Database`] whose keys start with a prefix, returned by
+/// [`Database::namespace`].
+pub struct Namespace<KC, DC, C = DefaultComparator> {
+    db: Database<Bytes, DC, C>,
+    prefix: Box<[u8]>,
+    _marker: marker::PhantomData<KC>,
+}
+
+impl<KC, DC, C> Namespace<KC, DC, C>
+where
+    C: LexicographicComparator,
+{
+    /// The prefix of the keys of the namespace.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Retrieves the value of a key of the namespace.
+    pub fn get<'a, 'txn>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        key: &'a KC::EItem,
+    ) -> Result<Option<DC::DItem>>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesDecode<'txn>,
+    {
+        self.db.get(txn, &self.prefixed(key)?)
+    }
+
+    /// Inserts an entry in the namespace, replacing the value of an existing key.
+    pub fn put<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        data: &'a DC::EItem,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+    {
+        let data = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        self.db.remap_data_type::<Bytes>().put(txn, &self.prefixed(key)?, &data)
+    }
+
+    /// Deletes a key of the namespace, returns `true` if it existed.
+    pub fn delete<'a>(&self, txn: &mut impl WriteTxn, key: &'a KC::EItem) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+    {
+        self.db.delete(txn, &self.prefixed(key)?)
+    }
+
+    /// Deletes all the entries of the namespace, the other namespaces are kept.
+    ///
+    /// Returns the number of deleted entries.
+    pub fn clear(&self, txn: &mut impl WriteTxn) -> Result<usize> {
+        self.db.delete_prefix(txn, &self.prefix)
+    }
+
+    /// Returns the number of entries of the namespace, found by iterating over them.
+    pub fn len(&self, txn: &impl ReadTxn) -> Result<u64> {
+        let mut len = 0;
+        for result in self.db.remap_data_type::<DecodeIgnore>().prefix_iter(txn, &self.prefix)? {
+            result?;
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// Returns `true` if the namespace has no entries.
+    pub fn is_empty(&self, txn: &impl ReadTxn) -> Result<bool> {
+        let mut iter = self.db.remap_data_type::<DecodeIgnore>().prefix_iter(txn, &self.prefix)?;
+        Ok(iter.next().transpose()?.is_none())
+    }
+
+    /// Returns an iterator over the entries of the namespace, ordered by key.
+    pub fn iter<'txn>(&self, txn: &'txn impl ReadTxn) -> Result<NamespaceIter<'txn, KC, DC, C>> {
+        self.bounded(txn, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Returns an iterator over a range of the entries of the namespace, ordered by key.
+    pub fn range<'a, 'txn, R>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        range: &'a R,
+    ) -> Result<NamespaceIter<'txn, KC, DC, C>>
+    where
+        KC: BytesEncode<'a>,
+        R: RangeBounds<KC::EItem>,
+    {
+        let (start, end) = encode_range::<KC, C, R>(range)?;
+        self.bounded(txn, start, end)
+    }
+
+    /// Iterates over the entries of the namespace between the bounds, given without the prefix.
+    fn bounded<'txn>(
+        &self,
+        txn: &'txn impl ReadTxn,
+        start: Bound<KeyBuffer>,
+        end: Bound<KeyBuffer>,
+    ) -> Result<NamespaceIter<'txn, KC, DC, C>> {
+        let start = match start {
+            Bound::Included(key) => Bound::Included(self.join(&key)),
+            Bound::Excluded(key) => Bound::Excluded(self.join(&key)),
+            Bound::Unbounded => Bound::Included(self.join(&[])),
+        };
+        // The iterator stops at the first key out of the namespace.
+        let end = match end {
+            Bound::Included(key) => Bound::Included(self.join(&key)),
+            Bound::Excluded(key) => Bound::Excluded(self.join(&key)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let bounds = (start.as_ref().map(|key| &key[..]), end.as_ref().map(|key| &key[..]));
+        Ok(NamespaceIter {
+            range: self.db.range(txn, &bounds)?,
+            prefix: self.prefix.clone(),
+            done: false,
+            _marker: marker::PhantomData,
+        })
+    }
+
+    fn prefixed<'a>(&self, key: &'a KC::EItem) -> Result<KeyBuffer>
+    where
+        KC: BytesEncode<'a>,
+    {
+        let mut bytes = KeyBuffer::from_slice(&self.prefix);
+        KC::bytes_encode_into(key, &mut bytes).map_err(Error::Encoding)?;
+        Ok(bytes)
+    }
+
+    fn join(&self, key: &[u8]) -> KeyBuffer {
+        let mut bytes = KeyBuffer::from_slice(&self.prefix);
+        bytes.extend_from_slice(key);
+        bytes
+    }
+}
+
+impl<KC, DC, C> Clone for Namespace<KC, DC, C> {
+    fn clone(&self) -> Self {
+        Namespace { db: self.db, prefix: self.prefix.clone(), _marker: marker::PhantomData }
+    }
+}
+
+impl<KC, DC, C> fmt::Debug for Namespace<KC, DC, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Namespace").field("db", &self.db).field("prefix", &self.prefix).finish()
+    }
+}
+
+/// An iterator over the entries of a [`Namespace`], returning the keys without the prefix.
+pub struct NamespaceIter<'txn, KC, DC, C = DefaultComparator> {
+    range: RoRange<'txn, Bytes, DC, C>,
+    prefix: Box<[u8]>,
+    done: bool,
+    _marker: marker::PhantomData<KC>,
+}
+
+impl<'txn, KC, DC, C> Iterator for NamespaceIter<'txn, KC, DC, C>
+where
+    KC: BytesDecode<'txn>,
+    DC: BytesDecode<'txn>,
+    C: Comparator,
+{
+    type Item = Result<(KC::DItem, DC::DItem)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.range.next()? {
+            Ok((key, data)) => match key.strip_prefix(&self.prefix[..]) {
+                Some(key) => {
+                    Some(KC::bytes_decode(key).map(|key| (key, data)).map_err(Error::Decoding))
+                }
+                None => {
+                    self.done = true;
+                    None
+                }
+            },
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<KC, DC, C> fmt::Debug for NamespaceIter<'_, KC, DC, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NamespaceIter").field("prefix", &self.prefix).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byteorder::BigEndian;
+    use crate::types::{Str, U32};
+
+    #[test]
+    fn namespaces_are_isolated() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<U32<BigEndian>, Str> = env.create_database(&mut wtxn, Some("tenants"))?;
+        db.remap_key_type::<Bytes>().put(&mut wtxn, b"", "before")?;
+        db.remap_key_type::<Bytes>().put(&mut wtxn, b"\xff", "after")?;
+        let (a, b, c) = (db.namespace(b"a"), db.namespace(b"b"), db.namespace(b"c"));
+        for i in 0..10 {
+            a.put(&mut wtxn, &i, "a")?;
+            b.put(&mut wtxn, &i, "b")?;
+        }
+        c.put(&mut wtxn, &u32::MAX, "c")?;
+
+        assert_eq!(b.get(&wtxn, &3)?, Some("b"));
+        assert_eq!(c.get(&wtxn, &3)?, None);
+        assert_eq!((a.len(&wtxn)?, c.len(&wtxn)?), (10, 1));
+        let keys: Vec<_> = b.iter(&wtxn)?.map(|r| r.map(|(k, _)| k)).collect::<Result<_>>()?;
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+        let entries: Vec<_> = b.range(&wtxn, &(7..))?.collect::<Result<_>>()?;
+        assert_eq!(entries, [(7, "b"), (8, "b"), (9, "b")]);
+        assert_eq!(a.range(&wtxn, &(..=1))?.count(), 2);
+        assert!(matches!(
+            a.range(&wtxn, &(Bound::Included(5), Bound::Excluded(2))),
+            Err(Error::InvalidRange)
+        ));
+
+        assert!(b.delete(&mut wtxn, &0)?);
+        assert!(!c.delete(&mut wtxn, &0)?);
+        assert_eq!(a.clear(&mut wtxn)?, 10);
+        assert!(a.is_empty(&wtxn)?);
+        assert_eq!(b.len(&wtxn)?, 9);
+        assert_eq!(db.len(&wtxn)?, 12);
+        assert_eq!(db.namespace(b"").len(&wtxn)?, 12);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::borrow::Borrow;
+use std::io::{self, BufWriter, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// A line of an NDJSON export.
+#[derive(Serialize, Deserialize)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Writes all the entries of this database as newline-delimited JSON,
+    /// one `{"key":…,"value":…}` object per line.
+    ///
+    /// The entries are decoded with the database codecs and serialized with serde.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<U32<BigEndian>, Str> = env.create_database(&mut wtxn, Some("numbers"))?;
+    /// db.put(&mut wtxn, &1, "one")?;
+    /// db.put(&mut wtxn, &2, "two")?;
+    ///
+    /// let mut export = Vec::new();
+    /// db.export_ndjson(&wtxn, &mut export)?;
+    /// assert_eq!(export, b"{\"key\":1,\"value\":\"one\"}\n{\"key\":2,\"value\":\"two\"}\n");
+    ///
+    /// db.clear(&mut wtxn)?;
+    /// let count = db.import_ndjson::<u32, String>(&mut wtxn, &export[..])?;
+    /// assert_eq!(count, 2);
+    /// assert_eq!(db.get(&wtxn, &2)?, Some("two"));
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn export_ndjson<'txn>(&self, rtxn: &'txn impl ReadTxn, writer: impl Write) -> Result<()>
+    where
+        KC: BytesDecode<'txn>,
+        DC: BytesDecode<'txn>,
+        KC::DItem: Serialize,
+        DC::DItem: Serialize,
+    {
+        let mut writer = BufWriter::new(writer);
+        for result in self.iter(rtxn)? {
+            let (key, value) = result?;
+            serde_json::to_writer(&mut writer, &Entry { key, value })
+                .map_err(|e| json_error(e, Error::Encoding))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Inserts the entries of a newline-delimited JSON export into this database.
+    ///
+    /// Every `{"key":…,"value":…}` object is deserialized as a `K` and a `V`
+    /// that are then encoded with the database codecs, `K` and `V` are the owned
+    /// versions of the codecs items, e.g. `String` for a [`Str`](types::Str) codec.
+    /// Returns the number of inserted entries.
+    pub fn import_ndjson<K, V>(&self, wtxn: &mut impl WriteTxn, reader: impl Read) -> Result<usize>
+    where
+        KC: for<'a> BytesEncode<'a>,
+        DC: for<'a> BytesEncode<'a>,
+        K: DeserializeOwned + for<'a> Borrow<<KC as BytesEncode<'a>>::EItem>,
+        V: DeserializeOwned + for<'a> Borrow<<DC as BytesEncode<'a>>::EItem>,
+    {
+        let mut count = 0;
+        let entries = serde_json::Deserializer::from_reader(reader).into_iter::<Entry<K, V>>();
+        for result in entries {
+            let Entry { key, value } = result.map_err(|e| json_error(e, Error::Decoding))?;
+            self.put(wtxn, key.borrow(), value.borrow())?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Converts a serde_json error into an I/O error if it comes from the I/O,
+/// or uses `f` to wrap it otherwise.
+fn json_error(error: serde_json::Error, f: fn(BoxedError) -> Error) -> Error {
+    if error.is_io() {
+        Error::Io(io::Error::from(error))
+    } else {
+        f(Box::new(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::BigEndian;
+
+    use super::*;
+    use crate::types::{SerdeJson, Str, U32};
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn export_and_import() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, SerdeJson<Vec<u32>>> =
+            env.create_database(&mut wtxn, Some("lists"))?;
+        db.put(&mut wtxn, "primes", &vec![2, 3, 5])?;
+        db.put(&mut wtxn, "empty", &vec![])?;
+
+        let mut export = Vec::new();
+        db.export_ndjson(&wtxn, &mut export)?;
+        assert_eq!(
+            String::from_utf8(export.clone()).unwrap(),
+            "{\"key\":\"empty\",\"value\":[]}\n{\"key\":\"primes\",\"value\":[2,3,5]}\n"
+        );
+
+        let copy: Database<Str, SerdeJson<Vec<u32>>> =
+            env.create_database(&mut wtxn, Some("copy"))?;
+        assert_eq!(copy.import_ndjson::<String, Vec<u32>>(&mut wtxn, &export[..])?, 2);
+        assert_eq!(copy.get(&wtxn, "primes")?, Some(vec![2, 3, 5]));
+
+        let numbers: Database<U32<BigEndian>, Str> =
+            env.create_database(&mut wtxn, Some("numbers"))?;
+        let result = numbers.import_ndjson::<u32, String>(&mut wtxn, &export[..]);
+        assert!(matches!(result, Err(Error::Decoding(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+use crate::cursor::{MoveOperation, RoCursor};
+use crate::mdb::error::mdb_result;
+use crate::mdb::ffi;
+use crate::meta::Metadata;
+use crate::types::Bytes;
+use crate::*;
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Re-encodes all the entries of this database with new codecs.
+    ///
+    /// Entries are decoded with the current codecs, encoded with `NKC` and `NDC` and written
+    /// into a temporary database, committing a write transaction every `batch_size` entries
+    /// to bound the size of the transactions. Once all the entries are re-encoded, this
+    /// database is cleared and the re-encoded entries are copied back into it, also by
+    /// batches of `batch_size` entries. Readers see the old entries until the database is
+    /// cleared, then a growing part of the new ones until the copy back is done.
+    ///
+    /// The copy back is recorded in the metadata database: if it is interrupted, calling
+    /// this method again with the same codecs finishes it instead of re-encoding the
+    /// entries already copied.
+    ///
+    /// The codecs must decode owned values, i.e. values that do not borrow the database bytes.
+    /// Returns this database typed with the new codecs, the previous handle must not be used anymore.
+    ///
+    /// Fails if a write transaction is already opened on this thread, and the environment
+    /// must have room for two more named databases, the temporary and the metadata ones.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    /// use heed::byteorder::{BigEndian, LittleEndian};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<U32<LittleEndian>, SerdeJson<Vec<u8>>> = env.create_database(&mut wtxn, Some("numbers"))?;
+    /// for i in 0..1000 {
+    ///     db.put(&mut wtxn, &i, &vec![i as u8; 3])?;
+    /// }
+    /// wtxn.commit()?;
+    ///
+    /// let db = db.reencode_to::<U32<BigEndian>, SerdeBincode<Vec<u8>>>(&env, 100)?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// assert_eq!(db.len(&rtxn)?, 1000);
+    /// assert_eq!(db.get(&rtxn, &256)?, Some(vec![0; 3]));
+    /// # Ok(()) }
+    /// ```
+    pub fn reencode_to<NKC, NDC>(
+        &self,
+        env: &Env<impl TlsUsage>,
+        batch_size: usize,
+    ) -> Result<Database<NKC, NDC, C, CDUP>>
+    where
+        KC: BytesDecodeOwned,
+        DC: BytesDecodeOwned,
+        NKC: for<'a> BytesEncode<'a, EItem = KC::DItem>,
+        NDC: for<'a> BytesEncode<'a, EItem = DC::DItem>,
+        C: Comparator + 'static,
+        CDUP: Comparator + 'static,
+    {
+        assert!(batch_size > 0, "the batch size must be greater than zero");
+
+        let source = self.remap_types::<Bytes, Bytes>();
+        let name = env.inner.database_names.read().unwrap().get(&self.dbi).cloned().flatten();
+        let temp_name = format!("__heed_reencode:{}", name.as_deref().unwrap_or_default());
+
+        let mut wtxn = env.write_txn()?;
+        check_env_db_txn!(self, wtxn);
+        let flags = self.database_flags(&wtxn)?;
+        let temp: Database<Bytes, Bytes, C, CDUP> = env
+            .database_options()
+            .types()
+            .key_comparator()
+            .dup_sort_comparator()
+            .name(&temp_name)
+            .flags(flags)
+            .create(&mut wtxn)?;
+        let metadata = Metadata::create(env, &mut wtxn)?;
+
+        if !metadata.copying_reencoded(&wtxn, name.as_deref())? {
+            // Leftovers of a re-encoding interrupted before the copy back.
+            temp.clear(&mut wtxn)?;
+
+            let mut position = None;
+            loop {
+                let entries = next_batch(source, &wtxn, &mut position, batch_size)?;
+                for (key, data) in &entries {
+                    let key = KC::bytes_decode_owned(key).map_err(Error::Decoding)?;
+                    let data = DC::bytes_decode_owned(data).map_err(Error::Decoding)?;
+                    let key = NKC::bytes_encode(&key).map_err(Error::Encoding)?;
+                    let data = NDC::bytes_encode(&data).map_err(Error::Encoding)?;
+                    temp.put(&mut wtxn, &key, &data)?;
+                }
+
+                if entries.len() < batch_size {
+                    break;
+                }
+
+                wtxn.commit()?;
+                wtxn = env.write_txn()?;
+            }
+
+            source.clear(&mut wtxn)?;
+            metadata.set_copying_reencoded(&mut wtxn, name.as_deref(), true)?;
+            wtxn.commit()?;
+            wtxn = env.write_txn()?;
+        }
+
+        // The entries are copied back in order, an interrupted copy resumes after the last one.
+        let mut position = source.last(&wtxn)?.map(|(key, data)| (key.to_vec(), data.to_vec()));
+        loop {
+            let entries = next_batch(temp, &wtxn, &mut position, batch_size)?;
+            for (key, data) in &entries {
+                source.put(&mut wtxn, key, data)?;
+            }
+
+            if entries.len() < batch_size {
+                break;
+            }
+
+            wtxn.commit()?;
+            wtxn = env.write_txn()?;
+        }
+
+        metadata.set_copying_reencoded(&mut wtxn, name.as_deref(), false)?;
+        // Safety: the temporary database handle is not shared and not used after that.
+        unsafe { temp.remove(&mut wtxn)? };
+        wtxn.commit()?;
+
+        Ok(Database::new(self.env_generation, self.dbi))
+    }
+
+    /// Returns the flags of this database.
+    pub(crate) fn database_flags(&self, txn: &impl ReadTxn) -> Result<DatabaseFlags> {
+        check_env_db_txn!(self, txn);
+
+        let mut flags = 0;
+        unsafe { mdb_result(ffi::mdb_dbi_flags(txn.txn_ptr().as_ptr(), self.dbi, &mut flags))? };
+        Ok(DatabaseFlags::from_bits_truncate(flags))
+    }
+}
+
+/// Copies all the entries of `src` into `dst`, reading `batch_size` entries at a time.
+pub(crate) fn copy_entries<C, CDUP, NC, NCDUP>(
+    src: Database<Bytes, Bytes, C, CDUP>,
+    dst: Database<Bytes, Bytes, NC, NCDUP>,
+    wtxn: &mut impl WriteTxn,
+    batch_size: usize,
+) -> Result<()>
+where
+    C: Comparator + 'static,
+    CDUP: Comparator + 'static,
+{
+    let mut position = None;
+    loop {
+        let entries = next_batch(src, wtxn, &mut position, batch_size)?;
+        for (key, data) in &entries {
+            dst.put(wtxn, key, data)?;
+        }
+        if entries.len() < batch_size {
+            return Ok(());
+        }
+    }
+}
+
+/// Copies at most `batch_size` entries following the given position and moves it forward.
+///
+/// The position is the last entry read, the batch starts right after it. In a `DUP_SORT`
+/// database the entry is looked up with `MDB_GET_BOTH_RANGE`, the duplicates already read
+/// are not iterated again.
+pub(crate) fn next_batch<C, CDUP>(
+    db: Database<Bytes, Bytes, C, CDUP>,
+    rtxn: &impl ReadTxn,
+    position: &mut Option<(Vec<u8>, Vec<u8>)>,
+    batch_size: usize,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>>
+where
+    C: Comparator + 'static,
+    CDUP: Comparator + 'static,
+{
+    check_env_db_txn!(db, rtxn);
+
+    let mut cursor = RoCursor::new(rtxn, db.dbi)?;
+    let mut entry = match position {
+        Some((key, data)) => {
+            let dup_sort = db.database_flags(rtxn)?.contains(DatabaseFlags::DUP_SORT);
+            move_after(&mut cursor, dup_sort, key, data)?
+        }
+        None => cursor.move_on_first(MoveOperation::Any)?,
+    };
+
+    let mut entries = Vec::with_capacity(batch_size);
+    while let Some((key, data)) = entry {
+        entries.push((key.to_vec(), data.to_vec()));
+        if entries.len() == batch_size {
+            break;
+        }
+        entry = cursor.move_on_next(MoveOperation::Any)?;
+    }
+
+    if let Some(last) = entries.last() {
+        *position = Some(last.clone());
+    }
+
+    Ok(entries)
+}
+
+/// Moves the cursor on the entry following the given one, which may have been deleted.
+fn move_after<'txn>(
+    cursor: &mut RoCursor<'txn>,
+    dup_sort: bool,
+    key: &[u8],
+    data: &[u8],
+) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+    if dup_sort {
+        match cursor.move_on_key_value_greater_than_or_equal_to(key, data)? {
+            Some(found) if found == data => return cursor.move_on_next(MoveOperation::Any),
+            Some(_) => return cursor.current(),
+            // The key doesn't exist anymore or all its values are lower.
+            None => (),
+        }
+    }
+
+    match cursor.move_on_key_greater_than_or_equal_to(key)? {
+        Some((found, _)) if found == key => cursor.move_on_next(MoveOperation::NoDup),
+        entry => Ok(entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, LittleEndian};
+
+    use super::*;
+    use crate::types::U32;
+
+    #[test]
+    fn reencode_duplicates_in_batches() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<U32<BigEndian>, U32<LittleEndian>> = env
+            .database_options()
+            .types()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        for key in 0..3 {
+            for data in 0..300 {
+                db.put(&mut wtxn, &key, &data)?;
+            }
+        }
+        wtxn.commit()?;
+
+        let db = db.reencode_to::<U32<BigEndian>, U32<BigEndian>>(&env, 7)?;
+        let rtxn = env.read_txn()?;
+        let entries: Vec<_> = db.iter(&rtxn)?.collect::<Result<_>>()?;
+        let expected: Vec<_> =
+            (0..3).flat_map(|key| (0..300).map(move |data| (key, data))).collect();
+        assert_eq!(entries, expected);
+
+        // The temporary database and the marker of the copy back are removed.
+        assert!(env.open_database::<Bytes, Bytes>(&rtxn, Some("__heed_reencode:dups"))?.is_none());
+        let metadata = Metadata::open(&env, &rtxn)?.unwrap();
+        assert!(!metadata.copying_reencoded(&rtxn, Some("dups"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn resume_interrupted_copy_back() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        // A re-encoding interrupted after copying back the first two entries.
+        let mut wtxn = env.write_txn()?;
+        let db: Database<U32<LittleEndian>, U32<BigEndian>> =
+            env.create_database(&mut wtxn, Some("db"))?;
+        let temp: Database<U32<BigEndian>, U32<BigEndian>> =
+            env.create_database(&mut wtxn, Some("__heed_reencode:db"))?;
+        for i in 0..10 {
+            temp.put(&mut wtxn, &i, &(i * 2))?;
+        }
+        let copied = db.remap_key_type::<U32<BigEndian>>();
+        copied.put(&mut wtxn, &0, &0)?;
+        copied.put(&mut wtxn, &1, &2)?;
+        Metadata::create(&env, &mut wtxn)?.set_copying_reencoded(&mut wtxn, Some("db"), true)?;
+        wtxn.commit()?;
+
+        // The entries already copied are not decoded with the old codecs again.
+        let db = db.reencode_to::<U32<BigEndian>, U32<BigEndian>>(&env, 4)?;
+        let rtxn = env.read_txn()?;
+        let entries: Vec<_> = db.iter(&rtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, (0..10).map(|i| (i, i * 2)).collect::<Vec<_>>());
+        assert!(env.open_database::<Bytes, Bytes>(&rtxn, Some("__heed_reencode:db"))?.is_none());
+        Ok(())
+    }
+}
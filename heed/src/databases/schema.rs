@@ -0,0 +1,35 @@
+use crate::{Env, ReadTxn, Result, WriteTxn};
+
+/// A set of databases that are opened, or created, all at once.
+///
+/// It is usually implemented with `#[derive(DatabaseSchema)]`, when the `derive` feature
+/// is enabled, on a struct whose fields are all [`Database`](crate::Database)s.
+/// Each database is named after its field, unless a name is given with
+/// `#[heed(name = "...")]`, and flags can be set with `#[heed(flags = "DUP_SORT | DUP_FIXED")]`.
+///
+/// ```ignore
+/// use heed::types::*;
+/// use heed::{Database, DatabaseSchema};
+///
+/// #[derive(DatabaseSchema)]
+/// struct Schema {
+///     users: Database<Str, SerdeJson<User>>,
+///     #[heed(name = "users-by-age", flags = "DUP_SORT")]
+///     by_age: Database<U8, Str>,
+/// }
+///
+/// let env = unsafe { EnvOpenOptions::new().max_dbs(Schema::DATABASE_NAMES.len() as u32).open(path)? };
+/// let mut wtxn = env.write_txn()?;
+/// let schema = Schema::open(&env, &mut wtxn)?;
+/// wtxn.commit()?;
+/// ```
+pub trait DatabaseSchema: Sized {
+    /// The names of the databases of this schema.
+    const DATABASE_NAMES: &'static [&'static str];
+
+    /// Opens all the databases of this schema, creating the missing ones.
+    fn open<T>(env: &Env<T>, wtxn: &mut impl WriteTxn) -> Result<Self>;
+
+    /// Opens all the databases of this schema, returning `None` if any of them does not exist.
+    fn open_existing<T>(env: &Env<T>, rtxn: &impl ReadTxn) -> Result<Option<Self>>;
+}
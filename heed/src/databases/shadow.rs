@@ -0,0 +1,584 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::mdb::ffi;
+use crate::types::Bytes;
+use crate::*;
+
+/// A [`Database`] whose writes are mirrored in an in-memory model its reads are compared with.
+///
+/// The shadow is meant to burn in new codecs and comparators in staging before trusting them
+/// with production data: every written key and value is decoded back and compared with itself,
+/// and the reads, all of them or one in [`Self::sample`], are compared with the model. A full
+/// scan also checks that the comparator orders the keys like the [`Ord`] implementation of
+/// the decoded keys. The differences are recorded as [`ShadowDivergence`]s.
+///
+/// The codecs must decode owned items, see [`BytesDecodeOwned`].
+///
+/// The model follows the commits of the write transactions by comparing their ids with the last
+/// committed one. A write transaction writing to the shadow and then aborted must be aborted with
+/// [`Self::abort`], unless the next one writing to the shadow is started after another commit.
+/// The writes of the nested transactions count as the writes of their parent, and the reads of
+/// the read transactions older than the last write to the shadow are not compared.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::byteorder::BigEndian;
+/// use heed::types::*;
+/// use heed::{Database, ShadowDatabase};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let scores: Database<U32<BigEndian>, U64<BigEndian>> =
+///     env.create_database(&mut wtxn, Some("scores"))?;
+/// let scores = ShadowDatabase::new(scores, &wtxn)?;
+/// scores.put(&mut wtxn, &300, &12)?;
+/// scores.put(&mut wtxn, &20, &7)?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// assert_eq!(scores.get(&rtxn, &20)?, Some(7));
+/// scores.verify(&rtxn)?;
+/// scores.assert_ok();
+/// # Ok(()) }
+/// ```
+pub struct ShadowDatabase<KC, DC, C = DefaultComparator>
+where
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+{
+    db: Database<KC, DC, C>,
+    one_in: u64,
+    check_order: bool,
+    state: Mutex<State<KC::DItem, DC::DItem>>,
+}
+
+/// The model of a shadow database.
+struct State<K, V> {
+    /// The entries written by the committed transactions.
+    committed: BTreeMap<K, V>,
+    /// The id of the last transaction whose writes are committed in the model.
+    committed_txn: usize,
+    /// The writes of the write transaction in progress, `None` for the deletions.
+    pending: Option<(usize, BTreeMap<K, Option<V>>)>,
+    reads: u64,
+    compared: u64,
+    divergences: Vec<ShadowDivergence>,
+}
+
+impl<K: Ord, V> State<K, V> {
+    /// Commits the pending writes in the model if their transaction committed,
+    /// and drops them if it was aborted by a transaction with another id.
+    fn resolve(&mut self, last_committed: usize, txn_id: usize) {
+        match self.pending.take() {
+            Some((pending_id, writes)) if last_committed >= pending_id => {
+                for (key, value) in writes {
+                    match value {
+                        Some(value) => self.committed.insert(key, value),
+                        None => self.committed.remove(&key),
+                    };
+                }
+                self.committed_txn = pending_id;
+            }
+            Some((pending_id, writes)) if pending_id == txn_id => {
+                self.pending = Some((pending_id, writes));
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns whether the transaction sees the model, it doesn't if it is older.
+    fn sees_model(&self, txn_id: usize) -> bool {
+        txn_id >= self.committed_txn
+    }
+
+    /// The writes seen by the transaction on top of the committed entries.
+    fn pending_for(&self, txn_id: usize) -> Option<&BTreeMap<K, Option<V>>> {
+        self.pending.as_ref().filter(|(id, _)| *id == txn_id).map(|(_, writes)| writes)
+    }
+}
+
+impl<KC, DC, C> ShadowDatabase<KC, DC, C>
+where
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+    KC::DItem: Ord + Clone + Debug,
+    DC::DItem: PartialEq + Clone + Debug,
+    C: Comparator + 'static,
+{
+    /// Wraps the database, its model starts with the entries seen by the transaction.
+    ///
+    /// Every read is compared with the model, see [`Self::sample`].
+    pub fn new(db: Database<KC, DC, C>, txn: &impl ReadTxn) -> Result<Self> {
+        let committed = read_entries(db, txn)?.into_iter().collect();
+        let state = State {
+            committed,
+            committed_txn: txn_id(txn),
+            pending: None,
+            reads: 0,
+            compared: 0,
+            divergences: Vec::new(),
+        };
+        Ok(ShadowDatabase { db, one_in: 1, check_order: true, state: Mutex::new(state) })
+    }
+
+    /// Compares only one read in `one_in` with the model, every read with one.
+    pub fn sample(&mut self, one_in: u64) -> &mut Self {
+        self.one_in = one_in.max(1);
+        self
+    }
+
+    /// Checks that the scans return the keys in the order of their [`Ord`] implementation,
+    /// `true` by default. Disable it for a comparator meant to order the keys differently.
+    pub fn check_order(&mut self, check: bool) -> &mut Self {
+        self.check_order = check;
+        self
+    }
+
+    /// The wrapped database.
+    pub fn database(&self) -> Database<KC, DC, C> {
+        self.db
+    }
+
+    /// Inserts an entry in the database and in the model, and checks that
+    /// the key and the value decode to themselves.
+    pub fn put<'a>(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        data: &'a DC::EItem,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+        KC::EItem: ToOwned<Owned = KC::DItem>,
+        DC::EItem: ToOwned<Owned = DC::DItem>,
+    {
+        let key_bytes = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let data_bytes = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        self.db.remap_types::<Bytes, Bytes>().put(wtxn, &key_bytes, &data_bytes)?;
+
+        let (key, data) = (key.to_owned(), data.to_owned());
+        let decoded_key = KC::bytes_decode_owned(&key_bytes).map_err(Error::Decoding)?;
+        let decoded_data = DC::bytes_decode_owned(&data_bytes).map_err(Error::Decoding)?;
+        let mut state = self.writing(wtxn);
+        if decoded_key != key || decoded_data != data {
+            state.divergences.push(ShadowDivergence::Codec {
+                written: format!("{key:?} = {data:?}"),
+                decoded: format!("{decoded_key:?} = {decoded_data:?}"),
+            });
+        }
+        state.pending.as_mut().unwrap().1.insert(key, Some(data));
+        Ok(())
+    }
+
+    /// Deletes an entry from the database and from the model, and checks that the
+    /// database and the model agree on whether the entry existed.
+    pub fn delete<'a>(&self, wtxn: &mut impl WriteTxn, key: &'a KC::EItem) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+        KC::EItem: ToOwned<Owned = KC::DItem>,
+    {
+        let key_bytes = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let deleted = self.db.remap_types::<Bytes, Bytes>().delete(wtxn, &key_bytes)?;
+
+        let id = txn_id(wtxn);
+        let key = key.to_owned();
+        let mut state = self.writing(wtxn);
+        let (committed, pending) = (&state.committed, state.pending_for(id));
+        let expected = match pending.and_then(|writes| writes.get(&key)) {
+            Some(value) => value.is_some(),
+            None => committed.contains_key(&key),
+        };
+        if expected != deleted {
+            let key = format!("{key:?}");
+            let (expected, found) = (expected.then(|| "an entry".into()), None);
+            state.divergences.push(ShadowDivergence::Value { txn_id: id, key, expected, found });
+        }
+        state.pending.as_mut().unwrap().1.insert(key, None);
+        Ok(deleted)
+    }
+
+    /// Deletes all the entries of the database and of the model.
+    pub fn clear(&self, wtxn: &mut impl WriteTxn) -> Result<()> {
+        self.db.clear(wtxn)?;
+        let mut state = self.writing(wtxn);
+        let keys: Vec<_> = state.committed.keys().cloned().collect();
+        let writes = &mut state.pending.as_mut().unwrap().1;
+        writes.values_mut().for_each(|value| *value = None);
+        writes.extend(keys.into_iter().map(|key| (key, None)));
+        Ok(())
+    }
+
+    /// Aborts the write transaction and drops its writes from the model.
+    pub fn abort(&self, wtxn: RwTxn) {
+        let id = wtxn.id();
+        wtxn.abort();
+        let mut state = self.state.lock().unwrap();
+        if state.pending.as_ref().is_some_and(|(pending_id, _)| *pending_id == id) {
+            state.pending = None;
+        }
+    }
+
+    /// Retrieves the value of a key, compared with the model if the read is sampled.
+    pub fn get<'a>(&self, txn: &impl ReadTxn, key: &'a KC::EItem) -> Result<Option<DC::DItem>>
+    where
+        KC: BytesEncode<'a>,
+        KC::EItem: ToOwned<Owned = KC::DItem>,
+    {
+        let key_bytes = KC::bytes_encode(key).map_err(Error::Encoding)?;
+        let found = match self.db.remap_types::<Bytes, Bytes>().get(txn, &key_bytes)? {
+            Some(bytes) => Some(DC::bytes_decode_owned(bytes).map_err(Error::Decoding)?),
+            None => None,
+        };
+
+        let id = txn_id(txn);
+        let mut state = self.reading(txn);
+        if self.sampled(&mut state, id) {
+            let key = key.to_owned();
+            let expected = match state.pending_for(id).and_then(|writes| writes.get(&key)) {
+                Some(value) => value.as_ref(),
+                None => state.committed.get(&key),
+            };
+            if expected != found.as_ref() {
+                let key = format!("{key:?}");
+                let expected = expected.map(|value| format!("{value:?}"));
+                let found = found.as_ref().map(|value| format!("{value:?}"));
+                state.divergences.push(ShadowDivergence::Value {
+                    txn_id: id,
+                    key,
+                    expected,
+                    found,
+                });
+            }
+        }
+        Ok(found)
+    }
+
+    /// Returns all the entries of the database, compared with the model if the read is sampled.
+    pub fn entries(&self, txn: &impl ReadTxn) -> Result<Vec<(KC::DItem, DC::DItem)>> {
+        let entries = read_entries(self.db, txn)?;
+        let id = txn_id(txn);
+        let mut state = self.reading(txn);
+        if self.sampled(&mut state, id) {
+            self.compare_entries(&mut state, id, &entries);
+        }
+        Ok(entries)
+    }
+
+    /// Compares all the entries of the database with the model, whatever the sampling.
+    ///
+    /// Nothing is compared if the transaction is older than the last write to the shadow.
+    pub fn verify(&self, txn: &impl ReadTxn) -> Result<()> {
+        let entries = read_entries(self.db, txn)?;
+        let id = txn_id(txn);
+        let mut state = self.reading(txn);
+        if state.sees_model(id) {
+            state.compared += 1;
+            self.compare_entries(&mut state, id, &entries);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of reads compared with the model.
+    pub fn compared_reads(&self) -> u64 {
+        self.state.lock().unwrap().compared
+    }
+
+    /// Returns the differences found between the database and the model.
+    pub fn divergences(&self) -> Vec<ShadowDivergence> {
+        self.state.lock().unwrap().divergences.clone()
+    }
+
+    /// Panics with the differences found between the database and the model, if any.
+    #[track_caller]
+    pub fn assert_ok(&self) {
+        let divergences = self.divergences();
+        if !divergences.is_empty() {
+            let divergences: Vec<_> = divergences.iter().map(ToString::to_string).collect();
+            panic!("the shadow database diverged:\n{}", divergences.join("\n"));
+        }
+    }
+
+    /// Locks the model for a write of the transaction.
+    fn writing(&self, wtxn: &impl ReadTxn) -> MutexGuard<'_, State<KC::DItem, DC::DItem>> {
+        let id = txn_id(wtxn);
+        let mut state = self.state.lock().unwrap();
+        state.resolve(last_committed_txn_id(wtxn), id);
+        state.pending.get_or_insert_with(|| (id, BTreeMap::new()));
+        state
+    }
+
+    /// Locks the model for a read of the transaction.
+    fn reading(&self, txn: &impl ReadTxn) -> MutexGuard<'_, State<KC::DItem, DC::DItem>> {
+        let mut state = self.state.lock().unwrap();
+        state.resolve(last_committed_txn_id(txn), txn_id(txn));
+        state
+    }
+
+    /// Counts the read and returns whether it must be compared with the model.
+    fn sampled(&self, state: &mut State<KC::DItem, DC::DItem>, txn_id: usize) -> bool {
+        state.reads += 1;
+        let sampled = state.reads % self.one_in == 0 && state.sees_model(txn_id);
+        state.compared += u64::from(sampled);
+        sampled
+    }
+
+    fn compare_entries(
+        &self,
+        state: &mut State<KC::DItem, DC::DItem>,
+        txn_id: usize,
+        entries: &[(KC::DItem, DC::DItem)],
+    ) {
+        let mut expected = state.committed.clone();
+        for (key, value) in state.pending_for(txn_id).into_iter().flatten() {
+            match value {
+                Some(value) => expected.insert(key.clone(), value.clone()),
+                None => expected.remove(key),
+            };
+        }
+
+        let mut found: Vec<_> = entries.iter().map(|(key, value)| (key, value)).collect();
+        if !self.check_order {
+            found.sort_by_key(|(key, _)| *key);
+        }
+        let mut expected = expected.iter();
+        for position in 0.. {
+            let (expected, found) = (expected.next(), found.get(position).copied());
+            if expected.is_none() && found.is_none() {
+                break;
+            }
+            if expected != found {
+                let describe = |entry: Option<(&KC::DItem, &DC::DItem)>| {
+                    entry.map(|(key, value)| format!("{key:?} = {value:?}"))
+                };
+                state.divergences.push(ShadowDivergence::Entries {
+                    txn_id,
+                    position,
+                    expected: describe(expected),
+                    found: describe(found),
+                });
+                break;
+            }
+        }
+    }
+}
+
+impl<KC, DC, C> fmt::Debug for ShadowDatabase<KC, DC, C>
+where
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShadowDatabase")
+            .field("db", &self.db)
+            .field("one_in", &self.one_in)
+            .field("check_order", &self.check_order)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A difference between a [`ShadowDatabase`] and its model.
+///
+/// The keys and values are described with their [`Debug`] implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShadowDivergence {
+    /// A written entry doesn't decode to itself.
+    Codec {
+        /// The written entry.
+        written: String,
+        /// The entry decoded from the encoded bytes.
+        decoded: String,
+    },
+    /// A read or a deletion didn't find the value of the model.
+    Value {
+        /// The id of the transaction.
+        txn_id: usize,
+        /// The key.
+        key: String,
+        /// The value of the model, if any.
+        expected: Option<String>,
+        /// The value of the database, if any.
+        found: Option<String>,
+    },
+    /// A scan returned other entries than the model, or in another order.
+    Entries {
+        /// The id of the transaction.
+        txn_id: usize,
+        /// The position of the first differing entry.
+        position: usize,
+        /// The entry of the model, if any.
+        expected: Option<String>,
+        /// The entry of the database, if any.
+        found: Option<String>,
+    },
+}
+
+impl fmt::Display for ShadowDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let or_none = |entry: &Option<String>| entry.clone().unwrap_or_else(|| "nothing".into());
+        match self {
+            ShadowDivergence::Codec { written, decoded } => {
+                write!(f, "{written} was written but {decoded} was decoded")
+            }
+            ShadowDivergence::Value { txn_id, key, expected, found } => write!(
+                f,
+                "transaction {txn_id} expected {} for the key {key} but found {}",
+                or_none(expected),
+                or_none(found)
+            ),
+            ShadowDivergence::Entries { txn_id, position, expected, found } => write!(
+                f,
+                "transaction {txn_id} expected {} at position {position} but found {}",
+                or_none(expected),
+                or_none(found)
+            ),
+        }
+    }
+}
+
+fn read_entries<KC, DC, C>(
+    db: Database<KC, DC, C>,
+    txn: &impl ReadTxn,
+) -> Result<Vec<(KC::DItem, DC::DItem)>>
+where
+    KC: BytesDecodeOwned,
+    DC: BytesDecodeOwned,
+{
+    let mut entries = Vec::new();
+    for result in db.remap_types::<Bytes, Bytes>().iter(txn)? {
+        let (key, data) = result?;
+        let key = KC::bytes_decode_owned(key).map_err(Error::Decoding)?;
+        let data = DC::bytes_decode_owned(data).map_err(Error::Decoding)?;
+        entries.push((key, data));
+    }
+    Ok(entries)
+}
+
+fn txn_id(txn: &impl ReadTxn) -> usize {
+    unsafe { ffi::mdb_txn_id(txn.txn_ptr().as_ptr()) }
+}
+
+/// The id of the last committed write transaction, the write transaction
+/// in progress has the next one.
+fn last_committed_txn_id(txn: &impl ReadTxn) -> usize {
+    let mut info = mem::MaybeUninit::uninit();
+    unsafe {
+        ffi::mdb_env_info(txn.env_mut_ptr().as_ptr(), info.as_mut_ptr());
+        info.assume_init().me_last_txnid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::byteorder::{BigEndian, LittleEndian};
+    use crate::types::{U32, U64};
+    use crate::BoxedError;
+
+    /// A codec losing the case of the strings.
+    struct Lowercase;
+
+    impl<'a> BytesEncode<'a> for Lowercase {
+        type EItem = str;
+
+        fn bytes_encode(item: &'a str) -> std::result::Result<Cow<'a, [u8]>, BoxedError> {
+            Ok(Cow::Owned(item.to_lowercase().into_bytes()))
+        }
+    }
+
+    impl<'a> BytesDecode<'a> for Lowercase {
+        type DItem = String;
+
+        fn bytes_decode(bytes: &'a [u8]) -> std::result::Result<String, BoxedError> {
+            Ok(std::str::from_utf8(bytes)?.to_owned())
+        }
+    }
+
+    #[test]
+    fn shadow_writes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<U32<BigEndian>, U64<BigEndian>> =
+            env.create_database(&mut wtxn, Some("be"))?;
+        db.put(&mut wtxn, &1, &10)?;
+        let shadow = ShadowDatabase::new(db, &wtxn)?;
+        shadow.put(&mut wtxn, &2, &20)?;
+        assert_eq!(shadow.get(&wtxn, &1)?, Some(10));
+        wtxn.commit()?;
+
+        // The writes of an aborted transaction are dropped from the model.
+        let mut wtxn = env.write_txn()?;
+        shadow.put(&mut wtxn, &3, &30)?;
+        assert!(shadow.delete(&mut wtxn, &1)?);
+        shadow.abort(wtxn);
+        let rtxn = env.read_txn()?;
+        assert_eq!(shadow.entries(&rtxn)?, [(1, 10), (2, 20)]);
+        drop(rtxn);
+
+        // A checkpoint commits the writes made before it.
+        let mut wtxn = env.write_txn()?;
+        shadow.clear(&mut wtxn)?;
+        wtxn.checkpoint()?;
+        shadow.put(&mut wtxn, &4, &40)?;
+        assert!(!shadow.delete(&mut wtxn, &1)?);
+        wtxn.commit()?;
+        shadow.verify(&env.read_txn()?)?;
+        shadow.assert_ok();
+        assert_eq!(shadow.compared_reads(), 3);
+
+        // A write behind the back of the shadow is found.
+        let mut wtxn = env.write_txn()?;
+        db.put(&mut wtxn, &5, &50)?;
+        wtxn.commit()?;
+        shadow.verify(&env.read_txn()?)?;
+        assert!(matches!(
+            &shadow.divergences()[..],
+            [ShadowDivergence::Entries { position: 1, expected: None, .. }]
+        ));
+
+        // A comparator disagreeing with the order of the keys, and a lossy codec.
+        let mut wtxn = env.write_txn()?;
+        let le: Database<U32<LittleEndian>, Lowercase> =
+            env.create_database(&mut wtxn, Some("le"))?;
+        let mut shadow = ShadowDatabase::new(le, &wtxn)?;
+        shadow.sample(2);
+        shadow.put(&mut wtxn, &1, "a")?;
+        shadow.put(&mut wtxn, &256, "B")?;
+        for _ in 0..4 {
+            shadow.get(&wtxn, &256)?;
+        }
+        shadow.verify(&wtxn)?;
+        assert_eq!(shadow.compared_reads(), 3);
+        let divergences = shadow.divergences();
+        assert_eq!(divergences.len(), 4);
+        assert_eq!(
+            divergences[0],
+            ShadowDivergence::Codec {
+                written: "256 = \"B\"".into(),
+                decoded: "256 = \"b\"".into()
+            }
+        );
+        assert!(matches!(divergences[3], ShadowDivergence::Entries { position: 0, .. }));
+
+        let mut le = ShadowDatabase::new(le, &wtxn)?;
+        le.check_order(false);
+        le.verify(&wtxn)?;
+        le.assert_ok();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use super::reencode::copy_entries;
+use crate::meta::{Metadata, META_DATABASE_NAME};
+use crate::types::Bytes;
+use crate::*;
+
+/// The number of entries read at once when copying a database.
+const COPY_BATCH_SIZE: usize = 1024;
+
+impl<T> Env<T> {
+    /// Exchanges the content of two named databases in the given write transaction.
+    ///
+    /// Once the transaction is committed, readers see the entries of `second` under the name
+    /// `first` and the other way around. It is useful to rebuild a database in a staging one
+    /// and then flip it live atomically. The codec identities recorded with
+    /// [`DatabaseOpenOptions::check_codecs`] are exchanged too.
+    ///
+    /// LMDB doesn't support renaming databases, the entries are therefore copied
+    /// through a temporary database and the cost of a swap is proportional to the
+    /// number of entries. Database handles are bound to names: a handle on `first`
+    /// reads the entries previously stored in `second` once the swap is done.
+    ///
+    /// Both databases must exist and have the same flags. If they use custom comparators,
+    /// they must have been opened with them in this program run before the swap.
+    /// The environment must have room for one more named database.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let live = env.create_database::<Str, Str>(&mut wtxn, Some("live"))?;
+    /// let staging = env.create_database::<Str, Str>(&mut wtxn, Some("staging"))?;
+    /// live.put(&mut wtxn, "version", "old")?;
+    /// staging.put(&mut wtxn, "version", "new")?;
+    ///
+    /// env.swap_databases(&mut wtxn, "live", "staging")?;
+    /// wtxn.commit()?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// assert_eq!(live.get(&rtxn, "version")?, Some("new"));
+    /// assert_eq!(staging.get(&rtxn, "version")?, Some("old"));
+    /// # Ok(()) }
+    /// ```
+    pub fn swap_databases(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        first: &str,
+        second: &str,
+    ) -> Result<()> {
+        assert_eq_env_txn!(self, wtxn);
+
+        let first_db = self.open_raw_database(wtxn, first)?;
+        let second_db = self.open_raw_database(wtxn, second)?;
+        if first == second {
+            return Ok(());
+        }
+
+        let flags = first_db.database_flags(wtxn)?;
+        if flags != second_db.database_flags(wtxn)? {
+            return Err(Error::Mdb(MdbError::Incompatible));
+        }
+
+        let temp: Database<Bytes, Bytes> =
+            self.database_options().types().name("__heed_swap").flags(flags).create(wtxn)?;
+        temp.clear(wtxn)?;
+
+        copy_entries(first_db, temp, wtxn, COPY_BATCH_SIZE)?;
+        first_db.clear(wtxn)?;
+        copy_entries(second_db, first_db, wtxn, COPY_BATCH_SIZE)?;
+        second_db.clear(wtxn)?;
+        copy_entries(temp, second_db, wtxn, COPY_BATCH_SIZE)?;
+        // Safety: the temporary database handle is not shared and not used after that.
+        unsafe { temp.remove(wtxn)? };
+
+        if let Some(metadata) = Metadata::open(self, wtxn)? {
+            let first_id = metadata.codec_identifier(wtxn, Some(first))?.map(ToOwned::to_owned);
+            let second_id = metadata.codec_identifier(wtxn, Some(second))?.map(ToOwned::to_owned);
+            for (name, identifier) in [(first, second_id), (second, first_id)] {
+                match identifier {
+                    Some(identifier) => {
+                        metadata.set_codec_identifier(wtxn, Some(name), &identifier)?
+                    }
+                    None => {
+                        metadata.delete_codec_identifier(wtxn, Some(name))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Opens an existing named database without types, fails if it doesn't exist.
+    fn open_raw_database(&self, rtxn: &impl ReadTxn, name: &str) -> Result<Database<Bytes, Bytes>> {
+        if name == META_DATABASE_NAME {
+            return Err(Error::Mdb(MdbError::Incompatible));
+        }
+        let database = self.database_options().types().name(name).open(rtxn)?;
+        database.ok_or(Error::Mdb(MdbError::NotFound))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn swap_dup_sort_databases_and_codecs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let mut options = env.database_options().types::<Str, Bytes>();
+        options.flags(DatabaseFlags::DUP_SORT).check_codecs(true);
+        let live = options.name("live").create(&mut wtxn)?;
+        live.put(&mut wtxn, "a", b"old")?;
+        let mut options = options.types::<Str, Str>();
+        options.check_codecs(true);
+        let staging = options.name("staging").create(&mut wtxn)?;
+        for data in ["x", "y", "z"] {
+            staging.put(&mut wtxn, "a", data)?;
+        }
+        let other = env.create_database::<Str, Str>(&mut wtxn, Some("other"))?;
+
+        env.swap_databases(&mut wtxn, "live", "staging")?;
+        assert!(matches!(
+            env.swap_databases(&mut wtxn, "live", "other"),
+            Err(Error::Mdb(MdbError::Incompatible))
+        ));
+        assert!(matches!(
+            env.swap_databases(&mut wtxn, "live", "unknown"),
+            Err(Error::Mdb(MdbError::NotFound))
+        ));
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let live = live.remap_data_type::<Str>();
+        let new: Vec<_> = live.get_duplicates(&rtxn, "a")?.unwrap().collect::<Result<_>>()?;
+        assert_eq!(new, vec![("a", "x"), ("a", "y"), ("a", "z")]);
+        assert_eq!(staging.len(&rtxn)?, 1);
+        assert!(other.is_empty(&rtxn)?);
+        assert!(env.open_database::<Bytes, Bytes>(&rtxn, Some("__heed_swap"))?.is_none());
+
+        // The codecs identities followed the entries.
+        assert!(options.name("live").open(&rtxn)?.is_some());
+        assert!(matches!(options.name("staging").open(&rtxn), Err(Error::CodecMismatch { .. })));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::databases::key_buffer::encode_key;
+use crate::types::{Bytes, DecodeIgnore, Versioned};
+use crate::*;
+
+impl<KC, DC, C, CDUP> Database<KC, Versioned<DC>, C, CDUP> {
+    /// Writes a value if the stored version of the key is the expected one, and returns
+    /// its new version, the expected one plus one.
+    ///
+    /// A missing key has the version `0`, the first write of a key stores the version `1`.
+    /// Fails with [`Error::VersionConflict`] if another writer changed the value since it
+    /// was read, the value and its new version must then be read again to retry the update.
+    /// The check and the write happen in the write transaction, which is exclusive across
+    /// the processes using the environment. Deleting a key resets its version.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::{Database, Error};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Versioned<Str>> = env.create_database(&mut wtxn, Some("profiles"))?;
+    /// assert_eq!(db.put_versioned(&mut wtxn, "kero", 0, "hello")?, 1);
+    ///
+    /// let (version, bio) = db.get(&wtxn, "kero")?.unwrap();
+    /// assert_eq!((version, bio), (1, "hello"));
+    /// assert_eq!(db.put_versioned(&mut wtxn, "kero", version, "hello world")?, 2);
+    ///
+    /// // The update based on the first version conflicts with the second one.
+    /// let result = db.put_versioned(&mut wtxn, "kero", 1, "bye");
+    /// assert!(matches!(result, Err(Error::VersionConflict { expected: 1, found: 2 })));
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn put_versioned<'a>(
+        &self,
+        txn: &mut impl WriteTxn,
+        key: &'a KC::EItem,
+        expected_version: u64,
+        data: &'a DC::EItem,
+    ) -> Result<u64>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+        C: Comparator,
+    {
+        let key = encode_key::<KC>(key)?;
+        let db = self.remap_types::<Bytes, Bytes>();
+        let found = match db.get(txn, &key)? {
+            Some(bytes) => {
+                let (version, ()) =
+                    Versioned::<DecodeIgnore>::bytes_decode(bytes).map_err(Error::Decoding)?;
+                version
+            }
+            None => 0,
+        };
+        if found != expected_version {
+            return Err(Error::VersionConflict { expected: expected_version, found });
+        }
+
+        // The layout of the `Versioned` codec, the version is a big-endian prefix.
+        let version = expected_version + 1;
+        let data = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        db.put(txn, &key, &[&version.to_be_bytes()[..], &data].concat())?;
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+
+    #[test]
+    fn versions_across_transactions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Versioned<Str>> = env.create_database(&mut wtxn, Some("docs"))?;
+        let result = db.put_versioned(&mut wtxn, "a", 3, "x");
+        assert!(matches!(result, Err(Error::VersionConflict { expected: 3, found: 0 })));
+        assert_eq!(db.put_versioned(&mut wtxn, "a", 0, "x")?, 1);
+        wtxn.commit()?;
+
+        let (version, _) = db.get(&env.read_txn()?, "a")?.unwrap();
+        let mut wtxn = env.write_txn()?;
+        assert_eq!(db.put_versioned(&mut wtxn, "a", version, "y")?, 2);
+        db.put(&mut wtxn, "b", &(7, "z"))?;
+        assert_eq!(db.put_versioned(&mut wtxn, "b", 7, "z")?, 8);
+        assert!(db.delete(&mut wtxn, "a")?);
+        assert_eq!(db.put_versioned(&mut wtxn, "a", 0, "x")?, 1);
+
+        // A value written without the codec has no version.
+        db.remap_data_type::<Bytes>().put(&mut wtxn, "c", b"abc")?;
+        assert!(matches!(db.put_versioned(&mut wtxn, "c", 0, "x"), Err(Error::Decoding(_))));
+        Ok(())
+    }
+}
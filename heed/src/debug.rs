@@ -0,0 +1,597 @@
+//! Low-level views of an environment, to investigate the reports of corruption.
+//!
+//! LMDB doesn't expose its pages, [`dump_pages`] reads them from the data file and follows
+//! the B-tree of a database from its root, like a debugger on the LMDB library would. The dump
+//! lists the page numbers, their types, their number of keys and free space and every entry
+//! with a hexdump of its key.
+//!
+//! ```
+//! use heed::debug::dump_pages;
+//! use heed::types::*;
+//! use heed::EnvOpenOptions;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+//! let mut wtxn = env.write_txn()?;
+//! let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+//! for i in 0..1000 {
+//!     db.put(&mut wtxn, &format!("user-{i:04}"), "kero")?;
+//! }
+//! wtxn.commit()?;
+//!
+//! let rtxn = env.read_txn()?;
+//! let dump = dump_pages(&rtxn, db, &b"user-0500"[..]..&b"user-0510"[..])?;
+//! println!("{dump}");
+//! assert_eq!(dump.pages[0].number, dump.root.unwrap());
+//! assert!(dump.pages.iter().flat_map(|page| &page.entries).any(|e| e.key == b"user-0505"));
+//! # Ok(()) }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs::File;
+use std::mem::{self, size_of, ManuallyDrop};
+use std::ops::{Bound, RangeBounds};
+use std::{fmt, io};
+
+use crate::analyze::{database, open_dbi};
+use crate::mdb::error::mdb_result;
+use crate::mdb::ffi::{self, into_val, MAIN_DBI};
+use crate::verify::Record;
+use crate::*;
+
+/// The magic number at the beginning of the meta pages.
+const MDB_MAGIC: u32 = 0xBEEF_C0DE;
+/// The size of a node header, before its key.
+const NODE_SIZE: usize = 8;
+/// The deepest tree followed, deeper pages are considered corrupted.
+const MAX_DEPTH: usize = 64;
+
+pub(crate) const P_BRANCH: u16 = 0x01;
+pub(crate) const P_LEAF: u16 = 0x02;
+const P_OVERFLOW: u16 = 0x04;
+pub(crate) const P_LEAF2: u16 = 0x20;
+
+const F_BIGDATA: u16 = 0x01;
+const F_SUBDATA: u16 = 0x02;
+const F_DUPDATA: u16 = 0x04;
+
+/// The pages of a database visited by [`dump_pages`], see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PageDump {
+    /// The size of a page.
+    pub page_size: usize,
+    /// The root page of the database, `None` if the database is empty.
+    pub root: Option<usize>,
+    /// The visited pages, depth first from the root.
+    pub pages: Vec<PageInfo>,
+}
+
+/// A page of a [`PageDump`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PageInfo {
+    /// The number of the page in the file.
+    pub number: usize,
+    /// The depth of the page in the tree, zero for the root.
+    pub depth: usize,
+    /// The type of the page.
+    pub kind: PageKind,
+    /// The number of keys stored in the page.
+    pub keys: usize,
+    /// The unused bytes of the page.
+    pub free_bytes: usize,
+    /// The entries of a branch page, and the entries of a leaf page in the dumped range.
+    pub entries: Vec<PageEntry>,
+}
+
+/// The type of a [`PageInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PageKind {
+    /// A page of keys pointing to the pages below.
+    Branch,
+    /// A page of keys and values.
+    Leaf,
+    /// A page of fixed size keys without values, the duplicates of a `DUP_FIXED` database.
+    LeafFixed {
+        /// The size of the keys.
+        key_size: usize,
+    },
+    /// The first of the contiguous pages storing a value too large for a leaf page.
+    Overflow {
+        /// The number of contiguous pages.
+        pages: usize,
+    },
+    /// A page that can't be read or decoded.
+    Invalid {
+        /// Why the page is invalid.
+        reason: &'static str,
+    },
+}
+
+/// An entry of a [`PageInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageEntry {
+    /// The key of the entry, empty for the first entry of a branch page.
+    pub key: Vec<u8>,
+    /// What the entry points to.
+    pub node: Node,
+}
+
+/// What a [`PageEntry`] points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Node {
+    /// A page below a branch page, holding the keys from this key to the next one.
+    Child {
+        /// The number of the page.
+        page: usize,
+    },
+    /// A value stored in the leaf page.
+    Value {
+        /// The size of the value.
+        len: usize,
+    },
+    /// A value stored in overflow pages.
+    Overflow {
+        /// The number of the first overflow page.
+        page: usize,
+        /// The size of the value.
+        len: usize,
+    },
+    /// The duplicates of the key, stored in a sub-page of the leaf page.
+    Duplicates {
+        /// The size of the sub-page.
+        len: usize,
+    },
+    /// The record of the tree storing the duplicates of the key.
+    DuplicatesTree,
+    /// The record of a named database, in the unnamed database.
+    Database,
+}
+
+/// Returns the pages of the database holding the keys of the range.
+///
+/// The pages are the ones of the snapshot of the transaction, the pages of a write
+/// transaction are not written to the file before its commit. The keys are compared with the
+/// comparator of the database, the branch pages list all their entries and the leaf pages only
+/// the entries of the range. The trees of the duplicates are not followed.
+///
+/// The pages of an encrypted environment can't be decoded.
+pub fn dump_pages<'a, T, KC, DC, C, CDUP>(
+    rtxn: &RoTxn<T>,
+    db: Database<KC, DC, C, CDUP>,
+    range: impl RangeBounds<&'a [u8]>,
+) -> Result<PageDump> {
+    db.check_env(rtxn)?;
+
+    let reader = PageReader::open(rtxn)?;
+    let page_size = reader.page_size;
+    let root = reader.root(rtxn, db.dbi)?;
+
+    let mut dumper = Dumper {
+        rtxn,
+        dbi: db.dbi,
+        reader,
+        start: range.start_bound().map(|key| key.to_vec()),
+        end: range.end_bound().map(|key| key.to_vec()),
+        visited: HashSet::new(),
+        pages: Vec::new(),
+    };
+    if let Some(root) = root {
+        dumper.page(root, 0);
+    }
+
+    Ok(PageDump { page_size, root, pages: dumper.pages })
+}
+
+/// Returns the root page of the opened named database, from its record in the unnamed database.
+fn named_root(rtxn: &impl ReadTxn, dbi: ffi::MDB_dbi) -> Result<usize> {
+    for result in database(rtxn, MAIN_DBI).iter(rtxn)? {
+        let (key, record) = result?;
+        let name = match std::str::from_utf8(key) {
+            Ok(name) if !name.contains('\0') => name,
+            _ => continue,
+        };
+        match open_dbi(rtxn, name) {
+            Ok(found) if found == dbi => {
+                return Record::decode(record).map(|r| r.root).ok_or(MdbError::Corrupted.into())
+            }
+            Ok(_) | Err(Error::Mdb(MdbError::Incompatible)) => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(MdbError::NotFound.into())
+}
+
+#[cfg(unix)]
+unsafe fn file_from_handle(fd: ffi::mdb_filehandle_t) -> File {
+    std::os::unix::io::FromRawFd::from_raw_fd(fd)
+}
+
+#[cfg(windows)]
+unsafe fn file_from_handle(handle: ffi::mdb_filehandle_t) -> File {
+    std::os::windows::io::FromRawHandle::from_raw_handle(handle)
+}
+
+/// Reads the pages of the data file, whose headers are 16 bytes long, or 24 bytes long
+/// when LMDB stores the transaction id of the pages.
+pub(crate) struct PageReader {
+    file: ManuallyDrop<File>,
+    pub(crate) page_size: usize,
+    pub(crate) header: usize,
+    main_root: usize,
+}
+
+impl PageReader {
+    /// Reads the data file of the environment of the transaction, with the descriptor of LMDB.
+    pub(crate) fn open<T>(rtxn: &RoTxn<T>) -> Result<PageReader> {
+        let env = rtxn.env_mut_ptr();
+        let mut stat = mem::MaybeUninit::uninit();
+        let page_size = unsafe {
+            ffi::mdb_env_stat(env.as_ptr(), stat.as_mut_ptr());
+            stat.assume_init().ms_psize as usize
+        };
+        let mut fd = mem::MaybeUninit::uninit();
+        let file = unsafe {
+            mdb_result(ffi::mdb_env_get_fd(env.as_ptr(), fd.as_mut_ptr()))?;
+            ManuallyDrop::new(file_from_handle(fd.assume_init()))
+        };
+        PageReader::new(file, page_size, rtxn.id())
+    }
+
+    /// Finds the size of the page headers and the meta page of the transaction.
+    fn new(file: ManuallyDrop<File>, page_size: usize, txn_id: usize) -> Result<PageReader> {
+        let word = size_of::<usize>();
+        let record = 8 + 5 * word;
+        let mut reader = PageReader { file, page_size, header: 0, main_root: 0 };
+        for number in 0..2 {
+            let page = reader.read(number)?;
+            let header = [2 * word, 3 * word]
+                .into_iter()
+                .find(|&h| page.len() >= h + 4 && u32_at(&page, h) == MDB_MAGIC)
+                .ok_or(MdbError::Invalid)?;
+            // The magic, the version, the fixed address and the map size precede the records
+            // of the freelist and the unnamed database, followed by the last page and the txn id.
+            let records = header + 8 + 2 * word;
+            let txn_at = records + 2 * record + word;
+            if page.len() >= txn_at + word && usize_at(&page, txn_at) == txn_id {
+                reader.header = header;
+                reader.main_root = usize_at(&page, records + record + 8 + 4 * word);
+                return Ok(reader);
+            }
+        }
+        Err(MdbError::Corrupted.into())
+    }
+
+    /// Returns the root page of the database, `None` if it is empty.
+    pub(crate) fn root(&self, rtxn: &impl ReadTxn, dbi: ffi::MDB_dbi) -> Result<Option<usize>> {
+        let root = if dbi == MAIN_DBI { self.main_root } else { named_root(rtxn, dbi)? };
+        Ok((root != usize::MAX).then_some(root))
+    }
+
+    pub(crate) fn read(&self, number: usize) -> io::Result<Vec<u8>> {
+        let mut page = vec![0; self.page_size];
+        read_exact_at(&self.file, &mut page, (number * self.page_size) as u64)?;
+        Ok(page)
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        match std::os::windows::fs::FileExt::seek_read(file, buf, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Dumper<'a, 'e, T> {
+    rtxn: &'a RoTxn<'e, T>,
+    dbi: ffi::MDB_dbi,
+    reader: PageReader,
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    visited: HashSet<usize>,
+    pages: Vec<PageInfo>,
+}
+
+impl<T> Dumper<'_, '_, T> {
+    fn cmp(&self, a: &[u8], b: &[u8]) -> Ordering {
+        let (a, b) = unsafe { (into_val(a), into_val(b)) };
+        unsafe { ffi::mdb_cmp(self.rtxn.txn_ptr().as_ptr(), self.dbi, &a, &b) }.cmp(&0)
+    }
+
+    /// Whether the keys from `lower` included to `upper` excluded can be in the range.
+    fn overlaps(&self, lower: Option<&[u8]>, upper: Option<&[u8]>) -> bool {
+        let after_start = match (&self.start, upper) {
+            (Bound::Included(start) | Bound::Excluded(start), Some(upper)) => {
+                self.cmp(start, upper) == Ordering::Less
+            }
+            _ => true,
+        };
+        let before_end = match (&self.end, lower) {
+            (Bound::Included(end), Some(lower)) => self.cmp(end, lower) != Ordering::Less,
+            (Bound::Excluded(end), Some(lower)) => self.cmp(end, lower) == Ordering::Greater,
+            _ => true,
+        };
+        after_start && before_end
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        let after_start = match &self.start {
+            Bound::Included(start) => self.cmp(key, start) != Ordering::Less,
+            Bound::Excluded(start) => self.cmp(key, start) == Ordering::Greater,
+            Bound::Unbounded => true,
+        };
+        let before_end = match &self.end {
+            Bound::Included(end) => self.cmp(key, end) != Ordering::Greater,
+            Bound::Excluded(end) => self.cmp(key, end) == Ordering::Less,
+            Bound::Unbounded => true,
+        };
+        after_start && before_end
+    }
+
+    fn invalid(&mut self, number: usize, depth: usize, reason: &'static str) {
+        let kind = PageKind::Invalid { reason };
+        self.pages.push(PageInfo { number, depth, kind, keys: 0, free_bytes: 0, entries: vec![] });
+    }
+
+    /// Dumps the page and the pages below it that can hold keys of the range.
+    fn page(&mut self, number: usize, depth: usize) {
+        if depth > MAX_DEPTH {
+            return self.invalid(number, depth, "the tree is too deep");
+        }
+        if !self.visited.insert(number) {
+            return self.invalid(number, depth, "the page is already in the tree");
+        }
+        let page = match self.reader.read(number) {
+            Ok(page) => page,
+            Err(_) => return self.invalid(number, depth, "the page is outside of the file"),
+        };
+
+        let header = self.reader.header;
+        let flags = u16_at(&page, header - 6);
+        let (lower, upper) = (u16_at(&page, header - 4) as usize, u16_at(&page, header - 2));
+        let keys = lower.saturating_sub(header) / 2;
+        let kind = if flags & P_LEAF2 != 0 {
+            PageKind::LeafFixed { key_size: u16_at(&page, header - 8) as usize }
+        } else if flags & P_BRANCH != 0 {
+            PageKind::Branch
+        } else if flags & P_LEAF != 0 {
+            PageKind::Leaf
+        } else {
+            return self.invalid(number, depth, "the page is neither a branch nor a leaf");
+        };
+        if lower < header || lower > upper as usize || upper as usize > page.len() {
+            return self.invalid(number, depth, "the free space is outside of the page");
+        }
+
+        let mut entries = Vec::new();
+        let mut children = Vec::new();
+        let mut overflows = Vec::new();
+        for i in 0..keys {
+            let (key, node) = match kind {
+                PageKind::LeafFixed { key_size } => {
+                    let at = header + i * key_size;
+                    match page.get(at..at + key_size) {
+                        Some(key) => (key, Node::Value { len: 0 }),
+                        None => return self.invalid(number, depth, "a key is outside of the page"),
+                    }
+                }
+                _ => match decode_node(&page, u16_at(&page, header + 2 * i) as usize, kind) {
+                    Some(node) => node,
+                    None => return self.invalid(number, depth, "a node is outside of the page"),
+                },
+            };
+            match node {
+                Node::Child { page: child } => {
+                    let lower = (i > 0).then_some(key);
+                    let upper = (i + 1 < keys)
+                        .then(|| u16_at(&page, header + 2 * (i + 1)) as usize)
+                        .and_then(|at| decode_node(&page, at, kind))
+                        .map(|(upper, _)| upper);
+                    if self.overlaps(lower, upper) {
+                        children.push(child);
+                    }
+                }
+                _ if !self.contains(key) => continue,
+                Node::Overflow { page, .. } => overflows.push(page),
+                _ => (),
+            }
+            // The key of the first entry of a branch page is ignored, the child
+            // holds the keys lower than the key of the second entry.
+            let key = if kind == PageKind::Branch && i == 0 { Vec::new() } else { key.to_vec() };
+            entries.push(PageEntry { key, node });
+        }
+
+        let free_bytes = upper as usize - lower;
+        self.pages.push(PageInfo { number, depth, kind, keys, free_bytes, entries });
+        for overflow in overflows {
+            self.overflow(overflow, depth + 1);
+        }
+        for child in children {
+            self.page(child, depth + 1);
+        }
+    }
+
+    fn overflow(&mut self, number: usize, depth: usize) {
+        let header = self.reader.header;
+        let kind = match self.reader.read(number) {
+            Ok(page) if u16_at(&page, header - 6) & P_OVERFLOW != 0 => {
+                PageKind::Overflow { pages: u32_at(&page, header - 4) as usize }
+            }
+            Ok(_) => return self.invalid(number, depth, "the page isn't an overflow page"),
+            Err(_) => return self.invalid(number, depth, "the page is outside of the file"),
+        };
+        self.pages.push(PageInfo { number, depth, kind, keys: 0, free_bytes: 0, entries: vec![] });
+    }
+}
+
+/// Decodes the node at the offset: the two halves of its size or of its child page,
+/// its flags and the size of its key, then its key followed by its value.
+pub(crate) fn decode_node(page: &[u8], at: usize, kind: PageKind) -> Option<(&[u8], Node)> {
+    let header = page.get(at..at + NODE_SIZE)?;
+    let (lo, hi) = if cfg!(target_endian = "little") {
+        (u16_at(header, 0), u16_at(header, 2))
+    } else {
+        (u16_at(header, 2), u16_at(header, 0))
+    };
+    let (flags, key_size) = (u16_at(header, 4), u16_at(header, 6) as usize);
+    let key = page.get(at + NODE_SIZE..at + NODE_SIZE + key_size)?;
+    let len = lo as usize | (hi as usize) << 16;
+
+    let node = if kind == PageKind::Branch {
+        // The flags hold the high bits of the page number.
+        let high = if size_of::<usize>() > 4 { (flags as usize) << 16 << 16 } else { 0 };
+        Node::Child { page: len | high }
+    } else if flags & F_BIGDATA != 0 {
+        let at = at + NODE_SIZE + key_size;
+        let page = usize::from_ne_bytes(page.get(at..at + size_of::<usize>())?.try_into().ok()?);
+        Node::Overflow { page, len }
+    } else if flags & F_DUPDATA != 0 && flags & F_SUBDATA != 0 {
+        Node::DuplicatesTree
+    } else if flags & F_DUPDATA != 0 {
+        Node::Duplicates { len }
+    } else if flags & F_SUBDATA != 0 {
+        Node::Database
+    } else {
+        Node::Value { len }
+    };
+    Some((key, node))
+}
+
+pub(crate) fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_ne_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_ne_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn usize_at(bytes: &[u8], at: usize) -> usize {
+    usize::from_ne_bytes(bytes[at..at + size_of::<usize>()].try_into().unwrap())
+}
+
+impl fmt::Display for PageDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.root {
+            Some(root) => writeln!(f, "root page {root}, pages of {} bytes", self.page_size)?,
+            None => writeln!(f, "empty database")?,
+        }
+        for page in &self.pages {
+            let indent = "  ".repeat(page.depth);
+            write!(f, "{indent}page {}: ", page.number)?;
+            match page.kind {
+                PageKind::Branch => write!(f, "branch")?,
+                PageKind::Leaf => write!(f, "leaf")?,
+                PageKind::LeafFixed { key_size } => write!(f, "leaf of {key_size} bytes keys")?,
+                PageKind::Overflow { pages } => {
+                    writeln!(f, "overflow of {pages} pages")?;
+                    continue;
+                }
+                PageKind::Invalid { reason } => {
+                    writeln!(f, "invalid, {reason}")?;
+                    continue;
+                }
+            }
+            writeln!(f, ", {} keys, {} bytes free", page.keys, page.free_bytes)?;
+            for entry in &page.entries {
+                write!(f, "{indent}  ")?;
+                if page.kind == PageKind::Branch && entry.key.is_empty() {
+                    write!(f, "<lowest>")?;
+                } else {
+                    for byte in &entry.key {
+                        write!(f, "{byte:02x}")?;
+                    }
+                    write!(f, " \"{}\"", entry.key.escape_ascii())?;
+                }
+                match entry.node {
+                    Node::Child { page } => writeln!(f, " -> page {page}")?,
+                    Node::Value { len } => writeln!(f, ", {len} bytes")?,
+                    Node::Overflow { page, len } => writeln!(f, ", {len} bytes in page {page}")?,
+                    Node::Duplicates { len } => writeln!(f, ", duplicates in {len} bytes")?,
+                    Node::DuplicatesTree => writeln!(f, ", duplicates in a tree")?,
+                    Node::Database => writeln!(f, ", database")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Bytes, Str};
+
+    #[test]
+    fn dump_database_pages() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let unnamed = env.create_database::<Str, Bytes>(&mut wtxn, None)?;
+        let users = env.create_database::<Str, Bytes>(&mut wtxn, Some("users"))?;
+        let empty = env.create_database::<Str, Bytes>(&mut wtxn, Some("empty"))?;
+        for i in 0..2000 {
+            users.put(&mut wtxn, &format!("user-{i:04}"), &[0; 100])?;
+        }
+        users.put(&mut wtxn, "user-1000", &vec![1; 3 * 4096])?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let stat = users.stat(&rtxn)?;
+        let dump = dump_pages(&rtxn, users, ..)?;
+        assert_eq!(dump.pages[0].kind, PageKind::Branch);
+        assert_eq!(dump.pages[0].number, dump.root.unwrap());
+        let count =
+            |kind: fn(&PageKind) -> bool| dump.pages.iter().filter(|p| kind(&p.kind)).count();
+        assert_eq!(count(|k| *k == PageKind::Branch), stat.branch_pages);
+        assert_eq!(count(|k| *k == PageKind::Leaf), stat.leaf_pages);
+        assert_eq!(count(|k| matches!(k, PageKind::Overflow { pages: 4 })), 1);
+        let leaves = dump.pages.iter().filter(|p| p.kind == PageKind::Leaf);
+        assert_eq!(leaves.clone().map(|p| p.keys).sum::<usize>(), 2000);
+        assert_eq!(leaves.map(|p| p.entries.len()).sum::<usize>(), 2000);
+        assert!(dump.to_string().contains("757365722d31303030 \"user-1000\", 12288 bytes in page"));
+
+        // Only the pages of the range are visited.
+        let range = (Bound::Excluded(&b"user-0999"[..]), Bound::Included(&b"user-1001"[..]));
+        let dump = dump_pages(&rtxn, users, range)?;
+        let entries: Vec<_> = dump
+            .pages
+            .iter()
+            .filter(|p| p.kind == PageKind::Leaf)
+            .flat_map(|p| &p.entries)
+            .map(|e| (e.key.as_slice(), e.node))
+            .collect();
+        assert!(matches!(entries[0], (b"user-1000", Node::Overflow { len: 12288, .. })));
+        assert!(matches!(entries[1], (b"user-1001", Node::Value { len: 100 })));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(dump.pages.iter().filter(|p| p.kind == PageKind::Branch).count(), 1);
+
+        // The unnamed database holds the records of the named ones.
+        let dump = dump_pages(&rtxn, unnamed, ..)?;
+        let keys: Vec<_> = dump.pages[0].entries.iter().map(|e| (&e.key[..], e.node)).collect();
+        assert_eq!(keys, [(&b"empty"[..], Node::Database), (b"users", Node::Database)]);
+        assert_eq!(
+            dump_pages(&rtxn, empty, ..)?,
+            PageDump { page_size: dump.page_size, root: None, pages: vec![] }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use std::ffi::c_void;
+use std::ops::Range;
+use std::{io, ptr};
+
+use crate::mdb::error::mdb_result;
+use crate::mdb::ffi;
+#[allow(unused)] // for cargo auto doc links
+use crate::EnvFlags;
+use crate::{Env, Result};
+
+/// The expected access pattern of the memory map, see [`Env::advise`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Advice {
+    /// The default readahead of the operating system.
+    Normal,
+    /// The pages are accessed in random order, the readahead is disabled.
+    ///
+    /// It is what the point lookups expect, the [`EnvFlags::NO_READ_AHEAD`] flag
+    /// does the same for the whole life of the environment.
+    Random,
+    /// The pages are accessed in sequential order, the readahead is more aggressive.
+    ///
+    /// It is what the full scans of the large databases expect.
+    Sequential,
+    /// The bytes of the range of the map will be accessed soon, they are read in advance.
+    ///
+    /// The range is in bytes from the start of the data file, clamped to the map size.
+    WillNeed(Range<usize>),
+}
+
+impl<T> Env<T> {
+    /// Advises the operating system of the expected access pattern of the memory map.
+    ///
+    /// The readahead of the pages not yet in the page cache makes the cold scans several
+    /// times faster, but it wastes the memory and the disk bandwidth of the point lookups.
+    /// The advice applies to the map of this environment in this process, until the next
+    /// advice or a resize of the map.
+    ///
+    /// It opens a read transaction, to find the map, and fails like [`Env::read_txn`]
+    /// if the thread already has one. The advice is ignored when the environment is empty.
+    /// It is only available on Unix, where it calls `madvise`.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::Advice;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("logs"))?;
+    /// db.put(&mut wtxn, "2024-01-01", "started")?;
+    /// wtxn.commit()?;
+    ///
+    /// env.advise(Advice::Sequential)?;
+    /// env.advise(Advice::WillNeed(0..1024 * 1024))?;
+    /// let rtxn = env.read_txn()?;
+    /// assert_eq!(db.iter(&rtxn)?.count(), 1);
+    /// drop(rtxn);
+    /// env.advise(Advice::Normal)?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(unix)]
+    pub fn advise(&self, advice: Advice) -> Result<()> {
+        let Some(map) = self.map_address()? else { return Ok(()) };
+        let map_size = self.info().map_size;
+        let page_size = self.stat().page_size as usize;
+
+        let (range, advice) = match advice {
+            Advice::Normal => (0..map_size, libc::MADV_NORMAL),
+            Advice::Random => (0..map_size, libc::MADV_RANDOM),
+            Advice::Sequential => (0..map_size, libc::MADV_SEQUENTIAL),
+            Advice::WillNeed(range) => {
+                // The advised address must be aligned on a page.
+                let start = range.start.min(map_size) / page_size * page_size;
+                (start..range.end.min(map_size), libc::MADV_WILLNEED)
+            }
+        };
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let address = map.wrapping_add(range.start).cast::<c_void>();
+        if unsafe { libc::madvise(address, range.len(), advice) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Returns the address of the memory map, `None` if the environment is empty.
+    ///
+    /// LMDB only reports the address of the fixed maps, it is otherwise found from the page
+    /// of the first entry of the unnamed database, as the header of a page starts with its
+    /// number and the pages are aligned on the size of the pages of the system.
+    #[cfg(unix)]
+    fn map_address(&self) -> Result<Option<*mut u8>> {
+        let info = self.info();
+        if !info.map_addr.is_null() {
+            return Ok(Some(info.map_addr.cast()));
+        }
+
+        let rtxn = self.read_txn()?;
+        let mut cursor = ptr::null_mut();
+        let mut key = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+        let mut data = ffi::MDB_val { mv_size: 0, mv_data: ptr::null_mut() };
+        let result = unsafe {
+            mdb_result(ffi::mdb_cursor_open(rtxn.txn_ptr().as_mut(), ffi::MAIN_DBI, &mut cursor))?;
+            let result = mdb_result(ffi::mdb_cursor_get(
+                cursor,
+                &mut key,
+                &mut data,
+                ffi::cursor_op::MDB_FIRST,
+            ));
+            ffi::mdb_cursor_close(cursor);
+            result
+        };
+        match result {
+            Ok(()) => (),
+            Err(e) if e.not_found() => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let page_size = self.stat().page_size as usize;
+        let page = (key.mv_data as usize) / page_size * page_size;
+        // SAFETY: The key is stored in a page of the map, which is alive while the transaction
+        // is, and starts with the number of the page.
+        let page_number = unsafe { ptr::read(page as *const usize) };
+        Ok(Some((page - page_number * page_size) as *mut u8))
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use crate::mdb::lmdb_flags::AllDatabaseFlags;
+use crate::types::Bytes;
+use crate::*;
+
+/// The bytes at the start of every archive.
+const ARCHIVE_MAGIC: &[u8; 8] = b"HEEDARCH";
+/// The version of the archive format.
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Starts the entries of a database: its name and flags.
+pub(super) const DATABASE_TAG: u8 = b'D';
+/// An entry of the current database: its key and data.
+const ENTRY_TAG: u8 = b'E';
+/// The end of the archive.
+pub(super) const END_TAG: u8 = b'Z';
+
+/// The flags that define how the entries of a database are stored.
+pub(super) const ARCHIVED_FLAGS: AllDatabaseFlags = AllDatabaseFlags::REVERSE_KEY
+    .union(AllDatabaseFlags::DUP_SORT)
+    .union(AllDatabaseFlags::INTEGER_KEY)
+    .union(AllDatabaseFlags::DUP_FIXED)
+    .union(AllDatabaseFlags::INTEGER_DUP)
+    .union(AllDatabaseFlags::REVERSE_DUP);
+
+impl<T> Env<T> {
+    /// Writes all the databases of this environment into a portable archive.
+    ///
+    /// The archive contains the name, the flags and the entries of every database,
+    /// including the unnamed one and the [metadata](crate::meta) database with the schema
+    /// version and the codec identifiers. It is read in a single read transaction and is
+    /// therefore a consistent snapshot of the environment. Unlike [`Env::copy_to_file`],
+    /// the archive doesn't depend on the page size or on the endianness of the platform,
+    /// it can be streamed and loaded with [`Env::import_archive`] by any LMDB build.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+    /// db.put(&mut wtxn, "kero", "admin")?;
+    /// wtxn.commit()?;
+    ///
+    /// let mut archive = Vec::new();
+    /// env.export_archive(&mut archive)?;
+    ///
+    /// # let other_dir = tempfile::tempdir()?;
+    /// let other = unsafe { EnvOpenOptions::new().max_dbs(10).open(other_dir.path())? };
+    /// other.import_archive(&archive[..])?;
+    ///
+    /// let rtxn = other.read_txn()?;
+    /// let db = other.open_database::<Str, Str>(&rtxn, Some("users"))?.unwrap();
+    /// assert_eq!(db.get(&rtxn, "kero")?, Some("admin"));
+    /// # Ok(()) }
+    /// ```
+    pub fn export_archive(&self, writer: impl Write) -> Result<()> {
+        let rtxn = self.read_txn()?;
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_be_bytes())?;
+        writer.write_all(&[cfg!(target_endian = "big") as u8])?;
+
+        let names = self.database_names(&rtxn)?;
+        let unnamed: Database<Bytes, Bytes> =
+            self.database_options().types().open(&rtxn)?.ok_or(MdbError::NotFound)?;
+
+        // The names of the named databases are keys of the unnamed one, they are not entries.
+        let name_set: HashSet<&[u8]> = names.iter().map(|name| name.as_bytes()).collect();
+        let mut header_written = false;
+        for result in unnamed.iter(&rtxn)? {
+            let (key, data) = result?;
+            if name_set.contains(key) {
+                continue;
+            }
+            if !header_written {
+                write_database_header(&mut writer, None, unnamed.database_flags(&rtxn)?)?;
+                header_written = true;
+            }
+            write_entry(&mut writer, key, data)?;
+        }
+
+        for name in &names {
+            let database: Database<Bytes, Bytes> = self
+                .database_options()
+                .types()
+                .name(name)
+                .open(&rtxn)?
+                .ok_or(MdbError::NotFound)?;
+            write_database_header(&mut writer, Some(name), database.database_flags(&rtxn)?)?;
+            for result in database.iter(&rtxn)? {
+                let (key, data) = result?;
+                write_entry(&mut writer, key, data)?;
+            }
+        }
+
+        writer.write_all(&[END_TAG])?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads an archive written by [`Env::export_archive`] into this environment.
+    ///
+    /// The databases are created with the flags recorded in the archive and the entries are
+    /// inserted in a single write transaction, either the whole archive is loaded or nothing.
+    /// The archive is meant to be loaded into an empty environment, the entries of existing
+    /// databases are kept or overwritten and it fails with [`MdbError::Incompatible`] if their
+    /// flags differ from the archived ones. Databases using custom comparators must be created,
+    /// with their comparators, before loading the archive.
+    ///
+    /// Malformed archives fail with an [`io::ErrorKind::InvalidData`] error.
+    /// Fails if a write transaction is already opened on this thread.
+    pub fn import_archive(&self, reader: impl Read) -> Result<()> {
+        let mut reader = BufReader::new(reader);
+        let mut magic = [0; ARCHIVE_MAGIC.len()];
+        reader.read_exact(&mut magic).map_err(truncated_archive)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(invalid_archive("not a heed archive").into());
+        }
+        if read_u32(&mut reader)? != ARCHIVE_VERSION {
+            return Err(invalid_archive("unsupported archive version").into());
+        }
+        let swap_integers = read_swap_integers(&mut reader)?;
+
+        let mut wtxn = self.write_txn()?;
+        let mut current = None;
+        let mut key = Vec::new();
+        let mut data = Vec::new();
+        loop {
+            match read_u8(&mut reader)? {
+                DATABASE_TAG => {
+                    let (name, flags) = read_database_header(&mut reader)?;
+                    let database = self.create_archived_database(&mut wtxn, name, flags)?;
+                    current = Some((database, flags));
+                }
+                ENTRY_TAG => {
+                    let (database, flags) =
+                        current.ok_or_else(|| invalid_archive("entry outside of a database"))?;
+                    read_bytes(&mut reader, &mut key)?;
+                    read_bytes(&mut reader, &mut data)?;
+                    if swap_integers {
+                        if flags.contains(AllDatabaseFlags::INTEGER_KEY) {
+                            key.reverse();
+                        }
+                        if flags.contains(AllDatabaseFlags::INTEGER_DUP) {
+                            data.reverse();
+                        }
+                    }
+                    database.put(&mut wtxn, &key, &data)?;
+                }
+                END_TAG => break,
+                _ => return Err(invalid_archive("invalid record").into()),
+            }
+        }
+
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// Creates a database read from an archive, ensuring that its flags are the archived ones.
+    pub(super) fn create_archived_database(
+        &self,
+        wtxn: &mut RwTxn,
+        name: Option<String>,
+        flags: AllDatabaseFlags,
+    ) -> Result<Database<Bytes, Bytes>> {
+        let mut options = self.database_options().types::<Bytes, Bytes>();
+        options.flags(DatabaseFlags::from_bits_truncate(flags.bits()));
+        if let Some(name) = &name {
+            options.name(name);
+        }
+        let database = options.create(wtxn)?;
+        let stored = AllDatabaseFlags::from_bits_truncate(database.database_flags(wtxn)?.bits());
+        if stored & ARCHIVED_FLAGS != flags {
+            return Err(Error::Mdb(MdbError::Incompatible));
+        }
+        Ok(database)
+    }
+
+    /// Returns the names of all the named databases of this environment.
+    pub(crate) fn database_names(&self, rtxn: &impl ReadTxn) -> Result<Vec<String>> {
+        let unnamed: Database<Bytes, Bytes> = match self.database_options().types().open(rtxn)? {
+            Some(database) => database,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut names = Vec::new();
+        for result in unnamed.iter(rtxn)? {
+            let (key, _) = result?;
+            let name = match std::str::from_utf8(key) {
+                Ok(name) if !name.contains('\0') => name,
+                _ => continue,
+            };
+            // The keys that are not database names can't be opened as databases.
+            match self.database_options().types::<Bytes, Bytes>().name(name).open(rtxn) {
+                Ok(Some(_)) => names.push(name.to_owned()),
+                Ok(None) | Err(Error::Mdb(MdbError::Incompatible)) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(names)
+    }
+}
+
+pub(super) fn write_database_header(
+    writer: &mut impl Write,
+    name: Option<&str>,
+    flags: DatabaseFlags,
+) -> io::Result<()> {
+    writer.write_all(&[DATABASE_TAG])?;
+    match name {
+        Some(name) => {
+            writer.write_all(&[1])?;
+            write_bytes(writer, name.as_bytes())?;
+        }
+        None => writer.write_all(&[0])?,
+    }
+    let flags = AllDatabaseFlags::from_bits_truncate(flags.bits()) & ARCHIVED_FLAGS;
+    writer.write_all(&flags.bits().to_be_bytes())
+}
+
+fn write_entry(writer: &mut impl Write, key: &[u8], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&[ENTRY_TAG])?;
+    write_bytes(writer, key)?;
+    write_bytes(writer, data)
+}
+
+/// Writes the length of the bytes, as a big-endian `u64`, followed by the bytes.
+pub(super) fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_be_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Reads the endianness byte of an archive,
+/// returns whether the integer keys and duplicates must be swapped.
+pub(super) fn read_swap_integers(reader: &mut impl Read) -> io::Result<bool> {
+    let big_endian = match read_u8(reader)? {
+        0 => false,
+        1 => true,
+        _ => return Err(invalid_archive("invalid endianness")),
+    };
+    Ok(big_endian != cfg!(target_endian = "big"))
+}
+
+/// Reads the name and the flags of a database, after its tag.
+pub(super) fn read_database_header(
+    reader: &mut impl Read,
+) -> io::Result<(Option<String>, AllDatabaseFlags)> {
+    let name = match read_u8(reader)? {
+        0 => None,
+        1 => {
+            let mut name = Vec::new();
+            read_bytes(reader, &mut name)?;
+            Some(String::from_utf8(name).map_err(|_| invalid_archive("invalid name"))?)
+        }
+        _ => return Err(invalid_archive("invalid database name")),
+    };
+    let flags = AllDatabaseFlags::from_bits(read_u32(reader)?)
+        .filter(|flags| ARCHIVED_FLAGS.contains(*flags))
+        .ok_or_else(|| invalid_archive("invalid database flags"))?;
+    Ok((name, flags))
+}
+
+pub(super) fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte).map_err(truncated_archive)?;
+    Ok(byte[0])
+}
+
+pub(super) fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(truncated_archive)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+pub(super) fn read_bytes(reader: &mut impl Read, output: &mut Vec<u8>) -> io::Result<()> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len).map_err(truncated_archive)?;
+    let len = u64::from_be_bytes(len);
+    output.clear();
+    let read = reader.take(len).read_to_end(output)?;
+    if read as u64 != len {
+        return Err(invalid_archive("truncated archive"));
+    }
+    Ok(())
+}
+
+pub(super) fn truncated_archive(error: io::Error) -> io::Error {
+    if error.kind() == io::ErrorKind::UnexpectedEof {
+        invalid_archive("truncated archive")
+    } else {
+        error
+    }
+}
+
+pub(super) fn invalid_archive(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+
+    #[test]
+    fn export_and_import() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        env.migrate(3, |_| Result::Ok(()))?;
+
+        let mut wtxn = env.write_txn()?;
+        let unnamed = env.create_database::<Str, Str>(&mut wtxn, None)?;
+        unnamed.put(&mut wtxn, "hello", "world")?;
+        let mut options = env.database_options().types::<Str, Str>();
+        options.name("tags").flags(DatabaseFlags::DUP_SORT);
+        let tags = options.create(&mut wtxn)?;
+        tags.put(&mut wtxn, "rust", "fast")?;
+        tags.put(&mut wtxn, "rust", "safe")?;
+        env.create_database::<Str, Str>(&mut wtxn, Some("empty"))?;
+        wtxn.commit()?;
+
+        let mut archive = Vec::new();
+        env.export_archive(&mut archive)?;
+
+        let other_dir = tempfile::tempdir()?;
+        let other = unsafe { EnvOpenOptions::new().max_dbs(10).open(other_dir.path())? };
+        other.import_archive(&archive[..])?;
+
+        let rtxn = other.read_txn()?;
+        let mut names = other.database_names(&rtxn)?;
+        names.sort();
+        assert_eq!(names, ["__heed_meta", "empty", "tags"]);
+        let unnamed = other.open_database::<Str, Str>(&rtxn, None)?.unwrap();
+        assert_eq!(unnamed.get(&rtxn, "hello")?, Some("world"));
+        let tags = other.open_database::<Str, Str>(&rtxn, Some("tags"))?.unwrap();
+        assert_eq!(tags.database_flags(&rtxn)?, DatabaseFlags::DUP_SORT);
+        let values: Vec<_> = tags.get_duplicates(&rtxn, "rust")?.unwrap().collect::<Result<_>>()?;
+        assert_eq!(values, [("rust", "fast"), ("rust", "safe")]);
+        let metadata = meta::Metadata::open(&other, &rtxn)?.unwrap();
+        assert_eq!(metadata.schema_version(&rtxn)?, Some(3));
+        drop(rtxn);
+
+        // Truncated archives are rejected and nothing is loaded.
+        let third_dir = tempfile::tempdir()?;
+        let third = unsafe { EnvOpenOptions::new().max_dbs(10).open(third_dir.path())? };
+        let result = third.import_archive(&archive[..archive.len() - 1]);
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidData));
+        assert!(third.database_names(&third.read_txn()?)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use super::archive::{
+    invalid_archive, read_bytes, read_database_header, read_swap_integers, read_u32, read_u8,
+    truncated_archive, write_bytes, write_database_header, DATABASE_TAG, END_TAG,
+};
+use crate::changes::ChangeLog;
+use crate::mdb::lmdb_flags::AllDatabaseFlags;
+use crate::types::Bytes;
+use crate::*;
+
+/// The bytes at the start of every incremental backup.
+const BACKUP_MAGIC: &[u8; 8] = b"HEEDINCR";
+/// The version of the incremental backup format.
+const BACKUP_VERSION: u32 = 1;
+
+/// A put in the current database: its key and new data.
+const PUT_TAG: u8 = b'P';
+/// A delete in the current database: its key and old data.
+const DELETE_TAG: u8 = b'X';
+
+impl<T> Env<T> {
+    /// Writes the changes committed after the transaction `txn_id` into an incremental backup,
+    /// returns the id of the last transaction included in the backup.
+    ///
+    /// The changes are read from the [change log](crate::changes::ChangeLog), the capture of
+    /// the changes must therefore log them since the transaction `txn_id` and the log must not
+    /// be truncated past it. The backup replays the puts and deletes in their commit order,
+    /// it is meant to be loaded with [`Env::apply_backup`] into a copy of this environment as of
+    /// the transaction `txn_id`, e.g. an [archive](Env::export_archive) or a previous backup.
+    /// The returned id is the one to pass to the next backup.
+    ///
+    /// Fails with [`MdbError::NotFound`] if the changes are not logged, or if some changes
+    /// committed after `txn_id` may be missing from the log: the log was truncated past it or
+    /// the capture was enabled after it, see [`ChangeLog::logged_since`].
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::changes::ChangeCapture;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// # let copy_dir = tempfile::tempdir()?;
+    /// # let copy = unsafe { EnvOpenOptions::new().max_dbs(10).open(copy_dir.path())? };
+    /// let mut capture = ChangeCapture::new();
+    /// capture.log(true);
+    /// env.set_change_capture(Some(capture));
+    /// let last_backup = env.read_txn()?.id() as u64;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+    /// db.put(&mut wtxn, "kero", "admin")?;
+    /// wtxn.commit()?;
+    ///
+    /// let mut backup = Vec::new();
+    /// let last_backup = env.backup_since(last_backup, &mut backup)?;
+    /// assert_eq!(copy.apply_backup(&backup[..])?, last_backup);
+    ///
+    /// let rtxn = copy.read_txn()?;
+    /// let db = copy.open_database::<Str, Str>(&rtxn, Some("users"))?.unwrap();
+    /// assert_eq!(db.get(&rtxn, "kero")?, Some("admin"));
+    /// # Ok(()) }
+    /// ```
+    pub fn backup_since(&self, txn_id: u64, writer: impl Write) -> Result<u64> {
+        let rtxn = self.read_txn()?;
+        let log = ChangeLog::open(self, &rtxn)?.ok_or(MdbError::NotFound)?;
+        match log.logged_since(&rtxn)? {
+            Some(since) if txn_id.saturating_add(1) >= since => (),
+            _ => return Err(MdbError::NotFound.into()),
+        }
+
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(BACKUP_MAGIC)?;
+        writer.write_all(&BACKUP_VERSION.to_be_bytes())?;
+        writer.write_all(&[cfg!(target_endian = "big") as u8])?;
+        writer.write_all(&txn_id.to_be_bytes())?;
+
+        let mut database_flags = HashMap::new();
+        let mut current = None;
+        let mut last = txn_id;
+        for result in log.iter_since(&rtxn, txn_id.saturating_add(1))? {
+            let (id, changes) = result?;
+            for change in changes {
+                if current.as_ref() != Some(&change.database) {
+                    let name = change.database.as_deref();
+                    let flags = match database_flags.get(&change.database) {
+                        Some(flags) => *flags,
+                        None => {
+                            let flags = self.archived_flags(&rtxn, name)?;
+                            database_flags.insert(change.database.clone(), flags);
+                            flags
+                        }
+                    };
+                    write_database_header(&mut writer, name, flags)?;
+                    current = Some(change.database.clone());
+                }
+
+                // A delete always has the old data, used to delete a single duplicate.
+                match (change.new, change.old) {
+                    (Some(new), _) => {
+                        writer.write_all(&[PUT_TAG])?;
+                        write_bytes(&mut writer, &change.key)?;
+                        write_bytes(&mut writer, &new)?;
+                    }
+                    (None, Some(old)) => {
+                        writer.write_all(&[DELETE_TAG])?;
+                        write_bytes(&mut writer, &change.key)?;
+                        write_bytes(&mut writer, &old)?;
+                    }
+                    (None, None) => (),
+                }
+            }
+            last = id;
+        }
+
+        writer.write_all(&[END_TAG])?;
+        writer.write_all(&last.to_be_bytes())?;
+        writer.flush()?;
+        Ok(last)
+    }
+
+    /// Applies an incremental backup written by [`Env::backup_since`] to this environment,
+    /// returns the id of the last transaction included in the backup.
+    ///
+    /// The changes are applied in a single write transaction, either the whole backup is applied
+    /// or nothing. The missing databases are created with the flags they had when the backup was
+    /// written, it fails with [`MdbError::Incompatible`] if the flags of existing databases differ.
+    ///
+    /// Malformed backups fail with an [`io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData)
+    /// error. Fails if a write transaction is already opened on this thread.
+    pub fn apply_backup(&self, reader: impl Read) -> Result<u64> {
+        let mut reader = BufReader::new(reader);
+        let mut magic = [0; BACKUP_MAGIC.len()];
+        reader.read_exact(&mut magic).map_err(truncated_archive)?;
+        if &magic != BACKUP_MAGIC {
+            return Err(invalid_archive("not a heed incremental backup").into());
+        }
+        if read_u32(&mut reader)? != BACKUP_VERSION {
+            return Err(invalid_archive("unsupported backup version").into());
+        }
+        let swap_integers = read_swap_integers(&mut reader)?;
+        let _since = read_u64(&mut reader)?;
+
+        let mut wtxn = self.write_txn()?;
+        let mut current = None;
+        let mut key = Vec::new();
+        let mut data = Vec::new();
+        loop {
+            let tag = read_u8(&mut reader)?;
+            match tag {
+                DATABASE_TAG => {
+                    let (name, flags) = read_database_header(&mut reader)?;
+                    let database = self.create_archived_database(&mut wtxn, name, flags)?;
+                    current = Some((database, flags));
+                }
+                PUT_TAG | DELETE_TAG => {
+                    let (database, flags) =
+                        current.ok_or_else(|| invalid_archive("change outside of a database"))?;
+                    read_bytes(&mut reader, &mut key)?;
+                    read_bytes(&mut reader, &mut data)?;
+                    if swap_integers {
+                        if flags.contains(AllDatabaseFlags::INTEGER_KEY) {
+                            key.reverse();
+                        }
+                        if flags.contains(AllDatabaseFlags::INTEGER_DUP) {
+                            data.reverse();
+                        }
+                    }
+                    if tag == PUT_TAG {
+                        database.put(&mut wtxn, &key, &data)?;
+                    } else {
+                        database.delete_one_duplicate(&mut wtxn, &key, &data)?;
+                    }
+                }
+                END_TAG => break,
+                _ => return Err(invalid_archive("invalid record").into()),
+            }
+        }
+        let last = read_u64(&mut reader)?;
+
+        wtxn.commit()?;
+        Ok(last)
+    }
+
+    /// Returns the flags of a database to write in an archive,
+    /// the default flags if the database has been removed since.
+    fn archived_flags(&self, rtxn: &impl ReadTxn, name: Option<&str>) -> Result<DatabaseFlags> {
+        let mut options = self.database_options().types::<Bytes, Bytes>();
+        if let Some(name) = name {
+            options.name(name);
+        }
+        match options.open(rtxn)? {
+            Some(database) => database.database_flags(rtxn),
+            None => Ok(DatabaseFlags::empty()),
+        }
+    }
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes).map_err(truncated_archive)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::ChangeCapture;
+    use crate::types::Str;
+
+    #[test]
+    fn backup_and_apply() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let words = env.create_database::<Str, Str>(&mut wtxn, Some("words"))?;
+        words.put(&mut wtxn, "hello", "world")?;
+        words.put(&mut wtxn, "removed", "soon")?;
+        wtxn.commit()?;
+
+        let mut archive = Vec::new();
+        env.export_archive(&mut archive)?;
+        let copy_dir = tempfile::tempdir()?;
+        let copy = unsafe { EnvOpenOptions::new().max_dbs(10).open(copy_dir.path())? };
+        copy.import_archive(&archive[..])?;
+
+        // Without the change log there is nothing to back up from.
+        let base = env.read_txn()?.id() as u64;
+        let result = env.backup_since(base, Vec::new());
+        assert!(matches!(result, Err(Error::Mdb(MdbError::NotFound))));
+
+        let mut capture = ChangeCapture::new();
+        capture.log(true);
+        env.set_change_capture(Some(capture));
+
+        let mut wtxn = env.write_txn()?;
+        words.put(&mut wtxn, "hello", "there")?;
+        words.delete(&mut wtxn, "removed")?;
+        let mut options = env.database_options().types::<Str, Str>();
+        options.name("tags").flags(DatabaseFlags::DUP_SORT);
+        let tags = options.create(&mut wtxn)?;
+        tags.put(&mut wtxn, "rust", "fast")?;
+        tags.put(&mut wtxn, "rust", "safe")?;
+        wtxn.commit()?;
+
+        let mut backup = Vec::new();
+        let first = env.backup_since(base, &mut backup)?;
+        assert_eq!(copy.apply_backup(&backup[..])?, first);
+
+        let mut wtxn = env.write_txn()?;
+        tags.delete_one_duplicate(&mut wtxn, "rust", "fast")?;
+        wtxn.commit()?;
+
+        let mut backup = Vec::new();
+        let second = env.backup_since(first, &mut backup)?;
+        assert!(second > first);
+        // Truncated backups are rejected and nothing is applied.
+        let result = copy.apply_backup(&backup[..backup.len() - 1]);
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::InvalidData));
+        assert_eq!(copy.apply_backup(&backup[..])?, second);
+
+        let rtxn = copy.read_txn()?;
+        let words = copy.open_database::<Str, Str>(&rtxn, Some("words"))?.unwrap();
+        let entries: Vec<_> = words.iter(&rtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [("hello", "there")]);
+        let tags = copy.open_database::<Str, Str>(&rtxn, Some("tags"))?.unwrap();
+        assert_eq!(tags.database_flags(&rtxn)?, DatabaseFlags::DUP_SORT);
+        let entries: Vec<_> = tags.iter(&rtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [("rust", "safe")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn backup_since_unlogged_changes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let words = env.create_database::<Str, Str>(&mut wtxn, Some("words"))?;
+        wtxn.commit()?;
+
+        let mut capture = ChangeCapture::new();
+        capture.log(true);
+        env.set_change_capture(Some(capture));
+        let base = env.read_txn()?.id() as u64;
+        for word in ["a", "b", "c"] {
+            let mut wtxn = env.write_txn()?;
+            words.put(&mut wtxn, word, word)?;
+            wtxn.commit()?;
+        }
+
+        // The transaction before the base one wasn't logged.
+        assert!(env.backup_since(base, Vec::new()).is_ok());
+        let result = env.backup_since(base - 1, Vec::new());
+        assert!(matches!(result, Err(Error::Mdb(MdbError::NotFound))));
+
+        // The changes of the first logged transaction are truncated.
+        let mut wtxn = env.write_txn()?;
+        let log = ChangeLog::open(&env, &wtxn)?.unwrap();
+        assert_eq!(log.logged_since(&wtxn)?, Some(base + 1));
+        assert_eq!(log.truncate(&mut wtxn, base + 2)?, 1);
+        wtxn.commit()?;
+        let result = env.backup_since(base, Vec::new());
+        assert!(matches!(result, Err(Error::Mdb(MdbError::NotFound))));
+        assert!(env.backup_since(base + 1, Vec::new()).is_ok());
+
+        // A write made without the capture is missing from the log.
+        env.set_change_capture(None);
+        let mut wtxn = env.write_txn()?;
+        words.put(&mut wtxn, "d", "d")?;
+        wtxn.commit()?;
+        let mut capture = ChangeCapture::new();
+        capture.log(true);
+        env.set_change_capture(Some(capture));
+        let last = env.read_txn()?.id() as u64;
+        let mut wtxn = env.write_txn()?;
+        words.put(&mut wtxn, "e", "e")?;
+        wtxn.commit()?;
+
+        let result = env.backup_since(base + 1, Vec::new());
+        assert!(matches!(result, Err(Error::Mdb(MdbError::NotFound))));
+        assert!(env.backup_since(last, Vec::new()).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::{fmt, mem};
+
+use crate::mdb::ffi;
+use crate::observer::TxnObservation;
+use crate::paranoid::ValueCopies;
+use crate::txn::TlsUsage;
+use crate::{Env, ReadTxn, Result, RoTxn};
+
+/// The reset read transactions of an environment, waiting to be renewed.
+pub(crate) struct ReadTxnCache {
+    /// The transactions and the threads they are tied to, if they use thread local storage.
+    txns: Mutex<Vec<(Option<ThreadId>, NonNull<ffi::MDB_txn>)>>,
+}
+
+impl ReadTxnCache {
+    pub(crate) fn new() -> ReadTxnCache {
+        ReadTxnCache { txns: Mutex::new(Vec::new()) }
+    }
+
+    /// Aborts the cached transactions, must be called before the environment is closed.
+    pub(crate) fn clear(&self) {
+        let txns = mem::take(&mut *self.txns.lock().unwrap());
+        for (_, mut txn) in txns {
+            unsafe { ffi::mdb_txn_abort(txn.as_mut()) };
+        }
+    }
+
+    fn take(&self, thread: Option<ThreadId>) -> Option<NonNull<ffi::MDB_txn>> {
+        let mut txns = self.txns.lock().unwrap();
+        let position = txns.iter().position(|(owner, _)| *owner == thread)?;
+        Some(txns.swap_remove(position).1)
+    }
+
+    fn put(&self, thread: Option<ThreadId>, txn: NonNull<ffi::MDB_txn>) {
+        self.txns.lock().unwrap().push((thread, txn));
+    }
+}
+
+impl<T: TlsUsage> Env<T> {
+    /// Returns a read transaction renewed from a cache of reset transactions,
+    /// the transaction is reset and put back in the cache when the guard is dropped.
+    ///
+    /// Renewing a transaction avoids the cost of opening and closing one, which matters to
+    /// programs serving many short reads. The cache keeps one transaction per thread when the
+    /// read transactions use thread local storage, it keeps the transactions of the dropped
+    /// guards otherwise, see [`EnvOpenOptions::read_txn_without_tls`](crate::EnvOpenOptions::read_txn_without_tls).
+    /// The cached transactions don't prevent the reuse of pages but they keep their reader slot,
+    /// they are aborted when the environment is closed.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+    /// db.put(&mut wtxn, "kero", "admin")?;
+    /// wtxn.commit()?;
+    ///
+    /// for _ in 0..3 {
+    ///     let rtxn = env.cached_read_txn()?;
+    ///     assert_eq!(db.get(&rtxn, "kero")?, Some("admin"));
+    /// }
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// The same as [`Env::read_txn`].
+    pub fn cached_read_txn(&self) -> Result<CachedRoTxn<'_, T>> {
+        let thread = if T::ENABLED { Some(thread::current().id()) } else { None };
+        let txn = match self.inner.read_txn_cache.take(thread) {
+            Some(txn) => RoTxn::renew(self, txn)?,
+            None => self.read_txn()?,
+        };
+        Ok(CachedRoTxn { txn: Some(txn), env: self, thread })
+    }
+}
+
+/// A read transaction returned by [`Env::cached_read_txn`],
+/// put back in the cache of the environment when dropped.
+pub struct CachedRoTxn<'e, T> {
+    txn: Option<RoTxn<'e, T>>,
+    env: &'e Env<T>,
+    thread: Option<ThreadId>,
+}
+
+impl<'e, T> Deref for CachedRoTxn<'e, T> {
+    type Target = RoTxn<'e, T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.txn.as_ref().unwrap()
+    }
+}
+
+unsafe impl<T> ReadTxn for CachedRoTxn<'_, T> {
+    fn txn_ptr(&self) -> NonNull<ffi::MDB_txn> {
+        ReadTxn::txn_ptr(&**self)
+    }
+
+    fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
+        ReadTxn::env_mut_ptr(&**self)
+    }
+
+    fn env_generation(&self) -> u64 {
+        ReadTxn::env_generation(&**self)
+    }
+
+    fn observation(&self) -> Option<&TxnObservation> {
+        ReadTxn::observation(&**self)
+    }
+
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        ReadTxn::value_copies(&**self)
+    }
+}
+
+impl<T> Drop for CachedRoTxn<'_, T> {
+    fn drop(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            self.env.inner.read_txn_cache.put(self.thread, txn.into_reset());
+        }
+    }
+}
+
+impl<T> fmt::Debug for CachedRoTxn<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachedRoTxn").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Str;
+    use crate::{Database, EnvOpenOptions, Result};
+
+    #[test]
+    fn renewed_read_txns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("words"))?;
+        wtxn.commit()?;
+
+        let rtxn = env.cached_read_txn()?;
+        let first = rtxn.txn_ptr();
+        assert_eq!(db.get(&rtxn, "hello")?, None);
+        drop(rtxn);
+
+        let mut wtxn = env.write_txn()?;
+        db.put(&mut wtxn, "hello", "world")?;
+        wtxn.commit()?;
+
+        // The renewed transaction reads the latest snapshot.
+        let rtxn = env.cached_read_txn()?;
+        assert_eq!(rtxn.txn_ptr(), first);
+        assert_eq!(db.get(&rtxn, "hello")?, Some("world"));
+        drop(rtxn);
+
+        // Without thread local storage, the transactions of all the guards are cached.
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().read_txn_without_tls().open(dir.path())? };
+        let (first, second) = (env.cached_read_txn()?, env.cached_read_txn()?);
+        let ptrs = [first.txn_ptr(), second.txn_ptr()];
+        drop((first, second));
+        let (first, second) = (env.cached_read_txn()?, env.cached_read_txn()?);
+        assert!(ptrs.contains(&first.txn_ptr()) && ptrs.contains(&second.txn_ptr()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::sync::LazyLock;
+
+use heed_traits::{CodecIdentity, Comparator};
+use icu_collator::{Collator, CollatorOptions};
+
+/// The collation rules used by a [`CollationComparator`].
+///
+/// The collator is shared by all the comparisons of a database and must be the same
+/// every time the database is opened, the keys are not reordered when the rules change.
+pub trait Collation {
+    /// Returns the collator comparing the keys.
+    fn collator() -> &'static Collator;
+}
+
+/// The collation rules of the root locale, with the default options.
+///
+/// It orders the letters before taking the diacritics and then the case into account,
+/// i.e., `"cote" < "Cote" < "côte" < "Côte"`.
+#[derive(Debug)]
+pub enum RootCollation {}
+
+impl Collation for RootCollation {
+    fn collator() -> &'static Collator {
+        static COLLATOR: LazyLock<Collator> = LazyLock::new(|| {
+            Collator::try_new(&Default::default(), CollatorOptions::new())
+                .expect("the compiled data contains the root collation")
+        });
+        &COLLATOR
+    }
+}
+
+impl CodecIdentity for RootCollation {
+    const NAME: &'static str = "root";
+}
+
+/// A comparator sorting UTF-8 keys in a human-language order, for the listings shown to users.
+///
+/// This comparator is set with [`ffi::mdb_set_compare`](crate::mdb::ffi::mdb_set_compare)
+/// and can be used with the [`DatabaseOpenOptions::key_comparator`] and
+/// [`DatabaseOpenOptions::dup_sort_comparator`] methods. The keys that the collator considers
+/// equal, like two forms of the same normalized string, are ordered by their bytes so that LMDB
+/// still stores them as different keys.
+///
+/// It is not a [`LexicographicComparator`](crate::LexicographicComparator), the prefix iterators
+/// are not available but the ranges are.
+///
+/// ```
+/// # use std::fs;
+/// # use std::path::Path;
+/// # use heed::EnvOpenOptions;
+/// use heed::types::*;
+/// use heed::CollationComparator;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env
+///     .database_options()
+///     .types::<Str, Unit>()
+///     .key_comparator::<CollationComparator>()
+///     .name("names")
+///     .create(&mut wtxn)?;
+///
+/// for name in ["zoé", "Zoe", "émile", "Eve"] {
+///     db.put(&mut wtxn, name, &())?;
+/// }
+///
+/// let names = db.iter(&wtxn)?.map(|e| e.map(|(name, ())| name));
+/// assert_eq!(names.collect::<Result<Vec<_>, _>>()?, ["émile", "Eve", "Zoe", "zoé"]);
+///
+/// wtxn.commit()?;
+/// # Ok(()) }
+/// ```
+///
+/// [`DatabaseOpenOptions::key_comparator`]: crate::DatabaseOpenOptions::key_comparator
+/// [`DatabaseOpenOptions::dup_sort_comparator`]: crate::DatabaseOpenOptions::dup_sort_comparator
+pub struct CollationComparator<L = RootCollation> {
+    _collation: PhantomData<L>,
+}
+
+impl<L: Collation> Comparator for CollationComparator<L> {
+    fn compare(a: &[u8], b: &[u8]) -> Ordering {
+        L::collator().compare_utf8(a, b).then_with(|| a.cmp(b))
+    }
+}
+
+impl<L: CodecIdentity> CodecIdentity for CollationComparator<L> {
+    const NAME: &'static str = "collation";
+
+    fn write_parameters(identity: &mut String) {
+        L::write_identity(identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::*;
+
+    #[test]
+    fn canonically_equivalent_keys_stay_different() {
+        let composed = "caf\u{e9}".as_bytes();
+        let decomposed = "cafe\u{301}".as_bytes();
+        assert_eq!(RootCollation::collator().compare_utf8(composed, decomposed), Ordering::Equal);
+        let compare = CollationComparator::<RootCollation>::compare;
+        assert_eq!(compare(composed, decomposed), Ordering::Greater);
+        assert_eq!(compare(b"cafe", composed), Ordering::Less);
+    }
+}
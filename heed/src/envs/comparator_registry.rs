@@ -0,0 +1,142 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use heed_traits::Comparator;
+
+use super::{custom_key_cmp_wrapper, DefaultComparator, IntegerComparator, ReverseComparator};
+use crate::mdb::ffi;
+use crate::{Env, MdbError, Result};
+
+/// The comparators registered for the names of the databases, see [`Env::register_comparator`].
+#[derive(Default)]
+pub(crate) struct ComparatorRegistry {
+    keys: RwLock<HashMap<Option<String>, RegisteredComparator>>,
+    dups: RwLock<HashMap<Option<String>, RegisteredComparator>>,
+}
+
+impl ComparatorRegistry {
+    /// Returns the key comparator to install in the database of this name.
+    pub(crate) fn key_comparator<C: Comparator + 'static>(
+        &self,
+        name: Option<&str>,
+    ) -> Result<RegisteredComparator> {
+        RegisteredComparator::resolve::<C>(self.keys.read().unwrap().get(&name.map(str::to_owned)))
+    }
+
+    /// Returns the duplicate values comparator to install in the database of this name.
+    pub(crate) fn dup_sort_comparator<CDUP: Comparator + 'static>(
+        &self,
+        name: Option<&str>,
+    ) -> Result<RegisteredComparator> {
+        RegisteredComparator::resolve::<CDUP>(
+            self.dups.read().unwrap().get(&name.map(str::to_owned)),
+        )
+    }
+}
+
+/// A comparator type erased to be installed in LMDB.
+#[derive(Clone, Copy)]
+pub(crate) struct RegisteredComparator {
+    type_id: TypeId,
+    compare: ffi::MDB_cmp_func,
+}
+
+impl RegisteredComparator {
+    fn of<C: Comparator + 'static>() -> RegisteredComparator {
+        RegisteredComparator {
+            type_id: TypeId::of::<C>(),
+            compare: Some(custom_key_cmp_wrapper::<C>),
+        }
+    }
+
+    /// The registered comparator replaces the [`DefaultComparator`], the comparator the database
+    /// is opened with must otherwise be the registered one.
+    fn resolve<C: Comparator + 'static>(
+        registered: Option<&RegisteredComparator>,
+    ) -> Result<RegisteredComparator> {
+        match registered {
+            Some(registered) if TypeId::of::<C>() == TypeId::of::<DefaultComparator>() => {
+                Ok(*registered)
+            }
+            Some(registered) if registered.type_id != TypeId::of::<C>() => {
+                Err(MdbError::Incompatible.into())
+            }
+            _ => Ok(RegisteredComparator::of::<C>()),
+        }
+    }
+
+    /// Whether this comparator is the `C` one.
+    pub(crate) fn is<C: 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<C>()
+    }
+
+    /// The function to install with `mdb_set_compare` or `mdb_set_dupsort`, `None` if LMDB
+    /// implements this comparator itself.
+    pub(crate) fn compare_func(&self) -> ffi::MDB_cmp_func {
+        if self.is::<DefaultComparator>()
+            || self.is::<IntegerComparator>()
+            || self.is::<ReverseComparator>()
+        {
+            None
+        } else {
+            self.compare
+        }
+    }
+}
+
+impl<T> Env<T> {
+    /// Registers the key comparator of the databases of this name, the ones opened afterward with
+    /// the [`DefaultComparator`] are ordered by `C`.
+    ///
+    /// It is meant for the applications opening databases by their names at runtime, like the
+    /// plugin systems, that can't know the comparators of the databases. Opening the database
+    /// with another comparator than `C` and [`DefaultComparator`] returns an
+    /// [`MdbError::Incompatible`] error. The databases already opened are not changed.
+    ///
+    /// The ranges and prefixes of a database opened with the [`DefaultComparator`] are still
+    /// compared with it, open the database with `C` to iterate over the ranges of keys.
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::Path;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::ReverseComparator;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// env.register_comparator::<ReverseComparator>(Some("suffixes"));
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Unit>(&mut wtxn, Some("suffixes"))?;
+    /// db.put(&mut wtxn, "ab", &())?;
+    /// db.put(&mut wtxn, "ba", &())?;
+    ///
+    /// let keys = db.iter(&wtxn)?.map(|e| e.map(|(key, ())| key));
+    /// assert_eq!(keys.collect::<Result<Vec<_>, _>>()?, ["ba", "ab"]);
+    ///
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn register_comparator<C: Comparator + 'static>(&self, name: Option<&str>) {
+        let comparator = RegisteredComparator::of::<C>();
+        let mut keys = self.inner.comparators.keys.write().unwrap();
+        keys.insert(name.map(str::to_owned), comparator);
+    }
+
+    /// Registers the duplicate values comparator of the `DUP_SORT` databases of this name,
+    /// the ones opened afterward with the [`DefaultComparator`] are ordered by `CDUP`.
+    ///
+    /// For more info, see [`Env::register_comparator`].
+    pub fn register_dup_sort_comparator<CDUP: Comparator + 'static>(&self, name: Option<&str>) {
+        let comparator = RegisteredComparator::of::<CDUP>();
+        let mut dups = self.inner.comparators.dups.write().unwrap();
+        dups.insert(name.map(str::to_owned), comparator);
+    }
+}
@@ -6,7 +6,7 @@ use std::path::Path;
 use aead::generic_array::typenum::Unsigned;
 use aead::{AeadMutInPlace, Key, KeyInit, Nonce, Tag};
 
-use super::{Env, EnvClosingEvent, EnvInfo, FlagSetMode};
+use super::{Env, EnvClosingEvent, EnvEvent, EnvInfo, FlagSetMode};
 use crate::databases::{EncryptedDatabase, EncryptedDatabaseOpenOptions};
 use crate::envs::EnvStat;
 use crate::mdb::ffi::{self};
@@ -363,6 +363,18 @@ impl<T> EncryptedEnv<T> {
         self.inner.clear_stale_readers()
     }
 
+    /// Returns the id of the oldest snapshot still read by a transaction of any process
+    /// using this environment, `None` if there is no active reader.
+    pub fn oldest_reader_txn_id(&self) -> Result<Option<u64>> {
+        self.inner.oldest_reader_txn_id()
+    }
+
+    /// Returns the number of transactions committed since the snapshot of the oldest reader,
+    /// zero if there is no active reader.
+    pub fn reader_lag(&self) -> Result<u64> {
+        self.inner.reader_lag()
+    }
+
     /// Resize the memory map to a new size.
     ///
     /// # Safety
@@ -373,6 +385,15 @@ impl<T> EncryptedEnv<T> {
     pub unsafe fn resize(&self, new_size: usize) -> Result<()> {
         self.inner.resize(new_size)
     }
+
+    /// Adds a handler called after every successful maintenance operation on the environment,
+    /// and when the environment is closed, see [`Env::on_event`].
+    pub fn on_event<F>(&self, handler: F)
+    where
+        F: Fn(&EnvEvent) + Send + Sync + 'static,
+    {
+        self.inner.on_event(handler)
+    }
 }
 
 unsafe impl<T> Send for EncryptedEnv<T> {}
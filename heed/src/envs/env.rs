@@ -1,32 +1,50 @@
-use std::any::TypeId;
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::fs::{self, File};
 use std::io::Seek;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::ptr::{self, NonNull};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::{fmt, io, mem};
 
 use heed_traits::Comparator;
 use synchronoise::SignalEvent;
 
+use super::cached_read::ReadTxnCache;
+use super::comparator_registry::ComparatorRegistry;
+use super::events::EventHandlers;
+#[cfg(feature = "track-read-txns")]
+use super::live_readers::LiveReaders;
+use super::slow_txn::SlowTxnTracker;
+use super::watch::CommitSignal;
+use super::WriterLock;
 use super::{
-    custom_key_cmp_wrapper, get_file_fd, metadata_from_fd, DefaultComparator, EnvClosingEvent,
-    EnvInfo, FlagSetMode, IntegerComparator, OPENED_ENV,
+    get_file_fd, metadata_from_fd, reader_txn_ids, register_env_generation,
+    unregister_env_generation, EnvClosingEvent, EnvEvent, EnvInfo, FlagSetMode, IntegerComparator,
+    ReverseComparator, OPENED_ENV,
 };
+use crate::changes::ChangeCapture;
 use crate::cursor::{MoveOperation, RoCursor};
 use crate::envs::EnvStat;
 use crate::mdb::ffi::{self, MDB_env};
 use crate::mdb::lmdb_error::mdb_result;
 use crate::mdb::lmdb_flags::AllDatabaseFlags;
+#[cfg(any(test, feature = "testing"))]
+use crate::testing::faults::{faulty_copy, Boundary, FaultInjector};
 #[allow(unused)] // for cargo auto doc links
 use crate::EnvOpenOptions;
 use crate::{
-    CompactionOption, Database, DatabaseOpenOptions, EnvFlags, Error, ReadTxn, Result, RoTxn,
-    RwTxn, Unspecified, WithTls, WriteTxn,
+    CompactionOption, Database, DatabaseOpenOptions, EnvFlags, EnvObserver, Error, MdbError,
+    ReadTxn, Result, RoTxn, RwTxn, Unspecified, WithTls, WriteTxn,
 };
 
+/// The flags of the comparators LMDB implements, set from the comparator types.
+const COMPARE_FLAGS: AllDatabaseFlags = AllDatabaseFlags::INTEGER_KEY
+    .union(AllDatabaseFlags::INTEGER_DUP)
+    .union(AllDatabaseFlags::REVERSE_KEY)
+    .union(AllDatabaseFlags::REVERSE_DUP);
+
 /// An environment handle constructed by using [`EnvOpenOptions::open`].
 #[repr(transparent)]
 pub struct Env<T = WithTls> {
@@ -39,8 +57,28 @@ impl<T> Env<T> {
         env_ptr: NonNull<MDB_env>,
         path: PathBuf,
         signal_event: Arc<SignalEvent>,
+        slow_txns: Option<Arc<SlowTxnTracker>>,
     ) -> Self {
-        Env { inner: Arc::new(EnvInner { env_ptr, path, signal_event }), _tls_marker: PhantomData }
+        let inner = EnvInner {
+            env_ptr,
+            generation: register_env_generation(env_ptr),
+            signal_event,
+            path,
+            change_capture: RwLock::new(None),
+            database_names: RwLock::new(HashMap::new()),
+            commit_signal: CommitSignal::new(),
+            read_txn_cache: ReadTxnCache::new(),
+            writer_lock: WriterLock::new(),
+            observer: RwLock::new(None),
+            slow_txns,
+            events: EventHandlers::default(),
+            comparators: ComparatorRegistry::default(),
+            #[cfg(any(test, feature = "testing"))]
+            fault_injector: RwLock::new(None),
+            #[cfg(feature = "track-read-txns")]
+            live_readers: LiveReaders::new(),
+        };
+        Env { inner: Arc::new(inner), _tls_marker: PhantomData }
     }
 
     pub(crate) fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
@@ -297,40 +335,45 @@ impl<T> Env<T> {
         name: Option<&str>,
         mut flags: AllDatabaseFlags,
     ) -> Result<u32> {
-        if TypeId::of::<C>() == TypeId::of::<IntegerComparator>() {
+        // The comparators registered for this name replace the default ones.
+        let key_comparator = self.inner.comparators.key_comparator::<C>(name)?;
+        let dup_comparator = self.inner.comparators.dup_sort_comparator::<CDUP>(name)?;
+
+        if key_comparator.is::<IntegerComparator>() {
             flags.insert(AllDatabaseFlags::INTEGER_KEY);
         }
 
-        if TypeId::of::<CDUP>() == TypeId::of::<IntegerComparator>() {
+        if dup_comparator.is::<IntegerComparator>() {
             flags.insert(AllDatabaseFlags::INTEGER_DUP);
         }
 
+        if key_comparator.is::<ReverseComparator>() {
+            flags.insert(AllDatabaseFlags::REVERSE_KEY);
+        }
+
+        if dup_comparator.is::<ReverseComparator>() {
+            flags.insert(AllDatabaseFlags::REVERSE_DUP);
+        }
+
         let dbi = self.raw_open_dbi(raw_txn, name, flags.bits())?;
 
-        let cmp_type_id = TypeId::of::<C>();
-        if cmp_type_id != TypeId::of::<DefaultComparator>()
-            && cmp_type_id != TypeId::of::<IntegerComparator>()
-        {
-            unsafe {
-                mdb_result(ffi::mdb_set_compare(
-                    raw_txn.as_mut(),
-                    dbi,
-                    Some(custom_key_cmp_wrapper::<C>),
-                ))?
-            };
+        // LMDB opens an existing database with its own flags, the integer and reverse
+        // comparators are only correct if the database was created with them.
+        let compare_flags = flags & COMPARE_FLAGS;
+        if !compare_flags.is_empty() {
+            let mut stored = 0;
+            unsafe { mdb_result(ffi::mdb_dbi_flags(raw_txn.as_mut(), dbi, &mut stored))? };
+            if !AllDatabaseFlags::from_bits_truncate(stored).contains(compare_flags) {
+                return Err(MdbError::Incompatible.into());
+            }
         }
 
-        let cmp_dup_type_id = TypeId::of::<CDUP>();
-        if cmp_dup_type_id != TypeId::of::<DefaultComparator>()
-            && cmp_dup_type_id != TypeId::of::<IntegerComparator>()
-        {
-            unsafe {
-                mdb_result(ffi::mdb_set_dupsort(
-                    raw_txn.as_mut(),
-                    dbi,
-                    Some(custom_key_cmp_wrapper::<CDUP>),
-                ))?
-            };
+        if let Some(compare) = key_comparator.compare_func() {
+            unsafe { mdb_result(ffi::mdb_set_compare(raw_txn.as_mut(), dbi, Some(compare)))? };
+        }
+
+        if let Some(compare) = dup_comparator.compare_func() {
+            unsafe { mdb_result(ffi::mdb_set_dupsort(raw_txn.as_mut(), dbi, Some(compare)))? };
         }
 
         Ok(dbi)
@@ -353,6 +396,9 @@ impl<T> Env<T> {
         //         If a read-only is used with the MDB_CREATE flag, LMDB will throw an error.
         unsafe { mdb_result(ffi::mdb_dbi_open(raw_txn.as_mut(), name_ptr, flags, &mut dbi))? };
 
+        let name = name.map(|name| name.into_string().unwrap());
+        self.inner.database_names.write().unwrap().insert(dbi, name);
+
         Ok(dbi)
     }
 
@@ -363,9 +409,9 @@ impl<T> Env<T> {
     /// Only one [`RwTxn`] may exist simultaneously in the current environment.
     /// If another write transaction is initiated, while another write transaction exists
     /// the thread initiating the new one will wait on a mutex upon completion of the previous
-    /// transaction.
+    /// transaction. See [`Env::write_txn_timeout`] to give up after some time.
     pub fn write_txn(&self) -> Result<RwTxn<'_>> {
-        RwTxn::new(self)
+        RwTxn::new(self, None)
     }
 
     /// Create a nested transaction with read and write access for use with the environment.
@@ -487,6 +533,11 @@ impl<T> Env<T> {
     /// # Ok(()) }
     /// ```
     pub fn copy_to_file(&self, file: &mut File, option: CompactionOption) -> Result<()> {
+        #[cfg(any(test, feature = "testing"))]
+        if let Some(fault) = self.inner.injected_fault(Boundary::Copy) {
+            return faulty_copy(self, file, option, fault);
+        }
+
         let fd = get_file_fd(file);
         unsafe { self.copy_to_fd(fd, option) }
     }
@@ -573,7 +624,13 @@ impl<T> Env<T> {
 
     /// Flush the data buffers to disk.
     pub fn force_sync(&self) -> Result<()> {
+        #[cfg(any(test, feature = "testing"))]
+        if let Some(fault) = self.inner.injected_fault(Boundary::Sync) {
+            return Err(fault.to_io_error().into());
+        }
+
         unsafe { mdb_result(ffi::mdb_env_sync(self.inner.env_ptr.as_ptr(), 1))? }
+        self.inner.events.emit(EnvEvent::Synced);
         Ok(())
     }
 
@@ -616,7 +673,61 @@ impl<T> Env<T> {
         unsafe { mdb_result(ffi::mdb_reader_check(self.inner.env_ptr.as_ptr(), &mut dead))? }
         // safety: The reader_check function asks for an i32, initialize it to zero
         //         and never decrements it. It is safe to use either an u32 or u64 (usize).
-        Ok(dead as usize)
+        let count = dead as usize;
+        self.inner.events.emit(EnvEvent::StaleReadersCleared { count });
+        Ok(count)
+    }
+
+    /// Returns the id of the oldest snapshot still read by a transaction of any process
+    /// using this environment, `None` if there is no active reader.
+    ///
+    /// The pages freed after this snapshot can't be reused until the reader ends.
+    pub fn oldest_reader_txn_id(&self) -> Result<Option<u64>> {
+        Ok(reader_txn_ids(self.inner.env_ptr)?.into_iter().min())
+    }
+
+    /// Returns the number of transactions committed since the snapshot of the oldest reader,
+    /// zero if there is no active reader.
+    ///
+    /// A lag that keeps growing reveals a stale reader, which makes the environment grow
+    /// until the map is full, see [`Env::clear_stale_readers`] for the readers of dead processes.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::Database;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Str> = env.create_database(&mut wtxn, None)?;
+    /// wtxn.commit()?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// assert_eq!(env.oldest_reader_txn_id()?, Some(rtxn.id() as u64));
+    /// assert_eq!(env.reader_lag()?, 0);
+    ///
+    /// // The commits without any write don't create a new snapshot.
+    /// for value in ["hello", "world"] {
+    ///     let mut wtxn = env.write_txn()?;
+    ///     db.put(&mut wtxn, "key", value)?;
+    ///     wtxn.commit()?;
+    /// }
+    /// assert_eq!(env.reader_lag()?, 2);
+    ///
+    /// drop(rtxn);
+    /// assert_eq!(env.oldest_reader_txn_id()?, None);
+    /// # Ok(()) }
+    /// ```
+    pub fn reader_lag(&self) -> Result<u64> {
+        let head = self.info().last_txn_id as u64;
+        let oldest = self.oldest_reader_txn_id()?;
+        Ok(oldest.map_or(0, |oldest| head.saturating_sub(oldest)))
     }
 
     /// Resize the memory map to a new size.
@@ -635,8 +746,10 @@ impl<T> Env<T> {
             );
             return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg)));
         }
-        mdb_result(unsafe { ffi::mdb_env_set_mapsize(self.env_mut_ptr().as_mut(), new_size) })
-            .map_err(Into::into)
+        let old_size = self.info().map_size;
+        mdb_result(unsafe { ffi::mdb_env_set_mapsize(self.env_mut_ptr().as_mut(), new_size) })?;
+        self.inner.events.emit(EnvEvent::Resized { old_size, new_size });
+        Ok(())
     }
 }
 
@@ -657,8 +770,34 @@ impl<T> fmt::Debug for Env<T> {
 
 pub(crate) struct EnvInner {
     env_ptr: NonNull<MDB_env>,
+    /// Identifies this environment in the databases opened in it, see [`Error::EnvClosed`].
+    pub(crate) generation: u64,
     signal_event: Arc<SignalEvent>,
     pub(crate) path: PathBuf,
+    /// The capture applied to the write transactions, see [`Env::set_change_capture`].
+    pub(crate) change_capture: RwLock<Option<Arc<ChangeCapture>>>,
+    /// The names of the opened databases, used to describe the captured changes.
+    pub(crate) database_names: RwLock<HashMap<ffi::MDB_dbi, Option<String>>>,
+    /// Wakes the waiters of [`Env::watch`] after every commit.
+    pub(crate) commit_signal: CommitSignal,
+    /// The reset read transactions of [`Env::cached_read_txn`].
+    pub(crate) read_txn_cache: ReadTxnCache,
+    /// Serializes the write transactions of this process, see [`Env::write_txn_timeout`].
+    pub(crate) writer_lock: WriterLock,
+    /// Receives the metrics of the transactions, see [`Env::set_observer`].
+    pub(crate) observer: RwLock<Option<Arc<dyn EnvObserver>>>,
+    /// Reports the slow transactions, see [`EnvOpenOptions::warn_slow_txn`].
+    pub(crate) slow_txns: Option<Arc<SlowTxnTracker>>,
+    /// The handlers of the lifecycle events, see [`Env::on_event`].
+    pub(crate) events: EventHandlers,
+    /// The comparators of the databases opened by name, see [`Env::register_comparator`].
+    pub(crate) comparators: ComparatorRegistry,
+    /// Decides which commits, syncs and copies fail, see [`Env::set_fault_injector`].
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fault_injector: RwLock<Option<FaultInjector>>,
+    /// The live read transactions, see [`Env::live_readers_report`].
+    #[cfg(feature = "track-read-txns")]
+    pub(crate) live_readers: LiveReaders,
 }
 
 impl EnvInner {
@@ -672,9 +811,14 @@ unsafe impl Sync for EnvInner {}
 
 impl Drop for EnvInner {
     fn drop(&mut self) {
+        self.events.emit(EnvEvent::Closing { path: self.path.clone() });
         let mut lock = OPENED_ENV.write().unwrap();
         let removed = lock.remove(&self.path);
         debug_assert!(removed.is_some());
+        self.read_txn_cache.clear();
+        #[cfg(feature = "track-read-txns")]
+        self.live_readers.warn_leaked(&self.path);
+        unregister_env_generation(self.env_ptr);
         unsafe { ffi::mdb_env_close(self.env_ptr.as_mut()) };
         self.signal_event.signal();
     }
@@ -984,6 +1128,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn reader_lag() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().open(dir.path()).unwrap() };
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env.create_database::<Str, Str>(&mut wtxn, None).unwrap();
+        wtxn.commit().unwrap();
+        assert_eq!(env.oldest_reader_txn_id().unwrap(), None);
+        assert_eq!(env.reader_lag().unwrap(), 0);
+
+        let rtxn = env.read_txn().unwrap();
+        let snapshot = rtxn.id() as u64;
+        for lag in 1..=3 {
+            let mut wtxn = env.write_txn().unwrap();
+            db.put(&mut wtxn, "key", &lag.to_string()).unwrap();
+            wtxn.commit().unwrap();
+            assert_eq!(env.reader_lag().unwrap(), lag);
+        }
+
+        // A newer reader is not the oldest one.
+        let newer = env.read_txn().unwrap();
+        assert_eq!(env.oldest_reader_txn_id().unwrap(), Some(snapshot));
+        assert_eq!(env.reader_lag().unwrap(), 3);
+
+        drop(rtxn);
+        assert_eq!(env.oldest_reader_txn_id().unwrap(), Some(newer.id() as u64));
+        assert_eq!(env.reader_lag().unwrap(), 0);
+        drop(newer);
+        assert_eq!(env.oldest_reader_txn_id().unwrap(), None);
+        assert_eq!(env.reader_lag().unwrap(), 0);
+    }
+
     #[test]
     fn max_key_size() {
         let dir = tempfile::tempdir().unwrap();
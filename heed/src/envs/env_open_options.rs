@@ -6,6 +6,7 @@ use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::ptr::NonNull;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{io, ptr};
 
 #[cfg(master3)]
@@ -15,6 +16,7 @@ use synchronoise::SignalEvent;
 #[cfg(master3)]
 use super::encrypted_env::{encrypt_func_wrapper, EncryptedEnv};
 use super::env::Env;
+use super::slow_txn::SlowTxnTracker;
 use super::{canonicalize_path, OPENED_ENV};
 #[cfg(windows)]
 use crate::envs::OsStrExtLmdb as _;
@@ -30,7 +32,8 @@ pub struct EnvOpenOptions<T: TlsUsage = WithTls> {
     map_size: Option<usize>,
     max_readers: Option<u32>,
     max_dbs: Option<u32>,
-    flags: EnvFlags,
+    pub(super) flags: EnvFlags,
+    warn_slow_txn: Option<Duration>,
     _tls_marker: PhantomData<T>,
 }
 
@@ -42,6 +45,7 @@ impl EnvOpenOptions<WithTls> {
             max_readers: None,
             max_dbs: None,
             flags: EnvFlags::empty(),
+            warn_slow_txn: None,
             _tls_marker: PhantomData,
         }
     }
@@ -80,8 +84,15 @@ impl<T: TlsUsage> EnvOpenOptions<T> {
     /// # Ok(()) }
     /// ```
     pub fn read_txn_with_tls(self) -> EnvOpenOptions<WithTls> {
-        let Self { map_size, max_readers, max_dbs, flags, _tls_marker: _ } = self;
-        EnvOpenOptions { map_size, max_readers, max_dbs, flags, _tls_marker: PhantomData }
+        let Self { map_size, max_readers, max_dbs, flags, warn_slow_txn, _tls_marker: _ } = self;
+        EnvOpenOptions {
+            map_size,
+            max_readers,
+            max_dbs,
+            flags,
+            warn_slow_txn,
+            _tls_marker: PhantomData,
+        }
     }
 
     /// Make the read transactions `Send` by specifying they will
@@ -125,8 +136,15 @@ impl<T: TlsUsage> EnvOpenOptions<T> {
     /// # Ok(()) }
     /// ```
     pub fn read_txn_without_tls(self) -> EnvOpenOptions<WithoutTls> {
-        let Self { map_size, max_readers, max_dbs, flags, _tls_marker: _ } = self;
-        EnvOpenOptions { map_size, max_readers, max_dbs, flags, _tls_marker: PhantomData }
+        let Self { map_size, max_readers, max_dbs, flags, warn_slow_txn, _tls_marker: _ } = self;
+        EnvOpenOptions {
+            map_size,
+            max_readers,
+            max_dbs,
+            flags,
+            warn_slow_txn,
+            _tls_marker: PhantomData,
+        }
     }
 
     /// Set the size of the memory map to use for this environment.
@@ -149,6 +167,18 @@ impl<T: TlsUsage> EnvOpenOptions<T> {
         self
     }
 
+    /// Reports the transactions that stay open longer than the threshold.
+    ///
+    /// A long read transaction prevents the reuse of the pages freed after it began and a
+    /// long write transaction blocks the other writers. The transactions are reported on
+    /// the standard error, or to the handler given to [`Env::on_slow_txn`], when they
+    /// exceed the threshold while still open and once again when they end. The place
+    /// where they began is reported as well when backtraces are enabled.
+    pub fn warn_slow_txn(&mut self, threshold: Duration) -> &mut Self {
+        self.warn_slow_txn = Some(threshold);
+        self
+    }
+
     /// Set one or [more LMDB flags](http://www.lmdb.tech/doc/group__mdb__env.html).
     ///
     /// ```
@@ -192,6 +222,9 @@ impl<T: TlsUsage> EnvOpenOptions<T> {
     /// # Safety
     ///
     /// It is unsafe to use unsafe LMDB flags such as `NO_SYNC`, `NO_META_SYNC`, or `NO_LOCK`.
+    /// Prefer the methods requiring an explicit acknowledgment of the risk for these flags:
+    /// [`EnvOpenOptions::no_sync`], [`EnvOpenOptions::no_meta_sync`],
+    /// [`EnvOpenOptions::write_map_no_sync`] and [`EnvOpenOptions::no_lock`].
     pub unsafe fn flags(&mut self, flags: EnvFlags) -> &mut Self {
         self.flags |= flags;
         self
@@ -400,6 +433,7 @@ impl<T: TlsUsage> EnvOpenOptions<T> {
         path: &Path,
         #[cfg(master3)] enc: Option<(ffi::MDB_enc_func, &[u8], u32)>,
     ) -> Result<Env<T>> {
+        let slow_txns = self.warn_slow_txn.map(SlowTxnTracker::start).transpose()?;
         let mut lock = OPENED_ENV.write().unwrap();
 
         let path = match canonicalize_path(path) {
@@ -468,7 +502,7 @@ impl<T: TlsUsage> EnvOpenOptions<T> {
                         let signal_event = Arc::new(SignalEvent::manual(false));
                         let inserted = lock.insert(path.clone(), signal_event.clone());
                         debug_assert!(inserted.is_none());
-                        Ok(Env::new(env_ptr, path, signal_event))
+                        Ok(Env::new(env_ptr, path, signal_event, slow_txns))
                     }
                     Err(e) => {
                         ffi::mdb_env_close(env);
@@ -488,7 +522,7 @@ impl Default for EnvOpenOptions<WithTls> {
 
 impl<T: TlsUsage> Clone for EnvOpenOptions<T> {
     fn clone(&self) -> Self {
-        let Self { map_size, max_readers, max_dbs, flags, _tls_marker } = *self;
-        EnvOpenOptions { map_size, max_readers, max_dbs, flags, _tls_marker }
+        let Self { map_size, max_readers, max_dbs, flags, warn_slow_txn, _tls_marker } = *self;
+        EnvOpenOptions { map_size, max_readers, max_dbs, flags, warn_slow_txn, _tls_marker }
     }
 }
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use crate::Env;
+
+type EventHandler = dyn Fn(&EnvEvent) + Send + Sync;
+
+/// The handlers of the lifecycle events of an environment, see [`Env::on_event`].
+#[derive(Default)]
+pub(crate) struct EventHandlers {
+    handlers: RwLock<Vec<Arc<EventHandler>>>,
+}
+
+impl EventHandlers {
+    /// Hands the event to every handler, in the order they were added.
+    pub(crate) fn emit(&self, event: EnvEvent) {
+        let handlers = self.handlers.read().unwrap().clone();
+        handlers.iter().for_each(|handler| handler(&event));
+    }
+}
+
+/// A maintenance operation on an environment, handed to the handlers of [`Env::on_event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EnvEvent {
+    /// The memory map was resized with [`Env::resize`].
+    Resized {
+        /// The size of the map before the resize.
+        old_size: usize,
+        /// The size of the map after the resize.
+        new_size: usize,
+    },
+    /// The data was flushed to disk with [`Env::force_sync`].
+    Synced,
+    /// The reader table was checked with [`Env::clear_stale_readers`].
+    StaleReadersCleared {
+        /// The number of stale readers cleared.
+        count: usize,
+    },
+    /// The last handle to the environment was dropped, it is about to be closed.
+    Closing {
+        /// The path of the environment.
+        path: PathBuf,
+    },
+}
+
+impl<T> Env<T> {
+    /// Adds a handler called after every successful maintenance operation on the environment,
+    /// and when the environment is closed.
+    ///
+    /// The handlers are called on the thread doing the operation, in the order they were added,
+    /// and can't be removed. They must not drop the last handle to the environment.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use std::sync::mpsc;
+    /// use heed::EnvEvent;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let (sender, events) = mpsc::channel();
+    /// env.on_event(move |event| sender.send(event.clone()).unwrap());
+    ///
+    /// env.force_sync()?;
+    /// assert_eq!(events.recv()?, EnvEvent::Synced);
+    ///
+    /// drop(env);
+    /// assert!(matches!(events.recv()?, EnvEvent::Closing { .. }));
+    /// # Ok(()) }
+    /// ```
+    pub fn on_event<F>(&self, handler: F)
+    where
+        F: Fn(&EnvEvent) + Send + Sync + 'static,
+    {
+        self.inner.events.handlers.write().unwrap().push(Arc::new(handler));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{EnvOpenOptions, Result};
+
+    #[test]
+    fn env_events() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let page_size = page_size::get();
+        let env = unsafe { EnvOpenOptions::new().map_size(16 * page_size).open(dir.path())? };
+        let path = env.path().to_owned();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        env.on_event(move |event| sink.lock().unwrap().push(event.clone()));
+
+        env.force_sync()?;
+        env.clear_stale_readers()?;
+        unsafe { env.resize(32 * page_size)? };
+        // A failed operation is not reported.
+        assert!(unsafe { env.resize(page_size + 1) }.is_err());
+        drop(env);
+
+        let resized = EnvEvent::Resized { old_size: 16 * page_size, new_size: 32 * page_size };
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                EnvEvent::Synced,
+                EnvEvent::StaleReadersCleared { count: 0 },
+                resized,
+                EnvEvent::Closing { path },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Env;
+
+/// The live read transactions of an environment, with the place they began.
+pub(crate) struct LiveReaders {
+    readers: Mutex<HashMap<u64, Reader>>,
+    next_id: AtomicU64,
+}
+
+struct Reader {
+    thread: Option<String>,
+    began: Instant,
+    backtrace: Arc<Backtrace>,
+}
+
+impl LiveReaders {
+    pub(crate) fn new() -> LiveReaders {
+        LiveReaders { readers: Mutex::new(HashMap::new()), next_id: AtomicU64::new(0) }
+    }
+
+    /// Registers a new read transaction, returns the identifier to give to [`Self::remove`].
+    pub(crate) fn insert(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let reader = Reader {
+            thread: thread::current().name().map(str::to_owned),
+            began: Instant::now(),
+            backtrace: Arc::new(Backtrace::force_capture()),
+        };
+        self.readers.lock().unwrap().insert(id, reader);
+        id
+    }
+
+    pub(crate) fn remove(&self, id: u64) {
+        self.readers.lock().unwrap().remove(&id);
+    }
+
+    /// Returns the live read transactions, the oldest first.
+    fn report(&self) -> Vec<LiveReader> {
+        let readers = self.readers.lock().unwrap();
+        let mut report: Vec<_> = readers
+            .values()
+            .map(|reader| LiveReader {
+                thread: reader.thread.clone(),
+                age: reader.began.elapsed(),
+                backtrace: reader.backtrace.clone(),
+            })
+            .collect();
+        report.sort_by_key(|reader| std::cmp::Reverse(reader.age));
+        report
+    }
+
+    /// Warns about the read transactions still registered, called when the environment
+    /// is closed and no transaction can be alive anymore unless it was leaked.
+    pub(crate) fn warn_leaked(&self, path: &Path) {
+        let leaked = self.report();
+        if !leaked.is_empty() {
+            eprintln!(
+                "heed: the environment at {} is closed with {} leaked read transactions",
+                path.display(),
+                leaked.len()
+            );
+            leaked.iter().for_each(|reader| eprintln!("{reader}"));
+        }
+    }
+}
+
+/// A read transaction still alive, returned by [`Env::live_readers_report`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LiveReader {
+    /// The name of the thread that began the transaction, if it has one.
+    pub thread: Option<String>,
+    /// The time elapsed since the transaction began.
+    pub age: Duration,
+    /// Where the transaction began.
+    pub backtrace: Arc<Backtrace>,
+}
+
+impl fmt::Display for LiveReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "read transaction alive for {:?}", self.age)?;
+        if let Some(thread) = &self.thread {
+            write!(f, " began on thread {thread:?}")?;
+        }
+        write!(f, " at:\n{}", self.backtrace)
+    }
+}
+
+impl<T> Env<T> {
+    /// Returns the read transactions of this environment that are still alive, the oldest first.
+    ///
+    /// A read transaction prevents the reuse of the pages freed after it began, the ones kept
+    /// alive by mistake make the database grow without bound. Only available with the
+    /// `track-read-txns` feature, which captures a backtrace every time a read transaction
+    /// begins. The transactions that were leaked are also reported on the standard error
+    /// when the environment is closed.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let rtxn = env.read_txn()?;
+    /// let report = env.live_readers_report();
+    /// assert_eq!(report.len(), 1);
+    /// println!("{}", report[0]);
+    ///
+    /// drop(rtxn);
+    /// assert!(env.live_readers_report().is_empty());
+    /// # Ok(()) }
+    /// ```
+    pub fn live_readers_report(&self) -> Vec<LiveReader> {
+        self.inner.live_readers.report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvOpenOptions, Result};
+
+    #[test]
+    fn live_readers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().read_txn_without_tls().open(dir.path())? };
+
+        let first = env.read_txn()?;
+        let second = env.clone().static_read_txn()?;
+        let wtxn = env.write_txn()?;
+        assert_eq!(env.live_readers_report().len(), 2);
+        drop(wtxn);
+
+        let report = env.live_readers_report();
+        assert!(report[0].age >= report[1].age);
+
+        first.commit()?;
+        assert_eq!(env.live_readers_report().len(), 1);
+        drop(second);
+        assert!(env.live_readers_report().is_empty());
+        Ok(())
+    }
+}
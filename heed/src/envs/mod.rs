@@ -1,12 +1,14 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::ffi::c_void;
+use std::ffi::{c_char, c_int, c_void, CStr};
 use std::fs::{File, Metadata};
 #[cfg(unix)]
 use std::os::unix::io::{AsRawFd, BorrowedFd, RawFd};
 use std::panic::catch_unwind;
 use std::path::{Path, PathBuf};
 use std::process::abort;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::sync::{Arc, LazyLock, RwLock};
 use std::time::Duration;
 #[cfg(windows)]
@@ -16,29 +18,119 @@ use std::{
 };
 use std::{fmt, io};
 
-use heed_traits::{Comparator, LexicographicComparator};
+use byteorder::NativeEndian;
+use heed_traits::{CodecIdentity, Comparator, FixedSize, LexicographicComparator};
 use synchronoise::event::SignalEvent;
 
 use crate::mdb::ffi;
+use crate::types::{U32, U64};
 #[allow(unused)] // for cargo auto doc links
-use crate::{Database, DatabaseFlags};
-
+use crate::{Database, DatabaseFlags, Error, Result};
+
+mod advise;
+mod archive;
+mod backup;
+mod cached_read;
+#[cfg(feature = "collation")]
+mod collation;
+mod comparator_registry;
 #[cfg(master3)]
 mod encrypted_env;
 mod env;
 mod env_open_options;
-
+mod events;
+#[cfg(feature = "track-read-txns")]
+mod live_readers;
+mod risks;
+mod slow_txn;
+mod snapshot;
+mod watch;
+mod write_queue;
+mod writer_lock;
+
+pub use advise::Advice;
+pub use cached_read::CachedRoTxn;
+#[cfg(feature = "collation")]
+pub use collation::{Collation, CollationComparator, RootCollation};
 #[cfg(master3)]
 pub use encrypted_env::EncryptedEnv;
 pub use env::Env;
 pub(crate) use env::EnvInner;
 pub use env_open_options::EnvOpenOptions;
+pub use events::EnvEvent;
+#[cfg(feature = "track-read-txns")]
+pub use live_readers::LiveReader;
+pub use risks::{AcceptCorruption, AcceptDataLoss, AcceptNoLocking};
+pub use slow_txn::{SlowTxn, SlowTxnState};
+pub use watch::{CommitWatch, WaitPast};
+pub use write_queue::{PendingWrite, WriteQueue, WriteQueueOptions};
+pub use writer_lock::WriterHolder;
+pub(crate) use writer_lock::WriterLock;
 
 /// Records the current list of opened environments for tracking purposes. The canonical
 /// path of an environment is removed when either an `Env` or `EncryptedEnv` is closed.
 static OPENED_ENV: LazyLock<RwLock<HashMap<PathBuf, Arc<SignalEvent>>>> =
     LazyLock::new(RwLock::default);
 
+/// The generations of the opened environments, by the address of their LMDB environment.
+///
+/// A generation is never reused, it identifies the environment a [`Database`] was opened in
+/// even when a new environment is allocated at the address of a closed one.
+static ENV_GENERATIONS: LazyLock<RwLock<HashMap<usize, u64>>> = LazyLock::new(RwLock::default);
+
+/// The generation given to the next opened environment.
+static NEXT_ENV_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Gives a new generation to an opened environment.
+pub(crate) fn register_env_generation(env_ptr: NonNull<ffi::MDB_env>) -> u64 {
+    let generation = NEXT_ENV_GENERATION.fetch_add(1, AtomicOrdering::Relaxed);
+    ENV_GENERATIONS.write().unwrap().insert(env_ptr.as_ptr() as usize, generation);
+    generation
+}
+
+/// Forgets the generation of an environment, must be called before it is closed.
+pub(crate) fn unregister_env_generation(env_ptr: NonNull<ffi::MDB_env>) {
+    let generation = ENV_GENERATIONS.write().unwrap().remove(&(env_ptr.as_ptr() as usize));
+    if let Some(generation) = generation {
+        crate::databases::limits::forget_size_limits(generation);
+    }
+}
+
+/// Returns the generation of an opened environment, zero if it is not known.
+pub(crate) fn env_generation(env_ptr: NonNull<ffi::MDB_env>) -> u64 {
+    ENV_GENERATIONS.read().unwrap().get(&(env_ptr.as_ptr() as usize)).copied().unwrap_or(0)
+}
+
+/// Returns whether the environment of this generation is still opened.
+pub(crate) fn is_env_generation_opened(generation: u64) -> bool {
+    ENV_GENERATIONS.read().unwrap().values().any(|g| *g == generation)
+}
+
+/// Returns the snapshot ids of the active readers of the environment, of every process.
+pub(crate) fn reader_txn_ids(env_ptr: NonNull<ffi::MDB_env>) -> Result<Vec<u64>> {
+    // The reader table is only exposed as text, one line per reader ending with
+    // the id of its snapshot, or a `-` if the slot isn't used by a transaction.
+    unsafe extern "C" fn push_line(msg: *const c_char, ctx: *mut c_void) -> c_int {
+        let lines = &mut *(ctx as *mut Vec<String>);
+        lines.push(CStr::from_ptr(msg).to_string_lossy().into_owned());
+        0
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let ctx = ptr::addr_of_mut!(lines).cast();
+    let result = unsafe { ffi::mdb_reader_list(env_ptr.as_ptr(), Some(push_line), ctx) };
+    if result < 0 {
+        return Err(Error::Io(io::Error::other("failed to list the readers")));
+    }
+
+    let ids = lines
+        .iter()
+        .flat_map(|lines| lines.lines())
+        .filter_map(|line| line.split_whitespace().nth(2)?.parse().ok())
+        .collect();
+    Ok(ids)
+}
+
 /// Returns a struct that allows to wait for the effective closing of an environment.
 pub fn env_closing_event<P: AsRef<Path>>(path: P) -> Option<EnvClosingEvent> {
     let lock = OPENED_ENV.read().unwrap();
@@ -140,12 +232,12 @@ impl OsStrExtLmdb for OsStr {
 }
 
 #[cfg(unix)]
-fn get_file_fd(file: &File) -> RawFd {
+pub(crate) fn get_file_fd(file: &File) -> RawFd {
     file.as_raw_fd()
 }
 
 #[cfg(windows)]
-fn get_file_fd(file: &File) -> RawHandle {
+pub(crate) fn get_file_fd(file: &File) -> RawHandle {
     file.as_raw_handle()
 }
 
@@ -240,6 +332,62 @@ impl LexicographicComparator for DefaultComparator {
     }
 }
 
+impl CodecIdentity for DefaultComparator {
+    const NAME: &'static str = "default";
+}
+
+/// A lexicographic comparator ignoring the case of the ASCII letters.
+///
+/// The keys that only differ by the case of their ASCII letters are equal: LMDB stores a single
+/// one of them, with the bytes of the key that was inserted first. The prefix iterators are
+/// case-insensitive too, the `"app"` prefix finds the `"Apple"` and `"APPLICATION"` keys without
+/// storing lowercased copies of the keys.
+///
+/// The bytes are sorted by their ASCII lowercase value, the uppercase letters are moved between
+/// the `` ` `` and `{` bytes, along with their lowercase counterpart.
+#[derive(Debug)]
+pub enum AsciiCaseInsensitive {}
+
+impl LexicographicComparator for AsciiCaseInsensitive {
+    #[inline]
+    fn compare_elem(a: u8, b: u8) -> Ordering {
+        a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase())
+    }
+
+    #[inline]
+    fn successor(elem: u8) -> Option<u8> {
+        match elem.to_ascii_lowercase() {
+            u8::MAX => None,
+            // The uppercase letters are sorted with the lowercase ones, after the `` ` `` byte.
+            b'@' => Some(b'['),
+            elem => Some(elem + 1),
+        }
+    }
+
+    #[inline]
+    fn predecessor(elem: u8) -> Option<u8> {
+        match elem.to_ascii_lowercase() {
+            u8::MIN => None,
+            b'[' => Some(b'@'),
+            elem => Some(elem - 1),
+        }
+    }
+
+    #[inline]
+    fn max_elem() -> u8 {
+        u8::MAX
+    }
+
+    #[inline]
+    fn min_elem() -> u8 {
+        u8::MIN
+    }
+}
+
+impl CodecIdentity for AsciiCaseInsensitive {
+    const NAME: &'static str = "ascii-case-insensitive";
+}
+
 /// A representation of LMDB's `MDB_INTEGERKEY` and `MDB_INTEGERDUP` comparator behavior.
 ///
 /// This enum is used to indicate a table should be sorted by the keys numeric
@@ -274,6 +422,65 @@ impl Comparator for IntegerComparator {
     }
 }
 
+impl CodecIdentity for IntegerComparator {
+    const NAME: &'static str = "integer";
+}
+
+/// A representation of LMDB's `MDB_REVERSEKEY` and `MDB_REVERSEDUP` comparator behavior.
+///
+/// This enum is used to indicate a table should be sorted by comparing the bytes of the keys
+/// from the last one to the first one. When a [`Database`] is created or opened with
+/// [`ReverseComparator`], it signifies that the comparator should not be explicitly
+/// set via [`ffi::mdb_set_compare`], instead the flag [`DatabaseFlags::REVERSE_KEY`]
+/// or [`DatabaseFlags::REVERSE_DUP`] is set on the table.
+///
+/// The keys sharing a suffix are contiguous, not the keys sharing a prefix: it is not a
+/// [`LexicographicComparator`] and the prefix iterators are not available, while the ranges
+/// are compared like LMDB does.
+#[derive(Debug)]
+pub enum ReverseComparator {}
+
+impl Comparator for ReverseComparator {
+    fn compare(a: &[u8], b: &[u8]) -> Ordering {
+        a.iter().rev().cmp(b.iter().rev())
+    }
+}
+
+impl CodecIdentity for ReverseComparator {
+    const NAME: &'static str = "reverse";
+}
+
+/// An integer type that LMDB can compare natively, as the keys of a database opened with
+/// [`DatabaseOpenOptions::integer_keys`](crate::DatabaseOpenOptions::integer_keys).
+///
+/// LMDB compares the `MDB_INTEGERKEY` keys as unsigned integers of the size of an
+/// `unsigned int` or a `size_t`, stored in native byte order.
+pub trait IntegerKey {
+    /// The codec of the keys, in native byte order.
+    type Codec: FixedSize;
+}
+
+impl IntegerKey for u32 {
+    type Codec = U32<NativeEndian>;
+}
+
+#[cfg(target_pointer_width = "64")]
+impl IntegerKey for u64 {
+    type Codec = U64<NativeEndian>;
+}
+
+/// The keys of the size of a `size_t`, encoded as a [`u32`] or a [`u64`] depending on the target.
+#[cfg(target_pointer_width = "32")]
+impl IntegerKey for usize {
+    type Codec = U32<NativeEndian>;
+}
+
+/// The keys of the size of a `size_t`, encoded as a [`u32`] or a [`u64`] depending on the target.
+#[cfg(target_pointer_width = "64")]
+impl IntegerKey for usize {
+    type Codec = U64<NativeEndian>;
+}
+
 /// Whether to perform compaction while copying an environment.
 #[derive(Debug, Copy, Clone)]
 pub enum CompactionOption {
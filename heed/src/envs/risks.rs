@@ -0,0 +1,125 @@
+use crate::txn::TlsUsage;
+use crate::{EnvFlags, EnvOpenOptions};
+
+/// Acknowledges that the last committed transactions can be lost on a system crash,
+/// required by [`EnvOpenOptions::no_sync`] and [`EnvOpenOptions::no_meta_sync`].
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptDataLoss(());
+
+impl AcceptDataLoss {
+    /// Acknowledges the risk of losing the last committed transactions.
+    ///
+    /// # Safety
+    ///
+    /// The transactions committed since the last [`Env::force_sync`](crate::Env::force_sync)
+    /// can be lost, or rolled back when the environment is opened again, if the system crashes.
+    pub unsafe fn new() -> AcceptDataLoss {
+        AcceptDataLoss(())
+    }
+}
+
+/// Acknowledges that the environment can be corrupted by a system crash,
+/// required by [`EnvOpenOptions::write_map_no_sync`].
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptCorruption(());
+
+impl AcceptCorruption {
+    /// Acknowledges the risk of corrupting the environment.
+    ///
+    /// # Safety
+    ///
+    /// The operating system flushes the pages of the memory map in any order, the environment
+    /// can be left corrupted, not only outdated, if the system crashes before it is synced.
+    pub unsafe fn new() -> AcceptCorruption {
+        AcceptCorruption(())
+    }
+}
+
+/// Acknowledges that the accesses to the environment are not synchronized by LMDB,
+/// required by [`EnvOpenOptions::no_lock`].
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptNoLocking(());
+
+impl AcceptNoLocking {
+    /// Acknowledges the risk of unsynchronized accesses to the environment.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that only one write transaction is active at a time, and that
+    /// no read transaction uses a snapshot whose pages are reused by a writer, in every thread
+    /// and process using the environment.
+    pub unsafe fn new() -> AcceptNoLocking {
+        AcceptNoLocking(())
+    }
+}
+
+impl<T: TlsUsage> EnvOpenOptions<T> {
+    /// Doesn't flush the data to disk after every commit, see [`EnvFlags::NO_SYNC`].
+    ///
+    /// The risk is made visible by the [`AcceptDataLoss`] acknowledgment instead of an unsafe
+    /// call to [`EnvOpenOptions::flags`]. [`Env::force_sync`](crate::Env::force_sync) flushes
+    /// the data explicitly.
+    ///
+    /// ```
+    /// use heed::{AcceptDataLoss, EnvFlags, EnvOpenOptions};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dir = tempfile::tempdir()?;
+    /// let mut options = EnvOpenOptions::new();
+    /// // Safety: the cache can be rebuilt if the last transactions are lost.
+    /// options.no_sync(unsafe { AcceptDataLoss::new() });
+    /// let env = unsafe { options.open(dir.path())? };
+    /// assert_eq!(env.flags()?, Some(EnvFlags::NO_SYNC));
+    /// # Ok(()) }
+    /// ```
+    pub fn no_sync(&mut self, _ack: AcceptDataLoss) -> &mut Self {
+        self.flags |= EnvFlags::NO_SYNC;
+        self
+    }
+
+    /// Doesn't flush the metadata page after every commit, see [`EnvFlags::NO_META_SYNC`].
+    ///
+    /// The last transaction can be lost on a system crash, the integrity of the environment
+    /// is preserved.
+    pub fn no_meta_sync(&mut self, _ack: AcceptDataLoss) -> &mut Self {
+        self.flags |= EnvFlags::NO_META_SYNC;
+        self
+    }
+
+    /// Writes in place in the memory map without flushing the data after every commit, see
+    /// [`EnvFlags::WRITE_MAP`] and [`EnvFlags::NO_SYNC`].
+    ///
+    /// It is the fastest way to write, and the only combination of flags where a system
+    /// crash can corrupt the environment.
+    pub fn write_map_no_sync(&mut self, _ack: AcceptCorruption) -> &mut Self {
+        self.flags |= EnvFlags::WRITE_MAP | EnvFlags::NO_SYNC;
+        self
+    }
+
+    /// Doesn't use the lock file to synchronize the accesses, see [`EnvFlags::NO_LOCK`].
+    pub fn no_lock(&mut self, _ack: AcceptNoLocking) -> &mut Self {
+        self.flags |= EnvFlags::NO_LOCK;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn acknowledged_flags() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut options = EnvOpenOptions::new();
+        let data_loss = unsafe { AcceptDataLoss::new() };
+        options.no_meta_sync(data_loss).no_lock(unsafe { AcceptNoLocking::new() });
+        options.write_map_no_sync(unsafe { AcceptCorruption::new() });
+        let env = unsafe { options.open(dir.path())? };
+
+        let expected =
+            EnvFlags::NO_META_SYNC | EnvFlags::NO_LOCK | EnvFlags::WRITE_MAP | EnvFlags::NO_SYNC;
+        assert_eq!(env.flags()?, Some(expected));
+        Ok(())
+    }
+}
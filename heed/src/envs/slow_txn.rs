@@ -0,0 +1,227 @@
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+use std::{fmt, io, thread};
+
+use crate::Env;
+
+/// The shortest interval at which the open transactions are checked.
+const MIN_WATCH_INTERVAL: Duration = Duration::from_millis(10);
+
+type SlowTxnHandler = dyn Fn(&SlowTxn) + Send + Sync;
+
+/// Tracks the open transactions of an environment to report the slow ones,
+/// see [`EnvOpenOptions::warn_slow_txn`](crate::EnvOpenOptions::warn_slow_txn).
+pub(crate) struct SlowTxnTracker {
+    threshold: Duration,
+    handler: RwLock<Option<Arc<SlowTxnHandler>>>,
+    open: Mutex<HashMap<u64, OpenTxn>>,
+    next_id: AtomicU64,
+}
+
+struct OpenTxn {
+    write: bool,
+    began: Instant,
+    backtrace: Arc<Backtrace>,
+    /// Whether the transaction was reported while still open.
+    reported: bool,
+}
+
+impl SlowTxnTracker {
+    /// Creates a tracker and the thread reporting the transactions that stay open too long,
+    /// the thread stops once the tracker is dropped.
+    pub(crate) fn start(threshold: Duration) -> io::Result<Arc<SlowTxnTracker>> {
+        let tracker = Arc::new(SlowTxnTracker {
+            threshold,
+            handler: RwLock::new(None),
+            open: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        });
+        let weak = Arc::downgrade(&tracker);
+        thread::Builder::new().name("heed-slow-txn".into()).spawn(move || watch(weak))?;
+        Ok(tracker)
+    }
+
+    /// Starts tracking a transaction, returns the identifier to give to [`Self::finish`].
+    pub(crate) fn track(&self, write: bool) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let backtrace = Arc::new(Backtrace::capture());
+        let txn = OpenTxn { write, began: Instant::now(), backtrace, reported: false };
+        self.open.lock().unwrap().insert(id, txn);
+        id
+    }
+
+    /// Stops tracking a transaction, it is reported if it was slow.
+    pub(crate) fn finish(&self, id: u64, state: SlowTxnState) {
+        let txn = self.open.lock().unwrap().remove(&id);
+        if let Some(txn) = txn.filter(|txn| txn.began.elapsed() >= self.threshold) {
+            self.report(&txn.describe(state));
+        }
+    }
+
+    /// Reports the transactions that exceeded the threshold since the last check.
+    fn report_open(&self) {
+        let mut slow = Vec::new();
+        for txn in self.open.lock().unwrap().values_mut() {
+            if !txn.reported && txn.began.elapsed() >= self.threshold {
+                txn.reported = true;
+                slow.push(txn.describe(SlowTxnState::Open));
+            }
+        }
+        slow.iter().for_each(|txn| self.report(txn));
+    }
+
+    fn report(&self, txn: &SlowTxn) {
+        let handler = self.handler.read().unwrap().clone();
+        match handler {
+            Some(handler) => handler(txn),
+            None => eprintln!("heed: {txn}"),
+        }
+    }
+}
+
+impl OpenTxn {
+    fn describe(&self, state: SlowTxnState) -> SlowTxn {
+        SlowTxn {
+            write: self.write,
+            state,
+            duration: self.began.elapsed(),
+            backtrace: self.backtrace.clone(),
+        }
+    }
+}
+
+fn watch(tracker: Weak<SlowTxnTracker>) {
+    loop {
+        let interval = match tracker.upgrade() {
+            Some(tracker) => {
+                tracker.report_open();
+                (tracker.threshold / 2).max(MIN_WATCH_INTERVAL)
+            }
+            None => return,
+        };
+        thread::sleep(interval);
+    }
+}
+
+/// A transaction that stayed open longer than the threshold given to
+/// [`EnvOpenOptions::warn_slow_txn`](crate::EnvOpenOptions::warn_slow_txn).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SlowTxn {
+    /// Whether it is a write transaction.
+    pub write: bool,
+    /// Whether the transaction is still open or how it ended.
+    pub state: SlowTxnState,
+    /// The time elapsed since the transaction began.
+    pub duration: Duration,
+    /// Where the transaction began, captured if enabled by the `RUST_BACKTRACE`
+    /// or `RUST_LIB_BACKTRACE` environment variables, see [`Backtrace::capture`].
+    pub backtrace: Arc<Backtrace>,
+}
+
+/// The state of a [`SlowTxn`] when it is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowTxnState {
+    /// The transaction is still open, it is reported again once it ends.
+    Open,
+    /// The transaction was committed.
+    Committed,
+    /// The transaction was aborted or dropped.
+    Aborted,
+}
+
+impl fmt::Display for SlowTxn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.write { "write" } else { "read" };
+        let state = match self.state {
+            SlowTxnState::Open => "open for",
+            SlowTxnState::Committed => "committed after",
+            SlowTxnState::Aborted => "aborted after",
+        };
+        write!(
+            f,
+            "slow {kind} transaction {state} {:?}, began at:\n{}",
+            self.duration, self.backtrace
+        )
+    }
+}
+
+impl<T> Env<T> {
+    /// Hands the slow transactions to the handler instead of printing them on the standard error.
+    ///
+    /// Does nothing unless the environment was opened with
+    /// [`EnvOpenOptions::warn_slow_txn`](crate::EnvOpenOptions::warn_slow_txn).
+    /// The handler is called on the thread ending the transaction, or on a dedicated thread
+    /// for the transactions still open.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use std::sync::mpsc;
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// let env = unsafe {
+    ///     EnvOpenOptions::new()
+    ///         .warn_slow_txn(Duration::from_millis(10))
+    ///         .open(dir.path())?
+    /// };
+    /// let (sender, slow_txns) = mpsc::channel();
+    /// env.on_slow_txn(move |txn| sender.send(txn.duration).unwrap());
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// std::thread::sleep(Duration::from_millis(20));
+    /// drop(rtxn);
+    ///
+    /// assert!(slow_txns.recv()? >= Duration::from_millis(10));
+    /// # Ok(()) }
+    /// ```
+    pub fn on_slow_txn<F>(&self, handler: F)
+    where
+        F: Fn(&SlowTxn) + Send + Sync + 'static,
+    {
+        if let Some(tracker) = &self.inner.slow_txns {
+            *tracker.handler.write().unwrap() = Some(Arc::new(handler));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{EnvOpenOptions, Result};
+
+    #[test]
+    fn slow_txns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe {
+            EnvOpenOptions::new().warn_slow_txn(Duration::from_millis(50)).open(dir.path())?
+        };
+        let (sender, receiver) = mpsc::channel();
+        env.on_slow_txn(move |txn| sender.send((txn.write, txn.state)).unwrap());
+
+        // Quick transactions are not reported.
+        env.write_txn()?.commit()?;
+        env.read_txn()?;
+
+        let wtxn = env.write_txn()?;
+        let open = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(open, (true, SlowTxnState::Open));
+        wtxn.commit()?;
+        assert_eq!(receiver.recv().unwrap(), (true, SlowTxnState::Committed));
+
+        let rtxn = env.read_txn()?;
+        thread::sleep(Duration::from_millis(60));
+        drop(rtxn);
+        let reports: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(reports.last(), Some(&(false, SlowTxnState::Aborted)));
+        Ok(())
+    }
+}
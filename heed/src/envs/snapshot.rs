@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use crate::meta::Metadata;
+use crate::*;
+
+impl<T> Env<T> {
+    /// Writes a compacted, point-in-time copy of this environment to the file at the given path,
+    /// returns the id of the last transaction included in the copy.
+    ///
+    /// The id is recorded in the [metadata](crate::meta) of the snapshot, see
+    /// [`Metadata::snapshot_txn_id`]. Writers wait for the copy to complete so that the id is the
+    /// exact one of the copied state, readers are not blocked. The snapshot is a single file without
+    /// lock file, meant to be opened with [`Env::open_snapshot`]. An existing file is overwritten.
+    ///
+    /// Fails if a write transaction is already opened on this thread.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::meta::Metadata;
+    /// use heed::types::*;
+    /// use heed::Env;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+    /// db.put(&mut wtxn, "kero", "admin")?;
+    /// wtxn.commit()?;
+    ///
+    /// # let snapshot_dir = tempfile::tempdir()?;
+    /// let path = snapshot_dir.path().join("users.mdb");
+    /// let txn_id = env.snapshot_to(&path)?;
+    ///
+    /// let snapshot = unsafe { Env::open_snapshot(&path)? };
+    /// let rtxn = snapshot.read_txn()?;
+    /// let db = snapshot.open_database::<Str, Str>(&rtxn, Some("users"))?.unwrap();
+    /// assert_eq!(db.get(&rtxn, "kero")?, Some("admin"));
+    /// let metadata = Metadata::open(&snapshot, &rtxn)?.unwrap();
+    /// assert_eq!(metadata.snapshot_txn_id(&rtxn)?, Some(txn_id));
+    /// # Ok(()) }
+    /// ```
+    pub fn snapshot_to(&self, path: impl AsRef<Path>) -> Result<u64> {
+        let path = path.as_ref();
+
+        // The write transaction prevents any commit during the copy.
+        let wtxn = self.write_txn()?;
+        let txn_id = self.info().last_txn_id as u64;
+        self.copy_to_path(path, CompactionOption::Enabled)?;
+        wtxn.abort();
+
+        // The snapshot isn't shared yet, it can't be accessed concurrently.
+        let snapshot = unsafe {
+            EnvOpenOptions::new()
+                .read_txn_without_tls()
+                .map_size(self.info().map_size)
+                .max_dbs(1)
+                .flags(EnvFlags::NO_SUB_DIR | EnvFlags::NO_LOCK)
+                .open(path)?
+        };
+        let mut wtxn = snapshot.write_txn()?;
+        Metadata::create(&snapshot, &mut wtxn)?.set_snapshot_txn_id(&mut wtxn, txn_id)?;
+        wtxn.commit()?;
+
+        Ok(txn_id)
+    }
+}
+
+impl Env<WithoutTls> {
+    /// Opens a snapshot written by [`Env::snapshot_to`] strictly read-only.
+    ///
+    /// The snapshot is opened without locking, its readers don't use reader slots
+    /// and don't pin any page of the environment it was copied from. All its named
+    /// databases can be opened.
+    ///
+    /// # Safety
+    ///
+    /// The snapshot file must not be modified while it is opened, see [`EnvOpenOptions::open`].
+    pub unsafe fn open_snapshot(path: impl AsRef<Path>) -> Result<Env<WithoutTls>> {
+        let path = path.as_ref();
+        let flags = EnvFlags::READ_ONLY | EnvFlags::NO_SUB_DIR | EnvFlags::NO_LOCK;
+
+        // The named databases are entries of the unnamed one,
+        // their number is at most the number of its entries.
+        let entries = {
+            let env = EnvOpenOptions::new().read_txn_without_tls().flags(flags).open(path)?;
+            env.stat().entries
+        };
+
+        EnvOpenOptions::new()
+            .read_txn_without_tls()
+            .max_dbs(entries.try_into().unwrap_or(u32::MAX))
+            .flags(flags)
+            .open(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+
+    #[test]
+    fn snapshot_and_open() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let first = env.create_database::<Str, Str>(&mut wtxn, Some("first"))?;
+        let second = env.create_database::<Str, Str>(&mut wtxn, Some("second"))?;
+        first.put(&mut wtxn, "hello", "world")?;
+        second.put(&mut wtxn, "answer", "42")?;
+        wtxn.commit()?;
+
+        let snapshot_dir = tempfile::tempdir()?;
+        let path = snapshot_dir.path().join("snapshot.mdb");
+        let txn_id = env.snapshot_to(&path)?;
+        assert_eq!(txn_id, env.read_txn()?.id() as u64);
+
+        // The changes committed after the snapshot are not in it.
+        let mut wtxn = env.write_txn()?;
+        first.put(&mut wtxn, "hello", "there")?;
+        wtxn.commit()?;
+
+        let snapshot = unsafe { Env::open_snapshot(&path)? };
+        let rtxn = snapshot.read_txn()?;
+        let first = snapshot.open_database::<Str, Str>(&rtxn, Some("first"))?.unwrap();
+        let second = snapshot.open_database::<Str, Str>(&rtxn, Some("second"))?.unwrap();
+        assert_eq!(first.get(&rtxn, "hello")?, Some("world"));
+        assert_eq!(second.get(&rtxn, "answer")?, Some("42"));
+        let metadata = Metadata::open(&snapshot, &rtxn)?.unwrap();
+        assert_eq!(metadata.snapshot_txn_id(&rtxn)?, Some(txn_id));
+        drop(rtxn);
+
+        // The snapshot is strictly read-only and has no lock file.
+        assert!(snapshot.write_txn().is_err());
+        assert_eq!(std::fs::read_dir(snapshot_dir.path())?.count(), 1);
+
+        Ok(())
+    }
+}
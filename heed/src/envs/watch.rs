@@ -0,0 +1,288 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+use std::{fmt, mem, thread};
+
+use super::EnvInner;
+use crate::mdb::ffi;
+use crate::Env;
+
+/// The default interval at which the commits of other processes are looked for.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wakes the threads and tasks waiting for new commits in this process.
+pub(crate) struct CommitSignal {
+    state: Mutex<SignalState>,
+    condvar: Condvar,
+}
+
+struct SignalState {
+    /// The tasks waiting for a commit.
+    wakers: Vec<Waker>,
+    /// Whether a thread polls the last transaction id for the waiting tasks.
+    poller: bool,
+    /// The interval at which the poller wakes the waiting tasks.
+    poll_interval: Duration,
+}
+
+impl CommitSignal {
+    pub(crate) fn new() -> CommitSignal {
+        let state =
+            SignalState { wakers: Vec::new(), poller: false, poll_interval: DEFAULT_POLL_INTERVAL };
+        CommitSignal { state: Mutex::new(state), condvar: Condvar::new() }
+    }
+
+    /// Wakes everyone waiting, called after every commit of a write transaction.
+    pub(crate) fn notify(&self) {
+        let wakers = mem::take(&mut self.state.lock().unwrap().wakers);
+        self.condvar.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl<T> Env<T> {
+    /// Returns a handle to wait for the commits of write transactions.
+    ///
+    /// The commits of this process are noticed immediately, the ones of other processes are
+    /// noticed by polling the last transaction id at the [poll interval](CommitWatch::poll_interval).
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use std::thread;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let watch = env.watch();
+    /// let seen = watch.last_txn_id();
+    ///
+    /// let writer = thread::spawn({
+    ///     let env = env.clone();
+    ///     move || -> heed::Result<()> {
+    ///         let mut wtxn = env.write_txn()?;
+    ///         env.create_database::<Str, Str>(&mut wtxn, Some("events"))?;
+    ///         wtxn.commit()
+    ///     }
+    /// });
+    ///
+    /// let last = watch.wait_past(seen);
+    /// assert!(last > seen);
+    /// writer.join().unwrap()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn watch(&self) -> CommitWatch {
+        CommitWatch { env: self.inner.clone(), poll_interval: DEFAULT_POLL_INTERVAL }
+    }
+}
+
+/// A handle to wait until the last committed transaction id of an environment
+/// advances past a given value, returned by [`Env::watch`].
+///
+/// The handle keeps the environment opened.
+#[derive(Clone)]
+pub struct CommitWatch {
+    env: Arc<EnvInner>,
+    poll_interval: Duration,
+}
+
+impl CommitWatch {
+    /// Sets the interval at which the last transaction id is polled to notice the commits
+    /// of other processes, 100 milliseconds by default.
+    ///
+    /// The asynchronous waits all share the smallest interval of the watches in use.
+    pub fn poll_interval(&mut self, interval: Duration) -> &mut Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Returns the id of the last committed transaction.
+    ///
+    /// It is the [id](crate::RoTxn::id) of the read transactions opened now.
+    pub fn last_txn_id(&self) -> u64 {
+        let mut raw_info = mem::MaybeUninit::uninit();
+        unsafe { ffi::mdb_env_info(self.env.env_mut_ptr().as_ptr(), raw_info.as_mut_ptr()) };
+        unsafe { raw_info.assume_init() }.me_last_txnid as u64
+    }
+
+    /// Blocks this thread until the last committed transaction id is greater than `txn_id`,
+    /// returns the new last transaction id.
+    pub fn wait_past(&self, txn_id: u64) -> u64 {
+        self.wait_past_deadline(txn_id, None).unwrap()
+    }
+
+    /// Blocks this thread until the last committed transaction id is greater than `txn_id`
+    /// or until the timeout elapses. Returns the new last transaction id, `None` on timeout.
+    pub fn wait_past_timeout(&self, txn_id: u64, timeout: Duration) -> Option<u64> {
+        self.wait_past_deadline(txn_id, Instant::now().checked_add(timeout))
+    }
+
+    /// Waits for a new transaction id until the deadline, if any.
+    fn wait_past_deadline(&self, txn_id: u64, deadline: Option<Instant>) -> Option<u64> {
+        let signal = &self.env.commit_signal;
+        // The lock is held while reading the id: a commit can't notify in between.
+        let mut state = signal.state.lock().unwrap();
+        loop {
+            let last = self.last_txn_id();
+            if last > txn_id {
+                return Some(last);
+            }
+
+            let mut wait = self.poll_interval;
+            if let Some(deadline) = deadline {
+                match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => wait = wait.min(remaining),
+                    _ => return None,
+                }
+            }
+            state = signal.condvar.wait_timeout(state, wait).unwrap().0;
+        }
+    }
+
+    /// Returns a future that resolves to the new last transaction id
+    /// once the last committed transaction id is greater than `txn_id`.
+    ///
+    /// The future doesn't depend on an async runtime, the commits of other processes
+    /// are polled by a background thread while futures are waiting.
+    pub fn wait_past_async(&self, txn_id: u64) -> WaitPast<'_> {
+        WaitPast { watch: self, txn_id }
+    }
+}
+
+impl fmt::Debug for CommitWatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CommitWatch")
+            .field("path", &self.env.path.display())
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
+
+/// The future returned by [`CommitWatch::wait_past_async`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitPast<'a> {
+    watch: &'a CommitWatch,
+    txn_id: u64,
+}
+
+impl Future for WaitPast<'_> {
+    type Output = u64;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u64> {
+        let signal = &self.watch.env.commit_signal;
+        let mut state = signal.state.lock().unwrap();
+        let last = self.watch.last_txn_id();
+        if last > self.txn_id {
+            return Poll::Ready(last);
+        }
+
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        state.poll_interval = state.poll_interval.min(self.watch.poll_interval);
+        if !state.poller {
+            state.poller = true;
+            let env = Arc::downgrade(&self.watch.env);
+            let interval = state.poll_interval;
+            thread::Builder::new()
+                .name(String::from("heed-commit-watch"))
+                .spawn(move || poll_commits(env, interval))
+                .expect("failed to spawn the commit watch thread");
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Wakes the waiting tasks at every poll interval so that they check the last transaction id,
+/// until no task waits or the environment is closed.
+fn poll_commits(env: Weak<EnvInner>, mut interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let Some(env) = env.upgrade() else { return };
+        let mut state = env.commit_signal.state.lock().unwrap();
+        if state.wakers.is_empty() {
+            state.poller = false;
+            state.poll_interval = DEFAULT_POLL_INTERVAL;
+            return;
+        }
+        interval = state.poll_interval;
+        let wakers = mem::take(&mut state.wakers);
+        drop(state);
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+
+    use super::*;
+    use crate::types::Str;
+    use crate::{Database, EnvOpenOptions, Result};
+
+    /// Runs a future to completion on the current thread.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct ThreadWaker(thread::Thread, AtomicBool);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.1.store(true, Ordering::SeqCst);
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(ThreadWaker(thread::current(), AtomicBool::new(false)));
+        let mut future = std::pin::pin!(future);
+        let task_waker = Waker::from(waker.clone());
+        let mut cx = Context::from_waker(&task_waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            while !waker.1.swap(false, Ordering::SeqCst) {
+                thread::park();
+            }
+        }
+    }
+
+    #[test]
+    fn wait_for_commits() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let watch = env.watch();
+
+        let first = watch.last_txn_id();
+        assert_eq!(watch.wait_past_timeout(first, Duration::from_millis(10)), None);
+        assert_eq!(watch.wait_past_timeout(first.saturating_sub(1), Duration::ZERO), Some(first));
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("events"))?;
+        wtxn.commit()?;
+        let second = watch.wait_past(first);
+        assert_eq!(second, first + 1);
+        assert_eq!(second, env.read_txn()?.id() as u64);
+
+        let writer = thread::spawn({
+            let env = env.clone();
+            move || -> Result<()> {
+                thread::sleep(Duration::from_millis(50));
+                let mut wtxn = env.write_txn()?;
+                db.put(&mut wtxn, "hello", "world")?;
+                wtxn.commit()
+            }
+        });
+        assert_eq!(block_on(watch.wait_past_async(second)), second + 1);
+        writer.join().unwrap()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+use std::{fmt, io, thread};
+
+use crate::{Env, Error, Result, RwTxn};
+
+/// Options to spawn the writer thread of a [`WriteQueue`].
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::types::*;
+/// use heed::WriteQueueOptions;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, U64<byteorder::BE>>(&mut wtxn, Some("counters"))?;
+/// wtxn.commit()?;
+///
+/// let queue = WriteQueueOptions::new().max_batch_ops(64).spawn(&env)?;
+/// let pending: Vec<_> = (0..10)
+///     .map(|i| queue.submit(move |wtxn| db.put(wtxn, &format!("counter-{i}"), &i)))
+///     .collect();
+/// for pending in pending {
+///     pending.wait()?;
+/// }
+///
+/// let rtxn = env.read_txn()?;
+/// assert_eq!(db.len(&rtxn)?, 10);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WriteQueueOptions {
+    max_batch_ops: usize,
+    max_batch_latency: Duration,
+    max_batch_bytes: usize,
+}
+
+impl Default for WriteQueueOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WriteQueueOptions {
+    /// Creates the options of a queue that runs every closure in its own transaction.
+    pub fn new() -> Self {
+        WriteQueueOptions {
+            max_batch_ops: 1,
+            max_batch_latency: Duration::ZERO,
+            max_batch_bytes: usize::MAX,
+        }
+    }
+
+    /// Sets the maximum number of queued closures that can be coalesced into a single
+    /// write transaction, 1 by default.
+    ///
+    /// The closures waiting in the queue when the writer thread is ready run one after the other
+    /// in nested transactions of the same write transaction, which is committed once. A closure
+    /// that fails only aborts its nested transaction, but the failure of the commit is the
+    /// failure of all the closures of the transaction.
+    pub fn max_batch_ops(&mut self, max: usize) -> &mut Self {
+        self.max_batch_ops = max.max(1);
+        self
+    }
+
+    /// Sets how long the writer thread waits for more closures to coalesce
+    /// after the first one of a transaction is submitted, zero by default.
+    ///
+    /// Without waiting, only the closures already queued are coalesced. Waiting lets many
+    /// small writes share the commit, and its sync to disk, of a single transaction, at the
+    /// cost of delaying each of them by at most this latency. The transaction starts as soon
+    /// as the [maximum number of closures](Self::max_batch_ops) or of
+    /// [bytes](Self::max_batch_bytes) is reached.
+    pub fn max_batch_latency(&mut self, latency: Duration) -> &mut Self {
+        self.max_batch_latency = latency;
+        self
+    }
+
+    /// Sets the maximum number of bytes the closures of a single transaction may write,
+    /// unlimited by default.
+    ///
+    /// The number of bytes of a closure is the one given to [`WriteQueue::submit_sized`],
+    /// it is zero for the other closures. A closure exceeding the limit on its own runs
+    /// alone in its transaction.
+    pub fn max_batch_bytes(&mut self, max: usize) -> &mut Self {
+        self.max_batch_bytes = max;
+        self
+    }
+
+    /// Spawns the writer thread of the environment and returns a handle to its queue.
+    ///
+    /// The thread keeps the environment opened, it stops once all the handles of the
+    /// queue are dropped and the closures submitted to it are executed.
+    pub fn spawn<T: 'static>(&self, env: &Env<T>) -> Result<WriteQueue> {
+        let (sender, receiver) = mpsc::channel();
+        let env = env.clone();
+        let options = self.clone();
+        thread::Builder::new()
+            .name(String::from("heed-writer"))
+            .spawn(move || run_writer(env, options, receiver))?;
+        Ok(WriteQueue { sender })
+    }
+}
+
+impl<T> Env<T> {
+    /// Spawns a writer thread that executes write closures in their submission order,
+    /// see [`WriteQueueOptions`] to coalesce them into fewer transactions.
+    pub fn write_queue(&self) -> Result<WriteQueue>
+    where
+        T: 'static,
+    {
+        WriteQueueOptions::new().spawn(self)
+    }
+}
+
+/// A handle to the queue of a dedicated writer thread, returned by [`Env::write_queue`]
+/// and [`WriteQueueOptions::spawn`].
+///
+/// LMDB allows a single write transaction at a time, the writer thread owns it and the
+/// other threads and tasks submit closures to it instead of waiting for the write lock.
+/// The handle can be cloned and shared to submit from anywhere.
+///
+/// A closure must not wait for another closure of the same queue, and a thread must
+/// not wait for a closure while it holds a write transaction of the environment,
+/// it would never complete.
+#[derive(Clone)]
+pub struct WriteQueue {
+    sender: Sender<Box<dyn Job>>,
+}
+
+impl WriteQueue {
+    /// Submits a closure to the writer thread, its transaction is committed if it returns `Ok`.
+    ///
+    /// The returned handle can be waited for by blocking the thread or be awaited.
+    pub fn submit<F, T, E>(&self, f: F) -> PendingWrite<T, E>
+    where
+        F: FnOnce(&mut RwTxn) -> std::result::Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        self.submit_sized(0, f)
+    }
+
+    /// Submits a closure that writes about `bytes` bytes to the writer thread,
+    /// the size is only used to limit the [bytes of a batch](WriteQueueOptions::max_batch_bytes).
+    pub fn submit_sized<F, T, E>(&self, bytes: usize, f: F) -> PendingWrite<T, E>
+    where
+        F: FnOnce(&mut RwTxn) -> std::result::Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        let slot = Arc::new(Slot::new());
+        let job = WriteJob { bytes, f: Some(f), output: None, slot: Some(slot.clone()) };
+        // The job completes its slot with an error when it is dropped without being executed.
+        let _ = self.sender.send(Box::new(job));
+        PendingWrite { slot, _marker: PhantomData }
+    }
+
+    /// Submits a closure to the writer thread and blocks this thread until it is executed
+    /// and its transaction committed.
+    pub fn write<F, T, E>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(&mut RwTxn) -> std::result::Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        self.submit(f).wait()
+    }
+}
+
+impl fmt::Debug for WriteQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WriteQueue").finish_non_exhaustive()
+    }
+}
+
+/// The result of a closure submitted to a [`WriteQueue`].
+///
+/// It can be waited for with [`PendingWrite::wait`] or awaited, the future doesn't depend on
+/// an async runtime. A panic of the closure is resumed in the thread or the task that waits
+/// for it.
+#[must_use = "the result of the write is lost if it is not waited for"]
+pub struct PendingWrite<T, E = Error> {
+    slot: Arc<Slot<std::result::Result<T, E>>>,
+    _marker: PhantomData<fn() -> (T, E)>,
+}
+
+impl<T, E> PendingWrite<T, E> {
+    /// Blocks this thread until the closure is executed and its transaction committed.
+    pub fn wait(self) -> std::result::Result<T, E> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            if let Some(output) = state.output.take() {
+                return output.unwrap_or_else(|payload| resume_unwind(payload));
+            }
+            state = self.slot.condvar.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T, E> Future for PendingWrite<T, E> {
+    type Output = std::result::Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.state.lock().unwrap();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output.unwrap_or_else(|payload| resume_unwind(payload))),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for PendingWrite<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PendingWrite").finish_non_exhaustive()
+    }
+}
+
+/// The output of a closure, shared by the writer thread and the waiter.
+struct Slot<R> {
+    state: Mutex<SlotState<R>>,
+    condvar: Condvar,
+}
+
+struct SlotState<R> {
+    output: Option<thread::Result<R>>,
+    waker: Option<Waker>,
+}
+
+impl<R> Slot<R> {
+    fn new() -> Slot<R> {
+        Slot { state: Mutex::new(SlotState { output: None, waker: None }), condvar: Condvar::new() }
+    }
+
+    fn complete(&self, output: thread::Result<R>) {
+        let mut state = self.state.lock().unwrap();
+        state.output = Some(output);
+        let waker = state.waker.take();
+        drop(state);
+        self.condvar.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// A type-erased closure submitted to the writer thread.
+trait Job: Send {
+    /// The number of bytes the closure is expected to write.
+    fn bytes(&self) -> usize;
+
+    /// Executes the closure in the transaction, returns whether it succeeded.
+    fn run(&mut self, wtxn: &mut RwTxn) -> bool;
+
+    /// Sends the output of the closure, or the error that prevented its execution
+    /// or the commit of its transaction.
+    fn complete(self: Box<Self>, error: Option<&Error>);
+}
+
+struct WriteJob<F, T, E: From<Error>> {
+    bytes: usize,
+    f: Option<F>,
+    output: Option<thread::Result<std::result::Result<T, E>>>,
+    slot: Option<Arc<Slot<std::result::Result<T, E>>>>,
+}
+
+impl<F, T, E> Job for WriteJob<F, T, E>
+where
+    F: FnOnce(&mut RwTxn) -> std::result::Result<T, E> + Send,
+    T: Send,
+    E: From<Error> + Send,
+{
+    fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    fn run(&mut self, wtxn: &mut RwTxn) -> bool {
+        let f = self.f.take().unwrap();
+        let output = catch_unwind(AssertUnwindSafe(|| f(wtxn)));
+        let succeeded = matches!(output, Ok(Ok(_)));
+        self.output = Some(output);
+        succeeded
+    }
+
+    fn complete(mut self: Box<Self>, error: Option<&Error>) {
+        let output = match (self.output.take(), error) {
+            // A failed closure reports its own failure.
+            (Some(output @ (Ok(Err(_)) | Err(_))), _) => output,
+            (_, Some(error)) => Ok(Err(share_error(error).into())),
+            (Some(output), None) => output,
+            (None, None) => unreachable!("completing a write that never ran"),
+        };
+        if let Some(slot) = self.slot.take() {
+            slot.complete(output);
+        }
+    }
+}
+
+impl<F, T, E: From<Error>> Drop for WriteJob<F, T, E> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            let error = io::Error::new(io::ErrorKind::BrokenPipe, "the writer thread has stopped");
+            slot.complete(Ok(Err(Error::Io(error).into())));
+        }
+    }
+}
+
+/// Executes the submitted closures until all the queue handles are dropped.
+fn run_writer<T>(env: Env<T>, options: WriteQueueOptions, receiver: Receiver<Box<dyn Job>>) {
+    // The closure that didn't fit in the previous batch.
+    let mut carried = None;
+    loop {
+        let job = match carried.take().map_or_else(|| receiver.recv(), Ok) {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        let deadline = Instant::now().checked_add(options.max_batch_latency);
+        let mut bytes = job.bytes();
+        let mut batch = vec![job];
+        while batch.len() < options.max_batch_ops && bytes < options.max_batch_bytes {
+            let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            let next = match remaining {
+                Some(remaining) if remaining.is_zero() => receiver.try_recv().ok(),
+                Some(remaining) => receiver.recv_timeout(remaining).ok(),
+                None => receiver.recv().ok(),
+            };
+            let Some(job) = next else { break };
+            match bytes.checked_add(job.bytes()) {
+                Some(total) if total <= options.max_batch_bytes => {
+                    bytes = total;
+                    batch.push(job);
+                }
+                _ => {
+                    carried = Some(job);
+                    break;
+                }
+            }
+        }
+        run_batch(&env, batch);
+    }
+}
+
+/// Executes the closures in a single write transaction and sends their outputs.
+fn run_batch<T>(env: &Env<T>, mut batch: Vec<Box<dyn Job>>) {
+    let mut wtxn = match env.write_txn() {
+        Ok(wtxn) => wtxn,
+        Err(error) => return batch.into_iter().for_each(|job| job.complete(Some(&error))),
+    };
+
+    let mut errors = Vec::with_capacity(batch.len());
+    if let [job] = &mut batch[..] {
+        if !job.run(&mut wtxn) {
+            wtxn.abort();
+            return batch.into_iter().for_each(|job| job.complete(None));
+        }
+        errors.push(None);
+    } else {
+        // Every closure runs in a nested transaction so that its failure is isolated.
+        for job in &mut batch {
+            let error = match env.nested_write_txn(&mut wtxn) {
+                Ok(mut nested) => {
+                    if job.run(&mut nested) {
+                        nested.commit().err()
+                    } else {
+                        nested.abort();
+                        None
+                    }
+                }
+                Err(error) => Some(error),
+            };
+            errors.push(error);
+        }
+    }
+
+    match wtxn.commit() {
+        Ok(()) => {
+            batch.into_iter().zip(errors).for_each(|(job, error)| job.complete(error.as_ref()))
+        }
+        Err(error) => batch.into_iter().for_each(|job| job.complete(Some(&error))),
+    }
+}
+
+/// Duplicates an error to report it to all the closures of a transaction.
+fn share_error(error: &Error) -> Error {
+    match error {
+        Error::Mdb(error) => Error::Mdb(*error),
+        Error::Io(error) => Error::Io(io::Error::new(error.kind(), error.to_string())),
+        error => Error::Io(io::Error::other(error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+
+    use super::*;
+    use crate::types::Str;
+    use crate::{Database, EnvOpenOptions, MdbError};
+
+    #[test]
+    fn coalesced_writes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("words"))?;
+        wtxn.commit()?;
+
+        let queue = WriteQueueOptions::new().max_batch_ops(10).spawn(&env)?;
+
+        // The first closure blocks the writer thread until the others are queued.
+        let barrier = Arc::new(Barrier::new(2));
+        let first = queue.submit({
+            let barrier = barrier.clone();
+            move |wtxn| {
+                barrier.wait();
+                db.put(wtxn, "first", "1")?;
+                Result::Ok(wtxn.id())
+            }
+        });
+        barrier.wait();
+        let second = queue.submit(move |wtxn| {
+            db.put(wtxn, "second", "2")?;
+            Result::Ok(wtxn.id())
+        });
+        let failed = queue.submit(move |wtxn| {
+            db.put(wtxn, "failed", "3")?;
+            Result::<()>::Err(Error::Mdb(MdbError::Panic))
+        });
+        let third = queue.submit(move |wtxn| {
+            db.put(wtxn, "third", "4")?;
+            Result::Ok(wtxn.id())
+        });
+        let panicked = queue.submit(move |_| -> Result<()> { panic!("failed write") });
+
+        // The closures queued while the first one ran share a transaction.
+        let second = second.wait()?;
+        assert_ne!(first.wait()?, second);
+        assert!(matches!(failed.wait(), Err(Error::Mdb(MdbError::Panic))));
+        let third = third.wait()?;
+        assert_eq!(second, third);
+        let payload = catch_unwind(AssertUnwindSafe(|| panicked.wait())).unwrap_err();
+        assert_eq!(*payload.downcast::<&str>().unwrap(), "failed write");
+
+        // The writer thread survived the panic.
+        let last = queue.write(|wtxn| Result::Ok(wtxn.id()))?;
+        assert!(last > third);
+
+        let rtxn = env.read_txn()?;
+        let keys: Vec<_> = db.iter(&rtxn)?.map(|r| r.map(|(k, _)| k)).collect::<Result<_>>()?;
+        assert_eq!(keys, ["first", "second", "third"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn batching_policy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+
+        // The writer thread waits for the closures submitted right after the first one.
+        let queue = WriteQueueOptions::new()
+            .max_batch_ops(3)
+            .max_batch_latency(Duration::from_millis(200))
+            .spawn(&env)?;
+        let ids: Vec<_> = (0..4).map(|_| queue.submit(|wtxn| Result::Ok(wtxn.id()))).collect();
+        let ids: Vec<_> = ids.into_iter().map(PendingWrite::wait).collect::<Result<_>>()?;
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[1], ids[2]);
+        assert_ne!(ids[2], ids[3]);
+
+        let queue = WriteQueueOptions::new()
+            .max_batch_ops(10)
+            .max_batch_latency(Duration::from_millis(100))
+            .max_batch_bytes(15)
+            .spawn(&env)?;
+        let ids: Vec<_> = [5, 10, 10, 20, 1]
+            .into_iter()
+            .map(|bytes| queue.submit_sized(bytes, |wtxn| Result::Ok(wtxn.id())))
+            .collect();
+        let ids: Vec<_> = ids.into_iter().map(PendingWrite::wait).collect::<Result<_>>()?;
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[1], ids[2]);
+        assert_ne!(ids[2], ids[3]);
+        assert_ne!(ids[3], ids[4]);
+
+        Ok(())
+    }
+}
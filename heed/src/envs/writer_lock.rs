@@ -0,0 +1,182 @@
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Env, Error, Result, RwTxn};
+
+/// Serializes the write transactions of an environment in this process.
+///
+/// LMDB waits on its writer mutex without a timeout and without telling who holds it, the
+/// write transactions of this process wait on this lock first so that they can give up.
+pub(crate) struct WriterLock {
+    holder: Mutex<Option<Holder>>,
+    released: Condvar,
+}
+
+struct Holder {
+    thread: Option<String>,
+    since: Instant,
+}
+
+impl WriterLock {
+    pub(crate) fn new() -> WriterLock {
+        WriterLock { holder: Mutex::new(None), released: Condvar::new() }
+    }
+
+    /// Waits for the lock until the deadline, returns the time spent waiting.
+    pub(crate) fn acquire(&self, deadline: Option<Instant>) -> Result<Duration> {
+        let start = Instant::now();
+        let mut holder = self.holder.lock().unwrap();
+        while let Some(current) = holder.as_ref() {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(Error::WriteTxnTimeout {
+                            waited: now - start,
+                            holder: current.describe(),
+                        });
+                    }
+                    holder = self.released.wait_timeout(holder, deadline - now).unwrap().0;
+                }
+                None => holder = self.released.wait(holder).unwrap(),
+            }
+        }
+
+        let thread = thread::current().name().map(str::to_owned);
+        *holder = Some(Holder { thread, since: Instant::now() });
+        Ok(start.elapsed())
+    }
+
+    /// Releases the lock, called once the write transaction is committed or aborted.
+    pub(crate) fn release(&self) {
+        *self.holder.lock().unwrap() = None;
+        self.released.notify_one();
+    }
+
+    fn holder(&self) -> Option<WriterHolder> {
+        self.holder.lock().unwrap().as_ref().map(Holder::describe)
+    }
+}
+
+impl Holder {
+    fn describe(&self) -> WriterHolder {
+        WriterHolder {
+            pid: std::process::id(),
+            thread: self.thread.clone(),
+            held_for: self.since.elapsed(),
+        }
+    }
+}
+
+/// The write transaction holding the writer lock of an environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriterHolder {
+    /// The identifier of the process running the transaction.
+    pub pid: u32,
+    /// The name of the thread that began the transaction, if it has one.
+    pub thread: Option<String>,
+    /// The time elapsed since the transaction began.
+    pub held_for: Duration,
+}
+
+impl<T> Env<T> {
+    /// Create a transaction with read and write access, waiting at most `timeout`
+    /// for the write transaction in progress in this process.
+    ///
+    /// The time the transaction waited is given by [`RwTxn::lock_wait`]. The timeout only
+    /// applies to the write transactions of this process, LMDB waits for the ones of other
+    /// processes on a mutex of the lock file that can't time out.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use std::time::Duration;
+    /// use heed::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let wtxn = env.write_txn()?;
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| {
+    ///         let result = env.write_txn_timeout(Duration::from_millis(10));
+    ///         let Err(Error::WriteTxnTimeout { holder, .. }) = result else {
+    ///             panic!("expected a timeout");
+    ///         };
+    ///         assert_eq!(holder.pid, std::process::id());
+    ///     });
+    /// });
+    /// wtxn.commit()?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::WriteTxnTimeout`] if the write transaction in progress in this
+    /// process isn't done before the timeout, otherwise the same as [`Env::write_txn`].
+    pub fn write_txn_timeout(&self, timeout: Duration) -> Result<RwTxn<'_>> {
+        RwTxn::new(self, Some(Instant::now() + timeout))
+    }
+
+    /// Returns the write transaction of this process holding the writer lock, if any.
+    pub fn writer_holder(&self) -> Option<WriterHolder> {
+        self.inner.writer_lock.holder()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{EnvOpenOptions, Error, Result};
+
+    #[test]
+    fn write_txn_timeout() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+        assert_eq!(env.writer_holder(), None);
+
+        let (began, begin) = mpsc::channel();
+        let (release, released) = mpsc::channel::<()>();
+        let holding = thread::Builder::new().name("holder".into()).spawn({
+            let env = env.clone();
+            move || -> Result<()> {
+                let wtxn = env.write_txn()?;
+                began.send(()).unwrap();
+                released.recv().unwrap();
+                wtxn.commit()
+            }
+        })?;
+        begin.recv().unwrap();
+
+        let result = env.write_txn_timeout(Duration::from_millis(20));
+        let Err(Error::WriteTxnTimeout { waited, holder }) = result else {
+            panic!("expected a timeout");
+        };
+        assert!(waited >= Duration::from_millis(20));
+        assert_eq!(holder.thread.as_deref(), Some("holder"));
+        assert_eq!(env.writer_holder().map(|h| h.pid), Some(holder.pid));
+
+        let waiter = thread::spawn({
+            let env = env.clone();
+            move || -> Result<Duration> {
+                let wtxn = env.write_txn_timeout(Duration::from_secs(10))?;
+                Ok(wtxn.lock_wait())
+            }
+        });
+        thread::sleep(Duration::from_millis(20));
+        release.send(()).unwrap();
+        holding.join().unwrap()?;
+        assert!(waiter.join().unwrap()? >= Duration::from_millis(20));
+
+        // The lock is released when the transaction is dropped.
+        assert_eq!(env.writer_holder(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use std::marker;
+
+use crate::cursor::MoveOperation;
+use crate::iteration_method::MoveOnCurrentKeyDuplicates;
+use crate::types::DecodeIgnore;
+use crate::*;
+
+/// A read-only iterator over the keys of a `DUP_SORT` database, with the values of each key.
+///
+/// The iterator moves between the keys with `MDB_NEXT_NODUP` and the values of a key are
+/// read with `MDB_NEXT_DUP` by another cursor, the values of a key can be kept and read after
+/// the iterator moved on the next keys.
+pub struct RoGroups<'txn, KC, DC> {
+    cursor: RoCursor<'txn>,
+    move_on_first: bool,
+    _phantom: marker::PhantomData<(KC, DC)>,
+}
+
+impl<'txn, KC, DC> RoGroups<'txn, KC, DC> {
+    pub(crate) fn new(cursor: RoCursor<'txn>) -> RoGroups<'txn, KC, DC> {
+        RoGroups { cursor, move_on_first: true, _phantom: marker::PhantomData }
+    }
+
+    /// Change the codec types of this iterator, specifying the codecs.
+    pub fn remap_types<KC2, DC2>(self) -> RoGroups<'txn, KC2, DC2> {
+        RoGroups {
+            cursor: self.cursor,
+            move_on_first: self.move_on_first,
+            _phantom: marker::PhantomData,
+        }
+    }
+
+    /// Change the key codec type of this iterator, specifying the new codec.
+    pub fn remap_key_type<KC2>(self) -> RoGroups<'txn, KC2, DC> {
+        self.remap_types::<KC2, DC>()
+    }
+
+    /// Change the data codec type of this iterator, specifying the new codec.
+    pub fn remap_data_type<DC2>(self) -> RoGroups<'txn, KC, DC2> {
+        self.remap_types::<KC, DC2>()
+    }
+
+    /// Returns an iterator over the values of the key the cursor is on.
+    fn values_of(&self, key: &[u8]) -> Result<DupValues<'txn, DC>> {
+        let mut cursor = self.cursor.sibling()?;
+        let found = cursor.move_on_key(key)?;
+        let iter = RoIter::<DecodeIgnore, DC, MoveOnCurrentKeyDuplicates>::new(cursor);
+        Ok(DupValues::new(found.then_some(iter)))
+    }
+}
+
+impl<'txn, KC, DC> Iterator for RoGroups<'txn, KC, DC>
+where
+    KC: BytesDecode<'txn>,
+{
+    type Item = Result<(KC::DItem, DupValues<'txn, DC>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = if self.move_on_first {
+            self.move_on_first = false;
+            self.cursor.move_on_first(MoveOperation::NoDup)
+        } else {
+            self.cursor.move_on_next(MoveOperation::NoDup)
+        };
+
+        let key = match result {
+            Ok(Some((key, _))) => key,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let values = match self.values_of(key) {
+            Ok(values) => values,
+            Err(e) => return Some(Err(e)),
+        };
+
+        match KC::bytes_decode(key) {
+            Ok(key) => Some(Ok((key, values))),
+            Err(e) => Some(Err(Error::Decoding(e))),
+        }
+    }
+}
+
+impl<KC, DC> fmt::Debug for RoGroups<'_, KC, DC> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoGroups").finish()
+    }
+}
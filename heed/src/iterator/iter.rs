@@ -17,6 +17,11 @@ impl<'txn, KC, DC, IM> RoIter<'txn, KC, DC, IM> {
         RoIter { cursor, move_on_first: true, _phantom: marker::PhantomData }
     }
 
+    /// Creates an iterator that starts from the entry following the one the cursor is on.
+    pub(crate) fn new_after_current(cursor: RoCursor<'txn>) -> RoIter<'txn, KC, DC, IM> {
+        RoIter { cursor, move_on_first: false, _phantom: marker::PhantomData }
+    }
+
     /// Move on the first value of keys, ignoring duplicate values.
     ///
     /// ```
@@ -126,6 +131,54 @@ impl<'txn, KC, DC, IM> RoIter<'txn, KC, DC, IM> {
         }
     }
 
+    /// Reads in advance the given number of pages of the memory map following the entries
+    /// of the iteration, before they are decoded.
+    ///
+    /// The operating system is advised to read the next batch of pages as soon as the
+    /// iteration leaves the pages advised before, which hides the latency of the page faults
+    /// of the cold scans of the large databases stored on slow or networked storage. The
+    /// pages written in order are mostly contiguous, the advice of the scans of fragmented
+    /// databases reads pages that are not used. It is only available on Unix.
+    ///
+    /// See also [`Env::advise`] to advise the whole map.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::byteorder::BigEndian;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// type BEU32 = U32<BigEndian>;
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<BEU32, Bytes>(&mut wtxn, Some("prefetch"))?;
+    /// for i in 0..1000 {
+    ///     db.put(&mut wtxn, &i, &[0; 100])?;
+    /// }
+    /// wtxn.commit()?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// let mut count = 0;
+    /// for result in db.iter(&rtxn)?.prefetch(16) {
+    ///     let (key, value) = result?;
+    ///     assert_eq!((key, value.len()), (count, 100));
+    ///     count += 1;
+    /// }
+    /// assert_eq!(count, 1000);
+    /// # Ok(()) }
+    /// ```
+    #[cfg(unix)]
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.cursor.prefetch(pages, false);
+        self
+    }
+
     /// Change the codec types of this iterator, specifying the codecs.
     pub fn remap_types<KC2, DC2>(self) -> RoIter<'txn, KC2, DC2, IM> {
         RoIter {
@@ -242,6 +295,16 @@ impl<'txn, KC, DC, IM> RoRevIter<'txn, KC, DC, IM> {
         }
     }
 
+    /// Reads in advance the given number of pages of the memory map following the entries
+    /// of the iteration, before they are decoded.
+    ///
+    /// For more info, see [`RoIter::prefetch`].
+    #[cfg(unix)]
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.cursor.prefetch(pages, true);
+        self
+    }
+
     /// Change the codec types of this iterator, specifying the codecs.
     pub fn remap_types<KC2, DC2>(self) -> RoRevIter<'txn, KC2, DC2, IM> {
         RoRevIter {
@@ -1,8 +1,11 @@
+mod groups;
 mod iter;
 mod prefix;
 mod range;
 
+pub use self::groups::RoGroups;
 pub use self::iter::{RoIter, RoRevIter};
+pub(crate) use self::prefix::starts_with;
 pub use self::prefix::{RoPrefix, RoRevPrefix};
 pub use self::range::{RoRange, RoRevRange};
 
@@ -212,9 +215,11 @@ mod tests {
 
     #[test]
     fn range_iter_last() {
+        use std::ops::Bound;
+
         use crate::byteorder::BigEndian;
         use crate::types::*;
-        use crate::EnvOpenOptions;
+        use crate::{EnvOpenOptions, Error};
 
         let dir = tempfile::tempdir().unwrap();
         let env = unsafe {
@@ -283,8 +288,12 @@ mod tests {
         let iter = db.range(&wtxn, &range).unwrap();
         assert_eq!(iter.last().transpose().unwrap(), None);
 
+        // The empty ranges are valid but the inverted ones are not.
         #[allow(clippy::reversed_empty_ranges)]
         let range = 2..=1;
+        assert!(matches!(db.range(&wtxn, &range), Err(Error::InvalidRange)));
+        assert!(matches!(db.rev_range(&wtxn, &range), Err(Error::InvalidRange)));
+        let range = (Bound::Excluded(2), Bound::Included(2));
         let iter = db.range(&wtxn, &range).unwrap();
         assert_eq!(iter.last().transpose().unwrap(), None);
 
@@ -509,6 +518,69 @@ mod tests {
         wtxn.abort();
     }
 
+    #[test]
+    fn rev_prefix_iter_with_duplicates() {
+        use crate::byteorder::BigEndian;
+        use crate::types::*;
+        use crate::{DatabaseFlags, EnvOpenOptions};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(10 * 1024 * 1024) // 10MB
+                .max_dbs(3000)
+                .open(dir.path())
+                .unwrap()
+        };
+
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env
+            .database_options()
+            .types::<Str, U32<BigEndian>>()
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)
+            .unwrap();
+
+        for (key, count) in [("a", 2), ("ab", 3), ("abc", 2), ("b", 2)] {
+            for value in 1..=count {
+                db.put(&mut wtxn, key, &value).unwrap();
+            }
+        }
+
+        // The last key first, with its last value first.
+        let iter = db.rev_prefix_iter(&wtxn, "ab").unwrap();
+        let entries: Vec<_> = iter.map(Result::unwrap).collect();
+        assert_eq!(entries, [("abc", 2), ("abc", 1), ("ab", 3), ("ab", 2), ("ab", 1)]);
+        let iter = db.rev_prefix_iter(&wtxn, "ab").unwrap().move_between_keys();
+        let entries: Vec<_> = iter.map(Result::unwrap).collect();
+        assert_eq!(entries, [("abc", 2), ("ab", 3)]);
+
+        // The last entry is the first value of the first key, or its last value between keys.
+        let iter = db.rev_prefix_iter(&wtxn, "ab").unwrap();
+        assert_eq!(iter.last().transpose().unwrap(), Some(("ab", 1)));
+        let iter = db.rev_prefix_iter(&wtxn, "ab").unwrap().move_between_keys();
+        assert_eq!(iter.last().transpose().unwrap(), Some(("ab", 3)));
+        let iter = db.rev_prefix_iter(&wtxn, "b").unwrap().move_between_keys();
+        assert_eq!(iter.last().transpose().unwrap(), Some(("b", 2)));
+
+        // The iterator on a value of the first key isn't past its other values.
+        let mut iter = db.rev_prefix_iter(&wtxn, "ab").unwrap();
+        assert_eq!(iter.nth(3).transpose().unwrap(), Some(("ab", 2)));
+        assert_eq!(iter.last().transpose().unwrap(), Some(("ab", 1)));
+        let mut iter = db.rev_prefix_iter(&wtxn, "ab").unwrap();
+        assert_eq!(iter.nth(4).transpose().unwrap(), Some(("ab", 1)));
+        assert_eq!(iter.last().transpose().unwrap(), None);
+
+        let mut iter = db.rev_prefix_iter(&wtxn, "ab").unwrap().move_between_keys();
+        assert_eq!(iter.next().transpose().unwrap(), Some(("abc", 2)));
+        assert_eq!(iter.last().transpose().unwrap(), Some(("ab", 3)));
+        let mut iter = db.rev_prefix_iter(&wtxn, "ab").unwrap().move_between_keys();
+        assert_eq!(iter.nth(1).transpose().unwrap(), Some(("ab", 3)));
+        assert_eq!(iter.last().transpose().unwrap(), None);
+
+        wtxn.abort();
+    }
+
     #[test]
     fn rev_prefix_iter_last_with_byte_255() {
         use crate::types::*;
@@ -683,4 +755,150 @@ mod tests {
 
         wtxn.abort();
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn prefetch_keeps_the_entries() {
+        use crate::byteorder::BigEndian;
+        use crate::types::*;
+        use crate::EnvOpenOptions;
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(10 * 1024 * 1024) // 10MB
+                .max_dbs(3000)
+                .open(dir.path())
+                .unwrap()
+        };
+
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env.create_database::<U32<BigEndian>, Bytes>(&mut wtxn, None).unwrap();
+        for i in 0..2000 {
+            db.put(&mut wtxn, &i, &[i as u8; 300]).unwrap();
+        }
+        wtxn.commit().unwrap();
+
+        // Prefetching more and less pages than a window of entries changes nothing to them.
+        let rtxn = env.read_txn().unwrap();
+        for pages in [0, 1, 64] {
+            let entries: Vec<_> =
+                db.iter(&rtxn).unwrap().prefetch(pages).map(Result::unwrap).collect();
+            let expected: Vec<_> = db.iter(&rtxn).unwrap().map(Result::unwrap).collect();
+            assert_eq!(entries, expected);
+            let entries: Vec<_> =
+                db.rev_iter(&rtxn).unwrap().prefetch(pages).map(Result::unwrap).collect();
+            let expected: Vec<_> = db.rev_iter(&rtxn).unwrap().map(Result::unwrap).collect();
+            assert_eq!(entries, expected);
+            let range = db.range(&rtxn, &(500..1500)).unwrap().prefetch(pages);
+            assert_eq!(range.map(Result::unwrap).count(), 1000);
+            let range = db.rev_range(&rtxn, &(500..)).unwrap().prefetch(pages);
+            assert_eq!(range.map(Result::unwrap).count(), 1500);
+        }
+    }
+
+    #[test]
+    fn dup_fixed_values_read_by_page() {
+        use crate::byteorder::BigEndian;
+        use crate::types::*;
+        use crate::{DatabaseFlags, EnvOpenOptions};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(10 * 1024 * 1024) // 10MB
+                .max_dbs(3000)
+                .open(dir.path())
+                .unwrap()
+        };
+
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env
+            .database_options()
+            .types::<U32<BigEndian>, U32<BigEndian>>()
+            .flags(DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED)
+            .create(&mut wtxn)
+            .unwrap();
+
+        // Keys with a single value, a few values and many pages of values.
+        let mut expected = Vec::new();
+        for (key, count) in [(0, 1), (1, 3), (2, 5000), (3, 1), (4, 2000)] {
+            for value in 0..count {
+                db.put(&mut wtxn, &key, &value).unwrap();
+                expected.push((key, value));
+            }
+        }
+        wtxn.commit().unwrap();
+
+        let rtxn = env.read_txn().unwrap();
+        let entries: Vec<_> = db.iter(&rtxn).unwrap().map(Result::unwrap).collect();
+        assert_eq!(entries, expected);
+        let entries: Vec<_> = db.range(&rtxn, &(1..=2)).unwrap().map(Result::unwrap).collect();
+        assert_eq!(entries, expected[1..5004]);
+        let values: Vec<_> =
+            db.get_duplicates(&rtxn, &4).unwrap().unwrap().map(|e| e.unwrap().1).collect();
+        assert_eq!(values, (0..2000).collect::<Vec<_>>());
+
+        // Moving the cursor in the middle of a page of values starts from the current value.
+        let mut iter = db.iter(&rtxn).unwrap();
+        assert_eq!(iter.nth(100).transpose().unwrap(), Some((2, 96)));
+        let mut iter = iter.move_between_keys();
+        assert_eq!(iter.next().transpose().unwrap(), Some((3, 0)));
+        let mut iter = iter.move_through_duplicate_values();
+        assert_eq!(iter.nth(1000).transpose().unwrap(), Some((4, 1000)));
+        assert_eq!(iter.count(), 999);
+    }
+
+    #[test]
+    fn groups_of_duplicates() {
+        use crate::byteorder::BigEndian;
+        use crate::types::*;
+        use crate::{Database, DatabaseFlags, EnvOpenOptions, Error, MdbError};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(10 * 1024 * 1024) // 10MB
+                .max_dbs(3000)
+                .open(dir.path())
+                .unwrap()
+        };
+
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env
+            .database_options()
+            .types::<U32<BigEndian>, U32<BigEndian>>()
+            .name("dup-fixed")
+            .flags(DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED)
+            .create(&mut wtxn)
+            .unwrap();
+        let plain: Database<U32<BigEndian>, U32<BigEndian>> =
+            env.create_database(&mut wtxn, Some("plain")).unwrap();
+
+        for (key, count) in [(0, 1), (1, 3000), (2, 2)] {
+            for value in 0..count {
+                db.put(&mut wtxn, &key, &value).unwrap();
+            }
+        }
+        plain.put(&mut wtxn, &0, &0).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = env.read_txn().unwrap();
+        let groups: Vec<_> = db.iter_groups(&rtxn).unwrap().map(Result::unwrap).collect();
+        assert_eq!(groups.iter().map(|(key, _)| *key).collect::<Vec<_>>(), [0, 1, 2]);
+
+        // The values of a key are read after the iterator moved on the next keys.
+        let counts: Vec<_> = groups.into_iter().map(|(_, values)| values.count()).collect();
+        assert_eq!(counts, [1, 3000, 2]);
+
+        let mut groups = db.iter_groups(&rtxn).unwrap();
+        let (_, mut values) = groups.nth(1).unwrap().unwrap();
+        assert_eq!(values.nth(2000).transpose().unwrap(), Some(2000));
+        let (key, values) = groups.next().unwrap().unwrap();
+        assert_eq!((key, values.map(Result::unwrap).collect::<Vec<_>>()), (2, vec![0, 1]));
+        assert!(groups.next().is_none());
+
+        let result = plain.iter_groups(&rtxn);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::Incompatible))));
+    }
 }
@@ -4,6 +4,7 @@ use heed_traits::LexicographicComparator;
 use types::LazyDecode;
 
 use crate::cursor::MoveOperation;
+use crate::databases::key_buffer::KeyBuffer;
 use crate::envs::DefaultComparator;
 use crate::iteration_method::{IterationMethod, MoveBetweenKeys, MoveThroughDuplicateValues};
 use crate::*;
@@ -46,6 +47,12 @@ fn retreat_prefix<C: LexicographicComparator>(bytes: &mut [u8]) -> bool {
     true
 }
 
+/// Returns whether `key` starts with `prefix` as defined by the `C` comparator, the bytes
+/// of the key can differ from the ones of the prefix if the comparator considers them equal.
+pub(crate) fn starts_with<C: LexicographicComparator>(key: &[u8], prefix: &[u8]) -> bool {
+    key.get(..prefix.len()).is_some_and(|start| C::compare(start, prefix).is_eq())
+}
+
 fn move_on_prefix_end<'txn, C: LexicographicComparator>(
     cursor: &mut RoCursor<'txn>,
     prefix: &mut [u8],
@@ -62,16 +69,35 @@ fn move_on_prefix_end<'txn, C: LexicographicComparator>(
     }
 }
 
+/// Moves the cursor on the last entry of a reverse iteration over the prefix, the first value
+/// of the first key, or its last value if the iteration moves between the keys, as the
+/// reverse iteration reads the last value of each key.
+fn move_on_prefix_start<'txn>(
+    cursor: &mut RoCursor<'txn>,
+    prefix: &[u8],
+    op: MoveOperation,
+) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+    let first = cursor.move_on_key_greater_than_or_equal_to(prefix)?;
+    match (first, op) {
+        (Some(_), MoveOperation::NoDup) => match cursor.move_on_next(MoveOperation::NoDup)? {
+            Some(_) => cursor.move_on_prev(MoveOperation::Any),
+            // The first key is the last key of the database.
+            None => cursor.move_on_last(MoveOperation::Any),
+        },
+        (first, _) => Ok(first),
+    }
+}
+
 /// A read-only prefix iterator structure.
 pub struct RoPrefix<'txn, KC, DC, C = DefaultComparator, IM = MoveThroughDuplicateValues> {
     cursor: RoCursor<'txn>,
-    prefix: Vec<u8>,
+    prefix: KeyBuffer,
     move_on_first: bool,
     _phantom: marker::PhantomData<(KC, DC, C, IM)>,
 }
 
 impl<'txn, KC, DC, C, IM> RoPrefix<'txn, KC, DC, C, IM> {
-    pub(crate) fn new(cursor: RoCursor<'txn>, prefix: Vec<u8>) -> RoPrefix<'txn, KC, DC, C, IM> {
+    pub(crate) fn new(cursor: RoCursor<'txn>, prefix: KeyBuffer) -> RoPrefix<'txn, KC, DC, C, IM> {
         RoPrefix { cursor, prefix, move_on_first: true, _phantom: marker::PhantomData }
     }
 
@@ -101,6 +127,16 @@ impl<'txn, KC, DC, C, IM> RoPrefix<'txn, KC, DC, C, IM> {
         }
     }
 
+    /// Reads in advance the given number of pages of the memory map following the entries
+    /// of the iteration, before they are decoded.
+    ///
+    /// For more info, see [`RoIter::prefetch`].
+    #[cfg(unix)]
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.cursor.prefetch(pages, false);
+        self
+    }
+
     /// Change the codec types of this iterator, specifying the codecs.
     pub fn remap_types<KC2, DC2>(self) -> RoPrefix<'txn, KC2, DC2, C, IM> {
         RoPrefix {
@@ -146,7 +182,7 @@ where
 
         match result {
             Ok(Some((key, data))) => {
-                if key.starts_with(&self.prefix) {
+                if starts_with::<C>(key, &self.prefix) {
                     match (KC::bytes_decode(key), DC::bytes_decode(data)) {
                         (Ok(key), Ok(data)) => Some(Ok((key, data))),
                         (Err(e), _) | (_, Err(e)) => Some(Err(Error::Decoding(e))),
@@ -178,7 +214,7 @@ where
 
         match result {
             Ok(Some((key, data))) => {
-                if key.starts_with(&self.prefix) {
+                if starts_with::<C>(key, &self.prefix) {
                     match (KC::bytes_decode(key), DC::bytes_decode(data)) {
                         (Ok(key), Ok(data)) => Some(Ok((key, data))),
                         (Err(e), _) | (_, Err(e)) => Some(Err(Error::Decoding(e))),
@@ -202,17 +238,20 @@ impl<KC, DC, C, IM> fmt::Debug for RoPrefix<'_, KC, DC, C, IM> {
 /// A reverse read-only prefix iterator structure.
 pub struct RoRevPrefix<'txn, KC, DC, C = DefaultComparator, IM = MoveThroughDuplicateValues> {
     cursor: RoCursor<'txn>,
-    prefix: Vec<u8>,
+    prefix: KeyBuffer,
     move_on_last: bool,
     _phantom: marker::PhantomData<(KC, DC, C, IM)>,
 }
 
 impl<'txn, KC, DC, C, IM> RoRevPrefix<'txn, KC, DC, C, IM> {
-    pub(crate) fn new(cursor: RoCursor<'txn>, prefix: Vec<u8>) -> RoRevPrefix<'txn, KC, DC, C, IM> {
+    pub(crate) fn new(
+        cursor: RoCursor<'txn>,
+        prefix: KeyBuffer,
+    ) -> RoRevPrefix<'txn, KC, DC, C, IM> {
         RoRevPrefix { cursor, prefix, move_on_last: true, _phantom: marker::PhantomData }
     }
 
-    /// Move on the first value of keys, ignoring duplicate values.
+    /// Move on the last value of keys, ignoring duplicate values.
     ///
     /// For more info, see [`RoIter::move_between_keys`].
     pub fn move_between_keys(self) -> RoRevPrefix<'txn, KC, DC, C, MoveBetweenKeys> {
@@ -238,6 +277,16 @@ impl<'txn, KC, DC, C, IM> RoRevPrefix<'txn, KC, DC, C, IM> {
         }
     }
 
+    /// Reads in advance the given number of pages of the memory map following the entries
+    /// of the iteration, before they are decoded.
+    ///
+    /// For more info, see [`RoIter::prefetch`].
+    #[cfg(unix)]
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.cursor.prefetch(pages, true);
+        self
+    }
+
     /// Change the codec types of this iterator, specifying the codecs.
     pub fn remap_types<KC2, DC2>(self) -> RoRevPrefix<'txn, KC2, DC2, C, IM> {
         RoRevPrefix {
@@ -283,7 +332,7 @@ where
 
         match result {
             Ok(Some((key, data))) => {
-                if key.starts_with(&self.prefix) {
+                if starts_with::<C>(key, &self.prefix) {
                     match (KC::bytes_decode(key), DC::bytes_decode(data)) {
                         (Ok(key), Ok(data)) => Some(Ok((key, data))),
                         (Err(e), _) | (_, Err(e)) => Some(Err(Error::Decoding(e))),
@@ -299,13 +348,17 @@ where
 
     fn last(mut self) -> Option<Self::Item> {
         let result = if self.move_on_last {
-            self.cursor.move_on_key_greater_than_or_equal_to(&self.prefix)
+            move_on_prefix_start(&mut self.cursor, &self.prefix, IM::MOVE_OPERATION)
         } else {
             let current = self.cursor.current();
-            let start = self.cursor.move_on_key_greater_than_or_equal_to(&self.prefix);
+            let start = move_on_prefix_start(&mut self.cursor, &self.prefix, IM::MOVE_OPERATION);
             match (current, start) {
-                (Ok(Some((ckey, _))), Ok(Some((key, data)))) if ckey != key => {
-                    Ok(Some((key, data)))
+                // The duplicates of the first key are compared too, the iterator can be on a
+                // value of the first key that is not the last one of the iteration.
+                (Ok(Some(current)), Ok(Some(start)))
+                    if starts_with::<C>(current.0, &self.prefix) && current != start =>
+                {
+                    Ok(Some(start))
                 }
                 (Ok(_), Ok(_)) => Ok(None),
                 (Err(e), _) | (_, Err(e)) => Err(e),
@@ -314,7 +367,7 @@ where
 
         match result {
             Ok(Some((key, data))) => {
-                if key.starts_with(&self.prefix) {
+                if starts_with::<C>(key, &self.prefix) {
                     match (KC::bytes_decode(key), DC::bytes_decode(data)) {
                         (Ok(key), Ok(data)) => Some(Ok((key, data))),
                         (Err(e), _) | (_, Err(e)) => Some(Err(Error::Decoding(e))),
@@ -4,12 +4,13 @@ use std::ops::Bound;
 use types::LazyDecode;
 
 use crate::cursor::MoveOperation;
+use crate::databases::key_buffer::KeyBuffer;
 use crate::iteration_method::{IterationMethod, MoveBetweenKeys, MoveThroughDuplicateValues};
 use crate::*;
 
 fn move_on_range_end<'txn>(
     cursor: &mut RoCursor<'txn>,
-    end_bound: &Bound<Vec<u8>>,
+    end_bound: &Bound<KeyBuffer>,
 ) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
     match end_bound {
         Bound::Included(end) => match cursor.move_on_key_greater_than_or_equal_to(end) {
@@ -26,12 +27,12 @@ fn move_on_range_end<'txn>(
 
 fn move_on_range_start<'txn>(
     cursor: &mut RoCursor<'txn>,
-    start_bound: &mut Bound<Vec<u8>>,
+    start_bound: &mut Bound<KeyBuffer>,
 ) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
     match start_bound {
         Bound::Included(start) => cursor.move_on_key_greater_than_or_equal_to(start),
         Bound::Excluded(start) => match cursor.move_on_key_greater_than_or_equal_to(start)? {
-            Some((key, _)) if key == start => cursor.move_on_next(MoveOperation::NoDup),
+            Some((key, _)) if key == &start[..] => cursor.move_on_next(MoveOperation::NoDup),
             result => Ok(result),
         },
         Bound::Unbounded => cursor.move_on_first(MoveOperation::NoDup),
@@ -42,16 +43,16 @@ fn move_on_range_start<'txn>(
 pub struct RoRange<'txn, KC, DC, C = DefaultComparator, IM = MoveThroughDuplicateValues> {
     cursor: RoCursor<'txn>,
     move_on_start: bool,
-    start_bound: Bound<Vec<u8>>,
-    end_bound: Bound<Vec<u8>>,
+    start_bound: Bound<KeyBuffer>,
+    end_bound: Bound<KeyBuffer>,
     _phantom: marker::PhantomData<(KC, DC, C, IM)>,
 }
 
 impl<'txn, KC, DC, C, IM> RoRange<'txn, KC, DC, C, IM> {
     pub(crate) fn new(
         cursor: RoCursor<'txn>,
-        start_bound: Bound<Vec<u8>>,
-        end_bound: Bound<Vec<u8>>,
+        start_bound: Bound<KeyBuffer>,
+        end_bound: Bound<KeyBuffer>,
     ) -> RoRange<'txn, KC, DC, C, IM> {
         RoRange {
             cursor,
@@ -90,6 +91,16 @@ impl<'txn, KC, DC, C, IM> RoRange<'txn, KC, DC, C, IM> {
         }
     }
 
+    /// Reads in advance the given number of pages of the memory map following the entries
+    /// of the iteration, before they are decoded.
+    ///
+    /// For more info, see [`RoIter::prefetch`].
+    #[cfg(unix)]
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.cursor.prefetch(pages, false);
+        self
+    }
+
     /// Change the codec types of this iterator, specifying the codecs.
     pub fn remap_types<KC2, DC2>(self) -> RoRange<'txn, KC2, DC2, C, IM> {
         RoRange {
@@ -202,16 +213,16 @@ impl<KC, DC, C, IM> fmt::Debug for RoRange<'_, KC, DC, C, IM> {
 pub struct RoRevRange<'txn, KC, DC, C = DefaultComparator, IM = MoveThroughDuplicateValues> {
     cursor: RoCursor<'txn>,
     move_on_end: bool,
-    start_bound: Bound<Vec<u8>>,
-    end_bound: Bound<Vec<u8>>,
+    start_bound: Bound<KeyBuffer>,
+    end_bound: Bound<KeyBuffer>,
     _phantom: marker::PhantomData<(KC, DC, C, IM)>,
 }
 
 impl<'txn, KC, DC, C, IM> RoRevRange<'txn, KC, DC, C, IM> {
     pub(crate) fn new(
         cursor: RoCursor<'txn>,
-        start_bound: Bound<Vec<u8>>,
-        end_bound: Bound<Vec<u8>>,
+        start_bound: Bound<KeyBuffer>,
+        end_bound: Bound<KeyBuffer>,
     ) -> RoRevRange<'txn, KC, DC, C, IM> {
         RoRevRange {
             cursor,
@@ -250,6 +261,16 @@ impl<'txn, KC, DC, C, IM> RoRevRange<'txn, KC, DC, C, IM> {
         }
     }
 
+    /// Reads in advance the given number of pages of the memory map following the entries
+    /// of the iteration, before they are decoded.
+    ///
+    /// For more info, see [`RoIter::prefetch`].
+    #[cfg(unix)]
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.cursor.prefetch(pages, true);
+        self
+    }
+
     /// Change the codec types of this iterator, specifying the codecs.
     pub fn remap_types<KC2, DC2>(self) -> RoRevRange<'txn, KC2, DC2, C, IM> {
         RoRevRange {
@@ -69,44 +69,84 @@
 //! ```
 #![warn(missing_docs)]
 
+pub mod analyze;
+pub mod changes;
 pub mod cookbook;
 mod cursor;
 mod databases;
+pub mod debug;
 mod envs;
 pub mod iteration_method;
 mod iterator;
 mod mdb;
+pub mod meta;
+pub mod multi_env;
+mod observer;
+mod paranoid;
+#[cfg(unix)]
+mod prefetch;
 mod reserved_space;
+mod staged;
+pub mod structures;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+pub mod tools;
 mod txn;
 #[cfg(test)]
 mod txn_split_safety_tests;
+mod typed_env;
+pub mod verify;
 
 use std::ffi::CStr;
+use std::time::Duration;
 use std::{error, fmt, io, mem, result};
 
+#[cfg(feature = "derive")]
+pub use heed_derive::{BytesDecode, BytesEncode, DatabaseSchema};
 use heed_traits as traits;
+#[cfg(feature = "collation")]
+pub use icu_collator;
 pub use {byteorder, heed_types as types};
 
 use self::cursor::{RoCursor, RwCursor};
-pub use self::databases::{Database, DatabaseOpenOptions, DatabaseStat};
+pub use self::databases::{
+    BlobDatabase, BlobReader, BlobWriter, ByIndex, CountEstimate, Database, DatabaseAny,
+    DatabaseOpenOptions, DatabaseSchema, DatabaseStat, DupValues, IndexedDatabase, MultiDatabase,
+    Namespace, NamespaceIter, SecondaryIndex, ShadowDatabase, ShadowDivergence,
+};
 #[cfg(master3)]
 pub use self::databases::{EncryptedDatabase, EncryptedDatabaseOpenOptions};
 #[cfg(master3)]
 pub use self::envs::EncryptedEnv;
+#[cfg(feature = "track-read-txns")]
+pub use self::envs::LiveReader;
 pub use self::envs::{
-    env_closing_event, CompactionOption, DefaultComparator, Env, EnvClosingEvent, EnvInfo,
-    EnvOpenOptions, FlagSetMode, IntegerComparator,
+    env_closing_event, AcceptCorruption, AcceptDataLoss, AcceptNoLocking, Advice,
+    AsciiCaseInsensitive, CachedRoTxn, CommitWatch, CompactionOption, DefaultComparator, Env,
+    EnvClosingEvent, EnvEvent, EnvInfo, EnvOpenOptions, FlagSetMode, IntegerComparator, IntegerKey,
+    PendingWrite, ReverseComparator, SlowTxn, SlowTxnState, WaitPast, WriteQueue,
+    WriteQueueOptions, WriterHolder,
 };
+#[cfg(feature = "collation")]
+pub use self::envs::{Collation, CollationComparator, RootCollation};
 pub use self::iterator::{
-    RoIter, RoPrefix, RoRange, RoRevIter, RoRevPrefix, RoRevRange,
+    RoGroups, RoIter, RoPrefix, RoRange, RoRevIter, RoRevPrefix, RoRevRange,
 };
 pub use self::mdb::error::Error as MdbError;
 use self::mdb::ffi::{from_val, into_val};
 pub use self::mdb::flags::{DatabaseFlags, EnvFlags, PutFlags};
+pub use self::observer::{EnvObserver, ReadTxnStats, WriteTxnStats};
 pub use self::reserved_space::ReservedSpace;
-pub use self::traits::{BoxedError, BytesDecode, BytesEncode, Comparator, LexicographicComparator};
+pub use self::staged::{StagedIter, StagedTxn};
+pub use self::traits::{
+    BoxedError, BytesDecode, BytesDecodeOwned, BytesEncode, CodecIdentity, Comparator, FixedSize,
+    LexicographicComparator,
+};
 pub use self::txn::{
-    AnyTls, ReadHalf, ReadTxn, RoTxn, RwTxn, TlsUsage, WithTls, WithoutTls, WriteHalf, WriteTxn,
+    AnyTls, Guarded, ReadHalf, ReadTxn, RoTxn, RwTxn, SyncRoTxn, TlsUsage, WithTls, WithoutTls,
+    WriteHalf, WriteTxn,
 };
 
 /// The underlying LMDB library version information.
@@ -175,6 +215,97 @@ pub enum Error {
     /// The environment is already open in this program;
     /// close it to be able to open it again with different options.
     EnvAlreadyOpened,
+    /// The schema version stored in the environment is more recent than
+    /// the one supported by this program.
+    UnsupportedSchemaVersion {
+        /// The schema version stored in the environment.
+        stored: u64,
+        /// The most recent schema version this program supports.
+        supported: u64,
+    },
+    /// The codecs used to open a database differ from the ones recorded when it was created.
+    CodecMismatch {
+        /// The name of the database, `None` for the unnamed one.
+        database: Option<String>,
+        /// The codecs and comparators recorded in the environment.
+        stored: String,
+        /// The codecs and comparators used to open the database.
+        requested: String,
+    },
+    /// An entry conflicts with another one in a unique index.
+    UniqueViolation {
+        /// The name of the unique index.
+        index: String,
+        /// The encoded index key that already maps to another entry.
+        key: Vec<u8>,
+    },
+    /// The database was opened in an environment that is now closed.
+    EnvClosed,
+    /// The database was opened in another environment than the one of the transaction.
+    ForeignDatabase,
+    /// The write transaction in progress in this process wasn't done before the timeout,
+    /// see [`Env::write_txn_timeout`].
+    WriteTxnTimeout {
+        /// The time spent waiting for the write transaction.
+        waited: Duration,
+        /// The write transaction that was in progress.
+        holder: WriterHolder,
+    },
+    /// The database is not in the allow-list of the half of the transaction,
+    /// see [`RwTxn::split_with`].
+    DbNotAllowedInHalf,
+    /// A key is larger than the limit of its database,
+    /// see [`DatabaseOpenOptions::max_key_size`].
+    KeyTooLarge {
+        /// The name of the database, `None` for the unnamed one.
+        database: Option<String>,
+        /// The first bytes of the encoded key.
+        key_preview: Vec<u8>,
+        /// The size of the encoded key.
+        len: usize,
+        /// The maximum size of the keys of the database.
+        max: usize,
+    },
+    /// A value is larger than the limit of its database,
+    /// see [`DatabaseOpenOptions::max_value_size`].
+    ValueTooLarge {
+        /// The name of the database, `None` for the unnamed one.
+        database: Option<String>,
+        /// The first bytes of the encoded key of the value.
+        key_preview: Vec<u8>,
+        /// The size of the encoded value.
+        len: usize,
+        /// The maximum size of the values of the database.
+        max: usize,
+    },
+    /// The value was read before a write through the write half of the split transaction,
+    /// see [`ReadHalf::get`].
+    StaleValue,
+    /// A checkpoint of the write transaction committed but couldn't begin a new transaction,
+    /// the write transaction can only be dropped, see [`RwTxn::checkpoint`].
+    TxnPoisoned,
+    /// The start of a range is after its end, as defined by the comparator of the database.
+    InvalidRange,
+    /// The stored version of the value isn't the expected one,
+    /// see [`Database::put_versioned`].
+    VersionConflict {
+        /// The version the value was expected to have.
+        expected: u64,
+        /// The stored version, `0` for a missing key.
+        found: u64,
+    },
+    /// The transactions of the first environments of a multi-environment transaction were
+    /// committed but not the following ones, see [`multi_env::MultiEnvTxn::commit`].
+    PartialCommit {
+        /// The id of the multi-environment transaction.
+        id: u64,
+        /// The number of committed environments.
+        committed: usize,
+        /// The number of environments of the transaction.
+        participants: usize,
+        /// The error of the failed commit.
+        error: Box<Error>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -188,6 +319,64 @@ impl fmt::Display for Error {
                 "environment already open in this program; \
                 close it to be able to open it again with different options",
             ),
+            Error::UnsupportedSchemaVersion { stored, supported } => write!(
+                f,
+                "the stored schema version ({stored}) is more recent \
+                than the supported one ({supported})"
+            ),
+            Error::CodecMismatch { database, stored, requested } => write!(
+                f,
+                "the {} database was created with {stored} but is opened with {requested}",
+                database.as_deref().unwrap_or("unnamed")
+            ),
+            Error::UniqueViolation { index, key } => {
+                write!(f, "the key {key:?} of the unique index {index} is already used")
+            }
+            Error::EnvClosed => f.write_str("the environment of the database is closed"),
+            Error::ForeignDatabase => {
+                f.write_str("the database doesn't belong to the environment of the transaction")
+            }
+            Error::WriteTxnTimeout { waited, holder } => {
+                let WriterHolder { pid, thread, held_for } = holder;
+                write!(f, "timed out after {waited:?} waiting for the writer of pid {pid}")?;
+                if let Some(thread) = thread {
+                    write!(f, " on thread {thread:?}")?;
+                }
+                write!(f, " running for {held_for:?}")
+            }
+            Error::DbNotAllowedInHalf => {
+                f.write_str("the database is not allowed in this half of the split transaction")
+            }
+            Error::KeyTooLarge { database, key_preview, len, max } => write!(
+                f,
+                "the key of {len} bytes starting with {:?} is larger than the {max} bytes \
+                 allowed in the {} database",
+                key_preview.escape_ascii().to_string(),
+                database.as_deref().unwrap_or("unnamed")
+            ),
+            Error::ValueTooLarge { database, key_preview, len, max } => write!(
+                f,
+                "the value of {len} bytes of the key starting with {:?} is larger than the {max} \
+                 bytes allowed in the {} database",
+                key_preview.escape_ascii().to_string(),
+                database.as_deref().unwrap_or("unnamed")
+            ),
+            Error::StaleValue => f.write_str(
+                "the value was read before a write through the write half of the split transaction",
+            ),
+            Error::TxnPoisoned => f.write_str(
+                "the write transaction couldn't begin again after a checkpoint and can only be \
+                 dropped",
+            ),
+            Error::InvalidRange => f.write_str("the start of the range is after its end"),
+            Error::VersionConflict { expected, found } => {
+                write!(f, "expected the version {expected} of the value but found {found}")
+            }
+            Error::PartialCommit { id, committed, participants, error } => write!(
+                f,
+                "the multi-environment transaction {id} was committed in {committed} of its \
+                 {participants} environments: {error}"
+            ),
         }
     }
 }
@@ -220,15 +409,23 @@ pub type Result<T> = result::Result<T, Error>;
 /// to properly define them.
 pub enum Unspecified {}
 
-macro_rules! assert_eq_env_db_txn {
+macro_rules! check_env_db_txn {
     ($database:ident, $txn:ident) => {
-        assert!(
-            $database.env_ident == unsafe { $txn.env_mut_ptr().as_mut() as *mut _ as usize },
-            "The database environment doesn't match the transaction's environment"
-        );
+        $database.check_env(&*$txn)?
     };
 }
 
+macro_rules! check_env_db_wtxn {
+    ($database:ident, $txn:ident) => {{
+        $database.check_env(&*$txn)?;
+        $txn.before_write($database.dbi, false)?;
+    }};
+    ($database:ident, $txn:ident, drop) => {{
+        $database.check_env(&*$txn)?;
+        $txn.before_write($database.dbi, true)?;
+    }};
+}
+
 macro_rules! assert_eq_env_txn {
     ($env:expr, $txn:ident) => {
         assert!(
@@ -238,7 +435,7 @@ macro_rules! assert_eq_env_txn {
     };
 }
 
-pub(crate) use {assert_eq_env_db_txn, assert_eq_env_txn};
+pub(crate) use {assert_eq_env_txn, check_env_db_txn, check_env_db_wtxn};
 
 #[cfg(test)]
 mod tests {
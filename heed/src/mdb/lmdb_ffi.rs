@@ -1,14 +1,14 @@
 use std::ptr;
 
 pub use ffi::{
-    mdb_cursor_close, mdb_cursor_del, mdb_cursor_get, mdb_cursor_open, mdb_dbi_open, mdb_del,
-    mdb_drop, mdb_env_close, mdb_env_copyfd2, mdb_env_create, mdb_env_get_fd, mdb_env_get_flags,
-    mdb_env_get_maxkeysize, mdb_env_get_maxreaders, mdb_env_info, mdb_env_open,
+    mdb_cmp, mdb_cursor_close, mdb_cursor_count, mdb_cursor_dbi, mdb_cursor_del, mdb_cursor_get, mdb_cursor_open, mdb_cursor_txn, mdb_dbi_flags, mdb_dbi_open, mdb_dcmp,
+    mdb_del, mdb_drop, mdb_env_close, mdb_env_copyfd2, mdb_env_create, mdb_env_get_fd,
+    mdb_env_get_flags, mdb_env_get_maxkeysize, mdb_env_get_maxreaders, mdb_env_info, mdb_env_open,
     mdb_env_set_flags, mdb_env_set_mapsize, mdb_env_set_maxdbs, mdb_env_set_maxreaders,
-    mdb_env_stat, mdb_env_sync, mdb_filehandle_t, mdb_get, mdb_put, mdb_reader_check,
+    mdb_env_stat, mdb_env_sync, mdb_filehandle_t, mdb_get, mdb_put, mdb_reader_check, mdb_reader_list,
     mdb_set_compare, mdb_set_dupsort, mdb_stat, mdb_txn_abort, mdb_txn_begin, mdb_txn_commit,
-    mdb_txn_id, mdb_version, MDB_cursor, MDB_dbi, MDB_env, MDB_envinfo, MDB_stat, MDB_txn,
-    MDB_val, MDB_CP_COMPACT, MDB_RDONLY, MDB_RESERVE,
+    mdb_txn_id, mdb_txn_renew, mdb_txn_reset, mdb_version, MDB_cmp_func, MDB_cursor, MDB_cursor_op, MDB_dbi, MDB_env, MDB_envinfo, MDB_stat, MDB_txn, MDB_val,
+    MDB_CP_COMPACT, MDB_NODUPDATA, MDB_RDONLY, MDB_RESERVE,
 };
 #[cfg(master3)]
 pub use ffi::{mdb_env_set_encrypt, MDB_enc_func};
@@ -17,6 +17,11 @@ use lmdb_master3_sys as ffi;
 #[cfg(not(master3))]
 use lmdb_master_sys as ffi;
 
+/// The database of the free pages.
+pub const FREE_DBI: MDB_dbi = 0;
+/// The unnamed database, which also stores the named databases.
+pub const MAIN_DBI: MDB_dbi = 1;
+
 pub mod cursor_op {
     use super::ffi::{self, MDB_cursor_op};
 
@@ -33,6 +38,10 @@ pub mod cursor_op {
     pub const MDB_NEXT_NODUP: MDB_cursor_op = ffi::MDB_NEXT_NODUP;
     pub const MDB_NEXT_DUP: MDB_cursor_op = ffi::MDB_NEXT_DUP;
     pub const MDB_GET_CURRENT: MDB_cursor_op = ffi::MDB_GET_CURRENT;
+    pub const MDB_GET_BOTH: MDB_cursor_op = ffi::MDB_GET_BOTH;
+    pub const MDB_GET_BOTH_RANGE: MDB_cursor_op = ffi::MDB_GET_BOTH_RANGE;
+    pub const MDB_GET_MULTIPLE: MDB_cursor_op = ffi::MDB_GET_MULTIPLE;
+    pub const MDB_NEXT_MULTIPLE: MDB_cursor_op = ffi::MDB_NEXT_MULTIPLE;
 }
 
 pub fn reserve_size_val(size: usize) -> ffi::MDB_val {
@@ -5,7 +5,7 @@ use lmdb_master3_sys as ffi;
 use lmdb_master_sys as ffi;
 
 #[allow(unused)] // for cargo auto doc links
-use crate::{Database, IntegerComparator};
+use crate::{Database, IntegerComparator, ReverseComparator};
 
 bitflags! {
     /// LMDB environment flags (see <http://www.lmdb.tech/doc/group__mdb__env.html> for more details).
@@ -26,6 +26,9 @@ bitflags! {
         /// Don't fsync metapage after commit.
         const NO_META_SYNC = ffi::MDB_NOMETASYNC;
         /// Use writable mmap.
+        ///
+        /// The keys and values returned by the read half of a split write transaction
+        /// are copied, as the writes modify the memory map in place.
         const WRITE_MAP = ffi::MDB_WRITEMAP;
         /// Use asynchronous msync when MDB_WRITEMAP is used.
         const MAP_ASYNC = ffi::MDB_MAPASYNC;
@@ -49,6 +52,9 @@ bitflags! {
     #[repr(transparent)]
     pub struct AllDatabaseFlags: u32 {
         /// Use reverse string keys.
+        ///
+        /// It is recommended to set the comparator to [`ReverseComparator`](crate::ReverseComparator),
+        /// rather than setting this flag manually.
         const REVERSE_KEY = ffi::MDB_REVERSEKEY;
         /// Use sorted duplicates.
         const DUP_SORT = ffi::MDB_DUPSORT;
@@ -63,6 +69,9 @@ bitflags! {
         /// With [`DatabaseFlags::DUP_SORT`], dups are [`DatabaseFlags::INTEGER_KEY`]-style integers.
         const INTEGER_DUP = ffi::MDB_INTEGERDUP;
         /// With [`DatabaseFlags::DUP_SORT`], use reverse string dups.
+        ///
+        /// It is recommended to set the dup sort comparator to
+        /// [`ReverseComparator`](crate::ReverseComparator), rather than setting this flag manually.
         const REVERSE_DUP = ffi::MDB_REVERSEDUP;
         /// Create DB if not already existing.
         const CREATE = ffi::MDB_CREATE;
@@ -78,6 +87,11 @@ bitflags! {
     pub struct DatabaseFlags: u32 {
         /// Use reverse string keys.
         ///
+        /// It is recommended to use the [`ReverseComparator`] when
+        /// opening the [`Database`] instead. This comparator keeps the
+        /// ranges compared like LMDB does and does not allow for prefix
+        /// iteration, as the keys sharing a prefix are not contiguous.
+        ///
         /// ```
         /// # use std::fs;
         /// # use std::path::Path;
@@ -122,6 +136,7 @@ bitflags! {
         /// wtxn.commit()?;
         /// # Ok(()) }
         /// ```
+        #[deprecated(since="0.22.0", note="prefer using `ReverseComparator` with the `DatabaseOpenOptions::key_comparator` method instead")]
         const REVERSE_KEY = ffi::MDB_REVERSEKEY;
         /// Use sorted duplicates.
         ///
@@ -358,6 +373,9 @@ bitflags! {
         const INTEGER_DUP = ffi::MDB_INTEGERDUP;
         /// With [`DatabaseFlags::DUP_SORT`], use reverse string dups.
         ///
+        /// It is recommended to use the [`ReverseComparator`] as the dup sort
+        /// comparator when opening the [`Database`] instead.
+        ///
         /// ```
         /// # use std::fs;
         /// # use std::path::Path;
@@ -397,6 +415,7 @@ bitflags! {
         /// wtxn.commit()?;
         /// # Ok(()) }
         /// ```
+        #[deprecated(since="0.22.0", note="prefer using `ReverseComparator` with the `DatabaseOpenOptions::dup_sort_comparator` method instead")]
         const REVERSE_DUP = ffi::MDB_REVERSEDUP;
     }
 }
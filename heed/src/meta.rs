@@ -0,0 +1,461 @@
+//! Persistent metadata about the environment and its databases.
+//!
+//! The metadata is stored in a reserved named database, [`META_DATABASE_NAME`],
+//! that counts in the [`EnvOpenOptions::max_dbs`](crate::EnvOpenOptions::max_dbs) limit.
+//! It records the version of the application schema, used by [`Env::migrate`],
+//! identifiers describing the codecs of each database, the [`Sequence`]s, the databases
+//! whose re-encoded entries are being copied back by [`Database::reencode_to`], the first
+//! transaction of the [change log](crate::changes::ChangeLog) and, in snapshots,
+//! the id of the transaction they were copied from.
+
+use std::ops::Range;
+use std::sync::Mutex;
+
+use byteorder::BigEndian;
+
+use crate::types::{Bytes, Str, U64};
+use crate::{Database, Env, Error, ReadTxn, Result, RwTxn, WriteTxn};
+
+/// The name of the database where the metadata is stored.
+pub const META_DATABASE_NAME: &str = "__heed_meta";
+
+const SCHEMA_VERSION_KEY: &str = "schema-version";
+const CODEC_KEY_PREFIX: &str = "codec:";
+const SEQUENCE_KEY_PREFIX: &str = "sequence:";
+const SNAPSHOT_TXN_ID_KEY: &str = "snapshot-txn-id";
+const REENCODE_KEY_PREFIX: &str = "reencode:";
+const CHANGE_LOG_SINCE_KEY: &str = "change-log-since";
+
+/// A handle to the metadata database of an environment.
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    db: Database<Str, Bytes>,
+}
+
+impl Metadata {
+    /// Opens the metadata database, creating it if it doesn't exist.
+    pub fn create<T>(env: &Env<T>, wtxn: &mut impl WriteTxn) -> Result<Metadata> {
+        let db = env.create_database(wtxn, Some(META_DATABASE_NAME))?;
+        Ok(Metadata { db })
+    }
+
+    /// Opens the metadata database, returns `None` if it doesn't exist.
+    pub fn open<T>(env: &Env<T>, rtxn: &impl ReadTxn) -> Result<Option<Metadata>> {
+        let db = env.open_database(rtxn, Some(META_DATABASE_NAME))?;
+        Ok(db.map(|db| Metadata { db }))
+    }
+
+    /// Returns the stored schema version, if any.
+    pub fn schema_version(&self, rtxn: &impl ReadTxn) -> Result<Option<u64>> {
+        self.db.remap_data_type::<U64<BigEndian>>().get(rtxn, SCHEMA_VERSION_KEY)
+    }
+
+    /// Stores the schema version.
+    pub fn set_schema_version(&self, wtxn: &mut impl WriteTxn, version: u64) -> Result<()> {
+        self.db.remap_data_type::<U64<BigEndian>>().put(wtxn, SCHEMA_VERSION_KEY, &version)
+    }
+
+    /// Returns the id of the last transaction included in this snapshot,
+    /// if the environment is a snapshot written by [`Env::snapshot_to`].
+    pub fn snapshot_txn_id(&self, rtxn: &impl ReadTxn) -> Result<Option<u64>> {
+        self.db.remap_data_type::<U64<BigEndian>>().get(rtxn, SNAPSHOT_TXN_ID_KEY)
+    }
+
+    /// Stores the id of the last transaction included in this snapshot.
+    pub fn set_snapshot_txn_id(&self, wtxn: &mut impl WriteTxn, txn_id: u64) -> Result<()> {
+        self.db.remap_data_type::<U64<BigEndian>>().put(wtxn, SNAPSHOT_TXN_ID_KEY, &txn_id)
+    }
+
+    /// Returns the id of the first transaction from which the changes of every transaction
+    /// are in the change log, if any.
+    pub fn change_log_since(&self, rtxn: &impl ReadTxn) -> Result<Option<u64>> {
+        self.db.remap_data_type::<U64<BigEndian>>().get(rtxn, CHANGE_LOG_SINCE_KEY)
+    }
+
+    /// Stores the id of the first transaction from which the changes are in the change log.
+    pub fn set_change_log_since(&self, wtxn: &mut impl WriteTxn, txn_id: u64) -> Result<()> {
+        self.db.remap_data_type::<U64<BigEndian>>().put(wtxn, CHANGE_LOG_SINCE_KEY, &txn_id)
+    }
+
+    /// Returns the codec identifier stored for the given database, if any.
+    ///
+    /// The unnamed database is designated by `None`.
+    pub fn codec_identifier<'txn>(
+        &self,
+        rtxn: &'txn impl ReadTxn,
+        database: Option<&str>,
+    ) -> Result<Option<&'txn str>> {
+        let key = codec_key(database);
+        self.db.remap_data_type::<Str>().get(rtxn, &key)
+    }
+
+    /// Stores the codec identifier of the given database.
+    ///
+    /// The unnamed database is designated by `None`.
+    pub fn set_codec_identifier(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        database: Option<&str>,
+        identifier: &str,
+    ) -> Result<()> {
+        let key = codec_key(database);
+        self.db.remap_data_type::<Str>().put(wtxn, &key, identifier)
+    }
+
+    /// Deletes the codec identifier of the given database, returns `true` if there was one.
+    ///
+    /// The unnamed database is designated by `None`.
+    pub fn delete_codec_identifier(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        database: Option<&str>,
+    ) -> Result<bool> {
+        let key = codec_key(database);
+        self.db.delete(wtxn, &key)
+    }
+
+    /// Returns the next value that the given sequence will allocate, if any.
+    ///
+    /// Values handed out from the cache of a [`Sequence`] with a stride are below it.
+    pub fn sequence(&self, rtxn: &impl ReadTxn, name: &str) -> Result<Option<u64>> {
+        let key = format!("{SEQUENCE_KEY_PREFIX}{name}");
+        self.db.remap_data_type::<U64<BigEndian>>().get(rtxn, &key)
+    }
+
+    /// Stores the next value that the given sequence will allocate.
+    pub fn set_sequence(&self, wtxn: &mut impl WriteTxn, name: &str, next: u64) -> Result<()> {
+        let key = format!("{SEQUENCE_KEY_PREFIX}{name}");
+        self.db.remap_data_type::<U64<BigEndian>>().put(wtxn, &key, &next)
+    }
+
+    /// Returns `true` if the re-encoded entries of the given database are being copied back
+    /// into it, the database then only holds a part of them.
+    ///
+    /// The unnamed database is designated by `None`.
+    pub(crate) fn copying_reencoded(
+        &self,
+        rtxn: &impl ReadTxn,
+        database: Option<&str>,
+    ) -> Result<bool> {
+        let key = format!("{REENCODE_KEY_PREFIX}{}", database.unwrap_or_default());
+        Ok(self.db.get(rtxn, &key)?.is_some())
+    }
+
+    /// Records whether the re-encoded entries of the given database are being copied back.
+    ///
+    /// The unnamed database is designated by `None`.
+    pub(crate) fn set_copying_reencoded(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        database: Option<&str>,
+        copying: bool,
+    ) -> Result<()> {
+        let key = format!("{REENCODE_KEY_PREFIX}{}", database.unwrap_or_default());
+        if copying {
+            self.db.put(wtxn, &key, &[])
+        } else {
+            self.db.delete(wtxn, &key).map(drop)
+        }
+    }
+}
+
+fn codec_key(database: Option<&str>) -> String {
+    format!("{CODEC_KEY_PREFIX}{}", database.unwrap_or_default())
+}
+
+/// A persistent and monotonic `u64` generator, returned by [`Env::sequence`].
+///
+/// The values start at `0` and are allocated in write transactions: a value is never
+/// allocated twice, even after a crash, but the values allocated in aborted transactions
+/// are lost. With a [stride](Sequence::with_stride), the values are reserved by blocks
+/// and handed out from memory, only one write out of `stride` allocations updates the
+/// stored sequence and the values not handed out when the program stops are lost.
+pub struct Sequence<T> {
+    env: Env<T>,
+    name: String,
+    stride: u64,
+    /// The values reserved in the environment but not handed out yet.
+    cache: Mutex<Range<u64>>,
+}
+
+impl<T> Sequence<T> {
+    /// Reserves the values by blocks of `stride`, `1` by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stride is `0`.
+    pub fn with_stride(mut self, stride: u64) -> Sequence<T> {
+        assert!(stride > 0, "the stride of a sequence must be greater than zero");
+        self.stride = stride;
+        self
+    }
+
+    /// The name of the sequence in the metadata.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Allocates the next value of the sequence.
+    ///
+    /// Fails with [`MdbError::Incompatible`](crate::MdbError::Incompatible)
+    /// if the sequence is exhausted.
+    pub fn next(&self, wtxn: &mut impl WriteTxn) -> Result<u64> {
+        let metadata = Metadata::create(&self.env, wtxn)?;
+        let stored = metadata.sequence(wtxn, &self.name)?.unwrap_or(0);
+
+        let mut cache = self.cache.lock().unwrap();
+        // The cached block is only valid if its reservation is the last one written,
+        // it is not if the reserving transaction was aborted or another handle reserved values.
+        if cache.is_empty() || cache.end != stored {
+            let end = stored.checked_add(self.stride).ok_or(crate::MdbError::Incompatible)?;
+            metadata.set_sequence(wtxn, &self.name, end)?;
+            *cache = stored..end;
+        }
+
+        let value = cache.start;
+        cache.start += 1;
+        Ok(value)
+    }
+}
+
+/// The context given to the migration function of [`Env::migrate`].
+pub struct MigrationCtx<'a, 'e, T> {
+    env: &'a Env<T>,
+    wtxn: &'a mut RwTxn<'e>,
+    metadata: Metadata,
+    version: u64,
+}
+
+impl<'e, T> MigrationCtx<'_, 'e, T> {
+    /// The environment being migrated.
+    pub fn env(&self) -> &Env<T> {
+        self.env
+    }
+
+    /// The write transaction in which all the migrations are executed.
+    pub fn wtxn(&mut self) -> &mut RwTxn<'e> {
+        self.wtxn
+    }
+
+    /// The metadata of the environment, to update the codec identifiers for example.
+    pub fn metadata(&self) -> Metadata {
+        self.metadata
+    }
+
+    /// The version this migration must bring the schema to.
+    ///
+    /// The schema is currently at `version() - 1`.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl<T> Env<T> {
+    /// Returns a handle on the persistent sequence with the given name.
+    ///
+    /// The sequence is stored in the metadata database when a first value is allocated.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let user_ids = env.sequence("user-ids").with_stride(100);
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// assert_eq!(user_ids.next(&mut wtxn)?, 0);
+    /// assert_eq!(user_ids.next(&mut wtxn)?, 1);
+    /// wtxn.commit()?;
+    ///
+    /// // A new handle doesn't know about the values reserved by the previous one.
+    /// let user_ids = env.sequence("user-ids");
+    /// let mut wtxn = env.write_txn()?;
+    /// assert_eq!(user_ids.next(&mut wtxn)?, 100);
+    /// # Ok(()) }
+    /// ```
+    pub fn sequence(&self, name: &str) -> Sequence<T> {
+        Sequence { env: self.clone(), name: name.to_owned(), stride: 1, cache: Mutex::new(0..0) }
+    }
+
+    /// Migrates the schema of this environment up to `current_version`.
+    ///
+    /// The `migration` function is called once for every version between the stored schema
+    /// version, `0` if there is none, and `current_version`, in increasing order, with the
+    /// version to migrate to available as [`MigrationCtx::version`]. All the migrations run
+    /// in a single write transaction that also stores the new schema version, they are
+    /// therefore either all applied or not at all.
+    ///
+    /// Returns the schema version stored before the migration.
+    /// Fails with [`Error::UnsupportedSchemaVersion`] if the stored version is
+    /// greater than `current_version`.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let previous = env.migrate(2, |ctx| {
+    ///     let env = ctx.env().clone();
+    ///     match ctx.version() {
+    ///         1 => {
+    ///             env.create_database::<Str, Str>(ctx.wtxn(), Some("users"))?;
+    ///         }
+    ///         2 => {
+    ///             let users = env.create_database::<Str, Str>(ctx.wtxn(), Some("users"))?;
+    ///             users.put(ctx.wtxn(), "admin", "root")?;
+    ///         }
+    ///         _ => unreachable!(),
+    ///     }
+    ///     heed::Result::Ok(())
+    /// })?;
+    /// assert_eq!(previous, 0);
+    ///
+    /// // Migrations that are already applied are not executed again.
+    /// let previous = env.migrate(2, |_| -> heed::Result<()> { unreachable!() })?;
+    /// assert_eq!(previous, 2);
+    /// # Ok(()) }
+    /// ```
+    pub fn migrate<F, E>(
+        &self,
+        current_version: u64,
+        mut migration: F,
+    ) -> std::result::Result<u64, E>
+    where
+        F: FnMut(&mut MigrationCtx<T>) -> std::result::Result<(), E>,
+        E: From<Error>,
+    {
+        let mut wtxn = self.write_txn()?;
+        let metadata = Metadata::create(self, &mut wtxn)?;
+        let stored_version = metadata.schema_version(&wtxn)?.unwrap_or(0);
+
+        if stored_version > current_version {
+            return Err(Error::UnsupportedSchemaVersion {
+                stored: stored_version,
+                supported: current_version,
+            }
+            .into());
+        }
+
+        if stored_version == current_version {
+            return Ok(stored_version);
+        }
+
+        for version in stored_version + 1..=current_version {
+            let mut ctx = MigrationCtx { env: self, wtxn: &mut wtxn, metadata, version };
+            (migration)(&mut ctx)?;
+        }
+
+        metadata.set_schema_version(&mut wtxn, current_version)?;
+        wtxn.commit()?;
+
+        Ok(stored_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn migrate_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(10 * 1024 * 1024) // 10MB
+                .max_dbs(10)
+                .open(dir.path())
+                .unwrap()
+        };
+
+        let result = env.migrate(3, |ctx| {
+            let env = ctx.env().clone();
+            let version = ctx.version().to_string();
+            let db = env.create_database::<Str, Str>(ctx.wtxn(), Some("db"))?;
+            db.put(ctx.wtxn(), "version", &version)?;
+            if ctx.version() == 3 {
+                Err(Error::Decoding("failing migration".into()))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(matches!(result, Err(Error::Decoding(_))));
+
+        // Nothing was written, not even the first two migrations.
+        let rtxn = env.read_txn().unwrap();
+        assert!(Metadata::open(&env, &rtxn).unwrap().is_none());
+        assert!(env.open_database::<Str, Str>(&rtxn, Some("db")).unwrap().is_none());
+        drop(rtxn);
+
+        let previous = env.migrate(2, |_| Ok::<_, Error>(())).unwrap();
+        assert_eq!(previous, 0);
+
+        let result = env.migrate(1, |_| Ok::<_, Error>(()));
+        assert!(matches!(result, Err(Error::UnsupportedSchemaVersion { stored: 2, supported: 1 })));
+
+        let rtxn = env.read_txn().unwrap();
+        let metadata = Metadata::open(&env, &rtxn).unwrap().unwrap();
+        assert_eq!(metadata.schema_version(&rtxn).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn codec_identifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()).unwrap() };
+
+        let mut wtxn = env.write_txn().unwrap();
+        let metadata = Metadata::create(&env, &mut wtxn).unwrap();
+        metadata.set_codec_identifier(&mut wtxn, Some("users"), "Str/SerdeJson").unwrap();
+        metadata.set_codec_identifier(&mut wtxn, None, "Bytes/Bytes").unwrap();
+
+        assert_eq!(metadata.codec_identifier(&wtxn, Some("users")).unwrap(), Some("Str/SerdeJson"));
+        assert_eq!(metadata.codec_identifier(&wtxn, None).unwrap(), Some("Bytes/Bytes"));
+        assert_eq!(metadata.codec_identifier(&wtxn, Some("unknown")).unwrap(), None);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn sequences_never_reuse_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()).unwrap() };
+        let ids = env.sequence("ids").with_stride(10);
+
+        // The block reserved by an aborted transaction is reserved again.
+        let mut wtxn = env.write_txn().unwrap();
+        assert_eq!(ids.next(&mut wtxn).unwrap(), 0);
+        wtxn.abort();
+
+        let mut wtxn = env.write_txn().unwrap();
+        assert_eq!(ids.next(&mut wtxn).unwrap(), 0);
+        assert_eq!(ids.next(&mut wtxn).unwrap(), 1);
+        wtxn.commit().unwrap();
+
+        // Values handed out from the cache in aborted transactions are lost.
+        let mut wtxn = env.write_txn().unwrap();
+        assert_eq!(ids.next(&mut wtxn).unwrap(), 2);
+        wtxn.abort();
+
+        let other = env.sequence("ids");
+        let mut wtxn = env.write_txn().unwrap();
+        assert_eq!(ids.next(&mut wtxn).unwrap(), 3);
+        assert_eq!(other.next(&mut wtxn).unwrap(), 10);
+        // The other handle reserved values, the cached block is not valid anymore.
+        assert_eq!(ids.next(&mut wtxn).unwrap(), 11);
+        wtxn.commit().unwrap();
+
+        let rtxn = env.read_txn().unwrap();
+        let metadata = Metadata::open(&env, &rtxn).unwrap().unwrap();
+        assert_eq!(metadata.sequence(&rtxn, "ids").unwrap(), Some(21));
+        assert_eq!(metadata.sequence(&rtxn, "unknown").unwrap(), None);
+    }
+}
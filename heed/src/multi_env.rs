@@ -0,0 +1,355 @@
+//! Best-effort transactions over several environments.
+//!
+//! LMDB commits the transactions of one environment atomically but can't commit the write
+//! transactions of several environments at once, like the shards of an application spread
+//! over several files. A [`MultiEnvTxn`] opens a write transaction in every environment and
+//! commits them one after the other in the order of the environments, after a preparation:
+//!
+//! 1. A marker identifying the multi-environment transaction is written in the
+//!    [journal](JOURNAL_DATABASE_NAME) of every environment, in its write transaction.
+//! 2. The [prepared callback](MultiEnvTxn::on_prepared) is called, an error aborts
+//!    every transaction and nothing is committed.
+//! 3. The transactions are committed in order. If a commit fails, the following transactions
+//!    are aborted and the [`Error::PartialCommit`] error tells how many were committed.
+//! 4. The markers are removed, in a write transaction per environment.
+//!
+//! A marker is committed with the data of its environment. If the process stops during the
+//! commits, [`MultiEnvTxn::recover`] finds on restart the transactions whose markers are in
+//! some environments only, the application decides how to repair them and then
+//! [resolves](MultiEnvTxn::resolve) them. There is no rollback of the committed environments.
+//!
+//! ```
+//! # use heed::EnvOpenOptions;
+//! use heed::multi_env::MultiEnvTxn;
+//! use heed::types::*;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let (dir_a, dir_b) = (tempfile::tempdir()?, tempfile::tempdir()?);
+//! let shard_a = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir_a.path())? };
+//! let shard_b = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir_b.path())? };
+//! // The transactions left partially committed by a previous run.
+//! assert!(MultiEnvTxn::recover(&[&shard_a, &shard_b])?.is_empty());
+//!
+//! let mut txn = MultiEnvTxn::begin(&[&shard_a, &shard_b])?;
+//! let users_a = shard_a.create_database::<Str, Str>(txn.txn(0), Some("users"))?;
+//! let users_b = shard_b.create_database::<Str, Str>(txn.txn(1), Some("users"))?;
+//! users_a.put(txn.txn(0), "kero", "a")?;
+//! users_b.put(txn.txn(1), "tamo", "b")?;
+//! txn.on_prepared(|id| {
+//!     println!("the transaction {id} is prepared");
+//!     Ok(())
+//! });
+//! txn.commit()?;
+//!
+//! let rtxn = shard_b.read_txn()?;
+//! assert_eq!(users_b.get(&rtxn, "tamo")?, Some("b"));
+//! # Ok(()) }
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::types::{Bytes, U64};
+use crate::{Database, Env, Error, Result, RwTxn, WithTls};
+
+/// The name of the database where the markers of the multi-environment transactions are
+/// written, in every environment. It counts in the
+/// [`EnvOpenOptions::max_dbs`](crate::EnvOpenOptions::max_dbs) limit.
+pub const JOURNAL_DATABASE_NAME: &str = "__heed_multi_env";
+
+/// The markers by transaction id.
+type Journal = Database<U64<BigEndian>, Bytes>;
+
+type Prepared<'e> = Box<dyn FnOnce(u64) -> Result<()> + 'e>;
+
+/// The write transactions of several environments committed together,
+/// see the [module documentation](self).
+pub struct MultiEnvTxn<'e, T = WithTls> {
+    envs: Vec<&'e Env<T>>,
+    txns: Vec<RwTxn<'e>>,
+    prepared: Option<Prepared<'e>>,
+}
+
+impl<'e, T> MultiEnvTxn<'e, T> {
+    /// Opens a write transaction in every environment, in order.
+    ///
+    /// The environments shared by several multi-environment transactions must be given in the
+    /// same order everywhere, else two of them can wait for each other's write transactions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an environment is given twice.
+    pub fn begin(envs: &[&'e Env<T>]) -> Result<Self> {
+        for (i, env) in envs.iter().enumerate() {
+            assert!(
+                envs[..i].iter().all(|other| other.path() != env.path()),
+                "the environment {} is given twice",
+                env.path().display()
+            );
+        }
+        let txns = envs.iter().map(|env| env.write_txn()).collect::<Result<_>>()?;
+        Ok(MultiEnvTxn { envs: envs.to_vec(), txns, prepared: None })
+    }
+
+    /// The write transaction of the environment at this index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no environment at this index.
+    pub fn txn(&mut self, index: usize) -> &mut RwTxn<'e> {
+        &mut self.txns[index]
+    }
+
+    /// Calls the function with the id of the transaction once the markers are written in every
+    /// environment, before the first commit. An error aborts every transaction.
+    pub fn on_prepared<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnOnce(u64) -> Result<()> + 'e,
+    {
+        self.prepared = Some(Box::new(f));
+        self
+    }
+
+    /// Commits the transactions in the order of the environments and returns the id of
+    /// the multi-environment transaction.
+    ///
+    /// Fails with the error of the first commit if nothing was committed, and with
+    /// [`Error::PartialCommit`] if the transactions of the first environments were committed.
+    /// The markers are removed once every transaction is committed, the markers left by a
+    /// failure are removed by [`Self::recover`].
+    pub fn commit(mut self) -> Result<u64> {
+        let mut id = 0;
+        let mut journals = Vec::with_capacity(self.envs.len());
+        for (env, txn) in self.envs.iter().zip(&mut self.txns) {
+            let journal: Journal = env.create_database(txn, Some(JOURNAL_DATABASE_NAME))?;
+            if let Some((last, _)) = journal.last(txn)? {
+                id = id.max(last);
+            }
+            journals.push(journal);
+        }
+
+        // The id is unique in every journal, the nonce tells apart the transactions
+        // of disjoint environments that got the same id.
+        let id = id + 1;
+        let nonce = RandomState::new().build_hasher().finish();
+        let participants = self.envs.len() as u32;
+        for (index, (journal, txn)) in journals.iter().zip(&mut self.txns).enumerate() {
+            let marker = Marker { nonce, participants, index: index as u32 };
+            journal.put(txn, &id, &marker.encode())?;
+        }
+
+        if let Some(prepared) = self.prepared.take() {
+            prepared(id)?;
+        }
+
+        for (committed, txn) in self.txns.drain(..).enumerate() {
+            if let Err(error) = txn.commit() {
+                return Err(match committed {
+                    0 => error,
+                    _ => Error::PartialCommit {
+                        id,
+                        committed,
+                        participants: participants as usize,
+                        error: Box::new(error),
+                    },
+                });
+            }
+        }
+
+        for (env, journal) in self.envs.iter().zip(journals) {
+            // Every transaction is committed, a marker that can't be removed
+            // now is found complete and removed by the recovery.
+            let _ = delete_marker(env, journal, id, nonce);
+        }
+        Ok(id)
+    }
+
+    /// Aborts the transactions of every environment.
+    pub fn abort(self) {
+        self.txns.into_iter().for_each(RwTxn::abort);
+    }
+
+    /// Returns the multi-environment transactions committed in some of the environments only,
+    /// and removes the markers of the transactions committed in all of them.
+    ///
+    /// Every environment that took part in a multi-environment transaction must be given, else
+    /// its transactions look partially committed. The indexes of [`PartialCommit::committed`]
+    /// are the indexes of the environments in the slice.
+    pub fn recover(envs: &[&Env<T>]) -> Result<Vec<PartialCommit>> {
+        let mut transactions = BTreeMap::new();
+        for (position, env) in envs.iter().enumerate() {
+            let rtxn = env.read_txn()?;
+            let Some(journal) =
+                env.open_database::<U64<BigEndian>, Bytes>(&rtxn, Some(JOURNAL_DATABASE_NAME))?
+            else {
+                continue;
+            };
+            for result in journal.iter(&rtxn)? {
+                let (id, bytes) = result?;
+                let marker = Marker::decode(bytes)?;
+                let (_, committed) = transactions
+                    .entry((id, marker.nonce))
+                    .or_insert_with(|| (marker.participants as usize, Vec::new()));
+                committed.push(position);
+            }
+        }
+
+        let mut partial_commits = Vec::new();
+        for ((id, nonce), (participants, committed)) in transactions {
+            let partial = PartialCommit { id, participants, committed, nonce };
+            if partial.committed.len() == participants {
+                Self::resolve(envs, &partial)?;
+            } else {
+                partial_commits.push(partial);
+            }
+        }
+        Ok(partial_commits)
+    }
+
+    /// Removes the markers of a partially committed transaction,
+    /// once the application repaired it.
+    ///
+    /// The environments must be the ones given to [`Self::recover`].
+    pub fn resolve(envs: &[&Env<T>], partial: &PartialCommit) -> Result<()> {
+        for &position in &partial.committed {
+            let env = envs[position];
+            let rtxn = env.read_txn()?;
+            let journal = env.open_database(&rtxn, Some(JOURNAL_DATABASE_NAME))?;
+            drop(rtxn);
+            if let Some(journal) = journal {
+                delete_marker(env, journal, partial.id, partial.nonce)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> fmt::Debug for MultiEnvTxn<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let paths: Vec<_> = self.envs.iter().map(|env| env.path()).collect();
+        f.debug_struct("MultiEnvTxn").field("envs", &paths).finish_non_exhaustive()
+    }
+}
+
+/// A multi-environment transaction committed in some of its environments only,
+/// returned by [`MultiEnvTxn::recover`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialCommit {
+    /// The id of the multi-environment transaction.
+    pub id: u64,
+    /// The number of environments of the transaction.
+    pub participants: usize,
+    /// The indexes of the environments where the transaction is committed.
+    pub committed: Vec<usize>,
+    nonce: u64,
+}
+
+/// The marker of a multi-environment transaction in the journal of an environment.
+struct Marker {
+    nonce: u64,
+    participants: u32,
+    /// The index of the environment in the transaction.
+    index: u32,
+}
+
+impl Marker {
+    const SIZE: usize = 16;
+
+    fn encode(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        BigEndian::write_u64(&mut bytes[..8], self.nonce);
+        BigEndian::write_u32(&mut bytes[8..12], self.participants);
+        BigEndian::write_u32(&mut bytes[12..], self.index);
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Marker> {
+        if bytes.len() != Self::SIZE {
+            return Err(Error::Decoding("invalid multi-environment transaction marker".into()));
+        }
+        Ok(Marker {
+            nonce: BigEndian::read_u64(&bytes[..8]),
+            participants: BigEndian::read_u32(&bytes[8..12]),
+            index: BigEndian::read_u32(&bytes[12..]),
+        })
+    }
+}
+
+/// Removes the marker of the transaction from the journal, if it has this nonce.
+fn delete_marker<T>(env: &Env<T>, journal: Journal, id: u64, nonce: u64) -> Result<()> {
+    let mut wtxn = env.write_txn()?;
+    match journal.get(&wtxn, &id)?.map(Marker::decode).transpose()? {
+        Some(marker) if marker.nonce == nonce => {
+            journal.delete(&mut wtxn, &id)?;
+            wtxn.commit()
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn commit_and_recover() -> Result<()> {
+        let (dir_a, dir_b) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        let a = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir_a.path())? };
+        let b = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir_b.path())? };
+
+        let mut txn = MultiEnvTxn::begin(&[&a, &b])?;
+        let db_a = a.create_database::<Str, Str>(txn.txn(0), Some("data"))?;
+        let db_b = b.create_database::<Str, Str>(txn.txn(1), Some("data"))?;
+        db_a.put(txn.txn(0), "k", "a")?;
+        db_b.put(txn.txn(1), "k", "b")?;
+        assert_eq!(txn.commit()?, 1);
+        for (env, db, value) in [(&a, db_a, "a"), (&b, db_b, "b")] {
+            let rtxn = env.read_txn()?;
+            assert_eq!(db.get(&rtxn, "k")?, Some(value));
+            let journal: Journal = env.open_database(&rtxn, Some(JOURNAL_DATABASE_NAME))?.unwrap();
+            assert!(journal.is_empty(&rtxn)?);
+        }
+
+        // A failing preparation commits nothing.
+        let mut txn = MultiEnvTxn::begin(&[&a, &b])?;
+        db_a.put(txn.txn(0), "k", "lost")?;
+        txn.on_prepared(|_| Err(Error::Decoding("rejected".into())));
+        assert!(matches!(txn.commit(), Err(Error::Decoding(_))));
+        assert_eq!(db_a.get(&a.read_txn()?, "k")?, Some("a"));
+
+        // A transaction committed in the first environment only, and another one committed
+        // everywhere but whose markers weren't removed.
+        let mut wtxn = a.write_txn()?;
+        let journal: Journal = a.create_database(&mut wtxn, Some(JOURNAL_DATABASE_NAME))?;
+        journal.put(&mut wtxn, &7, &Marker { nonce: 1, participants: 2, index: 0 }.encode())?;
+        journal.put(&mut wtxn, &8, &Marker { nonce: 2, participants: 2, index: 0 }.encode())?;
+        wtxn.commit()?;
+        let mut wtxn = b.write_txn()?;
+        let journal: Journal = b.create_database(&mut wtxn, Some(JOURNAL_DATABASE_NAME))?;
+        journal.put(&mut wtxn, &8, &Marker { nonce: 2, participants: 2, index: 1 }.encode())?;
+        wtxn.commit()?;
+
+        let partial_commits = MultiEnvTxn::recover(&[&a, &b])?;
+        assert_eq!(partial_commits.len(), 1);
+        let partial = &partial_commits[0];
+        assert_eq!((partial.id, partial.participants, &partial.committed[..]), (7, 2, &[0][..]));
+        MultiEnvTxn::resolve(&[&a, &b], partial)?;
+        assert!(MultiEnvTxn::recover(&[&a, &b])?.is_empty());
+
+        // The next id follows the ids of the journals.
+        let txn = MultiEnvTxn::begin(&[&b])?;
+        assert_eq!(txn.commit()?, 1);
+        let mut txn = MultiEnvTxn::begin(&[&a, &b])?;
+        let journal: Journal = a.open_database(txn.txn(0), Some(JOURNAL_DATABASE_NAME))?.unwrap();
+        journal.put(txn.txn(0), &41, &Marker { nonce: 3, participants: 1, index: 0 }.encode())?;
+        assert_eq!(txn.commit()?, 42);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::envs::EnvInner;
+use crate::mdb::ffi;
+use crate::{Env, ReadTxn};
+
+/// Receives the metrics of the transactions of an environment, see [`Env::set_observer`].
+///
+/// The methods are called on the thread that ends the transaction and do nothing by default.
+/// They should be quick as they delay the next write transaction.
+pub trait EnvObserver: Send + Sync {
+    /// Called after a write transaction, or one of its [checkpoints](crate::RwTxn::checkpoint),
+    /// is committed.
+    fn write_txn_committed(&self, stats: &WriteTxnStats) {
+        let _ = stats;
+    }
+
+    /// Called when a read transaction is committed, dropped or put back in the cache
+    /// of [`Env::cached_read_txn`].
+    fn read_txn_ended(&self, stats: &ReadTxnStats) {
+        let _ = stats;
+    }
+}
+
+/// The metrics of a committed write transaction.
+///
+/// The bytes are the sum of the keys and values read and written through the databases
+/// and cursors of the transaction, including its nested transactions.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct WriteTxnStats {
+    /// The identifier of the transaction.
+    pub txn_id: u64,
+    /// The time elapsed between the beginning of the transaction and the end of the commit.
+    pub duration: Duration,
+    /// The time spent committing the transaction.
+    pub commit_latency: Duration,
+    /// The number of bytes read.
+    pub bytes_read: u64,
+    /// The number of bytes written.
+    pub bytes_written: u64,
+    /// The part of the map used after the commit, between zero and one.
+    pub map_fill: f64,
+    /// The number of reader slots used in the environment after the commit.
+    pub readers: u32,
+}
+
+/// The metrics of an ended read transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReadTxnStats {
+    /// The identifier of the transaction, the one of the snapshot it read.
+    pub txn_id: u64,
+    /// The time elapsed between the beginning and the end of the transaction.
+    pub duration: Duration,
+    /// The number of bytes of the keys and values read.
+    pub bytes_read: u64,
+}
+
+impl<T> Env<T> {
+    /// Hands the metrics of the transactions to the observer, replacing the previous one.
+    ///
+    /// Only the transactions started afterward are observed.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use heed::types::*;
+    /// use heed::{EnvObserver, WriteTxnStats};
+    ///
+    /// #[derive(Default)]
+    /// struct BytesWritten(AtomicU64);
+    ///
+    /// impl EnvObserver for BytesWritten {
+    ///     fn write_txn_committed(&self, stats: &WriteTxnStats) {
+    ///         self.0.fetch_add(stats.bytes_written, Ordering::Relaxed);
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let observer = Arc::new(BytesWritten::default());
+    /// env.set_observer(observer.clone());
+    ///
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+    /// db.put(&mut wtxn, "kero", "admin")?;
+    /// wtxn.commit()?;
+    ///
+    /// assert_eq!(observer.0.load(Ordering::Relaxed), 9);
+    /// # Ok(()) }
+    /// ```
+    pub fn set_observer(&self, observer: Arc<dyn EnvObserver>) {
+        *self.inner.observer.write().unwrap() = Some(observer);
+    }
+
+    /// Stops handing the metrics of the transactions started afterward to the observer.
+    pub fn remove_observer(&self) {
+        *self.inner.observer.write().unwrap() = None;
+    }
+}
+
+/// The metrics gathered by an observed transaction.
+pub struct TxnObservation {
+    observer: Arc<dyn EnvObserver>,
+    txn_id: u64,
+    began: Instant,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl TxnObservation {
+    /// Starts observing a transaction if the environment has an observer.
+    pub(crate) fn start(env: &EnvInner, txn: NonNull<ffi::MDB_txn>) -> Option<Arc<TxnObservation>> {
+        let observer = env.observer.read().unwrap().clone()?;
+        Some(Arc::new(TxnObservation {
+            observer,
+            txn_id: unsafe { ffi::mdb_txn_id(txn.as_ptr()) } as u64,
+            began: Instant::now(),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+        }))
+    }
+
+    pub(crate) fn read(&self, key: &[u8], data: &[u8]) {
+        self.bytes_read.fetch_add((key.len() + data.len()) as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn written(&self, key: &[u8], data_len: usize) {
+        self.bytes_written.fetch_add((key.len() + data_len) as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn read_txn_ended(&self) {
+        self.observer.read_txn_ended(&ReadTxnStats {
+            txn_id: self.txn_id,
+            duration: self.began.elapsed(),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+        });
+    }
+
+    pub(crate) fn write_txn_committed(&self, env: NonNull<ffi::MDB_env>, commit_latency: Duration) {
+        let mut info = mem::MaybeUninit::uninit();
+        let mut stat = mem::MaybeUninit::uninit();
+        let (info, stat) = unsafe {
+            ffi::mdb_env_info(env.as_ptr(), info.as_mut_ptr());
+            ffi::mdb_env_stat(env.as_ptr(), stat.as_mut_ptr());
+            (info.assume_init(), stat.assume_init())
+        };
+        let used = (info.me_last_pgno + 1) as f64 * stat.ms_psize as f64;
+
+        self.observer.write_txn_committed(&WriteTxnStats {
+            txn_id: self.txn_id,
+            duration: self.began.elapsed(),
+            commit_latency,
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            map_fill: (used / info.me_mapsize as f64).min(1.0),
+            readers: info.me_numreaders,
+        });
+    }
+}
+
+/// Records the bytes of a key and a value read by the transaction, if it is observed.
+pub(crate) fn record_read<T: ReadTxn + ?Sized>(txn: &T, key: &[u8], data: &[u8]) {
+    if let Some(observation) = txn.observation() {
+        observation.read(key, data);
+    }
+}
+
+/// Records the bytes of a key and a value written by the transaction, if it is observed.
+pub(crate) fn record_written<T: ReadTxn + ?Sized>(txn: &T, key: &[u8], data_len: usize) {
+    if let Some(observation) = txn.observation() {
+        observation.written(key, data_len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::types::Str;
+    use crate::{EnvOpenOptions, Result};
+
+    #[derive(Default)]
+    struct Recorder {
+        writes: Mutex<Vec<WriteTxnStats>>,
+        reads: Mutex<Vec<ReadTxnStats>>,
+    }
+
+    impl EnvObserver for Recorder {
+        fn write_txn_committed(&self, stats: &WriteTxnStats) {
+            self.writes.lock().unwrap().push(stats.clone());
+        }
+
+        fn read_txn_ended(&self, stats: &ReadTxnStats) {
+            self.reads.lock().unwrap().push(stats.clone());
+        }
+    }
+
+    #[test]
+    fn observed_txns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let recorder = Arc::new(Recorder::default());
+        env.set_observer(recorder.clone());
+
+        let mut wtxn = env.write_txn()?;
+        let db = env.create_database::<Str, Str>(&mut wtxn, Some("words"))?;
+        db.put(&mut wtxn, "hello", "world")?;
+        let mut nested = env.nested_write_txn(&mut wtxn)?;
+        db.put(&mut nested, "bonjour", "monde")?;
+        nested.commit()?;
+        assert_eq!(db.get(&wtxn, "hello")?, Some("world"));
+        let txn_id = wtxn.id() as u64;
+        wtxn.commit()?;
+
+        // Aborted transactions are not reported.
+        let mut wtxn = env.write_txn()?;
+        db.put(&mut wtxn, "hola", "mundo")?;
+        wtxn.abort();
+
+        let rtxn = env.read_txn()?;
+        assert_eq!(db.iter(&rtxn)?.count(), 2);
+        drop(rtxn);
+
+        let writes = recorder.writes.lock().unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].txn_id, txn_id);
+        assert_eq!(writes[0].bytes_written, 22);
+        assert_eq!(writes[0].bytes_read, 10);
+        assert!(writes[0].map_fill > 0.0 && writes[0].map_fill <= 1.0);
+
+        let reads = recorder.reads.lock().unwrap();
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads[0].txn_id, txn_id);
+        assert_eq!(reads[0].bytes_read, 22);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! The copies of the values returned by the transactions with the `paranoid-copies` feature,
+//! and by the read halves of the split write transactions of the environments opened with
+//! [`EnvFlags::WRITE_MAP`].
+//!
+//! The keys and values are normally borrowed from the memory map and stay readable after
+//! the end of their transaction, or change under the reader when their page is reused. With
+//! the feature, they are copied in buffers freed when the transaction ends, so that a tool
+//! like AddressSanitizer reports the values used longer than their transaction. With a
+//! writable memory map, the writes modify the pages in place, the values read by the
+//! [`ReadHalf`](crate::ReadHalf) of a split transaction are therefore copied to not change
+//! under the reader.
+//!
+//! With the `quarantine-values` feature, the copies are also overwritten and freed at every
+//! write through the [`WriteHalf`](crate::WriteHalf) of a split transaction. The values still
+//! used by the read half are then reported by AddressSanitizer or Miri, whether LMDB reuses
+//! their pages or not.
+
+use std::sync::Mutex;
+#[cfg(feature = "quarantine-values")]
+use std::{hint, mem};
+
+#[allow(unused)] // for cargo auto doc links
+use crate::EnvFlags;
+use crate::ReadTxn;
+
+/// The byte written over the copies before they are freed.
+#[cfg(feature = "quarantine-values")]
+const POISON: u8 = 0xa5;
+
+/// The buffers holding the copies of the values returned by a transaction.
+#[derive(Default)]
+pub struct ValueCopies {
+    copies: Mutex<Vec<Box<[u8]>>>,
+}
+
+impl ValueCopies {
+    /// Copies the bytes in a buffer that lives as long as these copies.
+    pub(crate) fn copy<'a>(&'a self, bytes: &[u8]) -> &'a [u8] {
+        let copy = Box::<[u8]>::from(bytes);
+        let (ptr, len) = (copy.as_ptr(), copy.len());
+        self.copies.lock().unwrap().push(copy);
+        // SAFETY: The buffer is on the heap and is never modified nor freed before the copies.
+        unsafe { std::slice::from_raw_parts(ptr, len) }
+    }
+
+    /// Overwrites and frees the copies, called when a write invalidates the values.
+    ///
+    /// The values still borrowed from the copies dangle on purpose, the feature is only meant
+    /// for the tests to report the values used after being invalidated.
+    #[cfg(feature = "quarantine-values")]
+    pub(crate) fn quarantine(&self) {
+        let copies = mem::take(&mut *self.copies.lock().unwrap());
+        for mut copy in copies {
+            copy.fill(POISON);
+            hint::black_box(&copy);
+        }
+    }
+
+    #[cfg(all(test, feature = "quarantine-values"))]
+    fn len(&self) -> usize {
+        self.copies.lock().unwrap().len()
+    }
+}
+
+/// Returns the bytes read by the transaction, copied in a buffer freed at the end of the
+/// transaction if it copies its values.
+pub(crate) fn copy_value<'a, T: ReadTxn + ?Sized>(txn: &'a T, bytes: &'a [u8]) -> &'a [u8] {
+    match txn.value_copies() {
+        Some(copies) => copies.copy(bytes),
+        None => bytes,
+    }
+}
+
+#[cfg(all(test, feature = "paranoid-copies"))]
+mod tests {
+    use crate::types::Str;
+    use crate::{Database, EnvOpenOptions, Result};
+
+    #[test]
+    fn values_are_copied() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, None)?;
+        db.put(&mut wtxn, "hello", "world")?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let value = db.get(&rtxn, "hello")?.unwrap();
+        let (key, data) = db.iter(&rtxn)?.next().unwrap()?;
+        assert_eq!((key, data, value), ("hello", "world", "world"));
+        // Every read returns a new copy.
+        assert!(!std::ptr::eq(value, data));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "quarantine-values")]
+    fn values_are_quarantined() -> Result<()> {
+        use crate::ReadTxn;
+
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src"))?;
+        let dst: Database<Str, Str> = env.create_database(&mut wtxn, Some("dst"))?;
+        src.put(&mut wtxn, "hello", "world")?;
+
+        {
+            let (read, mut write) = wtxn.split();
+            let value = src.get(&read, "hello")?.unwrap().to_owned();
+            assert_eq!(read.value_copies().unwrap().len(), 1);
+            dst.put(&mut write, "hello", &value)?;
+            assert_eq!(read.value_copies().unwrap().len(), 0);
+        }
+
+        // The writes through the transaction itself can't invalidate borrowed values.
+        assert_eq!(src.get(&wtxn, "hello")?, Some("world"));
+        dst.put(&mut wtxn, "hello", "there")?;
+        assert_eq!(wtxn.value_copies().unwrap().len(), 1);
+        Ok(())
+    }
+}
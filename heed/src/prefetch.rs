@@ -0,0 +1,45 @@
+use std::ffi::c_void;
+use std::ops::Range;
+
+/// The readahead of the pages of a scan, see [`RoIter::prefetch`].
+///
+/// The pages of the B-tree written in order are mostly contiguous in the map, the pages
+/// following the entry read by the cursor are advised as soon as it leaves the window of
+/// the pages advised before, so that the operating system reads a batch of pages while the
+/// entries of the previous batch are decoded.
+///
+/// [`RoIter::prefetch`]: crate::RoIter::prefetch
+pub(crate) struct Prefetch {
+    /// The length of the advised windows, in bytes.
+    len: usize,
+    page_size: usize,
+    /// Whether the scan reads the entries in reverse order.
+    backward: bool,
+    /// The addresses of the pages advised last.
+    window: Range<usize>,
+}
+
+impl Prefetch {
+    pub(crate) fn new(pages: usize, backward: bool) -> Prefetch {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as usize;
+        Prefetch { len: pages.max(1) * page_size, page_size, backward, window: 0..0 }
+    }
+
+    /// Advises the pages following the given bytes, in the order of the scan, if they are
+    /// not in the window advised last.
+    pub(crate) fn touch(&mut self, bytes: &[u8]) {
+        let address = bytes.as_ptr() as usize;
+        if self.window.contains(&address) {
+            return;
+        }
+
+        let page = address / self.page_size * self.page_size;
+        let start =
+            if self.backward { page.saturating_sub(self.len - self.page_size) } else { page };
+        self.window = start..start.saturating_add(self.len);
+
+        // The window can go past the end of the map, or the entries be in the pages of a
+        // write transaction, the advice is a hint and its failures are of no consequence.
+        unsafe { libc::madvise(start as *mut c_void, self.len, libc::MADV_WILLNEED) };
+    }
+}
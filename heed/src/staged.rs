@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::{fmt, marker, mem, slice};
+
+use crate::databases::key_buffer::encode_key;
+use crate::mdb::ffi;
+use crate::types::{Bytes, DecodeIgnore};
+use crate::*;
+
+/// The raw keys and values staged in a database, `None` for the deletions.
+type StagedEntries = Vec<StagedEntry>;
+
+type StagedEntry = (Vec<u8>, Option<Vec<u8>>);
+
+/// A read transaction with an in-memory buffer of writes, read back by its gets and iterators.
+///
+/// A request handler can stage its changes while reading a snapshot and only take the writer
+/// lock of the environment to [apply](Self::apply) them at the end. The staged changes are
+/// kept sorted with the comparator of their database and merged with the entries of the
+/// snapshot. There is no conflict detection: the changes are applied as they are, replacing
+/// the writes committed since the snapshot.
+///
+/// A put replaces the values of the key, the databases with duplicates are not supported.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::types::*;
+/// use heed::StagedTxn;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, Str>(&mut wtxn, Some("carts"))?;
+/// db.put(&mut wtxn, "kero", "apple")?;
+/// wtxn.commit()?;
+///
+/// let mut staged = StagedTxn::new(env.read_txn()?);
+/// staged.put(&db, "tamo", "pear")?;
+/// staged.delete(&db, "kero")?;
+/// assert_eq!(staged.get(&db, "kero")?, None);
+/// let carts: Vec<_> = staged.iter(&db)?.collect::<heed::Result<_>>()?;
+/// assert_eq!(carts, [("tamo", "pear")]);
+///
+/// let mut wtxn = env.write_txn()?;
+/// staged.apply(&mut wtxn)?;
+/// wtxn.commit()?;
+/// # Ok(()) }
+/// ```
+pub struct StagedTxn<'e, T> {
+    rtxn: RoTxn<'e, T>,
+    staged: BTreeMap<ffi::MDB_dbi, (Database<Bytes, Bytes>, StagedEntries)>,
+}
+
+impl<'e, T> StagedTxn<'e, T> {
+    /// Stages writes over the snapshot of the read transaction.
+    pub fn new(rtxn: RoTxn<'e, T>) -> StagedTxn<'e, T> {
+        StagedTxn { rtxn, staged: BTreeMap::new() }
+    }
+
+    /// The read transaction, to read the snapshot without the staged changes.
+    pub fn snapshot(&self) -> &RoTxn<'e, T> {
+        &self.rtxn
+    }
+
+    /// Returns `true` if no change is staged.
+    pub fn is_empty(&self) -> bool {
+        self.staged.values().all(|(_, entries)| entries.is_empty())
+    }
+
+    /// Retrieves the value of a key, the staged one if any, else the one of the snapshot.
+    pub fn get<'a, 'txn, KC, DC, C, CDUP>(
+        &'txn self,
+        db: &Database<KC, DC, C, CDUP>,
+        key: &'a KC::EItem,
+    ) -> Result<Option<DC::DItem>>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesDecode<'txn>,
+        C: Comparator,
+    {
+        db.check_env(&self.rtxn)?;
+        let key = encode_key::<KC>(key)?;
+        let bytes = match self.staged_value::<C>(db.dbi, &key) {
+            Some(staged) => staged,
+            None => db.remap_types::<Bytes, Bytes>().get(&self.rtxn, &key)?,
+        };
+        bytes.map(|bytes| DC::bytes_decode(bytes).map_err(Error::Decoding)).transpose()
+    }
+
+    /// Stages the insertion of an entry, replacing the value of the key.
+    pub fn put<'a, KC, DC, C, CDUP>(
+        &mut self,
+        db: &Database<KC, DC, C, CDUP>,
+        key: &'a KC::EItem,
+        data: &'a DC::EItem,
+    ) -> Result<()>
+    where
+        KC: BytesEncode<'a>,
+        DC: BytesEncode<'a>,
+        C: Comparator,
+    {
+        db.check_env(&self.rtxn)?;
+        let key = encode_key::<KC>(key)?;
+        let data = DC::bytes_encode(data).map_err(Error::Encoding)?;
+        self.stage(db, key.to_vec(), Some(data.into_owned()));
+        Ok(())
+    }
+
+    /// Stages the deletion of a key, returns `true` if it existed in the snapshot
+    /// or was staged.
+    pub fn delete<'a, KC, DC, C, CDUP>(
+        &mut self,
+        db: &Database<KC, DC, C, CDUP>,
+        key: &'a KC::EItem,
+    ) -> Result<bool>
+    where
+        KC: BytesEncode<'a>,
+        C: Comparator,
+    {
+        let existed = self.get(&db.remap_data_type::<DecodeIgnore>(), key)?.is_some();
+        let key = encode_key::<KC>(key)?;
+        self.stage(db, key.to_vec(), None);
+        Ok(existed)
+    }
+
+    /// Returns an iterator over the entries of the snapshot merged with the staged changes,
+    /// ordered by key.
+    pub fn iter<'txn, KC, DC, C, CDUP>(
+        &'txn self,
+        db: &Database<KC, DC, C, CDUP>,
+    ) -> Result<StagedIter<'txn, KC, DC, C>> {
+        let snapshot = db.remap_types::<Bytes, Bytes>().iter(&self.rtxn)?;
+        let staged = self.staged.get(&db.dbi).map_or(&[][..], |(_, entries)| entries);
+        Ok(StagedIter {
+            snapshot,
+            head: None,
+            done: false,
+            staged: staged.iter().peekable(),
+            _marker: marker::PhantomData,
+        })
+    }
+
+    /// Writes the staged changes in the write transaction and empties the buffer.
+    ///
+    /// The buffer is kept if a write fails, the write transaction must then be aborted.
+    pub fn apply(&mut self, wtxn: &mut RwTxn) -> Result<()> {
+        for (db, entries) in self.staged.values() {
+            for (key, value) in entries {
+                match value {
+                    Some(value) => db.put(wtxn, key, value)?,
+                    None => {
+                        db.delete(wtxn, key)?;
+                    }
+                }
+            }
+        }
+        self.staged.clear();
+        Ok(())
+    }
+
+    /// Returns the staged value of the key, `Some(None)` if its deletion is staged.
+    fn staged_value<C: Comparator>(&self, dbi: ffi::MDB_dbi, key: &[u8]) -> Option<Option<&[u8]>> {
+        let (_, entries) = self.staged.get(&dbi)?;
+        let index = entries.binary_search_by(|(staged, _)| C::compare(staged, key)).ok()?;
+        Some(entries[index].1.as_deref())
+    }
+
+    fn stage<KC, DC, C, CDUP>(
+        &mut self,
+        db: &Database<KC, DC, C, CDUP>,
+        key: Vec<u8>,
+        value: Option<Vec<u8>>,
+    ) where
+        C: Comparator,
+    {
+        let (_, entries) = self
+            .staged
+            .entry(db.dbi)
+            .or_insert_with(|| (Database::new(db.env_generation, db.dbi), Vec::new()));
+        match entries.binary_search_by(|(staged, _)| C::compare(staged, &key)) {
+            Ok(index) => entries[index].1 = value,
+            Err(index) => entries.insert(index, (key, value)),
+        }
+    }
+}
+
+impl<T> fmt::Debug for StagedTxn<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let staged: usize = self.staged.values().map(|(_, entries)| entries.len()).sum();
+        f.debug_struct("StagedTxn").field("staged", &staged).finish_non_exhaustive()
+    }
+}
+
+/// An iterator over the entries of a database merged with the staged changes,
+/// returned by [`StagedTxn::iter`].
+pub struct StagedIter<'txn, KC, DC, C = DefaultComparator> {
+    snapshot: RoIter<'txn, Bytes, Bytes>,
+    /// The next entry of the snapshot.
+    head: Option<(&'txn [u8], &'txn [u8])>,
+    done: bool,
+    staged: Peekable<slice::Iter<'txn, StagedEntry>>,
+    _marker: marker::PhantomData<(KC, DC, C)>,
+}
+
+impl<'txn, KC, DC, C: Comparator> StagedIter<'txn, KC, DC, C> {
+    fn step(&mut self) -> Result<Option<(&'txn [u8], &'txn [u8])>> {
+        loop {
+            if self.head.is_none() && !self.done {
+                self.head = self.snapshot.next().transpose()?;
+                self.done = self.head.is_none();
+            }
+            let ordering = match (self.head, self.staged.peek()) {
+                (None, None) => return Ok(None),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (Some((key, _)), Some((staged, _))) => C::compare(key, staged),
+            };
+            if ordering.is_lt() {
+                return Ok(mem::take(&mut self.head));
+            }
+            // The staged change replaces the entry of the snapshot.
+            if ordering.is_eq() {
+                self.head = None;
+            }
+            if let (key, Some(value)) = self.staged.next().unwrap() {
+                return Ok(Some((key, value)));
+            }
+        }
+    }
+}
+
+impl<'txn, KC, DC, C> Iterator for StagedIter<'txn, KC, DC, C>
+where
+    KC: BytesDecode<'txn>,
+    DC: BytesDecode<'txn>,
+    C: Comparator,
+{
+    type Item = Result<(KC::DItem, DC::DItem)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, data) = match self.step().transpose()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let key = KC::bytes_decode(key).map_err(Error::Decoding);
+        let data = DC::bytes_decode(data).map_err(Error::Decoding);
+        Some(key.and_then(|key| data.map(|data| (key, data))))
+    }
+}
+
+impl<KC, DC, C> fmt::Debug for StagedIter<'_, KC, DC, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StagedIter").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+
+    #[test]
+    fn staged_writes_are_read_back() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db = env.create_database::<Str, Str>(&mut wtxn, Some("forward"))?;
+        let reverse = env
+            .database_options()
+            .types::<Str, Str>()
+            .key_comparator::<ReverseComparator>()
+            .name("reverse")
+            .create(&mut wtxn)?;
+        for key in ["a", "c", "e"] {
+            db.put(&mut wtxn, key, key)?;
+            reverse.put(&mut wtxn, key, key)?;
+        }
+        wtxn.commit()?;
+
+        let mut staged = StagedTxn::new(env.read_txn()?);
+        assert!(staged.is_empty());
+        for (key, value) in [("b", "B"), ("c", "C"), ("f", "F")] {
+            staged.put(&db, key, value)?;
+            staged.put(&reverse, key, value)?;
+        }
+        assert!(staged.delete(&db, "a")?);
+        assert!(staged.delete(&db, "f")?);
+        assert!(!staged.delete(&db, "z")?);
+        assert!(staged.delete(&reverse, "e")?);
+
+        assert_eq!(staged.get(&db, "a")?, None);
+        assert_eq!(staged.get(&db, "c")?, Some("C"));
+        assert_eq!(staged.get(&db, "e")?, Some("e"));
+        assert_eq!(db.get(staged.snapshot(), "c")?, Some("c"));
+        let entries: Vec<_> = staged.iter(&db)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [("b", "B"), ("c", "C"), ("e", "e")]);
+        let entries: Vec<_> = staged.iter(&reverse)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [("f", "F"), ("c", "C"), ("b", "B"), ("a", "a")]);
+
+        let mut wtxn = env.write_txn()?;
+        staged.apply(&mut wtxn)?;
+        wtxn.commit()?;
+        assert!(staged.is_empty());
+        drop(staged);
+
+        let rtxn = env.read_txn()?;
+        let entries: Vec<_> = db.iter(&rtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [("b", "B"), ("c", "C"), ("e", "e")]);
+        let entries: Vec<_> = reverse.iter(&rtxn)?.collect::<Result<_>>()?;
+        assert_eq!(entries, [("f", "F"), ("c", "C"), ("b", "B"), ("a", "a")]);
+        Ok(())
+    }
+}
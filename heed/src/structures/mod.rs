@@ -0,0 +1,5 @@
+//! Data structures stored in the databases of an environment.
+
+mod queue;
+
+pub use self::queue::Queue;
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use byteorder::BigEndian;
+
+use crate::meta::Sequence;
+use crate::types::{Bytes, U64};
+use crate::*;
+
+/// A durable FIFO queue, whose items are pushed and popped in write transactions.
+///
+/// The items are stored in a named database under big-endian ids allocated by a [`Sequence`]
+/// of the metadata database, named after the queue. The ids only increase, even after the
+/// queue is emptied or the program restarts, and an id is never reused. The items are appended
+/// with [`PutFlags::APPEND`], an id lower than the last one fails with [`MdbError::KeyExist`]
+/// instead of breaking the order of the queue.
+///
+/// An item popped in a write transaction that is aborted, or interrupted by a crash, is still
+/// in the queue: consuming an item and recording its effects in the same transaction processes
+/// it exactly once.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::byteorder::BigEndian;
+/// use heed::structures::Queue;
+/// use heed::types::*;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// let mut wtxn = env.write_txn()?;
+/// // The documents to index, by id.
+/// let to_index: Queue<U32<BigEndian>> = Queue::create(&env, &mut wtxn, "to-index")?;
+/// to_index.push(&mut wtxn, &42)?;
+/// to_index.push(&mut wtxn, &7)?;
+/// to_index.push(&mut wtxn, &13)?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// assert_eq!(to_index.peek(&rtxn)?, Some((0, 42)));
+/// drop(rtxn);
+///
+/// let mut wtxn = env.write_txn()?;
+/// assert_eq!(to_index.pop(&mut wtxn)?, Some((0, 42)));
+/// assert_eq!(to_index.pop_batch(&mut wtxn, 10)?, [(1, 7), (2, 13)]);
+/// assert!(to_index.is_empty(&wtxn)?);
+/// wtxn.commit()?;
+/// # Ok(()) }
+/// ```
+pub struct Queue<DC, T = WithTls> {
+    db: Database<U64<BigEndian>, DC>,
+    sequence: Sequence<T>,
+}
+
+impl<DC, T> Queue<DC, T> {
+    /// Opens the queue stored in the named database, creating the database if it doesn't exist.
+    pub fn create(env: &Env<T>, wtxn: &mut impl WriteTxn, name: &str) -> Result<Queue<DC, T>>
+    where
+        DC: 'static,
+    {
+        let db = env.create_database(wtxn, Some(name))?;
+        Ok(Queue { db, sequence: env.sequence(&format!("queue:{name}")) })
+    }
+
+    /// The database of the items, by id.
+    pub fn database(&self) -> Database<U64<BigEndian>, DC> {
+        self.db
+    }
+
+    /// Appends an item at the end of the queue and returns its id.
+    ///
+    /// Fails with [`MdbError::Incompatible`] once the `u64` ids are exhausted.
+    pub fn push<'a>(&self, wtxn: &mut impl WriteTxn, item: &'a DC::EItem) -> Result<u64>
+    where
+        DC: BytesEncode<'a>,
+    {
+        let data = DC::bytes_encode(item).map_err(Error::Encoding)?;
+        let id = self.sequence.next(wtxn)?;
+        self.db.remap_data_type::<Bytes>().put_with_flags(wtxn, PutFlags::APPEND, &id, &data)?;
+        Ok(id)
+    }
+
+    /// Returns the item at the front of the queue, without removing it.
+    pub fn peek<'txn>(&self, txn: &'txn impl ReadTxn) -> Result<Option<(u64, DC::DItem)>>
+    where
+        DC: BytesDecode<'txn>,
+    {
+        self.db.first(txn)
+    }
+
+    /// Removes the item at the front of the queue and returns it.
+    pub fn pop(&self, wtxn: &mut impl WriteTxn) -> Result<Option<(u64, DC::DItem)>>
+    where
+        DC: BytesDecodeOwned,
+    {
+        Ok(self.pop_batch(wtxn, 1)?.pop())
+    }
+
+    /// Removes up to `max` items from the front of the queue and returns them, in order.
+    pub fn pop_batch(&self, wtxn: &mut impl WriteTxn, max: usize) -> Result<Vec<(u64, DC::DItem)>>
+    where
+        DC: BytesDecodeOwned,
+    {
+        let mut items = Vec::new();
+        for result in self.db.remap_data_type::<Bytes>().iter(wtxn)?.take(max) {
+            let (id, bytes) = result?;
+            items.push((id, DC::bytes_decode_owned(bytes).map_err(Error::Decoding)?));
+        }
+        if let Some(&(last, _)) = items.last() {
+            self.db.delete_range(wtxn, &(..=last))?;
+        }
+        Ok(items)
+    }
+
+    /// Returns the number of items in the queue.
+    pub fn len(&self, txn: &impl ReadTxn) -> Result<u64> {
+        self.db.len(txn)
+    }
+
+    /// Returns `true` if the queue has no items.
+    pub fn is_empty(&self, txn: &impl ReadTxn) -> Result<bool> {
+        self.db.is_empty(txn)
+    }
+}
+
+impl<DC, T> fmt::Debug for Queue<DC, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("db", &self.db)
+            .field("sequence", &self.sequence.name())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::U32;
+
+    #[test]
+    fn fifo_across_transactions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let queue: Queue<U32<BigEndian>> = Queue::create(&env, &mut wtxn, "queue")?;
+        for i in 0..5 {
+            assert_eq!(queue.push(&mut wtxn, &(i * 10))?, i as u64);
+        }
+        wtxn.commit()?;
+
+        // The items popped in an aborted transaction are still in the queue.
+        let mut wtxn = env.write_txn()?;
+        assert_eq!(queue.pop_batch(&mut wtxn, 3)?, [(0, 0), (1, 10), (2, 20)]);
+        wtxn.abort();
+        let mut wtxn = env.write_txn()?;
+        assert_eq!(queue.pop(&mut wtxn)?, Some((0, 0)));
+        assert_eq!(queue.pop_batch(&mut wtxn, 0)?, []);
+        assert_eq!(queue.len(&wtxn)?, 4);
+        assert_eq!(queue.pop_batch(&mut wtxn, 10)?.len(), 4);
+        assert_eq!(queue.pop(&mut wtxn)?, None);
+        wtxn.commit()?;
+
+        // The ids keep increasing once the queue is empty, also with another handle.
+        let mut wtxn = env.write_txn()?;
+        let queue: Queue<U32<BigEndian>> = Queue::create(&env, &mut wtxn, "queue")?;
+        assert_eq!(queue.push(&mut wtxn, &7)?, 5);
+        assert_eq!(queue.peek(&wtxn)?, Some((5, 7)));
+
+        // An id lower than the last one is refused.
+        queue.database().put(&mut wtxn, &100, &0)?;
+        assert!(matches!(queue.push(&mut wtxn, &8), Err(Error::Mdb(MdbError::KeyExist))));
+        Ok(())
+    }
+}
@@ -0,0 +1,552 @@
+//! A harness killing a process writing to an environment and checking what survived.
+//!
+//! Every round forks a writer process committing transactions to the [`CRASH_DATABASE_NAME`]
+//! database and reporting every successful commit to the harness through a pipe. The harness
+//! kills it after a random delay with `SIGKILL`, or the writer is killed at one of its commits
+//! or syncs by a [`Fault::Crash`]. The harness then reopens the environment and checks that:
+//!
+//! - the environment opens and [`Env::verify`] finds no problem,
+//! - every reported commit survived, at most one commit wasn't reported yet,
+//! - the entries are exactly the ones written by the surviving transactions.
+//!
+//! The transactions are generated from the seed, the harness replays them to know the expected
+//! entries. A killed process keeps the writes it handed to the system: the harness measures
+//! what a crash of the program risks with the [`EnvFlags`] of the environment, not a crash of
+//! the system or a power loss, which can lose the unsynced transactions of an environment
+//! opened with [`EnvFlags::NO_SYNC`] or [`EnvFlags::NO_META_SYNC`].
+//!
+//! ```
+//! use std::time::Duration;
+//! use heed::testing::crash::CrashOptions;
+//! use heed::EnvFlags;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let mut options = CrashOptions::new();
+//! options.seed(7).rounds(10).txns(50).kill_within(Duration::from_millis(10));
+//! unsafe { options.flags(EnvFlags::NO_SYNC) };
+//!
+//! let report = options.run(dir.path())?;
+//! report.assert_ok();
+//! assert_eq!(report.interrupted + report.completed, 10);
+//! # Ok(()) }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::FromRawFd;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::time::Duration;
+use std::{fmt, thread};
+
+use super::faults::{Boundary, Fault, FaultInjector};
+use super::SplitMix64;
+use crate::byteorder::BE;
+use crate::types::{Bytes, U64};
+use crate::verify::{Problem, VerifyOptions};
+use crate::{Database, Env, EnvFlags, EnvOpenOptions, Result, RoTxn};
+
+/// The name of the database written by the harness, it is cleared when the harness starts.
+pub const CRASH_DATABASE_NAME: &str = "__heed_crash";
+
+/// The key holding the number of the last committed transaction.
+const LAST_TXN_KEY: u64 = u64::MAX;
+
+type CrashDatabase = Database<U64<BE>, Bytes>;
+
+/// Options to configure and run a crash test, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct CrashOptions {
+    seed: u64,
+    rounds: usize,
+    txns: u64,
+    ops_per_txn: usize,
+    key_space: u64,
+    max_value_len: usize,
+    sync_every: u64,
+    kill_within: Duration,
+    crash_faults: bool,
+    flags: EnvFlags,
+    map_size: usize,
+}
+
+impl Default for CrashOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrashOptions {
+    /// Creates the options of twenty rounds of a hundred transactions of sixteen operations,
+    /// killed within twenty milliseconds, in an environment of 100 MiB without flags.
+    pub fn new() -> Self {
+        CrashOptions {
+            seed: 0,
+            rounds: 20,
+            txns: 100,
+            ops_per_txn: 16,
+            key_space: 1000,
+            max_value_len: 256,
+            sync_every: 8,
+            kill_within: Duration::from_millis(20),
+            crash_faults: true,
+            flags: EnvFlags::empty(),
+            map_size: 100 * 1024 * 1024,
+        }
+    }
+
+    /// Sets the seed the transactions, the delays and the crashes are generated from.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of writer processes killed one after the other.
+    pub fn rounds(&mut self, rounds: usize) -> &mut Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Sets the number of transactions a writer commits if it isn't killed before.
+    pub fn txns(&mut self, txns: u64) -> &mut Self {
+        self.txns = txns.max(1);
+        self
+    }
+
+    /// Sets the number of puts and deletes of every transaction.
+    pub fn ops_per_txn(&mut self, ops: usize) -> &mut Self {
+        self.ops_per_txn = ops;
+        self
+    }
+
+    /// Sets the number of distinct keys, a thousand by default.
+    pub fn key_space(&mut self, keys: u64) -> &mut Self {
+        self.key_space = keys.max(1);
+        self
+    }
+
+    /// Sets the maximum length of the values, 256 bytes by default.
+    /// The values are at least sixteen bytes long.
+    pub fn max_value_len(&mut self, len: usize) -> &mut Self {
+        self.max_value_len = len;
+        self
+    }
+
+    /// Sets the number of transactions between the calls to [`Env::force_sync`],
+    /// eight by default, zero to never call it.
+    pub fn sync_every(&mut self, txns: u64) -> &mut Self {
+        self.sync_every = txns;
+        self
+    }
+
+    /// Sets the maximum delay before a writer is killed.
+    pub fn kill_within(&mut self, delay: Duration) -> &mut Self {
+        self.kill_within = delay;
+        self
+    }
+
+    /// Sets whether half of the writers are killed at a random commit or sync by a
+    /// [`Fault::Crash`] instead of after a delay, enabled by default.
+    pub fn crash_faults(&mut self, enabled: bool) -> &mut Self {
+        self.crash_faults = enabled;
+        self
+    }
+
+    /// Sets the flags the environment is opened with.
+    ///
+    /// # Safety
+    ///
+    /// The flags must be safe to use, see [`EnvOpenOptions::flags`].
+    pub unsafe fn flags(&mut self, flags: EnvFlags) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the size of the memory map, 100 MiB by default.
+    pub fn map_size(&mut self, size: usize) -> &mut Self {
+        self.map_size = size;
+        self
+    }
+
+    /// Runs the rounds against the environment of the directory, created if needed,
+    /// and reports the violated invariants.
+    ///
+    /// The harness forks the current process, the environment must not be opened by it
+    /// while the harness runs. The environment must allow one more named database.
+    ///
+    /// Fails with the errors of the harness, the errors of the writers are reported as
+    /// [`Violation::WriterFailed`]. The rounds stop at the first environment that
+    /// can't be opened anymore.
+    pub fn run(&self, path: impl AsRef<Path>) -> Result<CrashReport> {
+        let path = path.as_ref();
+        let env = self.open(path)?;
+        let mut wtxn = env.write_txn()?;
+        let db: CrashDatabase = env.create_database(&mut wtxn, Some(CRASH_DATABASE_NAME))?;
+        db.clear(&mut wtxn)?;
+        wtxn.commit()?;
+        env.prepare_for_closing().wait();
+
+        let mut rng = SplitMix64(self.seed);
+        let mut model = Model { entries: BTreeMap::new(), txn: 0 };
+        let mut report = CrashReport::default();
+        for round in 0..self.rounds {
+            let crash = self.crash_faults && rng.below(2) == 0;
+            let crash = crash.then(|| {
+                if self.sync_every > 0 && rng.below(2) == 0 {
+                    let syncs = (self.txns / self.sync_every).max(1);
+                    (Boundary::Sync, 1 + rng.below(syncs) as usize)
+                } else {
+                    (Boundary::Commit, 1 + rng.below(self.txns) as usize)
+                }
+            });
+            let delay = rng.below(self.kill_within.as_micros() as u64 + 1);
+
+            let (mut reader, writer) = pipe()?;
+            match unsafe { libc::fork() } {
+                -1 => return Err(std::io::Error::last_os_error().into()),
+                0 => {
+                    drop(reader);
+                    let result =
+                        catch_unwind(AssertUnwindSafe(|| self.writer(path, writer, crash)));
+                    let code = if matches!(result, Ok(Ok(()))) { 0 } else { 1 };
+                    unsafe { libc::_exit(code) }
+                }
+                pid => {
+                    drop(writer);
+                    thread::sleep(Duration::from_micros(delay));
+                    let mut status = 0;
+                    unsafe {
+                        libc::kill(pid, libc::SIGKILL);
+                        libc::waitpid(pid, &mut status, 0);
+                    }
+                    if libc::WIFSIGNALED(status) {
+                        report.interrupted += 1;
+                    } else if libc::WEXITSTATUS(status) == 0 {
+                        report.completed += 1;
+                    } else {
+                        let status = libc::WEXITSTATUS(status);
+                        report.violations.push(Violation::WriterFailed { round, status });
+                    }
+                }
+            }
+
+            let mut acks = Vec::new();
+            reader.read_to_end(&mut acks)?;
+            let acknowledged = match acks.rchunks_exact(8).next() {
+                Some(last) => u64::from_be_bytes(last.try_into().unwrap()),
+                None => model.txn,
+            };
+            report.acknowledged += acks.len() as u64 / 8;
+
+            if !self.check(path, round, acknowledged, &mut model, &mut report)? {
+                break;
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn open(&self, path: &Path) -> Result<Env> {
+        unsafe {
+            EnvOpenOptions::new().map_size(self.map_size).max_dbs(1).flags(self.flags).open(path)
+        }
+    }
+
+    /// Commits the transactions following the last committed one, in the forked process,
+    /// and writes the number of every committed transaction into the pipe.
+    fn writer(&self, path: &Path, mut acks: File, crash: Option<(Boundary, usize)>) -> Result<()> {
+        let env = self.open(path)?;
+        if let Some((boundary, n)) = crash {
+            let faults = FaultInjector::new();
+            faults.fail_nth(boundary, n, Fault::Crash);
+            env.set_fault_injector(Some(faults));
+        }
+
+        let rtxn = env.read_txn()?;
+        let db: CrashDatabase = env.open_database(&rtxn, Some(CRASH_DATABASE_NAME))?.unwrap();
+        let last = last_txn(&db, &rtxn)?;
+        drop(rtxn);
+
+        for txn in last + 1..=last + self.txns {
+            let mut wtxn = env.write_txn()?;
+            for (key, value) in self.operations(txn) {
+                match value {
+                    Some(value) => db.put(&mut wtxn, &key, &value)?,
+                    None => {
+                        db.delete(&mut wtxn, &key)?;
+                    }
+                }
+            }
+            db.put(&mut wtxn, &LAST_TXN_KEY, &txn.to_be_bytes())?;
+            wtxn.commit()?;
+            acks.write_all(&txn.to_be_bytes())?;
+
+            if self.sync_every > 0 && (txn - last) % self.sync_every == 0 {
+                env.force_sync()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the puts and deletes of the transaction, which only depend on the seed and
+    /// on the number of the transaction. The values start with the transaction and the key.
+    fn operations(&self, txn: u64) -> Vec<(u64, Option<Vec<u8>>)> {
+        let mut rng = SplitMix64(self.seed ^ txn.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        (0..self.ops_per_txn)
+            .map(|_| {
+                let key = rng.below(self.key_space);
+                if rng.below(4) == 0 {
+                    return (key, None);
+                }
+                let len = 16 + rng.below(self.max_value_len.saturating_sub(16) as u64 + 1);
+                let mut value = Vec::with_capacity(len as usize);
+                value.extend_from_slice(&txn.to_be_bytes());
+                value.extend_from_slice(&key.to_be_bytes());
+                value.resize(len as usize, txn as u8);
+                (key, Some(value))
+            })
+            .collect()
+    }
+
+    /// Reopens the environment after a round and checks the invariants.
+    /// Returns whether the environment could be opened.
+    fn check(
+        &self,
+        path: &Path,
+        round: usize,
+        acknowledged: u64,
+        model: &mut Model,
+        report: &mut CrashReport,
+    ) -> Result<bool> {
+        let env = match self.open(path) {
+            Ok(env) => env,
+            Err(error) => {
+                let error = error.to_string();
+                report.violations.push(Violation::Unrecoverable { round, error });
+                return Ok(false);
+            }
+        };
+
+        let rtxn = env.read_txn()?;
+        let verification = env.verify(&rtxn, &VerifyOptions::new())?;
+        report.violations.extend(
+            verification
+                .problems
+                .into_iter()
+                .map(|problem| Violation::Corrupted { round, problem }),
+        );
+
+        let db: CrashDatabase = env.open_database(&rtxn, Some(CRASH_DATABASE_NAME))?.unwrap();
+        let recovered = last_txn(&db, &rtxn)?;
+        if recovered < acknowledged {
+            report.lost_commits += acknowledged - recovered;
+            report.violations.push(Violation::LostCommits { round, acknowledged, recovered });
+        } else if recovered > acknowledged + 1 {
+            report.violations.push(Violation::UnacknowledgedCommits {
+                round,
+                acknowledged,
+                recovered,
+            });
+        }
+
+        if recovered < model.txn {
+            *model = Model { entries: BTreeMap::new(), txn: 0 };
+        }
+        for txn in model.txn + 1..=recovered {
+            for (key, value) in self.operations(txn) {
+                match value {
+                    Some(value) => model.entries.insert(key, value),
+                    None => model.entries.remove(&key),
+                };
+            }
+        }
+        model.txn = recovered;
+        report.commits = recovered;
+
+        if let Some(key) = model.first_difference(&db, &rtxn)? {
+            report.violations.push(Violation::Mismatch { round, txn: recovered, key });
+        }
+
+        drop(rtxn);
+        env.prepare_for_closing().wait();
+        Ok(true)
+    }
+}
+
+/// The entries written by the transactions up to `txn`.
+struct Model {
+    entries: BTreeMap<u64, Vec<u8>>,
+    txn: u64,
+}
+
+impl Model {
+    /// Returns the first key whose entry differs from the database, if any.
+    fn first_difference(&self, db: &CrashDatabase, rtxn: &RoTxn) -> Result<Option<u64>> {
+        let mut expected = self.entries.iter();
+        for result in db.iter(rtxn)? {
+            let (key, value) = result?;
+            if key == LAST_TXN_KEY {
+                continue;
+            }
+            match expected.next() {
+                Some((&k, v)) if k == key && v == value => (),
+                Some((&k, _)) => return Ok(Some(k.min(key))),
+                None => return Ok(Some(key)),
+            }
+        }
+        Ok(expected.next().map(|(&key, _)| key))
+    }
+}
+
+fn last_txn(db: &CrashDatabase, rtxn: &RoTxn) -> Result<u64> {
+    Ok(db.get(rtxn, &LAST_TXN_KEY)?.map_or(0, |txn| u64::from_be_bytes(txn.try_into().unwrap())))
+}
+
+/// Creates a pipe, returning its read and write ends.
+fn pipe() -> Result<(File, File)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    unsafe { Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))) }
+}
+
+/// The outcome of a crash test, returned by [`CrashOptions::run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrashReport {
+    /// The number of writers killed before committing all their transactions.
+    pub interrupted: usize,
+    /// The number of writers that committed all their transactions before being killed.
+    pub completed: usize,
+    /// The number of commits reported by the writers.
+    pub acknowledged: u64,
+    /// The number of transactions that survived the last round.
+    pub commits: u64,
+    /// The number of reported commits that didn't survive, over all the rounds.
+    pub lost_commits: u64,
+    /// The violated invariants.
+    pub violations: Vec<Violation>,
+}
+
+impl CrashReport {
+    /// Returns whether no invariant was violated.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Panics with the violated invariants, if any.
+    #[track_caller]
+    pub fn assert_ok(&self) {
+        if !self.is_ok() {
+            let violations: Vec<_> = self.violations.iter().map(ToString::to_string).collect();
+            panic!("the crash test found violations:\n{}", violations.join("\n"));
+        }
+    }
+}
+
+/// An invariant violated during a crash test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// The environment couldn't be opened after the writer was killed.
+    Unrecoverable {
+        /// The round of the writer.
+        round: usize,
+        /// The description of the error.
+        error: String,
+    },
+    /// The verification of the environment found a problem.
+    Corrupted {
+        /// The round of the writer.
+        round: usize,
+        /// The problem.
+        problem: Problem,
+    },
+    /// Commits reported by the writer didn't survive.
+    LostCommits {
+        /// The round of the writer.
+        round: usize,
+        /// The last transaction reported.
+        acknowledged: u64,
+        /// The last transaction that survived.
+        recovered: u64,
+    },
+    /// More than the transaction being committed when the writer was killed survived.
+    UnacknowledgedCommits {
+        /// The round of the writer.
+        round: usize,
+        /// The last transaction reported.
+        acknowledged: u64,
+        /// The last transaction that survived.
+        recovered: u64,
+    },
+    /// The entries differ from the ones written by the surviving transactions.
+    Mismatch {
+        /// The round of the writer.
+        round: usize,
+        /// The last transaction that survived.
+        txn: u64,
+        /// The first differing key.
+        key: u64,
+    },
+    /// The writer stopped with an error or a panic.
+    WriterFailed {
+        /// The round of the writer.
+        round: usize,
+        /// The exit status of the writer.
+        status: i32,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Unrecoverable { round, error } => {
+                write!(f, "round {round}: the environment can't be opened: {error}")
+            }
+            Violation::Corrupted { round, problem } => write!(f, "round {round}: {problem}"),
+            Violation::LostCommits { round, acknowledged, recovered } => write!(
+                f,
+                "round {round}: transaction {acknowledged} was committed \
+                 but only transaction {recovered} survived"
+            ),
+            Violation::UnacknowledgedCommits { round, acknowledged, recovered } => write!(
+                f,
+                "round {round}: transaction {acknowledged} was the last committed \
+                 but transaction {recovered} survived"
+            ),
+            Violation::Mismatch { round, txn, key } => {
+                write!(f, "round {round}: the key {key} differs after transaction {txn}")
+            }
+            Violation::WriterFailed { round, status } => {
+                write!(f, "round {round}: the writer exited with the status {status}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_recovery() -> Result<()> {
+        for flags in [EnvFlags::empty(), EnvFlags::NO_SYNC | EnvFlags::NO_META_SYNC] {
+            let dir = tempfile::tempdir()?;
+            let mut options = CrashOptions::new();
+            options.seed(3).rounds(8).txns(40).kill_within(Duration::from_millis(5));
+            unsafe { options.flags(flags) };
+
+            let report = options.run(dir.path())?;
+            report.assert_ok();
+            assert_eq!(report.interrupted + report.completed, 8);
+            assert_eq!(report.lost_commits, 0);
+            assert!(report.commits <= report.acknowledged + 8);
+        }
+
+        Ok(())
+    }
+}
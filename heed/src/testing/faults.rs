@@ -0,0 +1,341 @@
+//! Injection of I/O failures at the begins, commits, syncs and copies of an environment.
+//!
+//! The failures of the disk are hard to reproduce, a [`FaultInjector`] set on an environment
+//! with [`Env::set_fault_injector`] makes the chosen occurrences of these operations fail
+//! with the errors LMDB returns in these cases, to test how a program handles them.
+//!
+//! - A failed begin of a write transaction returns the error from [`Env::write_txn`], or
+//!   from [`RwTxn::checkpoint`](crate::RwTxn::checkpoint) once it committed.
+//! - A failed commit writes nothing, the transaction is aborted.
+//! - A failed sync, [`Env::force_sync`], keeps the committed transactions.
+//! - A failed copy, [`Env::copy_to_file`] or [`Env::copy_to_path`], writes nothing, or
+//!   half of the file with a [`Fault::ShortWrite`].
+//! - A [`Fault::Crash`] kills the process before the operation, see the [`crash`] harness.
+//!
+//! [`crash`]: crate::testing::crash
+//!
+//! ```
+//! use heed::testing::faults::{Boundary, Fault, FaultInjector};
+//! use heed::types::*;
+//! use heed::{EnvOpenOptions, Error};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+//!
+//! let faults = FaultInjector::new();
+//! faults.fail_nth(Boundary::Commit, 2, Fault::NoSpace);
+//! env.set_fault_injector(Some(faults.clone()));
+//!
+//! let mut wtxn = env.write_txn()?;
+//! let db = env.create_database::<Str, U8>(&mut wtxn, Some("counters"))?;
+//! wtxn.commit()?;
+//!
+//! let mut wtxn = env.write_txn()?;
+//! db.put(&mut wtxn, "visits", &1)?;
+//! let error = wtxn.commit().unwrap_err();
+//! assert!(matches!(error, Error::Io(e) if e.kind() == std::io::ErrorKind::StorageFull));
+//!
+//! let rtxn = env.read_txn()?;
+//! assert_eq!(db.get(&rtxn, "visits")?, None);
+//! assert_eq!(faults.count(Boundary::Commit), 2);
+//! # Ok(()) }
+//! ```
+
+use std::fs::File;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use crate::envs::{get_file_fd, EnvInner};
+use crate::{CompactionOption, Env, Result};
+
+/// An operation of an environment at which a fault can be injected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Boundary {
+    /// The begin of a write transaction, or of the new transaction of a checkpoint.
+    /// The nested transactions are not counted.
+    Begin,
+    /// The commit of a write transaction, or of one of its checkpoints.
+    /// The commits of the nested transactions are not counted.
+    Commit,
+    /// A sync of the environment with [`Env::force_sync`].
+    Sync,
+    /// A copy of the environment with [`Env::copy_to_file`] or [`Env::copy_to_path`].
+    Copy,
+}
+
+/// A failure injected at a boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// The disk is full, the operation fails with `ENOSPC`.
+    NoSpace,
+    /// The disk fails, the operation fails with `EIO`.
+    Io,
+    /// The disk accepted a part of the data, the operation fails with `EIO`.
+    /// A copy writes half of the file, the other operations write nothing.
+    ShortWrite,
+    /// The process is killed before the operation, with `SIGKILL` on Unix.
+    Crash,
+}
+
+impl Fault {
+    /// The error LMDB returns for this fault.
+    pub fn to_io_error(self) -> io::Error {
+        match self {
+            Fault::NoSpace => io::Error::from_raw_os_error(libc::ENOSPC),
+            Fault::Io | Fault::ShortWrite | Fault::Crash => io::Error::from_raw_os_error(libc::EIO),
+        }
+    }
+}
+
+/// A fault injected by a [`FaultInjector`], see [`FaultInjector::injected`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InjectedFault {
+    /// The boundary of the fault.
+    pub boundary: Boundary,
+    /// The occurrence of the boundary that failed, starting at one.
+    pub occurrence: usize,
+    /// The fault.
+    pub fault: Fault,
+}
+
+/// Decides which operations of an environment fail, see the [module documentation](self).
+///
+/// The occurrences of every boundary are counted from one once the injector is set
+/// on an environment. The clones share their rules and counts, a clone can be kept
+/// to change the rules or check the counts after setting the injector.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    rules: Vec<Rule>,
+    begins: usize,
+    commits: usize,
+    syncs: usize,
+    copies: usize,
+    injected: Vec<InjectedFault>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    boundary: Boundary,
+    first: usize,
+    repeat: bool,
+    fault: Fault,
+}
+
+impl FaultInjector {
+    /// Creates an injector that injects nothing.
+    pub fn new() -> FaultInjector {
+        FaultInjector::default()
+    }
+
+    /// Makes the `n`th occurrence of the boundary fail.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn fail_nth(&self, boundary: Boundary, n: usize, fault: Fault) -> &Self {
+        self.rule(Rule { boundary, first: n, repeat: false, fault })
+    }
+
+    /// Makes the `n`th occurrence of the boundary fail, and all the following ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn fail_from(&self, boundary: Boundary, n: usize, fault: Fault) -> &Self {
+        self.rule(Rule { boundary, first: n, repeat: true, fault })
+    }
+
+    fn rule(&self, rule: Rule) -> &Self {
+        assert!(rule.first > 0, "the occurrences are counted from one");
+        self.state.lock().unwrap().rules.push(rule);
+        self
+    }
+
+    /// Removes the rules, the next operations succeed. The counts are kept.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().rules.clear();
+    }
+
+    /// Returns the number of occurrences of the boundary, failed or not.
+    pub fn count(&self, boundary: Boundary) -> usize {
+        let mut state = self.state.lock().unwrap();
+        *state.count_mut(boundary)
+    }
+
+    /// Returns the faults injected so far, in the order of the operations.
+    pub fn injected(&self) -> Vec<InjectedFault> {
+        self.state.lock().unwrap().injected.clone()
+    }
+
+    /// Counts an occurrence of the boundary and returns the fault to inject, if any.
+    /// The first rule matching the occurrence wins.
+    fn next(&self, boundary: Boundary) -> Option<Fault> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.count_mut(boundary);
+        *count += 1;
+        let occurrence = *count;
+
+        let fault = state
+            .rules
+            .iter()
+            .find(|rule| {
+                rule.boundary == boundary
+                    && (occurrence == rule.first || (rule.repeat && occurrence > rule.first))
+            })
+            .map(|rule| rule.fault)?;
+        state.injected.push(InjectedFault { boundary, occurrence, fault });
+        Some(fault)
+    }
+}
+
+impl State {
+    fn count_mut(&mut self, boundary: Boundary) -> &mut usize {
+        match boundary {
+            Boundary::Begin => &mut self.begins,
+            Boundary::Commit => &mut self.commits,
+            Boundary::Sync => &mut self.syncs,
+            Boundary::Copy => &mut self.copies,
+        }
+    }
+}
+
+impl EnvInner {
+    /// Counts an occurrence of the boundary and returns the fault to inject, if any.
+    /// Doesn't return if the fault is a crash.
+    pub(crate) fn injected_fault(&self, boundary: Boundary) -> Option<Fault> {
+        let fault = self.fault_injector.read().unwrap().as_ref()?.next(boundary)?;
+        if fault == Fault::Crash {
+            crash();
+        }
+        Some(fault)
+    }
+}
+
+impl<T> Env<T> {
+    /// Sets the injector deciding which begins, commits, syncs and copies of this environment fail,
+    /// replacing the previous one. `None` removes the injector.
+    ///
+    /// For more info, see the [`faults`](crate::testing::faults) module.
+    pub fn set_fault_injector(&self, injector: Option<FaultInjector>) {
+        *self.inner.fault_injector.write().unwrap() = injector;
+    }
+}
+
+/// Kills the process without running the destructors or flushing anything.
+fn crash() -> ! {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(libc::getpid(), libc::SIGKILL);
+    }
+    std::process::abort()
+}
+
+/// Copies the environment into the file like a copy failing with the fault.
+pub(crate) fn faulty_copy<T>(
+    env: &Env<T>,
+    file: &mut File,
+    option: CompactionOption,
+    fault: Fault,
+) -> Result<()> {
+    if fault == Fault::ShortWrite {
+        let start = file.metadata()?.len();
+        unsafe { env.copy_to_fd(get_file_fd(file), option)? };
+        let end = file.metadata()?.len();
+        file.set_len(start + (end - start) / 2)?;
+    }
+    Err(fault.to_io_error().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Str, U8};
+    use crate::{EnvOpenOptions, Error};
+
+    fn is_fault(result: Result<impl Sized>, fault: Fault) -> bool {
+        let expected = fault.to_io_error().raw_os_error();
+        matches!(result, Err(Error::Io(e)) if e.raw_os_error() == expected)
+    }
+
+    #[test]
+    fn injected_faults() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let faults = FaultInjector::new();
+        faults.fail_nth(Boundary::Commit, 2, Fault::Io).fail_from(Boundary::Sync, 2, Fault::Io);
+        faults.fail_nth(Boundary::Copy, 1, Fault::NoSpace);
+        faults.fail_nth(Boundary::Copy, 2, Fault::ShortWrite);
+        env.set_fault_injector(Some(faults.clone()));
+
+        let mut wtxn = env.write_txn()?;
+        let db = env.create_database::<Str, U8>(&mut wtxn, None)?;
+        db.put(&mut wtxn, "a", &1)?;
+        wtxn.commit()?;
+
+        // The nested transactions are not counted.
+        let mut wtxn = env.write_txn()?;
+        let mut nested = env.nested_write_txn(&mut wtxn)?;
+        db.put(&mut nested, "b", &2)?;
+        nested.commit()?;
+        assert!(is_fault(wtxn.commit(), Fault::Io));
+
+        // The failed checkpoint loses its operations, a new transaction begins.
+        let mut wtxn = env.write_txn()?;
+        db.put(&mut wtxn, "c", &3)?;
+        wtxn.checkpoint()?;
+        faults.fail_nth(Boundary::Commit, 4, Fault::NoSpace);
+        db.put(&mut wtxn, "d", &4)?;
+        assert!(is_fault(wtxn.checkpoint(), Fault::NoSpace));
+        db.put(&mut wtxn, "e", &5)?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let keys: Vec<_> = db.iter(&rtxn)?.map(|e| e.map(|(k, _)| k)).collect::<Result<_>>()?;
+        assert_eq!(keys, ["a", "c", "e"]);
+        drop(rtxn);
+
+        env.force_sync()?;
+        assert!(is_fault(env.force_sync(), Fault::Io));
+        assert!(is_fault(env.force_sync(), Fault::Io));
+
+        let copy = tempfile::tempdir()?;
+        let path = copy.path().join("data.mdb");
+        assert!(is_fault(env.copy_to_path(&path, CompactionOption::Disabled), Fault::NoSpace));
+        assert!(!path.exists());
+        let mut file = File::create(&path)?;
+        assert!(is_fault(env.copy_to_file(&mut file, CompactionOption::Disabled), Fault::Io));
+        let short = file.metadata()?.len();
+        assert!(short > 0);
+
+        faults.clear();
+        let mut file = File::create(&path)?;
+        env.copy_to_file(&mut file, CompactionOption::Disabled)?;
+        assert_eq!(file.metadata()?.len(), short * 2);
+
+        assert_eq!(faults.count(Boundary::Commit), 5);
+        assert_eq!(faults.count(Boundary::Sync), 3);
+        assert_eq!(faults.count(Boundary::Copy), 3);
+        let injected: Vec<_> =
+            faults.injected().iter().map(|f| (f.boundary, f.occurrence)).collect();
+        assert_eq!(
+            injected,
+            [
+                (Boundary::Commit, 2),
+                (Boundary::Commit, 4),
+                (Boundary::Sync, 2),
+                (Boundary::Sync, 3),
+                (Boundary::Copy, 1),
+                (Boundary::Copy, 2)
+            ]
+        );
+
+        Ok(())
+    }
+}
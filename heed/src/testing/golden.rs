@@ -0,0 +1,231 @@
+//! Golden snapshots of databases, to catch the changes of their content on disk.
+//!
+//! A snapshot is a canonical text form of the entries of a database, compared with a golden file
+//! checked in with the tests. A change of a codec or of a comparator that changes the bytes or
+//! the order of the entries makes the test fail. When the change is expected, the golden files
+//! are rewritten by running the tests with the [`UPDATE_GOLDEN_VAR`] environment variable set.
+//!
+//! The snapshot starts with the flags and the number of entries of the database, followed by
+//! every key and value on its own line, in the `mdb_dump -p` print format: a space then the
+//! printable ASCII characters as is, backslashes doubled and the other bytes as a backslash
+//! and two hex digits. The parts that change at every run, like the timestamps, can be redacted.
+//!
+//! ```
+//! use heed::testing::golden::Golden;
+//! use heed::types::*;
+//! use heed::EnvOpenOptions;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+//! let mut wtxn = env.write_txn()?;
+//! let db = env.create_database::<Str, Str>(&mut wtxn, Some("logins"))?;
+//! db.put(&mut wtxn, "kero", "2024-05-01T10:00:00Z")?;
+//! db.put(&mut wtxn, "nil\tbis", "2024-05-02T12:30:00Z")?;
+//!
+//! let mut golden = Golden::new();
+//! golden.redact_values(|_, _| Some("[timestamp]".to_string()));
+//! assert_eq!(
+//!     golden.render(&db, &wtxn)?,
+//!     "flags=\nentries=2\n kero\n[timestamp]\n nil\\09bis\n[timestamp]\n",
+//! );
+//!
+//! // Compares with the golden file, written when `HEED_UPDATE_GOLDEN` is set.
+//! # std::fs::write(dir.path().join("logins.golden"), golden.render(&db, &wtxn)?)?;
+//! golden.assert_matches(&db, &wtxn, dir.path().join("logins.golden"))?;
+//! # Ok(()) }
+//! ```
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::databases::dump::{write_printable, DUMP_FLAGS};
+use crate::mdb::lmdb_flags::AllDatabaseFlags;
+use crate::types::Bytes;
+use crate::{Comparator, Database, ReadTxn, Result};
+
+/// The environment variable that makes [`Golden::assert_matches`] write the golden files
+/// instead of comparing them, when it is set to anything but `0`.
+pub const UPDATE_GOLDEN_VAR: &str = "HEED_UPDATE_GOLDEN";
+
+type KeyRedaction = Box<dyn Fn(&[u8]) -> Option<String>>;
+type ValueRedaction = Box<dyn Fn(&[u8], &[u8]) -> Option<String>>;
+
+/// Renders the snapshots of databases and compares them with golden files,
+/// see the [module documentation](self).
+#[derive(Default)]
+pub struct Golden {
+    keys: Option<KeyRedaction>,
+    values: Option<ValueRedaction>,
+}
+
+impl Golden {
+    /// Creates a renderer without redaction.
+    pub fn new() -> Golden {
+        Golden::default()
+    }
+
+    /// Replaces the keys for which the function returns a string by that string,
+    /// written as is on the line of the key.
+    pub fn redact_keys<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&[u8]) -> Option<String> + 'static,
+    {
+        self.keys = Some(Box::new(f));
+        self
+    }
+
+    /// Replaces the values for which the function, given the key and the value, returns
+    /// a string by that string, written as is on the line of the value.
+    pub fn redact_values<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&[u8], &[u8]) -> Option<String> + 'static,
+    {
+        self.values = Some(Box::new(f));
+        self
+    }
+
+    /// Returns the snapshot of the database.
+    pub fn render<KC, DC, C, CDUP>(
+        &self,
+        db: &Database<KC, DC, C, CDUP>,
+        rtxn: &impl ReadTxn,
+    ) -> Result<String>
+    where
+        C: Comparator + 'static,
+    {
+        let flags = AllDatabaseFlags::from_bits_truncate(db.database_flags(rtxn)?.bits());
+        let flags: Vec<_> =
+            DUMP_FLAGS.iter().filter(|(flag, _)| flags.contains(*flag)).map(|(_, n)| *n).collect();
+
+        let mut snapshot = Vec::new();
+        writeln!(snapshot, "flags={}", flags.join(","))?;
+        writeln!(snapshot, "entries={}", db.len(rtxn)?)?;
+        for result in db.remap_types::<Bytes, Bytes>().iter(rtxn)? {
+            let (key, value) = result?;
+            match self.keys.as_ref().and_then(|redact| redact(key)) {
+                Some(redacted) => writeln!(snapshot, "{redacted}")?,
+                None => write_printable(&mut snapshot, key)?,
+            }
+            match self.values.as_ref().and_then(|redact| redact(key, value)) {
+                Some(redacted) => writeln!(snapshot, "{redacted}")?,
+                None => write_printable(&mut snapshot, value)?,
+            }
+        }
+
+        // The printable format writes ASCII characters and the redactions are strings.
+        Ok(String::from_utf8(snapshot).unwrap())
+    }
+
+    /// Compares the snapshot of the database with the golden file.
+    ///
+    /// When the [`UPDATE_GOLDEN_VAR`] environment variable is set, the golden file and its
+    /// directories are written instead. Fails with the errors of the database and the I/O
+    /// errors, except the missing golden file.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the first differing line if the snapshot differs from the golden file,
+    /// or if the golden file is missing.
+    #[track_caller]
+    pub fn assert_matches<KC, DC, C, CDUP>(
+        &self,
+        db: &Database<KC, DC, C, CDUP>,
+        rtxn: &impl ReadTxn,
+        path: impl AsRef<Path>,
+    ) -> Result<()>
+    where
+        C: Comparator + 'static,
+    {
+        let path = path.as_ref();
+        let snapshot = self.render(db, rtxn)?;
+
+        if std::env::var_os(UPDATE_GOLDEN_VAR).is_some_and(|update| update != "0") {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, snapshot)?;
+            return Ok(());
+        }
+
+        let golden = match fs::read_to_string(path) {
+            Ok(golden) => golden,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => panic!(
+                "the golden file {} is missing, \
+                 run the tests with {UPDATE_GOLDEN_VAR}=1 to create it",
+                path.display()
+            ),
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(line) = first_difference(&golden, &snapshot) {
+            let (expected, found) = (golden.lines().nth(line), snapshot.lines().nth(line));
+            panic!(
+                "the snapshot differs from the golden file {} at line {}:\n\
+                 expected: {}\n   found: {}\n\
+                 run the tests with {UPDATE_GOLDEN_VAR}=1 if the change is expected",
+                path.display(),
+                line + 1,
+                expected.unwrap_or("<end of file>"),
+                found.unwrap_or("<end of snapshot>"),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the index of the first line that differs, ignoring the line endings.
+fn first_difference(golden: &str, snapshot: &str) -> Option<usize> {
+    let (mut golden, mut snapshot) = (golden.lines(), snapshot.lines());
+    let mut line = 0;
+    loop {
+        match (golden.next(), snapshot.next()) {
+            (None, None) => return None,
+            (expected, found) if expected != found => return Some(line),
+            _ => line += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestEnv;
+    use crate::DatabaseFlags;
+
+    #[test]
+    fn golden_snapshots() -> Result<()> {
+        let env = TestEnv::builder().build()?;
+        let mut wtxn = env.write_txn()?;
+        let db = env
+            .database_options()
+            .types::<Bytes, Bytes>()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        db.put(&mut wtxn, b"a\\b", b"\x00\xff")?;
+        db.put(&mut wtxn, b"a\\b", b"session-123")?;
+        db.put(&mut wtxn, b"c", b"d")?;
+
+        let mut golden = Golden::new();
+        golden.redact_values(|_, value| value.starts_with(b"session-").then(|| "[id]".into()));
+        let snapshot = golden.render(&db, &wtxn)?;
+        assert_eq!(
+            snapshot,
+            "flags=dupsort\nentries=3\n a\\\\b\n \\00\\ff\n a\\\\b\n[id]\n c\n d\n"
+        );
+
+        let path = env.path().join("golden").join("dups.golden");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, snapshot.replace('\n', "\r\n"))?;
+        golden.assert_matches(&db, &wtxn, &path)?;
+
+        assert_eq!(first_difference("a\nb\n", "a\nb\n"), None);
+        assert_eq!(first_difference("a\nb\n", "a\nc\n"), Some(1));
+        assert_eq!(first_difference("a\n", "a\nb\n"), Some(1));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+//! Utilities to test the programs using heed.
+
+#[cfg(unix)]
+pub mod crash;
+pub mod faults;
+pub mod golden;
+pub mod model;
+pub mod stress;
+mod test_env;
+
+pub use self::test_env::{Distribution, TestEnv, TestEnvBuilder};
+
+/// A small pseudo-random generator, the generated data only needs to be reproducible.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// Returns a number between zero included and one excluded.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
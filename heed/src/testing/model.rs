@@ -0,0 +1,456 @@
+//! A harness running random sequences of operations against a `DUP_SORT` database
+//! and an in-memory model, and checking that they stay equivalent.
+//!
+//! The writes and the reads are generated from a seed, a failing sequence can therefore be
+//! replayed. Every read is compared with the model when it is done, and the whole database
+//! is compared with it after every commit. Some transactions are aborted, the model is then
+//! restored to the last committed state. Programs can generate the keys and the values of their
+//! own schemas to check that their encodings are ordered like the model.
+//!
+//! ```
+//! use heed::testing::model::ModelOptions;
+//! use heed::EnvOpenOptions;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+//!
+//! let report = ModelOptions::new()
+//!     .seed(42)
+//!     .txns(20)
+//!     .keys_with(|n| format!("user-{n:03}").into_bytes())
+//!     .run(&env)?;
+//!
+//! report.assert_ok();
+//! assert_eq!(report.committed + report.aborted, 20);
+//! # Ok(()) }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+
+use super::SplitMix64;
+use crate::types::Bytes;
+use crate::{Database, DatabaseFlags, Env, Result, RwTxn};
+
+/// The name of the database written by the harness, it is cleared when the harness starts.
+pub const MODEL_DATABASE_NAME: &str = "__heed_model";
+
+type Generator = Box<dyn Fn(u64) -> Vec<u8>>;
+type ModelDatabase = Database<Bytes, Bytes>;
+/// The sorted values of every key, as stored in a `DUP_SORT` database.
+type Model = BTreeMap<Vec<u8>, Vec<Vec<u8>>>;
+
+/// Options to configure and run a model test, see the [module documentation](self).
+pub struct ModelOptions {
+    seed: u64,
+    txns: usize,
+    ops_per_txn: usize,
+    key_space: u64,
+    value_space: u64,
+    keys: Option<Generator>,
+    values: Option<Generator>,
+}
+
+impl Default for ModelOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModelOptions {
+    /// Creates the options of a test of a hundred transactions of sixteen operations,
+    /// over 64 keys of 16 values.
+    pub fn new() -> Self {
+        ModelOptions {
+            seed: 0,
+            txns: 100,
+            ops_per_txn: 16,
+            key_space: 64,
+            value_space: 16,
+            keys: None,
+            values: None,
+        }
+    }
+
+    /// Sets the seed the operations are generated from.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of write transactions.
+    pub fn txns(&mut self, txns: usize) -> &mut Self {
+        self.txns = txns;
+        self
+    }
+
+    /// Sets the number of operations done in every write transaction.
+    pub fn ops_per_txn(&mut self, ops: usize) -> &mut Self {
+        self.ops_per_txn = ops;
+        self
+    }
+
+    /// Sets the number of different keys, a smaller space makes the operations
+    /// on the same keys more frequent.
+    pub fn key_space(&mut self, keys: u64) -> &mut Self {
+        self.key_space = keys.max(1);
+        self
+    }
+
+    /// Sets the number of different values of a key.
+    pub fn value_space(&mut self, values: u64) -> &mut Self {
+        self.value_space = values.max(1);
+        self
+    }
+
+    /// Sets how the keys are generated from a number of the key space, the big-endian
+    /// bytes of the number by default. The keys must not be empty.
+    ///
+    /// The model orders the keys by their bytes, like the database.
+    pub fn keys_with<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(u64) -> Vec<u8> + 'static,
+    {
+        self.keys = Some(Box::new(f));
+        self
+    }
+
+    /// Sets how the values are generated from a number of the value space, the big-endian
+    /// bytes of the number by default.
+    pub fn values_with<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(u64) -> Vec<u8> + 'static,
+    {
+        self.values = Some(Box::new(f));
+        self
+    }
+
+    /// Runs the operations against the environment and reports the first divergence from the
+    /// model, the environment must allow one more named database.
+    ///
+    /// Fails with the first error returned by the database.
+    pub fn run<T>(&self, env: &Env<T>) -> Result<ModelReport> {
+        let mut wtxn = env.write_txn()?;
+        let db: ModelDatabase = env
+            .database_options()
+            .types()
+            .name(MODEL_DATABASE_NAME)
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        db.clear(&mut wtxn)?;
+        wtxn.commit()?;
+
+        let mut rng = SplitMix64(self.seed);
+        let mut report = ModelReport::default();
+        let mut model = Model::new();
+
+        for txn in 0..self.txns {
+            let mut wtxn = env.write_txn()?;
+            let mut pending = model.clone();
+            for _ in 0..self.ops_per_txn {
+                let operation = self.operation(&mut rng);
+                report.operations += 1;
+                if let Some((expected, found)) = operation.apply(db, &mut wtxn, &mut pending)? {
+                    let divergence = Divergence { txn, operation, expected, found };
+                    report.divergence = Some(divergence);
+                    return Ok(report);
+                }
+            }
+
+            // One transaction out of eight is aborted and leaves the model unchanged.
+            if rng.below(8) == 0 {
+                wtxn.abort();
+                report.aborted += 1;
+            } else {
+                wtxn.commit()?;
+                model = pending;
+                report.committed += 1;
+            }
+
+            let rtxn = env.read_txn()?;
+            let found = entries(db.iter(&rtxn)?)?;
+            let expected = flatten(model.iter());
+            if found != expected {
+                let operation = Operation::Commit;
+                let (expected, found) = (format!("{expected:?}"), format!("{found:?}"));
+                report.divergence = Some(Divergence { txn, operation, expected, found });
+                return Ok(report);
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn key(&self, rng: &mut SplitMix64) -> Vec<u8> {
+        let n = rng.below(self.key_space);
+        match &self.keys {
+            Some(keys) => keys(n),
+            None => n.to_be_bytes().to_vec(),
+        }
+    }
+
+    fn value(&self, rng: &mut SplitMix64) -> Vec<u8> {
+        let n = rng.below(self.value_space);
+        match &self.values {
+            Some(values) => values(n),
+            None => n.to_be_bytes().to_vec(),
+        }
+    }
+
+    fn operation(&self, rng: &mut SplitMix64) -> Operation {
+        match rng.below(10) {
+            0..=2 => Operation::Put { key: self.key(rng), value: self.value(rng) },
+            3 => Operation::Delete { key: self.key(rng) },
+            4 => Operation::DeleteDuplicate { key: self.key(rng), value: self.value(rng) },
+            5 => {
+                let (a, b) = (self.key(rng), self.key(rng));
+                Operation::DeleteRange { start: a.clone().min(b.clone()), end: a.max(b) }
+            }
+            6 => Operation::Get { key: self.key(rng) },
+            7 => Operation::Duplicates { key: self.key(rng) },
+            8 => {
+                let (a, b) = (self.key(rng), self.key(rng));
+                Operation::Range { start: a.clone().min(b.clone()), end: a.max(b) }
+            }
+            _ => {
+                let key = self.key(rng);
+                Operation::Prefix { prefix: key[..key.len().div_ceil(2)].to_vec() }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ModelOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ModelOptions")
+            .field("seed", &self.seed)
+            .field("txns", &self.txns)
+            .field("ops_per_txn", &self.ops_per_txn)
+            .field("key_space", &self.key_space)
+            .field("value_space", &self.value_space)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An operation run against the database and the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Puts a value of a key.
+    Put {
+        /// The key.
+        key: Vec<u8>,
+        /// The value added to the values of the key.
+        value: Vec<u8>,
+    },
+    /// Deletes all the values of a key.
+    Delete {
+        /// The key.
+        key: Vec<u8>,
+    },
+    /// Deletes a value of a key.
+    DeleteDuplicate {
+        /// The key.
+        key: Vec<u8>,
+        /// The deleted value.
+        value: Vec<u8>,
+    },
+    /// Deletes the keys of an inclusive range.
+    DeleteRange {
+        /// The first key of the range.
+        start: Vec<u8>,
+        /// The last key of the range.
+        end: Vec<u8>,
+    },
+    /// Reads the first value of a key.
+    Get {
+        /// The key.
+        key: Vec<u8>,
+    },
+    /// Reads all the values of a key.
+    Duplicates {
+        /// The key.
+        key: Vec<u8>,
+    },
+    /// Reads the entries of a range, from its first key included to its last key excluded.
+    Range {
+        /// The first key of the range.
+        start: Vec<u8>,
+        /// The key after the range.
+        end: Vec<u8>,
+    },
+    /// Reads the entries of the keys starting with a prefix.
+    Prefix {
+        /// The prefix.
+        prefix: Vec<u8>,
+    },
+    /// Commits or aborts the transaction, the database is compared with the model.
+    Commit,
+}
+
+impl Operation {
+    /// Runs the operation and returns the descriptions of the expected and found results
+    /// if the database and the model differ.
+    fn apply(
+        &self,
+        db: ModelDatabase,
+        wtxn: &mut RwTxn,
+        model: &mut Model,
+    ) -> Result<Option<(String, String)>> {
+        fn compare<T: PartialEq + fmt::Debug>(expected: T, found: T) -> Option<(String, String)> {
+            (expected != found).then(|| (format!("{expected:?}"), format!("{found:?}")))
+        }
+
+        Ok(match self {
+            Operation::Put { key, value } => {
+                db.put(wtxn, key, value)?;
+                let values = model.entry(key.clone()).or_default();
+                if let Err(index) = values.binary_search(value) {
+                    values.insert(index, value.clone());
+                }
+                None
+            }
+            Operation::Delete { key } => {
+                compare(model.remove(key).is_some(), db.delete(wtxn, key)?)
+            }
+            Operation::DeleteDuplicate { key, value } => {
+                let values = model.get_mut(key);
+                let expected = match values.as_ref().map(|values| values.binary_search(value)) {
+                    Some(Ok(index)) => {
+                        let values = values.unwrap();
+                        values.remove(index);
+                        if values.is_empty() {
+                            model.remove(key);
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                compare(expected, db.delete_one_duplicate(wtxn, key, value)?)
+            }
+            Operation::DeleteRange { start, end } => {
+                let range = (Bound::Included(&start[..]), Bound::Included(&end[..]));
+                let keys: Vec<_> =
+                    model.range::<[u8], _>(range).map(|(key, _)| key.clone()).collect();
+                let expected: usize =
+                    keys.iter().filter_map(|key| model.remove(key)).map(|v| v.len()).sum();
+                compare(expected, db.delete_range(wtxn, &range)?)
+            }
+            Operation::Get { key } => {
+                let expected = model.get(key).and_then(|values| values.first());
+                compare(expected.map(Vec::as_slice), db.get(wtxn, key)?)
+            }
+            Operation::Duplicates { key } => {
+                let expected = model.get(key).map(|values| flatten([(key, values)]));
+                let found = db.get_duplicates(wtxn, key)?.map(entries).transpose()?;
+                compare(expected, found)
+            }
+            Operation::Range { start, end } => {
+                let range = (Bound::Included(&start[..]), Bound::Excluded(&end[..]));
+                let expected = flatten(model.range::<[u8], _>(range));
+                compare(expected, entries(db.range(wtxn, &range)?)?)
+            }
+            Operation::Prefix { prefix } => {
+                let keys = model.iter().filter(|(key, _)| key.starts_with(prefix));
+                compare(flatten(keys), entries(db.prefix_iter(wtxn, prefix)?)?)
+            }
+            Operation::Commit => None,
+        })
+    }
+}
+
+/// Lists the entries of the model, a key is repeated for each of its values.
+fn flatten<'m>(
+    model: impl IntoIterator<Item = (&'m Vec<u8>, &'m Vec<Vec<u8>>)>,
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let entries = model.into_iter().flat_map(|(key, values)| values.iter().map(move |v| (key, v)));
+    entries.map(|(key, value)| (key.clone(), value.clone())).collect()
+}
+
+/// Collects the entries read from the database.
+fn entries<'txn>(
+    iter: impl Iterator<Item = Result<(&'txn [u8], &'txn [u8])>>,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    iter.map(|entry| entry.map(|(key, value)| (key.to_vec(), value.to_vec()))).collect()
+}
+
+/// The outcome of a model test, returned by [`ModelOptions::run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelReport {
+    /// The number of committed write transactions.
+    pub committed: usize,
+    /// The number of aborted write transactions.
+    pub aborted: usize,
+    /// The number of operations run.
+    pub operations: usize,
+    /// The first difference between the database and the model, the test stops there.
+    pub divergence: Option<Divergence>,
+}
+
+impl ModelReport {
+    /// Returns whether the database stayed equivalent to the model.
+    pub fn is_ok(&self) -> bool {
+        self.divergence.is_none()
+    }
+
+    /// Panics with the divergence from the model, if any.
+    #[track_caller]
+    pub fn assert_ok(&self) {
+        if let Some(divergence) = &self.divergence {
+            panic!("the database diverged from the model: {divergence}");
+        }
+    }
+}
+
+/// A difference between the database and the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the write transaction.
+    pub txn: usize,
+    /// The operation that returned a different result.
+    pub operation: Operation,
+    /// The description of the result of the model.
+    pub expected: String,
+    /// The description of the result of the database.
+    pub found: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Divergence { txn, operation, expected, found } = self;
+        write!(f, "{operation:?} of transaction {txn} expected {expected} but found {found}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn model_equivalence() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let report = ModelOptions::new().seed(7).txns(50).key_space(16).run(&env)?;
+        report.assert_ok();
+        assert_eq!(report.committed + report.aborted, 50);
+        assert_eq!(report.operations, 50 * 16);
+
+        // The same seed runs the same operations.
+        let again = ModelOptions::new().seed(7).txns(50).key_space(16).run(&env)?;
+        assert_eq!(again, report);
+
+        // The keys of varying lengths exercise the prefixes.
+        let report = ModelOptions::new()
+            .seed(3)
+            .keys_with(|n| vec![b'k'; n as usize % 5 + 1])
+            .values_with(|n| n.to_string().into_bytes())
+            .run(&env)?;
+        report.assert_ok();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+//! A harness running concurrent readers and writers against an environment
+//! and checking that the guarantees of LMDB hold under load.
+//!
+//! The writers append entries to the [`STRESS_DATABASE_NAME`] database, a fixed number per
+//! transaction, while the readers check that their transactions ids never decrease, that they
+//! never see a part of a write transaction, that their snapshot doesn't change while they read
+//! it and that the entries decode. Programs can add their own reads and writes to the
+//! transactions of the harness to check their usage patterns with the same invariants.
+//!
+//! ```
+//! use std::time::Duration;
+//! use heed::testing::stress::StressOptions;
+//! use heed::types::*;
+//! use heed::EnvOpenOptions;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+//! let mut wtxn = env.write_txn()?;
+//! let users = env.create_database::<Str, U64<byteorder::BE>>(&mut wtxn, Some("users"))?;
+//! wtxn.commit()?;
+//!
+//! let report = StressOptions::new()
+//!     .readers(4)
+//!     .writers(2)
+//!     .duration(Duration::from_millis(200))
+//!     .write_with(move |wtxn, writer| users.put(wtxn, &format!("user-{writer}"), &42))
+//!     .read_with(move |rtxn, _reader| users.iter(rtxn)?.try_for_each(|e| e.map(drop)))
+//!     .run(&env)?;
+//!
+//! report.assert_ok();
+//! assert!(report.write_txns > 0);
+//! # Ok(()) }
+//! ```
+
+use std::panic::resume_unwind;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fmt, thread};
+
+use crate::byteorder::BE;
+use crate::types::U64;
+use crate::{Database, Env, Error, Result, RoTxn, RwTxn};
+
+/// The name of the database written by the harness, it is cleared when the harness starts.
+pub const STRESS_DATABASE_NAME: &str = "__heed_stress";
+
+type ReadOp = Box<dyn Fn(&RoTxn, usize) -> Result<()> + Send + Sync>;
+type WriteOp = Box<dyn Fn(&mut RwTxn, usize) -> Result<()> + Send + Sync>;
+type StressDatabase = Database<U64<BE>, U64<BE>>;
+
+/// Options to configure and run a stress test, see the [module documentation](self).
+pub struct StressOptions {
+    readers: usize,
+    writers: usize,
+    duration: Duration,
+    entries_per_txn: u64,
+    read_op: Option<ReadOp>,
+    write_op: Option<WriteOp>,
+}
+
+impl Default for StressOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StressOptions {
+    /// Creates the options of a one second test with four readers and one writer.
+    pub fn new() -> Self {
+        StressOptions {
+            readers: 4,
+            writers: 1,
+            duration: Duration::from_secs(1),
+            entries_per_txn: 8,
+            read_op: None,
+            write_op: None,
+        }
+    }
+
+    /// Sets the number of reader threads.
+    pub fn readers(&mut self, readers: usize) -> &mut Self {
+        self.readers = readers;
+        self
+    }
+
+    /// Sets the number of writer threads, they wait for each other to open their transactions.
+    pub fn writers(&mut self, writers: usize) -> &mut Self {
+        self.writers = writers;
+        self
+    }
+
+    /// Sets how long the readers and the writers run.
+    pub fn duration(&mut self, duration: Duration) -> &mut Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets the number of entries appended to the stress database by every write transaction.
+    pub fn entries_per_txn(&mut self, entries: u64) -> &mut Self {
+        self.entries_per_txn = entries.max(1);
+        self
+    }
+
+    /// Sets a read performed by the readers in every read transaction,
+    /// the closure receives the index of the reader thread.
+    ///
+    /// A decoding error is reported as a [`Violation`], the other errors stop the test.
+    pub fn read_with<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&RoTxn, usize) -> Result<()> + Send + Sync + 'static,
+    {
+        self.read_op = Some(Box::new(f));
+        self
+    }
+
+    /// Sets a write performed by the writers in every write transaction,
+    /// the closure receives the index of the writer thread.
+    ///
+    /// A decoding error is reported as a [`Violation`], the other errors stop the test.
+    pub fn write_with<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&mut RwTxn, usize) -> Result<()> + Send + Sync + 'static,
+    {
+        self.write_op = Some(Box::new(f));
+        self
+    }
+
+    /// Runs the readers and the writers against the environment and reports the violated
+    /// invariants, the environment must allow one more named database.
+    ///
+    /// Fails with the first error, other than a decoding error, of a reader or a writer.
+    /// A thread stops at its first violation, the others go on.
+    pub fn run<T>(&self, env: &Env<T>) -> Result<StressReport> {
+        let mut wtxn = env.write_txn()?;
+        let db: StressDatabase = env.create_database(&mut wtxn, Some(STRESS_DATABASE_NAME))?;
+        db.clear(&mut wtxn)?;
+        wtxn.commit()?;
+
+        let stress = Stress {
+            options: self,
+            db,
+            deadline: Instant::now() + self.duration,
+            stop: AtomicBool::new(false),
+            write_txns: AtomicU64::new(0),
+            read_txns: AtomicU64::new(0),
+            last_write_txn_id: AtomicUsize::new(0),
+            violations: Mutex::new(Vec::new()),
+        };
+
+        thread::scope(|s| {
+            let stress = &stress;
+            let writers = (0..self.writers).map(|i| s.spawn(move || stress.writer(env, i)));
+            let readers = (0..self.readers).map(|i| s.spawn(move || stress.reader(env, i)));
+            let handles: Vec<_> = writers.chain(readers).collect();
+
+            let mut result = Ok(());
+            for handle in handles {
+                match handle.join() {
+                    Ok(Ok(())) => (),
+                    Ok(Err(error)) => {
+                        stress.stop.store(true, Ordering::Relaxed);
+                        if result.is_ok() {
+                            result = Err(error);
+                        }
+                    }
+                    Err(payload) => resume_unwind(payload),
+                }
+            }
+            result
+        })?;
+
+        Ok(StressReport {
+            write_txns: stress.write_txns.into_inner(),
+            read_txns: stress.read_txns.into_inner(),
+            violations: stress.violations.into_inner().unwrap(),
+        })
+    }
+}
+
+impl fmt::Debug for StressOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StressOptions")
+            .field("readers", &self.readers)
+            .field("writers", &self.writers)
+            .field("duration", &self.duration)
+            .field("entries_per_txn", &self.entries_per_txn)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The outcome of a stress test, returned by [`StressOptions::run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StressReport {
+    /// The number of committed write transactions.
+    pub write_txns: u64,
+    /// The number of checked read transactions.
+    pub read_txns: u64,
+    /// The violated invariants.
+    pub violations: Vec<Violation>,
+}
+
+impl StressReport {
+    /// Returns whether no invariant was violated.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Panics with the violated invariants, if any.
+    #[track_caller]
+    pub fn assert_ok(&self) {
+        if !self.is_ok() {
+            let violations: Vec<_> = self.violations.iter().map(ToString::to_string).collect();
+            panic!("the stress test found violations:\n{}", violations.join("\n"));
+        }
+    }
+}
+
+/// An invariant violated during a stress test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// A write transaction didn't have a greater id than the previous one.
+    WriteTxnIdNotIncreasing {
+        /// The id of the previous write transaction.
+        previous: usize,
+        /// The id of the write transaction.
+        current: usize,
+    },
+    /// A reader opened a transaction with a smaller id than its previous one.
+    ReadTxnIdDecreased {
+        /// The id of the previous read transaction.
+        previous: usize,
+        /// The id of the read transaction.
+        current: usize,
+    },
+    /// A read transaction saw a part of the entries of a write transaction.
+    PartialWrite {
+        /// The id of the read transaction.
+        txn_id: usize,
+        /// The number of entries seen.
+        entries: u64,
+        /// The last key seen.
+        last_key: u64,
+    },
+    /// The entries seen by a read transaction changed while it was opened.
+    SnapshotChanged {
+        /// The id of the read transaction.
+        txn_id: usize,
+        /// The number of entries seen first.
+        before: u64,
+        /// The number of entries seen last.
+        after: u64,
+    },
+    /// A key or a data failed to decode.
+    Decoding {
+        /// The description of the decoding error.
+        error: String,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::WriteTxnIdNotIncreasing { previous, current } => {
+                write!(f, "write transaction {current} follows write transaction {previous}")
+            }
+            Violation::ReadTxnIdDecreased { previous, current } => {
+                write!(f, "read transaction {current} follows read transaction {previous}")
+            }
+            Violation::PartialWrite { txn_id, entries, last_key } => write!(
+                f,
+                "read transaction {txn_id} sees {entries} entries up to the key {last_key}"
+            ),
+            Violation::SnapshotChanged { txn_id, before, after } => {
+                write!(f, "read transaction {txn_id} first sees {before} entries and then {after}")
+            }
+            Violation::Decoding { error } => write!(f, "decoding error: {error}"),
+        }
+    }
+}
+
+/// The state shared by the threads of a stress test.
+struct Stress<'o> {
+    options: &'o StressOptions,
+    db: StressDatabase,
+    deadline: Instant,
+    stop: AtomicBool,
+    write_txns: AtomicU64,
+    read_txns: AtomicU64,
+    last_write_txn_id: AtomicUsize,
+    violations: Mutex<Vec<Violation>>,
+}
+
+impl Stress<'_> {
+    fn running(&self) -> bool {
+        !self.stop.load(Ordering::Relaxed) && Instant::now() < self.deadline
+    }
+
+    /// Records the violation or the decoding error that stopped a thread.
+    fn stopped(&self, result: Result<Option<Violation>>) -> Result<()> {
+        let violation = match result {
+            Ok(None) => return Ok(()),
+            Ok(Some(violation)) => violation,
+            Err(Error::Decoding(error)) => Violation::Decoding { error: error.to_string() },
+            Err(error) => return Err(error),
+        };
+        self.violations.lock().unwrap().push(violation);
+        Ok(())
+    }
+
+    fn writer<T>(&self, env: &Env<T>, index: usize) -> Result<()> {
+        let result = (|| {
+            while self.running() {
+                let mut wtxn = env.write_txn()?;
+                let current = wtxn.id();
+                // The write lock is held: the previous id is the one of the last write transaction.
+                let previous = self.last_write_txn_id.swap(current, Ordering::SeqCst);
+                if current <= previous {
+                    return Ok(Some(Violation::WriteTxnIdNotIncreasing { previous, current }));
+                }
+
+                let last = self.db.last(&wtxn)?.map_or(0, |(key, _)| key);
+                for key in last + 1..=last + self.options.entries_per_txn {
+                    self.db.put(&mut wtxn, &key, &(index as u64))?;
+                }
+                if let Some(write_op) = &self.options.write_op {
+                    write_op(&mut wtxn, index)?;
+                }
+                wtxn.commit()?;
+                self.write_txns.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(None)
+        })();
+        self.stopped(result)
+    }
+
+    fn reader<T>(&self, env: &Env<T>, index: usize) -> Result<()> {
+        let result = (|| {
+            let mut previous = 0;
+            while self.running() {
+                let rtxn = env.read_txn()?;
+                let txn_id = rtxn.id();
+                if txn_id < previous {
+                    return Ok(Some(Violation::ReadTxnIdDecreased { previous, current: txn_id }));
+                }
+                previous = txn_id;
+
+                let entries = self.db.len(&rtxn)?;
+                let last_key = self.db.last(&rtxn)?.map_or(0, |(key, _)| key);
+                if entries != last_key || entries % self.options.entries_per_txn != 0 {
+                    return Ok(Some(Violation::PartialWrite { txn_id, entries, last_key }));
+                }
+                let recent = usize::try_from(self.options.entries_per_txn).unwrap_or(usize::MAX);
+                for entry in self.db.rev_iter(&rtxn)?.take(recent) {
+                    entry?;
+                }
+                if let Some(read_op) = &self.options.read_op {
+                    read_op(rtxn.as_any_tls(), index)?;
+                }
+
+                // Give the writers a chance to commit before reading the snapshot again.
+                thread::yield_now();
+                let after = self.db.len(&rtxn)?;
+                if after != entries {
+                    return Ok(Some(Violation::SnapshotChanged { txn_id, before: entries, after }));
+                }
+                self.read_txns.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(None)
+        })();
+        self.stopped(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn stress_readers_and_writers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("words"))?;
+        wtxn.commit()?;
+
+        let report = StressOptions::new()
+            .readers(3)
+            .writers(2)
+            .duration(Duration::from_millis(200))
+            .write_with(move |wtxn, _| db.put(wtxn, "hello", "world"))
+            .read_with(move |rtxn, _| db.get(rtxn, "hello").map(drop))
+            .run(&env)?;
+        report.assert_ok();
+        assert!(report.write_txns > 0);
+        assert!(report.read_txns > 0);
+
+        // The decoding errors of the reads are reported.
+        let report = StressOptions::new()
+            .readers(1)
+            .writers(0)
+            .duration(Duration::from_millis(50))
+            .read_with(|_, _| Err(Error::Decoding("invalid".into())))
+            .run(&env)?;
+        assert_eq!(report.violations, [Violation::Decoding { error: String::from("invalid") }]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use std::ops::{Deref, RangeInclusive};
+use std::path::Path;
+
+use tempfile::TempDir;
+
+use super::SplitMix64;
+use crate::types::Bytes;
+use crate::{Database, Env, EnvOpenOptions, Result};
+
+/// An environment in a temporary directory, removed when it is dropped.
+///
+/// The environment is filled by a generator seeded by [`TestEnvBuilder::seed`], the same
+/// options always create the same entries. It dereferences to its [`Env`].
+///
+/// ```
+/// use heed::testing::{Distribution, TestEnv};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let env = TestEnv::builder()
+///     .seed(42)
+///     .dbs(&["users", "posts"])
+///     .entries(1000)
+///     .values(Distribution::Uniform { len: 10..=100 })
+///     .build()?;
+///
+/// let rtxn = env.read_txn()?;
+/// assert_eq!(env.database("users").len(&rtxn)?, 1000);
+/// assert_eq!(env.database("posts").len(&rtxn)?, 1000);
+///
+/// // The entries of a database only depend on the seed and on its name.
+/// let again = TestEnv::builder().seed(42).dbs(&["users"]).entries(1000).build()?;
+/// let again_rtxn = again.read_txn()?;
+/// assert_eq!(again.database("users").last(&again_rtxn)?, env.database("users").last(&rtxn)?);
+/// # Ok(()) }
+/// ```
+pub struct TestEnv {
+    env: Env,
+    databases: Vec<(String, Database<Bytes, Bytes>)>,
+    // Dropped after the environment.
+    dir: TempDir,
+}
+
+impl TestEnv {
+    /// Creates the options of an empty environment of 100 MiB with room for ten named databases.
+    pub fn builder() -> TestEnvBuilder {
+        TestEnvBuilder::new()
+    }
+
+    /// Returns the environment.
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Returns the temporary directory of the environment.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Returns the database of this name created by the builder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the database wasn't created by the builder.
+    #[track_caller]
+    pub fn database(&self, name: &str) -> Database<Bytes, Bytes> {
+        match self.databases.iter().find(|(n, _)| n == name) {
+            Some((_, db)) => *db,
+            None => panic!("the {name:?} database wasn't created by the builder"),
+        }
+    }
+}
+
+impl Deref for TestEnv {
+    type Target = Env;
+
+    fn deref(&self) -> &Env {
+        &self.env
+    }
+}
+
+/// How the keys or the values of a [`TestEnv`] are generated.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Distribution {
+    /// The big-endian `u64` index of the entry, the keys are then unique and ordered
+    /// like the entries were generated.
+    Sequential,
+    /// Random bytes, of a length uniformly chosen in the range.
+    Uniform {
+        /// The lengths of the bytes.
+        len: RangeInclusive<usize>,
+    },
+    /// The big-endian `u64` rank of an item among `items`, chosen with a Zipf distribution
+    /// of parameter `exponent`: the first ranks are generated much more often than the last
+    /// ones, like the hot keys of a real workload.
+    Zipf {
+        /// The number of ranks.
+        items: u64,
+        /// The skew of the distribution, the larger the more the first ranks are generated.
+        exponent: f64,
+    },
+}
+
+impl Distribution {
+    fn check(&self) {
+        if let Distribution::Zipf { items, .. } = self {
+            assert!(*items > 0, "a Zipf distribution needs at least one item");
+        }
+    }
+
+    /// Prepares the generation, the Zipf distribution needs the cumulated weights of its ranks.
+    fn sampler(&self) -> Sampler {
+        match self {
+            Distribution::Sequential => Sampler::Sequential,
+            Distribution::Uniform { len } => Sampler::Uniform(len.clone()),
+            Distribution::Zipf { items, exponent } => {
+                let mut total = 0.0;
+                let cumulated = (1..=*items)
+                    .map(|rank| {
+                        total += 1.0 / (rank as f64).powf(*exponent);
+                        total
+                    })
+                    .collect();
+                Sampler::Zipf(cumulated)
+            }
+        }
+    }
+}
+
+enum Sampler {
+    Sequential,
+    Uniform(RangeInclusive<usize>),
+    Zipf(Vec<f64>),
+}
+
+impl Sampler {
+    fn sample(&self, index: u64, rng: &mut SplitMix64) -> Vec<u8> {
+        match self {
+            Sampler::Sequential => index.to_be_bytes().to_vec(),
+            Sampler::Uniform(len) => {
+                let span = (len.end() - len.start()) as u64 + 1;
+                let len = len.start() + rng.below(span) as usize;
+                (0..len).map(|_| rng.next() as u8).collect()
+            }
+            Sampler::Zipf(cumulated) => {
+                let target = rng.unit() * cumulated.last().copied().unwrap_or(0.0);
+                let rank = cumulated.partition_point(|&weight| weight <= target);
+                (rank.min(cumulated.len() - 1) as u64).to_be_bytes().to_vec()
+            }
+        }
+    }
+}
+
+/// Options to create a [`TestEnv`], see [`TestEnv::builder`].
+#[derive(Debug, Clone)]
+pub struct TestEnvBuilder {
+    seed: u64,
+    dbs: Vec<String>,
+    entries: usize,
+    keys: Distribution,
+    values: Distribution,
+    map_size: usize,
+    max_dbs: u32,
+}
+
+impl TestEnvBuilder {
+    fn new() -> TestEnvBuilder {
+        TestEnvBuilder {
+            seed: 0,
+            dbs: Vec::new(),
+            entries: 0,
+            keys: Distribution::Sequential,
+            values: Distribution::Uniform { len: 0..=32 },
+            map_size: 100 * 1024 * 1024,
+            max_dbs: 10,
+        }
+    }
+
+    /// Sets the seed the entries are generated from.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the named databases to create, with the same number of generated entries.
+    ///
+    /// The entries of a database only depend on the seed and on its name.
+    pub fn dbs(&mut self, names: &[&str]) -> &mut Self {
+        self.dbs = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Sets the number of entries put in every database, none by default.
+    ///
+    /// The keys generated several times are overwritten, a database
+    /// can therefore hold fewer entries with random keys.
+    pub fn entries(&mut self, entries: usize) -> &mut Self {
+        self.entries = entries;
+        self
+    }
+
+    /// Sets how the keys are generated, [`Distribution::Sequential`] by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the distribution can generate empty keys or keys larger than 511 bytes,
+    /// or if it is a Zipf distribution without items.
+    pub fn keys(&mut self, keys: Distribution) -> &mut Self {
+        if let Distribution::Uniform { len } = &keys {
+            assert!(
+                *len.start() > 0 && *len.end() <= 511,
+                "LMDB refuses the empty keys and the keys larger than 511 bytes"
+            );
+        }
+        keys.check();
+        self.keys = keys;
+        self
+    }
+
+    /// Sets how the values are generated, random bytes of up to 32 bytes by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the distribution is a Zipf distribution without items.
+    pub fn values(&mut self, values: Distribution) -> &mut Self {
+        values.check();
+        self.values = values;
+        self
+    }
+
+    /// Sets the size of the memory map, 100 MiB by default.
+    pub fn map_size(&mut self, size: usize) -> &mut Self {
+        self.map_size = size;
+        self
+    }
+
+    /// Sets the maximum number of named databases, ten by default.
+    pub fn max_dbs(&mut self, dbs: u32) -> &mut Self {
+        self.max_dbs = dbs;
+        self
+    }
+
+    /// Creates the environment in a new temporary directory and fills its databases,
+    /// in a single write transaction.
+    pub fn build(&self) -> Result<TestEnv> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe {
+            EnvOpenOptions::new().map_size(self.map_size).max_dbs(self.max_dbs).open(dir.path())?
+        };
+
+        let (keys, values) = (self.keys.sampler(), self.values.sampler());
+        let mut wtxn = env.write_txn()?;
+        let mut databases = Vec::with_capacity(self.dbs.len());
+        for name in &self.dbs {
+            let db = env.create_database(&mut wtxn, Some(name))?;
+            let mut rng = SplitMix64(self.seed ^ fnv1a(name.as_bytes()));
+            for index in 0..self.entries as u64 {
+                let key = keys.sample(index, &mut rng);
+                let value = values.sample(index, &mut rng);
+                db.put(&mut wtxn, &key[..], &value[..])?;
+            }
+            databases.push((name.clone(), db));
+        }
+        wtxn.commit()?;
+
+        Ok(TestEnv { env, databases, dir })
+    }
+}
+
+/// Hashes the name of a database to derive the seed of its entries.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xCBF2_9CE4_8422_2325, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(0x100_0000_01B3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_environments() -> Result<()> {
+        let build = |seed| {
+            TestEnv::builder()
+                .seed(seed)
+                .dbs(&["a", "b"])
+                .entries(500)
+                .keys(Distribution::Zipf { items: 100, exponent: 1.2 })
+                .values(Distribution::Sequential)
+                .build()
+        };
+        let entries = |env: &TestEnv, name| -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+            let rtxn = env.read_txn()?;
+            let iter = env.database(name).iter(&rtxn)?;
+            iter.map(|e| e.map(|(k, v)| (k.to_vec(), v.to_vec()))).collect()
+        };
+
+        let (first, second, other) = (build(7)?, build(7)?, build(8)?);
+        assert_ne!(first.path(), second.path());
+        assert_eq!(entries(&first, "a")?, entries(&second, "a")?);
+        assert_ne!(entries(&first, "a")?, entries(&other, "a")?);
+        assert_ne!(entries(&first, "a")?, entries(&first, "b")?);
+
+        // The Zipf keys are repeated and the first rank is almost always generated.
+        let a = entries(&first, "a")?;
+        assert!(a.len() < 100);
+        assert_eq!(a[0].0, 0u64.to_be_bytes());
+
+        let env = TestEnv::builder().build()?;
+        assert_eq!(env.info().map_size, 100 * 1024 * 1024);
+        assert!(env.path().join("data.mdb").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! An asynchronous facade of the environment for the [Tokio](https://tokio.rs) runtime.
+//!
+//! LMDB transactions block the thread they run on, the [`Env`] of this module runs them on
+//! the blocking thread pool of Tokio so that they never block the asynchronous tasks.
+//!
+//! ```
+//! use heed::types::*;
+//! use heed::EnvOpenOptions;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! # let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+//! # runtime.block_on(async {
+//! let env = unsafe {
+//!     EnvOpenOptions::new()
+//!         .read_txn_without_tls()
+//!         .max_dbs(10)
+//!         .open(dir.path())?
+//! };
+//! let mut wtxn = env.write_txn()?;
+//! let db = env.create_database::<Str, U32<byteorder::BE>>(&mut wtxn, Some("ages"))?;
+//! wtxn.commit()?;
+//!
+//! let env = heed::tokio::Env::new(env);
+//! env.write(move |wtxn| db.put(wtxn, "kero", &29)).await?;
+//!
+//! let age = env.read(move |rtxn| db.get(rtxn, "kero")).await?;
+//! assert_eq!(age, Some(29));
+//! # heed::Result::Ok(()) })?;
+//! # Ok(()) }
+//! ```
+
+use std::io;
+use std::panic::resume_unwind;
+
+use ::tokio::task::{spawn_blocking, JoinError};
+
+use crate::{Error, RoTxn, RwTxn, WithoutTls};
+
+/// An environment whose transactions run on the blocking thread pool of Tokio.
+///
+/// The closures given to [`Env::read`] and [`Env::write`] are `'static` and so are the values
+/// they return: nothing borrowed from a transaction can escape it. A panic in a closure is
+/// resumed in the task awaiting it, a write transaction is aborted when its closure panics.
+#[derive(Clone)]
+pub struct Env {
+    env: crate::Env<WithoutTls>,
+}
+
+impl Env {
+    /// Wraps an environment whose read transactions don't use thread local storage.
+    pub fn new(env: crate::Env<WithoutTls>) -> Env {
+        Env { env }
+    }
+
+    /// Returns the underlying environment.
+    pub fn inner(&self) -> &crate::Env<WithoutTls> {
+        &self.env
+    }
+
+    /// Returns the underlying environment.
+    pub fn into_inner(self) -> crate::Env<WithoutTls> {
+        self.env
+    }
+
+    /// Runs the closure with a read transaction on the blocking thread pool.
+    ///
+    /// The transaction is opened on the thread that runs the closure and closed once it returns.
+    pub async fn read<F, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&RoTxn<WithoutTls>) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        let env = self.env.clone();
+        let task = spawn_blocking(move || {
+            let rtxn = env.read_txn()?;
+            f(&rtxn)
+        });
+        task.await.unwrap_or_else(|error| Err(join_error(error).into()))
+    }
+
+    /// Runs the closure with a write transaction on the blocking thread pool.
+    ///
+    /// The transaction is committed if the closure returns `Ok`, it is aborted otherwise.
+    pub async fn write<F, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut RwTxn) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<Error> + Send + 'static,
+    {
+        let env = self.env.clone();
+        let task = spawn_blocking(move || {
+            let mut wtxn = env.write_txn()?;
+            let output = f(&mut wtxn)?;
+            wtxn.commit()?;
+            Ok(output)
+        });
+        task.await.unwrap_or_else(|error| Err(join_error(error).into()))
+    }
+}
+
+impl From<crate::Env<WithoutTls>> for Env {
+    fn from(env: crate::Env<WithoutTls>) -> Env {
+        Env::new(env)
+    }
+}
+
+impl std::fmt::Debug for Env {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Env").field("path", &self.env.path().display()).finish()
+    }
+}
+
+/// Resumes the panic of a blocking task, converts its cancellation into an error.
+fn join_error(error: JoinError) -> Error {
+    match error.try_into_panic() {
+        Ok(payload) => resume_unwind(payload),
+        Err(error) => Error::Io(io::Error::new(io::ErrorKind::Interrupted, error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tokio::runtime::{Builder, Runtime};
+
+    use super::*;
+    use crate::types::Str;
+    use crate::{Database, EnvOpenOptions, Result};
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().build().unwrap()
+    }
+
+    #[test]
+    fn read_and_write() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env =
+            unsafe { EnvOpenOptions::new().read_txn_without_tls().max_dbs(10).open(dir.path())? };
+        let env = Env::new(env);
+
+        runtime().block_on(async {
+            let inner = env.inner().clone();
+            let db: Database<Str, Str> = env
+                .write(move |wtxn| {
+                    let db = inner.create_database(wtxn, Some("words"))?;
+                    db.put(wtxn, "hello", "world")?;
+                    Result::Ok(db)
+                })
+                .await?;
+
+            // A failing closure aborts its transaction.
+            let result = env
+                .write(move |wtxn| {
+                    db.put(wtxn, "hello", "there")?;
+                    Result::<()>::Err(Error::Mdb(crate::MdbError::Panic))
+                })
+                .await;
+            assert!(result.is_err());
+
+            let value =
+                env.read(move |rtxn| Result::Ok(db.get(rtxn, "hello")?.map(str::to_owned))).await?;
+            assert_eq!(value.as_deref(), Some("world"));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn panics_are_propagated() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().read_txn_without_tls().open(dir.path())? };
+        let env = Env::new(env);
+
+        let runtime = runtime();
+        let task = runtime.spawn({
+            let env = env.clone();
+            async move { env.write(|_| -> Result<()> { panic!("failed write") }).await }
+        });
+        let error = runtime.block_on(task).unwrap_err();
+        assert_eq!(*error.into_panic().downcast::<&str>().unwrap(), "failed write");
+
+        // The write transaction has been released by the panic.
+        runtime.block_on(env.write(|_| Result::Ok(())))
+    }
+}
@@ -0,0 +1,324 @@
+//! Import and export databases as CSV files.
+//!
+//! A [`Mapping`] describes which columns of the file hold the keys and the values
+//! for [`Database::export_csv`] and [`Database::import_csv`]. Fields are converted from text with [`FromStr`] and written with [`Display`],
+//! before being encoded or after being decoded by the database codecs.
+//!
+//! ```
+//! # use heed::EnvOpenOptions;
+//! use heed::Database;
+//! use heed::tools::csv::Mapping;
+//! use heed::types::*;
+//! use heed::byteorder::BigEndian;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! # let env = unsafe { EnvOpenOptions::new()
+//! #     .map_size(10 * 1024 * 1024) // 10MB
+//! #     .max_dbs(3000)
+//! #     .open(dir.path())?
+//! # };
+//! let mut wtxn = env.write_txn()?;
+//! let db: Database<U32<BigEndian>, Str> = env.create_database(&mut wtxn, Some("cities"))?;
+//!
+//! let file = "name,population,id\nParis,2102650,1\nLyon,522250,2\n";
+//! let mut mapping = Mapping::new();
+//! mapping.key_column("id").value_column("name");
+//! let count = db.import_csv::<u32, String>(&mut wtxn, file.as_bytes(), &mapping)?;
+//! assert_eq!(count, 2);
+//! assert_eq!(db.get(&wtxn, &2)?, Some("Lyon"));
+//!
+//! let mut export = Vec::new();
+//! db.export_csv(&wtxn, &mut export, &mapping)?;
+//! assert_eq!(export, b"id,name\n1,Paris\n2,Lyon\n");
+//! wtxn.commit()?;
+//! # Ok(()) }
+//! ```
+
+use std::borrow::Borrow;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use super::Column;
+use crate::{
+    BoxedError, BytesDecode, BytesEncode, Database, Error, PutFlags, ReadTxn, Result, WriteTxn,
+};
+
+/// Describes how the rows of a CSV file map to the entries of a database.
+///
+/// By default the keys are in the first column, the values in the second one,
+/// the file starts with a header row and the fields are separated by commas.
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    key: Column,
+    value: Column,
+    has_headers: bool,
+    delimiter: u8,
+    append: bool,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mapping {
+    /// Creates the default mapping.
+    pub fn new() -> Mapping {
+        Mapping {
+            key: Column::Index(0),
+            value: Column::Index(1),
+            has_headers: true,
+            delimiter: b',',
+            append: false,
+        }
+    }
+
+    /// Sets the column containing the keys.
+    pub fn key_column(&mut self, column: impl Into<Column>) -> &mut Self {
+        self.key = column.into();
+        self
+    }
+
+    /// Sets the column containing the values.
+    pub fn value_column(&mut self, column: impl Into<Column>) -> &mut Self {
+        self.value = column.into();
+        self
+    }
+
+    /// Whether the first row of the file is a header row, `true` by default.
+    ///
+    /// Columns can only be designated by their name when there is a header row.
+    pub fn has_headers(&mut self, has_headers: bool) -> &mut Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Sets the field delimiter, a comma by default.
+    pub fn delimiter(&mut self, delimiter: u8) -> &mut Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Inserts the entries with [`PutFlags::APPEND`] when importing.
+    ///
+    /// This is much faster for large files but requires the file to be sorted by key,
+    /// in the order of the database comparator, and every key to be greater than the
+    /// ones already in the database. The import fails with [`MdbError::KeyExist`](crate::MdbError::KeyExist)
+    /// otherwise.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Returns the positions of the key and value columns.
+    fn positions(&self, headers: Option<&::csv::StringRecord>) -> Result<(usize, usize)> {
+        let position = |column: &Column| match (column, headers) {
+            (Column::Index(index), _) => Ok(*index),
+            (Column::Name(name), Some(headers)) => {
+                headers.iter().position(|h| h == name).ok_or_else(|| {
+                    invalid_mapping(format!("column {name:?} not found in the headers"))
+                })
+            }
+            (Column::Name(name), None) => Err(invalid_mapping(format!(
+                "column {name:?} designated by name without a header row"
+            ))),
+        };
+
+        let key = position(&self.key)?;
+        let value = position(&self.value)?;
+        if key == value {
+            return Err(invalid_mapping("the key and value columns must differ".to_owned()));
+        }
+        Ok((key, value))
+    }
+}
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Writes all the entries of this database as CSV rows following the mapping.
+    ///
+    /// Keys and values are written in their column, the other columns, if any, are left empty.
+    /// The header row uses the names of the columns, or `key` and `value` for the columns
+    /// designated by position. Columns designated by name are written in the key, value order.
+    pub fn export_csv<'txn>(
+        &self,
+        rtxn: &'txn impl ReadTxn,
+        writer: impl Write,
+        mapping: &Mapping,
+    ) -> Result<()>
+    where
+        KC: BytesDecode<'txn>,
+        DC: BytesDecode<'txn>,
+        KC::DItem: Display,
+        DC::DItem: Display,
+    {
+        let mut names = [(&mapping.key, "key"), (&mapping.value, "value")].map(
+            |(column, default)| match column {
+                Column::Index(index) => (*index, default),
+                Column::Name(name) => (usize::MAX, name.as_str()),
+            },
+        );
+        // Columns designated by name are written after the ones designated by position.
+        let mut next = names.iter().filter(|(i, _)| *i != usize::MAX).map(|(i, _)| i + 1).max();
+        for (index, _) in names.iter_mut().filter(|(i, _)| *i == usize::MAX) {
+            *index = next.unwrap_or(0);
+            next = Some(*index + 1);
+        }
+        let [(key, key_name), (value, value_name)] = names;
+        if key == value {
+            return Err(invalid_mapping("the key and value columns must differ".to_owned()));
+        }
+
+        let width = key.max(value) + 1;
+        let mut writer =
+            ::csv::WriterBuilder::new().delimiter(mapping.delimiter).from_writer(writer);
+        if mapping.has_headers {
+            let mut fields = vec![""; width];
+            fields[key] = key_name;
+            fields[value] = value_name;
+            writer.write_record(&fields).map_err(|e| csv_error(e, Error::Encoding))?;
+        }
+
+        let mut fields = vec![String::new(); width];
+        for result in self.iter(rtxn)? {
+            let (k, v) = result?;
+            fields[key] = k.to_string();
+            fields[value] = v.to_string();
+            writer.write_record(&fields).map_err(|e| csv_error(e, Error::Encoding))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Inserts the rows of a CSV file into this database following the mapping.
+    ///
+    /// The key and value fields are parsed as a `K` and a `V` that are then encoded with
+    /// the database codecs, `K` and `V` are the owned versions of the codecs items,
+    /// e.g. `String` for a [`Str`](crate::types::Str) codec. Returns the number of inserted entries.
+    pub fn import_csv<K, V>(
+        &self,
+        wtxn: &mut impl WriteTxn,
+        reader: impl Read,
+        mapping: &Mapping,
+    ) -> Result<usize>
+    where
+        KC: for<'a> BytesEncode<'a>,
+        DC: for<'a> BytesEncode<'a>,
+        K: FromStr + for<'a> Borrow<<KC as BytesEncode<'a>>::EItem>,
+        V: FromStr + for<'a> Borrow<<DC as BytesEncode<'a>>::EItem>,
+        K::Err: Into<BoxedError>,
+        V::Err: Into<BoxedError>,
+    {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .has_headers(mapping.has_headers)
+            .delimiter(mapping.delimiter)
+            .from_reader(reader);
+
+        let headers = if mapping.has_headers {
+            Some(reader.headers().map_err(|e| csv_error(e, Error::Decoding))?.clone())
+        } else {
+            None
+        };
+        let (key_column, value_column) = mapping.positions(headers.as_ref())?;
+        let flags = if mapping.append { PutFlags::APPEND } else { PutFlags::empty() };
+
+        let mut count = 0;
+        let mut record = ::csv::StringRecord::new();
+        while reader.read_record(&mut record).map_err(|e| csv_error(e, Error::Decoding))? {
+            let field = |column: usize| {
+                record.get(column).ok_or_else(|| {
+                    let line = record.position().map_or(0, |p| p.line());
+                    Error::Decoding(format!("missing column {column} on line {line}").into())
+                })
+            };
+            let key: K =
+                field(key_column)?.parse().map_err(|e: K::Err| Error::Decoding(e.into()))?;
+            let value: V =
+                field(value_column)?.parse().map_err(|e: V::Err| Error::Decoding(e.into()))?;
+            self.put_with_flags(wtxn, flags, key.borrow(), value.borrow())?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+/// Converts a csv error into an I/O error if it comes from the I/O,
+/// or uses `f` to wrap it otherwise.
+fn csv_error(error: ::csv::Error, f: fn(BoxedError) -> Error) -> Error {
+    if error.is_io_error() {
+        Error::Io(io::Error::from(error))
+    } else {
+        f(Box::new(error))
+    }
+}
+
+fn invalid_mapping(message: String) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Str, U64};
+    use crate::{EnvOpenOptions, MdbError};
+
+    #[test]
+    fn import_and_export() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, U64<byteorder::BigEndian>> =
+            env.create_database(&mut wtxn, Some("words"))?;
+
+        let file = "hello;12\n\"semi;colon\";3\n";
+        let mut mapping = Mapping::new();
+        mapping.has_headers(false).delimiter(b';');
+        assert_eq!(db.import_csv::<String, u64>(&mut wtxn, file.as_bytes(), &mapping)?, 2);
+        assert_eq!(db.get(&wtxn, "semi;colon")?, Some(3));
+
+        let mut export = Vec::new();
+        mapping.value_column(2);
+        db.export_csv(&wtxn, &mut export, &mapping)?;
+        assert_eq!(export, b"hello;;12\n\"semi;colon\";;3\n");
+
+        // A field that can't be parsed by the key type.
+        let file = "key,value\nword,not a number\n";
+        let result = db.import_csv::<String, u64>(&mut wtxn, file.as_bytes(), &Mapping::new());
+        assert!(matches!(result, Err(Error::Decoding(_))));
+
+        let result = db.import_csv::<String, u64>(
+            &mut wtxn,
+            file.as_bytes(),
+            Mapping::new().key_column("missing"),
+        );
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == io::ErrorKind::InvalidInput));
+
+        Ok(())
+    }
+
+    #[test]
+    fn append_sorted_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("sorted"))?;
+        let mut mapping = Mapping::new();
+        mapping.append(true);
+
+        let file = "key,value\na,1\nb,2\nc,3\n";
+        assert_eq!(db.import_csv::<String, String>(&mut wtxn, file.as_bytes(), &mapping)?, 3);
+        assert_eq!(db.len(&wtxn)?, 3);
+
+        let file = "key,value\nd,4\nb,5\n";
+        let result = db.import_csv::<String, String>(&mut wtxn, file.as_bytes(), &mapping);
+        assert!(matches!(result, Err(Error::Mdb(MdbError::KeyExist))));
+
+        Ok(())
+    }
+}
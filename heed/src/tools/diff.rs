@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+use std::marker;
+
+use heed_traits::{BytesDecode, Comparator};
+
+use crate::types::{Bytes, Lazy, LazyDecode};
+use crate::{Database, DatabaseFlags, Error, ReadTxn, Result, RoIter};
+
+/// Returns the differences between the entries of two databases, in the order of their keys.
+///
+/// The databases can belong to two environments or be read by two snapshots of the same
+/// environment. Their cursors are merged, the keys are compared with the comparator of the
+/// databases and decoded, the values are only compared as bytes and can be decoded on demand.
+/// In a `DUP_SORT` database, a changed duplicate is a removed and an added entry.
+///
+/// ```
+/// use heed::tools::{diff, Difference};
+/// use heed::types::*;
+/// use heed::EnvOpenOptions;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, U32<byteorder::BE>>(&mut wtxn, Some("stock"))?;
+/// db.put(&mut wtxn, "apples", &10)?;
+/// db.put(&mut wtxn, "pears", &3)?;
+/// wtxn.commit()?;
+///
+/// let before = env.read_txn()?;
+/// let mut wtxn = env.write_txn()?;
+/// db.put(&mut wtxn, "apples", &8)?;
+/// db.delete(&mut wtxn, "pears")?;
+/// db.put(&mut wtxn, "plums", &12)?;
+/// wtxn.commit()?;
+/// let after = env.read_txn()?;
+///
+/// let mut differences = diff(&db, &before, &db, &after)?;
+/// match differences.next().transpose()? {
+///     Some(Difference::Changed { key, old, new }) => {
+///         assert_eq!((key, old.decode()?, new.decode()?), ("apples", 10, 8));
+///     }
+///     _ => unreachable!(),
+/// }
+/// let keys: Vec<_> = differences
+///     .map(|difference| difference.map(|d| d.key().to_string()))
+///     .collect::<heed::Result<_>>()?;
+/// assert_eq!(keys, ["pears", "plums"]);
+/// # Ok(()) }
+/// ```
+pub fn diff<'a, KC, DC, C, CDUP>(
+    db_a: &Database<KC, DC, C, CDUP>,
+    rtxn_a: &'a impl ReadTxn,
+    db_b: &Database<KC, DC, C, CDUP>,
+    rtxn_b: &'a impl ReadTxn,
+) -> Result<Diff<'a, KC, DC, C, CDUP>> {
+    let dup_sort = db_a.database_flags(rtxn_a)?.contains(DatabaseFlags::DUP_SORT);
+    Ok(Diff {
+        a: Side::new(db_a.remap_types::<Bytes, Bytes>().iter(rtxn_a)?),
+        b: Side::new(db_b.remap_types::<Bytes, Bytes>().iter(rtxn_b)?),
+        dup_sort,
+        _marker: marker::PhantomData,
+    })
+}
+
+/// A difference between two databases, returned by [`diff`].
+///
+/// The values are decoded on demand with [`Lazy::decode`].
+pub enum Difference<'a, K, DC> {
+    /// The key is only in the second database.
+    Added {
+        /// The key.
+        key: K,
+        /// The value in the second database.
+        new: Lazy<'a, DC>,
+    },
+    /// The key is only in the first database.
+    Removed {
+        /// The key.
+        key: K,
+        /// The value in the first database.
+        old: Lazy<'a, DC>,
+    },
+    /// The key is in both databases with different values.
+    Changed {
+        /// The key.
+        key: K,
+        /// The value in the first database.
+        old: Lazy<'a, DC>,
+        /// The value in the second database.
+        new: Lazy<'a, DC>,
+    },
+}
+
+impl<K, DC> Difference<'_, K, DC> {
+    /// Returns the key of the difference.
+    pub fn key(&self) -> &K {
+        match self {
+            Difference::Added { key, .. }
+            | Difference::Removed { key, .. }
+            | Difference::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// The iterator of the differences between two databases, see [`diff`].
+pub struct Diff<'a, KC, DC, C, CDUP> {
+    a: Side<'a>,
+    b: Side<'a>,
+    dup_sort: bool,
+    _marker: marker::PhantomData<(KC, DC, C, CDUP)>,
+}
+
+/// The cursor of a database with the entry it is on.
+struct Side<'a> {
+    iter: RoIter<'a, Bytes, Bytes>,
+    head: Option<(&'a [u8], &'a [u8])>,
+    done: bool,
+}
+
+impl<'a> Side<'a> {
+    fn new(iter: RoIter<'a, Bytes, Bytes>) -> Side<'a> {
+        Side { iter, head: None, done: false }
+    }
+
+    fn peek(&mut self) -> Result<Option<(&'a [u8], &'a [u8])>> {
+        if self.head.is_none() && !self.done {
+            self.head = self.iter.next().transpose()?;
+            self.done = self.head.is_none();
+        }
+        Ok(self.head)
+    }
+}
+
+impl<'a, KC, DC, C, CDUP> Diff<'a, KC, DC, C, CDUP>
+where
+    KC: BytesDecode<'a>,
+    DC: 'static,
+    C: Comparator,
+    CDUP: Comparator,
+{
+    fn step(&mut self) -> Result<Option<Difference<'a, KC::DItem, DC>>> {
+        loop {
+            let ordering = match (self.a.peek()?, self.b.peek()?) {
+                (None, None) => return Ok(None),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((key_a, value_a)), Some((key_b, value_b))) => C::compare(key_a, key_b)
+                    .then_with(|| {
+                        if self.dup_sort {
+                            CDUP::compare(value_a, value_b)
+                        } else {
+                            Ordering::Equal
+                        }
+                    }),
+            };
+
+            let difference = match ordering {
+                Ordering::Less => {
+                    let (key, old) = self.a.head.take().unwrap();
+                    Difference::Removed { key: decode_key::<KC>(key)?, old: lazy(old) }
+                }
+                Ordering::Greater => {
+                    let (key, new) = self.b.head.take().unwrap();
+                    Difference::Added { key: decode_key::<KC>(key)?, new: lazy(new) }
+                }
+                Ordering::Equal => {
+                    let ((key, old), (_, new)) =
+                        (self.a.head.take().unwrap(), self.b.head.take().unwrap());
+                    if old == new {
+                        continue;
+                    }
+                    Difference::Changed {
+                        key: decode_key::<KC>(key)?,
+                        old: lazy(old),
+                        new: lazy(new),
+                    }
+                }
+            };
+            return Ok(Some(difference));
+        }
+    }
+}
+
+impl<'a, KC, DC, C, CDUP> Iterator for Diff<'a, KC, DC, C, CDUP>
+where
+    KC: BytesDecode<'a>,
+    DC: 'static,
+    C: Comparator,
+    CDUP: Comparator,
+{
+    type Item = Result<Difference<'a, KC::DItem, DC>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().transpose()
+    }
+}
+
+fn decode_key<'a, KC: BytesDecode<'a>>(key: &'a [u8]) -> Result<KC::DItem> {
+    KC::bytes_decode(key).map_err(Error::Decoding)
+}
+
+fn lazy<DC: 'static>(bytes: &[u8]) -> Lazy<'_, DC> {
+    // Wrapping the bytes never fails.
+    LazyDecode::<DC>::bytes_decode(bytes).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn diff_environments() -> Result<()> {
+        let (dir_a, dir_b) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        let env_a = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir_a.path())? };
+        let env_b = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir_b.path())? };
+
+        let mut wtxn = env_a.write_txn()?;
+        let a = env_a
+            .database_options()
+            .types::<Str, Str>()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        for (key, value) in [("a", "1"), ("b", "1"), ("b", "2"), ("d", "1")] {
+            a.put(&mut wtxn, key, value)?;
+        }
+        wtxn.commit()?;
+
+        let mut wtxn = env_b.write_txn()?;
+        let b = env_b
+            .database_options()
+            .types::<Str, Str>()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        for (key, value) in [("a", "1"), ("b", "2"), ("b", "3"), ("c", "1")] {
+            b.put(&mut wtxn, key, value)?;
+        }
+        wtxn.commit()?;
+
+        let (rtxn_a, rtxn_b) = (env_a.read_txn()?, env_b.read_txn()?);
+        let describe = |d: Difference<&str, Str>| match d {
+            Difference::Added { key, new } => format!("+{key}={}", new.decode().unwrap()),
+            Difference::Removed { key, old } => format!("-{key}={}", old.decode().unwrap()),
+            Difference::Changed { key, .. } => format!("~{key}"),
+        };
+        let differences: Vec<_> =
+            diff(&a, &rtxn_a, &b, &rtxn_b)?.map(|d| d.map(describe)).collect::<Result<_>>()?;
+        assert_eq!(differences, ["-b=1", "+b=3", "+c=1", "-d=1"]);
+
+        let differences: Vec<_> =
+            diff(&b, &rtxn_b, &a, &rtxn_a)?.map(|d| d.map(describe)).collect::<Result<_>>()?;
+        assert_eq!(differences, ["+b=1", "-b=3", "-c=1", "+d=1"]);
+        assert_eq!(diff(&a, &rtxn_a, &a, &rtxn_a)?.count(), 0);
+
+        // Without duplicates, a different value is a changed entry.
+        let mut wtxn = env_a.write_txn()?;
+        let plain = env_a.create_database::<Str, Str>(&mut wtxn, Some("plain"))?;
+        plain.put(&mut wtxn, "k", "old")?;
+        wtxn.commit()?;
+        let before = env_a.read_txn()?;
+        let mut wtxn = env_a.write_txn()?;
+        plain.put(&mut wtxn, "k", "new")?;
+        wtxn.commit()?;
+        let after = env_a.read_txn()?;
+        let differences: Vec<_> = diff(&plain, &before, &plain, &after)?.collect::<Result<_>>()?;
+        match &differences[..] {
+            [Difference::Changed { key: "k", old, new }] => {
+                assert_eq!((old.decode().unwrap(), new.decode().unwrap()), ("old", "new"));
+            }
+            _ => panic!("unexpected differences"),
+        }
+
+        Ok(())
+    }
+}
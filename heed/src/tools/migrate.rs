@@ -0,0 +1,289 @@
+use std::path::Path;
+
+use crate::types::Bytes;
+use crate::*;
+
+/// The number of entries inserted by each write transaction of a migration.
+const MIGRATION_BATCH_SIZE: usize = 10_000;
+
+/// The progress of a migration, reported after every committed batch of entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationProgress<'a> {
+    /// The name of the database being filled.
+    pub database: &'a str,
+    /// The number of entries of this database copied so far.
+    pub copied: u64,
+    /// The number of entries of this database, when the source store knows it.
+    pub total: Option<u64>,
+}
+
+/// Copies all the trees of a [sled](https://docs.rs/sled) store into the named databases
+/// of the same name, preserving the raw bytes of the keys and values.
+///
+/// The default tree of sled is copied into a database named `__sled__default`. Entries are
+/// added to the existing databases and a write transaction is committed every batch of
+/// entries, `progress` is called after each one. Returns the total number of copied entries.
+///
+/// Failures of sled are returned as [`Error::Decoding`], or [`Error::Io`] for I/O errors.
+/// Fails if a write transaction is already opened on this thread, and the environment
+/// must be able to open one named database per tree.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use heed::types::*;
+/// use heed::tools::migrate_from_sled;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// # let env = unsafe { EnvOpenOptions::new()
+/// #     .map_size(10 * 1024 * 1024) // 10MB
+/// #     .max_dbs(3000)
+/// #     .open(dir.path())?
+/// # };
+/// # let sled_dir = tempfile::tempdir()?;
+/// # let sled_path = sled_dir.path().join("store");
+/// let store = sled::open(&sled_path)?;
+/// store.open_tree("users")?.insert("kero", "28")?;
+/// drop(store);
+///
+/// let copied = migrate_from_sled(&env, &sled_path, |progress| {
+///     println!("{}: {} entries", progress.database, progress.copied);
+/// })?;
+/// assert_eq!(copied, 1);
+///
+/// let rtxn = env.read_txn()?;
+/// let users = env.open_database::<Str, Str>(&rtxn, Some("users"))?.unwrap();
+/// assert_eq!(users.get(&rtxn, "kero")?, Some("28"));
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "sled")]
+pub fn migrate_from_sled(
+    env: &Env<impl TlsUsage>,
+    path: impl AsRef<Path>,
+    mut progress: impl FnMut(MigrationProgress),
+) -> Result<u64> {
+    let store = sled::open(path).map_err(sled_error)?;
+
+    let mut copied = 0;
+    for name in store.tree_names() {
+        let name = std::str::from_utf8(&name).map_err(|e| Error::Decoding(Box::new(e)))?.to_owned();
+        let tree = store.open_tree(&name).map_err(sled_error)?;
+        let entries = tree.iter().map(|result| result.map_err(sled_error));
+        copied += copy_database(env, &name, None, entries, &mut progress)?;
+    }
+
+    Ok(copied)
+}
+
+/// Copies all the tables of a [redb](https://docs.rs/redb) store into the named databases
+/// of the same name, preserving the raw bytes of the keys and values.
+///
+/// The tables must have been created with `&[u8]` keys and values, the other tables
+/// must be copied with [`migrate_redb_table`] and multimap tables are not copied.
+/// All the tables are opened before copying anything, the migration fails with
+/// [`Error::Decoding`] without copying anything if one of them has different types.
+///
+/// Entries are added to the existing databases and a write transaction is committed
+/// every batch of entries, `progress` is called after each one. Returns the total number
+/// of copied entries. Fails if a write transaction is already opened on this thread,
+/// and the environment must be able to open one named database per table.
+#[cfg(feature = "redb")]
+pub fn migrate_from_redb(
+    env: &Env<impl TlsUsage>,
+    path: impl AsRef<Path>,
+    mut progress: impl FnMut(MigrationProgress),
+) -> Result<u64> {
+    use redb::{ReadableTable, ReadableTableMetadata, TableHandle};
+
+    let store = redb::Database::open(path).map_err(redb_error)?;
+    let rtxn = store.begin_read().map_err(redb_error)?;
+
+    let names: Vec<String> =
+        rtxn.list_tables().map_err(redb_error)?.map(|handle| handle.name().to_owned()).collect();
+    let mut tables = Vec::with_capacity(names.len());
+    for name in &names {
+        let definition = redb::TableDefinition::<&[u8], &[u8]>::new(name);
+        tables.push(rtxn.open_table(definition).map_err(redb_error)?);
+    }
+
+    let mut copied = 0;
+    for (name, table) in names.iter().zip(tables) {
+        let total = table.len().map_err(redb_error)?;
+        let entries = table.iter().map_err(redb_error)?.map(|result| {
+            let (key, value) = result.map_err(redb_error)?;
+            Ok((key.value().to_vec(), value.value().to_vec()))
+        });
+        copied += copy_database(env, name, Some(total), entries, &mut progress)?;
+    }
+
+    Ok(copied)
+}
+
+/// Copies a table of an opened [redb](https://docs.rs/redb) store into the named
+/// database of the same name.
+///
+/// The keys and values are stored as encoded by redb for the `K` and `V` types of the table,
+/// e.g. little-endian integers, and can be read back with the matching codecs. Entries are
+/// added to the existing database and a write transaction is committed every batch of
+/// entries, `progress` is called after each one. Returns the number of copied entries.
+#[cfg(feature = "redb")]
+pub fn migrate_redb_table<K, V>(
+    env: &Env<impl TlsUsage>,
+    store: &redb::Database,
+    definition: redb::TableDefinition<K, V>,
+    mut progress: impl FnMut(MigrationProgress),
+) -> Result<u64>
+where
+    K: redb::Key + 'static,
+    V: redb::Value + 'static,
+{
+    use redb::{ReadableTable, ReadableTableMetadata, TableHandle};
+
+    let rtxn = store.begin_read().map_err(redb_error)?;
+    let table = rtxn.open_table(definition).map_err(redb_error)?;
+    let total = table.len().map_err(redb_error)?;
+    let entries = table.iter().map_err(redb_error)?.map(|result| {
+        let (key, value) = result.map_err(redb_error)?;
+        let (key, value) = (key.value(), value.value());
+        let key = K::as_bytes(&key).as_ref().to_vec();
+        let value = V::as_bytes(&value).as_ref().to_vec();
+        Ok((key, value))
+    });
+    copy_database(env, definition.name(), Some(total), entries, &mut progress)
+}
+
+/// Copies the entries into the named database, committing every batch of entries.
+fn copy_database<K, V>(
+    env: &Env<impl TlsUsage>,
+    name: &str,
+    total: Option<u64>,
+    entries: impl Iterator<Item = Result<(K, V)>>,
+    progress: &mut impl FnMut(MigrationProgress),
+) -> Result<u64>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let mut wtxn = env.write_txn()?;
+    let database: Database<Bytes, Bytes> = env.create_database(&mut wtxn, Some(name))?;
+
+    let mut copied = 0;
+    for result in entries {
+        let (key, value) = result?;
+        database.put(&mut wtxn, key.as_ref(), value.as_ref())?;
+        copied += 1;
+        if copied % MIGRATION_BATCH_SIZE as u64 == 0 {
+            wtxn.commit()?;
+            progress(MigrationProgress { database: name, copied, total });
+            wtxn = env.write_txn()?;
+        }
+    }
+
+    wtxn.commit()?;
+    if copied % MIGRATION_BATCH_SIZE as u64 != 0 || copied == 0 {
+        progress(MigrationProgress { database: name, copied, total });
+    }
+
+    Ok(copied)
+}
+
+#[cfg(feature = "sled")]
+fn sled_error(error: sled::Error) -> Error {
+    match error {
+        sled::Error::Io(error) => Error::Io(error),
+        error => Error::Decoding(Box::new(error)),
+    }
+}
+
+#[cfg(feature = "redb")]
+fn redb_error(error: impl Into<redb::Error>) -> Error {
+    match error.into() {
+        redb::Error::Io(error) => Error::Io(error),
+        error => Error::Decoding(Box::new(error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "sled")]
+    fn migrate_sled_trees() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let sled_dir = tempfile::tempdir()?;
+        let store = sled::open(sled_dir.path()).unwrap();
+        store.insert(b"default", b"tree").unwrap();
+        let tree = store.open_tree("numbers").unwrap();
+        for i in 0..MIGRATION_BATCH_SIZE as u32 + 10 {
+            tree.insert(i.to_be_bytes(), &[0xff]).unwrap();
+        }
+        drop((tree, store));
+
+        let mut reports = Vec::new();
+        let copied = migrate_from_sled(&env, sled_dir.path(), |progress| {
+            reports.push((progress.database.to_owned(), progress.copied));
+        })?;
+        assert_eq!(copied, MIGRATION_BATCH_SIZE as u64 + 11);
+        assert_eq!(
+            reports,
+            [
+                ("__sled__default".to_owned(), 1),
+                ("numbers".to_owned(), MIGRATION_BATCH_SIZE as u64),
+                ("numbers".to_owned(), MIGRATION_BATCH_SIZE as u64 + 10),
+            ]
+        );
+
+        let rtxn = env.read_txn()?;
+        let numbers = env.open_database::<Bytes, Bytes>(&rtxn, Some("numbers"))?.unwrap();
+        assert_eq!(numbers.len(&rtxn)?, MIGRATION_BATCH_SIZE as u64 + 10);
+        assert_eq!(numbers.get(&rtxn, &7u32.to_be_bytes())?, Some(&[0xff][..]));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "redb")]
+    fn migrate_redb_tables() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        const RAW: redb::TableDefinition<&[u8], &[u8]> = redb::TableDefinition::new("raw");
+        const TYPED: redb::TableDefinition<u64, &str> = redb::TableDefinition::new("typed");
+
+        let redb_dir = tempfile::tempdir()?;
+        let path = redb_dir.path().join("store.redb");
+        let store = redb::Database::create(&path).unwrap();
+        let wtxn = store.begin_write().unwrap();
+        wtxn.open_table(RAW).unwrap().insert(&b"hello"[..], &b"world"[..]).unwrap();
+        wtxn.commit().unwrap();
+        drop(store);
+
+        assert_eq!(migrate_from_redb(&env, &path, |_| ())?, 1);
+
+        let store = redb::Database::open(&path).unwrap();
+        let wtxn = store.begin_write().unwrap();
+        wtxn.open_table(TYPED).unwrap().insert(42, "answer").unwrap();
+        wtxn.commit().unwrap();
+        drop(store);
+
+        // The typed table can't be read as raw bytes.
+        let result = migrate_from_redb(&env, &path, |_| ());
+        assert!(matches!(result, Err(Error::Decoding(_))));
+
+        let store = redb::Database::open(&path).unwrap();
+        assert_eq!(migrate_redb_table(&env, &store, TYPED, |_| ())?, 1);
+
+        let rtxn = env.read_txn()?;
+        let raw = env.open_database::<Bytes, Bytes>(&rtxn, Some("raw"))?.unwrap();
+        assert_eq!(raw.get(&rtxn, b"hello")?, Some(&b"world"[..]));
+        let typed = env
+            .open_database::<types::U64<byteorder::LittleEndian>, types::Str>(&rtxn, Some("typed"))?
+            .unwrap();
+        assert_eq!(typed.get(&rtxn, &42)?, Some("answer"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+//! Utilities to move data in and out of LMDB environments and to compare them.
+
+#[cfg(feature = "csv")]
+pub mod csv;
+mod diff;
+#[cfg(any(feature = "sled", feature = "redb"))]
+mod migrate;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use self::diff::{diff, Diff, Difference};
+#[cfg(feature = "sled")]
+pub use self::migrate::migrate_from_sled;
+#[cfg(any(feature = "sled", feature = "redb"))]
+pub use self::migrate::MigrationProgress;
+#[cfg(feature = "redb")]
+pub use self::migrate::{migrate_from_redb, migrate_redb_table};
+
+/// A column of a file or of a query result, designated by its position or by its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    /// The zero-based position of the column.
+    Index(usize),
+    /// The name of the column, e.g. in the header row of a CSV file.
+    Name(String),
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Column {
+        Column::Index(index)
+    }
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Column {
+        Column::Name(name.to_owned())
+    }
+}
+
+impl From<String> for Column {
+    fn from(name: String) -> Column {
+        Column::Name(name)
+    }
+}
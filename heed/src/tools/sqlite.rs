@@ -0,0 +1,207 @@
+//! Import the results of SQLite queries into databases.
+//!
+//! Every row returned by the query becomes an entry, the key is read from a column
+//! and the value is built from the row by a function, the codec of the database then
+//! decides how the value is serialized. The rows are streamed from SQLite and the
+//! entries are committed in batches, see [`Database::import_sqlite`].
+//!
+//! ```
+//! # use heed::EnvOpenOptions;
+//! use heed::Database;
+//! use heed::tools::sqlite::ImportOptions;
+//! use heed::types::*;
+//! use heed::byteorder::BigEndian;
+//! use rusqlite::Connection;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = tempfile::tempdir()?;
+//! # let env = unsafe { EnvOpenOptions::new()
+//! #     .map_size(10 * 1024 * 1024) // 10MB
+//! #     .max_dbs(3000)
+//! #     .open(dir.path())?
+//! # };
+//! let connection = Connection::open_in_memory()?;
+//! connection.execute_batch(
+//!     "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER);
+//!      INSERT INTO users VALUES (1, 'kero', 28), (2, 'tamo', 27);",
+//! )?;
+//!
+//! let mut wtxn = env.write_txn()?;
+//! let db: Database<U32<BigEndian>, SerdeJson<(String, u8)>> =
+//!     env.create_database(&mut wtxn, Some("users"))?;
+//! wtxn.commit()?;
+//!
+//! let mut options = ImportOptions::new();
+//! options.key_column("id").batch_size(1000);
+//! let count = db.import_sqlite::<u32, (String, u8), _>(
+//!     &env,
+//!     &connection,
+//!     "SELECT id, name, age FROM users",
+//!     &options,
+//!     |row| Ok((row.get("name")?, row.get("age")?)),
+//! )?;
+//! assert_eq!(count, 2);
+//!
+//! let rtxn = env.read_txn()?;
+//! assert_eq!(db.get(&rtxn, &1)?, Some((String::from("kero"), 28)));
+//! # Ok(()) }
+//! ```
+
+use std::borrow::Borrow;
+
+use rusqlite::types::FromSql;
+use rusqlite::{Connection, Row};
+
+use super::Column;
+use crate::*;
+
+/// The options of a SQLite import.
+///
+/// By default the keys are read from the first column
+/// and a write transaction is committed every 10 000 rows.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    key: Column,
+    batch_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImportOptions {
+    /// Creates the default options.
+    pub fn new() -> ImportOptions {
+        ImportOptions { key: Column::Index(0), batch_size: 10_000 }
+    }
+
+    /// Sets the column of the query results containing the keys.
+    pub fn key_column(&mut self, column: impl Into<Column>) -> &mut Self {
+        self.key = column.into();
+        self
+    }
+
+    /// Sets the number of rows inserted by each write transaction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the batch size is zero.
+    pub fn batch_size(&mut self, batch_size: usize) -> &mut Self {
+        assert!(batch_size > 0, "the batch size must be greater than zero");
+        self.batch_size = batch_size;
+        self
+    }
+}
+
+impl<KC, DC, C, CDUP> Database<KC, DC, C, CDUP> {
+    /// Inserts the rows returned by a SQLite query into this database.
+    ///
+    /// The key of every row is read from the key column of the options as a `K`,
+    /// the owned version of the key codec item, e.g. `String` for a [`Str`](types::Str) codec,
+    /// and the value is built from the row by the `value` function. A write transaction
+    /// is committed every batch of rows, the entries of the previous batches therefore stay
+    /// in the database if the import fails. Returns the number of inserted entries.
+    ///
+    /// Failures of SQLite, including the conversion of the columns, are returned as
+    /// [`Error::Decoding`]. Fails if a write transaction is already opened on this thread.
+    pub fn import_sqlite<K, V, F>(
+        &self,
+        env: &Env<impl TlsUsage>,
+        connection: &Connection,
+        query: &str,
+        options: &ImportOptions,
+        mut value: F,
+    ) -> Result<usize>
+    where
+        KC: for<'a> BytesEncode<'a>,
+        DC: for<'a> BytesEncode<'a>,
+        K: FromSql + for<'a> Borrow<<KC as BytesEncode<'a>>::EItem>,
+        V: for<'a> Borrow<<DC as BytesEncode<'a>>::EItem>,
+        F: FnMut(&Row) -> rusqlite::Result<V>,
+    {
+        let mut statement = connection.prepare(query).map_err(sqlite_error)?;
+        let mut rows = statement.query([]).map_err(sqlite_error)?;
+
+        let mut wtxn = env.write_txn()?;
+        check_env_db_txn!(self, wtxn);
+
+        let mut count = 0;
+        while let Some(row) = rows.next().map_err(sqlite_error)? {
+            let key: K = match &options.key {
+                Column::Index(index) => row.get(*index),
+                Column::Name(name) => row.get(name.as_str()),
+            }
+            .map_err(sqlite_error)?;
+            let value = value(row).map_err(sqlite_error)?;
+            self.put(&mut wtxn, key.borrow(), value.borrow())?;
+
+            count += 1;
+            if count % options.batch_size == 0 {
+                wtxn.commit()?;
+                wtxn = env.write_txn()?;
+            }
+        }
+
+        wtxn.commit()?;
+        Ok(count)
+    }
+}
+
+fn sqlite_error(error: rusqlite::Error) -> Error {
+    Error::Decoding(Box::new(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Str;
+
+    #[test]
+    fn import_in_batches() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE words (word TEXT, definition TEXT);
+                 INSERT INTO words VALUES ('a', 'first'), ('b', 'second'), ('c', 'third'),
+                                          ('d', 'fourth'), ('e', NULL);",
+            )
+            .unwrap();
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("words"))?;
+        wtxn.commit()?;
+
+        let mut options = ImportOptions::new();
+        options.batch_size(2);
+        let query = "SELECT word, definition FROM words WHERE definition IS NOT NULL";
+        let count =
+            db.import_sqlite::<String, String, _>(&env, &connection, query, &options, |row| {
+                row.get(1)
+            })?;
+        assert_eq!(count, 4);
+
+        let rtxn = env.read_txn()?;
+        assert_eq!(db.len(&rtxn)?, 4);
+        assert_eq!(db.get(&rtxn, "d")?, Some("fourth"));
+        drop(rtxn);
+
+        // A NULL can't be converted into a String, the first batches are kept.
+        let mut wtxn = env.write_txn()?;
+        db.clear(&mut wtxn)?;
+        wtxn.commit()?;
+        let query = "SELECT word, definition FROM words";
+        let result =
+            db.import_sqlite::<String, String, _>(&env, &connection, query, &options, |row| {
+                row.get("definition")
+            });
+        assert!(matches!(result, Err(Error::Decoding(_))));
+        assert_eq!(db.len(&env.read_txn()?)?, 4);
+
+        Ok(())
+    }
+}
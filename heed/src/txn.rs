@@ -1,13 +1,23 @@
 use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::marker::PhantomData;
 use std::ops::Deref;
 use std::ptr::{self, NonNull};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{io, mem};
 
+use crate::changes::{Change, ChangeRecorder};
 use crate::envs::{Env, EnvInner};
 use crate::mdb::error::mdb_result;
 use crate::mdb::ffi;
-use crate::Result;
+use crate::observer::TxnObservation;
+use crate::paranoid::ValueCopies;
+#[cfg(any(test, feature = "testing"))]
+use crate::testing::faults::Boundary;
+use crate::{
+    BytesDecode, BytesEncode, Database, DatabaseAny, EnvFlags, Error, Result, SlowTxnState,
+};
 
 /// A trait for transactions that support read operations.
 ///
@@ -22,6 +32,43 @@ pub unsafe trait ReadTxn {
 
     /// Returns the raw LMDB environment pointer.
     fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env>;
+
+    /// Returns whether the transaction is shared between threads, see [`SyncRoTxn`].
+    #[doc(hidden)]
+    fn is_shared(&self) -> bool {
+        false
+    }
+
+    /// Returns the generation of the environment, see [`Error::EnvClosed`](crate::Error::EnvClosed).
+    #[doc(hidden)]
+    fn env_generation(&self) -> u64 {
+        crate::envs::env_generation(self.env_mut_ptr())
+    }
+
+    /// Returns the metrics of the transaction, see [`Env::set_observer`].
+    #[doc(hidden)]
+    fn observation(&self) -> Option<&TxnObservation> {
+        None
+    }
+
+    /// Checks that the transaction can still be used, see [`RwTxn::checkpoint`].
+    #[doc(hidden)]
+    fn check_usable(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Checks that the transaction can use the database, see [`RwTxn::split_with`].
+    #[doc(hidden)]
+    fn check_dbi(&self, dbi: ffi::MDB_dbi) -> Result<()> {
+        let _ = dbi;
+        Ok(())
+    }
+
+    /// Returns the copies of the values returned by the transaction, if they must be copied.
+    #[doc(hidden)]
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        None
+    }
 }
 
 /// A marker trait for transactions that support write operations.
@@ -30,7 +77,41 @@ pub unsafe trait ReadTxn {
 ///
 /// Implementors must ensure the underlying `MDB_txn` was opened without
 /// `MDB_RDONLY`.
-pub unsafe trait WriteTxn: ReadTxn {}
+pub unsafe trait WriteTxn: ReadTxn {
+    /// Returns the recorder of the changes, if the transaction captures its changes.
+    #[doc(hidden)]
+    fn change_recorder(&mut self) -> Option<&mut ChangeRecorder> {
+        None
+    }
+
+    /// Called before every write to the database through the transaction, `drop` is whether
+    /// the database is cleared or removed. See [`Guarded`] and [`RwTxn::split`].
+    #[doc(hidden)]
+    fn before_write(&mut self, dbi: ffi::MDB_dbi, drop: bool) -> Result<()> {
+        let _ = (dbi, drop);
+        Ok(())
+    }
+
+    /// Returns the buffer reused to encode the keys and values written through the
+    /// transaction, see [`BytesEncode::bytes_encode_into`].
+    #[doc(hidden)]
+    fn scratch_buffer(&self) -> Option<&Cell<Vec<u8>>> {
+        None
+    }
+}
+
+/// Takes the buffer reused by the transaction to encode, a new one if it has none.
+pub(crate) fn take_scratch<T: WriteTxn + ?Sized>(txn: &T) -> Vec<u8> {
+    txn.scratch_buffer().map(Cell::take).unwrap_or_default()
+}
+
+/// Gives the buffer back to the transaction, for the next write to reuse it.
+pub(crate) fn give_back_scratch<T: WriteTxn + ?Sized>(txn: &T, mut buffer: Vec<u8>) {
+    if let Some(scratch) = txn.scratch_buffer() {
+        buffer.clear();
+        scratch.set(buffer);
+    }
+}
 
 // Implement ReadTxn generically for all RoTxn<T> — the T marker (AnyTls,
 // WithTls, WithoutTls) affects only PhantomData, not the inner layout.
@@ -42,6 +123,18 @@ unsafe impl<T> ReadTxn for RoTxn<'_, T> {
     fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
         self.inner.env.env_mut_ptr()
     }
+
+    fn env_generation(&self) -> u64 {
+        self.inner.env.generation
+    }
+
+    fn observation(&self) -> Option<&TxnObservation> {
+        self.inner.observation.as_deref()
+    }
+
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        cfg!(feature = "paranoid-copies").then_some(&self.inner.copies)
+    }
 }
 
 unsafe impl ReadTxn for RwTxn<'_> {
@@ -52,9 +145,36 @@ unsafe impl ReadTxn for RwTxn<'_> {
     fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
         self.txn.inner.env.env_mut_ptr()
     }
+
+    fn env_generation(&self) -> u64 {
+        self.txn.inner.env.generation
+    }
+
+    fn observation(&self) -> Option<&TxnObservation> {
+        self.txn.inner.observation.as_deref()
+    }
+
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        cfg!(feature = "paranoid-copies").then_some(&self.txn.inner.copies)
+    }
+
+    fn check_usable(&self) -> Result<()> {
+        match self.txn.inner.txn {
+            Some(_) => Ok(()),
+            None => Err(Error::TxnPoisoned),
+        }
+    }
 }
 
-unsafe impl WriteTxn for RwTxn<'_> {}
+unsafe impl WriteTxn for RwTxn<'_> {
+    fn change_recorder(&mut self) -> Option<&mut ChangeRecorder> {
+        self.changes.as_mut()
+    }
+
+    fn scratch_buffer(&self) -> Option<&Cell<Vec<u8>>> {
+        Some(&self.scratch)
+    }
+}
 
 /// A read-only transaction.
 ///
@@ -104,6 +224,44 @@ struct RoTxnInner<'e> {
     /// Makes the struct covariant and !Sync
     pub(crate) txn: Option<NonNull<ffi::MDB_txn>>,
     env: Cow<'e, Arc<EnvInner>>,
+    /// The metrics of the transaction, if the environment has an observer.
+    observation: Option<Arc<TxnObservation>>,
+    /// The identifier of the transaction in the tracker of the slow transactions.
+    tracked: Option<u64>,
+    /// The identifier of the read transaction in the live readers of the environment.
+    #[cfg(feature = "track-read-txns")]
+    reader: Option<u64>,
+    /// The copies of the values returned by the transaction, freed when it ends.
+    copies: ValueCopies,
+}
+
+impl<'e> RoTxnInner<'e> {
+    fn new(txn: NonNull<ffi::MDB_txn>, env: Cow<'e, Arc<EnvInner>>, write: bool) -> RoTxnInner<'e> {
+        let observation = TxnObservation::start(&env, txn);
+        let tracked = env.slow_txns.as_ref().map(|tracker| tracker.track(write));
+        #[cfg(feature = "track-read-txns")]
+        let reader = (!write).then(|| env.live_readers.insert());
+        RoTxnInner {
+            txn: Some(txn),
+            env,
+            observation,
+            tracked,
+            #[cfg(feature = "track-read-txns")]
+            reader,
+            copies: ValueCopies::default(),
+        }
+    }
+
+    /// Stops tracking the transaction, it is reported if it was slow.
+    fn finish(&mut self, state: SlowTxnState) {
+        if let (Some(id), Some(tracker)) = (self.tracked.take(), &self.env.slow_txns) {
+            tracker.finish(id, state);
+        }
+        #[cfg(feature = "track-read-txns")]
+        if let Some(id) = self.reader.take() {
+            self.env.live_readers.remove(id);
+        }
+    }
 }
 
 impl<'e, T> RoTxn<'e, T> {
@@ -120,7 +278,7 @@ impl<'e, T> RoTxn<'e, T> {
         };
 
         Ok(RoTxn {
-            inner: RoTxnInner { txn: NonNull::new(txn), env: Cow::Borrowed(&env.inner) },
+            inner: RoTxnInner::new(NonNull::new(txn).unwrap(), Cow::Borrowed(&env.inner), false),
             _tls_marker: PhantomData,
         })
     }
@@ -138,11 +296,42 @@ impl<'e, T> RoTxn<'e, T> {
         };
 
         Ok(RoTxn {
-            inner: RoTxnInner { txn: NonNull::new(txn), env: Cow::Owned(env.inner) },
+            inner: RoTxnInner::new(NonNull::new(txn).unwrap(), Cow::Owned(env.inner), false),
+            _tls_marker: PhantomData,
+        })
+    }
+
+    /// Renews a transaction previously reset by [`RoTxn::into_reset`].
+    ///
+    /// The transaction is aborted if it can't be renewed.
+    pub(crate) fn renew(env: &'e Env<T>, mut txn: NonNull<ffi::MDB_txn>) -> Result<RoTxn<'e, T>> {
+        if let Err(error) = unsafe { mdb_result(ffi::mdb_txn_renew(txn.as_mut())) } {
+            unsafe { ffi::mdb_txn_abort(txn.as_mut()) };
+            return Err(error.into());
+        }
+
+        Ok(RoTxn {
+            inner: RoTxnInner::new(txn, Cow::Borrowed(&env.inner), false),
             _tls_marker: PhantomData,
         })
     }
 
+    /// Returns the transaction without its thread local storage usage, like the `Deref` impls.
+    #[cfg_attr(not(any(test, feature = "testing")), allow(dead_code))]
+    pub(crate) fn as_any_tls(&self) -> &RoTxn<'e, AnyTls> {
+        // SAFETY: OK because repr(transparent) means RoTxn<T> always has the same layout
+        // as RoTxnInner.
+        unsafe { std::mem::transmute(self) }
+    }
+
+    /// Releases the snapshot of the transaction but keeps its handle to be renewed later,
+    /// the handle must be renewed or aborted before the environment is closed.
+    pub(crate) fn into_reset(mut self) -> NonNull<ffi::MDB_txn> {
+        let mut txn = self.inner.txn.take().unwrap();
+        unsafe { ffi::mdb_txn_reset(txn.as_mut()) };
+        txn
+    }
+
     pub(crate) fn txn_ptr(&self) -> NonNull<ffi::MDB_txn> {
         self.inner.txn.unwrap()
     }
@@ -170,6 +359,9 @@ impl<'e, T> RoTxn<'e, T> {
         // committed/aborter and ensure we cannot use it twice.
         let mut txn = self.inner.txn.take().unwrap();
         let result = unsafe { mdb_result(ffi::mdb_txn_commit(txn.as_mut())) };
+        if result.is_ok() {
+            self.inner.finish(SlowTxnState::Committed);
+        }
         result.map_err(Into::into)
     }
 }
@@ -219,6 +411,10 @@ impl<T> Drop for RoTxn<'_, T> {
             // committed/aborter and ensure we cannot use it twice.
             unsafe { ffi::mdb_txn_abort(txn.as_mut()) }
         }
+        if let Some(observation) = self.inner.observation.take() {
+            observation.read_txn_ended();
+        }
+        self.inner.finish(SlowTxnState::Aborted);
     }
 }
 
@@ -279,6 +475,101 @@ impl TlsUsage for AnyTls {
 /// Is sendable only if `MDB_NOTLS` has been used to open this transaction.
 unsafe impl Send for RoTxn<'_, WithoutTls> {}
 
+/// A read transaction that can be shared between threads, to read the same snapshot
+/// from many threads without opening a transaction in each of them.
+///
+/// A [`RoTxn`] isn't `Sync`, even when opened without thread local storage: LMDB updates
+/// the state of a transaction the first time it reads a named database and when a database
+/// is opened with it. A `SyncRoTxn` performs the first read of every database opened in the
+/// environment when it is created, the reads through it then leave the transaction unchanged.
+/// Opening a database with a `SyncRoTxn` fails, the databases opened after its creation
+/// can't be read with it.
+///
+/// ```
+/// # use heed::EnvOpenOptions;
+/// use std::thread;
+/// use heed::types::*;
+/// use heed::SyncRoTxn;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// let env = unsafe { EnvOpenOptions::new().read_txn_without_tls().max_dbs(10).open(dir.path())? };
+/// let mut wtxn = env.write_txn()?;
+/// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+/// db.put(&mut wtxn, "kero", "admin")?;
+/// wtxn.commit()?;
+///
+/// let rtxn = SyncRoTxn::new(env.read_txn()?);
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| assert_eq!(db.get(&rtxn, "kero").unwrap(), Some("admin")));
+///     }
+/// });
+/// # Ok(()) }
+/// ```
+pub struct SyncRoTxn<'e> {
+    txn: RoTxn<'e, WithoutTls>,
+}
+
+impl<'e> SyncRoTxn<'e> {
+    /// Prepares a read transaction opened without thread local storage to be shared.
+    pub fn new(txn: RoTxn<'e, WithoutTls>) -> SyncRoTxn<'e> {
+        let dbis: Vec<_> = txn.inner.env.database_names.read().unwrap().keys().copied().collect();
+        for dbi in dbis {
+            // Reading the statistics of a database refreshes its state in the transaction,
+            // the databases opened after the transaction are invalid for it and are ignored.
+            let mut stat = std::mem::MaybeUninit::uninit();
+            unsafe { ffi::mdb_stat(txn.txn_ptr().as_ptr(), dbi, stat.as_mut_ptr()) };
+        }
+        SyncRoTxn { txn }
+    }
+
+    /// Return the transaction's ID, see [`RoTxn::id`].
+    pub fn id(&self) -> usize {
+        self.txn.id()
+    }
+
+    /// Returns the read transaction, which can't be shared anymore.
+    pub fn into_inner(self) -> RoTxn<'e, WithoutTls> {
+        self.txn
+    }
+}
+
+unsafe impl ReadTxn for SyncRoTxn<'_> {
+    fn txn_ptr(&self) -> NonNull<ffi::MDB_txn> {
+        self.txn.txn_ptr()
+    }
+
+    fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
+        self.txn.inner.env.env_mut_ptr()
+    }
+
+    fn env_generation(&self) -> u64 {
+        self.txn.inner.env.generation
+    }
+
+    fn observation(&self) -> Option<&TxnObservation> {
+        self.txn.inner.observation.as_deref()
+    }
+
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        cfg!(feature = "paranoid-copies").then_some(&self.txn.inner.copies)
+    }
+
+    fn is_shared(&self) -> bool {
+        true
+    }
+}
+
+/// The transaction is left unchanged by the reads and can't be used to open databases.
+unsafe impl Sync for SyncRoTxn<'_> {}
+
+impl std::fmt::Debug for SyncRoTxn<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SyncRoTxn").field("id", &self.id()).finish()
+    }
+}
+
 /// A read-write transaction.
 ///
 /// ## LMDB Limitations
@@ -318,30 +609,50 @@ unsafe impl Send for RoTxn<'_, WithoutTls> {}
 /// ```
 pub struct RwTxn<'p> {
     pub(crate) txn: RoTxn<'p, WithoutTls>,
+    /// The changes recorded by this transaction, if the capture is enabled.
+    changes: Option<ChangeRecorder>,
+    /// The recorder of the parent transaction, for nested transactions.
+    parent_changes: Option<&'p mut ChangeRecorder>,
+    /// Whether the transaction is nested in another one, see [`RwTxn::checkpoint`].
+    nested: bool,
+    /// The time spent waiting for the writer lock, see [`RwTxn::lock_wait`].
+    lock_wait: Duration,
+    /// The uses of the databases by the halves of the split transaction.
+    split: SplitState,
+    /// The buffer reused to encode the keys and values written through the transaction.
+    scratch: Cell<Vec<u8>>,
 }
 
 impl<'p> RwTxn<'p> {
-    pub(crate) fn new<T>(env: &'p Env<T>) -> Result<RwTxn<'p>> {
-        let mut txn: *mut ffi::MDB_txn = ptr::null_mut();
-
-        unsafe {
-            mdb_result(ffi::mdb_txn_begin(
-                env.env_mut_ptr().as_mut(),
-                ptr::null_mut(),
-                0,
-                &mut txn,
-            ))?
+    pub(crate) fn new<T>(env: &'p Env<T>, deadline: Option<Instant>) -> Result<RwTxn<'p>> {
+        let lock_wait = env.inner.writer_lock.acquire(deadline)?;
+        let txn = match begin_write_txn(&env.inner) {
+            Ok(txn) => txn,
+            Err(error) => {
+                env.inner.writer_lock.release();
+                return Err(error);
+            }
         };
 
-        Ok(RwTxn {
+        let mut wtxn = RwTxn {
             txn: RoTxn {
-                inner: RoTxnInner { txn: NonNull::new(txn), env: Cow::Borrowed(&env.inner) },
+                inner: RoTxnInner::new(txn, Cow::Borrowed(&env.inner), true),
                 _tls_marker: PhantomData,
             },
-        })
+            changes: None,
+            parent_changes: None,
+            nested: false,
+            lock_wait,
+            split: SplitState::default(),
+            scratch: Cell::default(),
+        };
+        wtxn.changes = ChangeRecorder::start(env, &mut wtxn)?;
+
+        Ok(wtxn)
     }
 
     pub(crate) fn nested<T>(env: &'p Env<T>, parent: &'p mut RwTxn) -> Result<RwTxn<'p>> {
+        parent.check_usable()?;
         let mut txn: *mut ffi::MDB_txn = ptr::null_mut();
         let parent_ptr: *mut ffi::MDB_txn = unsafe { parent.txn.inner.txn.unwrap().as_mut() };
 
@@ -351,9 +662,23 @@ impl<'p> RwTxn<'p> {
 
         Ok(RwTxn {
             txn: RoTxn {
-                inner: RoTxnInner { txn: NonNull::new(txn), env: Cow::Borrowed(&env.inner) },
+                inner: RoTxnInner {
+                    txn: NonNull::new(txn),
+                    env: Cow::Borrowed(&env.inner),
+                    observation: parent.txn.inner.observation.clone(),
+                    tracked: None,
+                    #[cfg(feature = "track-read-txns")]
+                    reader: None,
+                    copies: ValueCopies::default(),
+                },
                 _tls_marker: PhantomData,
             },
+            changes: parent.changes.as_ref().map(ChangeRecorder::nested),
+            parent_changes: parent.changes.as_mut(),
+            nested: true,
+            lock_wait: Duration::ZERO,
+            split: SplitState::default(),
+            scratch: Cell::default(),
         })
     }
 
@@ -373,8 +698,9 @@ impl<'p> RwTxn<'p> {
     /// go to heap-allocated copy-on-write pages, leaving existing read cursors
     /// valid.  In `WRITEMAP` mode, writes are done in-place through an
     /// mmap'd region, which *can* invalidate pointers held by read cursors on
-    /// the **same** database.  Since `WRITEMAP` is behind an `unsafe` API,
-    /// that responsibility falls on the caller.
+    /// the **same** database.  The keys and values returned by the `ReadHalf`
+    /// of a `WRITEMAP` environment are therefore copied in buffers owned by the
+    /// transaction, the cursors themselves remain the caller's responsibility.
     ///
     /// # Hazards — Read This Before Using
     ///
@@ -396,6 +722,11 @@ impl<'p> RwTxn<'p> {
     ///    same-database access. The first write to each named DB triggers
     ///    `mdb_cursor_touch` which copy-on-writes `MAIN_DBI` pages,
     ///    corrupting any cursor registered on `MAIN_DBI` by the `ReadHalf`.
+    ///    The split refuses these uses with [`Error::DbNotAllowedInHalf`]:
+    ///    the writes to named databases once the `ReadHalf` used the unnamed
+    ///    one, the uses of the unnamed database by the `ReadHalf` once named
+    ///    databases were written, and clearing or removing the unnamed database
+    ///    once the `ReadHalf` used named ones.
     ///
     /// 3. **Behavioral skew on same-database iteration** — Even without
     ///    loose-page reuse, concurrent `put` calls through `WriteHalf` on the
@@ -411,7 +742,7 @@ impl<'p> RwTxn<'p> {
     /// **Unsafe patterns**:
     /// ```text
     /// read db_a  +  write db_a          ✗  same database
-    /// read unnamed_db  +  write db_x    ✗  unnamed DB is MAIN_DBI
+    /// read unnamed_db  +  write db_x    ✗  unnamed DB is MAIN_DBI (refused)
     /// ```
     ///
     /// # Example
@@ -474,23 +805,273 @@ impl<'p> RwTxn<'p> {
     /// drop(write);
     /// # Ok(()) }
     /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Panics if a checkpoint couldn't begin a new transaction, [`RwTxn::split_with`]
+    /// returns [`Error::TxnPoisoned`] instead.
     pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        self.split_halves(None, None)
+    }
+
+    /// Splits this read-write transaction like [`RwTxn::split`], each half being only
+    /// allowed to use the listed databases.
+    ///
+    /// The operations of a half on the other databases fail with [`Error::DbNotAllowedInHalf`],
+    /// which turns the hazards described in [`RwTxn::split`] into errors when the databases
+    /// used by each half are only known at runtime.
+    ///
+    /// ```
+    /// # use std::error::Error;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::Database;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe {
+    /// #     EnvOpenOptions::new()
+    /// #         .map_size(10 * 1024 * 1024)
+    /// #         .max_dbs(3000)
+    /// #         .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src"))?;
+    /// let dst: Database<Str, Str> = env.create_database(&mut wtxn, Some("dst"))?;
+    /// src.put(&mut wtxn, "hello", "world")?;
+    ///
+    /// let (read, mut write) = wtxn.split_with(&[src.as_any()], &[dst.as_any()])?;
+    /// let val = src.get(&read, "hello")?.unwrap();
+    /// dst.put(&mut write, "hello", val)?;
+    /// assert!(matches!(src.put(&mut write, "hello", "there"), Err(heed::Error::DbNotAllowedInHalf)));
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::DbNotAllowedInHalf`] if a database is listed for both halves, or if the
+    /// unnamed database is listed for the read half while databases are listed for the write
+    /// half, as writing to the named databases modifies the unnamed one.
+    pub fn split_with(
+        &mut self,
+        read_dbis: &[&DatabaseAny],
+        write_dbis: &[&DatabaseAny],
+    ) -> Result<(ReadHalf<'_>, WriteHalf<'_>)> {
+        self.check_usable()?;
+        for database in read_dbis.iter().chain(write_dbis) {
+            database.check_env(self)?;
+        }
+        let read: Vec<_> = read_dbis.iter().map(|database| database.dbi).collect();
+        let write: Vec<_> = write_dbis.iter().map(|database| database.dbi).collect();
+        let reads_main = read.contains(&ffi::MAIN_DBI) && !write.is_empty();
+        if reads_main || read.iter().any(|dbi| write.contains(dbi)) {
+            return Err(Error::DbNotAllowedInHalf);
+        }
+        Ok(self.split_halves(Some(read), Some(write)))
+    }
+
+    fn split_halves(
+        &mut self,
+        read_dbis: Option<Vec<ffi::MDB_dbi>>,
+        write_dbis: Option<Vec<ffi::MDB_dbi>>,
+    ) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        // The halves of a previous split can't be alive anymore.
+        self.split = SplitState::default();
         let txn = self.txn.inner.txn.unwrap();
         let env = self.txn.inner.env.env_mut_ptr();
+        let generation = self.txn.inner.env.generation;
+        let changes = self.changes.as_mut().map(NonNull::from);
+        let observation = self.txn.inner.observation.as_deref();
+        let copies = self.value_copies();
+        // The writes modify the pages of a writable memory map in place, under the values
+        // borrowed by the read half.
+        let read_copies = copies.or_else(|| self.writemap().then_some(&self.txn.inner.copies));
+        let split = &self.split;
+        let scratch = &self.scratch;
         (
-            ReadHalf { txn, env, _marker: PhantomData },
-            WriteHalf { txn, env, _marker: PhantomData },
+            ReadHalf {
+                txn,
+                env,
+                generation,
+                allowed_dbis: read_dbis,
+                split,
+                observation,
+                copies: read_copies,
+                _marker: PhantomData,
+            },
+            WriteHalf {
+                txn,
+                env,
+                generation,
+                allowed_dbis: write_dbis,
+                split,
+                changes,
+                observation,
+                copies,
+                scratch,
+                _marker: PhantomData,
+            },
         )
     }
 
+    /// Whether the environment writes in place in the memory map.
+    fn writemap(&self) -> bool {
+        let mut flags = 0;
+        let env = self.txn.inner.env.env_mut_ptr();
+        let result = unsafe { mdb_result(ffi::mdb_env_get_flags(env.as_ptr(), &mut flags)) };
+        result.is_ok() && flags & EnvFlags::WRITE_MAP.bits() != 0
+    }
+
     /// Commit all the operations of a transaction into the database.
     /// The transaction is reset.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::TxnPoisoned`] if a checkpoint couldn't begin a new transaction.
     pub fn commit(mut self) -> Result<()> {
+        self.check_usable()?;
+        let changes = self.changes.take();
+        if let Some(changes) = &changes {
+            changes.write_log(&mut self)?;
+        }
+        let txn_id = self.txn.id() as u64;
+
         // Asserts that the transaction hasn't been already
         // committed/aborter and ensure we cannot use it two times.
-        let mut txn = self.txn.inner.txn.take().unwrap();
-        let result = unsafe { mdb_result(ffi::mdb_txn_commit(txn.as_mut())) };
-        result.map_err(Into::into)
+        let txn = self.txn.inner.txn.take().unwrap();
+        let start = Instant::now();
+        unsafe { self.commit_txn(txn)? };
+        self.txn.inner.env.commit_signal.notify();
+        self.txn.inner.finish(SlowTxnState::Committed);
+        if let Some(observation) = self.txn.inner.observation.take().filter(|_| !self.nested) {
+            observation.write_txn_committed(self.txn.inner.env.env_mut_ptr(), start.elapsed());
+        }
+
+        if let Some(changes) = changes {
+            changes.committed(txn_id, self.parent_changes.take());
+        }
+        Ok(())
+    }
+
+    /// Commits the operations done so far and begins a new write transaction in place.
+    ///
+    /// LMDB keeps every page dirtied by a write transaction in memory until it is committed,
+    /// a job writing a large amount of entries can checkpoint regularly to bound it. The
+    /// values borrowed from the transaction can't outlive the checkpoint and the entries
+    /// committed by a checkpoint are visible to the readers, even if the transaction is
+    /// aborted later. The changes recorded by the transaction are handed to the capture
+    /// at every checkpoint, see [`Env::set_change_capture`].
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<U32<byteorder::BE>, Unit>(&mut wtxn, Some("numbers"))?;
+    /// for i in 0..10_000 {
+    ///     db.put(&mut wtxn, &i, &())?;
+    ///     if i % 1000 == 999 {
+    ///         wtxn.checkpoint()?;
+    ///     }
+    /// }
+    /// wtxn.abort();
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// assert_eq!(db.len(&rtxn)?, 10_000);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Nested transactions can't be checkpointed, their pages are only written when their
+    /// parent is committed. If the commit fails, the operations done since the last
+    /// checkpoint are lost and a new transaction begins anyway.
+    ///
+    /// If the new transaction can't begin, its error is returned and this transaction is
+    /// poisoned: its operations, commit and checkpoints return [`Error::TxnPoisoned`] and it
+    /// must be dropped to let another write transaction begin.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.check_usable()?;
+        if self.nested {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a nested write transaction can't be checkpointed",
+            )
+            .into());
+        }
+
+        if let Some(changes) = self.changes.take() {
+            let result = changes.write_log(self);
+            self.changes = Some(changes);
+            result?;
+        }
+        let txn_id = self.txn.id() as u64;
+
+        let txn = self.txn.inner.txn.take().unwrap();
+        let start = Instant::now();
+        let committed = unsafe { self.commit_txn(txn) };
+        let observation = self.txn.inner.observation.take();
+        let state = if committed.is_ok() { SlowTxnState::Committed } else { SlowTxnState::Aborted };
+        self.txn.inner.finish(state);
+        if committed.is_ok() {
+            self.txn.inner.env.commit_signal.notify();
+            if let Some(observation) = observation {
+                let env = self.txn.inner.env.env_mut_ptr();
+                observation.write_txn_committed(env, start.elapsed());
+            }
+        }
+
+        if let Some(changes) = self.changes.as_mut() {
+            let previous = mem::replace(changes, changes.restart());
+            if committed.is_ok() {
+                previous.committed(txn_id, None);
+            }
+        }
+
+        // The transaction stays without an LMDB transaction, poisoned, if none can begin.
+        let txn = begin_write_txn(&self.txn.inner.env)?;
+        let env = self.txn.inner.env.clone();
+        self.txn.inner = RoTxnInner::new(txn, env, true);
+
+        committed
+    }
+
+    /// Commits the LMDB transaction, or aborts it if a fault is injected,
+    /// see [`Env::set_fault_injector`](crate::Env::set_fault_injector).
+    ///
+    /// # Safety
+    ///
+    /// The transaction must be the one of this transaction, taken out of it.
+    unsafe fn commit_txn(&self, mut txn: NonNull<ffi::MDB_txn>) -> Result<()> {
+        #[cfg(any(test, feature = "testing"))]
+        if !self.nested {
+            if let Some(fault) = self.txn.inner.env.injected_fault(Boundary::Commit) {
+                ffi::mdb_txn_abort(txn.as_mut());
+                return Err(fault.to_io_error().into());
+            }
+        }
+        mdb_result(ffi::mdb_txn_commit(txn.as_mut())).map_err(Into::into)
+    }
+
+    /// Returns the time this transaction waited for the write transactions in progress,
+    /// zero for nested transactions.
+    pub fn lock_wait(&self) -> Duration {
+        self.lock_wait
+    }
+
+    /// Returns the changes recorded so far by this transaction.
+    ///
+    /// The list is empty if the capture of the changes was disabled when the transaction
+    /// started, see [`Env::set_change_capture`].
+    pub fn changes(&self) -> &[Change] {
+        self.changes.as_ref().map_or(&[], ChangeRecorder::changes)
     }
 
     /// Abandon all the operations of the transaction instead of saving them.
@@ -498,8 +1079,37 @@ impl<'p> RwTxn<'p> {
     pub fn abort(mut self) {
         // Asserts that the transaction hasn't been already
         // committed/aborter and ensure we cannot use it twice.
-        let mut txn = self.txn.inner.txn.take().unwrap();
-        unsafe { ffi::mdb_txn_abort(txn.as_mut()) }
+        // A poisoned transaction has nothing to abort.
+        if let Some(mut txn) = self.txn.inner.txn.take() {
+            unsafe { ffi::mdb_txn_abort(txn.as_mut()) }
+        }
+    }
+}
+
+/// Begins a write transaction that isn't nested, or fails if a fault is injected,
+/// see [`Env::set_fault_injector`](crate::Env::set_fault_injector).
+fn begin_write_txn(env: &EnvInner) -> Result<NonNull<ffi::MDB_txn>> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(fault) = env.injected_fault(Boundary::Begin) {
+        return Err(fault.to_io_error().into());
+    }
+    let mut txn: *mut ffi::MDB_txn = ptr::null_mut();
+    unsafe {
+        mdb_result(ffi::mdb_txn_begin(env.env_mut_ptr().as_mut(), ptr::null_mut(), 0, &mut txn))?
+    };
+    Ok(NonNull::new(txn).unwrap())
+}
+
+impl Drop for RwTxn<'_> {
+    fn drop(&mut self) {
+        if let Some(mut txn) = self.txn.inner.txn.take() {
+            unsafe { ffi::mdb_txn_abort(txn.as_mut()) }
+        }
+        // Only the commits of the write transactions are observed.
+        self.txn.inner.observation = None;
+        if !self.nested {
+            self.txn.inner.env.writer_lock.release();
+        }
     }
 }
 
@@ -521,7 +1131,7 @@ impl std::ops::DerefMut for RwTxn<'_> {
 
 /// The read half of a split [`RwTxn`].
 ///
-/// Created by [`RwTxn::split`]. Borrows from the parent transaction,
+/// Created by [`RwTxn::split`] or [`RwTxn::split_with`]. Borrows from the parent transaction,
 /// preventing [`RwTxn::commit`] or [`RwTxn::abort`] while this value is
 /// alive.  Implements [`ReadTxn`], so it can be passed to any database
 /// method that needs a read transaction reference.
@@ -536,7 +1146,9 @@ impl std::ops::DerefMut for RwTxn<'_> {
 /// database**. If `WriteHalf` writes to the same database — or to **any**
 /// named database while `ReadHalf` reads the unnamed database — LMDB's
 /// internal loose-page reuse can silently overwrite the memory behind those
-/// references. See [`RwTxn::split`] for the full list of hazards.
+/// references. See [`RwTxn::split`] for the full list of hazards,
+/// [`ReadHalf::get`] for reads that are checked against the writes, and
+/// [`Database::get_owned`] for values that don't borrow the transaction.
 ///
 /// ```compile_fail
 /// fn assert_send<T: Send>() {}
@@ -545,12 +1157,20 @@ impl std::ops::DerefMut for RwTxn<'_> {
 pub struct ReadHalf<'a> {
     txn: NonNull<ffi::MDB_txn>,
     env: NonNull<ffi::MDB_env>,
+    /// The generation of the environment, see [`ReadTxn::env_generation`].
+    generation: u64,
+    /// The databases this half can use, all of them if `None`.
+    allowed_dbis: Option<Vec<ffi::MDB_dbi>>,
+    split: &'a SplitState,
+    observation: Option<&'a TxnObservation>,
+    /// The copies of the values returned by the half, if they must be copied.
+    copies: Option<&'a ValueCopies>,
     _marker: PhantomData<&'a ()>,
 }
 
 /// The write half of a split [`RwTxn`].
 ///
-/// Created by [`RwTxn::split`]. Borrows from the parent transaction,
+/// Created by [`RwTxn::split`] or [`RwTxn::split_with`]. Borrows from the parent transaction,
 /// preventing [`RwTxn::commit`] or [`RwTxn::abort`] while this value is
 /// alive.  Implements both [`ReadTxn`] and [`WriteTxn`], so it can be
 /// used for both reads and writes.
@@ -573,6 +1193,17 @@ pub struct ReadHalf<'a> {
 pub struct WriteHalf<'a> {
     txn: NonNull<ffi::MDB_txn>,
     env: NonNull<ffi::MDB_env>,
+    /// The generation of the environment, see [`ReadTxn::env_generation`].
+    generation: u64,
+    /// The databases this half can use, all of them if `None`.
+    allowed_dbis: Option<Vec<ffi::MDB_dbi>>,
+    split: &'a SplitState,
+    changes: Option<NonNull<ChangeRecorder>>,
+    observation: Option<&'a TxnObservation>,
+    /// The copies of the values returned by the half, if they must be copied.
+    copies: Option<&'a ValueCopies>,
+    /// The buffer of the transaction reused to encode the keys and values.
+    scratch: &'a Cell<Vec<u8>>,
     _marker: PhantomData<&'a mut ()>,
 }
 
@@ -587,6 +1218,34 @@ unsafe impl ReadTxn for ReadHalf<'_> {
     fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
         self.env
     }
+
+    fn env_generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn observation(&self) -> Option<&TxnObservation> {
+        self.observation
+    }
+
+    fn check_dbi(&self, dbi: ffi::MDB_dbi) -> Result<()> {
+        if !self.allowed_dbis.as_ref().is_none_or(|dbis| dbis.contains(&dbi)) {
+            return Err(Error::DbNotAllowedInHalf);
+        }
+        if dbi == ffi::MAIN_DBI {
+            // The pages of the unnamed database are modified by the writes to the named ones.
+            if self.split.wrote_named.get() {
+                return Err(Error::DbNotAllowedInHalf);
+            }
+            self.split.read_main.set(true);
+        } else {
+            self.split.read_named.set(true);
+        }
+        Ok(())
+    }
+
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        self.copies
+    }
 }
 
 // SAFETY: WriteHalf holds the same valid MDB_txn pointer and the underlying
@@ -599,9 +1258,237 @@ unsafe impl ReadTxn for WriteHalf<'_> {
     fn env_mut_ptr(&self) -> NonNull<ffi::MDB_env> {
         self.env
     }
+
+    fn env_generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn observation(&self) -> Option<&TxnObservation> {
+        self.observation
+    }
+
+    fn check_dbi(&self, dbi: ffi::MDB_dbi) -> Result<()> {
+        if self.allowed_dbis.as_ref().is_none_or(|dbis| dbis.contains(&dbi)) {
+            Ok(())
+        } else {
+            Err(Error::DbNotAllowedInHalf)
+        }
+    }
+
+    fn value_copies(&self) -> Option<&ValueCopies> {
+        self.copies
+    }
+}
+
+unsafe impl WriteTxn for WriteHalf<'_> {
+    fn change_recorder(&mut self) -> Option<&mut ChangeRecorder> {
+        // SAFETY: The recorder belongs to the RwTxn mutably borrowed by the split halves,
+        // and only the WriteHalf accesses it.
+        self.changes.map(|mut changes| unsafe { changes.as_mut() })
+    }
+
+    fn scratch_buffer(&self) -> Option<&Cell<Vec<u8>>> {
+        Some(self.scratch)
+    }
+
+    fn before_write(&mut self, dbi: ffi::MDB_dbi, drop: bool) -> Result<()> {
+        let split = self.split;
+        if cfg!(debug_assertions) && split.borrows(dbi) != 0 {
+            panic!("a write through the write half reuses the pages of values still borrowed");
+        }
+        if dbi == ffi::MAIN_DBI {
+            // Dropping the unnamed database drops the records of the named databases.
+            if drop && split.read_named.get() {
+                return Err(Error::DbNotAllowedInHalf);
+            }
+        } else {
+            if split.read_main.get() {
+                return Err(Error::DbNotAllowedInHalf);
+            }
+            split.wrote_named.set(true);
+        }
+        split.epoch.set(split.epoch.get() + 1);
+        #[cfg(feature = "quarantine-values")]
+        if let Some(copies) = self.copies {
+            copies.quarantine();
+        }
+        Ok(())
+    }
 }
 
-unsafe impl WriteTxn for WriteHalf<'_> {}
+impl<'a> ReadHalf<'a> {
+    /// Reads a value like [`Database::get`], guarded against the writes through the
+    /// [`WriteHalf`] of the same split.
+    ///
+    /// The value can only be accessed with [`Guarded::get`], which borrows the [`WriteHalf`]
+    /// so that nothing can be written while the value is used, and fails if something was
+    /// written since the value was read. The zero-copy values can no longer point to memory
+    /// reused by a write, whatever the databases used by each half.
+    ///
+    /// ```
+    /// # use std::error::Error;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::Database;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe {
+    /// #     EnvOpenOptions::new()
+    /// #         .map_size(10 * 1024 * 1024)
+    /// #         .max_dbs(3000)
+    /// #         .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Str> = env.create_database(&mut wtxn, None)?;
+    /// db.put(&mut wtxn, "hello", "world")?;
+    ///
+    /// let (read, mut write) = wtxn.split();
+    /// let value = read.get(&db, "hello")?.unwrap();
+    /// assert_eq!(*value.get(&write)?, "world");
+    ///
+    /// db.delete(&mut write, "hello")?;
+    /// assert!(matches!(value.get(&write), Err(heed::Error::StaleValue)));
+    /// # Ok(()) }
+    /// ```
+    pub fn get<'h, 'k, KC, DC, C, CDUP>(
+        &'h self,
+        database: &Database<KC, DC, C, CDUP>,
+        key: &'k KC::EItem,
+    ) -> Result<Option<Guarded<'h, DC::DItem>>>
+    where
+        KC: BytesEncode<'k>,
+        DC: BytesDecode<'h>,
+    {
+        let split = self.split;
+        let read_at = split.epoch.get();
+        let value = database.get(self, key)?;
+        Ok(value.map(|value| {
+            let tracked_dbi = split.tracking.get().then_some(database.dbi);
+            if let Some(dbi) = tracked_dbi {
+                split.add_borrow(dbi);
+            }
+            Guarded { value, split, read_at, tracked_dbi }
+        }))
+    }
+
+    /// Tracks the values read with [`ReadHalf::get`] until they are dropped, a write through
+    /// the [`WriteHalf`] to the database of a tracked value then panics in debug builds.
+    ///
+    /// It catches the values kept across a write, that [`Guarded::get`] only reports when
+    /// they are accessed, where the write happens.
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::Database;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe {
+    /// #     EnvOpenOptions::new()
+    /// #         .map_size(10 * 1024 * 1024)
+    /// #         .max_dbs(3000)
+    /// #         .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db"))?;
+    /// db.put(&mut wtxn, "hello", "world")?;
+    ///
+    /// let (read, mut write) = wtxn.split();
+    /// read.track_borrows();
+    /// let value = read.get(&db, "hello")?.unwrap();
+    /// db.put(&mut write, "hello", "there")?; // panics, `value` is still alive
+    /// # drop(value);
+    /// # Ok(()) }
+    /// ```
+    pub fn track_borrows(&self) {
+        self.split.tracking.set(true);
+    }
+}
+
+/// The uses of the databases by the halves of a split transaction.
+#[derive(Default)]
+struct SplitState {
+    /// The number of writes through the write half, see [`Guarded`].
+    epoch: Cell<u64>,
+    /// Whether the read half used the unnamed database.
+    read_main: Cell<bool>,
+    /// Whether the read half used a named database.
+    read_named: Cell<bool>,
+    /// Whether the write half wrote to a named database.
+    wrote_named: Cell<bool>,
+    /// Whether the values read with [`ReadHalf::get`] are tracked, see [`ReadHalf::track_borrows`].
+    tracking: Cell<bool>,
+    /// The number of tracked values alive, per database.
+    borrows: RefCell<Vec<(ffi::MDB_dbi, usize)>>,
+}
+
+impl SplitState {
+    /// Returns the number of tracked values of the database that are alive.
+    fn borrows(&self, dbi: ffi::MDB_dbi) -> usize {
+        let borrows = self.borrows.borrow();
+        borrows.iter().find(|(id, _)| *id == dbi).map_or(0, |(_, count)| *count)
+    }
+
+    /// Counts a new tracked value of the database.
+    fn add_borrow(&self, dbi: ffi::MDB_dbi) {
+        let mut borrows = self.borrows.borrow_mut();
+        match borrows.iter_mut().find(|(id, _)| *id == dbi) {
+            Some((_, count)) => *count += 1,
+            None => borrows.push((dbi, 1)),
+        }
+    }
+
+    /// Forgets a dropped tracked value of the database.
+    fn remove_borrow(&self, dbi: ffi::MDB_dbi) {
+        let mut borrows = self.borrows.borrow_mut();
+        if let Some((_, count)) = borrows.iter_mut().find(|(id, _)| *id == dbi) {
+            *count -= 1;
+        }
+    }
+}
+
+/// A value read with [`ReadHalf::get`], only accessible while nothing is written
+/// through the [`WriteHalf`].
+pub struct Guarded<'a, T> {
+    value: T,
+    split: &'a SplitState,
+    read_at: u64,
+    /// The database of the value, if it is tracked, see [`ReadHalf::track_borrows`].
+    tracked_dbi: Option<ffi::MDB_dbi>,
+}
+
+impl<T> Guarded<'_, T> {
+    /// Returns the value, borrowing the write half so that nothing can be written while
+    /// it is used.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::StaleValue`] if something was written through the write half since
+    /// the value was read, or if the write half is not the one of the split that read it.
+    pub fn get<'g>(&'g self, write: &'g WriteHalf<'_>) -> Result<&'g T> {
+        if ptr::eq(self.split, write.split) && !self.is_stale() {
+            Ok(&self.value)
+        } else {
+            Err(Error::StaleValue)
+        }
+    }
+
+    /// Returns whether something was written through the write half since the value was read.
+    pub fn is_stale(&self) -> bool {
+        self.split.epoch.get() != self.read_at
+    }
+}
+
+impl<T> Drop for Guarded<'_, T> {
+    fn drop(&mut self) {
+        if let Some(dbi) = self.tracked_dbi {
+            self.split.remove_borrow(dbi);
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -614,6 +1501,268 @@ mod tests {
         is_send::<RoTxn<WithoutTls>>();
     }
 
+    #[test]
+    fn sync_ro_txns_are_send_and_sync() {
+        use crate::SyncRoTxn;
+
+        fn is_send_sync<T: Send + Sync>() {}
+
+        is_send_sync::<SyncRoTxn>();
+    }
+
+    #[test]
+    fn sync_ro_txns_cant_open_databases() {
+        use crate::types::Str;
+        use crate::{EnvOpenOptions, Error, SyncRoTxn};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env =
+            unsafe { EnvOpenOptions::new().read_txn_without_tls().max_dbs(10).open(dir.path()) }
+                .unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env.create_database::<Str, Str>(&mut wtxn, Some("words")).unwrap();
+        db.put(&mut wtxn, "hello", "world").unwrap();
+        env.create_database::<Str, Str>(&mut wtxn, Some("others")).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = SyncRoTxn::new(env.read_txn().unwrap());
+        let result = env.open_database::<Str, Str>(&rtxn, Some("others"));
+        assert!(
+            matches!(result, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput)
+        );
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| assert_eq!(db.get(&rtxn, "hello").unwrap(), Some("world")));
+            }
+        });
+
+        // The transaction can open databases again once it isn't shared.
+        let rtxn = rtxn.into_inner();
+        assert!(env.open_database::<Str, Str>(&rtxn, Some("others")).unwrap().is_some());
+    }
+
+    #[test]
+    fn checkpoints() {
+        use std::io::ErrorKind;
+
+        use crate::types::Str;
+        use crate::{EnvOpenOptions, Error};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env.create_database::<Str, Str>(&mut wtxn, Some("words")).unwrap();
+        db.put(&mut wtxn, "hello", "world").unwrap();
+        let first_id = wtxn.id();
+        wtxn.checkpoint().unwrap();
+        assert!(wtxn.id() > first_id);
+
+        let mut nested = env.nested_write_txn(&mut wtxn).unwrap();
+        let result = nested.checkpoint();
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == ErrorKind::InvalidInput));
+        drop(nested);
+
+        // The entries written before the checkpoint survive an abort.
+        db.put(&mut wtxn, "bonjour", "monde").unwrap();
+        wtxn.abort();
+        let rtxn = env.read_txn().unwrap();
+        assert_eq!(db.get(&rtxn, "hello").unwrap(), Some("world"));
+        assert_eq!(db.get(&rtxn, "bonjour").unwrap(), None);
+    }
+
+    #[test]
+    fn checkpoints_failing_to_begin() {
+        use crate::testing::faults::{Boundary, Fault, FaultInjector};
+        use crate::types::Str;
+        use crate::{EnvOpenOptions, Error};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()) }.unwrap();
+        let faults = FaultInjector::new();
+        faults.fail_nth(Boundary::Begin, 2, Fault::Io);
+        env.set_fault_injector(Some(faults.clone()));
+
+        let mut wtxn = env.write_txn().unwrap();
+        let db = env.create_database::<Str, Str>(&mut wtxn, Some("words")).unwrap();
+        db.put(&mut wtxn, "hello", "world").unwrap();
+        assert!(matches!(wtxn.checkpoint(), Err(Error::Io(_))));
+
+        // The checkpoint committed, but the transaction is poisoned.
+        assert!(matches!(db.get(&wtxn, "hello"), Err(Error::TxnPoisoned)));
+        assert!(matches!(db.put(&mut wtxn, "hola", "mundo"), Err(Error::TxnPoisoned)));
+        let result = env.open_database::<Str, Str>(&wtxn, Some("words"));
+        assert!(matches!(result, Err(Error::TxnPoisoned)));
+        assert!(matches!(env.nested_write_txn(&mut wtxn), Err(Error::TxnPoisoned)));
+        assert!(matches!(wtxn.split_with(&[], &[]), Err(Error::TxnPoisoned)));
+        assert!(matches!(wtxn.checkpoint(), Err(Error::TxnPoisoned)));
+        assert!(matches!(wtxn.commit(), Err(Error::TxnPoisoned)));
+
+        // Dropping it releases the write lock.
+        let wtxn = env.write_txn().unwrap();
+        assert_eq!(db.get(&wtxn, "hello").unwrap(), Some("world"));
+        assert_eq!(faults.count(Boundary::Begin), 3);
+    }
+
+    #[test]
+    fn split_with_allow_lists() {
+        use crate::types::Str;
+        use crate::{Database, EnvOpenOptions, Error};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let unnamed: Database<Str, Str> = env.create_database(&mut wtxn, None).unwrap();
+        let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src")).unwrap();
+        let dst: Database<Str, Str> = env.create_database(&mut wtxn, Some("dst")).unwrap();
+        src.put(&mut wtxn, "hello", "world").unwrap();
+
+        let result = wtxn.split_with(&[src.as_any()], &[src.as_any()]);
+        assert!(matches!(result, Err(Error::DbNotAllowedInHalf)));
+        let result = wtxn.split_with(&[unnamed.as_any()], &[dst.as_any()]);
+        assert!(matches!(result, Err(Error::DbNotAllowedInHalf)));
+
+        {
+            let (read, mut write) = wtxn.split_with(&[src.as_any()], &[dst.as_any()]).unwrap();
+            let value = src.get(&read, "hello").unwrap().unwrap();
+            dst.put(&mut write, "hello", value).unwrap();
+            assert_eq!(dst.get(&write, "hello").unwrap(), Some("world"));
+            assert!(matches!(dst.get(&read, "hello"), Err(Error::DbNotAllowedInHalf)));
+            assert!(matches!(src.delete(&mut write, "hello"), Err(Error::DbNotAllowedInHalf)));
+            assert!(matches!(unnamed.iter(&read), Err(Error::DbNotAllowedInHalf)));
+        }
+
+        // Without the lists, the halves can use every database.
+        let (read, _write) = wtxn.split();
+        assert_eq!(dst.get(&read, "hello").unwrap(), Some("world"));
+    }
+
+    #[test]
+    fn split_unnamed_database() {
+        use crate::types::Str;
+        use crate::{Database, EnvOpenOptions, Error};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let unnamed: Database<Str, Str> = env.create_database(&mut wtxn, None).unwrap();
+        let named: Database<Str, Str> = env.create_database(&mut wtxn, Some("named")).unwrap();
+        named.put(&mut wtxn, "hello", "world").unwrap();
+
+        {
+            let (read, mut write) = wtxn.split();
+            assert_eq!(named.get(&read, "hello").unwrap(), Some("world"));
+            // Writing to the unnamed database leaves the named ones intact.
+            unnamed.put(&mut write, "hello", "world").unwrap();
+            let result = unnamed.clear(&mut write);
+            assert!(matches!(result, Err(Error::DbNotAllowedInHalf)));
+        }
+
+        // Each split starts afresh.
+        let (read, mut write) = wtxn.split();
+        assert_eq!(unnamed.get(&read, "hello").unwrap(), Some("world"));
+        let result = named.delete(&mut write, "hello");
+        assert!(matches!(result, Err(Error::DbNotAllowedInHalf)));
+        assert!(unnamed.delete(&mut write, "hello").unwrap());
+    }
+
+    #[test]
+    fn writemap_values_are_copied() {
+        use crate::types::Str;
+        use crate::{Database, EnvFlags, EnvOpenOptions, ReadTxn};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        unsafe { options.max_dbs(10).flags(EnvFlags::WRITE_MAP) };
+        let env = unsafe { options.open(dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src")).unwrap();
+        src.put(&mut wtxn, "hello", "world").unwrap();
+
+        // Only the read half copies, the borrows of the transaction outlive no write.
+        let paranoid = cfg!(feature = "paranoid-copies");
+        assert_eq!(wtxn.value_copies().is_some(), paranoid);
+        let (read, mut write) = wtxn.split();
+        assert!(read.value_copies().is_some());
+        assert_eq!(write.value_copies().is_some(), paranoid);
+
+        let value = src.get(&read, "hello").unwrap().unwrap();
+        // The value of the same size is overwritten in place in the memory map.
+        src.put(&mut write, "hello", "there").unwrap();
+        assert_eq!(value, "world");
+        assert_eq!(src.get(&read, "hello").unwrap(), Some("there"));
+    }
+
+    #[test]
+    fn guarded_values() {
+        use crate::types::Str;
+        use crate::{Database, EnvOpenOptions, Error};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
+        db.put(&mut wtxn, "hello", "world").unwrap();
+
+        {
+            let (read, mut write) = wtxn.split();
+            assert!(read.get(&db, "missing").unwrap().is_none());
+            let value = read.get(&db, "hello").unwrap().unwrap();
+            assert_eq!(*value.get(&write).unwrap(), "world");
+            assert!(!value.is_stale());
+
+            // Any write invalidates the values, even one that doesn't change anything.
+            assert!(!db.delete(&mut write, "missing").unwrap());
+            assert!(value.is_stale());
+            assert!(matches!(value.get(&write), Err(Error::StaleValue)));
+
+            let value = read.get(&db, "hello").unwrap().unwrap();
+            assert_eq!(*value.get(&write).unwrap(), "world");
+        }
+
+        wtxn.commit().unwrap();
+
+        // The write half of another split can't access the value.
+        let other_dir = tempfile::tempdir().unwrap();
+        let other_env = unsafe { EnvOpenOptions::new().open(other_dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let mut other_wtxn = other_env.write_txn().unwrap();
+        let (read, _write) = wtxn.split();
+        let (_other_read, other_write) = other_wtxn.split();
+        let value = read.get(&db, "hello").unwrap().unwrap();
+        assert!(matches!(value.get(&other_write), Err(Error::StaleValue)));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn tracked_borrows() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        use crate::types::Str;
+        use crate::{Database, EnvOpenOptions};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path()) }.unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src")).unwrap();
+        let dst: Database<Str, Str> = env.create_database(&mut wtxn, Some("dst")).unwrap();
+        src.put(&mut wtxn, "hello", "world").unwrap();
+
+        let (read, mut write) = wtxn.split();
+        read.track_borrows();
+        let value = read.get(&src, "hello").unwrap().unwrap();
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| src.put(&mut write, "hello", "there")));
+        assert!(result.is_err());
+        // The refused write is not recorded as a write to a named database.
+        assert!(!write.split.wrote_named.get());
+        dst.put(&mut write, "hello", "world").unwrap();
+
+        // The write is allowed once the value is dropped.
+        drop(value);
+        src.put(&mut write, "hello", "there").unwrap();
+    }
+
     #[test]
     fn rw_txns_are_send() {
         use crate::RwTxn;
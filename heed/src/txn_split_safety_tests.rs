@@ -7,43 +7,23 @@
 //!
 //! Tests annotated with `#[should_panic]` are expected to detect
 //! corruption or behavioral inconsistencies caused by the current API
-//! allowing same-database (or MAIN_DBI-aliased) use of both halves.
+//! allowing same-database use of both halves. The MAIN_DBI-aliased uses
+//! are refused by the split.
 //!
-//! Run with ASan for even stronger detection:
+//! Run with ASan for even stronger detection, the `quarantine-values`
+//! feature frees the values at every write through the `WriteHalf` so
+//! that the stale references are reported without depending on the
+//! reuse of the LMDB pages:
 //! ```sh
-//! RUSTFLAGS="-Zsanitizer=address" cargo +nightly test -p heed --lib txn_split_safety_tests -- --nocapture
+//! RUSTFLAGS="-Zsanitizer=address" cargo +nightly test -p heed --lib \
+//!     --features quarantine-values txn_split_safety_tests -- --nocapture
 //! ```
 
 use crate::Database;
-use crate::EnvOpenOptions;
+use crate::Error;
+use crate::testing::TestEnv;
 use crate::types::*;
 
-/// Helper: open a temporary env with room for named databases.
-fn tmp_env() -> (tempfile::TempDir, crate::Env<crate::WithTls>) {
-    let dir = tempfile::tempdir().unwrap();
-    let env = unsafe {
-        EnvOpenOptions::new()
-            .map_size(100 * 1024 * 1024) // 100 MB — room for large tests
-            .max_dbs(10)
-            .open(dir.path())
-            .unwrap()
-    };
-    (dir, env)
-}
-
-/// Helper: open a temporary env with many named-DB slots.
-fn tmp_env_many_dbs() -> (tempfile::TempDir, crate::Env<crate::WithTls>) {
-    let dir = tempfile::tempdir().unwrap();
-    let env = unsafe {
-        EnvOpenOptions::new()
-            .map_size(100 * 1024 * 1024)
-            .max_dbs(100)
-            .open(dir.path())
-            .unwrap()
-    };
-    (dir, env)
-}
-
 /// Helper: check a raw pointer against expected content. Panics on corruption.
 fn assert_ref_intact(ptr: *const u8, len: usize, expected: &str, label: &str) {
     let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
@@ -70,7 +50,7 @@ fn assert_ref_intact(ptr: *const u8, len: usize, expected: &str, label: &str) {
 /// ReadHalf while inserting into a different database via WriteHalf.
 #[test]
 fn cross_db_split_iter_and_put() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src")).unwrap();
@@ -116,7 +96,7 @@ fn cross_db_split_iter_and_put() {
 /// relies on LMDB implementation details, not documented guarantees.
 #[test]
 fn same_db_get_held_across_put_fresh_page() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
@@ -152,7 +132,7 @@ fn same_db_get_held_across_put_fresh_page() {
 #[test]
 #[should_panic(expected = "UNSOUND")]
 fn same_db_dirty_page_reuse_after_merge() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
@@ -195,7 +175,7 @@ fn same_db_dirty_page_reuse_after_merge() {
 /// count may still be correct — this tests behavioral consistency.
 #[test]
 fn same_db_iter_with_concurrent_deletes() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
@@ -255,7 +235,7 @@ fn same_db_iter_with_concurrent_deletes() {
 #[test]
 #[should_panic(expected = "BEHAVIORAL")]
 fn same_db_iter_with_concurrent_inserts() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
@@ -310,7 +290,7 @@ fn same_db_iter_with_concurrent_inserts() {
 /// writes cannot touch db_a's pages — should be safe.
 #[test]
 fn cross_db_with_prior_dirty_read_db() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db_a: Database<Str, Str> = env.create_database(&mut wtxn, Some("a")).unwrap();
@@ -358,7 +338,7 @@ fn cross_db_with_prior_dirty_read_db() {
 #[test]
 #[should_panic(expected = "UNSOUND")]
 fn same_db_heavy_delete_reinsert_cycle() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
@@ -405,7 +385,7 @@ fn same_db_heavy_delete_reinsert_cycle() {
 /// Cross-database range iteration — should be safe.
 #[test]
 fn cross_db_range_read_with_batch_write() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let src: Database<Str, Str> = env.create_database(&mut wtxn, Some("src")).unwrap();
@@ -451,7 +431,7 @@ fn cross_db_range_read_with_batch_write() {
 #[test]
 #[should_panic(expected = "UNSOUND")]
 fn same_db_get_dirty_page_then_overwrite_same_key() {
-    let (_dir, env) = tmp_env();
+    let env = TestEnv::builder().build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let db: Database<Str, Str> = env.create_database(&mut wtxn, Some("db")).unwrap();
@@ -480,21 +460,21 @@ fn same_db_get_dirty_page_then_overwrite_same_key() {
 }
 
 // ═══════════════════════════════════════════════════════════════════════
-// 10. MAIN_DBI aliasing: unnamed DB + named DB share B-tree (UB)
+// 10. MAIN_DBI aliasing: unnamed DB + named DB share B-tree (refused)
 // ═══════════════════════════════════════════════════════════════════════
 
 /// The **unnamed** database (opened with `None`) IS `MAIN_DBI` (DBI 1)
 /// in LMDB. All **named** database metadata records are also stored in
 /// `MAIN_DBI`. They share the same B-tree.
 ///
-/// This test dirties unnamed-DB (MAIN_DBI) pages, holds a zero-copy
-/// ref, then deletes + re-inserts via WriteHalf to trigger loose-page
-/// reuse in MAIN_DBI. It also writes to a named DB to exercise the
-/// `mdb_cursor_touch` path that COW's MAIN_DBI pages.
+/// Writing to a named DB through the WriteHalf COW's MAIN_DBI pages
+/// (`mdb_cursor_touch`) while the ReadHalf holds a zero-copy ref into
+/// them. The split refuses the named-DB writes once the unnamed DB has
+/// been read, and the reads of the unnamed DB once named DBs have been
+/// written.
 #[test]
-#[should_panic(expected = "UNSOUND")]
 fn main_dbi_aliasing_unnamed_db_plus_named_db() {
-    let (_dir, env) = tmp_env_many_dbs();
+    let env = TestEnv::builder().max_dbs(100).build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let unnamed: Database<Str, Str> = env.create_database(&mut wtxn, None).unwrap();
@@ -525,46 +505,41 @@ fn main_dbi_aliasing_unnamed_db_plus_named_db() {
         let ptr = val.as_ptr();
         let expected = val.to_string();
 
-        // Write to named DB (triggers mdb_cursor_touch on MAIN_DBI).
-        let named_val = "N".repeat(512);
-        for i in 0u32..5000 {
-            named.put(&mut write, &format!("n-{i:05}"), &named_val).unwrap();
-        }
+        // Write to named DB (would trigger mdb_cursor_touch on MAIN_DBI).
+        let result = named.put(&mut write, "n-00000", "N");
+        assert!(matches!(result, Err(Error::DbNotAllowedInHalf)), "{result:?}");
 
-        // Delete unnamed-DB entries → MAIN_DBI page merges → loose pages.
-        for i in 0u32..2500 {
-            unnamed.delete(&mut write, &format!("u-{i:05}")).unwrap();
-        }
+        assert_ref_intact(ptr, expected.len(), &expected, "unnamed-db ref (u-01500)");
+    }
 
-        // Re-insert to trigger loose-page reuse.
-        let reuse_val = "R".repeat(512);
-        for i in 5000u32..8000 {
-            unnamed.put(&mut write, &format!("u-{i:05}"), &reuse_val).unwrap();
-        }
+    {
+        let (read, mut write) = wtxn.split();
 
-        assert_ref_intact(ptr, expected.len(), &expected, "unnamed-db ref (u-01500)");
+        // The other way around, in a new split.
+        named.put(&mut write, "n-00000", "N").unwrap();
+        let result = unnamed.get(&read, "u-01500");
+        assert!(matches!(result, Err(Error::DbNotAllowedInHalf)), "{result:?}");
+        assert_eq!(named.get(&read, "n-00000").unwrap(), Some("N"));
     }
 }
 
 // ═══════════════════════════════════════════════════════════════════════
 // 11. MAIN_DBI aliasing: iterate unnamed DB while writing named DBs
-//     (behavioral)
+//     (refused)
 // ═══════════════════════════════════════════════════════════════════════
 
 /// Iterates the unnamed DB while writing to many named databases.
 /// Named-DB writes trigger `mdb_cursor_touch` on MAIN_DBI for the
 /// first write per named DB, COW'ing shared B-tree pages. The
 /// unnamed-DB iterator cursor is registered on MAIN_DBI and may be
-/// affected.
+/// affected: named-DB writes corrupt the page data visible to the
+/// unnamed-DB iterator, causing decode errors (invalid UTF-8) or
+/// wrong entry counts.
 ///
-/// This is a cross-database scenario the design intends to be safe,
-/// but the MAIN_DBI B-tree is shared. In practice, named-DB writes
-/// corrupt the page data visible to the unnamed-DB iterator, causing
-/// decode errors (invalid UTF-8) or wrong entry counts.
+/// The split refuses the named-DB writes, the iterator stays intact.
 #[test]
-#[should_panic(expected = "UNSOUND")]
 fn main_dbi_aliasing_iter_unnamed_while_writing_named() {
-    let (_dir, env) = tmp_env_many_dbs();
+    let env = TestEnv::builder().max_dbs(100).build().unwrap();
 
     let mut wtxn = env.write_txn().unwrap();
     let unnamed: Database<Str, Str> = env.create_database(&mut wtxn, None).unwrap();
@@ -586,43 +561,19 @@ fn main_dbi_aliasing_iter_unnamed_while_writing_named() {
     {
         let (read, mut write) = wtxn.split();
 
-        let mut iter = unnamed.iter(&read).unwrap();
         let mut read_count = 0u32;
-        let mut write_count = 0u32;
-
-        while let Some(result) = iter.next() {
-            match result {
-                Ok((_key, _value)) => {
-                    read_count += 1;
-                    if read_count % 50 == 0 {
-                        for (j, ndb) in named_dbs.iter().enumerate() {
-                            let k = format!("wr-{read_count}-{j}");
-                            ndb.put(&mut write, &k, "nval").unwrap();
-                            write_count += 1;
-                        }
-                    }
-                }
-                Err(e) => {
-                    panic!(
-                        "[UNSOUND] Iterator decode error after {read_count} reads / \
-                         {write_count} writes: {e}. Named-DB writes corrupted \
-                         MAIN_DBI pages under the unnamed-DB cursor."
-                    );
+        for result in unnamed.iter(&read).unwrap() {
+            let (_key, _value) = result.unwrap();
+            read_count += 1;
+            if read_count % 50 == 0 {
+                for (j, ndb) in named_dbs.iter().enumerate() {
+                    let k = format!("wr-{read_count}-{j}");
+                    let result = ndb.put(&mut write, &k, "nval");
+                    assert!(matches!(result, Err(Error::DbNotAllowedInHalf)), "{result:?}");
                 }
             }
         }
 
-        eprintln!(
-            "[main_dbi_aliasing_iter] read_count={read_count}, write_count={write_count}"
-        );
-
-        // If MAIN_DBI sharing causes issues, read_count != 1000.
-        if read_count != 1000 {
-            panic!(
-                "[UNSOUND] Expected 1000 entries from unnamed DB \
-                 but iterator returned {read_count}. Named-DB writes \
-                 affected the MAIN_DBI B-tree."
-            );
-        }
+        assert_eq!(read_count, 1000);
     }
 }
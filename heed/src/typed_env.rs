@@ -0,0 +1,145 @@
+/// Generates a newtype around an [`Env`](crate::Env) that only exposes typed databases.
+///
+/// Every field declares a database, named after the field unless a name is given with
+/// `= "name"`, and with the flags listed after `where`. The codecs and comparators are
+/// the ones of the field type. All the databases are opened, or created, at once by the
+/// generated `new` function and are then only reachable through their accessor,
+/// it is therefore not possible to open the same database twice with different types.
+///
+/// The generated type has the same `T` parameter as the [`Env`](crate::Env) it wraps and exposes
+/// the functions to open transactions and the `DATABASE_NAMES` constant, useful to configure
+/// [`EnvOpenOptions::max_dbs`](crate::EnvOpenOptions::max_dbs).
+///
+/// ```
+/// use heed::byteorder::BigEndian;
+/// use heed::types::*;
+/// use heed::{typed_env, Database, EnvOpenOptions};
+///
+/// typed_env! {
+///     /// The environment of the application.
+///     pub struct AppEnv {
+///         /// The names of the users by id.
+///         users: Database<U32<BigEndian>, Str>,
+///         /// The ids of the users by age.
+///         by_age: Database<U8, U32<BigEndian>> = "users-by-age" where DUP_SORT,
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = tempfile::tempdir()?;
+/// let env = unsafe {
+///     EnvOpenOptions::new()
+///         .max_dbs(AppEnv::DATABASE_NAMES.len() as u32)
+///         .open(dir.path())?
+/// };
+/// let env = AppEnv::new(env)?;
+///
+/// let mut wtxn = env.write_txn()?;
+/// env.users().put(&mut wtxn, &1, "kero")?;
+/// env.by_age().put(&mut wtxn, &28, &1)?;
+/// wtxn.commit()?;
+///
+/// let rtxn = env.read_txn()?;
+/// assert_eq!(env.users().get(&rtxn, &1)?, Some("kero"));
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! typed_env {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $ty:ty $(= $db_name:literal)? $(where $($flag:ident)|+)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name<T = $crate::WithTls> {
+            env: $crate::Env<T>,
+            $($field: $ty,)*
+        }
+
+        impl $name {
+            /// The names of the databases of this environment.
+            $vis const DATABASE_NAMES: &'static [&'static str] =
+                &[$($crate::typed_env!(@name $field $($db_name)?)),*];
+        }
+
+        impl<T> $name<T> {
+            /// Opens all the databases of the environment, creating the missing ones.
+            $vis fn new(env: $crate::Env<T>) -> $crate::Result<Self> {
+                let mut wtxn = env.write_txn()?;
+                $(
+                    let $field: $ty = {
+                        let mut options = env
+                            .database_options()
+                            .types()
+                            .key_comparator()
+                            .dup_sort_comparator();
+                        options.name($crate::typed_env!(@name $field $($db_name)?));
+                        options.flags(
+                            $crate::DatabaseFlags::empty()
+                            $($(| $crate::DatabaseFlags::$flag)+)?
+                        );
+                        options.create(&mut wtxn)?
+                    };
+                )*
+                wtxn.commit()?;
+                ::std::result::Result::Ok($name { env, $($field),* })
+            }
+
+            $(
+                $(#[$field_meta])*
+                $vis fn $field(&self) -> $ty {
+                    self.$field
+                }
+            )*
+
+            /// Creates a transaction with read-only access, see [`Env::read_txn`]($crate::Env::read_txn).
+            $vis fn read_txn(&self) -> $crate::Result<$crate::RoTxn<'_, T>> {
+                self.env.read_txn()
+            }
+
+            /// Creates a transaction with read and write access, see [`Env::write_txn`]($crate::Env::write_txn).
+            $vis fn write_txn(&self) -> $crate::Result<$crate::RwTxn<'_>> {
+                self.env.write_txn()
+            }
+
+            /// Creates a nested write transaction, see [`Env::nested_write_txn`]($crate::Env::nested_write_txn).
+            $vis fn nested_write_txn<'p>(
+                &'p self,
+                parent: &'p mut $crate::RwTxn,
+            ) -> $crate::Result<$crate::RwTxn<'p>> {
+                self.env.nested_write_txn(parent)
+            }
+
+            /// Flushes the data buffers to disk, see [`Env::force_sync`]($crate::Env::force_sync).
+            $vis fn force_sync(&self) -> $crate::Result<()> {
+                self.env.force_sync()
+            }
+
+            /// The path of the environment, see [`Env::path`]($crate::Env::path).
+            $vis fn path(&self) -> &::std::path::Path {
+                self.env.path()
+            }
+
+            /// Prepares the environment for closing, see [`Env::prepare_for_closing`]($crate::Env::prepare_for_closing).
+            $vis fn prepare_for_closing(self) -> $crate::EnvClosingEvent {
+                self.env.prepare_for_closing()
+            }
+        }
+
+        impl<T> ::std::clone::Clone for $name<T> {
+            fn clone(&self) -> Self {
+                $name { env: self.env.clone(), $($field: self.$field),* }
+            }
+        }
+    };
+    (@name $field:ident) => {
+        ::std::stringify!($field)
+    };
+    (@name $field:ident $db_name:literal) => {
+        $db_name
+    };
+}
@@ -0,0 +1,504 @@
+//! Verification of the structure of an environment, see [`Env::verify`].
+//!
+//! LMDB doesn't check the pages it reads, a corrupted file is only noticed when a lookup
+//! returns a wrong entry or crashes. The verification reads a snapshot of the environment
+//! and checks what the public interface of LMDB allows to check: the records describing the
+//! named databases, the order of their entries, their number and the number of pages.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem::size_of;
+
+use crate::analyze::{database, freelist_entries, open_dbi, FreelistEntry};
+use crate::mdb::error::mdb_result;
+use crate::mdb::ffi::{self, into_val, FREE_DBI, MAIN_DBI};
+use crate::mdb::lmdb_flags::AllDatabaseFlags;
+use crate::*;
+
+/// The number of pages holding the meta records at the beginning of the file.
+const META_PAGES: usize = 2;
+/// The root page number of an empty database.
+const INVALID_PAGE: usize = usize::MAX;
+/// The flags LMDB stores in the record of a database.
+const PERSISTENT_FLAGS: AllDatabaseFlags = AllDatabaseFlags::REVERSE_KEY
+    .union(AllDatabaseFlags::DUP_SORT)
+    .union(AllDatabaseFlags::INTEGER_KEY)
+    .union(AllDatabaseFlags::DUP_FIXED)
+    .union(AllDatabaseFlags::INTEGER_DUP)
+    .union(AllDatabaseFlags::REVERSE_DUP);
+
+/// The options of a verification, see [`Env::verify`].
+///
+/// By default the order of every database is checked
+/// and the verification stops after a hundred problems.
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    unordered: Vec<Option<String>>,
+    max_problems: usize,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VerifyOptions {
+    /// Creates the default options.
+    pub fn new() -> VerifyOptions {
+        VerifyOptions { unordered: Vec::new(), max_problems: 100 }
+    }
+
+    /// Doesn't check the order of the entries of the database of this name.
+    ///
+    /// The entries are compared with the comparators of the databases opened by the
+    /// environment, the other ones are compared with the comparators of their flags. A
+    /// database with a custom comparator must be opened before the verification or ignored.
+    pub fn ignore_ordering(&mut self, name: Option<&str>) -> &mut Self {
+        self.unordered.push(name.map(str::to_owned));
+        self
+    }
+
+    /// Sets the number of problems after which the verification stops.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of problems is zero.
+    pub fn max_problems(&mut self, max_problems: usize) -> &mut Self {
+        assert!(max_problems > 0, "the maximum number of problems must be greater than zero");
+        self.max_problems = max_problems;
+        self
+    }
+}
+
+/// The outcome of a verification, returned by [`Env::verify`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct VerifyReport {
+    /// The id of the verified snapshot.
+    pub txn_id: usize,
+    /// The number of pages of the snapshot, `None` if a write transaction was committed after
+    /// the snapshot, the pages are then not counted.
+    pub total_pages: Option<usize>,
+    /// The number of pages in the freelist.
+    pub free_pages: usize,
+    /// The unnamed database followed by the named databases, in the order of their names.
+    pub databases: Vec<VerifiedDatabase>,
+    /// The problems found, the verification stops at the maximum number of problems.
+    pub problems: Vec<Problem>,
+}
+
+impl VerifyReport {
+    /// Returns whether no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Panics with the problems found, if any.
+    #[track_caller]
+    pub fn assert_ok(&self) {
+        if !self.problems.is_empty() {
+            let problems: Vec<_> = self.problems.iter().map(ToString::to_string).collect();
+            panic!("the environment is corrupted: {}", problems.join(", "));
+        }
+    }
+}
+
+/// A database checked by the verification, see [`VerifyReport::databases`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct VerifiedDatabase {
+    /// The name of the database, `None` for the unnamed one.
+    pub name: Option<String>,
+    /// The flags of the database.
+    pub flags: DatabaseFlags,
+    /// The statistics recorded for the database.
+    pub stat: DatabaseStat,
+    /// The number of entries read, the values of a key are counted separately.
+    pub entries: usize,
+}
+
+/// A problem found by the verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// The record of a named database in the unnamed database is inconsistent.
+    InvalidRecord {
+        /// The name of the database.
+        database: String,
+        /// What is inconsistent.
+        reason: &'static str,
+    },
+    /// A key is not after the previous one.
+    UnorderedKey {
+        /// The name of the database, `None` for the unnamed one.
+        database: Option<String>,
+        /// The key.
+        key: Vec<u8>,
+    },
+    /// A value of a `DUP_SORT` database is not after the previous value of its key.
+    UnorderedValue {
+        /// The name of the database, `None` for the unnamed one.
+        database: Option<String>,
+        /// The key of the value.
+        key: Vec<u8>,
+        /// The value.
+        value: Vec<u8>,
+    },
+    /// The number of entries read differs from the number recorded.
+    EntryCount {
+        /// The name of the database, `None` for the unnamed one.
+        database: Option<String>,
+        /// The number of entries recorded.
+        recorded: usize,
+        /// The number of entries read.
+        read: usize,
+    },
+    /// The pages of the databases and of the freelist don't add up to the pages of the file.
+    PageCount {
+        /// The number of pages of the snapshot.
+        total: usize,
+        /// The number of pages of the databases and of the freelist.
+        counted: usize,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::InvalidRecord { database, reason } => {
+                write!(f, "the record of the {database:?} database is invalid: {reason}")
+            }
+            Problem::UnorderedKey { database, key } => {
+                write!(f, "the key {key:?} of the {database:?} database is out of order")
+            }
+            Problem::UnorderedValue { database, key, value } => write!(
+                f,
+                "the value {value:?} of the key {key:?} of the {database:?} database is out of order"
+            ),
+            Problem::EntryCount { database, recorded, read } => write!(
+                f,
+                "the {database:?} database records {recorded} entries but {read} were read"
+            ),
+            Problem::PageCount { total, counted } => {
+                write!(f, "the file has {total} pages but {counted} were counted")
+            }
+        }
+    }
+}
+
+/// The record of a database stored in the unnamed database, the `MDB_db` struct of LMDB.
+pub(crate) struct Record {
+    flags: u32,
+    depth: usize,
+    branch_pages: usize,
+    leaf_pages: usize,
+    overflow_pages: usize,
+    entries: usize,
+    pub(crate) root: usize,
+}
+
+impl Record {
+    /// Decodes the record, a `u32` padding, the `u16` flags and depth,
+    /// then the page counts, the number of entries and the root page as `usize`s.
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Record> {
+        if bytes.len() != 8 + 5 * size_of::<usize>() {
+            return None;
+        }
+        let u16_at = |at: usize| u16::from_ne_bytes([bytes[at], bytes[at + 1]]);
+        let mut words = bytes[8..]
+            .chunks_exact(size_of::<usize>())
+            .map(|word| usize::from_ne_bytes(word.try_into().unwrap()));
+        Some(Record {
+            flags: u32::from(u16_at(4)),
+            depth: usize::from(u16_at(6)),
+            branch_pages: words.next()?,
+            leaf_pages: words.next()?,
+            overflow_pages: words.next()?,
+            entries: words.next()?,
+            root: words.next()?,
+        })
+    }
+
+    /// Returns why the record is inconsistent, if it is.
+    fn check(&self, total_pages: Option<usize>) -> Option<&'static str> {
+        let pages = self.branch_pages + self.leaf_pages + self.overflow_pages;
+        if self.flags & !PERSISTENT_FLAGS.bits() != 0 {
+            Some("unknown flags")
+        } else if self.root == INVALID_PAGE {
+            (self.depth != 0 || pages != 0 || self.entries != 0)
+                .then_some("an empty database has pages or entries")
+        } else if self.depth == 0 || self.leaf_pages == 0 || self.entries == 0 {
+            Some("a database with a root page has no entries")
+        } else if self.branch_pages < self.depth - 1 {
+            Some("the tree is deeper than its branch pages")
+        } else if self.root < META_PAGES || total_pages.is_some_and(|total| self.root >= total) {
+            Some("the root page is outside of the file")
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Env<T> {
+    /// Verifies the structure of the snapshot of the transaction, like the `mdb_verify` tool.
+    ///
+    /// The verification checks:
+    ///  - the records of the named databases stored in the unnamed database,
+    ///  - the order of the keys of every database, and of the values of the `DUP_SORT` ones,
+    ///  - the number of entries read against the number recorded for every database,
+    ///  - the number of pages of the databases and of the freelist against the pages of the
+    ///    file, when the transaction reads the last committed snapshot. The pages of the
+    ///    duplicate values stored in their own trees can't be counted, there can be more pages
+    ///    than counted in an environment with `DUP_SORT` databases.
+    ///
+    /// Every entry is read, it can take a while on a large environment. The problems are
+    /// reported, the errors of LMDB, e.g. [`MdbError::Corrupted`], are returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transaction wasn't opened in this environment.
+    ///
+    /// ```
+    /// # use heed::EnvOpenOptions;
+    /// use heed::types::*;
+    /// use heed::verify::VerifyOptions;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let env = unsafe { EnvOpenOptions::new()
+    /// #     .map_size(10 * 1024 * 1024) // 10MB
+    /// #     .max_dbs(3000)
+    /// #     .open(dir.path())?
+    /// # };
+    /// let mut wtxn = env.write_txn()?;
+    /// let db = env.create_database::<Str, Str>(&mut wtxn, Some("users"))?;
+    /// db.put(&mut wtxn, "kero", "admin")?;
+    /// wtxn.commit()?;
+    ///
+    /// let rtxn = env.read_txn()?;
+    /// let report = env.verify(&rtxn, &VerifyOptions::new())?;
+    /// report.assert_ok();
+    ///
+    /// let users = report.databases.iter().find(|db| db.name.as_deref() == Some("users")).unwrap();
+    /// assert_eq!(users.entries, 1);
+    /// # Ok(()) }
+    /// ```
+    pub fn verify(&self, rtxn: &RoTxn<T>, options: &VerifyOptions) -> Result<VerifyReport> {
+        assert_eq_env_txn!(self, rtxn);
+
+        let txn_id = rtxn.id();
+        let info = self.info();
+        let total_pages = (info.last_txn_id == txn_id).then_some(info.last_page_number + 1);
+        let mut verifier = Verifier { rtxn, options, problems: Vec::new() };
+
+        let main = database(rtxn, MAIN_DBI);
+        let mut databases = vec![verifier.database(None, MAIN_DBI)?];
+        for result in main.iter(rtxn)? {
+            if verifier.is_done() {
+                break;
+            }
+            let (key, record) = result?;
+            let name = match std::str::from_utf8(key) {
+                Ok(name) if !name.contains('\0') => name,
+                _ => continue,
+            };
+            // The keys that are not database names can't be opened as databases.
+            let dbi = match open_dbi(rtxn, name) {
+                Ok(dbi) => dbi,
+                Err(Error::Mdb(MdbError::Incompatible)) => continue,
+                Err(e) => return Err(e),
+            };
+            let reason = match Record::decode(record) {
+                Some(record) => record.check(total_pages),
+                None => Some("unexpected size"),
+            };
+            if let Some(reason) = reason {
+                let database = name.to_owned();
+                verifier.problem(Problem::InvalidRecord { database, reason });
+            }
+            databases.push(verifier.database(Some(name), dbi)?);
+        }
+
+        let free_pages = freelist_entries(rtxn)?.iter().map(FreelistEntry::page_count).sum();
+        if let Some(total) = total_pages.filter(|_| !verifier.is_done()) {
+            let freelist = database(rtxn, FREE_DBI).stat(rtxn)?;
+            let counted = META_PAGES
+                + free_pages
+                + freelist.branch_pages
+                + freelist.leaf_pages
+                + freelist.overflow_pages
+                + databases
+                    .iter()
+                    .map(|db| db.stat.branch_pages + db.stat.leaf_pages + db.stat.overflow_pages)
+                    .sum::<usize>();
+            let dup_sort = databases.iter().any(|db| db.flags.contains(DatabaseFlags::DUP_SORT));
+            if counted > total || (counted < total && !dup_sort) {
+                verifier.problem(Problem::PageCount { total, counted });
+            }
+        }
+
+        Ok(VerifyReport { txn_id, total_pages, free_pages, databases, problems: verifier.problems })
+    }
+}
+
+struct Verifier<'a, 'e, T> {
+    rtxn: &'a RoTxn<'e, T>,
+    options: &'a VerifyOptions,
+    problems: Vec<Problem>,
+}
+
+impl<T> Verifier<'_, '_, T> {
+    fn is_done(&self) -> bool {
+        self.problems.len() >= self.options.max_problems
+    }
+
+    fn problem(&mut self, problem: Problem) {
+        if !self.is_done() {
+            self.problems.push(problem);
+        }
+    }
+
+    /// Reads the entries of the database, checks their order and counts them.
+    fn database(&mut self, name: Option<&str>, dbi: ffi::MDB_dbi) -> Result<VerifiedDatabase> {
+        let rtxn = self.rtxn;
+        let mut flags = 0;
+        unsafe { mdb_result(ffi::mdb_dbi_flags(rtxn.txn_ptr().as_ptr(), dbi, &mut flags))? };
+        let flags = DatabaseFlags::from_bits_retain(flags);
+        let dup_sort = flags.contains(DatabaseFlags::DUP_SORT);
+        let ordered = !self.options.unordered.iter().any(|n| n.as_deref() == name);
+
+        let db = database(rtxn, dbi);
+        let stat = db.stat(rtxn)?;
+        let mut entries = 0;
+        let mut previous: Option<(&[u8], &[u8])> = None;
+        for result in db.iter(rtxn)? {
+            let (key, value) = result?;
+            entries += 1;
+            if let Some((previous_key, previous_value)) = previous.filter(|_| ordered) {
+                let database = name.map(str::to_owned);
+                match compare(rtxn, dbi, ffi::mdb_cmp, previous_key, key) {
+                    Ordering::Less => (),
+                    Ordering::Equal if dup_sort => {
+                        let order = compare(rtxn, dbi, ffi::mdb_dcmp, previous_value, value);
+                        if order.is_ge() {
+                            let (key, value) = (key.to_vec(), value.to_vec());
+                            self.problem(Problem::UnorderedValue { database, key, value });
+                        }
+                    }
+                    _ => self.problem(Problem::UnorderedKey { database, key: key.to_vec() }),
+                }
+            }
+            if self.is_done() {
+                break;
+            }
+            previous = Some((key, value));
+        }
+
+        if entries != stat.entries && !self.is_done() {
+            let database = name.map(str::to_owned);
+            self.problem(Problem::EntryCount { database, recorded: stat.entries, read: entries });
+        }
+
+        Ok(VerifiedDatabase { name: name.map(str::to_owned), flags, stat, entries })
+    }
+}
+
+type CompareFn = unsafe extern "C" fn(
+    *mut ffi::MDB_txn,
+    ffi::MDB_dbi,
+    *const ffi::MDB_val,
+    *const ffi::MDB_val,
+) -> std::ffi::c_int;
+
+/// Compares two keys, or two values, with the comparator LMDB uses for the database.
+fn compare<T>(rtxn: &RoTxn<T>, dbi: ffi::MDB_dbi, cmp: CompareFn, a: &[u8], b: &[u8]) -> Ordering {
+    let (a, b) = unsafe { (into_val(a), into_val(b)) };
+    unsafe { cmp(rtxn.txn_ptr().as_ptr(), dbi, &a, &b) }.cmp(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Bound;
+
+    use crate::types::{Bytes, Str, Unit};
+    use crate::EnvOpenOptions;
+
+    #[test]
+    fn verify_environment() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let env = unsafe { EnvOpenOptions::new().max_dbs(10).open(dir.path())? };
+
+        let mut wtxn = env.write_txn()?;
+        let unnamed = env.create_database::<Bytes, Bytes>(&mut wtxn, None)?;
+        let plain = env.create_database::<Str, Bytes>(&mut wtxn, Some("plain"))?;
+        let dups = env
+            .database_options()
+            .types::<Str, Str>()
+            .name("dups")
+            .flags(DatabaseFlags::DUP_SORT)
+            .create(&mut wtxn)?;
+        unnamed.put(&mut wtxn, b"not-a-db", b"value")?;
+        for i in 0..1000 {
+            plain.put(&mut wtxn, &format!("{i:04}"), &vec![0; i % 3000])?;
+            dups.put(&mut wtxn, &format!("{}", i % 7), &format!("{i}"))?;
+        }
+        wtxn.commit()?;
+
+        // Free some pages.
+        let mut wtxn = env.write_txn()?;
+        plain.delete_range(&mut wtxn, &(Bound::Included("0000"), Bound::Excluded("0500")))?;
+        wtxn.commit()?;
+
+        let rtxn = env.read_txn()?;
+        let report = env.verify(&rtxn, &VerifyOptions::new())?;
+        report.assert_ok();
+        assert_eq!(report.txn_id, rtxn.id());
+        assert!(report.total_pages.is_some());
+        assert!(report.free_pages > 0);
+
+        let names: Vec<_> = report.databases.iter().map(|db| db.name.as_deref()).collect();
+        assert_eq!(names, [None, Some("dups"), Some("plain")]);
+        assert_eq!(report.databases[0].entries, 3);
+        assert_eq!(report.databases[1].entries, 1000);
+        assert!(report.databases[1].flags.contains(DatabaseFlags::DUP_SORT));
+        assert_eq!(report.databases[2].entries, 500);
+
+        // The pages are not counted once the snapshot is not the last one.
+        let mut wtxn = env.write_txn()?;
+        env.create_database::<Str, Unit>(&mut wtxn, Some("other"))?.put(&mut wtxn, "a", &())?;
+        wtxn.commit()?;
+        let report = env.verify(&rtxn, &VerifyOptions::new())?;
+        report.assert_ok();
+        assert_eq!(report.total_pages, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_records() {
+        let word = |i: usize| 8 + i * size_of::<usize>()..8 + (i + 1) * size_of::<usize>();
+        let mut bytes = vec![0; 8 + 5 * size_of::<usize>()];
+        bytes[4..6].copy_from_slice(&(DatabaseFlags::DUP_SORT.bits() as u16).to_ne_bytes());
+        bytes[word(4)].copy_from_slice(&INVALID_PAGE.to_ne_bytes());
+        let record = Record::decode(&bytes).unwrap();
+        assert_eq!(record.flags, DatabaseFlags::DUP_SORT.bits());
+        assert_eq!(record.check(Some(10)), None);
+
+        // An empty database with an entry.
+        bytes[word(3)].copy_from_slice(&1usize.to_ne_bytes());
+        let record = Record::decode(&bytes).unwrap();
+        assert_eq!(record.check(Some(10)), Some("an empty database has pages or entries"));
+
+        // A database rooted after the end of the file.
+        bytes[6..8].copy_from_slice(&1u16.to_ne_bytes());
+        bytes[word(1)].copy_from_slice(&1usize.to_ne_bytes());
+        bytes[word(4)].copy_from_slice(&12usize.to_ne_bytes());
+        let record = Record::decode(&bytes).unwrap();
+        assert_eq!(record.check(Some(10)), Some("the root page is outside of the file"));
+        assert_eq!(record.check(None), None);
+
+        assert!(Record::decode(&bytes[1..]).is_none());
+    }
+}